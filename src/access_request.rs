@@ -0,0 +1,638 @@
+//! Approval workflow for single relationship tuples. A user creates an
+//! OpenFGAAccessRequest, approvers are notified through an Event and optionally
+//! a webhook, and an approver records a decision in `status.decision`. The
+//! admission webhook stamps who made the decision, a SubjectAccessReview checks
+//! that identity may `approve` the request, and only then is the tuple written.
+//! Every step is appended to `status.trail`.
+
+use crate::controller::{shutdown_requested, ControllerError, ControllerResult, Shutdown};
+use crate::instance_ref;
+use crate::metrics;
+use crate::notifications;
+use crate::openfga_client::{ClientError, OpenFGAClient};
+use crate::panic_isolation::isolate_panics;
+use crate::tuples::{TupleKey, TupleOperation};
+use crate::types::{
+    AccessDecision, AccessRequestPhase, AccessTrailEntry, OpenFGAAccessRequest,
+    OpenFGAAccessRequestStatus, OpenFGACondition, OpenFGAStore,
+};
+use futures::StreamExt;
+use k8s_openapi::api::authentication::v1::UserInfo;
+use k8s_openapi::api::authorization::v1::{
+    ResourceAttributes, SubjectAccessReview, SubjectAccessReviewSpec,
+};
+use kube::api::{Api, Patch, PatchParams, PostParams};
+use kube::core::admission::Operation;
+use kube::runtime::controller::{Action, Controller};
+use kube::runtime::events::{Event, EventType, Recorder};
+use kube::runtime::watcher::Config;
+use kube::{Client, Resource, ResourceExt};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tokio::time::Duration;
+use tracing::{debug, error, info, instrument, warn};
+
+const CONTROLLER_NAME: &str = "openfga-access-request-controller";
+
+/// Identity that created the request, stamped by the admission webhook.
+pub const REQUESTED_BY_ANNOTATION: &str = "openfga.dev/requested-by";
+
+/// Verb an approver needs on `openfgaaccessrequests` for a decision to count.
+pub const APPROVE_VERB: &str = "approve";
+
+/// Optional URL every new request is POSTed to, e.g. a chat or ticketing bridge.
+const NOTIFY_URL_ENV: &str = "OPENFGA_OPERATOR_ACCESS_REQUEST_WEBHOOK_URL";
+
+pub struct OpenFGAAccessRequestController {
+    client: Client,
+    notify_url: Option<String>,
+}
+
+impl OpenFGAAccessRequestController {
+    pub fn new(client: Client) -> Self {
+        Self {
+            client,
+            notify_url: notifications::url_from_env(NOTIFY_URL_ENV),
+        }
+    }
+
+    pub async fn run(self, shutdown: Shutdown) {
+        let requests: Api<OpenFGAAccessRequest> = Api::all(self.client.clone());
+
+        info!(
+            controller = CONTROLLER_NAME,
+            "Starting controller with OpenFGAAccessRequest resource monitoring"
+        );
+
+        let _watches = metrics::track_watch_streams(CONTROLLER_NAME, 1);
+        Controller::new(requests, Config::default().any_semantic())
+            .graceful_shutdown_on(shutdown_requested(shutdown.clone()))
+            .run(
+                |request, ctx| {
+                    let guarded = isolate_panics(
+                        ctx.client.clone(),
+                        CONTROLLER_NAME,
+                        request.clone(),
+                        reconcile(request.clone(), ctx),
+                    );
+                    metrics::observe_reconcile(CONTROLLER_NAME, request, guarded)
+                },
+                error_policy,
+                Arc::new(self),
+            )
+            .for_each(|res| async move {
+                match res {
+                    Ok(o) => {
+                        debug!(
+                            reconciliation_result = "success",
+                            object = ?o,
+                            "OpenFGAAccessRequest reconciliation completed successfully"
+                        );
+                    }
+                    Err(e) => {
+                        error!(
+                            reconciliation_result = "error",
+                            error = %e,
+                            "OpenFGAAccessRequest reconciliation failed"
+                        );
+                    }
+                }
+            })
+            .await;
+    }
+}
+
+/// Applies the identity stamps the admission webhook is responsible for.
+///
+/// On create the requester is recorded in an annotation; on update the annotation is
+/// kept as it was, and a changed `status.decision` gets the identity of whoever
+/// changed it, replacing anything the client claimed.
+pub fn stamp_identity(
+    operation: &Operation,
+    object: &Value,
+    old: Option<&Value>,
+    user: &UserInfo,
+) -> Value {
+    let mut stamped = object.clone();
+    let username = user.username.clone().unwrap_or_default();
+
+    let requested_by = match operation {
+        Operation::Create => Some(Value::String(username.clone())),
+        _ => old
+            .and_then(|o| {
+                o.pointer("/metadata/annotations")?
+                    .get(REQUESTED_BY_ANNOTATION)
+            })
+            .cloned(),
+    };
+    if let Some(metadata) = stamped.get_mut("metadata").and_then(Value::as_object_mut) {
+        let annotations = metadata.entry("annotations").or_insert_with(|| json!({}));
+        match (annotations.as_object_mut(), requested_by) {
+            (Some(annotations), Some(requested_by)) => {
+                annotations.insert(REQUESTED_BY_ANNOTATION.to_string(), requested_by);
+            }
+            (Some(annotations), None) => {
+                annotations.remove(REQUESTED_BY_ANNOTATION);
+            }
+            _ => {}
+        }
+        if annotations.as_object().is_some_and(|a| a.is_empty()) {
+            metadata.remove("annotations");
+        }
+    }
+
+    let previous = old.and_then(|o| o.pointer("/status/decision"));
+    if let Some(decision) = stamped
+        .pointer_mut("/status/decision")
+        .and_then(Value::as_object_mut)
+    {
+        let unchanged = previous.and_then(Value::as_object).is_some_and(|previous| {
+            let strip = |d: &serde_json::Map<String, Value>| {
+                let mut d = d.clone();
+                d.remove("approver");
+                d.remove("approverGroups");
+                d
+            };
+            strip(previous) == strip(decision)
+        });
+        if unchanged {
+            if let Some(previous) = previous.and_then(Value::as_object) {
+                *decision = previous.clone();
+            }
+        } else {
+            decision.insert("approver".to_string(), Value::String(username));
+            decision.insert(
+                "approverGroups".to_string(),
+                json!(user.groups.clone().unwrap_or_default()),
+            );
+        }
+    }
+    stamped
+}
+
+/// Reasons a decision is void before asking the API server whether the approver
+/// may approve at all.
+pub fn decision_error(requester: Option<&str>, decision: &AccessDecision) -> Option<String> {
+    match decision.approver.as_deref() {
+        None | Some("") => Some(
+            "decision carries no approver identity; is the admission webhook installed?"
+                .to_string(),
+        ),
+        Some(approver) if decision.approved && Some(approver) == requester => {
+            Some(format!("{} cannot approve their own request", approver))
+        }
+        _ => None,
+    }
+}
+
+fn trail_entry(action: &str, actor: Option<&str>, message: Option<String>) -> AccessTrailEntry {
+    AccessTrailEntry {
+        time: chrono::Utc::now().to_rfc3339(),
+        action: action.to_string(),
+        actor: actor.map(str::to_string),
+        message,
+    }
+}
+
+fn requested_tuple(request: &OpenFGAAccessRequest) -> TupleKey {
+    TupleKey::new(
+        &request.spec.user,
+        &request.spec.relation,
+        &request.spec.object,
+    )
+}
+
+/// Body POSTed to the notification webhook for a new request.
+pub fn notification(request: &OpenFGAAccessRequest) -> Value {
+    json!({
+        "namespace": request.namespace(),
+        "name": request.name_any(),
+        "requestedBy": request.annotations().get(REQUESTED_BY_ANNOTATION),
+        "store": request.spec.store_ref.name,
+        "tuple": requested_tuple(request),
+        "justification": request.spec.justification,
+    })
+}
+
+/// Tells approvers about a new request; the Event always, the webhook when configured.
+async fn notify(
+    ctx: &OpenFGAAccessRequestController,
+    request: &OpenFGAAccessRequest,
+) -> Result<(), String> {
+    let recorder = Recorder::new(
+        ctx.client.clone(),
+        CONTROLLER_NAME.into(),
+        request.object_ref(&()),
+    );
+    let event = Event {
+        type_: EventType::Normal,
+        reason: "AccessRequested".to_string(),
+        note: Some(format!(
+            "{} requested; approve by setting status.decision",
+            requested_tuple(request)
+        )),
+        action: "Notify".to_string(),
+        secondary: None,
+    };
+    recorder
+        .publish(event)
+        .await
+        .map_err(|e| format!("failed to publish event: {}", e))?;
+
+    let Some(url) = &ctx.notify_url else {
+        return Ok(());
+    };
+    notifications::post_json(url, &notification(request)).await
+}
+
+/// Asks the API server whether the stamped approver may approve this request.
+async fn authorize(
+    client: &Client,
+    request: &OpenFGAAccessRequest,
+    decision: &AccessDecision,
+) -> ControllerResult<Result<(), String>> {
+    let review = SubjectAccessReview {
+        spec: SubjectAccessReviewSpec {
+            user: decision.approver.clone(),
+            groups: Some(decision.approver_groups.clone()),
+            resource_attributes: Some(ResourceAttributes {
+                group: Some("authorization.openfga.dev".to_string()),
+                resource: Some("openfgaaccessrequests".to_string()),
+                verb: Some(APPROVE_VERB.to_string()),
+                namespace: request.namespace(),
+                name: Some(request.name_any()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let reviews: Api<SubjectAccessReview> = Api::all(client.clone());
+    let status = reviews
+        .create(&PostParams::default(), &review)
+        .await?
+        .status
+        .unwrap_or_default();
+    if status.allowed {
+        Ok(Ok(()))
+    } else {
+        Ok(Err(format!(
+            "{} may not {} openfgaaccessrequests{}",
+            decision.approver.as_deref().unwrap_or_default(),
+            APPROVE_VERB,
+            status
+                .reason
+                .filter(|r| !r.is_empty())
+                .map(|r| format!(": {}", r))
+                .unwrap_or_default()
+        )))
+    }
+}
+
+/// Writes the approved tuple to the referenced store. A tuple that already exists
+/// counts as written.
+async fn write_tuple(client: &Client, request: &OpenFGAAccessRequest) -> Result<String, String> {
+    let ns = request.namespace().unwrap_or_default();
+    let stores: Api<OpenFGAStore> = Api::namespaced(client.clone(), &ns);
+    let store_name = &request.spec.store_ref.name;
+    let store = stores
+        .get_opt(store_name)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("OpenFGAStore '{}' not found", store_name))?;
+    let store_id = store
+        .status
+        .and_then(|s| s.store_id)
+        .ok_or_else(|| format!("OpenFGAStore '{}' has no store yet", store_name))?;
+    let instance = instance_ref::resolve(client, &ns, "OpenFGAStore", &store.spec.instance_ref)
+        .await
+        .map_err(|e| e.to_string())??;
+
+    let tuple = requested_tuple(request);
+    match OpenFGAClient::for_instance(&instance)
+        .write_tuples(&store_id, &[TupleOperation::Write(tuple)])
+        .await
+    {
+        Ok(()) => Ok(store_id),
+        Err(ClientError::Status {
+            status: 400,
+            message,
+        }) if message.contains("already exists") => Ok(store_id),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+#[instrument(skip(ctx), fields(namespace = %request.namespace().unwrap_or_default(), name = %request.name_any(), generation = request.metadata.generation.unwrap_or_default()))]
+async fn reconcile(
+    request: Arc<OpenFGAAccessRequest>,
+    ctx: Arc<OpenFGAAccessRequestController>,
+) -> ControllerResult<Action> {
+    let ns = request.namespace().unwrap_or_default();
+    let name = request.name_any();
+    let previous = request.status.clone().unwrap_or_default();
+    let phase = previous.phase.unwrap_or_default();
+    if matches!(
+        phase,
+        AccessRequestPhase::Denied | AccessRequestPhase::Granted
+    ) {
+        return Ok(Action::await_change());
+    }
+
+    info!(
+        event = "access_request_reconciliation_start",
+        namespace = %ns,
+        resource_name = %name,
+        phase = ?phase,
+        "Starting OpenFGAAccessRequest reconciliation"
+    );
+
+    let requester = request.annotations().get(REQUESTED_BY_ANNOTATION).cloned();
+    let tuple = requested_tuple(&request);
+    let mut trail = previous.trail.clone().unwrap_or_default();
+    let mut status = OpenFGAAccessRequestStatus {
+        phase: Some(phase),
+        ..previous.clone()
+    };
+    let mut clear_decision = false;
+
+    if trail.is_empty() {
+        trail.push(trail_entry(
+            "Requested",
+            requester.as_deref(),
+            Some(match &request.spec.justification {
+                Some(justification) => format!("{}: {}", tuple, justification),
+                None => tuple.to_string(),
+            }),
+        ));
+        let notified = notify(&ctx, &request).await;
+        if let Err(e) = &notified {
+            warn!(
+                event = "access_request_notify_failed",
+                namespace = %ns,
+                resource_name = %name,
+                error = %e,
+                "Failed to notify approvers"
+            );
+        }
+        trail.push(trail_entry(
+            if notified.is_ok() {
+                "Notified"
+            } else {
+                "NotifyFailed"
+            },
+            None,
+            notified.err(),
+        ));
+    }
+
+    if phase == AccessRequestPhase::Pending {
+        if let Some(decision) = &previous.decision {
+            let verdict = match decision_error(requester.as_deref(), decision) {
+                Some(e) => Err(e),
+                None => authorize(&ctx.client, &request, decision).await?,
+            };
+            let approver = decision.approver.as_deref();
+            match verdict {
+                Err(reason) => {
+                    warn!(
+                        event = "access_request_decision_rejected",
+                        namespace = %ns,
+                        resource_name = %name,
+                        approver = ?approver,
+                        reason = %reason,
+                        "Ignoring decision from an unauthorized identity"
+                    );
+                    trail.push(trail_entry("DecisionRejected", approver, Some(reason)));
+                    clear_decision = true;
+                }
+                Ok(()) => {
+                    info!(
+                        event = "access_request_decided",
+                        namespace = %ns,
+                        resource_name = %name,
+                        approver = ?approver,
+                        approved = decision.approved,
+                        "Access request decided"
+                    );
+                    let (action, next) = if decision.approved {
+                        ("Approved", AccessRequestPhase::Approved)
+                    } else {
+                        ("Denied", AccessRequestPhase::Denied)
+                    };
+                    trail.push(trail_entry(action, approver, decision.comment.clone()));
+                    status.phase = Some(next);
+                }
+            }
+        }
+    }
+
+    let mut requeue = None;
+    let (complete, reason, message) = match status.phase.unwrap_or_default() {
+        AccessRequestPhase::Pending => (
+            false,
+            "AwaitingApproval",
+            "Waiting for an approver's decision".to_string(),
+        ),
+        AccessRequestPhase::Denied => (true, "Denied", format!("{} was denied", tuple)),
+        AccessRequestPhase::Approved | AccessRequestPhase::Granted => {
+            match write_tuple(&ctx.client, &request).await {
+                Ok(store_id) => {
+                    info!(
+                        event = "access_request_granted",
+                        namespace = %ns,
+                        resource_name = %name,
+                        tuple = %tuple,
+                        store_id = %store_id,
+                        "Wrote approved tuple"
+                    );
+                    trail.push(trail_entry(
+                        "TupleWritten",
+                        None,
+                        Some(format!("{} written to store {}", tuple, store_id)),
+                    ));
+                    status.phase = Some(AccessRequestPhase::Granted);
+                    (true, "Granted", format!("{} was written", tuple))
+                }
+                Err(e) => {
+                    warn!(
+                        event = "access_request_write_failed",
+                        namespace = %ns,
+                        resource_name = %name,
+                        error = %e,
+                        "Failed to write approved tuple, retrying"
+                    );
+                    requeue = Some(Duration::from_secs(30));
+                    (false, "WriteFailed", e)
+                }
+            }
+        }
+    };
+    status.conditions = Some(vec![OpenFGACondition::new(
+        "Complete",
+        complete,
+        reason,
+        &message,
+        request.metadata.generation,
+        previous.conditions.as_deref(),
+    )]);
+    status.trail = Some(trail);
+
+    let mut patch = json!({ "status": status });
+    // Only ever clear the decision; writing it back could race an approver's patch
+    if !clear_decision {
+        if let Some(status) = patch["status"].as_object_mut() {
+            status.remove("decision");
+        }
+    }
+    let requests: Api<OpenFGAAccessRequest> = Api::namespaced(ctx.client.clone(), &ns);
+    requests
+        .patch_status(&name, &PatchParams::default(), &Patch::Merge(&patch))
+        .await?;
+
+    Ok(requeue.map_or_else(Action::await_change, Action::requeue))
+}
+
+fn error_policy(
+    request: Arc<OpenFGAAccessRequest>,
+    error: &ControllerError,
+    _ctx: Arc<OpenFGAAccessRequestController>,
+) -> Action {
+    warn!(
+        event = "access_request_reconciliation_error",
+        namespace = %request.namespace().unwrap_or_default(),
+        resource_name = %request.name_any(),
+        error_message = %error,
+        "OpenFGAAccessRequest reconciliation failed, retrying"
+    );
+    metrics::record_reconcile_error::<OpenFGAAccessRequest>(CONTROLLER_NAME, error.kind());
+    Action::requeue(Duration::from_secs(30))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{OpenFGAAccessRequestSpec, StoreReference};
+    use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+
+    fn user(name: &str, groups: &[&str]) -> UserInfo {
+        UserInfo {
+            username: Some(name.to_string()),
+            groups: Some(groups.iter().map(|g| g.to_string()).collect()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_stamp_identity_on_create() {
+        let object = json!({
+            "metadata": { "name": "anne-viewer", "annotations": { REQUESTED_BY_ANNOTATION: "mallory" } },
+            "spec": {},
+        });
+        let stamped = stamp_identity(&Operation::Create, &object, None, &user("anne", &[]));
+        assert_eq!(
+            stamped["metadata"]["annotations"][REQUESTED_BY_ANNOTATION],
+            "anne"
+        );
+    }
+
+    #[test]
+    fn test_stamp_identity_on_decision() {
+        let old = json!({
+            "metadata": { "annotations": { REQUESTED_BY_ANNOTATION: "anne" } },
+            "status": { "phase": "Pending" },
+        });
+        let updated = json!({
+            "metadata": { "annotations": { REQUESTED_BY_ANNOTATION: "bob" } },
+            "status": { "phase": "Pending", "decision": { "approved": true, "approver": "ceo" } },
+        });
+        let stamped = stamp_identity(
+            &Operation::Update,
+            &updated,
+            Some(&old),
+            &user("carol", &["security"]),
+        );
+        assert_eq!(
+            stamped["metadata"]["annotations"][REQUESTED_BY_ANNOTATION],
+            "anne"
+        );
+        assert_eq!(stamped["status"]["decision"]["approver"], "carol");
+        assert_eq!(
+            stamped["status"]["decision"]["approverGroups"],
+            json!(["security"])
+        );
+
+        // Unrelated updates keep the recorded approver
+        let restamped = stamp_identity(
+            &Operation::Update,
+            &stamped,
+            Some(&stamped),
+            &user("operator", &[]),
+        );
+        assert_eq!(restamped, stamped);
+
+        let without_annotation = stamp_identity(
+            &Operation::Update,
+            &json!({ "metadata": { "annotations": { REQUESTED_BY_ANNOTATION: "bob" } } }),
+            Some(&json!({ "metadata": {} })),
+            &user("bob", &[]),
+        );
+        assert!(without_annotation["metadata"].get("annotations").is_none());
+    }
+
+    #[test]
+    fn test_decision_error() {
+        let decision = |approver: Option<&str>, approved| AccessDecision {
+            approved,
+            comment: None,
+            approver: approver.map(str::to_string),
+            approver_groups: vec![],
+        };
+
+        assert!(decision_error(Some("anne"), &decision(None, true))
+            .unwrap()
+            .contains("no approver identity"));
+        assert_eq!(
+            decision_error(Some("anne"), &decision(Some("anne"), true)),
+            Some("anne cannot approve their own request".to_string())
+        );
+        // Withdrawing your own request is not an approval
+        assert!(decision_error(Some("anne"), &decision(Some("anne"), false)).is_none());
+        assert!(decision_error(Some("anne"), &decision(Some("carol"), true)).is_none());
+    }
+
+    #[test]
+    fn test_notification() {
+        let request = OpenFGAAccessRequest {
+            metadata: ObjectMeta {
+                name: Some("anne-viewer".to_string()),
+                namespace: Some("team".to_string()),
+                annotations: Some(
+                    [(REQUESTED_BY_ANNOTATION.to_string(), "anne".to_string())].into(),
+                ),
+                ..Default::default()
+            },
+            spec: OpenFGAAccessRequestSpec {
+                store_ref: StoreReference {
+                    name: "docs".to_string(),
+                },
+                user: "user:anne".to_string(),
+                relation: "viewer".to_string(),
+                object: "document:roadmap".to_string(),
+                justification: Some("quarterly planning".to_string()),
+            },
+            status: None,
+        };
+
+        assert_eq!(
+            notification(&request),
+            json!({
+                "namespace": "team",
+                "name": "anne-viewer",
+                "requestedBy": "anne",
+                "store": "docs",
+                "tuple": { "user": "user:anne", "relation": "viewer", "object": "document:roadmap" },
+                "justification": "quarterly planning",
+            })
+        );
+    }
+}
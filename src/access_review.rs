@@ -0,0 +1,282 @@
+//! Access reviews for recertification. On a schedule, every tuple on a
+//! privileged relation is collected, compared with the previous report and the
+//! result written to a `<store>-access-review` ConfigMap. Added and removed
+//! grants are announced through an Event and, when configured, a webhook.
+
+use crate::apply;
+use crate::notifications;
+use crate::openfga_client::ClientResult;
+use crate::retention::matches_pattern;
+use crate::tuple_scan::TupleReader;
+use crate::tuples::TupleKey;
+use crate::types::{AccessReviewPolicy, OpenFGAStore};
+use k8s_openapi::api::core::v1::ConfigMap;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use kube::api::{Api, Patch};
+use kube::runtime::events::{Event, EventType, Recorder};
+use kube::{Client, Resource, ResourceExt};
+use serde_json::json;
+use std::collections::{BTreeMap, BTreeSet};
+
+const PAGE_SIZE: usize = 100;
+
+/// Optional URL every report with changes is POSTed to.
+const NOTIFY_URL_ENV: &str = "OPENFGA_OPERATOR_ACCESS_REVIEW_WEBHOOK_URL";
+
+/// Data key holding the grants of the latest report, one `object#relation@user` per line.
+pub const GRANTS_KEY: &str = "grants";
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AccessChanges {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl AccessChanges {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+pub fn report_name(store: &str) -> String {
+    format!("{}-access-review", store)
+}
+
+fn is_privileged(policy: &AccessReviewPolicy, tuple: &TupleKey) -> bool {
+    let object_type = tuple.object.split(':').next().unwrap_or_default();
+    policy
+        .relations
+        .iter()
+        .any(|p| matches_pattern(p, &tuple.relation))
+        && (policy.object_types.is_empty()
+            || policy
+                .object_types
+                .iter()
+                .any(|p| matches_pattern(p, object_type)))
+}
+
+/// Reads every tuple in the store and keeps those on a privileged relation.
+pub async fn collect_grants(
+    reader: &impl TupleReader,
+    policy: &AccessReviewPolicy,
+) -> ClientResult<BTreeSet<String>> {
+    let mut grants = BTreeSet::new();
+    let mut token: Option<String> = None;
+    loop {
+        let page = reader.read_page(PAGE_SIZE, token.as_deref()).await?;
+        grants.extend(
+            page.tuples
+                .iter()
+                .filter(|tuple| is_privileged(policy, tuple))
+                .map(|tuple| tuple.to_string()),
+        );
+        match page.continuation_token {
+            Some(next) => token = Some(next),
+            None => return Ok(grants),
+        }
+    }
+}
+
+pub fn compare(previous: &BTreeSet<String>, current: &BTreeSet<String>) -> AccessChanges {
+    AccessChanges {
+        added: current.difference(previous).cloned().collect(),
+        removed: previous.difference(current).cloned().collect(),
+    }
+}
+
+/// Grants recorded in an existing report ConfigMap.
+pub fn previous_grants(report: Option<&ConfigMap>) -> BTreeSet<String> {
+    report
+        .and_then(|cm| cm.data.as_ref())
+        .and_then(|data| data.get(GRANTS_KEY))
+        .map(|grants| grants.lines().map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+pub fn create_report(
+    store: &OpenFGAStore,
+    grants: &BTreeSet<String>,
+    changes: &AccessChanges,
+    generated_at: &str,
+) -> ConfigMap {
+    let join = |lines: &mut dyn Iterator<Item = &String>| {
+        lines.map(String::as_str).collect::<Vec<_>>().join("\n")
+    };
+    ConfigMap {
+        metadata: ObjectMeta {
+            name: Some(report_name(&store.name_any())),
+            namespace: store.namespace(),
+            labels: Some(BTreeMap::from([
+                (
+                    "app.kubernetes.io/managed-by".to_string(),
+                    "openfga-operator".to_string(),
+                ),
+                ("openfga.dev/store".to_string(), store.name_any()),
+            ])),
+            owner_references: store.controller_owner_ref(&()).map(|o| vec![o]),
+            ..Default::default()
+        },
+        data: Some(BTreeMap::from([
+            (GRANTS_KEY.to_string(), join(&mut grants.iter())),
+            ("added".to_string(), join(&mut changes.added.iter())),
+            ("removed".to_string(), join(&mut changes.removed.iter())),
+            ("generatedAt".to_string(), generated_at.to_string()),
+        ])),
+        ..Default::default()
+    }
+}
+
+/// Builds the report for `grants` against the stored one, applies it and announces
+/// any changes. Returns the changes; delivery failures are returned as a message.
+pub async fn publish_report(
+    client: &Client,
+    store: &OpenFGAStore,
+    grants: &BTreeSet<String>,
+    generated_at: &str,
+) -> Result<(AccessChanges, Option<String>), kube::Error> {
+    let ns = store.namespace().unwrap_or_default();
+    let config_maps: Api<ConfigMap> = Api::namespaced(client.clone(), &ns);
+    let name = report_name(&store.name_any());
+    let previous = config_maps.get_opt(&name).await?;
+    let changes = compare(&previous_grants(previous.as_ref()), grants);
+
+    let report = create_report(store, grants, &changes, generated_at);
+    config_maps
+        .patch(&name, &apply::apply_params(), &Patch::Apply(&report))
+        .await?;
+
+    if changes.is_empty() {
+        return Ok((changes, None));
+    }
+    let recorder = Recorder::new(
+        client.clone(),
+        "openfga-store-controller".into(),
+        store.object_ref(&()),
+    );
+    let event = Event {
+        type_: EventType::Normal,
+        reason: "AccessReviewChanged".to_string(),
+        note: Some(format!(
+            "{} privileged grant(s) added, {} removed; see ConfigMap {}",
+            changes.added.len(),
+            changes.removed.len(),
+            name
+        )),
+        action: "Review".to_string(),
+        secondary: None,
+    };
+    let mut failure = recorder
+        .publish(event)
+        .await
+        .err()
+        .map(|e| format!("failed to publish event: {}", e));
+    if let Some(url) = notifications::url_from_env(NOTIFY_URL_ENV) {
+        let body = json!({
+            "namespace": ns,
+            "store": store.name_any(),
+            "report": name,
+            "generatedAt": generated_at,
+            "added": changes.added,
+            "removed": changes.removed,
+        });
+        if let Err(e) = notifications::post_json(&url, &body).await {
+            failure = Some(e);
+        }
+    }
+    Ok((changes, failure))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::openfga_client::TuplePage;
+    use crate::types::{InstanceReference, OpenFGAStoreSpec};
+
+    struct Pages(Vec<TuplePage>);
+
+    impl TupleReader for Pages {
+        async fn read_page(
+            &self,
+            _page_size: usize,
+            continuation_token: Option<&str>,
+        ) -> ClientResult<TuplePage> {
+            let index = continuation_token.map_or(0, |t| t.parse().unwrap());
+            Ok(self.0[index].clone())
+        }
+    }
+
+    fn policy() -> AccessReviewPolicy {
+        AccessReviewPolicy {
+            relations: vec!["admin".to_string(), "*owner".to_string()],
+            object_types: vec!["account".to_string()],
+            interval_minutes: 1440,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_collect_grants() {
+        let reader = Pages(vec![
+            TuplePage {
+                tuples: vec![
+                    TupleKey::new("user:anne", "admin", "account:checking"),
+                    TupleKey::new("user:bob", "viewer", "account:checking"),
+                ],
+                continuation_token: Some("1".to_string()),
+            },
+            TuplePage {
+                tuples: vec![
+                    TupleKey::new("user:carol", "co_owner", "account:savings"),
+                    TupleKey::new("user:dave", "admin", "document:policy"),
+                ],
+                continuation_token: None,
+            },
+        ]);
+
+        let grants = collect_grants(&reader, &policy()).await.unwrap();
+        assert_eq!(
+            grants.into_iter().collect::<Vec<_>>(),
+            vec![
+                "account:checking#admin@user:anne".to_string(),
+                "account:savings#co_owner@user:carol".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compare_against_previous_report() {
+        let store = OpenFGAStore::new(
+            "banking",
+            OpenFGAStoreSpec {
+                instance_ref: InstanceReference {
+                    name: "openfga".to_string(),
+                    selector: BTreeMap::new(),
+                    namespace: None,
+                },
+                store_name: None,
+                retention: None,
+                access_review: Some(policy()),
+                access_tokens: vec![],
+            },
+        );
+        let first: BTreeSet<String> = ["a#admin@user:anne", "b#admin@user:bob"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let report = create_report(&store, &first, &AccessChanges::default(), "t0");
+        assert_eq!(
+            report.metadata.name.as_deref(),
+            Some("banking-access-review")
+        );
+        assert_eq!(previous_grants(Some(&report)), first);
+        assert!(previous_grants(None).is_empty());
+
+        let second: BTreeSet<String> = ["b#admin@user:bob", "c#admin@user:carol"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let changes = compare(&previous_grants(Some(&report)), &second);
+        assert_eq!(changes.added, vec!["c#admin@user:carol".to_string()]);
+        assert_eq!(changes.removed, vec!["a#admin@user:anne".to_string()]);
+        assert!(compare(&second, &second).is_empty());
+    }
+}
@@ -0,0 +1,715 @@
+//! Per-store API tokens. Every `spec.accessTokens` entry of an OpenFGAStore gets
+//! a random token, written to a Secret in the consumer's namespace together with
+//! the store id and API URL. The instance's `<name>-preshared-keys` Secret
+//! collects the tokens of all its stores plus one for the operator itself, and
+//! while any store declares a token the instance runs with OpenFGA's
+//! preshared-key authentication.
+//!
+//! OpenFGA does not scope preshared keys to stores: a token authenticates
+//! against the whole instance. Teams are scoped by which Secret they can read
+//! and which store id they are handed; removing a token from the spec revokes
+//! it once the pods have rolled.
+//!
+//! Consumer Secrets default to the store's namespace. Another namespace has to
+//! opt in with an OpenFGAReferenceGrant listing the store's namespace as an
+//! `OpenFGAStore` source (and the instance under `to`, if it lists any). The
+//! operator only ever writes or deletes Secrets it created for the same store;
+//! a token whose Secret already exists otherwise is refused.
+//!
+//! `spec.env` setting `OPENFGA_AUTHN_METHOD` takes precedence, leaving the
+//! instance's authentication to the user.
+
+use crate::apply;
+use crate::controller::ControllerResult;
+use crate::instance_ref;
+use crate::labels;
+use crate::openfga_client::instance_url;
+use crate::types::{
+    AccessTokenSpec, IssuedAccessToken, OpenFGA, OpenFGAReferenceGrant, OpenFGAStore,
+};
+use k8s_openapi::api::apps::v1::Deployment;
+use k8s_openapi::api::core::v1::{EnvVar, EnvVarSource, Secret, SecretKeySelector};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use k8s_openapi::ByteString;
+use kube::api::{Api, DeleteParams, ListParams, Patch};
+use kube::{Client, Resource, ResourceExt};
+use ring::rand::{SecureRandom, SystemRandom};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Mutex, OnceLock};
+use tracing::{info, instrument, warn};
+
+/// Key of the keys Secret holding every token, comma separated.
+pub const KEYS_KEY: &str = "keys";
+/// Key of the keys Secret holding the operator's own token.
+pub const OPERATOR_KEY: &str = "operator";
+/// Pod annotation that rolls the pods when the accepted keys change.
+pub const KEYS_HASH_ANNOTATION: &str = "openfga.dev/preshared-keys-hash";
+/// Variable the backup and restore Jobs read the operator's token from.
+pub const API_TOKEN_ENV: &str = "OPENFGA_API_TOKEN";
+
+const AUTHN_METHOD_ENV: &str = "OPENFGA_AUTHN_METHOD";
+const AUTHN_KEYS_ENV: &str = "OPENFGA_AUTHN_PRESHARED_KEYS";
+const STORE_LABEL: &str = "openfga.dev/store";
+const STORE_NAMESPACE_LABEL: &str = "openfga.dev/store-namespace";
+const TOKEN_BYTES: usize = 32;
+
+pub fn keys_secret_name(instance: &str) -> String {
+    format!("{}-preshared-keys", instance)
+}
+
+/// Key of a store's token in the keys Secret. Token names are DNS labels, so
+/// the last dot separates them from the store name. Stores from other
+/// namespaces are prefixed with theirs and an underscore, which no resource
+/// name contains.
+fn entry_key(store: &OpenFGAStore, instance: &OpenFGA, token: &str) -> String {
+    let store_ns = store.namespace().unwrap_or_default();
+    if store_ns == instance.namespace().unwrap_or_default() {
+        format!("{}.{}", store.name_any(), token)
+    } else {
+        format!("{}_{}.{}", store_ns, store.name_any(), token)
+    }
+}
+
+/// Whether `secret` was created by the operator for the tokens of `store`.
+fn issued_for(secret: &Secret, store: &OpenFGAStore) -> bool {
+    let labels = secret.labels();
+    labels
+        .get("app.kubernetes.io/managed-by")
+        .map(String::as_str)
+        == Some(labels::MANAGED_BY)
+        && labels.get(STORE_LABEL) == Some(&store.name_any())
+        && labels.get(STORE_NAMESPACE_LABEL) == Some(&store.namespace().unwrap_or_default())
+}
+
+/// Whether `grants`, from the consumer namespace, let stores in `store_ns`
+/// hand out tokens for `instance` there.
+pub fn consumer_granted(
+    grants: &[OpenFGAReferenceGrant],
+    store_ns: &str,
+    instance: &OpenFGA,
+) -> bool {
+    instance_ref::granted(grants, "OpenFGAStore", store_ns, &instance.name_any())
+}
+
+/// Namespace and name of the Secret a token is handed out in.
+pub fn consumer_secret(store: &OpenFGAStore, token: &AccessTokenSpec) -> (String, String) {
+    (
+        token
+            .namespace
+            .clone()
+            .unwrap_or_else(|| store.namespace().unwrap_or_default()),
+        token
+            .secret_name
+            .clone()
+            .unwrap_or_else(|| format!("{}-{}-token", store.name_any(), token.name)),
+    )
+}
+
+pub fn generate_token() -> String {
+    let mut bytes = [0u8; TOKEN_BYTES];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .expect("system random number generator unavailable");
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// The tokens `stores` declare for `instance`, keyed by store and token name,
+/// plus the operator's; empty when no store declares a token. Stores from
+/// other namespaces count once one of `grants` allows them. Existing values
+/// are kept so issued tokens stay valid.
+pub fn desired_entries(
+    stores: &[OpenFGAStore],
+    instance: &OpenFGA,
+    grants: &[OpenFGAReferenceGrant],
+    existing: &BTreeMap<String, String>,
+) -> BTreeMap<String, String> {
+    let mut entries = BTreeMap::new();
+    let instance_ns = instance.namespace().unwrap_or_default();
+    let declared = stores.iter().filter(|store| {
+        let store_ns = store.namespace().unwrap_or_default();
+        instance_ref::matches(&store.spec.instance_ref, &store_ns, instance)
+            && (store_ns == instance_ns
+                || instance_ref::granted(grants, "OpenFGAStore", &store_ns, &instance.name_any()))
+            && store.metadata.deletion_timestamp.is_none()
+    });
+    for store in declared {
+        for token in &store.spec.access_tokens {
+            let key = entry_key(store, instance, &token.name);
+            let value = existing.get(&key).cloned().unwrap_or_else(generate_token);
+            entries.insert(key, value);
+        }
+    }
+    if !entries.is_empty() {
+        let operator = existing
+            .get(OPERATOR_KEY)
+            .cloned()
+            .unwrap_or_else(generate_token);
+        entries.insert(OPERATOR_KEY.to_string(), operator);
+    }
+    entries
+}
+
+fn joined(entries: &BTreeMap<String, String>) -> String {
+    entries.values().cloned().collect::<Vec<_>>().join(",")
+}
+
+pub fn keys_hash(entries: &BTreeMap<String, String>) -> String {
+    format!("{:x}", Sha256::digest(joined(entries).as_bytes()))
+}
+
+fn secret_entries(secret: &Secret) -> BTreeMap<String, String> {
+    secret
+        .data
+        .iter()
+        .flatten()
+        .filter(|(key, _)| key.as_str() != KEYS_KEY)
+        .map(|(key, value)| (key.clone(), String::from_utf8_lossy(&value.0).into_owned()))
+        .collect()
+}
+
+fn operator_tokens() -> &'static Mutex<HashMap<String, String>> {
+    static TOKENS: OnceLock<Mutex<HashMap<String, String>>> = OnceLock::new();
+    TOKENS.get_or_init(Default::default)
+}
+
+fn instance_key(openfga: &OpenFGA) -> String {
+    format!(
+        "{}/{}",
+        openfga.namespace().unwrap_or_default(),
+        openfga.name_any()
+    )
+}
+
+fn remember(openfga: &OpenFGA, entries: Option<&BTreeMap<String, String>>) {
+    let mut tokens = operator_tokens().lock().unwrap();
+    match entries.and_then(|e| e.get(OPERATOR_KEY)) {
+        Some(token) => tokens.insert(instance_key(openfga), token.clone()),
+        None => tokens.remove(&instance_key(openfga)),
+    };
+}
+
+/// The token the operator authenticates to `openfga` with, once the keys have
+/// been synced since startup.
+pub fn operator_token(openfga: &OpenFGA) -> Option<String> {
+    operator_tokens()
+        .lock()
+        .unwrap()
+        .get(&instance_key(openfga))
+        .cloned()
+}
+
+/// The keys the instance currently accepts, without changing them.
+pub async fn read_keys(
+    client: &Client,
+    openfga: &OpenFGA,
+) -> ControllerResult<Option<BTreeMap<String, String>>> {
+    let secrets: Api<Secret> =
+        Api::namespaced(client.clone(), &openfga.namespace().unwrap_or_default());
+    let existing = secrets
+        .get_opt(&keys_secret_name(&openfga.name_any()))
+        .await?;
+    Ok(existing.map(|secret| secret_entries(&secret)))
+}
+
+/// Brings the keys Secret of `openfga` in line with the tokens its stores
+/// declare, deleting it once none do. Returns the accepted keys.
+#[instrument(skip(client, openfga), fields(namespace = %openfga.namespace().unwrap_or_default(), name = %openfga.name_any()))]
+pub async fn sync_keys(
+    client: &Client,
+    openfga: &OpenFGA,
+) -> ControllerResult<Option<BTreeMap<String, String>>> {
+    let ns = openfga.namespace().unwrap_or_default();
+    let name = openfga.name_any();
+    let secret_name = keys_secret_name(&name);
+    let stores = Api::<OpenFGAStore>::all(client.clone())
+        .list(&ListParams::default())
+        .await?;
+    let grants = Api::<OpenFGAReferenceGrant>::namespaced(client.clone(), &ns)
+        .list(&ListParams::default())
+        .await?;
+    let secrets: Api<Secret> = Api::namespaced(client.clone(), &ns);
+    let existing = secrets.get_opt(&secret_name).await?;
+    let existing_entries = existing.as_ref().map(secret_entries).unwrap_or_default();
+    let entries = desired_entries(&stores.items, openfga, &grants.items, &existing_entries);
+
+    if entries.is_empty() {
+        if existing.is_some() {
+            secrets
+                .delete(&secret_name, &DeleteParams::default())
+                .await?;
+            info!(
+                event = "preshared_keys_removed",
+                namespace = %ns,
+                resource_name = %name,
+                "No store declares access tokens, removed preshared keys"
+            );
+        }
+        remember(openfga, None);
+        return Ok(None);
+    }
+
+    if existing.is_none() || entries != existing_entries {
+        let secret = keys_secret(
+            openfga,
+            &entries,
+            existing.and_then(|s| s.metadata.resource_version),
+        );
+        secrets
+            .patch(&secret_name, &apply::apply_params(), &Patch::Apply(&secret))
+            .await?;
+        info!(
+            event = "preshared_keys_updated",
+            namespace = %ns,
+            resource_name = %name,
+            keys = entries.len(),
+            "Updated preshared keys"
+        );
+    }
+    remember(openfga, Some(&entries));
+    Ok(Some(entries))
+}
+
+/// Replaces the operator's key of `openfga` with a fresh one; the pods roll to
+/// accept it, and until they have, the operator's requests to the instance are
+/// rejected and retried on later reconciles. Returns false when the instance
+/// has no keys.
+#[instrument(skip(client, openfga), fields(namespace = %openfga.namespace().unwrap_or_default(), name = %openfga.name_any()))]
+pub async fn rotate_operator_token(client: &Client, openfga: &OpenFGA) -> ControllerResult<bool> {
+    let secrets: Api<Secret> =
+        Api::namespaced(client.clone(), &openfga.namespace().unwrap_or_default());
+    let secret_name = keys_secret_name(&openfga.name_any());
+    let Some(existing) = secrets.get_opt(&secret_name).await? else {
+        return Ok(false);
+    };
+    let mut entries = secret_entries(&existing);
+    if !entries.contains_key(OPERATOR_KEY) {
+        return Ok(false);
+    }
+    entries.insert(OPERATOR_KEY.to_string(), generate_token());
+
+    let secret = keys_secret(openfga, &entries, existing.metadata.resource_version);
+    secrets
+        .patch(&secret_name, &apply::apply_params(), &Patch::Apply(&secret))
+        .await?;
+    info!(
+        event = "operator_key_rotated",
+        namespace = %openfga.namespace().unwrap_or_default(),
+        resource_name = %openfga.name_any(),
+        "Rotated the operator's preshared key"
+    );
+    remember(openfga, Some(&entries));
+    Ok(true)
+}
+
+/// The keys Secret of `openfga` holding `entries`. With `resource_version` the
+/// write fails on a concurrent change instead of dropping its tokens.
+pub(crate) fn keys_secret(
+    openfga: &OpenFGA,
+    entries: &BTreeMap<String, String>,
+    resource_version: Option<String>,
+) -> Secret {
+    let name = openfga.name_any();
+    let mut data: BTreeMap<String, ByteString> = entries
+        .iter()
+        .map(|(key, value)| (key.clone(), ByteString(value.as_bytes().to_vec())))
+        .collect();
+    data.insert(
+        KEYS_KEY.to_string(),
+        ByteString(joined(entries).into_bytes()),
+    );
+    Secret {
+        metadata: ObjectMeta {
+            name: Some(keys_secret_name(&name)),
+            namespace: openfga.namespace(),
+            labels: Some(labels::instance_labels(openfga, &name)),
+            owner_references: openfga.controller_owner_ref(&()).map(|o| vec![o]),
+            resource_version,
+            ..Default::default()
+        },
+        type_: Some("Opaque".to_string()),
+        data: Some(data),
+        ..Default::default()
+    }
+}
+
+/// Turns on preshared-key authentication with `entries` in `deployment`,
+/// unless `spec.env` configures authentication itself.
+pub fn set_preshared_auth(
+    deployment: &mut Deployment,
+    openfga: &OpenFGA,
+    name: &str,
+    entries: &BTreeMap<String, String>,
+) {
+    if openfga
+        .spec
+        .env
+        .iter()
+        .any(|var| var.name == AUTHN_METHOD_ENV)
+    {
+        return;
+    }
+    let Some(spec) = deployment.spec.as_mut() else {
+        return;
+    };
+    spec.template
+        .metadata
+        .get_or_insert_with(Default::default)
+        .annotations
+        .get_or_insert_with(Default::default)
+        .insert(KEYS_HASH_ANNOTATION.to_string(), keys_hash(entries));
+    if let Some(container) = spec
+        .template
+        .spec
+        .as_mut()
+        .and_then(|p| p.containers.first_mut())
+    {
+        let env = container.env.get_or_insert_with(Vec::new);
+        env.retain(|var| var.name != AUTHN_METHOD_ENV && var.name != AUTHN_KEYS_ENV);
+        env.push(EnvVar {
+            name: AUTHN_METHOD_ENV.to_string(),
+            value: Some("preshared".to_string()),
+            ..Default::default()
+        });
+        env.push(secret_env(AUTHN_KEYS_ENV, name, KEYS_KEY, false));
+    }
+}
+
+fn secret_env(var: &str, instance: &str, key: &str, optional: bool) -> EnvVar {
+    EnvVar {
+        name: var.to_string(),
+        value_from: Some(EnvVarSource {
+            secret_key_ref: Some(SecretKeySelector {
+                name: Some(keys_secret_name(instance)),
+                key: key.to_string(),
+                optional: optional.then_some(true),
+            }),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+/// The operator's token for Jobs running the `backup` CLI against `openfga`;
+/// left empty while the instance accepts no keys.
+pub fn api_token_env(openfga: &OpenFGA) -> EnvVar {
+    secret_env(API_TOKEN_ENV, &openfga.name_any(), OPERATOR_KEY, true)
+}
+
+/// Tokens `issue_tokens` handed out, and why others were not.
+#[derive(Debug, Default)]
+pub struct Issued {
+    pub tokens: Option<Vec<IssuedAccessToken>>,
+    pub refused: Vec<String>,
+}
+
+/// Writes the tokens of `store` to their consumer Secrets and deletes the
+/// Secrets of tokens no longer declared. Tokens for a namespace that grants
+/// nothing, or whose Secret exists without being the store's, are refused.
+#[instrument(skip(client, store, instance, entries, previous), fields(namespace = %store.namespace().unwrap_or_default(), name = %store.name_any()))]
+pub async fn issue_tokens(
+    client: &Client,
+    store: &OpenFGAStore,
+    instance: &OpenFGA,
+    store_id: &str,
+    entries: Option<&BTreeMap<String, String>>,
+    previous: &[IssuedAccessToken],
+) -> ControllerResult<Issued> {
+    let store_ns = store.namespace().unwrap_or_default();
+    let mut issued = Vec::new();
+    let mut refused = Vec::new();
+    for token in &store.spec.access_tokens {
+        let Some(value) = entries.and_then(|e| e.get(&entry_key(store, instance, &token.name)))
+        else {
+            continue;
+        };
+        let (ns, secret_name) = consumer_secret(store, token);
+        if ns != store_ns {
+            let grants = Api::<OpenFGAReferenceGrant>::namespaced(client.clone(), &ns)
+                .list(&ListParams::default())
+                .await?;
+            if !consumer_granted(&grants.items, &store_ns, instance) {
+                refused.push(format!(
+                    "token {}: namespace {} has no OpenFGAReferenceGrant for OpenFGAStore from {}",
+                    token.name, ns, store_ns
+                ));
+                continue;
+            }
+        }
+        let secrets: Api<Secret> = Api::namespaced(client.clone(), &ns);
+        if let Some(existing) = secrets.get_opt(&secret_name).await? {
+            if !issued_for(&existing, store) {
+                warn!(
+                    event = "access_token_refused",
+                    namespace = %ns,
+                    secret = %secret_name,
+                    token = %token.name,
+                    "Secret exists and was not created for this store, not overwriting it"
+                );
+                refused.push(format!(
+                    "token {}: Secret {}/{} exists and was not created for this store",
+                    token.name, ns, secret_name
+                ));
+                continue;
+            }
+        }
+        let secret = Secret {
+            metadata: ObjectMeta {
+                name: Some(secret_name.clone()),
+                namespace: Some(ns.clone()),
+                labels: Some(BTreeMap::from([
+                    (
+                        "app.kubernetes.io/managed-by".to_string(),
+                        labels::MANAGED_BY.to_string(),
+                    ),
+                    (STORE_LABEL.to_string(), store.name_any()),
+                    (STORE_NAMESPACE_LABEL.to_string(), store_ns.clone()),
+                ])),
+                // Owner references cannot cross namespaces
+                owner_references: (ns == store_ns)
+                    .then(|| store.controller_owner_ref(&()).map(|o| vec![o]))
+                    .flatten(),
+                ..Default::default()
+            },
+            type_: Some("Opaque".to_string()),
+            data: Some(BTreeMap::from([
+                ("token".to_string(), ByteString(value.as_bytes().to_vec())),
+                (
+                    "storeId".to_string(),
+                    ByteString(store_id.as_bytes().to_vec()),
+                ),
+                (
+                    "apiUrl".to_string(),
+                    ByteString(instance_url(instance).into_bytes()),
+                ),
+            ])),
+            ..Default::default()
+        };
+        secrets
+            .patch(&secret_name, &apply::apply_params(), &Patch::Apply(&secret))
+            .await?;
+        issued.push(IssuedAccessToken {
+            name: token.name.clone(),
+            secret_namespace: ns,
+            secret_name,
+        });
+    }
+
+    for stale in previous.iter().filter(|p| !issued.contains(p)) {
+        let secrets: Api<Secret> = Api::namespaced(client.clone(), &stale.secret_namespace);
+        let Some(secret) = secrets.get_opt(&stale.secret_name).await? else {
+            continue;
+        };
+        if issued_for(&secret, store) {
+            secrets
+                .delete(&stale.secret_name, &DeleteParams::default())
+                .await?;
+            info!(
+                event = "access_token_revoked",
+                namespace = %stale.secret_namespace,
+                secret = %stale.secret_name,
+                token = %stale.name,
+                "Deleted Secret of an access token that is no longer declared"
+            );
+        }
+    }
+    Ok(Issued {
+        tokens: (!issued.is_empty()).then_some(issued),
+        refused,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::{create_deployment, create_test_openfga};
+
+    fn store(name: &str, tokens: &[&str]) -> OpenFGAStore {
+        let mut store = OpenFGAStore::new(
+            name,
+            serde_json::from_value(serde_json::json!({
+                "instanceRef": { "name": "authz" },
+                "accessTokens": tokens
+                    .iter()
+                    .map(|t| serde_json::json!({ "name": t }))
+                    .collect::<Vec<_>>(),
+            }))
+            .unwrap(),
+        );
+        store.metadata.namespace = Some("auth".to_string());
+        store
+    }
+
+    fn instance(name: &str) -> OpenFGA {
+        let mut openfga = create_test_openfga();
+        openfga.metadata.name = Some(name.to_string());
+        openfga.metadata.namespace = Some("auth".to_string());
+        openfga
+    }
+
+    #[test]
+    fn test_desired_entries() {
+        let authz = instance("authz");
+        assert!(
+            desired_entries(&[store("banking", &[])], &authz, &[], &BTreeMap::new()).is_empty()
+        );
+
+        let stores = [
+            store("banking", &["payments", "ledger"]),
+            store("rag", &["agent"]),
+        ];
+        let first = desired_entries(&stores, &authz, &[], &BTreeMap::new());
+        assert_eq!(
+            first.keys().collect::<Vec<_>>(),
+            vec![
+                "banking.ledger",
+                "banking.payments",
+                OPERATOR_KEY,
+                "rag.agent"
+            ]
+        );
+        assert_eq!(first["rag.agent"].len(), TOKEN_BYTES * 2);
+        assert_ne!(first["rag.agent"], first["banking.ledger"]);
+        // Another instance's stores do not contribute
+        assert!(desired_entries(&stores, &instance("other"), &[], &BTreeMap::new()).is_empty());
+
+        // Issued tokens are kept, revoked ones dropped
+        let second = desired_entries(&stores[..1], &authz, &[], &first);
+        assert_eq!(second.len(), 3);
+        assert_eq!(second["banking.payments"], first["banking.payments"]);
+        assert_eq!(second[OPERATOR_KEY], first[OPERATOR_KEY]);
+        assert_ne!(keys_hash(&first), keys_hash(&second));
+    }
+
+    #[test]
+    fn test_desired_entries_across_namespaces() {
+        let authz = instance("authz");
+        let mut foreign = store("banking", &["payments"]);
+        foreign.metadata.namespace = Some("tenant-a".to_string());
+        foreign.spec.instance_ref.namespace = Some("auth".to_string());
+        let stores = [store("banking", &["payments"]), foreign];
+
+        // Without a grant only the local store counts
+        let entries = desired_entries(&stores, &authz, &[], &BTreeMap::new());
+        assert_eq!(
+            entries.keys().collect::<Vec<_>>(),
+            vec!["banking.payments", OPERATOR_KEY]
+        );
+
+        let grant = OpenFGAReferenceGrant::new(
+            "tenants",
+            serde_json::from_value(serde_json::json!({
+                "from": [{ "kind": "OpenFGAStore", "namespace": "tenant-a" }]
+            }))
+            .unwrap(),
+        );
+        let entries = desired_entries(&stores, &authz, &[grant], &BTreeMap::new());
+        assert_eq!(
+            entries.keys().collect::<Vec<_>>(),
+            vec![
+                "banking.payments",
+                OPERATOR_KEY,
+                "tenant-a_banking.payments"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_consumer_secret() {
+        let mut banking = store("banking", &["payments"]);
+        assert_eq!(
+            consumer_secret(&banking, &banking.spec.access_tokens[0]),
+            ("auth".to_string(), "banking-payments-token".to_string())
+        );
+        banking.spec.access_tokens[0].namespace = Some("payments".to_string());
+        banking.spec.access_tokens[0].secret_name = Some("openfga".to_string());
+        assert_eq!(
+            consumer_secret(&banking, &banking.spec.access_tokens[0]),
+            ("payments".to_string(), "openfga".to_string())
+        );
+    }
+
+    #[test]
+    fn test_issued_for() {
+        let banking = store("banking", &["payments"]);
+        let mut secret = Secret::default();
+        assert!(!issued_for(&secret, &banking));
+
+        secret.metadata.labels = Some(BTreeMap::from([
+            (
+                "app.kubernetes.io/managed-by".to_string(),
+                labels::MANAGED_BY.to_string(),
+            ),
+            (STORE_LABEL.to_string(), "banking".to_string()),
+            (STORE_NAMESPACE_LABEL.to_string(), "auth".to_string()),
+        ]));
+        assert!(issued_for(&secret, &banking));
+        assert!(!issued_for(&secret, &store("rag", &["agent"])));
+    }
+
+    #[test]
+    fn test_consumer_granted() {
+        let authz = instance("authz");
+        let grant = |to: serde_json::Value| {
+            OpenFGAReferenceGrant::new(
+                "tokens",
+                serde_json::from_value(serde_json::json!({
+                    "from": [{ "kind": "OpenFGAStore", "namespace": "auth" }],
+                    "to": to,
+                }))
+                .unwrap(),
+            )
+        };
+        assert!(!consumer_granted(&[], "auth", &authz));
+        assert!(consumer_granted(
+            &[grant(serde_json::json!([]))],
+            "auth",
+            &authz
+        ));
+        assert!(!consumer_granted(
+            &[grant(serde_json::json!([]))],
+            "other",
+            &authz
+        ));
+        assert!(!consumer_granted(
+            &[grant(serde_json::json!([{ "name": "other" }]))],
+            "auth",
+            &authz
+        ));
+    }
+
+    #[test]
+    fn test_set_preshared_auth() {
+        let mut openfga = instance("authz");
+        let entries = BTreeMap::from([(OPERATOR_KEY.to_string(), "secret".to_string())]);
+        let mut deployment = create_deployment(&openfga, "auth", "authz").unwrap();
+        set_preshared_auth(&mut deployment, &openfga, "authz", &entries);
+        let template = deployment.spec.unwrap().template;
+        assert_eq!(
+            template.metadata.unwrap().annotations.unwrap()[KEYS_HASH_ANNOTATION],
+            keys_hash(&entries)
+        );
+        let env = template.spec.unwrap().containers[0].env.clone().unwrap();
+        assert!(env
+            .iter()
+            .any(|v| v.name == AUTHN_METHOD_ENV && v.value.as_deref() == Some("preshared")));
+        let keys = env.iter().find(|v| v.name == AUTHN_KEYS_ENV).unwrap();
+        let selector = keys.value_from.clone().unwrap().secret_key_ref.unwrap();
+        assert_eq!(selector.name.as_deref(), Some("authz-preshared-keys"));
+        assert_eq!(selector.key, KEYS_KEY);
+
+        // Authentication the user configures wins
+        openfga.spec.env = vec![EnvVar {
+            name: AUTHN_METHOD_ENV.to_string(),
+            value: Some("oidc".to_string()),
+            ..Default::default()
+        }];
+        let mut deployment = create_deployment(&openfga, "auth", "authz").unwrap();
+        set_preshared_auth(&mut deployment, &openfga, "authz", &entries);
+        let env = deployment.spec.unwrap().template.spec.unwrap().containers[0]
+            .env
+            .clone()
+            .unwrap();
+        assert!(!env.iter().any(|v| v.name == AUTHN_KEYS_ENV));
+    }
+}
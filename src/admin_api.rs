@@ -0,0 +1,367 @@
+//! On-demand actions on the operator's HTTP port, for platform teams that
+//! cannot or should not hand out kubectl access to annotate instances:
+//!
+//! - `POST /api/v1/resync/{namespace}/{name}` reconciles an instance now.
+//! - `POST /api/v1/pause` with `{"namespace", "name", "paused"}` pauses or
+//!   resumes reconciliation of an instance, like the `openfga.dev/paused`
+//!   annotation it sets.
+//! - `POST /api/v1/rotate-token` with `{"namespace", "name"}` rotates the
+//!   operator's preshared key of an instance.
+//!
+//! Requests authenticate with `Authorization: Bearer <OPERATOR_ADMIN_TOKEN>`.
+//! Without that variable the API is off and its paths answer 404.
+
+use crate::access_tokens;
+use crate::controller::PAUSED_ANNOTATION;
+use crate::responses::{self, HttpResult};
+use crate::types::OpenFGA;
+use hyper::header::AUTHORIZATION;
+use hyper::{Body, Method, Request, Response, StatusCode};
+use kube::api::{Api, Patch, PatchParams};
+use kube::Client;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::OnceLock;
+use tracing::{info, warn};
+
+pub const PATH_PREFIX: &str = "/api/v1/";
+
+/// Annotation stamped with the time of a requested resync. Changing it
+/// triggers a reconcile.
+pub const RESYNC_ANNOTATION: &str = "openfga.dev/resync-requested-at";
+
+const TOKEN_ENV: &str = "OPERATOR_ADMIN_TOKEN";
+
+/// An instance named in a request body.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+struct Target {
+    namespace: String,
+    name: String,
+    #[serde(default = "default_paused")]
+    paused: bool,
+}
+
+fn default_paused() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AdminAction {
+    Resync {
+        namespace: String,
+        name: String,
+    },
+    Pause {
+        namespace: String,
+        name: String,
+        paused: bool,
+    },
+    RotateToken {
+        namespace: String,
+        name: String,
+    },
+}
+
+impl AdminAction {
+    fn name(&self) -> &'static str {
+        match self {
+            AdminAction::Resync { .. } => "resync",
+            AdminAction::Pause { .. } => "pause",
+            AdminAction::RotateToken { .. } => "rotate-token",
+        }
+    }
+
+    fn target(&self) -> (&str, &str) {
+        match self {
+            AdminAction::Resync { namespace, name }
+            | AdminAction::Pause {
+                namespace, name, ..
+            }
+            | AdminAction::RotateToken { namespace, name } => (namespace, name),
+        }
+    }
+}
+
+fn admin_token() -> Option<&'static str> {
+    static TOKEN: OnceLock<Option<String>> = OnceLock::new();
+    TOKEN
+        .get_or_init(|| {
+            std::env::var(TOKEN_ENV)
+                .ok()
+                .filter(|t| !t.trim().is_empty())
+        })
+        .as_deref()
+}
+
+/// Compares in time independent of where the inputs differ.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Checks the `Authorization` header against the configured token: 404 while
+/// the API is off, 401 for a missing or wrong token.
+pub fn authorize(header: Option<&str>, token: Option<&str>) -> Result<(), StatusCode> {
+    let token = token.ok_or(StatusCode::NOT_FOUND)?;
+    let presented = header
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+    if constant_time_eq(presented.trim().as_bytes(), token.trim().as_bytes()) {
+        Ok(())
+    } else {
+        Err(StatusCode::UNAUTHORIZED)
+    }
+}
+
+/// The action a request to `path` (below [`PATH_PREFIX`]) with `body` asks for.
+pub fn parse_action(path: &str, body: &[u8]) -> Result<AdminAction, (StatusCode, String)> {
+    let target = || {
+        serde_json::from_slice::<Target>(body).map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                format!("body must name the instance: {}", e),
+            )
+        })
+    };
+    let segments: Vec<&str> = path
+        .strip_prefix(PATH_PREFIX)
+        .unwrap_or_default()
+        .split('/')
+        .collect();
+    match segments.as_slice() {
+        ["resync", namespace, name] if !namespace.is_empty() && !name.is_empty() => {
+            Ok(AdminAction::Resync {
+                namespace: namespace.to_string(),
+                name: name.to_string(),
+            })
+        }
+        ["pause"] => target().map(|t| AdminAction::Pause {
+            namespace: t.namespace,
+            name: t.name,
+            paused: t.paused,
+        }),
+        ["rotate-token"] => target().map(|t| AdminAction::RotateToken {
+            namespace: t.namespace,
+            name: t.name,
+        }),
+        _ => Err((StatusCode::NOT_FOUND, "unknown admin action".to_string())),
+    }
+}
+
+/// Metadata patch of the instance carrying out `action`, stamped with `now`;
+/// none for actions that do not change the instance itself.
+pub fn action_patch(action: &AdminAction, now: &str) -> Option<Value> {
+    match action {
+        AdminAction::Resync { .. } => {
+            Some(json!({ "metadata": { "annotations": { RESYNC_ANNOTATION: now } } }))
+        }
+        AdminAction::Pause { paused: true, .. } => {
+            Some(json!({ "metadata": { "annotations": { PAUSED_ANNOTATION: "true" } } }))
+        }
+        AdminAction::Pause { paused: false, .. } => {
+            Some(json!({ "metadata": { "annotations": { PAUSED_ANNOTATION: Value::Null } } }))
+        }
+        AdminAction::RotateToken { .. } => None,
+    }
+}
+
+fn error(status: StatusCode, message: &str) -> HttpResult<Response<Body>> {
+    responses::json(
+        status,
+        &json!({
+            "error": status.canonical_reason().unwrap_or("error"),
+            "message": message,
+        }),
+    )
+}
+
+/// Serves a request below [`PATH_PREFIX`] with the operator's client, none
+/// until it has connected to the Kubernetes API.
+pub async fn handle(req: Request<Body>, client: Option<Client>) -> HttpResult<Response<Body>> {
+    let header = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok());
+    if let Err(status) = authorize(header, admin_token()) {
+        let message = match status {
+            StatusCode::NOT_FOUND => "Not Found",
+            _ => "a valid bearer token is required",
+        };
+        return error(status, message);
+    }
+    if req.method() != Method::POST {
+        return error(StatusCode::METHOD_NOT_ALLOWED, "admin actions are POST");
+    }
+
+    let path = req.uri().path().to_string();
+    let body = match hyper::body::to_bytes(req.into_body()).await {
+        Ok(body) => body,
+        Err(e) => return error(StatusCode::BAD_REQUEST, &e.to_string()),
+    };
+    let action = match parse_action(&path, &body) {
+        Ok(action) => action,
+        Err((status, message)) => return error(status, &message),
+    };
+    let Some(client) = client else {
+        return error(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "not connected to the Kubernetes API yet",
+        );
+    };
+
+    match execute(&client, &action).await {
+        Ok(Some(status)) => {
+            let (namespace, name) = action.target();
+            info!(
+                event = "admin_action",
+                action = action.name(),
+                namespace = %namespace,
+                resource_name = %name,
+                "Admin action carried out"
+            );
+            responses::json(
+                status,
+                &json!({ "action": action.name(), "namespace": namespace, "name": name }),
+            )
+        }
+        Ok(None) => error(StatusCode::NOT_FOUND, "no such instance, or it has no keys"),
+        Err(e) => {
+            warn!(
+                event = "admin_action_failed",
+                action = action.name(),
+                error = %e,
+                "Admin action failed"
+            );
+            error(StatusCode::BAD_GATEWAY, &e.to_string())
+        }
+    }
+}
+
+/// Carries out `action`; none when its instance (or, to rotate, its keys) does
+/// not exist.
+async fn execute(client: &Client, action: &AdminAction) -> anyhow::Result<Option<StatusCode>> {
+    let (namespace, name) = action.target();
+    let instances: Api<OpenFGA> = Api::namespaced(client.clone(), namespace);
+    let Some(instance) = instances.get_opt(name).await? else {
+        return Ok(None);
+    };
+
+    if let Some(patch) = action_patch(action, &chrono::Utc::now().to_rfc3339()) {
+        instances
+            .patch(name, &PatchParams::default(), &Patch::Merge(&patch))
+            .await?;
+        return Ok(Some(match action {
+            AdminAction::Resync { .. } => StatusCode::ACCEPTED,
+            _ => StatusCode::OK,
+        }));
+    }
+    Ok(access_tokens::rotate_operator_token(client, &instance)
+        .await?
+        .then_some(StatusCode::OK))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_authorize() {
+        assert_eq!(
+            authorize(Some("Bearer s3cret"), None),
+            Err(StatusCode::NOT_FOUND)
+        );
+        assert_eq!(
+            authorize(None, Some("s3cret")),
+            Err(StatusCode::UNAUTHORIZED)
+        );
+        assert_eq!(
+            authorize(Some("Basic s3cret"), Some("s3cret")),
+            Err(StatusCode::UNAUTHORIZED)
+        );
+        assert_eq!(
+            authorize(Some("Bearer s3cre"), Some("s3cret")),
+            Err(StatusCode::UNAUTHORIZED)
+        );
+        assert_eq!(authorize(Some("Bearer s3cret"), Some("s3cret\n")), Ok(()));
+    }
+
+    #[test]
+    fn test_parse_action() {
+        assert_eq!(
+            parse_action("/api/v1/resync/auth/authz", b""),
+            Ok(AdminAction::Resync {
+                namespace: "auth".to_string(),
+                name: "authz".to_string()
+            })
+        );
+        assert_eq!(
+            parse_action(
+                "/api/v1/pause",
+                br#"{"namespace": "auth", "name": "authz", "paused": false}"#
+            ),
+            Ok(AdminAction::Pause {
+                namespace: "auth".to_string(),
+                name: "authz".to_string(),
+                paused: false
+            })
+        );
+        assert!(matches!(
+            parse_action(
+                "/api/v1/pause",
+                br#"{"namespace": "auth", "name": "authz"}"#
+            ),
+            Ok(AdminAction::Pause { paused: true, .. })
+        ));
+        assert_eq!(
+            parse_action(
+                "/api/v1/rotate-token",
+                br#"{"namespace": "auth", "name": "authz"}"#
+            ),
+            Ok(AdminAction::RotateToken {
+                namespace: "auth".to_string(),
+                name: "authz".to_string()
+            })
+        );
+
+        assert_eq!(
+            parse_action("/api/v1/rotate-token", b"").unwrap_err().0,
+            StatusCode::BAD_REQUEST
+        );
+        for path in [
+            "/api/v1/resync/auth",
+            "/api/v1/resync//authz",
+            "/api/v1/restart",
+        ] {
+            assert_eq!(
+                parse_action(path, b"").unwrap_err().0,
+                StatusCode::NOT_FOUND
+            );
+        }
+    }
+
+    #[test]
+    fn test_action_patch() {
+        let target = |paused| AdminAction::Pause {
+            namespace: "auth".to_string(),
+            name: "authz".to_string(),
+            paused,
+        };
+        assert_eq!(
+            action_patch(&target(true), "t"),
+            Some(json!({ "metadata": { "annotations": { "openfga.dev/paused": "true" } } }))
+        );
+        assert_eq!(
+            action_patch(&target(false), "t"),
+            Some(json!({ "metadata": { "annotations": { "openfga.dev/paused": null } } }))
+        );
+        let resync = AdminAction::Resync {
+            namespace: "auth".to_string(),
+            name: "authz".to_string(),
+        };
+        assert_eq!(
+            action_patch(&resync, "2026-10-15T00:00:00+00:00"),
+            Some(json!({ "metadata": { "annotations": {
+                "openfga.dev/resync-requested-at": "2026-10-15T00:00:00+00:00"
+            } } }))
+        );
+    }
+}
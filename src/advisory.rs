@@ -0,0 +1,195 @@
+//! Support status of the OpenFGA version an instance runs, from the advisory
+//! data in `advisories/openfga-versions.yaml`.
+
+use serde::Deserialize;
+use std::cmp::Ordering;
+use std::fmt;
+use std::sync::OnceLock;
+
+const ADVISORY_DATA: &str = include_str!("../advisories/openfga-versions.yaml");
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SupportStatus {
+    Supported,
+    Deprecated,
+    EndOfLife,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct SeriesAdvisory {
+    pub version: Version,
+    pub status: SupportStatus,
+    pub note: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Advisories {
+    pub latest: Version,
+    pub series: Vec<SeriesAdvisory>,
+}
+
+/// A `vMAJOR.MINOR[.PATCH]` version; pre-release suffixes are ignored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+impl Version {
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.strip_prefix('v').unwrap_or(s);
+        let core = s.split(['-', '+']).next()?;
+        let mut parts = core.split('.').map(|p| p.parse::<u64>().ok());
+        let major = parts.next()??;
+        let minor = parts.next()??;
+        let patch = parts.next().unwrap_or(Some(0))?;
+        if parts.next().is_some() {
+            return None;
+        }
+        Some(Self {
+            major,
+            minor,
+            patch,
+        })
+    }
+
+    fn series_cmp(&self, other: &Version) -> Ordering {
+        (self.major, self.minor).cmp(&(other.major, other.minor))
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "v{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+impl<'de> Deserialize<'de> for Version {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Version::parse(&s)
+            .ok_or_else(|| serde::de::Error::custom(format!("invalid version '{}'", s)))
+    }
+}
+
+/// What the advisory data says about one running version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionAdvice {
+    pub version: Version,
+    pub status: SupportStatus,
+    /// Newest release, when it is newer than the running version.
+    pub update: Option<Version>,
+    pub note: Option<String>,
+}
+
+/// The advisory data embedded at build time.
+pub fn advisories() -> &'static Advisories {
+    static ADVISORIES: OnceLock<Advisories> = OnceLock::new();
+    ADVISORIES.get_or_init(|| {
+        serde_yaml::from_str(ADVISORY_DATA).expect("embedded OpenFGA advisory data is valid")
+    })
+}
+
+/// The tag of an image reference, or None for digests and untagged images.
+pub fn image_tag(image: &str) -> Option<&str> {
+    if image.contains('@') {
+        return None;
+    }
+    let name = &image[image.rfind('/').map_or(0, |i| i + 1)..];
+    name.split_once(':').map(|(_, tag)| tag)
+}
+
+impl Advisories {
+    /// Advice for an image, or None when its tag is not a version (e.g. `latest`).
+    pub fn advise(&self, image: &str) -> Option<VersionAdvice> {
+        let version = Version::parse(image_tag(image)?)?;
+
+        let entry = self
+            .series
+            .iter()
+            .find(|s| s.version.series_cmp(&version) == Ordering::Equal);
+        let oldest = self.series.iter().map(|s| s.version).min();
+        let (status, note) = match entry {
+            Some(entry) => (entry.status, entry.note.clone()),
+            None if oldest.is_some_and(|oldest| version.series_cmp(&oldest) == Ordering::Less) => {
+                (SupportStatus::EndOfLife, None)
+            }
+            None => (SupportStatus::Supported, None),
+        };
+
+        Some(VersionAdvice {
+            version,
+            status,
+            update: (self.latest > version).then_some(self.latest),
+            note,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data() -> Advisories {
+        serde_yaml::from_str(
+            "latest: v1.8.4\nseries:\n  - version: v1.8\n    status: Supported\n\
+             \x20 - version: v1.5\n    status: Deprecated\n    note: upgrade\n\
+             \x20 - version: v1.3\n    status: EndOfLife\n",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_embedded_data_parses() {
+        assert!(!advisories().series.is_empty());
+    }
+
+    #[test]
+    fn test_version_parsing() {
+        assert_eq!(
+            Version::parse("v1.5.3"),
+            Some(Version {
+                major: 1,
+                minor: 5,
+                patch: 3
+            })
+        );
+        assert_eq!(Version::parse("1.5").unwrap().to_string(), "v1.5.0");
+        assert_eq!(Version::parse("v1.6.0-rc1").unwrap().to_string(), "v1.6.0");
+        assert!(Version::parse("latest").is_none());
+        assert!(Version::parse("v1.2.3.4").is_none());
+
+        assert_eq!(image_tag("openfga/openfga:v1.4.0"), Some("v1.4.0"));
+        assert_eq!(image_tag("registry:5000/openfga"), None);
+        assert_eq!(image_tag("openfga/openfga@sha256:abc"), None);
+    }
+
+    #[test]
+    fn test_advise() {
+        let data = data();
+        assert!(data.advise("openfga/openfga:latest").is_none());
+
+        let current = data.advise("openfga/openfga:v1.8.4").unwrap();
+        assert_eq!(current.status, SupportStatus::Supported);
+        assert_eq!(current.update, None);
+
+        let patch_behind = data.advise("openfga/openfga:v1.8.1").unwrap();
+        assert_eq!(patch_behind.update.unwrap().to_string(), "v1.8.4");
+
+        let deprecated = data.advise("openfga/openfga:v1.5.0").unwrap();
+        assert_eq!(deprecated.status, SupportStatus::Deprecated);
+        assert_eq!(deprecated.note.as_deref(), Some("upgrade"));
+
+        // Unlisted series: older than the oldest entry is EOL, in between is supported
+        assert_eq!(
+            data.advise("openfga/openfga:v1.1.0").unwrap().status,
+            SupportStatus::EndOfLife
+        );
+        assert_eq!(
+            data.advise("openfga/openfga:v1.6.0").unwrap().status,
+            SupportStatus::Supported
+        );
+        assert_eq!(data.advise("openfga/openfga:v1.9.0").unwrap().update, None);
+    }
+}
@@ -0,0 +1,231 @@
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::env;
+use std::time::Duration;
+use tracing::debug;
+
+const REDACTED: &str = "[REDACTED]";
+
+/// Header names whose values are never logged.
+const SENSITIVE_HEADERS: &[&str] = &[
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "set-cookie",
+    "x-api-key",
+];
+
+/// JSON keys whose values are never logged, matched case-insensitively.
+const SENSITIVE_FIELDS: &[&str] = &[
+    "authorization",
+    "token",
+    "api_token",
+    "apitoken",
+    "access_token",
+    "client_secret",
+    "clientsecret",
+    "password",
+    "preshared_key",
+    "presharedkey",
+    "secret",
+];
+
+/// JSON keys holding subject identifiers in OpenFGA requests and responses.
+const SUBJECT_FIELDS: &[&str] = &["user", "users", "subject"];
+
+/// Opt-in debug logging of outbound OpenFGA API traffic.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ApiLogConfig {
+    pub enabled: bool,
+    /// Replace subject identifiers with a stable hash so log lines can still be correlated.
+    pub hash_subjects: bool,
+}
+
+impl ApiLogConfig {
+    /// Reads `OPENFGA_API_DEBUG_LOG` and `OPENFGA_API_LOG_HASH_SUBJECTS`.
+    pub fn from_env() -> Self {
+        let flag = |name: &str| {
+            env::var(name)
+                .map(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes"))
+                .unwrap_or(false)
+        };
+
+        Self {
+            enabled: flag("OPENFGA_API_DEBUG_LOG"),
+            hash_subjects: flag("OPENFGA_API_LOG_HASH_SUBJECTS"),
+        }
+    }
+
+    pub fn log_request(&self, method: &str, url: &str, headers: &[(String, String)], body: &Value) {
+        if !self.enabled {
+            return;
+        }
+
+        debug!(
+            event = "openfga_api_request",
+            method = %method,
+            url = %url,
+            headers = ?redact_headers(headers),
+            body = %self.redact_body(body),
+            "Outbound OpenFGA API request"
+        );
+    }
+
+    pub fn log_response(
+        &self,
+        method: &str,
+        url: &str,
+        status: u16,
+        latency: Duration,
+        body: &Value,
+    ) {
+        if !self.enabled {
+            return;
+        }
+
+        debug!(
+            event = "openfga_api_response",
+            method = %method,
+            url = %url,
+            status = status,
+            latency_ms = latency.as_millis() as u64,
+            body = %self.redact_body(body),
+            "OpenFGA API response"
+        );
+    }
+
+    /// Returns a copy of `body` with credentials removed and, if configured, subjects hashed.
+    pub fn redact_body(&self, body: &Value) -> Value {
+        match body {
+            Value::Object(map) => Value::Object(
+                map.iter()
+                    .map(|(key, value)| {
+                        let lower = key.to_lowercase();
+                        let redacted = if SENSITIVE_FIELDS.contains(&lower.as_str()) {
+                            Value::String(REDACTED.to_string())
+                        } else if self.hash_subjects && SUBJECT_FIELDS.contains(&lower.as_str()) {
+                            hash_subjects(value)
+                        } else {
+                            self.redact_body(value)
+                        };
+                        (key.clone(), redacted)
+                    })
+                    .collect(),
+            ),
+            Value::Array(items) => {
+                Value::Array(items.iter().map(|v| self.redact_body(v)).collect())
+            }
+            other => other.clone(),
+        }
+    }
+}
+
+pub fn redact_headers(headers: &[(String, String)]) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            if SENSITIVE_HEADERS.contains(&name.to_lowercase().as_str()) {
+                (name.clone(), REDACTED.to_string())
+            } else {
+                (name.clone(), value.clone())
+            }
+        })
+        .collect()
+}
+
+/// Hashes the identifier part of `type:id` subjects, keeping the type and any
+/// `#relation` suffix readable (e.g. `group:eng#member`).
+pub fn hash_subject(subject: &str) -> String {
+    let (object, relation) = match subject.split_once('#') {
+        Some((object, relation)) => (object, Some(relation)),
+        None => (subject, None),
+    };
+    let (kind, id) = object.split_once(':').unwrap_or(("", object));
+    if id == "*" {
+        return subject.to_string();
+    }
+
+    let digest = Sha256::digest(id.as_bytes());
+    let short: String = digest
+        .iter()
+        .take(6)
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    let hashed = if kind.is_empty() {
+        format!("sha256:{}", short)
+    } else {
+        format!("{}:sha256:{}", kind, short)
+    };
+
+    match relation {
+        Some(relation) => format!("{}#{}", hashed, relation),
+        None => hashed,
+    }
+}
+
+fn hash_subjects(value: &Value) -> Value {
+    match value {
+        Value::String(subject) => Value::String(hash_subject(subject)),
+        Value::Array(items) => Value::Array(items.iter().map(hash_subjects).collect()),
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(k, v)| (k.clone(), hash_subjects(v)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_redacts_credentials() {
+        let config = ApiLogConfig {
+            enabled: true,
+            hash_subjects: false,
+        };
+        let body = json!({
+            "credentials": {"method": "api_token", "config": {"api_token": "s3cr3t"}},
+            "tuple_key": {"user": "user:anne", "relation": "viewer", "object": "doc:1"}
+        });
+
+        let redacted = config.redact_body(&body);
+        assert_eq!(redacted["credentials"]["config"]["api_token"], REDACTED);
+        assert_eq!(redacted["tuple_key"]["user"], "user:anne");
+
+        let headers = redact_headers(&[
+            ("Authorization".to_string(), "Bearer abc".to_string()),
+            ("Content-Type".to_string(), "application/json".to_string()),
+        ]);
+        assert_eq!(headers[0].1, REDACTED);
+        assert_eq!(headers[1].1, "application/json");
+    }
+
+    #[test]
+    fn test_hashes_subjects_when_configured() {
+        let config = ApiLogConfig {
+            enabled: true,
+            hash_subjects: true,
+        };
+        let body = json!({"writes": {"tuple_keys": [{"user": "group:eng#member", "relation": "viewer", "object": "doc:1"}]}});
+
+        let redacted = config.redact_body(&body);
+        let user = redacted["writes"]["tuple_keys"][0]["user"]
+            .as_str()
+            .unwrap();
+        assert!(user.starts_with("group:sha256:"));
+        assert!(user.ends_with("#member"));
+        assert!(!user.contains("eng#"));
+        assert_eq!(redacted["writes"]["tuple_keys"][0]["object"], "doc:1");
+    }
+
+    #[test]
+    fn test_hash_subject_is_stable_and_keeps_wildcards() {
+        assert_eq!(hash_subject("user:anne"), hash_subject("user:anne"));
+        assert_ne!(hash_subject("user:anne"), hash_subject("user:bob"));
+        assert_eq!(hash_subject("user:*"), "user:*");
+    }
+}
@@ -0,0 +1,144 @@
+//! Server-side apply for the children the operator manages. Every object is
+//! applied with one field manager and `force`, so fields another manager took
+//! over with a plain update (e.g. `kubectl edit`) are reclaimed instead of
+//! failing with a conflict. Fields that are meant to be owned elsewhere, such
+//! as the replica count of an autoscaled Deployment, are left out of the apply.
+
+use k8s_openapi::api::apps::v1::Deployment;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ManagedFieldsEntry;
+use kube::api::PatchParams;
+
+/// Field manager of every object the operator applies.
+pub const FIELD_MANAGER: &str = "openfga-operator";
+
+/// Subresource autoscalers (HPA, KEDA, `kubectl scale`) write replica counts through.
+const SCALE_SUBRESOURCE: &str = "scale";
+
+pub fn apply_params() -> PatchParams {
+    PatchParams::apply(FIELD_MANAGER).force()
+}
+
+fn owns_field(entry: &ManagedFieldsEntry, path: &[&str]) -> bool {
+    let Some(fields) = &entry.fields_v1 else {
+        return false;
+    };
+    path.iter()
+        .try_fold(&fields.0, |node, field| node.get(format!("f:{}", field)))
+        .is_some()
+}
+
+/// Managers other than the operator that own `spec.replicas` through the scale
+/// subresource.
+pub fn scale_managers(live: &Deployment) -> Vec<String> {
+    live.metadata
+        .managed_fields
+        .iter()
+        .flatten()
+        .filter(|entry| entry.manager.as_deref() != Some(FIELD_MANAGER))
+        .filter(|entry| entry.subresource.as_deref() == Some(SCALE_SUBRESOURCE))
+        .filter(|entry| owns_field(entry, &["spec", "replicas"]))
+        .filter_map(|entry| entry.manager.clone())
+        .collect()
+}
+
+/// Drops `spec.replicas` from the desired Deployment when an autoscaler owns it on
+/// the live one, so forcing the apply does not undo its scaling decisions. Returns
+/// the managers the field was left to.
+pub fn cede_replicas(desired: &mut Deployment, live: Option<&Deployment>) -> Vec<String> {
+    let managers = live.map(scale_managers).unwrap_or_default();
+    if !managers.is_empty() {
+        if let Some(spec) = desired.spec.as_mut() {
+            spec.replicas = None;
+        }
+    }
+    managers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    fn entry(manager: &str, operation: &str, subresource: Option<&str>, fields: Value) -> Value {
+        json!({
+            "manager": manager,
+            "operation": operation,
+            "subresource": subresource,
+            "fieldsType": "FieldsV1",
+            "fieldsV1": fields,
+        })
+    }
+
+    fn deployment(managed_fields: Vec<Value>) -> Deployment {
+        serde_json::from_value(json!({
+            "metadata": { "name": "openfga", "managedFields": managed_fields },
+            "spec": { "replicas": 3, "selector": {}, "template": {} },
+        }))
+        .unwrap()
+    }
+
+    fn replicas() -> Value {
+        json!({ "f:spec": { "f:replicas": {} } })
+    }
+
+    #[test]
+    fn test_apply_params_force_with_field_manager() {
+        let params = apply_params();
+        assert!(params.force);
+        assert_eq!(params.field_manager.as_deref(), Some(FIELD_MANAGER));
+    }
+
+    #[test]
+    fn test_replicas_ceded_to_autoscaler() {
+        let live = deployment(vec![
+            entry(
+                FIELD_MANAGER,
+                "Apply",
+                None,
+                json!({ "f:spec": { "f:template": {} } }),
+            ),
+            entry(
+                "kube-controller-manager",
+                "Update",
+                Some("scale"),
+                replicas(),
+            ),
+        ]);
+        let mut desired = deployment(vec![]);
+
+        assert_eq!(
+            cede_replicas(&mut desired, Some(&live)),
+            vec!["kube-controller-manager".to_string()]
+        );
+        assert_eq!(desired.spec.unwrap().replicas, None);
+    }
+
+    #[test]
+    fn test_replicas_reclaimed_from_plain_update() {
+        // kubectl edit writes the main resource; that is drift, not autoscaling
+        let live = deployment(vec![
+            entry(FIELD_MANAGER, "Apply", None, replicas()),
+            entry("kubectl-edit", "Update", None, replicas()),
+        ]);
+        let mut desired = deployment(vec![]);
+
+        assert!(cede_replicas(&mut desired, Some(&live)).is_empty());
+        assert_eq!(desired.spec.unwrap().replicas, Some(3));
+    }
+
+    #[test]
+    fn test_replicas_kept_without_live_object_or_foreign_owner() {
+        let mut desired = deployment(vec![]);
+        assert!(cede_replicas(&mut desired, None).is_empty());
+
+        // Scale writes that touched other fields only do not count
+        let live = deployment(vec![entry(
+            "keda-operator",
+            "Update",
+            Some("scale"),
+            json!({ "f:status": { "f:replicas": {} } }),
+        )]);
+        assert!(cede_replicas(&mut desired, Some(&live)).is_empty());
+        assert_eq!(desired.spec.unwrap().replicas, Some(3));
+    }
+}
@@ -0,0 +1,156 @@
+//! Per-object exponential backoff for failed reconciles. Each error class has a
+//! base requeue interval; consecutive failures of the same object double it up
+//! to a cap, with jitter so objects failing together do not retry in lockstep.
+//! A successful reconcile resets the object's count.
+
+use crate::controller::ControllerError;
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Upper bound on the requeue interval of a persistently failing object.
+pub const MAX_BACKOFF: Duration = Duration::from_secs(600);
+
+/// Error class and base requeue interval for a reconcile error.
+pub fn classify(error: &ControllerError) -> (&'static str, Duration) {
+    match error {
+        ControllerError::Kube(kube::Error::Api(response)) => match response.code {
+            404 => ("NotFound", Duration::from_secs(5)),
+            409 => ("Conflict", Duration::from_secs(1)),
+            401 | 403 => ("Permission", Duration::from_secs(300)),
+            422 => ("Invalid", Duration::from_secs(120)),
+            429 => ("RateLimit", Duration::from_secs(60)),
+            500..=599 => ("ServerError", Duration::from_secs(30)),
+            _ => ("Unknown", Duration::from_secs(30)),
+        },
+        ControllerError::Kube(
+            kube::Error::HyperError(_) | kube::Error::Service(_) | kube::Error::ReadEvents(_),
+        ) => ("Network", Duration::from_secs(30)),
+        ControllerError::Kube(kube::Error::SerdeError(_)) | ControllerError::Serialization(_) => {
+            ("Serialization", Duration::from_secs(120))
+        }
+        ControllerError::Kube(_) => ("Unknown", Duration::from_secs(30)),
+        ControllerError::Panic(_) => ("Panic", Duration::from_secs(300)),
+        ControllerError::ImageVerification(_) => ("ImageVerification", Duration::from_secs(120)),
+        ControllerError::InvalidOverrides(_) => ("InvalidOverrides", Duration::from_secs(120)),
+        ControllerError::TargetCluster(_) => ("TargetCluster", Duration::from_secs(60)),
+    }
+}
+
+/// `base` doubled for each failure after the first, capped at `max`.
+pub fn exponential(base: Duration, attempt: u32, max: Duration) -> Duration {
+    let factor = 1u32 << attempt.saturating_sub(1).min(16);
+    base.saturating_mul(factor).min(max)
+}
+
+/// A delay in `[delay / 2, delay]`, picked by `seed`.
+pub fn jitter(delay: Duration, seed: u64) -> Duration {
+    let half = delay / 2;
+    let spread = (delay - half).as_millis() as u64;
+    if spread == 0 {
+        return delay;
+    }
+    half + Duration::from_millis(seed % (spread + 1))
+}
+
+/// Consecutive failure counts by object key.
+#[derive(Debug, Default)]
+pub struct Backoff {
+    attempts: Mutex<HashMap<String, u32>>,
+}
+
+impl Backoff {
+    /// Records a failure of `key` and returns the attempt number and the delay
+    /// before the next reconcile.
+    pub fn next(&self, key: &str, base: Duration) -> (u32, Duration) {
+        let attempt = {
+            let mut attempts = self.attempts.lock().unwrap_or_else(|e| e.into_inner());
+            let attempt = attempts.entry(key.to_string()).or_insert(0);
+            *attempt = attempt.saturating_add(1);
+            *attempt
+        };
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write(key.as_bytes());
+        hasher.write_u32(attempt);
+        let delay = jitter(exponential(base, attempt, MAX_BACKOFF), hasher.finish());
+        (attempt, delay)
+    }
+
+    /// Whether the last reconcile of `key` failed.
+    pub fn failing(&self, key: &str) -> bool {
+        self.attempts
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .contains_key(key)
+    }
+
+    /// Forgets the failures of `key` after a successful reconcile.
+    pub fn reset(&self, key: &str) {
+        self.attempts
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kube::core::ErrorResponse;
+
+    fn api_error(code: u16) -> ControllerError {
+        ControllerError::Kube(kube::Error::Api(ErrorResponse {
+            status: "Failure".to_string(),
+            message: "it contains conflict but is a not found".to_string(),
+            reason: "NotFound".to_string(),
+            code,
+        }))
+    }
+
+    #[test]
+    fn test_classify_uses_status_code() {
+        assert_eq!(classify(&api_error(404)).0, "NotFound");
+        assert_eq!(classify(&api_error(409)).0, "Conflict");
+        assert_eq!(classify(&api_error(403)).0, "Permission");
+        assert_eq!(classify(&api_error(429)).0, "RateLimit");
+        assert_eq!(classify(&api_error(503)).0, "ServerError");
+        assert_eq!(
+            classify(&ControllerError::Panic("boom".to_string())).0,
+            "Panic"
+        );
+    }
+
+    #[test]
+    fn test_exponential_doubles_up_to_cap() {
+        let base = Duration::from_secs(5);
+        assert_eq!(exponential(base, 1, MAX_BACKOFF), base);
+        assert_eq!(exponential(base, 2, MAX_BACKOFF), Duration::from_secs(10));
+        assert_eq!(exponential(base, 4, MAX_BACKOFF), Duration::from_secs(40));
+        assert_eq!(exponential(base, 30, MAX_BACKOFF), MAX_BACKOFF);
+    }
+
+    #[test]
+    fn test_jitter_stays_within_bounds() {
+        let delay = Duration::from_secs(10);
+        for seed in [0, 1, 4_999, 5_000, 5_001, u64::MAX] {
+            let jittered = jitter(delay, seed);
+            assert!(jittered >= delay / 2 && jittered <= delay);
+        }
+    }
+
+    #[test]
+    fn test_backoff_grows_per_key_and_resets() {
+        let backoff = Backoff::default();
+        let base = Duration::from_secs(4);
+        assert_eq!(backoff.next("ns/a", base).0, 1);
+        let (attempt, delay) = backoff.next("ns/a", base);
+        assert_eq!(attempt, 2);
+        assert!(delay >= Duration::from_secs(4) && delay <= Duration::from_secs(8));
+        assert_eq!(backoff.next("ns/b", base).0, 1);
+
+        backoff.reset("ns/a");
+        assert_eq!(backoff.next("ns/a", base).0, 1);
+    }
+}
@@ -0,0 +1,796 @@
+//! Backup artifacts of OpenFGA stores and the subcommands the OpenFGABackup
+//! and OpenFGARestore jobs run to produce and replay them, e.g.
+//! `openfga-operator backup export --url http://authz.auth:8080 --output /backup/nightly-backup-29000000.json`.
+//!
+//! An artifact is one JSON document holding every authorization model version
+//! and every tuple of the exported stores, read through the HTTP API, or the
+//! gRPC API for tuples when `--grpc-url` is given.
+
+use crate::access_tokens;
+use crate::bulk_writer::{BulkWriter, BulkWriterConfig, TupleBatchLedger, MAX_TUPLES_PER_WRITE};
+use crate::openfga_client::{ClientResult, OpenFGAClient, StoreWriter};
+use crate::tuple_scan::{StoreReader, TupleReader};
+use crate::tuples::{TupleKey, TupleOperation};
+use crate::types::{OpenFGARestore, RestoreProgress};
+use kube::api::{Api, Patch, PatchParams};
+use kube::{Client, ResourceExt};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeSet;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+
+const USAGE: &str = "\
+Usage:
+  openfga-operator backup export --url <url> --output <file> [options]
+  openfga-operator backup restore --url <url> --input <file> [options]
+
+Common options:
+  --grpc-url <url>      Read and write tuples through the gRPC API at <url>
+
+Export options:
+  --store <name>        Export only this store; repeat for several (default: every store)
+  --keep <n>            Afterwards delete all but the newest <n> artifacts of this backup
+                        next to <file>
+
+Restore options:
+  --store <name>        Restore only this store; repeat for several (default: every store)
+  --dry-run             Report what would be written without writing
+  --batch-size <n>      Tuples per Write request, 1 to 100 (default: 100)
+  --report <ns>/<name>  Publish progress in the status of this OpenFGARestore";
+
+/// Format version written into every artifact.
+pub const ARTIFACT_VERSION: u32 = 1;
+
+const PAGE_SIZE: usize = 100;
+
+/// Tuples written between two progress reports.
+const PROGRESS_INTERVAL: usize = 1000;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupArtifact {
+    pub version: u32,
+    pub created_at: String,
+    pub stores: Vec<StoreBackup>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct StoreBackup {
+    pub id: String,
+    pub name: String,
+    /// Every model version, newest first, as returned by the API.
+    pub authorization_models: Vec<Value>,
+    pub tuples: Vec<TupleKey>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Export(ExportCommand),
+    Restore(RestoreCommand),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportCommand {
+    pub url: String,
+    pub grpc_url: Option<String>,
+    pub output: PathBuf,
+    pub stores: Vec<String>,
+    pub keep: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RestoreCommand {
+    pub url: String,
+    pub grpc_url: Option<String>,
+    pub input: PathBuf,
+    pub options: RestoreOptions,
+    /// Namespace and name of the OpenFGARestore to report progress to.
+    pub report: Option<(String, String)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RestoreOptions {
+    /// Stores to restore; empty restores every store in the artifact.
+    pub stores: Vec<String>,
+    pub dry_run: bool,
+    pub batch_size: usize,
+}
+
+impl Default for RestoreOptions {
+    fn default() -> Self {
+        Self {
+            stores: vec![],
+            dry_run: false,
+            batch_size: MAX_TUPLES_PER_WRITE,
+        }
+    }
+}
+
+/// Returns true when the arguments select a backup subcommand.
+pub fn is_backup_invocation(args: &[String]) -> bool {
+    args.get(1).is_some_and(|arg| arg == "backup")
+}
+
+/// Runs a backup subcommand, returning the process exit code.
+pub async fn run(args: &[String]) -> i32 {
+    let command = match parse(&args[1..]) {
+        Ok(command) => command,
+        Err(message) => {
+            eprintln!("{}", message);
+            return 2;
+        }
+    };
+    let result = match &command {
+        Command::Export(command) => export_to_file(command).await,
+        Command::Restore(command) => restore_from_file(command).await,
+    };
+    match result {
+        Ok(summary) => {
+            println!("{}", summary);
+            0
+        }
+        Err(message) => {
+            eprintln!("backup failed: {}", message);
+            1
+        }
+    }
+}
+
+fn positive(flag: &str, value: &str, max: usize) -> Result<usize, String> {
+    value
+        .parse::<usize>()
+        .ok()
+        .filter(|n| (1..=max).contains(n))
+        .ok_or_else(|| {
+            format!(
+                "{} must be a number from 1 to {}, got '{}'",
+                flag, max, value
+            )
+        })
+}
+
+fn parse(args: &[String]) -> Result<Command, String> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let ["backup", subcommand @ ("export" | "restore"), options @ ..] = args.as_slice() else {
+        return Err(USAGE.to_string());
+    };
+    let (mut url, mut file, mut stores, mut keep, mut report) = (None, None, vec![], None, None);
+    let mut grpc_url = None;
+    let mut restore = RestoreOptions::default();
+    let mut rest = options.iter();
+    while let Some(flag) = rest.next() {
+        if *subcommand == "restore" && *flag == "--dry-run" {
+            restore.dry_run = true;
+            continue;
+        }
+        let value = rest.next().ok_or_else(|| USAGE.to_string())?;
+        match (*subcommand, *flag) {
+            (_, "--url") => url = Some(value.to_string()),
+            (_, "--grpc-url") => grpc_url = Some(value.to_string()),
+            (_, "--store") => stores.push(value.to_string()),
+            ("export", "--output") | ("restore", "--input") => file = Some(PathBuf::from(value)),
+            ("export", "--keep") => keep = Some(positive(flag, value, usize::MAX)?),
+            ("restore", "--batch-size") => {
+                restore.batch_size = positive(flag, value, MAX_TUPLES_PER_WRITE)?
+            }
+            ("restore", "--report") => {
+                let (ns, name) = value
+                    .split_once('/')
+                    .filter(|(ns, name)| !ns.is_empty() && !name.is_empty())
+                    .ok_or_else(|| {
+                        format!("--report must be <namespace>/<name>, got '{}'", value)
+                    })?;
+                report = Some((ns.to_string(), name.to_string()));
+            }
+            _ => return Err(USAGE.to_string()),
+        }
+    }
+    let (Some(url), Some(file)) = (url, file) else {
+        return Err(USAGE.to_string());
+    };
+    Ok(match *subcommand {
+        "export" => Command::Export(ExportCommand {
+            url,
+            grpc_url,
+            output: file,
+            stores,
+            keep,
+        }),
+        _ => Command::Restore(RestoreCommand {
+            url,
+            grpc_url,
+            input: file,
+            options: RestoreOptions { stores, ..restore },
+            report,
+        }),
+    })
+}
+
+/// Every tuple the reader returns, following continuation tokens to the end.
+pub async fn read_all_tuples(reader: &impl TupleReader) -> ClientResult<Vec<TupleKey>> {
+    let mut tuples = Vec::new();
+    let mut token: Option<String> = None;
+    loop {
+        let page = reader.read_page(PAGE_SIZE, token.as_deref()).await?;
+        tuples.extend(page.tuples);
+        match page.continuation_token {
+            Some(next) => token = Some(next),
+            None => return Ok(tuples),
+        }
+    }
+}
+
+/// Exports the stores named in `stores`, or every store when it is empty.
+pub async fn export(client: &OpenFGAClient, stores: &[String]) -> ClientResult<BackupArtifact> {
+    let mut exported = Vec::new();
+    for store in client.list_stores().await? {
+        if !stores.is_empty() && !stores.contains(&store.name) {
+            continue;
+        }
+        let reader = StoreReader {
+            client,
+            store_id: &store.id,
+        };
+        exported.push(StoreBackup {
+            authorization_models: client.read_authorization_models(&store.id).await?,
+            tuples: read_all_tuples(&reader).await?,
+            id: store.id,
+            name: store.name,
+        });
+    }
+    Ok(BackupArtifact {
+        version: ARTIFACT_VERSION,
+        created_at: chrono::Utc::now().to_rfc3339(),
+        stores: exported,
+    })
+}
+
+/// Names in `wanted` that no store of the artifact has.
+fn missing_stores<'a>(artifact: &BackupArtifact, wanted: &'a [String]) -> Vec<&'a str> {
+    wanted
+        .iter()
+        .filter(|name| !artifact.stores.iter().any(|s| &s.name == *name))
+        .map(String::as_str)
+        .collect()
+}
+
+/// The preshared key Jobs pass in `OPENFGA_API_TOKEN`, if the instance has keys.
+fn api_token() -> Option<String> {
+    std::env::var(access_tokens::API_TOKEN_ENV)
+        .ok()
+        .filter(|token| !token.is_empty())
+}
+
+async fn export_to_file(command: &ExportCommand) -> Result<String, String> {
+    let client = OpenFGAClient::new(&command.url)
+        .with_token(api_token())
+        .with_grpc(command.grpc_url.clone());
+    let artifact = export(&client, &command.stores)
+        .await
+        .map_err(|e| e.to_string())?;
+    let missing = missing_stores(&artifact, &command.stores);
+    if !missing.is_empty() {
+        return Err(format!("stores not found: {}", missing.join(", ")));
+    }
+
+    // Written under a temporary name so an interrupted run never leaves a
+    // truncated artifact that looks complete
+    let bytes = serde_json::to_vec(&artifact).map_err(|e| e.to_string())?;
+    let partial = command.output.with_extension("json.partial");
+    std::fs::write(&partial, bytes).map_err(|e| format!("{}: {}", partial.display(), e))?;
+    std::fs::rename(&partial, &command.output)
+        .map_err(|e| format!("{}: {}", command.output.display(), e))?;
+
+    let mut summary = format!(
+        "wrote {} with {} stores, {} tuples",
+        command.output.display(),
+        artifact.stores.len(),
+        artifact
+            .stores
+            .iter()
+            .map(|s| s.tuples.len())
+            .sum::<usize>()
+    );
+    if let Some(keep) = command.keep {
+        let removed = prune(&command.output, keep).map_err(|e| e.to_string())?;
+        summary.push_str(&format!(", pruned {} old artifacts", removed.len()));
+    }
+    Ok(summary)
+}
+
+/// Where a restore reports how far it got, and which tuple batches it has
+/// applied.
+pub trait ProgressSink {
+    fn report(&self, progress: &RestoreProgress) -> impl Future<Output = ()>;
+    fn record_batches(&self, applied: Vec<String>) -> impl Future<Output = ()>;
+}
+
+/// Prints progress and, when given an OpenFGARestore, publishes it in its status.
+struct ProgressReporter {
+    restore: Option<(Api<OpenFGARestore>, String)>,
+}
+
+impl ProgressSink for ProgressReporter {
+    async fn report(&self, progress: &RestoreProgress) {
+        println!(
+            "stores {}/{}, models written {}, tuples written {} skipped {} failed {} of {}",
+            progress.stores_done,
+            progress.stores_total,
+            progress.models_written,
+            progress.tuples_written,
+            progress.tuples_skipped,
+            progress.tuples_failed,
+            progress.tuples_total
+        );
+        let Some((restores, name)) = &self.restore else {
+            return;
+        };
+        let patch = serde_json::json!({ "status": { "progress": progress } });
+        if let Err(e) = restores
+            .patch_status(name, &PatchParams::default(), &Patch::Merge(&patch))
+            .await
+        {
+            eprintln!("failed to report progress: {}", e);
+        }
+    }
+
+    async fn record_batches(&self, applied: Vec<String>) {
+        let Some((restores, name)) = &self.restore else {
+            return;
+        };
+        let patch = serde_json::json!({ "status": { "appliedTupleBatches": applied } });
+        if let Err(e) = restores
+            .patch_status(name, &PatchParams::default(), &Patch::Merge(&patch))
+            .await
+        {
+            eprintln!("failed to record applied batches: {}", e);
+        }
+    }
+}
+
+/// The fields of an artifact model the Write API accepts; the id is assigned anew.
+fn model_body(model: &Value) -> Value {
+    let mut body = Map::new();
+    for key in ["schema_version", "type_definitions", "conditions"] {
+        match model.get(key) {
+            Some(Value::Null) | None => {}
+            Some(Value::Object(map)) if map.is_empty() => {}
+            Some(value) => {
+                body.insert(key.to_string(), value.clone());
+            }
+        }
+    }
+    Value::Object(body)
+}
+
+/// Model versions of `artifact` (newest first) the target lacks, oldest first
+/// so the artifact's newest model ends up the target's latest.
+pub fn missing_models(artifact: &[Value], existing: &[Value]) -> Vec<Value> {
+    let existing: Vec<Value> = existing.iter().map(model_body).collect();
+    artifact
+        .iter()
+        .rev()
+        .map(model_body)
+        .filter(|model| !existing.contains(model))
+        .collect()
+}
+
+/// Tuples of `artifact` the target lacks, each once.
+pub fn missing_tuples(artifact: &[TupleKey], existing: &[TupleKey]) -> Vec<TupleKey> {
+    let existing: BTreeSet<&TupleKey> = existing.iter().collect();
+    let mut seen = BTreeSet::new();
+    artifact
+        .iter()
+        .filter(|tuple| !existing.contains(tuple) && seen.insert(*tuple))
+        .cloned()
+        .collect()
+}
+
+/// Replays the stores of `artifact` into the instance behind `client`. Stores
+/// are matched by name and created when missing; only models and tuples the
+/// target lacks are written, so repeating a restore writes nothing new. Tuple
+/// batches are also recorded in `ledger` under `run` and the store id, and a
+/// retry of the same run skips the recorded ones, even where their tuples
+/// have been deleted since.
+pub async fn restore(
+    client: &OpenFGAClient,
+    artifact: &BackupArtifact,
+    options: &RestoreOptions,
+    run: &str,
+    ledger: &mut TupleBatchLedger,
+    sink: &impl ProgressSink,
+) -> ClientResult<RestoreProgress> {
+    let selected: Vec<&StoreBackup> = artifact
+        .stores
+        .iter()
+        .filter(|s| options.stores.is_empty() || options.stores.contains(&s.name))
+        .collect();
+    let mut progress = RestoreProgress {
+        stores_total: selected.len() as i64,
+        tuples_total: selected.iter().map(|s| s.tuples.len() as i64).sum(),
+        ..Default::default()
+    };
+    let targets = client.list_stores().await?;
+
+    for store in selected {
+        progress.current_store = Some(store.name.clone());
+        sink.report(&progress).await;
+
+        let target = targets.iter().find(|t| t.name == store.name);
+        let (existing_models, existing_tuples) = match target {
+            Some(target) => {
+                let reader = StoreReader {
+                    client,
+                    store_id: &target.id,
+                };
+                (
+                    client.read_authorization_models(&target.id).await?,
+                    read_all_tuples(&reader).await?,
+                )
+            }
+            None => (vec![], vec![]),
+        };
+        let models = missing_models(&store.authorization_models, &existing_models);
+        let tuples = missing_tuples(&store.tuples, &existing_tuples);
+
+        if options.dry_run {
+            progress.models_written += models.len() as i64;
+            progress.tuples_written += tuples.len() as i64;
+            progress.tuples_skipped += (store.tuples.len() - tuples.len()) as i64;
+            progress.stores_done += 1;
+            continue;
+        }
+
+        let store_id = match target {
+            Some(target) => target.id.clone(),
+            None => client.create_store(&store.name).await?,
+        };
+        for model in &models {
+            client.write_authorization_model(&store_id, model).await?;
+            progress.models_written += 1;
+        }
+        let writer = BulkWriter::new(
+            StoreWriter {
+                client,
+                store_id: &store_id,
+            },
+            BulkWriterConfig {
+                max_batch_size: options.batch_size,
+                ..Default::default()
+            },
+            "restore",
+        );
+        // Batches are cut from the whole artifact, not just the missing
+        // tuples, so they are the same on every attempt
+        let pending: BTreeSet<TupleKey> = tuples.into_iter().collect();
+        let operations = missing_tuples(&store.tuples, &[])
+            .into_iter()
+            .map(TupleOperation::Write)
+            .collect();
+        let before = progress.clone();
+        let report = writer
+            .apply_once(
+                &format!("{}/{}", run, store_id),
+                operations,
+                ledger,
+                |op| pending.contains(op.tuple()),
+                PROGRESS_INTERVAL,
+                |report, ledger| {
+                    let current = RestoreProgress {
+                        tuples_written: before.tuples_written + report.written as i64,
+                        tuples_skipped: before.tuples_skipped + report.skipped as i64,
+                        tuples_failed: before.tuples_failed + report.failed.len() as i64,
+                        ..before.clone()
+                    };
+                    let applied = ledger.to_status();
+                    async move {
+                        sink.report(&current).await;
+                        sink.record_batches(applied).await;
+                    }
+                },
+            )
+            .await;
+        progress.tuples_written += report.written as i64;
+        progress.tuples_skipped +=
+            (store.tuples.len() - report.written - report.failed.len()) as i64;
+        progress.tuples_failed += report.failed.len() as i64;
+        progress.stores_done += 1;
+    }
+
+    progress.current_store = None;
+    sink.report(&progress).await;
+    Ok(progress)
+}
+
+async fn restore_from_file(command: &RestoreCommand) -> Result<String, String> {
+    let raw =
+        std::fs::read(&command.input).map_err(|e| format!("{}: {}", command.input.display(), e))?;
+    let artifact: BackupArtifact =
+        serde_json::from_slice(&raw).map_err(|e| format!("{}: {}", command.input.display(), e))?;
+    if artifact.version != ARTIFACT_VERSION {
+        return Err(format!(
+            "artifact version {} is not supported, expected {}",
+            artifact.version, ARTIFACT_VERSION
+        ));
+    }
+    let missing = missing_stores(&artifact, &command.options.stores);
+    if !missing.is_empty() {
+        return Err(format!(
+            "stores not in the artifact: {}",
+            missing.join(", ")
+        ));
+    }
+
+    // A run is one generation of the OpenFGARestore; its ledger lives in the status
+    let mut run = String::new();
+    let mut ledger = TupleBatchLedger::default();
+    let restore_api = match &command.report {
+        Some((ns, name)) => {
+            let client = Client::try_default().await.map_err(|e| e.to_string())?;
+            let restores: Api<OpenFGARestore> = Api::namespaced(client, ns);
+            let current = restores
+                .get_status(name)
+                .await
+                .map_err(|e| format!("failed to read OpenFGARestore {}: {}", name, e))?;
+            run = format!(
+                "{}/{}",
+                current.uid().unwrap_or_default(),
+                current.metadata.generation.unwrap_or_default()
+            );
+            ledger = TupleBatchLedger::from_status(
+                current
+                    .status
+                    .as_ref()
+                    .and_then(|s| s.applied_tuple_batches.as_ref()),
+            );
+            Some((restores, name.clone()))
+        }
+        None => None,
+    };
+    let sink = ProgressReporter {
+        restore: restore_api,
+    };
+    let client = OpenFGAClient::new(&command.url)
+        .with_token(api_token())
+        .with_grpc(command.grpc_url.clone());
+    let progress = restore(
+        &client,
+        &artifact,
+        &command.options,
+        &run,
+        &mut ledger,
+        &sink,
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+    if progress.tuples_failed > 0 {
+        return Err(format!(
+            "{} tuples could not be written",
+            progress.tuples_failed
+        ));
+    }
+    Ok(format!(
+        "{} {} of {} tuples and {} models into {} stores ({} already present)",
+        if command.options.dry_run {
+            "would write"
+        } else {
+            "wrote"
+        },
+        progress.tuples_written,
+        progress.tuples_total,
+        progress.models_written,
+        progress.stores_done,
+        progress.tuples_skipped
+    ))
+}
+
+/// Artifacts of the same backup as `artifact`, which like it are named
+/// `<cronjob>-<scheduled time>.json` and live in the same directory.
+fn siblings(artifact: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let dir = artifact.parent().unwrap_or_else(|| Path::new("."));
+    let stem = artifact
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default();
+    let Some((cron_job, _)) = stem.rsplit_once('-') else {
+        return Ok(vec![]);
+    };
+    let same_backup = |path: &Path| {
+        path.extension().is_some_and(|ext| ext == "json")
+            && path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .and_then(|s| s.rsplit_once('-'))
+                .is_some_and(|(prefix, time)| {
+                    prefix == cron_job
+                        && !time.is_empty()
+                        && time.bytes().all(|b| b.is_ascii_digit())
+                })
+    };
+    let mut found: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| same_backup(path))
+        .collect();
+    found.sort();
+    Ok(found)
+}
+
+/// Deletes all but the newest `keep` artifacts of the backup `artifact` belongs
+/// to. Names end in the scheduled time, so they sort oldest first.
+pub fn prune(artifact: &Path, keep: usize) -> std::io::Result<Vec<PathBuf>> {
+    let artifacts = siblings(artifact)?;
+    let excess = artifacts.len().saturating_sub(keep);
+    let removed: Vec<PathBuf> = artifacts.into_iter().take(excess).collect();
+    for path in &removed {
+        std::fs::remove_file(path)?;
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::openfga_client::TuplePage;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_parse_export() {
+        let command = parse(&args(
+            "backup export --url http://authz:8080 --output /backup/a.json --store one --store two --keep 3",
+        ))
+        .unwrap();
+        assert_eq!(
+            command,
+            Command::Export(ExportCommand {
+                url: "http://authz:8080".to_string(),
+                grpc_url: None,
+                output: PathBuf::from("/backup/a.json"),
+                stores: vec!["one".to_string(), "two".to_string()],
+                keep: Some(3),
+            })
+        );
+        assert!(parse(&args("backup export --url http://authz:8080")).is_err());
+        assert!(parse(&args("backup export --url u --output o --keep 0")).is_err());
+        assert!(parse(&args("backup export --url u --output o --dry-run")).is_err());
+        assert!(parse(&args("backup import")).is_err());
+    }
+
+    #[test]
+    fn test_parse_restore() {
+        let command = parse(&args(
+            "backup restore --url http://authz:8080 --grpc-url https://authz:8081 --input /work/a.json --dry-run --store one --batch-size 50 --report auth/restore-1",
+        ))
+        .unwrap();
+        assert_eq!(
+            command,
+            Command::Restore(RestoreCommand {
+                url: "http://authz:8080".to_string(),
+                grpc_url: Some("https://authz:8081".to_string()),
+                input: PathBuf::from("/work/a.json"),
+                options: RestoreOptions {
+                    stores: vec!["one".to_string()],
+                    dry_run: true,
+                    batch_size: 50,
+                },
+                report: Some(("auth".to_string(), "restore-1".to_string())),
+            })
+        );
+        assert!(parse(&args("backup restore --url u --input i --batch-size 101")).is_err());
+        assert!(parse(&args("backup restore --url u --input i --report restore-1")).is_err());
+        assert!(parse(&args("backup restore --url u --output o")).is_err());
+    }
+
+    #[test]
+    fn test_missing_models_oldest_first() {
+        let v1 = serde_json::json!({ "id": "01A", "schema_version": "1.1", "type_definitions": [{ "type": "user" }] });
+        let v2 = serde_json::json!({ "id": "01B", "schema_version": "1.1", "type_definitions": [{ "type": "user" }, { "type": "doc" }], "conditions": {} });
+        let artifact = [v2.clone(), v1.clone()];
+
+        let all = missing_models(&artifact, &[]);
+        assert_eq!(all.len(), 2);
+        assert_eq!(
+            all[0],
+            serde_json::json!({ "schema_version": "1.1", "type_definitions": [{ "type": "user" }] })
+        );
+        assert!(all[1].get("conditions").is_none());
+
+        // The target holds v1 under another id
+        let restored_v1 = serde_json::json!({ "id": "01Z", "schema_version": "1.1", "type_definitions": [{ "type": "user" }] });
+        assert_eq!(missing_models(&artifact, &[restored_v1]).len(), 1);
+        assert!(missing_models(&artifact, &all).is_empty());
+    }
+
+    #[test]
+    fn test_missing_tuples_skips_existing_and_duplicates() {
+        let anne = TupleKey::new("user:anne", "viewer", "doc:1");
+        let bob = TupleKey::new("user:bob", "viewer", "doc:1");
+        let carl = TupleKey::new("user:carl", "viewer", "doc:1");
+        let missing = missing_tuples(
+            &[anne.clone(), bob.clone(), bob.clone(), carl.clone()],
+            &[anne],
+        );
+        assert_eq!(missing, vec![bob, carl]);
+    }
+
+    struct Pages(Vec<TuplePage>);
+
+    impl TupleReader for Pages {
+        async fn read_page(
+            &self,
+            _page_size: usize,
+            continuation_token: Option<&str>,
+        ) -> ClientResult<TuplePage> {
+            let index = continuation_token.map_or(0, |t| t.parse().unwrap());
+            Ok(self.0[index].clone())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_read_all_tuples_follows_pages() {
+        let reader = Pages(vec![
+            TuplePage {
+                tuples: vec![TupleKey::new("user:anne", "viewer", "doc:1")],
+                continuation_token: Some("1".to_string()),
+            },
+            TuplePage {
+                tuples: vec![TupleKey::new("user:bob", "viewer", "doc:2")],
+                continuation_token: None,
+            },
+        ]);
+        let tuples = read_all_tuples(&reader).await.unwrap();
+        assert_eq!(tuples.len(), 2);
+        assert_eq!(tuples[1].user, "user:bob");
+    }
+
+    #[test]
+    fn test_prune_keeps_newest_of_same_backup() {
+        let dir = std::env::temp_dir().join(format!("openfga-backup-prune-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in [
+            "nightly-backup-100.json",
+            "nightly-backup-200.json",
+            "nightly-backup-300.json",
+            "weekly-backup-100.json",
+            "nightly-backup-backup-100.json",
+            "nightly-backup-150.json.partial",
+        ] {
+            std::fs::write(dir.join(name), "{}").unwrap();
+        }
+
+        let removed = prune(&dir.join("nightly-backup-300.json"), 2).unwrap();
+        assert_eq!(removed, vec![dir.join("nightly-backup-100.json")]);
+        assert!(dir.join("weekly-backup-100.json").exists());
+        assert!(dir.join("nightly-backup-backup-100.json").exists());
+        assert!(dir.join("nightly-backup-200.json").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_artifact_round_trip() {
+        let artifact = BackupArtifact {
+            version: ARTIFACT_VERSION,
+            created_at: "2024-01-01T00:00:00Z".to_string(),
+            stores: vec![StoreBackup {
+                id: "01HV".to_string(),
+                name: "default".to_string(),
+                authorization_models: vec![serde_json::json!({ "schema_version": "1.1" })],
+                tuples: vec![TupleKey::new("user:anne", "viewer", "doc:1")],
+            }],
+        };
+        let json = serde_json::to_value(&artifact).unwrap();
+        assert_eq!(
+            json["stores"][0]["authorizationModels"][0]["schema_version"],
+            "1.1"
+        );
+        assert_eq!(
+            serde_json::from_value::<BackupArtifact>(json).unwrap(),
+            artifact
+        );
+    }
+}
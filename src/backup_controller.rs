@@ -0,0 +1,793 @@
+//! Periodic exports of OpenFGA stores. Each OpenFGABackup owns a CronJob whose
+//! jobs run `openfga-operator backup export` against the instance and, for
+//! object storage, hand the artifact to an upload container that also deletes
+//! artifacts beyond `spec.retention.keepLast`.
+//!
+//! Artifacts are named after the job, `<backup>-backup-<scheduled time>.json`,
+//! so they sort by age and the status can point at the last one written.
+
+use crate::access_tokens;
+use crate::apply::apply_params;
+use crate::controller::{shutdown_requested, ControllerError, ControllerResult, Shutdown};
+use crate::metrics;
+use crate::openfga_client::{instance_grpc_url, instance_url};
+use crate::panic_isolation::isolate_panics;
+use crate::types::{
+    BackupDestination, GcsDestination, OpenFGA, OpenFGABackup, OpenFGABackupStatus,
+    OpenFGACondition, PvcDestination, S3Destination,
+};
+use futures::StreamExt;
+use k8s_openapi::api::batch::v1::{CronJob, CronJobSpec, Job, JobSpec, JobTemplateSpec};
+use k8s_openapi::api::core::v1::{
+    Container, EmptyDirVolumeSource, EnvFromSource, EnvVar, EnvVarSource, ObjectFieldSelector,
+    PersistentVolumeClaimVolumeSource, PodSpec, PodTemplateSpec, SecretEnvSource,
+    SecretVolumeSource, Volume, VolumeMount,
+};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use kube::api::{Api, ListParams, Patch, PatchParams};
+use kube::runtime::controller::{Action, Controller};
+use kube::runtime::watcher::Config;
+use kube::{Client, Resource, ResourceExt};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tokio::time::Duration;
+use tracing::{debug, error, info, instrument, warn};
+
+const CONTROLLER_NAME: &str = "openfga-backup-controller";
+
+/// Upper bound between reconciles so job outcomes reach the status.
+const RESYNC_INTERVAL: Duration = Duration::from_secs(300);
+
+/// Image running the export; the operator's own by default.
+const OPERATOR_IMAGE_ENV: &str = "OPENFGA_OPERATOR_IMAGE";
+const DEFAULT_OPERATOR_IMAGE: &str = "ghcr.io/jralmaraz/authcore-openfga-operator:latest";
+pub(crate) const AWS_CLI_IMAGE: &str = "amazon/aws-cli:2.15.30";
+pub(crate) const CLOUD_SDK_IMAGE: &str = "google/cloud-sdk:470.0.0-slim";
+
+/// Label on every backup job naming the OpenFGABackup it ran for.
+pub const BACKUP_LABEL: &str = "openfga.dev/backup";
+
+pub(crate) const WORK_DIR: &str = "/work";
+pub(crate) const PVC_MOUNT: &str = "/backup";
+const GCS_KEY_DIR: &str = "/var/secrets/google";
+
+/// Copies the artifact to `$DEST` and deletes all but the newest `$KEEP`
+/// artifacts of this CronJob there.
+const S3_UPLOAD_SCRIPT: &str = r#"set -eu
+aws $ENDPOINT_ARGS s3 cp "/work/$JOB_NAME.json" "$DEST/$JOB_NAME.json"
+aws $ENDPOINT_ARGS s3 ls "$DEST/" | awk '{print $4}' | grep -E "^$CRON_JOB-[0-9]+\.json$" | sort | head -n "-$KEEP" | while read -r old; do
+  aws $ENDPOINT_ARGS s3 rm "$DEST/$old"
+done
+"#;
+
+const GCS_UPLOAD_SCRIPT: &str = r#"set -eu
+gcloud auth activate-service-account --key-file=/var/secrets/google/key.json
+gsutil cp "/work/$JOB_NAME.json" "$DEST/$JOB_NAME.json"
+gsutil ls "$DEST/" | grep -E "/$CRON_JOB-[0-9]+\.json$" | sort | head -n "-$KEEP" | while read -r old; do
+  gsutil rm "$old"
+done
+"#;
+
+pub struct OpenFGABackupController {
+    client: Client,
+}
+
+impl OpenFGABackupController {
+    pub fn new(client: Client) -> Self {
+        Self { client }
+    }
+
+    pub async fn run(self, shutdown: Shutdown) {
+        let backups: Api<OpenFGABackup> = Api::all(self.client.clone());
+        let cron_jobs: Api<CronJob> = Api::all(self.client.clone());
+
+        info!(
+            controller = CONTROLLER_NAME,
+            "Starting controller with OpenFGABackup resource monitoring"
+        );
+
+        let _watches = metrics::track_watch_streams(CONTROLLER_NAME, 2);
+        Controller::new(backups, Config::default().any_semantic())
+            .owns(cron_jobs, Config::default())
+            .graceful_shutdown_on(shutdown_requested(shutdown.clone()))
+            .run(
+                |backup, ctx| {
+                    let guarded = isolate_panics(
+                        ctx.client.clone(),
+                        CONTROLLER_NAME,
+                        backup.clone(),
+                        reconcile(backup.clone(), ctx),
+                    );
+                    metrics::observe_reconcile(CONTROLLER_NAME, backup, guarded)
+                },
+                error_policy,
+                Arc::new(self),
+            )
+            .for_each(|res| async move {
+                match res {
+                    Ok(o) => {
+                        debug!(
+                            reconciliation_result = "success",
+                            object = ?o,
+                            "OpenFGABackup reconciliation completed successfully"
+                        );
+                    }
+                    Err(e) => {
+                        error!(
+                            reconciliation_result = "error",
+                            error = %e,
+                            "OpenFGABackup reconciliation failed"
+                        );
+                    }
+                }
+            })
+            .await;
+    }
+}
+
+pub fn cron_job_name(backup_name: &str) -> String {
+    format!("{}-backup", backup_name)
+}
+
+pub(crate) fn with_prefix(base: String, prefix: &str) -> String {
+    match prefix.trim_matches('/') {
+        "" => base,
+        prefix => format!("{}/{}", base, prefix),
+    }
+}
+
+/// Where artifacts are written, e.g. `s3://bucket/openfga`. Errors unless
+/// exactly one destination is set.
+pub fn destination_uri(destination: &BackupDestination) -> Result<String, String> {
+    match (&destination.s3, &destination.gcs, &destination.pvc) {
+        (Some(s3), None, None) => Ok(with_prefix(format!("s3://{}", s3.bucket), &s3.prefix)),
+        (None, Some(gcs), None) => Ok(with_prefix(format!("gs://{}", gcs.bucket), &gcs.prefix)),
+        (None, None, Some(pvc)) => Ok(with_prefix(format!("pvc://{}", pvc.claim_name), &pvc.path)),
+        _ => Err(
+            "exactly one of destination.s3, destination.gcs and destination.pvc must be set"
+                .to_string(),
+        ),
+    }
+}
+
+pub(crate) fn env(name: &str, value: impl Into<String>) -> EnvVar {
+    EnvVar {
+        name: name.to_string(),
+        value: Some(value.into()),
+        ..Default::default()
+    }
+}
+
+pub(crate) fn mount(name: &str, path: &str) -> VolumeMount {
+    VolumeMount {
+        name: name.to_string(),
+        mount_path: path.to_string(),
+        ..Default::default()
+    }
+}
+
+/// The image a backup or restore job runs: `spec_image`, else
+/// `OPENFGA_OPERATOR_IMAGE`, else the operator's published image.
+pub(crate) fn operator_image(spec_image: Option<&str>) -> String {
+    spec_image
+        .map(str::to_string)
+        .or_else(|| std::env::var(OPERATOR_IMAGE_ENV).ok())
+        .filter(|image| !image.is_empty())
+        .unwrap_or_else(|| DEFAULT_OPERATOR_IMAGE.to_string())
+}
+
+/// `JOB_NAME`, the name of the Job the pod belongs to.
+pub(crate) fn job_name_env() -> EnvVar {
+    EnvVar {
+        name: "JOB_NAME".to_string(),
+        value_from: Some(EnvVarSource {
+            field_ref: Some(ObjectFieldSelector {
+                field_path: "metadata.labels['job-name']".to_string(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+/// The `work` scratch volume artifacts pass through on their way to or from object storage.
+pub(crate) fn work_volume() -> Volume {
+    Volume {
+        name: "work".to_string(),
+        empty_dir: Some(EmptyDirVolumeSource::default()),
+        ..Default::default()
+    }
+}
+
+/// The `backup` volume of a PVC destination.
+pub(crate) fn pvc_volume(pvc: &PvcDestination) -> Volume {
+    Volume {
+        name: "backup".to_string(),
+        persistent_volume_claim: Some(PersistentVolumeClaimVolumeSource {
+            claim_name: pvc.claim_name.clone(),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+/// Environment of an aws CLI container talking to `s3`: `ENDPOINT_ARGS` for
+/// the scripts, the region and the credentials secret.
+pub(crate) fn s3_env(s3: &S3Destination) -> (Vec<EnvVar>, Vec<EnvFromSource>) {
+    let mut env_vars = vec![env(
+        "ENDPOINT_ARGS",
+        s3.endpoint
+            .as_ref()
+            .map(|endpoint| format!("--endpoint-url {}", endpoint))
+            .unwrap_or_default(),
+    )];
+    if let Some(region) = &s3.region {
+        env_vars.push(env("AWS_DEFAULT_REGION", region.clone()));
+    }
+    let credentials = EnvFromSource {
+        secret_ref: Some(SecretEnvSource {
+            name: Some(s3.credentials_secret.clone()),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    (env_vars, vec![credentials])
+}
+
+/// The volume holding the service account key of `gcs` and its mount, which
+/// the scripts activate with `gcloud auth`.
+pub(crate) fn gcs_credentials(gcs: &GcsDestination) -> (Volume, VolumeMount) {
+    let volume = Volume {
+        name: "gcs-credentials".to_string(),
+        secret: Some(SecretVolumeSource {
+            secret_name: Some(gcs.credentials_secret.clone()),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let mount = VolumeMount {
+        read_only: Some(true),
+        ..mount("gcs-credentials", GCS_KEY_DIR)
+    };
+    (volume, mount)
+}
+
+/// The export container, writing `<output_dir>/$(JOB_NAME).json`.
+fn export_container(
+    backup: &OpenFGABackup,
+    instance: &OpenFGA,
+    image: &str,
+    output_dir: &str,
+    volume: VolumeMount,
+    keep: Option<u32>,
+) -> Container {
+    let mut args = vec![
+        "backup".to_string(),
+        "export".to_string(),
+        "--url".to_string(),
+        instance_url(instance),
+        "--output".to_string(),
+        format!("{}/$(JOB_NAME).json", output_dir),
+    ];
+    if let Some(grpc_url) = instance_grpc_url(instance) {
+        args.extend(["--grpc-url".to_string(), grpc_url]);
+    }
+    for store in &backup.spec.stores {
+        args.extend(["--store".to_string(), store.clone()]);
+    }
+    if let Some(keep) = keep {
+        args.extend(["--keep".to_string(), keep.to_string()]);
+    }
+    Container {
+        name: "export".to_string(),
+        image: Some(image.to_string()),
+        args: Some(args),
+        env: Some(vec![job_name_env(), access_tokens::api_token_env(instance)]),
+        volume_mounts: Some(vec![volume]),
+        ..Default::default()
+    }
+}
+
+/// The CronJob running `backup`'s exports against `instance`.
+pub fn build_cron_job(
+    backup: &OpenFGABackup,
+    instance: &OpenFGA,
+    image: &str,
+) -> Result<CronJob, String> {
+    let name = backup.name_any();
+    let cron_job = cron_job_name(&name);
+    let destination = &backup.spec.destination;
+    let dest = destination_uri(destination)?;
+    let keep = backup.spec.retention.keep_last.max(1);
+
+    // Object storage: export into a scratch volume, then upload from there
+    let upload = |upload_image: &str, script: &str, mut env_vars: Vec<EnvVar>| {
+        let export = export_container(
+            backup,
+            instance,
+            image,
+            WORK_DIR,
+            mount("work", WORK_DIR),
+            None,
+        );
+        env_vars.extend([
+            env("DEST", dest.clone()),
+            env("CRON_JOB", cron_job.clone()),
+            env("KEEP", keep.to_string()),
+        ]);
+        let uploader = Container {
+            name: "upload".to_string(),
+            image: Some(upload_image.to_string()),
+            command: Some(vec![
+                "/bin/sh".to_string(),
+                "-c".to_string(),
+                script.to_string(),
+            ]),
+            env: Some([export.env.clone().unwrap_or_default(), env_vars].concat()),
+            volume_mounts: Some(vec![mount("work", WORK_DIR)]),
+            ..Default::default()
+        };
+        (export, uploader)
+    };
+
+    let (init_containers, containers, volumes) = if let Some(pvc) = &destination.pvc {
+        let dir = with_prefix(PVC_MOUNT.to_string(), &pvc.path);
+        let export = export_container(
+            backup,
+            instance,
+            image,
+            &dir,
+            mount("backup", PVC_MOUNT),
+            Some(keep),
+        );
+        (None, vec![export], vec![pvc_volume(pvc)])
+    } else if let Some(s3) = &destination.s3 {
+        let (env_vars, env_from) = s3_env(s3);
+        let (export, mut uploader) = upload(AWS_CLI_IMAGE, S3_UPLOAD_SCRIPT, env_vars);
+        uploader.env_from = Some(env_from);
+        (Some(vec![export]), vec![uploader], vec![work_volume()])
+    } else if let Some(gcs) = &destination.gcs {
+        let (export, mut uploader) = upload(CLOUD_SDK_IMAGE, GCS_UPLOAD_SCRIPT, vec![]);
+        let (credentials, credentials_mount) = gcs_credentials(gcs);
+        uploader
+            .volume_mounts
+            .get_or_insert_with(Vec::new)
+            .push(credentials_mount);
+        (
+            Some(vec![export]),
+            vec![uploader],
+            vec![work_volume(), credentials],
+        )
+    } else {
+        unreachable!("destination_uri accepted the destination");
+    };
+
+    let job_labels = BTreeMap::from([
+        (
+            "app.kubernetes.io/managed-by".to_string(),
+            "openfga-operator".to_string(),
+        ),
+        (BACKUP_LABEL.to_string(), name.clone()),
+    ]);
+    Ok(CronJob {
+        metadata: ObjectMeta {
+            name: Some(cron_job),
+            namespace: backup.namespace(),
+            labels: Some(job_labels.clone()),
+            owner_references: backup.controller_owner_ref(&()).map(|o| vec![o]),
+            ..Default::default()
+        },
+        spec: Some(CronJobSpec {
+            schedule: backup.spec.schedule.clone(),
+            suspend: Some(backup.spec.suspend),
+            // An export still running when the next one is due is left alone
+            concurrency_policy: Some("Forbid".to_string()),
+            successful_jobs_history_limit: Some(3),
+            failed_jobs_history_limit: Some(3),
+            job_template: JobTemplateSpec {
+                metadata: Some(ObjectMeta {
+                    labels: Some(job_labels.clone()),
+                    ..Default::default()
+                }),
+                spec: Some(JobSpec {
+                    backoff_limit: Some(2),
+                    template: PodTemplateSpec {
+                        metadata: Some(ObjectMeta {
+                            labels: Some(job_labels),
+                            ..Default::default()
+                        }),
+                        spec: Some(PodSpec {
+                            restart_policy: Some("Never".to_string()),
+                            init_containers,
+                            containers,
+                            volumes: Some(volumes),
+                            ..Default::default()
+                        }),
+                    },
+                    ..Default::default()
+                }),
+            },
+            ..Default::default()
+        }),
+        ..Default::default()
+    })
+}
+
+/// Names of the newest succeeded job and of the newest failed job, if it ran
+/// after that success.
+pub fn job_outcome(jobs: &[Job]) -> (Option<String>, Option<String>) {
+    let started = |job: &Job| {
+        job.status
+            .as_ref()
+            .and_then(|s| s.start_time.as_ref())
+            .map(|t| t.0)
+    };
+    let has_condition = |job: &Job, kind: &str| {
+        job.status
+            .as_ref()
+            .and_then(|s| s.conditions.as_ref())
+            .is_some_and(|conditions| {
+                conditions
+                    .iter()
+                    .any(|c| c.type_ == kind && c.status == "True")
+            })
+    };
+    let newest = |kind: &str| {
+        jobs.iter()
+            .filter(|job| has_condition(job, kind))
+            .max_by_key(|job| started(job))
+    };
+    let succeeded = newest("Complete");
+    let failed = newest("Failed")
+        .filter(|failed| succeeded.is_none_or(|succeeded| started(failed) > started(succeeded)));
+    (
+        succeeded.map(|job| job.name_any()),
+        failed.map(|job| job.name_any()),
+    )
+}
+
+#[instrument(skip(ctx), fields(namespace = %backup.namespace().unwrap_or_default(), name = %backup.name_any(), generation = backup.metadata.generation.unwrap_or_default()))]
+async fn reconcile(
+    backup: Arc<OpenFGABackup>,
+    ctx: Arc<OpenFGABackupController>,
+) -> ControllerResult<Action> {
+    let ns = backup.namespace().unwrap_or_default();
+    let name = backup.name_any();
+    let previous = backup.status.clone().unwrap_or_default();
+    let mut status = OpenFGABackupStatus {
+        conditions: None,
+        ..previous.clone()
+    };
+    let condition = |ready: bool, reason: &str, message: &str| {
+        OpenFGACondition::new(
+            "Ready",
+            ready,
+            reason,
+            message,
+            backup.metadata.generation,
+            previous.conditions.as_deref(),
+        )
+    };
+
+    info!(
+        event = "backup_reconciliation_start",
+        namespace = %ns,
+        resource_name = %name,
+        instance = %backup.spec.instance_ref.name,
+        "Starting OpenFGABackup reconciliation"
+    );
+
+    let instances: Api<OpenFGA> = Api::namespaced(ctx.client.clone(), &ns);
+    let Some(instance) = instances.get_opt(&backup.spec.instance_ref.name).await? else {
+        warn!(
+            event = "backup_instance_missing",
+            namespace = %ns,
+            resource_name = %name,
+            instance = %backup.spec.instance_ref.name,
+            "Referenced OpenFGA instance does not exist"
+        );
+        status.conditions = Some(vec![condition(
+            false,
+            "InstanceNotFound",
+            &format!(
+                "OpenFGA instance '{}' not found",
+                backup.spec.instance_ref.name
+            ),
+        )]);
+        patch_status(&ctx.client, &ns, &name, &status).await?;
+        return Ok(Action::requeue(Duration::from_secs(60)));
+    };
+
+    let image = operator_image(backup.spec.image.as_deref());
+    let cron_job = match build_cron_job(&backup, &instance, &image) {
+        Ok(cron_job) => cron_job,
+        Err(message) => {
+            warn!(
+                event = "backup_invalid_destination",
+                namespace = %ns,
+                resource_name = %name,
+                error = %message,
+                "OpenFGABackup destination is invalid"
+            );
+            status.conditions = Some(vec![condition(false, "InvalidDestination", &message)]);
+            patch_status(&ctx.client, &ns, &name, &status).await?;
+            return Ok(Action::await_change());
+        }
+    };
+    let dest = destination_uri(&backup.spec.destination).unwrap_or_default();
+
+    let cron_job_name = cron_job_name(&name);
+    let cron_jobs: Api<CronJob> = Api::namespaced(ctx.client.clone(), &ns);
+    let applied = cron_jobs
+        .patch(&cron_job_name, &apply_params(), &Patch::Apply(&cron_job))
+        .await?;
+    let cron_status = applied.status.unwrap_or_default();
+    status.cron_job = Some(cron_job_name.clone());
+    status.last_schedule_time = cron_status.last_schedule_time.map(|t| t.0.to_rfc3339());
+    status.last_successful_time = cron_status.last_successful_time.map(|t| t.0.to_rfc3339());
+
+    let jobs: Api<Job> = Api::namespaced(ctx.client.clone(), &ns);
+    let jobs = jobs
+        .list(&ListParams::default().labels(&format!("{}={}", BACKUP_LABEL, name)))
+        .await?;
+    let (succeeded, failed) = job_outcome(&jobs.items);
+    if let Some(job) = &succeeded {
+        status.last_backup = Some(format!("{}/{}.json", dest, job));
+    }
+
+    status.conditions = Some(vec![match &failed {
+        Some(job) => {
+            warn!(
+                event = "backup_job_failed",
+                namespace = %ns,
+                resource_name = %name,
+                job = %job,
+                "Backup job failed"
+            );
+            condition(false, "BackupFailed", &format!("job {} failed", job))
+        }
+        None if backup.spec.suspend => condition(true, "Suspended", "backups are suspended"),
+        None => condition(
+            true,
+            "Scheduled",
+            &format!("backups to {} run by CronJob {}", dest, cron_job_name),
+        ),
+    }]);
+
+    patch_status(&ctx.client, &ns, &name, &status).await?;
+    Ok(Action::requeue(RESYNC_INTERVAL))
+}
+
+async fn patch_status(
+    client: &Client,
+    ns: &str,
+    name: &str,
+    status: &OpenFGABackupStatus,
+) -> ControllerResult<()> {
+    let backups: Api<OpenFGABackup> = Api::namespaced(client.clone(), ns);
+    backups
+        .patch_status(
+            name,
+            &PatchParams::default(),
+            &Patch::Merge(&serde_json::json!({ "status": status })),
+        )
+        .await?;
+    Ok(())
+}
+
+fn error_policy(
+    backup: Arc<OpenFGABackup>,
+    error: &ControllerError,
+    _ctx: Arc<OpenFGABackupController>,
+) -> Action {
+    warn!(
+        event = "backup_reconciliation_error",
+        namespace = %backup.namespace().unwrap_or_default(),
+        resource_name = %backup.name_any(),
+        error_message = %error,
+        "OpenFGABackup reconciliation failed, retrying"
+    );
+    metrics::record_reconcile_error::<OpenFGABackup>(CONTROLLER_NAME, error.kind());
+    Action::requeue(Duration::from_secs(30))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::create_test_openfga;
+    use k8s_openapi::api::batch::v1::{JobCondition, JobStatus};
+    use k8s_openapi::apimachinery::pkg::apis::meta::v1::Time;
+    use serde_json::{json, Value};
+
+    fn instance() -> OpenFGA {
+        let mut openfga = create_test_openfga();
+        openfga.metadata.name = Some("authz".to_string());
+        openfga.metadata.namespace = Some("auth".to_string());
+        openfga
+    }
+
+    fn backup(destination: Value) -> OpenFGABackup {
+        let mut backup = OpenFGABackup::new(
+            "nightly",
+            serde_json::from_value(json!({
+                "instanceRef": { "name": "authz" },
+                "schedule": "0 2 * * *",
+                "stores": ["default"],
+                "destination": destination,
+                "retention": { "keepLast": 5 },
+            }))
+            .unwrap(),
+        );
+        backup.metadata.namespace = Some("auth".to_string());
+        backup.metadata.uid = Some("1234".to_string());
+        backup
+    }
+
+    fn pod_spec(cron_job: &CronJob) -> PodSpec {
+        cron_job
+            .spec
+            .clone()
+            .unwrap()
+            .job_template
+            .spec
+            .unwrap()
+            .template
+            .spec
+            .unwrap()
+    }
+
+    #[test]
+    fn test_destination_uri() {
+        let uri = |destination| destination_uri(&backup(destination).spec.destination);
+        assert_eq!(
+            uri(
+                json!({ "s3": { "bucket": "b", "prefix": "/openfga/", "credentialsSecret": "s" } })
+            ),
+            Ok("s3://b/openfga".to_string())
+        );
+        assert_eq!(
+            uri(json!({ "gcs": { "bucket": "b", "credentialsSecret": "s" } })),
+            Ok("gs://b".to_string())
+        );
+        assert!(uri(json!({})).is_err());
+        assert!(uri(json!({
+            "gcs": { "bucket": "b", "credentialsSecret": "s" },
+            "pvc": { "claimName": "c" },
+        }))
+        .is_err());
+    }
+
+    #[test]
+    fn test_pvc_cron_job_exports_in_place() {
+        let cron_job = build_cron_job(
+            &backup(json!({ "pvc": { "claimName": "backups", "path": "openfga" } })),
+            &instance(),
+            "operator:1",
+        )
+        .unwrap();
+        assert_eq!(cron_job.metadata.name.as_deref(), Some("nightly-backup"));
+        assert_eq!(
+            cron_job.metadata.owner_references.as_ref().unwrap()[0].uid,
+            "1234"
+        );
+        let spec = cron_job.spec.as_ref().unwrap();
+        assert_eq!(spec.schedule, "0 2 * * *");
+        assert_eq!(spec.concurrency_policy.as_deref(), Some("Forbid"));
+
+        let pod = pod_spec(&cron_job);
+        assert!(pod.init_containers.is_none());
+        assert_eq!(
+            pod.containers[0].args.as_ref().unwrap(),
+            &[
+                "backup",
+                "export",
+                "--url",
+                "http://authz.auth.svc:8080",
+                "--output",
+                "/backup/openfga/$(JOB_NAME).json",
+                "--store",
+                "default",
+                "--keep",
+                "5",
+            ]
+        );
+        let volume = &pod.volumes.as_ref().unwrap()[0];
+        assert_eq!(
+            volume.persistent_volume_claim.as_ref().unwrap().claim_name,
+            "backups"
+        );
+    }
+
+    #[test]
+    fn test_s3_cron_job_uploads_from_scratch_volume() {
+        let cron_job = build_cron_job(
+            &backup(json!({ "s3": {
+                "bucket": "backups",
+                "prefix": "openfga",
+                "endpoint": "http://minio:9000",
+                "credentialsSecret": "s3-creds",
+            } })),
+            &instance(),
+            "operator:1",
+        )
+        .unwrap();
+        let pod = pod_spec(&cron_job);
+        let export = &pod.init_containers.as_ref().unwrap()[0];
+        assert_eq!(export.image.as_deref(), Some("operator:1"));
+        assert!(!export
+            .args
+            .as_ref()
+            .unwrap()
+            .contains(&"--keep".to_string()));
+
+        let upload = &pod.containers[0];
+        assert_eq!(upload.image.as_deref(), Some(AWS_CLI_IMAGE));
+        let env: BTreeMap<_, _> = upload
+            .env
+            .as_ref()
+            .unwrap()
+            .iter()
+            .map(|e| (e.name.as_str(), e.value.clone()))
+            .collect();
+        assert_eq!(env["DEST"].as_deref(), Some("s3://backups/openfga"));
+        assert_eq!(env["CRON_JOB"].as_deref(), Some("nightly-backup"));
+        assert_eq!(env["KEEP"].as_deref(), Some("5"));
+        assert_eq!(
+            env["ENDPOINT_ARGS"].as_deref(),
+            Some("--endpoint-url http://minio:9000")
+        );
+        assert!(env.contains_key("JOB_NAME"));
+        assert_eq!(
+            upload.env_from.as_ref().unwrap()[0]
+                .secret_ref
+                .as_ref()
+                .unwrap()
+                .name
+                .as_deref(),
+            Some("s3-creds")
+        );
+    }
+
+    fn job(name: &str, started_minutes_ago: i64, outcome: &str) -> Job {
+        Job {
+            metadata: ObjectMeta {
+                name: Some(name.to_string()),
+                ..Default::default()
+            },
+            status: Some(JobStatus {
+                start_time: Some(Time(
+                    chrono::Utc::now() - chrono::Duration::minutes(started_minutes_ago),
+                )),
+                conditions: Some(vec![JobCondition {
+                    type_: outcome.to_string(),
+                    status: "True".to_string(),
+                    ..Default::default()
+                }]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_job_outcome() {
+        assert_eq!(job_outcome(&[]), (None, None));
+        let jobs = [
+            job("nightly-backup-1", 30, "Complete"),
+            job("nightly-backup-2", 20, "Failed"),
+            job("nightly-backup-3", 10, "Complete"),
+        ];
+        assert_eq!(
+            job_outcome(&jobs),
+            (Some("nightly-backup-3".to_string()), None)
+        );
+        assert_eq!(
+            job_outcome(&jobs[..2]),
+            (
+                Some("nightly-backup-1".to_string()),
+                Some("nightly-backup-2".to_string())
+            )
+        );
+    }
+}
@@ -0,0 +1,41 @@
+//! Prints the CustomResourceDefinitions generated from the Rust types as a
+//! multi-document YAML stream. With a directory argument, writes one
+//! `<singular>-crd.yaml` file per resource there instead.
+//!
+//! ```sh
+//! cargo run --bin crdgen > crds.yaml
+//! cargo run --bin crdgen -- target/crds
+//! ```
+
+use anyhow::{Context, Result};
+use openfga_operator::types::crds;
+use std::path::PathBuf;
+
+fn main() -> Result<()> {
+    let output = std::env::args().nth(1).map(PathBuf::from);
+    if let Some(dir) = &output {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("failed to create {}", dir.display()))?;
+    }
+
+    for crd in crds() {
+        let yaml = serde_yaml::to_string(&crd)?;
+        match &output {
+            Some(dir) => {
+                let path = dir.join(format!(
+                    "{}-crd.yaml",
+                    crd.spec
+                        .names
+                        .singular
+                        .as_deref()
+                        .unwrap_or(&crd.spec.names.plural)
+                ));
+                std::fs::write(&path, yaml)
+                    .with_context(|| format!("failed to write {}", path.display()))?;
+                eprintln!("wrote {}", path.display());
+            }
+            None => print!("---\n{}", yaml),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,125 @@
+//! Stores listed in `spec.bootstrap.stores` are created through the OpenFGA API
+//! once the instance answers, so a single OpenFGA resource yields a usable
+//! service. Existing stores are matched by name and never recreated.
+
+use crate::openfga_client::{ClientResult, OpenFGAClient, StoreInfo};
+use crate::types::{BootstrappedStore, OpenFGACondition};
+use std::collections::BTreeSet;
+
+/// Matches the wanted store names against the stores on the instance. Returns the
+/// stores that already exist and the names still to create, both in spec order.
+/// OpenFGA allows duplicate names; the first listed store wins.
+pub fn plan_stores(
+    wanted: &[String],
+    existing: &[StoreInfo],
+) -> (Vec<BootstrappedStore>, Vec<String>) {
+    let mut seen = BTreeSet::new();
+    let mut found = Vec::new();
+    let mut missing = Vec::new();
+    for name in wanted.iter().filter(|name| seen.insert(name.as_str())) {
+        match existing.iter().find(|store| &store.name == name) {
+            Some(store) => found.push(BootstrappedStore {
+                name: name.clone(),
+                id: store.id.clone(),
+            }),
+            None => missing.push(name.clone()),
+        }
+    }
+    (found, missing)
+}
+
+/// Creates every wanted store that does not exist yet and returns all of them
+/// with their IDs, in spec order.
+pub async fn ensure_stores(
+    client: &OpenFGAClient,
+    wanted: &[String],
+) -> ClientResult<Vec<BootstrappedStore>> {
+    let (mut stores, missing) = plan_stores(wanted, &client.list_stores().await?);
+    for name in missing {
+        let id = client.create_store(&name).await?;
+        stores.push(BootstrappedStore { name, id });
+    }
+    stores.sort_by_key(|store| wanted.iter().position(|name| *name == store.name));
+    Ok(stores)
+}
+
+/// Derives the `StoresBootstrapped` condition, `None` while the server is not reachable.
+pub fn stores_condition(
+    result: Option<&Result<Vec<BootstrappedStore>, String>>,
+    generation: Option<i64>,
+    previous: Option<&[OpenFGACondition]>,
+) -> OpenFGACondition {
+    let (status, reason, message) = match result {
+        None => (
+            false,
+            "WaitingForServer",
+            "Stores are created once the API is reachable".to_string(),
+        ),
+        Some(Ok(stores)) => (
+            true,
+            "StoresReady",
+            format!("{} store(s) exist", stores.len()),
+        ),
+        Some(Err(e)) => (false, "BootstrapFailed", e.clone()),
+    };
+    OpenFGACondition::new(
+        "StoresBootstrapped",
+        status,
+        reason,
+        &message,
+        generation,
+        previous,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_stores() {
+        let existing = vec![
+            StoreInfo {
+                id: "01A".to_string(),
+                name: "banking".to_string(),
+            },
+            StoreInfo {
+                id: "01B".to_string(),
+                name: "banking".to_string(),
+            },
+            StoreInfo {
+                id: "01C".to_string(),
+                name: "other".to_string(),
+            },
+        ];
+        let wanted: Vec<String> = ["genai", "banking", "genai"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+
+        let (found, missing) = plan_stores(&wanted, &existing);
+        assert_eq!(
+            found,
+            vec![BootstrappedStore {
+                name: "banking".to_string(),
+                id: "01A".to_string(),
+            }]
+        );
+        assert_eq!(missing, vec!["genai".to_string()]);
+    }
+
+    #[test]
+    fn test_stores_condition() {
+        let waiting = stores_condition(None, Some(1), None);
+        assert_eq!(waiting.status, "False");
+        assert_eq!(waiting.reason.as_deref(), Some("WaitingForServer"));
+
+        let ready = stores_condition(Some(&Ok(vec![])), Some(1), None);
+        assert_eq!(ready.type_, "StoresBootstrapped");
+        assert_eq!(ready.status, "True");
+
+        let failed = stores_condition(Some(&Err("refused".to_string())), Some(1), None);
+        assert_eq!(failed.reason.as_deref(), Some("BootstrapFailed"));
+        assert_eq!(failed.message.as_deref(), Some("refused"));
+    }
+}
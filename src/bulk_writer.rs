@@ -0,0 +1,654 @@
+use crate::metrics;
+use crate::tuples::TupleOperation;
+use futures::future::join_all;
+use sha2::{Digest, Sha256};
+use std::collections::{HashSet, VecDeque};
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tracing::{debug, warn};
+
+/// OpenFGA rejects Write requests carrying more than 100 writes and deletes combined.
+pub const MAX_TUPLES_PER_WRITE: usize = 100;
+
+/// Upper bound on idempotency keys kept in a ledger so it stays small enough for CR status.
+pub const MAX_LEDGER_ENTRIES: usize = 1000;
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum WriteError {
+    #[error("rate limited by OpenFGA")]
+    RateLimited { retry_after: Option<Duration> },
+    #[error("write failed: {0}")]
+    Failed(String),
+}
+
+/// Sink for a single OpenFGA Write call. Implemented by the API client; the
+/// pipeline only cares about latency and whether the call was throttled.
+pub trait TupleWriter {
+    fn write(&self, batch: &[TupleOperation]) -> impl Future<Output = Result<(), WriteError>>;
+}
+
+#[derive(Debug, Clone)]
+pub struct BulkWriterConfig {
+    pub max_batch_size: usize,
+    pub min_batch_size: usize,
+    pub max_concurrency: usize,
+    /// Batches slower than this shrink the batch size.
+    pub target_latency: Duration,
+    /// How often a throttled batch is retried before being reported as failed.
+    pub max_retries: u32,
+    pub base_backoff: Duration,
+}
+
+impl Default for BulkWriterConfig {
+    fn default() -> Self {
+        Self {
+            max_batch_size: MAX_TUPLES_PER_WRITE,
+            min_batch_size: 1,
+            max_concurrency: 8,
+            target_latency: Duration::from_millis(500),
+            max_retries: 5,
+            base_backoff: Duration::from_millis(200),
+        }
+    }
+}
+
+/// Additive-increase / multiplicative-decrease limits for batch size and concurrency.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AdaptiveLimits {
+    pub batch_size: usize,
+    pub concurrency: usize,
+}
+
+impl AdaptiveLimits {
+    fn new(config: &BulkWriterConfig) -> Self {
+        Self {
+            batch_size: config.max_batch_size.clamp(1, MAX_TUPLES_PER_WRITE),
+            concurrency: 1,
+        }
+    }
+
+    fn on_throttled(&mut self, config: &BulkWriterConfig) {
+        self.concurrency = (self.concurrency / 2).max(1);
+        self.batch_size = (self.batch_size / 2).max(config.min_batch_size.max(1));
+    }
+
+    fn on_slow(&mut self, config: &BulkWriterConfig) {
+        self.batch_size = (self.batch_size * 3 / 4).max(config.min_batch_size.max(1));
+    }
+
+    fn on_healthy(&mut self, config: &BulkWriterConfig) {
+        let max_batch = config.max_batch_size.clamp(1, MAX_TUPLES_PER_WRITE);
+        self.batch_size = (self.batch_size + self.batch_size / 4 + 1).min(max_batch);
+        self.concurrency = (self.concurrency + 1).min(config.max_concurrency.max(1));
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct BulkWriteReport {
+    pub written: usize,
+    pub deleted: usize,
+    pub batches: usize,
+    pub throttled: usize,
+    /// Operations skipped because their batch was already recorded in the ledger.
+    pub skipped: usize,
+    /// Operations that could not be applied, with the error from the last attempt.
+    pub failed: Vec<(TupleOperation, WriteError)>,
+}
+
+impl BulkWriteReport {
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty()
+    }
+
+    fn merge(&mut self, other: BulkWriteReport) {
+        self.written += other.written;
+        self.deleted += other.deleted;
+        self.batches += other.batches;
+        self.throttled += other.throttled;
+        self.skipped += other.skipped;
+        self.failed.extend(other.failed);
+    }
+}
+
+/// Stable idempotency key for a batch of operations within `scope`, which
+/// names the store and the run (e.g. restore generation) the batch belongs to,
+/// so the same tuples written to another store or by a later run count anew.
+pub fn batch_key(scope: &str, operations: &[TupleOperation]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(format!("{}\n", scope));
+    for op in operations {
+        let kind = match op {
+            TupleOperation::Write(_) => "w",
+            TupleOperation::Delete(_) => "d",
+        };
+        let tuple = op.tuple();
+        hasher.update(format!(
+            "{}|{}|{}|{}\n",
+            kind, tuple.user, tuple.relation, tuple.object
+        ));
+    }
+    hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Record of batches that have been fully applied, persisted in CR status (an
+/// OpenFGARestore's, for restores) so retried runs and restarts mid-import neither
+/// replay nor revert finished batches.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TupleBatchLedger {
+    applied: VecDeque<String>,
+}
+
+impl TupleBatchLedger {
+    pub fn from_status(keys: Option<&Vec<String>>) -> Self {
+        Self {
+            applied: keys.cloned().unwrap_or_default().into(),
+        }
+    }
+
+    pub fn to_status(&self) -> Vec<String> {
+        self.applied.iter().cloned().collect()
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.applied.iter().any(|k| k == key)
+    }
+
+    pub fn record(&mut self, key: String) {
+        if self.contains(&key) {
+            return;
+        }
+        self.applied.push_back(key);
+        while self.applied.len() > MAX_LEDGER_ENTRIES {
+            self.applied.pop_front();
+        }
+    }
+}
+
+/// Shared write pipeline for imports, seeds, restores and group sync.
+pub struct BulkWriter<W> {
+    writer: W,
+    config: BulkWriterConfig,
+    pipeline: String,
+    /// Limits reached by the last call, where the next one starts.
+    limits: Mutex<AdaptiveLimits>,
+}
+
+impl<W: TupleWriter> BulkWriter<W> {
+    pub fn new(writer: W, config: BulkWriterConfig, pipeline: &str) -> Self {
+        Self {
+            limits: Mutex::new(AdaptiveLimits::new(&config)),
+            writer,
+            config,
+            pipeline: pipeline.to_string(),
+        }
+    }
+
+    /// Applies all operations in batches, adapting batch size and concurrency to
+    /// observed latency and throttling. Batches in flight together may land in any
+    /// order, so callers must not mix a write and a delete of the same tuple.
+    pub async fn apply(&self, operations: Vec<TupleOperation>) -> BulkWriteReport {
+        let started = Instant::now();
+        let mut report = BulkWriteReport::default();
+        let mut limits = self
+            .limits
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        let mut pending: VecDeque<TupleOperation> = operations.into();
+        let mut retries: VecDeque<(Vec<TupleOperation>, u32)> = VecDeque::new();
+
+        while !pending.is_empty() || !retries.is_empty() {
+            let mut round = Vec::with_capacity(limits.concurrency);
+            while round.len() < limits.concurrency {
+                if let Some(retry) = retries.pop_front() {
+                    round.push(retry);
+                } else if !pending.is_empty() {
+                    let take = limits.batch_size.min(pending.len());
+                    round.push((pending.drain(..take).collect(), 0));
+                } else {
+                    break;
+                }
+            }
+
+            let results = join_all(round.iter().map(|(batch, _)| async move {
+                let batch_started = Instant::now();
+                let result = self.writer.write(batch).await;
+                (result, batch_started.elapsed())
+            }))
+            .await;
+
+            let mut throttled = false;
+            let mut slow = false;
+            let mut backoff = Duration::ZERO;
+
+            for ((batch, attempt), (result, latency)) in round.into_iter().zip(results) {
+                report.batches += 1;
+                slow |= latency > self.config.target_latency;
+
+                match result {
+                    Ok(()) => self.record_applied(&mut report, &batch),
+                    Err(WriteError::RateLimited { retry_after }) => {
+                        throttled = true;
+                        report.throttled += 1;
+                        metrics::metrics()
+                            .bulk_write_throttled_total
+                            .with_label_values(&[&self.pipeline])
+                            .inc();
+
+                        if attempt + 1 > self.config.max_retries {
+                            let error = WriteError::RateLimited { retry_after };
+                            report
+                                .failed
+                                .extend(batch.into_iter().map(|op| (op, error.clone())));
+                        } else {
+                            let wait = retry_after.unwrap_or(
+                                self.config.base_backoff * 2_u32.saturating_pow(attempt),
+                            );
+                            backoff = backoff.max(wait);
+                            retries.push_back((batch, attempt + 1));
+                        }
+                    }
+                    Err(error) => {
+                        warn!(
+                            pipeline = %self.pipeline,
+                            batch_size = batch.len(),
+                            error = %error,
+                            "Bulk write batch failed"
+                        );
+                        report
+                            .failed
+                            .extend(batch.into_iter().map(|op| (op, error.clone())));
+                    }
+                }
+            }
+
+            if throttled {
+                limits.on_throttled(&self.config);
+            } else if slow {
+                limits.on_slow(&self.config);
+            } else {
+                limits.on_healthy(&self.config);
+            }
+
+            debug!(
+                pipeline = %self.pipeline,
+                batch_size = limits.batch_size,
+                concurrency = limits.concurrency,
+                throttled = throttled,
+                "Adjusted bulk write limits"
+            );
+            let metrics = metrics::metrics();
+            metrics
+                .bulk_write_batch_size
+                .with_label_values(&[&self.pipeline])
+                .set(limits.batch_size as i64);
+            metrics
+                .bulk_write_concurrency
+                .with_label_values(&[&self.pipeline])
+                .set(limits.concurrency as i64);
+
+            if !backoff.is_zero() {
+                tokio::time::sleep(backoff).await;
+            }
+        }
+
+        let elapsed = started.elapsed().as_secs_f64();
+        if elapsed > 0.0 {
+            metrics::metrics()
+                .bulk_write_tuples_per_second
+                .with_label_values(&[&self.pipeline])
+                .set((report.written + report.deleted) as f64 / elapsed);
+        }
+
+        *self.limits.lock().unwrap_or_else(|e| e.into_inner()) = limits;
+        report
+    }
+
+    /// Like [`BulkWriter::apply`], in steps of `step` operations, passing the
+    /// report so far to `on_progress` after each step. Limits carry over from
+    /// one step to the next, so reporting progress does not slow the writes down.
+    pub async fn apply_with_progress<F, Fut>(
+        &self,
+        operations: Vec<TupleOperation>,
+        step: usize,
+        mut on_progress: F,
+    ) -> BulkWriteReport
+    where
+        F: FnMut(&BulkWriteReport) -> Fut,
+        Fut: Future<Output = ()>,
+    {
+        let total = operations.len();
+        let mut report = BulkWriteReport::default();
+        for chunk in operations.chunks(step.max(1)) {
+            report.merge(self.apply(chunk.to_vec()).await);
+            debug!(
+                event = "bulk_write_progress",
+                pipeline = %self.pipeline,
+                applied = report.written + report.deleted,
+                failed = report.failed.len(),
+                total = total,
+                "Bulk write progress"
+            );
+            on_progress(&report).await;
+        }
+        report
+    }
+
+    /// Like [`BulkWriter::apply_with_progress`], but splits the operations into fixed
+    /// segments keyed by [`batch_key`] in `scope` and skips segments the ledger has
+    /// already seen. Of the other segments only operations `pending` accepts are sent;
+    /// a segment is recorded once every one of those succeeded. `on_progress` gets
+    /// the ledger too, so callers can persist it as they go.
+    pub async fn apply_once<F, Fut>(
+        &self,
+        scope: &str,
+        operations: Vec<TupleOperation>,
+        ledger: &mut TupleBatchLedger,
+        pending: impl Fn(&TupleOperation) -> bool,
+        step: usize,
+        mut on_progress: F,
+    ) -> BulkWriteReport
+    where
+        F: FnMut(&BulkWriteReport, &TupleBatchLedger) -> Fut,
+        Fut: Future<Output = ()>,
+    {
+        let segments: Vec<&[TupleOperation]> = operations.chunks(MAX_TUPLES_PER_WRITE).collect();
+        let per_step = step.div_ceil(MAX_TUPLES_PER_WRITE).max(1);
+        let mut report = BulkWriteReport::default();
+        for group in segments.chunks(per_step) {
+            let mut keyed = Vec::new();
+            let mut remaining = Vec::new();
+            for segment in group {
+                let key = batch_key(scope, segment);
+                if ledger.contains(&key) {
+                    report.skipped += segment.len();
+                    continue;
+                }
+                let ops: Vec<TupleOperation> =
+                    segment.iter().filter(|op| pending(op)).cloned().collect();
+                report.skipped += segment.len() - ops.len();
+                remaining.extend(ops.iter().cloned());
+                keyed.push((key, ops));
+            }
+
+            let applied = self.apply(remaining).await;
+            let failed: HashSet<&TupleOperation> =
+                applied.failed.iter().map(|(op, _)| op).collect();
+            for (key, ops) in keyed {
+                if ops.iter().all(|op| !failed.contains(op)) {
+                    ledger.record(key);
+                }
+            }
+            report.merge(applied);
+            on_progress(&report, ledger).await;
+        }
+        report
+    }
+
+    fn record_applied(&self, report: &mut BulkWriteReport, batch: &[TupleOperation]) {
+        let writes = batch
+            .iter()
+            .filter(|op| matches!(op, TupleOperation::Write(_)))
+            .count();
+        let deletes = batch.len() - writes;
+        report.written += writes;
+        report.deleted += deletes;
+
+        let tuples = &metrics::metrics().bulk_write_tuples_total;
+        tuples
+            .with_label_values(&[&self.pipeline, "write"])
+            .inc_by(writes as u64);
+        tuples
+            .with_label_values(&[&self.pipeline, "delete"])
+            .inc_by(deletes as u64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tuples::TupleKey;
+    use std::sync::Mutex;
+
+    struct RecordingWriter {
+        batches: Mutex<Vec<usize>>,
+        throttle_first: Mutex<usize>,
+    }
+
+    impl RecordingWriter {
+        fn new(throttle_first: usize) -> Self {
+            Self {
+                batches: Mutex::new(vec![]),
+                throttle_first: Mutex::new(throttle_first),
+            }
+        }
+    }
+
+    impl TupleWriter for &RecordingWriter {
+        async fn write(&self, batch: &[TupleOperation]) -> Result<(), WriteError> {
+            let mut throttle = self.throttle_first.lock().unwrap();
+            if *throttle > 0 {
+                *throttle -= 1;
+                return Err(WriteError::RateLimited {
+                    retry_after: Some(Duration::from_millis(1)),
+                });
+            }
+            self.batches.lock().unwrap().push(batch.len());
+            Ok(())
+        }
+    }
+
+    fn operations(count: usize) -> Vec<TupleOperation> {
+        (0..count)
+            .map(|i| {
+                TupleOperation::Write(TupleKey::new(&format!("user:{}", i), "viewer", "doc:1"))
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_batches_respect_write_limit() {
+        let writer = RecordingWriter::new(0);
+        let bulk = BulkWriter::new(&writer, BulkWriterConfig::default(), "test");
+
+        let report = bulk.apply(operations(450)).await;
+
+        assert!(report.is_complete());
+        assert_eq!(report.written, 450);
+        let batches = writer.batches.lock().unwrap();
+        assert!(batches.iter().all(|size| *size <= MAX_TUPLES_PER_WRITE));
+        assert_eq!(batches.iter().sum::<usize>(), 450);
+    }
+
+    #[tokio::test]
+    async fn test_throttled_batches_are_retried() {
+        let writer = RecordingWriter::new(2);
+        let bulk = BulkWriter::new(&writer, BulkWriterConfig::default(), "test");
+
+        let report = bulk.apply(operations(150)).await;
+
+        assert!(report.is_complete());
+        assert_eq!(report.written, 150);
+        assert_eq!(report.throttled, 2);
+    }
+
+    #[tokio::test]
+    async fn test_exhausted_retries_are_reported() {
+        let writer = RecordingWriter::new(usize::MAX);
+        let config = BulkWriterConfig {
+            max_retries: 1,
+            ..Default::default()
+        };
+        let bulk = BulkWriter::new(&writer, config, "test");
+
+        let report = bulk.apply(operations(3)).await;
+
+        assert_eq!(report.written, 0);
+        assert_eq!(report.failed.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_apply_with_progress() {
+        let writer = RecordingWriter::new(1);
+        let bulk = BulkWriter::new(&writer, BulkWriterConfig::default(), "test");
+
+        let mut seen = vec![];
+        let report = bulk
+            .apply_with_progress(operations(250), 100, |report| {
+                seen.push(report.written);
+                async {}
+            })
+            .await;
+
+        assert_eq!(report.written, 250);
+        assert_eq!(report.throttled, 1);
+        assert_eq!(seen, vec![100, 200, 250]);
+    }
+
+    #[tokio::test]
+    async fn test_limits_carry_over_between_calls() {
+        let writer = RecordingWriter::new(1);
+        let bulk = BulkWriter::new(&writer, BulkWriterConfig::default(), "test");
+
+        bulk.apply(operations(100)).await;
+        // The throttled first round halved the batch size, the retry grew it again
+        let limits = bulk.limits.lock().unwrap().clone();
+        assert_eq!(limits.batch_size, 63);
+        assert_eq!(limits.concurrency, 2);
+
+        writer.batches.lock().unwrap().clear();
+        bulk.apply(operations(63 * 2)).await;
+        assert_eq!(*writer.batches.lock().unwrap(), vec![63, 63]);
+    }
+
+    async fn apply_once(
+        bulk: &BulkWriter<&RecordingWriter>,
+        scope: &str,
+        operations: Vec<TupleOperation>,
+        ledger: &mut TupleBatchLedger,
+    ) -> BulkWriteReport {
+        bulk.apply_once(scope, operations, ledger, |_| true, 1000, |_, _| async {})
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_apply_once_skips_recorded_batches() {
+        let writer = RecordingWriter::new(0);
+        let bulk = BulkWriter::new(&writer, BulkWriterConfig::default(), "test");
+        let mut ledger = TupleBatchLedger::default();
+
+        let all = operations(250);
+        let first = apply_once(&bulk, "store/1", all[..200].to_vec(), &mut ledger).await;
+        assert_eq!(first.written, 200);
+        assert_eq!(ledger.to_status().len(), 2);
+
+        let mut ledger = TupleBatchLedger::from_status(Some(&ledger.to_status()));
+        let second = apply_once(&bulk, "store/1", all.clone(), &mut ledger).await;
+        assert_eq!(second.skipped, 200);
+        assert_eq!(second.written, 50);
+        assert_eq!(ledger.to_status().len(), 3);
+
+        // Another store or run writes the same tuples anew
+        let other = apply_once(&bulk, "store/2", all, &mut ledger).await;
+        assert_eq!(other.skipped, 0);
+        assert_eq!(other.written, 250);
+    }
+
+    #[tokio::test]
+    async fn test_apply_once_sends_only_pending_operations() {
+        let writer = RecordingWriter::new(0);
+        let bulk = BulkWriter::new(&writer, BulkWriterConfig::default(), "test");
+        let mut ledger = TupleBatchLedger::default();
+        let all = operations(150);
+        let present: HashSet<TupleOperation> = all[..120].iter().cloned().collect();
+
+        let mut seen = vec![];
+        let report = bulk
+            .apply_once(
+                "store/1",
+                all,
+                &mut ledger,
+                |op| !present.contains(op),
+                100,
+                |report, ledger| {
+                    seen.push((report.written, ledger.to_status().len()));
+                    async {}
+                },
+            )
+            .await;
+        assert_eq!(report.written, 30);
+        assert_eq!(report.skipped, 120);
+        // Both segments are recorded, the first without writing anything
+        assert_eq!(seen, vec![(0, 1), (30, 2)]);
+    }
+
+    #[tokio::test]
+    async fn test_apply_once_does_not_record_failed_batches() {
+        let writer = RecordingWriter::new(usize::MAX);
+        let config = BulkWriterConfig {
+            max_retries: 0,
+            ..Default::default()
+        };
+        let bulk = BulkWriter::new(&writer, config, "test");
+        let mut ledger = TupleBatchLedger::default();
+
+        let report = apply_once(&bulk, "store/1", operations(10), &mut ledger).await;
+        assert_eq!(report.failed.len(), 10);
+        assert!(ledger.to_status().is_empty());
+    }
+
+    #[test]
+    fn test_batch_key_is_stable_and_order_sensitive() {
+        let ops = operations(3);
+        assert_eq!(batch_key("s", &ops), batch_key("s", &operations(3)));
+        assert_ne!(batch_key("s", &ops), batch_key("t", &ops));
+
+        let mut reversed = ops.clone();
+        reversed.reverse();
+        assert_ne!(batch_key("s", &ops), batch_key("s", &reversed));
+
+        let delete = vec![TupleOperation::Delete(ops[0].tuple().clone())];
+        assert_ne!(batch_key("s", &ops[..1]), batch_key("s", &delete));
+    }
+
+    #[test]
+    fn test_ledger_is_bounded() {
+        let mut ledger = TupleBatchLedger::default();
+        for i in 0..MAX_LEDGER_ENTRIES + 5 {
+            ledger.record(i.to_string());
+        }
+        ledger.record(MAX_LEDGER_ENTRIES.to_string());
+        assert_eq!(ledger.to_status().len(), MAX_LEDGER_ENTRIES);
+        assert!(!ledger.contains("4"));
+        assert!(ledger.contains("5"));
+    }
+
+    #[test]
+    fn test_adaptive_limits() {
+        let config = BulkWriterConfig::default();
+        let mut limits = AdaptiveLimits::new(&config);
+        assert_eq!(limits.batch_size, MAX_TUPLES_PER_WRITE);
+
+        limits.on_healthy(&config);
+        assert_eq!(limits.concurrency, 2);
+        assert_eq!(limits.batch_size, MAX_TUPLES_PER_WRITE);
+
+        limits.on_throttled(&config);
+        assert_eq!(
+            limits,
+            AdaptiveLimits {
+                batch_size: 50,
+                concurrency: 1
+            }
+        );
+
+        limits.on_slow(&config);
+        assert_eq!(limits.batch_size, 37);
+    }
+}
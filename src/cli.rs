@@ -0,0 +1,183 @@
+//! Offline subcommands for working with authorization model files, e.g.
+//! `openfga-operator model validate model.fga`.
+
+use crate::model::{self, LintConfig, LintSeverity, ModelFormat};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+const USAGE: &str = "\
+Usage:
+  openfga-operator model validate <file>
+  openfga-operator model lint <file> [--rule <rule>=<off|info|warning|error>]...
+  openfga-operator model convert <file> [--to dsl|json]
+
+Files ending in .json are read as JSON, anything else as the OpenFGA DSL.";
+
+/// Returns true when the arguments select a CLI subcommand rather than the operator.
+pub fn is_cli_invocation(args: &[String]) -> bool {
+    args.get(1).is_some_and(|arg| arg == "model")
+}
+
+/// Runs a CLI subcommand, returning the process exit code.
+pub fn run(args: &[String]) -> i32 {
+    match dispatch(&args[1..]) {
+        Ok(output) => {
+            print!("{}", output);
+            0
+        }
+        Err(message) => {
+            eprintln!("{}", message);
+            1
+        }
+    }
+}
+
+fn dispatch(args: &[String]) -> Result<String, String> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args.as_slice() {
+        ["model", "validate", path] => validate(Path::new(path)),
+        ["model", "lint", path, rules @ ..] => lint(Path::new(path), rules),
+        ["model", "convert", path] => convert(Path::new(path), None),
+        ["model", "convert", path, "--to", target] => convert(Path::new(path), Some(target)),
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+fn validate(path: &Path) -> Result<String, String> {
+    let (model, _) = model::load_file(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    model::validate(&model).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(format!("{}: model is valid\n", path.display()))
+}
+
+fn lint(path: &Path, rule_args: &[&str]) -> Result<String, String> {
+    let mut overrides = BTreeMap::new();
+    let mut rest = rule_args.iter();
+    while let Some(arg) = rest.next() {
+        let value = match (*arg, rest.next()) {
+            ("--rule", Some(value)) => value,
+            _ => return Err(USAGE.to_string()),
+        };
+        let (rule, severity) = value
+            .split_once('=')
+            .ok_or_else(|| format!("expected <rule>=<severity>, got '{}'", value))?;
+        overrides.insert(rule.to_string(), severity.parse::<LintSeverity>()?);
+    }
+    let config = LintConfig::new(overrides)?;
+
+    let (model, _) = model::load_file(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    model::validate(&model).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+    let findings = model::lint(&model, &config);
+    let report: String = findings
+        .iter()
+        .map(|f| format!("{}: {}\n", path.display(), f))
+        .collect();
+
+    if findings.iter().any(|f| f.severity == LintSeverity::Error) {
+        Err(report.trim_end().to_string())
+    } else if findings.is_empty() {
+        Ok(format!("{}: no lint findings\n", path.display()))
+    } else {
+        Ok(report)
+    }
+}
+
+fn convert(path: &Path, target: Option<&str>) -> Result<String, String> {
+    let (model, source_format) =
+        model::load_file(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+    let target = match target {
+        None => match source_format {
+            ModelFormat::Dsl => ModelFormat::Json,
+            ModelFormat::Json => ModelFormat::Dsl,
+        },
+        Some("json") => ModelFormat::Json,
+        Some("dsl") => ModelFormat::Dsl,
+        Some(other) => return Err(format!("unknown target format '{}'\n\n{}", other, USAGE)),
+    };
+
+    match target {
+        ModelFormat::Dsl => Ok(model::to_dsl(&model)),
+        ModelFormat::Json => serde_json::to_string_pretty(&model::to_json(&model))
+            .map(|json| json + "\n")
+            .map_err(|e| e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_cli_detection() {
+        assert!(is_cli_invocation(&args(&[
+            "openfga-operator",
+            "model",
+            "validate"
+        ])));
+        assert!(!is_cli_invocation(&args(&["openfga-operator"])));
+    }
+
+    #[test]
+    fn test_validate_and_convert_files() {
+        let dir = std::env::temp_dir().join(format!("openfga-cli-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let dsl_path = dir.join("model.fga");
+        std::fs::write(
+            &dsl_path,
+            "model\n  schema 1.1\ntype user\ntype doc\n  relations\n    define viewer: [user]\n",
+        )
+        .unwrap();
+
+        let path = dsl_path.to_str().unwrap();
+        assert!(dispatch(&args(&["model", "validate", path])).is_ok());
+
+        let json = dispatch(&args(&["model", "convert", path])).unwrap();
+        assert!(json.contains("\"schema_version\": \"1.1\""));
+
+        let json_path = dir.join("model.json");
+        std::fs::write(&json_path, &json).unwrap();
+        let dsl = dispatch(&args(&["model", "convert", json_path.to_str().unwrap()])).unwrap();
+        assert!(dsl.contains("define viewer: [user]"));
+
+        std::fs::write(
+            &dsl_path,
+            "model\n  schema 1.1\ntype doc\n  relations\n    define viewer: [user]\n",
+        )
+        .unwrap();
+        let err = dispatch(&args(&["model", "validate", path])).unwrap_err();
+        assert!(err.contains("type 'user' is not defined"));
+
+        assert!(dispatch(&args(&["model", "convert", path, "--to", "yaml"])).is_err());
+
+        std::fs::write(
+            &dsl_path,
+            "model\n  schema 1.1\ntype user\ntype doc\n  relations\n    define viewer: [user:*]\n",
+        )
+        .unwrap();
+        let warnings = dispatch(&args(&["model", "lint", path])).unwrap();
+        assert!(warnings.contains("[warning] public-wildcard doc#viewer"));
+        let err = dispatch(&args(&[
+            "model",
+            "lint",
+            path,
+            "--rule",
+            "public-wildcard=error",
+        ]))
+        .unwrap_err();
+        assert!(err.contains("[error] public-wildcard"));
+        assert!(dispatch(&args(&[
+            "model",
+            "lint",
+            path,
+            "--rule",
+            "public-wildcard=loud"
+        ]))
+        .is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,315 @@
+//! Shared transport of the operator's OpenFGA API clients. Every client sends
+//! through one pooled hyper client, so connections to an instance are reused
+//! across reconciles, and under one policy: a timeout per attempt, retries with
+//! jittered exponential backoff for failures that are safe to repeat, and a
+//! circuit breaker per server. A server that keeps failing is skipped for a
+//! cooldown instead of every reconcile of its stores and models waiting out the
+//! timeout and holding up the rest of the queue.
+//!
+//! Instances reached over gRPC get one HTTP/2 channel per server, created on
+//! first use. `https` servers are verified against the system roots and the
+//! PEM bundle in `OPENFGA_CLIENT_GRPC_CA_FILE`, if set.
+
+use crate::backoff::{exponential, jitter};
+use crate::openfga_client::{ClientError, ClientResult};
+use hyper::client::HttpConnector;
+use hyper::Client;
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tonic::transport::{Certificate, Channel, ClientTlsConfig, Endpoint};
+
+const TIMEOUT_ENV: &str = "OPENFGA_CLIENT_TIMEOUT_SECONDS";
+const MAX_RETRIES_ENV: &str = "OPENFGA_CLIENT_MAX_RETRIES";
+const FAILURE_THRESHOLD_ENV: &str = "OPENFGA_CLIENT_CIRCUIT_FAILURE_THRESHOLD";
+const COOLDOWN_ENV: &str = "OPENFGA_CLIENT_CIRCUIT_COOLDOWN_SECONDS";
+const GRPC_CA_FILE_ENV: &str = "OPENFGA_CLIENT_GRPC_CA_FILE";
+
+/// Upper bound on the wait between two attempts of one request.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientPolicy {
+    /// Upper bound on one attempt, from sending the request to reading the body.
+    pub timeout: Duration,
+    /// Attempts after the first for a retryable failure.
+    pub max_retries: u32,
+    /// Wait before the first retry, doubled for each further one.
+    pub retry_base: Duration,
+    /// Consecutive server failures that open a server's circuit.
+    pub failure_threshold: u32,
+    /// How long an open circuit rejects requests before letting one through.
+    pub cooldown: Duration,
+}
+
+impl Default for ClientPolicy {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(10),
+            max_retries: 2,
+            retry_base: Duration::from_millis(200),
+            failure_threshold: 5,
+            cooldown: Duration::from_secs(30),
+        }
+    }
+}
+
+impl ClientPolicy {
+    /// Defaults, overridden by any valid `OPENFGA_CLIENT_*` variables.
+    pub fn from_env() -> Self {
+        Self::from_lookup(|name| std::env::var(name).ok())
+    }
+
+    fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let defaults = Self::default();
+        let parse = |name: &str| lookup(name).and_then(|v| v.trim().parse::<u64>().ok());
+        Self {
+            timeout: parse(TIMEOUT_ENV)
+                .filter(|&n| n > 0)
+                .map_or(defaults.timeout, Duration::from_secs),
+            max_retries: parse(MAX_RETRIES_ENV).map_or(defaults.max_retries, |n| n as u32),
+            retry_base: defaults.retry_base,
+            failure_threshold: parse(FAILURE_THRESHOLD_ENV)
+                .filter(|&n| n > 0)
+                .map_or(defaults.failure_threshold, |n| n as u32),
+            cooldown: parse(COOLDOWN_ENV)
+                .filter(|&n| n > 0)
+                .map_or(defaults.cooldown, Duration::from_secs),
+        }
+    }
+
+    /// Wait before retry `attempt` (1-based) of a request to `url`.
+    pub fn retry_delay(&self, url: &str, attempt: u32) -> Duration {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write(url.as_bytes());
+        hasher.write_u32(attempt);
+        jitter(
+            exponential(self.retry_base, attempt, MAX_RETRY_DELAY),
+            hasher.finish(),
+        )
+    }
+}
+
+#[derive(Debug, Default)]
+struct BreakerState {
+    failures: u32,
+    open_until: Option<Instant>,
+}
+
+/// Consecutive-failure circuit breaker of one server. Once the cooldown is
+/// over, requests are let through again; the failure count is kept until one
+/// succeeds, so the next failure reopens the circuit straight away.
+#[derive(Debug, Default)]
+pub struct CircuitBreaker {
+    state: Mutex<BreakerState>,
+}
+
+impl CircuitBreaker {
+    /// Ok when a request may be sent, otherwise the rest of the cooldown.
+    pub fn allow(&self, now: Instant) -> Result<(), Duration> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        match state.open_until {
+            Some(until) if until > now => Err(until - now),
+            Some(_) => {
+                state.open_until = None;
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Records a server failure; returns true when it opened the circuit.
+    pub fn record_failure(&self, now: Instant, policy: &ClientPolicy) -> bool {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.failures = state.failures.saturating_add(1);
+        if state.failures >= policy.failure_threshold && state.open_until.is_none() {
+            state.open_until = Some(now + policy.cooldown);
+            return true;
+        }
+        false
+    }
+
+    pub fn record_success(&self) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.failures = 0;
+        state.open_until = None;
+    }
+}
+
+/// The pooled hyper client, the gRPC channels and breakers by server URL and
+/// the policy.
+pub struct ClientPool {
+    http: Client<HttpConnector>,
+    channels: Mutex<HashMap<String, Channel>>,
+    policy: ClientPolicy,
+    breakers: Mutex<HashMap<String, Arc<CircuitBreaker>>>,
+}
+
+impl ClientPool {
+    pub fn new(policy: ClientPolicy) -> Self {
+        Self {
+            http: Client::builder()
+                .pool_idle_timeout(Duration::from_secs(90))
+                .pool_max_idle_per_host(8)
+                .build_http(),
+            channels: Mutex::new(HashMap::new()),
+            policy,
+            breakers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// A handle on the shared connection pool.
+    pub fn http(&self) -> Client<HttpConnector> {
+        self.http.clone()
+    }
+
+    pub fn policy(&self) -> ClientPolicy {
+        self.policy
+    }
+
+    /// The gRPC channel to the server at `url`, connecting lazily on the
+    /// first call. Must be called within the Tokio runtime.
+    pub fn channel(&self, url: &str) -> ClientResult<Channel> {
+        let mut channels = self.channels.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(channel) = channels.get(url) {
+            return Ok(channel.clone());
+        }
+        let mut endpoint = Endpoint::from_shared(url.to_string())?
+            .connect_timeout(self.policy.timeout)
+            .tcp_keepalive(Some(Duration::from_secs(60)))
+            .http2_keep_alive_interval(Duration::from_secs(30))
+            .keep_alive_while_idle(true);
+        if url.starts_with("https://") {
+            endpoint =
+                endpoint.tls_config(grpc_tls(std::env::var(GRPC_CA_FILE_ENV).ok().as_deref())?)?;
+        }
+        let channel = endpoint.connect_lazy();
+        channels.insert(url.to_string(), channel.clone());
+        Ok(channel)
+    }
+
+    /// The breaker of the server at `base_url`, shared by all its clients.
+    pub fn breaker(&self, base_url: &str) -> Arc<CircuitBreaker> {
+        self.breakers
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(base_url.to_string())
+            .or_default()
+            .clone()
+    }
+}
+
+/// TLS settings of gRPC channels, trusting the CA bundle at `ca_file` besides
+/// the system roots.
+fn grpc_tls(ca_file: Option<&str>) -> ClientResult<ClientTlsConfig> {
+    let tls = ClientTlsConfig::new();
+    let Some(path) = ca_file.filter(|path| !path.is_empty()) else {
+        return Ok(tls);
+    };
+    let pem = std::fs::read(path).map_err(|e| {
+        ClientError::Config(format!(
+            "cannot read {} '{}': {}",
+            GRPC_CA_FILE_ENV, path, e
+        ))
+    })?;
+    Ok(tls.ca_certificate(Certificate::from_pem(pem)))
+}
+
+/// The operator-wide pool, configured from the environment on first use.
+pub fn pool() -> &'static ClientPool {
+    static POOL: OnceLock<ClientPool> = OnceLock::new();
+    POOL.get_or_init(|| ClientPool::new(ClientPolicy::from_env()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_from_lookup() {
+        let policy = ClientPolicy::from_lookup(|name| match name {
+            TIMEOUT_ENV => Some("3".to_string()),
+            MAX_RETRIES_ENV => Some("0".to_string()),
+            FAILURE_THRESHOLD_ENV => Some("0".to_string()),
+            COOLDOWN_ENV => Some("nope".to_string()),
+            _ => None,
+        });
+        assert_eq!(policy.timeout, Duration::from_secs(3));
+        assert_eq!(policy.max_retries, 0);
+        assert_eq!(policy.failure_threshold, 5);
+        assert_eq!(policy.cooldown, Duration::from_secs(30));
+        assert_eq!(ClientPolicy::from_lookup(|_| None), ClientPolicy::default());
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        let policy = ClientPolicy::default();
+        for attempt in 1..=3 {
+            let full = exponential(policy.retry_base, attempt, MAX_RETRY_DELAY);
+            let delay = policy.retry_delay("http://authz.auth.svc:8080", attempt);
+            assert!(delay >= full / 2 && delay <= full, "{:?}", delay);
+        }
+        assert!(policy.retry_delay("http://a", 20) <= MAX_RETRY_DELAY);
+    }
+
+    #[test]
+    fn test_circuit_breaker() {
+        let policy = ClientPolicy {
+            failure_threshold: 2,
+            cooldown: Duration::from_secs(30),
+            ..ClientPolicy::default()
+        };
+        let breaker = CircuitBreaker::default();
+        let now = Instant::now();
+
+        assert!(!breaker.record_failure(now, &policy));
+        assert!(breaker.allow(now).is_ok());
+        assert!(breaker.record_failure(now, &policy));
+        assert_eq!(
+            breaker.allow(now + Duration::from_secs(10)),
+            Err(Duration::from_secs(20))
+        );
+
+        // After the cooldown one failure reopens the circuit
+        let later = now + Duration::from_secs(31);
+        assert!(breaker.allow(later).is_ok());
+        assert!(breaker.record_failure(later, &policy));
+        assert!(breaker.allow(later).is_err());
+
+        // A success closes it
+        breaker.record_success();
+        assert!(breaker.allow(later).is_ok());
+        assert!(!breaker.record_failure(later, &policy));
+    }
+
+    #[test]
+    fn test_breakers_are_shared_per_server() {
+        let pool = ClientPool::new(ClientPolicy::default());
+        let a = pool.breaker("http://a.ns.svc:8080");
+        assert!(Arc::ptr_eq(&a, &pool.breaker("http://a.ns.svc:8080")));
+        assert!(!Arc::ptr_eq(&a, &pool.breaker("http://b.ns.svc:8080")));
+    }
+
+    #[tokio::test]
+    async fn test_grpc_channels() {
+        let pool = ClientPool::new(ClientPolicy::default());
+        assert!(pool.channel("http://authz.auth.svc:8081").is_ok());
+        assert!(pool.channel("https://authz.auth.svc:8081").is_ok());
+        assert_eq!(pool.channels.lock().unwrap().len(), 2);
+        assert!(pool.channel("http://authz.auth.svc:8081").is_ok());
+        assert_eq!(pool.channels.lock().unwrap().len(), 2);
+        assert!(matches!(
+            pool.channel("not a url"),
+            Err(ClientError::Transport(_))
+        ));
+
+        assert!(grpc_tls(None).is_ok());
+        assert!(grpc_tls(Some("")).is_ok());
+        assert!(matches!(
+            grpc_tls(Some("/nonexistent/ca.crt")),
+            Err(ClientError::Config(message)) if message.contains("/nonexistent/ca.crt")
+        ));
+    }
+}
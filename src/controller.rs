@@ -1,21 +1,76 @@
-use crate::types::{OpenFGA, OpenFGAStatus};
+use crate::access_request::OpenFGAAccessRequestController;
+use crate::access_tokens;
+use crate::advisory::{self, SupportStatus, VersionAdvice};
+use crate::apply;
+use crate::backoff::{self, Backoff};
+use crate::backup_controller::OpenFGABackupController;
+use crate::bootstrap;
+use crate::conversion;
+use crate::debug_state;
+use crate::deletion;
+use crate::dependencies;
+use crate::drift;
+use crate::dry_run;
+use crate::history;
+use crate::image_digest;
+use crate::image_verification;
+use crate::ingress;
+use crate::labels;
+use crate::metrics;
+use crate::model_controller::AuthorizationModelController;
+use crate::monitoring;
+use crate::openfga_client::OpenFGAClient;
+use crate::operator_config::{OperatorConfig, OperatorMode};
+use crate::overrides;
+use crate::panic_isolation::isolate_panics;
+use crate::playground;
+use crate::pool_controller::OpenFGAPoolController;
+use crate::pruning;
+use crate::query_cache;
+use crate::reconcile_queue::{self, ReconcileQueue};
+use crate::resource_recommendations;
+use crate::restore_controller::OpenFGARestoreController;
+use crate::rollback;
+use crate::server_config;
+use crate::service_account;
+use crate::store_controller::OpenFGAStoreController;
+use crate::target_cluster;
+use crate::template_hash;
+use crate::topology;
+use crate::types::{
+    CacheVolumeConfig, CacheVolumeMedium, NodePorts, OpenFGA, OpenFGACondition, OpenFGAStatus,
+    ProbeConfig, ResourceQuantities, ServiceType, WorkloadType,
+};
+use crate::upgrade;
+use crate::workload;
 use anyhow::Result;
 use futures::StreamExt;
-use k8s_openapi::api::apps::v1::{Deployment, DeploymentSpec};
+use k8s_openapi::api::apps::v1::{Deployment, DeploymentSpec, StatefulSet};
 use k8s_openapi::api::core::v1::{
-    Container, ContainerPort, PodSpec, PodTemplateSpec, Service, ServicePort, ServiceSpec,
+    Capabilities, ConfigMap, Container, ContainerPort, EmptyDirVolumeSource, EnvFromSource, EnvVar,
+    EnvVarSource, ExecAction, GRPCAction, HTTPGetAction, Lifecycle, LifecycleHandler, Node, Pod,
+    PodSecurityContext, PodSpec, PodTemplateSpec, Probe, ResourceRequirements, SeccompProfile,
+    Secret, SecretKeySelector, SecretVolumeSource, SecurityContext, Service, ServicePort,
+    ServiceSpec, Volume, VolumeMount,
 };
+use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
 use k8s_openapi::apimachinery::pkg::apis::meta::v1::{LabelSelector, ObjectMeta};
 use k8s_openapi::apimachinery::pkg::util::intstr::IntOrString;
-use kube::api::{Api, Patch, PatchParams};
-use kube::runtime::controller::{Action, Controller};
-use kube::runtime::watcher::Config;
-use kube::{Client, ResourceExt};
-use std::collections::BTreeMap;
+use k8s_openapi::NamespaceResourceScope;
+use kube::api::{Api, DeleteParams, ListParams, Patch, PatchParams};
+use kube::runtime::controller::{self, Action, Controller};
+use kube::runtime::reflector::ObjectRef;
+use kube::runtime::watcher::{self, Config};
+use kube::runtime::{predicates, reflector, Predicate, WatchStreamExt};
+use kube::{Client, Resource, ResourceExt};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
 use std::sync::Arc;
 use thiserror::Error;
+use tokio::sync::watch;
 use tokio::time::Duration;
-use tracing::{debug, error, info, instrument, warn};
+use tracing::{debug, error, info, info_span, instrument, warn, Instrument};
 
 #[derive(Error, Debug)]
 pub enum ControllerError {
@@ -23,22 +78,82 @@ pub enum ControllerError {
     Kube(#[from] kube::Error),
     #[error("Serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
+    #[error("Reconcile panicked: {0}")]
+    Panic(String),
+    #[error("Image verification failed: {0}")]
+    ImageVerification(String),
+    #[error("Invalid overrides: {0}")]
+    InvalidOverrides(String),
+    #[error("Target cluster unavailable: {0}")]
+    TargetCluster(String),
+}
+
+impl ControllerError {
+    /// Name of the variant, as error metrics are labelled; see
+    /// [`backoff::classify`] for the finer class retries are timed by.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ControllerError::Kube(_) => "Kube",
+            ControllerError::Serialization(_) => "Serialization",
+            ControllerError::Panic(_) => "Panic",
+            ControllerError::ImageVerification(_) => "ImageVerification",
+            ControllerError::InvalidOverrides(_) => "InvalidOverrides",
+            ControllerError::TargetCluster(_) => "TargetCluster",
+        }
+    }
 }
 
 pub type ControllerResult<T> = std::result::Result<T, ControllerError>;
 
+/// Operator shutdown signal. Once it turns `true` controllers stop starting
+/// reconciles and return when the ones in flight, status writes included, finish.
+pub type Shutdown = watch::Receiver<bool>;
+
+/// Resolves once shutdown has been requested or its sender is gone.
+pub async fn shutdown_requested(mut shutdown: Shutdown) {
+    let _ = shutdown.wait_for(|requested| *requested).await;
+}
+
+const ZONE_LABEL: &str = "topology.kubernetes.io/zone";
+const LEGACY_ZONE_LABEL: &str = "failure-domain.beta.kubernetes.io/zone";
+const CONTROLLER_NAME: &str = "openfga-controller";
+const SERVER_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Annotation that, when set to `"true"`, stops the operator from reconciling an
+/// instance. The existing workload keeps running unchanged.
+pub const PAUSED_ANNOTATION: &str = "openfga.dev/paused";
+
+pub fn is_paused(openfga: &OpenFGA) -> bool {
+    openfga
+        .annotations()
+        .get(PAUSED_ANNOTATION)
+        .is_some_and(|v| v == "true")
+}
+
 pub struct OpenFGAController {
     client: Client,
+    config: OperatorConfig,
+    backoff: Backoff,
+    /// Admission by priority when reconciles are limited.
+    queue: Option<Arc<ReconcileQueue>>,
+    /// Instances triggered and not yet started, across the watched namespaces.
+    backlog: Arc<metrics::ReconcileBacklog>,
 }
 
 impl OpenFGAController {
-    pub fn new(client: Client) -> Self {
-        Self { client }
+    pub fn new(client: Client, config: OperatorConfig) -> Self {
+        Self {
+            queue: (config.max_concurrent_reconciles > 0)
+                .then(|| ReconcileQueue::new(config.max_concurrent_reconciles.into())),
+            client,
+            config,
+            backoff: Backoff::default(),
+            backlog: Arc::new(metrics::ReconcileBacklog::new(CONTROLLER_NAME)),
+        }
     }
 
-    pub async fn run(self) -> Result<()> {
+    pub async fn run(self, shutdown: Shutdown) -> Result<()> {
         let client = self.client.clone();
-        let openfgas: Api<OpenFGA> = Api::all(client.clone());
 
         info!(
             controller = "openfga-controller",
@@ -46,8 +161,8 @@ impl OpenFGAController {
         );
 
         debug!(
-            resources = "OpenFGA",
-            "Controller watching OpenFGA resources only"
+            resources = "OpenFGA,Deployment,StatefulSet,Service",
+            "Controller watching OpenFGA resources and their children"
         );
 
         // Test Kubernetes API connectivity before starting controller
@@ -67,31 +182,191 @@ impl OpenFGAController {
             }
         }
 
-        // Only watch OpenFGA resources, not owned Deployments/Services
-        // The reconcile function will manage owned resources directly
-        Controller::new(openfgas, Config::default().any_semantic())
-            .run(reconcile, error_policy, Arc::new(self))
-            .for_each(|res| async move {
-                match res {
-                    Ok(o) => {
-                        info!(
-                            reconciliation_result = "success",
-                            object = ?o,
-                            "Reconciliation completed successfully"
-                        );
+        let model_controller = AuthorizationModelController::new(client.clone());
+        let store_controller = OpenFGAStoreController::new(client.clone());
+        let pool_controller = OpenFGAPoolController::new(client.clone());
+        let access_request_controller = OpenFGAAccessRequestController::new(client.clone());
+        let backup_controller = OpenFGABackupController::new(client.clone());
+        let restore_controller = OpenFGARestoreController::new(client.clone());
+
+        // Child workloads and Services are watched so edits to them are reverted at once
+        // A second OpenFGA watch re-triggers instances that depend on the changed one
+        let scopes = self.config.watch_scopes();
+        let _watches = metrics::track_watch_streams(CONTROLLER_NAME, 6 * scopes.len() as i64);
+        let ctx = Arc::new(self);
+        let openfga_controller = futures::future::join_all(
+            scopes
+                .into_iter()
+                .map(|namespace| Self::watch(ctx.clone(), namespace, shutdown.clone())),
+        );
+
+        futures::join!(
+            openfga_controller,
+            model_controller.run(shutdown.clone()),
+            store_controller.run(shutdown.clone()),
+            pool_controller.run(shutdown.clone()),
+            access_request_controller.run(shutdown.clone()),
+            backup_controller.run(shutdown.clone()),
+            restore_controller.run(shutdown),
+        );
+
+        Ok(())
+    }
+
+    /// Runs the OpenFGA controller over one namespace, or the whole cluster for `None`.
+    async fn watch(ctx: Arc<Self>, namespace: Option<String>, shutdown: Shutdown) {
+        let client = ctx.client.clone();
+        let scope = namespace.as_deref();
+        let openfgas: Api<OpenFGA> = scoped_api(client.clone(), scope);
+        info!(
+            controller = CONTROLLER_NAME,
+            namespace = namespace.as_deref().unwrap_or("*"),
+            "Watching OpenFGA resources"
+        );
+
+        // Status-only updates and relists of unchanged instances are filtered out;
+        // child watches still trigger so workload readiness reaches the status
+        let (reader, writer) = reflector::store();
+        debug_state::register_cache(scope.unwrap_or("*"), reader.clone());
+        let changes = watcher::watcher(openfgas.clone(), Config::default().any_semantic())
+            .default_backoff()
+            .reflect(writer)
+            .applied_objects()
+            .predicate_filter(spec_change)
+            .inspect({
+                let backlog = ctx.backlog.clone();
+                move |changed| {
+                    if let Ok(openfga) = changed {
+                        backlog.schedule(Some(ObjectRef::from_obj(openfga)));
                     }
-                    Err(e) => {
-                        error!(
-                            reconciliation_result = "error",
-                            error = %e,
-                            "Reconciliation failed"
-                        );
+                }
+            });
+        // Every trigger passes through ctx.backlog, which counts the instances
+        // waiting to start as watch_queue_depth
+        let backlog = ctx.backlog.clone();
+        let deployments = backlog.clone();
+        let services = backlog.clone();
+        let stateful_sets = backlog.clone();
+        let secrets = backlog.clone();
+        let config_maps = backlog.clone();
+        let dependents = backlog.clone();
+        // Concurrency is limited by ctx.queue, which admits by priority instead of
+        // arrival and counts across the watched namespaces
+        let controller = Controller::for_stream(changes, reader);
+        let instances = controller.store();
+        let secret_readers = instances.clone();
+        let config_map_readers = instances.clone();
+        controller
+            .watches(
+                scoped_api::<Deployment>(client.clone(), scope),
+                Config::default().any_semantic(),
+                move |deployment| deployments.schedule(labels::instance_ref(&deployment.metadata)),
+            )
+            .watches(
+                scoped_api::<Service>(client.clone(), scope),
+                Config::default().any_semantic(),
+                move |service| services.schedule(labels::instance_ref(&service.metadata)),
+            )
+            .watches(
+                scoped_api::<StatefulSet>(client.clone(), scope),
+                Config::default().any_semantic(),
+                move |stateful_set| {
+                    stateful_sets.schedule(labels::instance_ref(&stateful_set.metadata))
+                },
+            )
+            // The preshared keys Secret, rewritten by the store controller, and
+            // the Secrets and ConfigMaps the pods read, hashed into the template.
+            // The mappers only read names and labels, so these watch metadata and
+            // keep no Secret data or ConfigMap contents in memory
+            .watches_stream(
+                watcher::metadata_watcher(
+                    scoped_api::<Secret>(client.clone(), scope),
+                    Config::default().any_semantic(),
+                )
+                .touched_objects(),
+                move |secret| {
+                    let name = secret.name_any();
+                    let mut refs = template_hash::referencing(
+                        &secret_readers.state(),
+                        secret.namespace().as_deref(),
+                        |refs| refs.secrets.contains(&name),
+                    );
+                    refs.extend(labels::instance_ref(&secret.metadata));
+                    secrets.schedule(refs)
+                },
+            )
+            .watches_stream(
+                watcher::metadata_watcher(
+                    scoped_api::<ConfigMap>(client.clone(), scope),
+                    Config::default().any_semantic(),
+                )
+                .touched_objects(),
+                move |config_map| {
+                    let name = config_map.name_any();
+                    config_maps.schedule(template_hash::referencing(
+                        &config_map_readers.state(),
+                        config_map.namespace().as_deref(),
+                        |refs| refs.config_maps.contains(&name),
+                    ))
+                },
+            )
+            .watches(openfgas, Config::default().any_semantic(), move |changed| {
+                dependents.schedule(dependencies::dependents(&instances.state(), &changed))
+            })
+            .graceful_shutdown_on(shutdown_requested(shutdown))
+            .run(
+                |openfga, ctx| {
+                    ctx.backlog.started(&ObjectRef::from_obj(openfga.as_ref()));
+                    let key = backoff_key(&openfga);
+                    let priority = reconcile_queue::priority(&openfga, ctx.backoff.failing(&key));
+                    let guarded = isolate_panics(
+                        ctx.client.clone(),
+                        CONTROLLER_NAME,
+                        openfga.clone(),
+                        reconcile(openfga.clone(), ctx.clone()),
+                    );
+                    let observed = metrics::observe_reconcile(CONTROLLER_NAME, openfga, guarded);
+                    async move {
+                        let _permit = match &ctx.queue {
+                            Some(queue) => Some(queue.acquire(priority).await),
+                            None => None,
+                        };
+                        let result = observed.await;
+                        if result.is_ok() {
+                            ctx.backoff.reset(&key);
+                            debug_state::clear_error(&key);
+                        }
+                        result
+                    }
+                },
+                error_policy,
+                ctx,
+            )
+            .for_each(|res| {
+                // Triggers for instances deleted before their reconcile started
+                if let Err(controller::Error::ObjectNotFound(object)) = &res {
+                    backlog.started(object);
+                }
+                async move {
+                    match res {
+                        Ok(o) => {
+                            info!(
+                                reconciliation_result = "success",
+                                object = ?o,
+                                "Reconciliation completed successfully"
+                            );
+                        }
+                        Err(e) => {
+                            error!(
+                                reconciliation_result = "error",
+                                error = %e,
+                                "Reconciliation failed"
+                            );
+                        }
                     }
                 }
             })
             .await;
-
-        Ok(())
     }
 
     async fn test_api_connectivity(&self) -> Result<(), kube::Error> {
@@ -119,7 +394,28 @@ impl OpenFGAController {
     }
 }
 
-#[instrument(skip(ctx), fields(namespace = %openfga.namespace().unwrap_or_default(), name = %openfga.name_any()))]
+fn scoped_api<K>(client: Client, namespace: Option<&str>) -> Api<K>
+where
+    K: Resource<Scope = NamespaceResourceScope>,
+    K::DynamicType: Default,
+{
+    match namespace {
+        Some(ns) => Api::namespaced(client, ns),
+        None => Api::all(client),
+    }
+}
+
+/// Hash of what a reconcile acts on: the spec, through `generation`, plus the
+/// labels and annotations that pause, restart or roll back an instance. Status
+/// writes leave it unchanged.
+fn spec_change(openfga: &OpenFGA) -> Option<u64> {
+    predicates::generation
+        .combine(predicates::labels)
+        .combine(predicates::annotations)
+        .hash_property(openfga)
+}
+
+#[instrument(skip(ctx), fields(namespace = %openfga.namespace().unwrap_or_default(), name = %openfga.name_any(), generation = openfga.metadata.generation.unwrap_or_default()))]
 async fn reconcile(openfga: Arc<OpenFGA>, ctx: Arc<OpenFGAController>) -> ControllerResult<Action> {
     let client = &ctx.client;
     let ns = openfga.namespace().unwrap_or_default();
@@ -134,6 +430,79 @@ async fn reconcile(openfga: Arc<OpenFGA>, ctx: Arc<OpenFGAController>) -> Contro
         "Starting OpenFGA reconciliation"
     );
 
+    if openfga.metadata.deletion_timestamp.is_some() {
+        resource_recommendations::forget(&openfga);
+        let target = match target_cluster::connect(client, &openfga).await {
+            Ok(target) => target,
+            // Without this, deleting the kubeconfig Secret first would keep the instance forever
+            Err(e) if deletion::force_cleanup_due(&openfga, chrono::Utc::now()) => {
+                warn!(
+                    event = "target_cluster_cleanup_skipped",
+                    namespace = %ns,
+                    resource_name = %name,
+                    error = %e,
+                    "Force cleanup requested, leaving the instance's objects in its target cluster behind"
+                );
+                return deletion::finalize(client, None, &openfga).await;
+            }
+            Err(e) => return Err(e),
+        };
+        let cluster = target.as_ref().map_or(client, |t| &t.client);
+        return deletion::finalize(client, Some(cluster), &openfga).await;
+    }
+
+    if is_paused(&openfga) {
+        info!(
+            event = "reconciliation_paused",
+            namespace = %ns,
+            resource_name = %name,
+            "Reconciliation is paused for this instance, skipping"
+        );
+        return Ok(Action::await_change());
+    }
+
+    let (openfga, violations) = playground::apply_policy(openfga, playground::disallowed());
+
+    if ctx.config.mode == OperatorMode::DryRun {
+        return dry_run::reconcile(
+            client,
+            &openfga,
+            &ns,
+            &name,
+            &violations,
+            ctx.config.reconcile_interval,
+        )
+        .await;
+    }
+    playground::report_violations(client, &openfga, &violations).await;
+
+    deletion::sync_finalizer(client, &openfga).await?;
+
+    if let Some(blocker) = dependencies::first_blocker(client, &openfga).await? {
+        dependencies::set_waiting_condition(client, &openfga, &blocker).await?;
+        return Ok(Action::requeue(dependencies::POLL_INTERVAL));
+    }
+
+    // Children are applied to the target cluster when there is one; the
+    // resource's own status, events and finalizers stay with `client`
+    let target = match target_cluster::connect(client, &openfga).await {
+        Ok(target) => target,
+        Err(e) => {
+            error!(
+                event = "target_cluster_unavailable",
+                namespace = %ns,
+                resource_name = %name,
+                error = %e,
+                "Failed to connect to the instance's target cluster"
+            );
+            return Err(e);
+        }
+    };
+    let (cluster, owner): (&Client, &OpenFGA) = match &target {
+        Some(target) => (&target.client, &target.instance),
+        None => (client, &openfga),
+    };
+
     debug!(
         event = "resource_analysis",
         namespace = %ns,
@@ -145,6 +514,30 @@ async fn reconcile(openfga: Arc<OpenFGA>, ctx: Arc<OpenFGAController>) -> Contro
         "Analyzing OpenFGA resource specification"
     );
 
+    // envFrom references to a missing ConfigMap keep the pods from starting
+    if let Err(e) = server_config::reconcile_config_map(cluster, owner, &ns, &name).await {
+        error!(
+            event = "config_map_reconciliation_failed",
+            namespace = %ns,
+            resource_name = %name,
+            error = %e,
+            "Failed to reconcile server configuration"
+        );
+        return Err(e);
+    }
+
+    // The pods cannot be scheduled until their ServiceAccount exists
+    if let Err(e) = service_account::reconcile_service_account(cluster, owner, &ns, &name).await {
+        error!(
+            event = "service_account_reconciliation_failed",
+            namespace = %ns,
+            resource_name = %name,
+            error = %e,
+            "Failed to reconcile service account"
+        );
+        return Err(e);
+    }
+
     // Create or update Deployment
     debug!(
         event = "deployment_reconciliation_start",
@@ -153,87 +546,200 @@ async fn reconcile(openfga: Arc<OpenFGA>, ctx: Arc<OpenFGAController>) -> Contro
         "Starting deployment reconciliation"
     );
 
-    let deployment = create_deployment(&openfga, &ns, &name)?;
-    let deployments: Api<Deployment> = Api::namespaced(client.clone(), &ns);
-
-    match deployments.get(&name).await {
-        Ok(existing_deployment) => {
-            debug!(
-                event = "deployment_exists",
+    let mut deployment = create_deployment(&openfga, &ns, &name)?;
+    let image_digest = image_digest::pinned_image(client, &openfga).await;
+    match &image_digest {
+        Some(Ok(pinned)) => upgrade::set_image(&mut deployment, pinned),
+        Some(Err(e)) => {
+            let previous = image_digest::previously_pinned(&openfga);
+            warn!(
+                event = "image_digest_resolution_failed",
                 namespace = %ns,
                 resource_name = %name,
-                current_replicas = existing_deployment.spec.as_ref().and_then(|s| s.replicas),
-                "Existing deployment found, updating"
+                image = %openfga.spec.image,
+                fallback = ?previous,
+                error = %e,
+                "Failed to resolve image digest"
             );
-
-            match deployments
-                .patch(
-                    &name,
-                    &PatchParams::apply("openfga-operator"),
-                    &Patch::Apply(&deployment),
-                )
-                .await
-            {
-                Ok(_) => {
-                    info!(
-                        event = "deployment_updated",
-                        namespace = %ns,
-                        resource_name = %name,
-                        replicas = openfga.spec.replicas,
-                        "Successfully updated deployment"
-                    );
-                }
-                Err(e) => {
-                    error!(
-                        event = "deployment_update_failed",
-                        namespace = %ns,
-                        resource_name = %name,
-                        error = %e,
-                        "Failed to update deployment"
-                    );
-                    return Err(e.into());
-                }
+            if let Some(previous) = previous {
+                upgrade::set_image(&mut deployment, &previous);
             }
         }
+        None => {}
+    }
+    let keys = access_tokens::sync_keys(client, &openfga).await?;
+    if let Some(keys) = &keys {
+        access_tokens::set_preshared_auth(&mut deployment, &openfga, &name, keys);
+    }
+    if let Some(target) = &target {
+        target_cluster::mirror_keys(target, keys.as_ref()).await?;
+    }
+    let overrides = match overrides::load(client, &openfga).await {
+        Ok(overrides) => overrides,
         Err(e) => {
-            debug!(
-                event = "deployment_not_found",
+            error!(
+                event = "overrides_load_failed",
                 namespace = %ns,
                 resource_name = %name,
                 error = %e,
-                "Deployment not found, creating new deployment"
+                "Failed to load workload overrides"
             );
+            return Err(e);
+        }
+    };
+    if let Some(patch) = &overrides.deployment {
+        debug!(
+            event = "overrides_applied",
+            namespace = %ns,
+            resource_name = %name,
+            kind = "Deployment",
+            fields = %overrides::describe(patch),
+            "Applying workload overrides"
+        );
+        deployment = overrides::apply(&deployment, Some(patch))?;
+    }
+    if let Some(hash) = template_hash::compute(cluster, &ns, &deployment).await? {
+        template_hash::stamp(&mut deployment, hash);
+    }
 
-            match deployments
-                .patch(
-                    &name,
-                    &PatchParams::apply("openfga-operator"),
-                    &Patch::Apply(&deployment),
-                )
-                .await
-            {
-                Ok(_) => {
-                    info!(
-                        event = "deployment_created",
-                        namespace = %ns,
-                        resource_name = %name,
-                        replicas = openfga.spec.replicas,
-                        "Successfully created deployment"
-                    );
+    let image_verification = match &openfga.spec.image_verification {
+        Some(policy) => {
+            let image = upgrade::container_image(&deployment).unwrap_or_default();
+            let result = image_verification::verify(client, &openfga, policy, &image).await;
+            if let Err(e) = &result {
+                error!(
+                    event = "image_verification_failed",
+                    namespace = %ns,
+                    resource_name = %name,
+                    image = %image,
+                    error = %e,
+                    "Image signature verification failed, not deploying"
+                );
+                image_verification::set_failed_condition(client, &openfga, e).await?;
+                return Err(ControllerError::ImageVerification(e.clone()));
+            }
+            Some(result)
+        }
+        None => None,
+    };
+    let deployments: Api<Deployment> = Api::namespaced(cluster.clone(), &ns);
+    let mut canary_requeue = None;
+
+    if openfga.spec.workload_type == WorkloadType::StatefulSet {
+        workload::apply_stateful_set(cluster, owner, &deployment, &ns, &name).await?;
+    } else {
+        match deployments.get(&name).await {
+            Ok(existing_deployment) => {
+                debug!(
+                    event = "deployment_exists",
+                    namespace = %ns,
+                    resource_name = %name,
+                    current_replicas = existing_deployment.spec.as_ref().and_then(|s| s.replicas),
+                    "Existing deployment found, updating"
+                );
+
+                // Both track their progress on the resource next to the workload
+                if target.is_none() {
+                    canary_requeue = upgrade::reconcile_canary(
+                        client,
+                        &openfga,
+                        &mut deployment,
+                        &existing_deployment,
+                        &ns,
+                        &name,
+                    )
+                    .await?;
+                    rollback::reconcile_rollback(
+                        client,
+                        &openfga,
+                        &mut deployment,
+                        &existing_deployment,
+                        &ns,
+                        &name,
+                    )
+                    .await?;
                 }
-                Err(e) => {
-                    error!(
-                        event = "deployment_creation_failed",
+
+                let autoscalers = apply::cede_replicas(&mut deployment, Some(&existing_deployment));
+                if !autoscalers.is_empty() {
+                    debug!(
+                        event = "deployment_replicas_ceded",
                         namespace = %ns,
                         resource_name = %name,
-                        error = %e,
-                        "Failed to create deployment"
+                        managers = ?autoscalers,
+                        "Leaving spec.replicas to the autoscaler that owns it"
                     );
-                    return Err(e.into());
+                }
+
+                let drift = drift::deployment_drift(&deployment, &existing_deployment);
+                if !drift.is_empty() {
+                    drift::report(client, &openfga, "Deployment", &drift).await;
+                }
+
+                match deployments
+                    .patch(&name, &apply::apply_params(), &Patch::Apply(&deployment))
+                    .instrument(info_span!("apply", kind = "Deployment"))
+                    .await
+                {
+                    Ok(_) => {
+                        info!(
+                            event = "deployment_updated",
+                            namespace = %ns,
+                            resource_name = %name,
+                            replicas = openfga.spec.replicas,
+                            "Successfully updated deployment"
+                        );
+                    }
+                    Err(e) => {
+                        error!(
+                            event = "deployment_update_failed",
+                            namespace = %ns,
+                            resource_name = %name,
+                            error = %e,
+                            "Failed to update deployment"
+                        );
+                        return Err(e.into());
+                    }
+                }
+            }
+            Err(e) => {
+                debug!(
+                    event = "deployment_not_found",
+                    namespace = %ns,
+                    resource_name = %name,
+                    error = %e,
+                    "Deployment not found, creating new deployment"
+                );
+
+                match deployments
+                    .patch(&name, &apply::apply_params(), &Patch::Apply(&deployment))
+                    .instrument(info_span!("apply", kind = "Deployment"))
+                    .await
+                {
+                    Ok(_) => {
+                        info!(
+                            event = "deployment_created",
+                            namespace = %ns,
+                            resource_name = %name,
+                            replicas = openfga.spec.replicas,
+                            "Successfully created deployment"
+                        );
+                    }
+                    Err(e) => {
+                        error!(
+                            event = "deployment_creation_failed",
+                            namespace = %ns,
+                            resource_name = %name,
+                            error = %e,
+                            "Failed to create deployment"
+                        );
+                        return Err(e.into());
+                    }
                 }
             }
         }
     }
+    workload::remove_unused(cluster, owner, &ns, &name).await?;
 
     // Create or update Service
     debug!(
@@ -243,8 +749,11 @@ async fn reconcile(openfga: Arc<OpenFGA>, ctx: Arc<OpenFGAController>) -> Contro
         "Starting service reconciliation"
     );
 
-    let service = create_service(&openfga, &ns, &name)?;
-    let services: Api<Service> = Api::namespaced(client.clone(), &ns);
+    let service = overrides::apply(
+        &create_service(&openfga, &ns, &name)?,
+        overrides.service.as_ref(),
+    )?;
+    let services: Api<Service> = Api::namespaced(cluster.clone(), &ns);
 
     match services.get(&name).await {
         Ok(existing_service) => {
@@ -256,15 +765,27 @@ async fn reconcile(openfga: Arc<OpenFGA>, ctx: Arc<OpenFGAController>) -> Contro
                 "Existing service found, updating"
             );
 
+            if requires_recreate(&existing_service, &service) {
+                info!(
+                    event = "service_recreate",
+                    namespace = %ns,
+                    resource_name = %name,
+                    "Recreating service to change its cluster IP mode"
+                );
+                services.delete(&name, &DeleteParams::default()).await?;
+            }
+
+            let drift = drift::service_drift(&service, &existing_service);
+            if !drift.is_empty() {
+                drift::report(client, &openfga, "Service", &drift).await;
+            }
+
             match services
-                .patch(
-                    &name,
-                    &PatchParams::apply("openfga-operator"),
-                    &Patch::Apply(&service),
-                )
+                .patch(&name, &apply::apply_params(), &Patch::Apply(&service))
+                .instrument(info_span!("apply", kind = "Service"))
                 .await
             {
-                Ok(_) => {
+                Ok(applied) => {
                     info!(
                         event = "service_updated",
                         namespace = %ns,
@@ -272,6 +793,7 @@ async fn reconcile(openfga: Arc<OpenFGA>, ctx: Arc<OpenFGAController>) -> Contro
                         ports = ?service.spec.as_ref().and_then(|s| s.ports.as_ref().map(|p| p.len())),
                         "Successfully updated service"
                     );
+                    pruning::prune_service_ports(&services, &service, &applied).await?;
                 }
                 Err(e) => {
                     error!(
@@ -295,11 +817,8 @@ async fn reconcile(openfga: Arc<OpenFGA>, ctx: Arc<OpenFGAController>) -> Contro
             );
 
             match services
-                .patch(
-                    &name,
-                    &PatchParams::apply("openfga-operator"),
-                    &Patch::Apply(&service),
-                )
+                .patch(&name, &apply::apply_params(), &Patch::Apply(&service))
+                .instrument(info_span!("apply", kind = "Service"))
                 .await
             {
                 Ok(_) => {
@@ -325,6 +844,68 @@ async fn reconcile(openfga: Arc<OpenFGA>, ctx: Arc<OpenFGAController>) -> Contro
         }
     }
 
+    // Create or update the read and write shards
+    if let Err(e) =
+        topology::reconcile_topology(cluster, owner, &deployment, &service, &ns, &name).await
+    {
+        error!(
+            event = "topology_reconciliation_failed",
+            namespace = %ns,
+            resource_name = %name,
+            error = %e,
+            "Failed to reconcile read/write shards"
+        );
+        return Err(e);
+    }
+
+    // Create, update or remove the authenticated playground Service
+    if let Err(e) = playground::reconcile_playground(cluster, owner, &ns, &name).await {
+        error!(
+            event = "playground_reconciliation_failed",
+            namespace = %ns,
+            resource_name = %name,
+            error = %e,
+            "Failed to reconcile playground auth proxy"
+        );
+        return Err(e);
+    }
+
+    // Create, update or remove the Ingress / HTTPRoute
+    if let Err(e) = ingress::reconcile_ingress(cluster, owner, &ns, &name).await {
+        error!(
+            event = "ingress_reconciliation_failed",
+            namespace = %ns,
+            resource_name = %name,
+            error = %e,
+            "Failed to reconcile ingress"
+        );
+        return Err(e);
+    }
+
+    // Create, update or remove the ServiceMonitor / PodMonitor
+    if let Err(e) = monitoring::reconcile_monitor(cluster, owner, &ns, &name).await {
+        error!(
+            event = "monitor_reconciliation_failed",
+            namespace = %ns,
+            resource_name = %name,
+            error = %e,
+            "Failed to reconcile Prometheus monitor"
+        );
+        return Err(e);
+    }
+
+    // Delete owned children the spec no longer declares
+    if let Err(e) = pruning::prune_children(cluster, owner, &ns, &name).await {
+        error!(
+            event = "prune_failed",
+            namespace = %ns,
+            resource_name = %name,
+            error = %e,
+            "Failed to prune orphaned child resources"
+        );
+        return Err(e);
+    }
+
     // Update status
     debug!(
         event = "status_update_start",
@@ -333,7 +914,17 @@ async fn reconcile(openfga: Arc<OpenFGA>, ctx: Arc<OpenFGAController>) -> Contro
         "Starting status update"
     );
 
-    match update_status(client, &openfga, &ns, &name).await {
+    match update_status(
+        client,
+        cluster,
+        &openfga,
+        &ns,
+        &name,
+        image_digest.as_ref(),
+        image_verification.as_ref(),
+    )
+    .await
+    {
         Ok(_) => {
             debug!(
                 event = "status_updated",
@@ -354,7 +945,9 @@ async fn reconcile(openfga: Arc<OpenFGA>, ctx: Arc<OpenFGAController>) -> Contro
         }
     }
 
-    let requeue_duration = Duration::from_secs(60);
+    let requeue_duration = canary_requeue.map_or(ctx.config.reconcile_interval, |d| {
+        d.min(ctx.config.reconcile_interval)
+    });
     info!(
         event = "reconciliation_complete",
         namespace = %ns,
@@ -367,7 +960,11 @@ async fn reconcile(openfga: Arc<OpenFGA>, ctx: Arc<OpenFGAController>) -> Contro
 }
 
 #[instrument(skip(openfga), fields(namespace = %ns, name = %name))]
-fn create_deployment(openfga: &OpenFGA, ns: &str, name: &str) -> ControllerResult<Deployment> {
+pub(crate) fn create_deployment(
+    openfga: &OpenFGA,
+    ns: &str,
+    name: &str,
+) -> ControllerResult<Deployment> {
     debug!(
         event = "deployment_creation_start",
         namespace = %ns,
@@ -377,12 +974,8 @@ fn create_deployment(openfga: &OpenFGA, ns: &str, name: &str) -> ControllerResul
         "Creating deployment specification"
     );
 
-    let labels = BTreeMap::from([
-        ("app".to_string(), "openfga".to_string()),
-        ("app.kubernetes.io/name".to_string(), "openfga".to_string()),
-        ("app.kubernetes.io/instance".to_string(), name.to_string()),
-        ("instance".to_string(), name.to_string()),
-    ]);
+    let labels = labels::instance_labels(openfga, name);
+    let selector = labels::selector_labels(name);
 
     let mut container_ports = vec![
         ContainerPort {
@@ -416,34 +1009,103 @@ fn create_deployment(openfga: &OpenFGA, ns: &str, name: &str) -> ControllerResul
         });
     }
 
+    if openfga.spec.observability.metrics.enabled {
+        container_ports.push(ContainerPort {
+            container_port: openfga.spec.observability.metrics.port,
+            name: Some("metrics".to_string()),
+            protocol: Some("TCP".to_string()),
+            ..Default::default()
+        });
+    }
+
     let container = Container {
         name: "openfga".to_string(),
         image: Some(openfga.spec.image.clone()),
+        image_pull_policy: openfga
+            .spec
+            .image_pull_policy
+            .map(|policy| policy.as_str().to_string()),
         ports: Some(container_ports),
-        env: Some(vec![]),
+        args: create_container_args(openfga),
+        env: Some(create_container_env(openfga)),
+        env_from: create_container_env_from(openfga, name),
+        resources: create_resource_requirements(openfga),
+        liveness_probe: create_probe(
+            &openfga.spec.probes.liveness,
+            http_health_probe(openfga),
+            LIVENESS_PROBE_DEFAULTS,
+        ),
+        readiness_probe: create_probe(
+            &openfga.spec.probes.readiness,
+            // The kubelet's gRPC probe cannot speak TLS
+            if tls_enabled(openfga, "GRPC") {
+                http_health_probe(openfga)
+            } else {
+                grpc_health_probe(openfga)
+            },
+            READINESS_PROBE_DEFAULTS,
+        ),
+        startup_probe: create_probe(
+            &openfga.spec.probes.startup,
+            http_health_probe(openfga),
+            STARTUP_PROBE_DEFAULTS,
+        ),
+        security_context: Some(create_security_context(openfga)?),
+        lifecycle: create_lifecycle(openfga),
         ..Default::default()
     };
 
+    let (mut volumes, volume_mounts) = create_volumes(openfga);
+    let mut containers = vec![Container {
+        volume_mounts,
+        ..container
+    }];
+    if let Some(proxy) = playground::auth_proxy(openfga) {
+        containers.push(playground::proxy_container(openfga, proxy));
+        let proxy_volumes = playground::proxy_volumes(name, proxy);
+        if !proxy_volumes.is_empty() {
+            volumes.get_or_insert_with(Vec::new).extend(proxy_volumes);
+        }
+    }
+    if let Some(sidecar) = query_cache::sidecar(openfga) {
+        containers.push(query_cache::container(sidecar));
+    }
+
     let deployment = Deployment {
         metadata: ObjectMeta {
             name: Some(name.to_string()),
             namespace: Some(ns.to_string()),
             labels: Some(labels.clone()),
+            annotations: labels::instance_annotations(openfga, []),
             ..Default::default()
         },
         spec: Some(DeploymentSpec {
             replicas: Some(openfga.spec.replicas),
             selector: LabelSelector {
-                match_labels: Some(labels.clone()),
+                match_labels: Some(selector),
                 ..Default::default()
             },
+            strategy: upgrade::rolling_update(openfga.spec.upgrade_strategy.as_ref()),
+            min_ready_seconds: openfga.spec.min_ready_seconds,
             template: PodTemplateSpec {
                 metadata: Some(ObjectMeta {
                     labels: Some(labels),
+                    annotations: create_pod_annotations(openfga),
                     ..Default::default()
                 }),
                 spec: Some(PodSpec {
-                    containers: vec![container],
+                    containers,
+                    volumes,
+                    security_context: Some(create_pod_security_context(openfga)?),
+                    service_account_name: service_account::service_account_name(openfga, name),
+                    automount_service_account_token: Some(service_account::automount_token(
+                        openfga,
+                    )),
+                    termination_grace_period_seconds: termination_grace_period(openfga),
+                    image_pull_secrets: (!openfga.spec.image_pull_secrets.is_empty())
+                        .then(|| openfga.spec.image_pull_secrets.clone()),
+                    priority_class_name: openfga.spec.priority_class_name.clone(),
+                    runtime_class_name: openfga.spec.runtime_class_name.clone(),
                     ..Default::default()
                 }),
             },
@@ -466,48 +1128,593 @@ fn create_deployment(openfga: &OpenFGA, ns: &str, name: &str) -> ControllerResul
     Ok(deployment)
 }
 
-#[instrument(skip(openfga), fields(namespace = %ns, name = %name))]
-fn create_service(openfga: &OpenFGA, ns: &str, name: &str) -> ControllerResult<Service> {
-    debug!(
-        event = "service_creation_start",
-        namespace = %ns,
-        name = %name,
-        grpc_port = openfga.spec.grpc.port,
-        http_port = openfga.spec.http.port,
-        playground_enabled = openfga.spec.playground.enabled,
-        "Creating service specification"
-    );
+/// preStop hook delaying SIGTERM until the pod is out of the endpoints.
+fn create_lifecycle(openfga: &OpenFGA) -> Option<Lifecycle> {
+    let seconds = openfga
+        .spec
+        .termination
+        .as_ref()?
+        .pre_stop_sleep_seconds
+        .filter(|&seconds| seconds > 0)?;
+    Some(Lifecycle {
+        pre_stop: Some(LifecycleHandler {
+            exec: Some(ExecAction {
+                command: Some(vec!["sleep".to_string(), seconds.to_string()]),
+            }),
+            ..Default::default()
+        }),
+        ..Default::default()
+    })
+}
 
-    let labels = BTreeMap::from([
-        ("app".to_string(), "openfga".to_string()),
-        ("app.kubernetes.io/name".to_string(), "openfga".to_string()),
-        ("app.kubernetes.io/instance".to_string(), name.to_string()),
-        ("instance".to_string(), name.to_string()),
-    ]);
+/// The configured grace period, or the Kubernetes default extended by the
+/// preStop sleep so OpenFGA keeps its full drain time after the hook.
+fn termination_grace_period(openfga: &OpenFGA) -> Option<i64> {
+    const KUBERNETES_DEFAULT: i64 = 30;
+    let termination = openfga.spec.termination.as_ref()?;
+    termination.grace_period_seconds.or_else(|| {
+        termination
+            .pre_stop_sleep_seconds
+            .filter(|&seconds| seconds > 0)
+            .map(|seconds| KUBERNETES_DEFAULT + seconds)
+    })
+}
 
-    let mut service_ports = vec![
-        ServicePort {
-            port: openfga.spec.grpc.port,
-            target_port: Some(IntOrString::Int(openfga.spec.grpc.port)),
-            name: Some("grpc".to_string()),
-            protocol: Some("TCP".to_string()),
-            ..Default::default()
-        },
-        ServicePort {
-            port: openfga.spec.http.port,
-            target_port: Some(IntOrString::Int(openfga.spec.http.port)),
-            name: Some("http".to_string()),
-            protocol: Some("TCP".to_string()),
-            ..Default::default()
-        },
-    ];
+pub(crate) struct ProbeDefaults {
+    pub initial_delay_seconds: i32,
+    pub period_seconds: i32,
+    pub timeout_seconds: i32,
+    pub failure_threshold: i32,
+}
 
-    if openfga.spec.playground.enabled {
-        debug!(
-            event = "playground_service_port_added",
-            namespace = %ns,
-            name = %name,
-            playground_port = openfga.spec.playground.port,
+pub(crate) const LIVENESS_PROBE_DEFAULTS: ProbeDefaults = ProbeDefaults {
+    initial_delay_seconds: 10,
+    period_seconds: 10,
+    timeout_seconds: 5,
+    failure_threshold: 3,
+};
+
+pub(crate) const READINESS_PROBE_DEFAULTS: ProbeDefaults = ProbeDefaults {
+    initial_delay_seconds: 5,
+    period_seconds: 5,
+    timeout_seconds: 3,
+    failure_threshold: 3,
+};
+
+/// Allows up to 150s for migrations and cache warm-up before liveness applies.
+pub(crate) const STARTUP_PROBE_DEFAULTS: ProbeDefaults = ProbeDefaults {
+    initial_delay_seconds: 0,
+    period_seconds: 5,
+    timeout_seconds: 3,
+    failure_threshold: 30,
+};
+
+/// Whether OpenFGA's `listener` (`GRPC` or `HTTP`) serves TLS only, through
+/// `spec.tls` or its `OPENFGA_<listener>_TLS_ENABLED` setting in `spec.env` or
+/// `spec.config`.
+fn tls_enabled(openfga: &OpenFGA, listener: &str) -> bool {
+    if listener == "GRPC" && openfga.spec.tls.is_some() {
+        return true;
+    }
+    let key = format!("OPENFGA_{}_TLS_ENABLED", listener);
+    let value = match openfga.spec.env.iter().find(|var| var.name == key) {
+        Some(var) => var.value.clone(),
+        None => server_config::render(&openfga.spec.config).remove(&key),
+    };
+    value.is_some_and(|value| value.eq_ignore_ascii_case("true"))
+}
+
+/// Probe handler hitting OpenFGA's HTTP `/healthz` endpoint; the kubelet does
+/// not verify certificates of HTTPS probes.
+fn http_health_probe(openfga: &OpenFGA) -> Probe {
+    let scheme = if tls_enabled(openfga, "HTTP") {
+        "HTTPS"
+    } else {
+        "HTTP"
+    };
+    Probe {
+        http_get: Some(HTTPGetAction {
+            path: Some("/healthz".to_string()),
+            port: IntOrString::Int(openfga.spec.http.port),
+            scheme: Some(scheme.to_string()),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+/// Probe handler using the gRPC health checking protocol OpenFGA serves.
+fn grpc_health_probe(openfga: &OpenFGA) -> Probe {
+    Probe {
+        grpc: Some(GRPCAction {
+            port: openfga.spec.grpc.port,
+            service: None,
+        }),
+        ..Default::default()
+    }
+}
+
+fn create_probe(config: &ProbeConfig, handler: Probe, defaults: ProbeDefaults) -> Option<Probe> {
+    if !config.enabled {
+        return None;
+    }
+
+    Some(Probe {
+        initial_delay_seconds: Some(
+            config
+                .initial_delay_seconds
+                .unwrap_or(defaults.initial_delay_seconds),
+        ),
+        period_seconds: Some(config.period_seconds.unwrap_or(defaults.period_seconds)),
+        timeout_seconds: Some(config.timeout_seconds.unwrap_or(defaults.timeout_seconds)),
+        failure_threshold: Some(
+            config
+                .failure_threshold
+                .unwrap_or(defaults.failure_threshold),
+        ),
+        ..handler
+    })
+}
+
+/// `spec.resources` plus the extended resources a `v1beta1` client set.
+fn create_resource_requirements(openfga: &OpenFGA) -> Option<ResourceRequirements> {
+    fn quantities(
+        q: Option<&ResourceQuantities>,
+        extended: Option<BTreeMap<String, Quantity>>,
+    ) -> Option<BTreeMap<String, Quantity>> {
+        let mut map: BTreeMap<String, Quantity> = q
+            .map(|q| {
+                [
+                    ("cpu", &q.cpu),
+                    ("memory", &q.memory),
+                    ("ephemeral-storage", &q.ephemeral_storage),
+                ]
+            })
+            .into_iter()
+            .flatten()
+            .filter_map(|(k, v)| v.as_ref().map(|v| (k.to_string(), Quantity(v.clone()))))
+            .collect();
+        map.extend(extended.unwrap_or_default());
+        (!map.is_empty()).then_some(map)
+    }
+
+    let resources = openfga.spec.resources.as_ref();
+    let extended = conversion::extended_resources(openfga);
+    if resources.is_none() && extended.requests.is_none() && extended.limits.is_none() {
+        return None;
+    }
+    Some(ResourceRequirements {
+        requests: quantities(
+            resources.and_then(|r| r.requests.as_ref()),
+            extended.requests,
+        ),
+        limits: quantities(resources.and_then(|r| r.limits.as_ref()), extended.limits),
+        ..Default::default()
+    })
+}
+
+/// uid/gid of the `nonroot` user in the distroless OpenFGA image.
+const NONROOT_ID: i64 = 65532;
+
+/// Restricted Pod Security Standard compatible pod defaults.
+fn create_pod_security_context(openfga: &OpenFGA) -> ControllerResult<PodSecurityContext> {
+    let defaults = PodSecurityContext {
+        run_as_non_root: Some(true),
+        run_as_user: Some(NONROOT_ID),
+        run_as_group: Some(NONROOT_ID),
+        fs_group: Some(NONROOT_ID),
+        seccomp_profile: Some(SeccompProfile {
+            type_: "RuntimeDefault".to_string(),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    overlay(defaults, openfga.spec.pod_security_context.as_ref())
+}
+
+/// Restricted Pod Security Standard compatible container defaults.
+fn create_security_context(openfga: &OpenFGA) -> ControllerResult<SecurityContext> {
+    let defaults = SecurityContext {
+        run_as_non_root: Some(true),
+        allow_privilege_escalation: Some(false),
+        read_only_root_filesystem: Some(true),
+        capabilities: Some(Capabilities {
+            drop: Some(vec!["ALL".to_string()]),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    overlay(defaults, openfga.spec.security_context.as_ref())
+}
+
+/// Replaces each top-level field of `defaults` that `overrides` sets.
+fn overlay<T>(defaults: T, overrides: Option<&T>) -> ControllerResult<T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    let Some(overrides) = overrides else {
+        return Ok(defaults);
+    };
+    let mut merged = serde_json::to_value(defaults)?;
+    if let (Some(merged), serde_json::Value::Object(overrides)) =
+        (merged.as_object_mut(), serde_json::to_value(overrides)?)
+    {
+        merged.extend(overrides);
+    }
+    Ok(serde_json::from_value(merged)?)
+}
+
+const CACHE_VOLUME_NAME: &str = "cache";
+
+fn create_cache_volume(config: &CacheVolumeConfig) -> (Volume, VolumeMount) {
+    let volume = Volume {
+        name: CACHE_VOLUME_NAME.to_string(),
+        empty_dir: Some(EmptyDirVolumeSource {
+            medium: match config.medium {
+                CacheVolumeMedium::Disk => None,
+                CacheVolumeMedium::Memory => Some("Memory".to_string()),
+            },
+            size_limit: config.size_limit.clone().map(Quantity),
+        }),
+        ..Default::default()
+    };
+    let mount = VolumeMount {
+        name: CACHE_VOLUME_NAME.to_string(),
+        mount_path: config.mount_path.clone(),
+        ..Default::default()
+    };
+    (volume, mount)
+}
+
+fn create_pod_annotations(openfga: &OpenFGA) -> Option<BTreeMap<String, String>> {
+    let config = server_config::render(&openfga.spec.config);
+    let config_hash = (!config.is_empty()).then(|| {
+        (
+            server_config::CONFIG_HASH_ANNOTATION.to_string(),
+            server_config::config_hash(&config),
+        )
+    });
+    labels::instance_annotations(
+        openfga,
+        monitoring::scrape_annotations(openfga)
+            .into_iter()
+            .chain(config_hash),
+    )
+}
+
+const TLS_VOLUME_NAME: &str = "grpc-tls";
+const TLS_MOUNT_PATH: &str = "/etc/openfga/tls";
+
+/// The cache and TLS volumes followed by the user-declared volumes and mounts.
+fn create_volumes(openfga: &OpenFGA) -> (Option<Vec<Volume>>, Option<Vec<VolumeMount>>) {
+    let mut volumes = Vec::new();
+    let mut mounts = Vec::new();
+    if let Some(cache) = &openfga.spec.cache_volume {
+        let (volume, mount) = create_cache_volume(cache);
+        volumes.push(volume);
+        mounts.push(mount);
+    }
+    if let Some(tls) = &openfga.spec.tls {
+        volumes.push(Volume {
+            name: TLS_VOLUME_NAME.to_string(),
+            secret: Some(SecretVolumeSource {
+                secret_name: Some(tls.secret_name.clone()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+        mounts.push(VolumeMount {
+            name: TLS_VOLUME_NAME.to_string(),
+            mount_path: TLS_MOUNT_PATH.to_string(),
+            read_only: Some(true),
+            ..Default::default()
+        });
+    }
+    volumes.extend(openfga.spec.volumes.iter().cloned());
+    mounts.extend(openfga.spec.volume_mounts.iter().cloned());
+    (
+        (!volumes.is_empty()).then_some(volumes),
+        (!mounts.is_empty()).then_some(mounts),
+    )
+}
+
+/// The generated server config comes first so user `envFrom` sources can override it.
+fn create_container_env_from(openfga: &OpenFGA, name: &str) -> Option<Vec<EnvFromSource>> {
+    let sources: Vec<EnvFromSource> = server_config::env_from(openfga, name)
+        .into_iter()
+        .chain(openfga.spec.env_from.iter().cloned())
+        .collect();
+    (!sources.is_empty()).then_some(sources)
+}
+
+/// `run` with the enabled experimental features; the image's default command
+/// when there are none.
+fn create_container_args(openfga: &OpenFGA) -> Option<Vec<String>> {
+    let flags = &openfga.spec.feature_flags;
+    if flags.is_empty() {
+        return None;
+    }
+    Some(vec![
+        "run".to_string(),
+        format!("--experimentals={}", flags.join(",")),
+    ])
+}
+
+/// Merges user-supplied environment variables with the operator-generated ones.
+/// User variables come first so `$(VAR)` references in generated values (e.g. a
+/// datastore URI) can expand them, and a user variable shadows a generated one.
+fn create_container_env(openfga: &OpenFGA) -> Vec<EnvVar> {
+    let mut env = openfga.spec.env.clone();
+    let mut generated = create_datastore_env(openfga);
+    generated.extend(create_metrics_env(openfga));
+    generated.extend(create_logging_env(openfga));
+    generated.extend(create_tracing_env(openfga));
+    generated.extend(create_tls_env(openfga));
+    generated.extend(query_cache::env(openfga));
+
+    env.extend(
+        generated
+            .into_iter()
+            .filter(|var| !openfga.spec.env.iter().any(|user| user.name == var.name)),
+    );
+
+    env
+}
+
+/// Builds the datastore environment for the OpenFGA container. A `uriSecretRef`
+/// is resolved by the kubelet so the connection string never lands in the pod spec.
+fn create_datastore_env(openfga: &OpenFGA) -> Vec<EnvVar> {
+    let datastore = &openfga.spec.datastore;
+    let mut env = vec![EnvVar {
+        name: "OPENFGA_DATASTORE_ENGINE".to_string(),
+        value: Some(datastore.engine.clone()),
+        ..Default::default()
+    }];
+
+    if let Some(secret_ref) = &datastore.uri_secret_ref {
+        env.push(EnvVar {
+            name: "OPENFGA_DATASTORE_URI".to_string(),
+            value_from: Some(EnvVarSource {
+                secret_key_ref: Some(SecretKeySelector {
+                    name: Some(secret_ref.name.clone()),
+                    key: secret_ref.key.clone(),
+                    optional: Some(false),
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+    } else if let Some(uri) = &datastore.uri {
+        let uri = match datastore.mysql.as_ref().and_then(|m| m.tls.as_deref()) {
+            Some(tls) if datastore.engine == "mysql" => with_query_param(uri, "tls", tls),
+            _ => uri.clone(),
+        };
+        env.push(EnvVar {
+            name: "OPENFGA_DATASTORE_URI".to_string(),
+            value: Some(uri),
+            ..Default::default()
+        });
+    }
+
+    let (max_open_conns, max_idle_conns) = match datastore.engine.as_str() {
+        "postgres" => datastore
+            .postgres
+            .as_ref()
+            .map(|p| (p.max_open_conns, p.max_idle_conns)),
+        "mysql" => datastore
+            .mysql
+            .as_ref()
+            .map(|m| (m.max_open_conns, m.max_idle_conns)),
+        _ => None,
+    }
+    .unwrap_or_default();
+    let mut settings = vec![
+        (
+            "OPENFGA_DATASTORE_MAX_OPEN_CONNS",
+            max_open_conns.map(|n| n.to_string()),
+        ),
+        (
+            "OPENFGA_DATASTORE_MAX_IDLE_CONNS",
+            max_idle_conns.map(|n| n.to_string()),
+        ),
+    ];
+    // The pgx driver reads libpq's PGSSLMODE, which also covers URIs from Secrets
+    if datastore.engine == "postgres" {
+        settings.push((
+            "PGSSLMODE",
+            datastore.postgres.as_ref().and_then(|p| p.sslmode.clone()),
+        ));
+    }
+    env.extend(settings.into_iter().filter_map(|(name, value)| {
+        value.map(|value| EnvVar {
+            name: name.to_string(),
+            value: Some(value),
+            ..Default::default()
+        })
+    }));
+
+    env
+}
+
+/// `uri` with `key=value` added to its query, unless it already sets `key`.
+fn with_query_param(uri: &str, key: &str, value: &str) -> String {
+    let query = uri.split_once('?').map(|(_, q)| q).unwrap_or_default();
+    if query.split('&').any(|p| p.split('=').next() == Some(key)) {
+        return uri.to_string();
+    }
+    let separator = if uri.contains('?') { '&' } else { '?' };
+    format!("{}{}{}={}", uri, separator, key, value)
+}
+
+fn create_metrics_env(openfga: &OpenFGA) -> Vec<EnvVar> {
+    let metrics = &openfga.spec.observability.metrics;
+    if !metrics.enabled {
+        return vec![];
+    }
+    [
+        ("OPENFGA_METRICS_ENABLED", "true".to_string()),
+        ("OPENFGA_METRICS_ADDR", format!("0.0.0.0:{}", metrics.port)),
+    ]
+    .into_iter()
+    .map(|(name, value)| EnvVar {
+        name: name.to_string(),
+        value: Some(value),
+        ..Default::default()
+    })
+    .collect()
+}
+
+/// Set as variables rather than in the server config ConfigMap so they win over
+/// `log.*` keys there.
+fn create_logging_env(openfga: &OpenFGA) -> Vec<EnvVar> {
+    let Some(logging) = &openfga.spec.logging else {
+        return vec![];
+    };
+    [
+        ("OPENFGA_LOG_LEVEL", logging.level.map(|l| l.as_str())),
+        ("OPENFGA_LOG_FORMAT", logging.format.map(|f| f.as_str())),
+    ]
+    .into_iter()
+    .filter_map(|(name, value)| {
+        value.map(|value| EnvVar {
+            name: name.to_string(),
+            value: Some(value.to_string()),
+            ..Default::default()
+        })
+    })
+    .collect()
+}
+
+/// Headers go through the OpenTelemetry SDK's own variable, which OpenFGA's OTLP
+/// exporter reads.
+fn create_tracing_env(openfga: &OpenFGA) -> Vec<EnvVar> {
+    let tracing = &openfga.spec.observability.tracing;
+    if !tracing.enabled {
+        return vec![];
+    }
+    let mut env: Vec<EnvVar> = [
+        ("OPENFGA_TRACE_ENABLED", Some("true".to_string())),
+        ("OPENFGA_TRACE_OTLP_ENDPOINT", tracing.otlp_endpoint.clone()),
+        (
+            "OPENFGA_TRACE_SAMPLE_RATIO",
+            tracing.sample_ratio.map(|ratio| ratio.to_string()),
+        ),
+        (
+            "OPENFGA_TRACE_OTLP_TLS_ENABLED",
+            Some(tracing.tls.to_string()),
+        ),
+    ]
+    .into_iter()
+    .filter_map(|(name, value)| {
+        value.map(|value| EnvVar {
+            name: name.to_string(),
+            value: Some(value),
+            ..Default::default()
+        })
+    })
+    .collect();
+
+    if let Some(secret_ref) = &tracing.headers_secret_ref {
+        env.push(EnvVar {
+            name: "OTEL_EXPORTER_OTLP_HEADERS".to_string(),
+            value_from: Some(EnvVarSource {
+                secret_key_ref: Some(SecretKeySelector {
+                    name: Some(secret_ref.name.clone()),
+                    key: secret_ref.key.clone(),
+                    optional: Some(false),
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+    } else if !tracing.headers.is_empty() {
+        let headers: Vec<String> = tracing
+            .headers
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect();
+        env.push(EnvVar {
+            name: "OTEL_EXPORTER_OTLP_HEADERS".to_string(),
+            value: Some(headers.join(",")),
+            ..Default::default()
+        });
+    }
+    env
+}
+
+fn create_tls_env(openfga: &OpenFGA) -> Vec<EnvVar> {
+    if openfga.spec.tls.is_none() {
+        return vec![];
+    }
+    [
+        ("OPENFGA_GRPC_TLS_ENABLED", "true".to_string()),
+        (
+            "OPENFGA_GRPC_TLS_CERT",
+            format!("{}/tls.crt", TLS_MOUNT_PATH),
+        ),
+        (
+            "OPENFGA_GRPC_TLS_KEY",
+            format!("{}/tls.key", TLS_MOUNT_PATH),
+        ),
+    ]
+    .into_iter()
+    .map(|(name, value)| EnvVar {
+        name: name.to_string(),
+        value: Some(value),
+        ..Default::default()
+    })
+    .collect()
+}
+
+#[instrument(skip(openfga), fields(namespace = %ns, name = %name))]
+pub(crate) fn create_service(openfga: &OpenFGA, ns: &str, name: &str) -> ControllerResult<Service> {
+    debug!(
+        event = "service_creation_start",
+        namespace = %ns,
+        name = %name,
+        grpc_port = openfga.spec.grpc.port,
+        http_port = openfga.spec.http.port,
+        playground_enabled = openfga.spec.playground.enabled,
+        "Creating service specification"
+    );
+
+    let labels = labels::instance_labels(openfga, name);
+    let selector = labels::selector_labels(name);
+
+    // Node ports are only valid on NodePort and LoadBalancer Services
+    let node_ports = match openfga.spec.service.type_ {
+        ServiceType::NodePort | ServiceType::LoadBalancer => {
+            openfga.spec.service.node_ports.clone()
+        }
+        ServiceType::ClusterIP | ServiceType::Headless => NodePorts::default(),
+    };
+
+    let mut service_ports = vec![
+        ServicePort {
+            port: openfga.spec.grpc.port,
+            target_port: Some(IntOrString::Int(openfga.spec.grpc.port)),
+            name: Some("grpc".to_string()),
+            protocol: Some("TCP".to_string()),
+            node_port: node_ports.grpc,
+            ..Default::default()
+        },
+        ServicePort {
+            port: openfga.spec.http.port,
+            target_port: Some(IntOrString::Int(openfga.spec.http.port)),
+            name: Some("http".to_string()),
+            protocol: Some("TCP".to_string()),
+            node_port: node_ports.http,
+            ..Default::default()
+        },
+    ];
+
+    // Behind an auth proxy the playground is only reachable through its own Service
+    if openfga.spec.playground.enabled && playground::auth_proxy(openfga).is_none() {
+        debug!(
+            event = "playground_service_port_added",
+            namespace = %ns,
+            name = %name,
+            playground_port = openfga.spec.playground.port,
             "Adding playground port to service"
         );
 
@@ -516,21 +1723,54 @@ fn create_service(openfga: &OpenFGA, ns: &str, name: &str) -> ControllerResult<S
             target_port: Some(IntOrString::Int(openfga.spec.playground.port)),
             name: Some("playground".to_string()),
             protocol: Some("TCP".to_string()),
+            node_port: node_ports.playground,
+            ..Default::default()
+        });
+    }
+
+    if openfga.spec.observability.metrics.enabled {
+        let port = openfga.spec.observability.metrics.port;
+        service_ports.push(ServicePort {
+            port,
+            target_port: Some(IntOrString::Int(port)),
+            name: Some("metrics".to_string()),
+            protocol: Some("TCP".to_string()),
+            node_port: node_ports.metrics,
             ..Default::default()
         });
     }
 
+    if let Some(sidecar) = query_cache::sidecar(openfga) {
+        service_ports.push(query_cache::service_port(sidecar));
+    }
+
+    let config = &openfga.spec.service;
+    let exposed = matches!(
+        config.type_,
+        ServiceType::NodePort | ServiceType::LoadBalancer
+    );
+    let load_balancer = config.type_ == ServiceType::LoadBalancer;
     let service = Service {
         metadata: ObjectMeta {
             name: Some(name.to_string()),
             namespace: Some(ns.to_string()),
-            labels: Some(labels.clone()),
+            labels: Some(labels),
+            annotations: labels::instance_annotations(openfga, config.annotations.clone()),
             ..Default::default()
         },
         spec: Some(ServiceSpec {
-            selector: Some(labels),
+            selector: Some(selector),
             ports: Some(service_ports),
-            type_: Some("ClusterIP".to_string()),
+            type_: Some(service_type(config.type_).to_string()),
+            cluster_ip: (config.type_ == ServiceType::Headless).then(|| "None".to_string()),
+            external_traffic_policy: config
+                .external_traffic_policy
+                .filter(|_| exposed)
+                .map(|p| format!("{:?}", p)),
+            load_balancer_class: config.load_balancer_class.clone().filter(|_| load_balancer),
+            load_balancer_source_ranges: (load_balancer
+                && !config.load_balancer_source_ranges.is_empty())
+            .then(|| config.load_balancer_source_ranges.clone()),
             ..Default::default()
         }),
         ..Default::default()
@@ -548,12 +1788,82 @@ fn create_service(openfga: &OpenFGA, ns: &str, name: &str) -> ControllerResult<S
     Ok(service)
 }
 
-#[instrument(skip(client, _openfga), fields(namespace = %ns, name = %name))]
+fn service_type(type_: ServiceType) -> &'static str {
+    match type_ {
+        ServiceType::ClusterIP | ServiceType::Headless => "ClusterIP",
+        ServiceType::NodePort => "NodePort",
+        ServiceType::LoadBalancer => "LoadBalancer",
+    }
+}
+
+/// The cluster IP of a Service is immutable, so switching to or from headless
+/// requires deleting the existing Service first.
+fn requires_recreate(existing: &Service, desired: &Service) -> bool {
+    let headless =
+        |s: &Service| s.spec.as_ref().and_then(|s| s.cluster_ip.as_deref()) == Some("None");
+    headless(existing) != headless(desired)
+}
+
+/// Returns the zone a node belongs to, preferring the GA topology label.
+fn node_zone(node: &Node) -> Option<String> {
+    let labels = node.metadata.labels.as_ref()?;
+    labels
+        .get(ZONE_LABEL)
+        .or_else(|| labels.get(LEGACY_ZONE_LABEL))
+        .cloned()
+}
+
+/// Resolves the distinct zones of the nodes the given pods are scheduled on.
+fn pod_zones(pods: &[Pod], node_zones: &BTreeMap<String, String>) -> Vec<String> {
+    pods.iter()
+        .filter_map(|pod| pod.spec.as_ref().and_then(|s| s.node_name.as_ref()))
+        .filter_map(|node_name| node_zones.get(node_name).cloned())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+#[instrument(skip(client), fields(namespace = %ns, name = %name))]
+async fn collect_instance_zones(
+    client: &Client,
+    ns: &str,
+    name: &str,
+) -> ControllerResult<Vec<String>> {
+    let pods: Api<Pod> = Api::namespaced(client.clone(), ns);
+    let pod_list = pods
+        .list(&ListParams::default().labels(&format!("app.kubernetes.io/instance={}", name)))
+        .await?;
+
+    let nodes: Api<Node> = Api::all(client.clone());
+    let mut node_zones = BTreeMap::new();
+    for node_name in pod_list
+        .items
+        .iter()
+        .filter_map(|pod| pod.spec.as_ref().and_then(|s| s.node_name.clone()))
+        .collect::<BTreeSet<_>>()
+    {
+        if let Some(zone) = nodes
+            .get_opt(&node_name)
+            .await?
+            .as_ref()
+            .and_then(node_zone)
+        {
+            node_zones.insert(node_name, zone);
+        }
+    }
+
+    Ok(pod_zones(&pod_list.items, &node_zones))
+}
+
+#[instrument(skip(client, cluster, openfga), fields(namespace = %ns, name = %name))]
 async fn update_status(
     client: &Client,
-    _openfga: &OpenFGA,
+    cluster: &Client,
+    openfga: &OpenFGA,
     ns: &str,
     name: &str,
+    image_digest: Option<&Result<String, String>>,
+    image_verification: Option<&Result<String, String>>,
 ) -> ControllerResult<()> {
     debug!(
         event = "status_update_start",
@@ -562,181 +1872,738 @@ async fn update_status(
         "Starting status update process"
     );
 
-    let deployments: Api<Deployment> = Api::namespaced(client.clone(), ns);
+    let previous = openfga.status.clone().unwrap_or_default();
 
-    match deployments.get(name).await {
-        Ok(deployment) => {
-            let current_replicas = deployment.status.as_ref().and_then(|s| s.replicas);
-            let ready_replicas = deployment.status.as_ref().and_then(|s| s.ready_replicas);
-
-            debug!(
-                event = "deployment_status_retrieved",
+    // A StatefulSet workload is presented as a Deployment, see workload::as_deployment_status
+    let deployment = match workload::current(cluster, openfga, ns, name).await {
+        Ok(Some(deployment)) => Some(deployment),
+        result => {
+            warn!(
+                event = "deployment_not_found_for_status",
                 namespace = %ns,
                 name = %name,
-                current_replicas = current_replicas,
-                ready_replicas = ready_replicas,
-                "Retrieved deployment status"
+                error = ?result.err(),
+                "Workload not found when updating status, this may be expected during resource creation"
             );
+            None
+        }
+    };
 
-            let status = OpenFGAStatus {
-                replicas: current_replicas,
-                ready_replicas,
-                conditions: None,
-            };
+    let current_replicas = deployment
+        .as_ref()
+        .and_then(|d| d.status.as_ref())
+        .and_then(|s| s.replicas);
+    let ready_replicas = deployment
+        .as_ref()
+        .and_then(|d| d.status.as_ref())
+        .and_then(|s| s.ready_replicas);
+    metrics::metrics()
+        .ready_replicas
+        .with_label_values(&[ns, name])
+        .set(ready_replicas.unwrap_or(0) as i64);
 
-            let openfgas: Api<OpenFGA> = Api::namespaced(client.clone(), ns);
-            let status_patch = serde_json::json!({
-                "status": status
-            });
+    debug!(
+        event = "deployment_status_retrieved",
+        namespace = %ns,
+        name = %name,
+        current_replicas = current_replicas,
+        ready_replicas = ready_replicas,
+        "Retrieved deployment status"
+    );
 
-            match openfgas
-                .patch_status(name, &PatchParams::default(), &Patch::Merge(&status_patch))
-                .await
-            {
-                Ok(_) => {
-                    debug!(
-                        event = "status_patch_applied",
-                        namespace = %ns,
-                        name = %name,
-                        replicas = current_replicas,
-                        ready_replicas = ready_replicas,
-                        "Status patch applied successfully"
-                    );
-                }
-                Err(e) => {
-                    error!(
-                        event = "status_patch_failed",
-                        namespace = %ns,
-                        name = %name,
-                        error = %e,
-                        "Failed to apply status patch"
-                    );
-                    return Err(e.into());
-                }
+    let zones = if deployment.is_some() {
+        match collect_instance_zones(cluster, ns, name).await {
+            Ok(zones) => Some(zones),
+            Err(e) => {
+                warn!(
+                    event = "zone_lookup_failed",
+                    namespace = %ns,
+                    name = %name,
+                    error = %e,
+                    "Failed to resolve pod zones, omitting zone placement from status"
+                );
+                None
             }
         }
-        Err(e) => {
+    } else {
+        None
+    };
+
+    if let Some(zones) = &zones {
+        metrics::metrics()
+            .instance_zones
+            .with_label_values(&[ns, name])
+            .set(zones.len() as i64);
+
+        if openfga.spec.replicas > 1 && zones.len() == 1 {
             warn!(
-                event = "deployment_not_found_for_status",
+                event = "single_zone_placement",
                 namespace = %ns,
                 name = %name,
-                error = %e,
-                "Deployment not found when updating status, this may be expected during resource creation"
+                replicas = openfga.spec.replicas,
+                zone = %zones[0],
+                "All replicas are scheduled in a single zone"
             );
         }
     }
 
-    Ok(())
-}
+    let mut conditions = rollout_conditions(
+        deployment.as_ref(),
+        openfga.spec.replicas,
+        openfga.metadata.generation,
+        previous.conditions.as_deref(),
+    );
 
-#[instrument(skip(_ctx))]
-fn error_policy(
-    openfga: Arc<OpenFGA>,
-    error: &ControllerError,
-    _ctx: Arc<OpenFGAController>,
-) -> Action {
-    let ns = openfga.namespace().unwrap_or_default();
-    let name = openfga.name_any();
+    let advice = advisory::advisories().advise(&openfga.spec.image);
+    metrics::metrics()
+        .instance_update_available
+        .with_label_values(&[ns, name])
+        .set(advice.as_ref().is_some_and(|a| a.update.is_some()) as i64);
+    metrics::metrics()
+        .instance_version_eol
+        .with_label_values(&[ns, name])
+        .set(
+            advice
+                .as_ref()
+                .is_some_and(|a| a.status == SupportStatus::EndOfLife) as i64,
+        );
+    if let Some(advice) = &advice {
+        if advice.status != SupportStatus::Supported {
+            warn!(
+                event = "outdated_openfga_version",
+                namespace = %ns,
+                name = %name,
+                version = %advice.version,
+                status = ?advice.status,
+                "Instance runs an OpenFGA version that is no longer supported"
+            );
+        }
+    }
+    conditions.push(rollback::rollback_condition(
+        openfga,
+        previous.conditions.as_deref(),
+    ));
+    conditions.extend(advisory_conditions(
+        &openfga.spec.image,
+        advice.as_ref(),
+        openfga.metadata.generation,
+        previous.conditions.as_deref(),
+    ));
+    if let Some(result) = image_digest {
+        conditions.push(image_digest::digest_condition(
+            result,
+            openfga.metadata.generation,
+            previous.conditions.as_deref(),
+        ));
+    }
+    if let Some(result) = image_verification {
+        conditions.push(image_verification::verified_condition(
+            result,
+            openfga.metadata.generation,
+            previous.conditions.as_deref(),
+        ));
+    }
+    let resolved_image = match image_digest {
+        Some(Ok(pinned)) => Some(pinned.clone()),
+        Some(Err(_)) => image_digest::previously_pinned(openfga),
+        None => None,
+    };
 
-    let requeue_duration = match error {
-        ControllerError::Kube(kube_error) => {
-            // More intelligent error handling based on kube-rs patterns
-            let error_str = kube_error.to_string().to_lowercase();
+    // Ready replicas only mean probes pass; confirm the API answers through the Service
+    let reachability = if ready_replicas.unwrap_or(0) > 0 {
+        let api = OpenFGAClient::for_instance(openfga);
+        let result = match tokio::time::timeout(SERVER_CHECK_TIMEOUT, api.verify()).await {
+            Ok(result) => result.map_err(|e| e.to_string()),
+            Err(_) => Err(format!(
+                "no response within {}s",
+                SERVER_CHECK_TIMEOUT.as_secs()
+            )),
+        };
+        if let Err(e) = &result {
+            warn!(
+                event = "server_unreachable",
+                namespace = %ns,
+                name = %name,
+                error = %e,
+                "OpenFGA API is not reachable through its Service"
+            );
+        }
+        Some(result)
+    } else {
+        None
+    };
+    conditions.push(server_reachable_condition(
+        reachability.as_ref(),
+        openfga.metadata.generation,
+        previous.conditions.as_deref(),
+    ));
+    require_reachable(
+        &mut conditions,
+        reachability.as_ref(),
+        openfga.metadata.generation,
+        previous.conditions.as_deref(),
+    );
 
-            if error_str.contains("notfound") || error_str.contains("not found") {
-                info!(
+    let wanted_stores = &openfga.spec.bootstrap.stores;
+    let mut stores = None;
+    if !wanted_stores.is_empty() {
+        let result = match &reachability {
+            Some(Ok(())) => {
+                let api = OpenFGAClient::for_instance(openfga);
+                Some(
+                    bootstrap::ensure_stores(&api, wanted_stores)
+                        .await
+                        .map_err(|e| e.to_string()),
+                )
+            }
+            _ => None,
+        };
+        match &result {
+            Some(Ok(ensured)) => stores = Some(ensured.clone()),
+            Some(Err(e)) => {
+                warn!(
+                    event = "store_bootstrap_failed",
                     namespace = %ns,
-                    resource_name = %name,
-                    error_type = "NotFound",
-                    error_detail = %kube_error,
-                    "Resource not found, fast retry for creation"
+                    name = %name,
+                    error = %e,
+                    "Failed to ensure bootstrap stores"
                 );
-                Duration::from_secs(5)
-            } else if error_str.contains("conflict") || error_str.contains("version") {
-                info!(
-                    namespace = %ns,
-                    resource_name = %name,
-                    error_type = "Conflict",
-                    error_detail = %kube_error,
-                    "Resource conflict or version mismatch, immediate retry"
-                );
-                Duration::from_secs(1)
-            } else if error_str.contains("forbidden") || error_str.contains("unauthorized") {
-                warn!(
-                    namespace = %ns,
-                    resource_name = %name,
-                    error_type = "Permission",
-                    error_detail = %kube_error,
-                    "Permission error, longer retry interval - check RBAC configuration"
-                );
-                Duration::from_secs(300) // 5 minutes for permission issues
-            } else if error_str.contains("toomanyrequests") || error_str.contains("throttled") {
-                warn!(
-                    namespace = %ns,
-                    resource_name = %name,
-                    error_type = "RateLimit",
-                    error_detail = %kube_error,
-                    "Rate limited by Kubernetes API, backing off"
-                );
-                Duration::from_secs(60) // 1 minute for rate limiting
-            } else if error_str.contains("timeout") || error_str.contains("connection") {
-                warn!(
-                    namespace = %ns,
-                    resource_name = %name,
-                    error_type = "Network",
-                    error_detail = %kube_error,
-                    "Network connectivity issue, retrying"
-                );
-                Duration::from_secs(30)
-            } else if error_str.contains("event queue") {
-                error!(
-                    namespace = %ns,
-                    resource_name = %name,
-                    error_type = "EventQueue",
-                    error_detail = %kube_error,
-                    "Event queue error detected - this should be resolved by the controller fixes"
-                );
-                Duration::from_secs(10) // Quick retry for event queue issues
-            } else {
-                warn!(
-                    namespace = %ns,
-                    resource_name = %name,
-                    error_type = "Unknown",
-                    error_detail = %kube_error,
-                    "Unknown Kubernetes API error, using standard retry"
-                );
-                Duration::from_secs(30)
+                stores = previous.stores.clone();
             }
+            None => stores = previous.stores.clone(),
         }
-        ControllerError::Serialization(ser_error) => {
+        conditions.push(bootstrap::stores_condition(
+            result.as_ref(),
+            openfga.metadata.generation,
+            previous.conditions.as_deref(),
+        ));
+    }
+
+    let resource_recommendations = if deployment.is_some() {
+        resource_recommendations::observe(cluster, openfga).await
+    } else {
+        previous.resource_recommendations.clone()
+    };
+
+    let history = history::record(
+        previous.history.as_deref(),
+        previous.conditions.as_deref().unwrap_or_default(),
+        &conditions,
+        &chrono::Utc::now().to_rfc3339(),
+    );
+
+    let status = OpenFGAStatus {
+        observed_generation: openfga.metadata.generation,
+        replicas: current_replicas,
+        ready_replicas,
+        selector: Some(labels::selector_string(name)),
+        conditions: Some(conditions),
+        zones,
+        history: Some(history),
+        stores,
+        // Written by upgrade::reconcile_canary; omitted so this merge patch keeps it
+        upgrade: None,
+        resolved_image,
+        // Only dry-run mode reports pending changes; applying clears them
+        pending_changes: None,
+        resource_recommendations,
+    };
+
+    let openfgas: Api<OpenFGA> = Api::namespaced(client.clone(), ns);
+    let status_patch = serde_json::json!({
+        "status": status
+    });
+
+    match openfgas
+        .patch_status(name, &PatchParams::default(), &Patch::Merge(&status_patch))
+        .await
+    {
+        Ok(_) => {
+            debug!(
+                event = "status_patch_applied",
+                namespace = %ns,
+                name = %name,
+                replicas = current_replicas,
+                ready_replicas = ready_replicas,
+                "Status patch applied successfully"
+            );
+        }
+        Err(e) => {
             error!(
+                event = "status_patch_failed",
                 namespace = %ns,
-                resource_name = %name,
-                error_type = "Serialization",
-                error_detail = %ser_error,
-                "JSON serialization/deserialization error, longer retry interval"
+                name = %name,
+                error = %e,
+                "Failed to apply status patch"
             );
-            Duration::from_secs(120)
+            return Err(e.into());
         }
+    }
+
+    Ok(())
+}
+
+/// Derives the `ServerReachable` condition from the post-rollout API check, `None` when
+/// no replica is ready to be checked.
+fn server_reachable_condition(
+    check: Option<&Result<(), String>>,
+    generation: Option<i64>,
+    previous: Option<&[OpenFGACondition]>,
+) -> OpenFGACondition {
+    let (status, reason, message) = match check {
+        None => (
+            false,
+            "NoReadyReplicas",
+            "Waiting for a ready replica before checking the API".to_string(),
+        ),
+        Some(Ok(())) => (
+            true,
+            "Reachable",
+            "Health check and store listing succeeded through the Service".to_string(),
+        ),
+        Some(Err(e)) => (false, "Unreachable", e.clone()),
+    };
+    OpenFGACondition::new(
+        "ServerReachable",
+        status,
+        reason,
+        &message,
+        generation,
+        previous,
+    )
+}
+
+/// Holds `Ready` at false until the API answers through the Service, so an
+/// instance whose pods pass their probes but cannot serve is not reported ready.
+fn require_reachable(
+    conditions: &mut [OpenFGACondition],
+    check: Option<&Result<(), String>>,
+    generation: Option<i64>,
+    previous: Option<&[OpenFGACondition]>,
+) {
+    if matches!(check, Some(Ok(()))) {
+        return;
+    }
+    if let Some(ready) = conditions
+        .iter_mut()
+        .find(|c| c.type_ == "Ready" && c.status == "True")
+    {
+        let message = match check {
+            Some(Err(e)) => format!("OpenFGA API is not reachable through its Service: {}", e),
+            _ => "Waiting for the OpenFGA API to answer through its Service".to_string(),
+        };
+        *ready = OpenFGACondition::new(
+            "Ready",
+            false,
+            "ServerUnreachable",
+            &message,
+            generation,
+            previous,
+        );
+    }
+}
+
+/// Derives the `UpdateAvailable` and `VersionEOL` conditions from the version advisory.
+fn advisory_conditions(
+    image: &str,
+    advice: Option<&VersionAdvice>,
+    generation: Option<i64>,
+    previous: Option<&[OpenFGACondition]>,
+) -> Vec<OpenFGACondition> {
+    let condition = |type_, status, reason, message: &str| {
+        OpenFGACondition::new(type_, status, reason, message, generation, previous)
+    };
+
+    let Some(advice) = advice else {
+        let message = format!("Image '{}' is not tagged with a release version", image);
+        return vec![
+            condition("UpdateAvailable", false, "UnknownVersion", &message),
+            condition("VersionEOL", false, "UnknownVersion", &message),
+        ];
+    };
+
+    let note = advice
+        .note
+        .as_ref()
+        .map(|n| format!(" {}", n))
+        .unwrap_or_default();
+    let update = match (&advice.update, advice.status) {
+        (_, SupportStatus::Deprecated) => condition(
+            "UpdateAvailable",
+            true,
+            "Deprecated",
+            &format!("OpenFGA {} is deprecated.{}", advice.version, note),
+        ),
+        (Some(latest), _) => condition(
+            "UpdateAvailable",
+            true,
+            "NewerVersion",
+            &format!(
+                "OpenFGA {} is available, running {}",
+                latest, advice.version
+            ),
+        ),
+        (None, _) => condition(
+            "UpdateAvailable",
+            false,
+            "UpToDate",
+            &format!("OpenFGA {} is the latest release", advice.version),
+        ),
+    };
+    let eol = if advice.status == SupportStatus::EndOfLife {
+        condition(
+            "VersionEOL",
+            true,
+            "EndOfLife",
+            &format!("OpenFGA {} is end of life.{}", advice.version, note),
+        )
+    } else {
+        condition(
+            "VersionEOL",
+            false,
+            "Supported",
+            &format!("OpenFGA {} is not end of life", advice.version),
+        )
+    };
+
+    vec![update, eol]
+}
+
+/// Derives the `Ready`, `Progressing` and `Degraded` conditions from the owned Deployment.
+fn rollout_conditions(
+    deployment: Option<&Deployment>,
+    desired_replicas: i32,
+    generation: Option<i64>,
+    previous: Option<&[OpenFGACondition]>,
+) -> Vec<OpenFGACondition> {
+    let condition = |type_, status, reason, message: &str| {
+        OpenFGACondition::new(type_, status, reason, message, generation, previous)
+    };
+
+    let Some(deployment) = deployment else {
+        return vec![
+            condition(
+                "Ready",
+                false,
+                "DeploymentNotFound",
+                "OpenFGA deployment has not been created yet",
+            ),
+            condition(
+                "Progressing",
+                true,
+                "Creating",
+                "Waiting for the OpenFGA deployment to be created",
+            ),
+            condition("Degraded", false, "AsExpected", "No failures observed"),
+        ];
+    };
+
+    let status = deployment.status.clone().unwrap_or_default();
+    let ready = status.ready_replicas.unwrap_or(0);
+    let updated = status.updated_replicas.unwrap_or(0);
+    let total = status.replicas.unwrap_or(0);
+    let deployment_current = match (status.observed_generation, deployment.metadata.generation) {
+        (Some(observed), Some(generation)) => observed >= generation,
+        _ => false,
+    };
+    let deployment_condition = |type_: &str| {
+        status
+            .conditions
+            .iter()
+            .flatten()
+            .find(|c| c.type_ == type_)
+    };
+    let deadline_exceeded = deployment_condition("Progressing").is_some_and(|c| {
+        c.status == "False" && c.reason.as_deref() == Some("ProgressDeadlineExceeded")
+    });
+    let unavailable = deployment_condition("Available").is_some_and(|c| c.status == "False");
+    let rolled_out = deployment_current
+        && updated >= desired_replicas
+        && total <= desired_replicas
+        && ready >= desired_replicas;
+    // Available replicas have been ready for minReadySeconds; older workloads may not report them
+    let available = status.available_replicas.unwrap_or(ready);
+    let replicas_message = format!("{}/{} replicas ready", ready, desired_replicas);
+
+    let ready_condition = if ready < desired_replicas {
+        condition("Ready", false, "ReplicasNotReady", &replicas_message)
+    } else if ready > desired_replicas {
+        condition(
+            "Ready",
+            false,
+            "SurplusReplicas",
+            &format!(
+                "{}, waiting for surplus pods to terminate",
+                replicas_message
+            ),
+        )
+    } else if available < desired_replicas {
+        condition(
+            "Ready",
+            false,
+            "MinReadySecondsPending",
+            &format!(
+                "{}, {} available after minReadySeconds",
+                replicas_message, available
+            ),
+        )
+    } else {
+        condition("Ready", true, "ReplicasReady", &replicas_message)
     };
 
+    let progressing = if deadline_exceeded {
+        condition(
+            "Progressing",
+            false,
+            "ProgressDeadlineExceeded",
+            "Rollout did not complete within the progress deadline",
+        )
+    } else if rolled_out {
+        condition(
+            "Progressing",
+            false,
+            "RolloutComplete",
+            &format!("{} replicas updated and ready", desired_replicas),
+        )
+    } else {
+        condition(
+            "Progressing",
+            true,
+            "RollingOut",
+            &format!(
+                "{}/{} replicas updated, {}",
+                updated, desired_replicas, replicas_message
+            ),
+        )
+    };
+
+    let degraded = if deadline_exceeded {
+        condition(
+            "Degraded",
+            true,
+            "ProgressDeadlineExceeded",
+            "Rollout is stuck, check pod events for scheduling or image errors",
+        )
+    } else if unavailable {
+        condition(
+            "Degraded",
+            true,
+            "MinimumReplicasUnavailable",
+            &replicas_message,
+        )
+    } else {
+        condition("Degraded", false, "AsExpected", "No failures observed")
+    };
+
+    vec![ready_condition, progressing, degraded]
+}
+
+#[instrument(skip(ctx))]
+fn error_policy(
+    openfga: Arc<OpenFGA>,
+    error: &ControllerError,
+    ctx: Arc<OpenFGAController>,
+) -> Action {
+    let ns = openfga.namespace().unwrap_or_default();
+    let name = openfga.name_any();
+
+    let (error_type, base) = backoff::classify(error);
+    let key = backoff_key(&openfga);
+    let (attempt, requeue_duration) = ctx.backoff.next(&key, base);
+    debug_state::record_error(&key, error_type, &error.to_string(), attempt);
+
+    if error_type == "Permission" {
+        warn!(
+            namespace = %ns,
+            resource_name = %name,
+            error_type = error_type,
+            "Permission error - check RBAC configuration"
+        );
+    }
+
     error!(
         event = "reconciliation_error",
         namespace = %ns,
         resource_name = %name,
-        error_type = ?std::mem::discriminant(error),
+        error_type = error_type,
         error_message = %error,
+        attempt = attempt,
         requeue_after_seconds = requeue_duration.as_secs(),
-        "Reconciliation failed, scheduling retry with intelligent backoff"
+        "Reconciliation failed, scheduling retry with exponential backoff"
     );
 
+    metrics::record_reconcile_error::<OpenFGA>(CONTROLLER_NAME, error_type);
+
     Action::requeue(requeue_duration)
 }
 
+fn backoff_key(openfga: &OpenFGA) -> String {
+    format!(
+        "{}/{}",
+        openfga.namespace().unwrap_or_default(),
+        openfga.name_any()
+    )
+}
+
+/// The instance unit tests start from: `test-openfga` in `test-ns` with the
+/// memory datastore.
+#[cfg(test)]
+pub(crate) fn create_test_openfga() -> OpenFGA {
+    OpenFGA {
+        metadata: ObjectMeta {
+            name: Some("test-openfga".to_string()),
+            namespace: Some("test-ns".to_string()),
+            ..Default::default()
+        },
+        spec: crate::types::OpenFGASpec {
+            replicas: 2,
+            image: "openfga/openfga:v1.0.0".to_string(),
+            datastore: crate::types::DatastoreConfig {
+                engine: "memory".to_string(),
+                uri: None,
+                uri_secret_ref: None,
+                postgres: None,
+                mysql: None,
+            },
+            playground: crate::types::PlaygroundConfig {
+                enabled: false,
+                port: 3000,
+                auth_proxy: None,
+            },
+            grpc: crate::types::GrpcConfig { port: 8081 },
+            http: crate::types::HttpConfig { port: 8080 },
+            api_protocol: Default::default(),
+            env: vec![],
+            env_from: vec![],
+            config: BTreeMap::new(),
+            volumes: vec![],
+            volume_mounts: vec![],
+            resources: None,
+            probes: Default::default(),
+            tls: None,
+            ingress: None,
+            cache_volume: None,
+            observability: Default::default(),
+            logging: None,
+            deletion_policy: Default::default(),
+            deletion_confirmation_threshold: 1000,
+            pod_security_context: None,
+            security_context: None,
+            service_account: Default::default(),
+            service: Default::default(),
+            depends_on: vec![],
+            bootstrap: Default::default(),
+            labels: BTreeMap::new(),
+            annotations: BTreeMap::new(),
+            workload_type: Default::default(),
+            persistence: None,
+            upgrade_strategy: None,
+            termination: None,
+            min_ready_seconds: None,
+            priority_class_name: None,
+            runtime_class_name: None,
+            topology: None,
+            overrides_from: None,
+            target_cluster_secret_ref: None,
+            query_cache: None,
+            feature_flags: vec![],
+            resolve_image_digest: false,
+            image_verification: None,
+            image_pull_secrets: vec![],
+            image_pull_policy: None,
+        },
+        status: None,
+    }
+}
+
+/// [`create_test_openfga`] with `spec`, camelCase as in a manifest, merged over
+/// its spec.
+#[cfg(test)]
+pub(crate) fn test_openfga(spec: serde_json::Value) -> OpenFGA {
+    let mut openfga = create_test_openfga();
+    let mut merged = serde_json::to_value(&openfga.spec).unwrap();
+    json_patch::merge(&mut merged, &spec);
+    openfga.spec = serde_json::from_value(merged).unwrap();
+    openfga
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::{DatastoreConfig, GrpcConfig, HttpConfig, PlaygroundConfig};
+    use crate::types::{
+        ExternalTrafficPolicy, ImagePullPolicy, LogFormat, LogLevel, LoggingConfig, ResourceSpec,
+        SecretKeyReference, TerminationConfig, TlsConfig,
+    };
+
+    #[tokio::test]
+    async fn test_shutdown_requested_waits_for_signal() {
+        let (tx, rx) = watch::channel(false);
+        let mut waiting = Box::pin(shutdown_requested(rx.clone()));
+        assert!(futures::poll!(waiting.as_mut()).is_pending());
+
+        tx.send(true).unwrap();
+        waiting.await;
+        // Controllers subscribing after the signal stop at once
+        shutdown_requested(rx).await;
+    }
+
+    #[test]
+    fn test_error_kind() {
+        assert_eq!(ControllerError::Panic("boom".to_string()).kind(), "Panic");
+        assert_eq!(
+            ControllerError::TargetCluster("unreachable".to_string()).kind(),
+            "TargetCluster"
+        );
+    }
+
+    #[test]
+    fn test_spec_change_ignores_status() {
+        let mut openfga = create_test_openfga();
+        openfga.metadata.generation = Some(1);
+        let before = spec_change(&openfga);
+
+        openfga.status = Some(Default::default());
+        openfga.metadata.resource_version = Some("42".to_string());
+        assert_eq!(spec_change(&openfga), before);
+
+        openfga
+            .annotations_mut()
+            .insert(PAUSED_ANNOTATION.to_string(), "true".to_string());
+        assert_ne!(spec_change(&openfga), before);
+
+        openfga.annotations_mut().clear();
+        openfga.metadata.generation = Some(2);
+        assert_ne!(spec_change(&openfga), before);
+    }
+
+    #[test]
+    fn test_generated_resources_match_captured_fixture() {
+        use crate::fixtures::FixtureBundle;
+
+        let bundle =
+            FixtureBundle::from_yaml(include_str!("../tests/fixtures/postgres-openfga.yaml"))
+                .unwrap();
+        let openfga: OpenFGA = bundle.get().unwrap();
+        let (ns, name) = (openfga.namespace().unwrap(), openfga.name_any());
+
+        let captured = bundle.get::<Deployment>().unwrap().spec.unwrap();
+        let generated = create_deployment(&openfga, &ns, &name)
+            .unwrap()
+            .spec
+            .unwrap();
+        assert_eq!(generated.replicas, captured.replicas);
+        assert_eq!(generated.selector, captured.selector);
+        let captured = &captured.template.spec.unwrap().containers[0];
+        let generated = &generated.template.spec.unwrap().containers[0];
+        assert_eq!(generated.image, captured.image);
+        assert_eq!(generated.ports, captured.ports);
+        assert_eq!(generated.env, captured.env);
+
+        let captured = bundle.get::<Service>().unwrap().spec.unwrap();
+        let generated = create_service(&openfga, &ns, &name).unwrap().spec.unwrap();
+        assert_eq!(generated.selector, captured.selector);
+        assert_eq!(generated.ports, captured.ports);
+    }
 
     #[test]
     fn test_create_deployment() {
@@ -779,6 +2646,33 @@ mod tests {
             .any(|p| p.name == Some("http".to_string()) && p.port == 8080));
     }
 
+    #[test]
+    fn test_playground_behind_auth_proxy() {
+        let mut openfga = create_test_openfga();
+        openfga.spec.playground.enabled = true;
+        openfga.spec.playground.auth_proxy = Some(crate::types::PlaygroundAuthProxy {
+            type_: crate::types::AuthProxyType::BasicAuth,
+            image: None,
+            port: 4180,
+            secret_name: "playground-htpasswd".to_string(),
+        });
+
+        let service = create_service(&openfga, "test-ns", "test-openfga").unwrap();
+        let ports = service.spec.unwrap().ports.unwrap();
+        assert!(!ports
+            .iter()
+            .any(|p| p.name.as_deref() == Some("playground")));
+
+        let deployment = create_deployment(&openfga, "test-ns", "test-openfga").unwrap();
+        let pod = deployment.spec.unwrap().template.spec.unwrap();
+        assert_eq!(pod.containers[1].name, playground::PROXY_CONTAINER);
+        assert!(pod.volumes.unwrap().iter().any(|v| v
+            .config_map
+            .as_ref()
+            .and_then(|c| c.name.as_deref())
+            == Some("test-openfga-playground-proxy")));
+    }
+
     #[test]
     fn test_create_service_with_playground() {
         let mut openfga = create_test_openfga();
@@ -794,30 +2688,978 @@ mod tests {
             .any(|p| p.name == Some("playground".to_string()) && p.port == 3000));
     }
 
-    fn create_test_openfga() -> OpenFGA {
-        use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+    #[test]
+    fn test_create_service_load_balancer() {
+        let mut openfga = create_test_openfga();
+        openfga.spec.service = serde_json::from_value(serde_json::json!({
+            "type": "LoadBalancer",
+            "annotations": { "service.beta.kubernetes.io/aws-load-balancer-type": "nlb" },
+            "externalTrafficPolicy": "Local",
+            "nodePorts": { "grpc": 30081 },
+            "loadBalancerSourceRanges": ["10.0.0.0/8"],
+        }))
+        .unwrap();
+
+        let service = create_service(&openfga, "test-ns", "test-openfga").unwrap();
+        assert!(service
+            .metadata
+            .annotations
+            .unwrap()
+            .contains_key("service.beta.kubernetes.io/aws-load-balancer-type"));
+
+        let spec = service.spec.unwrap();
+        assert_eq!(spec.type_.as_deref(), Some("LoadBalancer"));
+        assert_eq!(spec.external_traffic_policy.as_deref(), Some("Local"));
+        assert_eq!(
+            spec.load_balancer_source_ranges.unwrap(),
+            vec!["10.0.0.0/8"]
+        );
+        let ports = spec.ports.unwrap();
+        assert_eq!(ports[0].node_port, Some(30081));
+        assert_eq!(ports[1].node_port, None);
+    }
+
+    #[test]
+    fn test_create_service_headless() {
+        let mut openfga = create_test_openfga();
+        openfga.spec.service.type_ = ServiceType::Headless;
+        openfga.spec.service.node_ports.grpc = Some(30081);
+        openfga.spec.service.external_traffic_policy = Some(ExternalTrafficPolicy::Local);
+
+        let headless = create_service(&openfga, "test-ns", "test-openfga").unwrap();
+        let spec = headless.spec.as_ref().unwrap();
+        assert_eq!(spec.type_.as_deref(), Some("ClusterIP"));
+        assert_eq!(spec.cluster_ip.as_deref(), Some("None"));
+        assert!(spec.external_traffic_policy.is_none());
+        assert!(spec.ports.as_ref().unwrap()[0].node_port.is_none());
+
+        let cluster_ip = create_service(&create_test_openfga(), "test-ns", "test-openfga").unwrap();
+        assert!(requires_recreate(&cluster_ip, &headless));
+        assert!(!requires_recreate(&headless, &headless));
+    }
+
+    #[test]
+    fn test_create_deployment_with_datastore_uri_secret_ref() {
+        let mut openfga = create_test_openfga();
+        openfga.spec.datastore.engine = "postgres".to_string();
+        openfga.spec.datastore.uri = Some("postgresql://ignored".to_string());
+        openfga.spec.datastore.uri_secret_ref = Some(crate::types::SecretKeyReference {
+            name: "pg-creds".to_string(),
+            key: "uri".to_string(),
+        });
+
+        let deployment = create_deployment(&openfga, "test-ns", "test-openfga").unwrap();
+        let container = &deployment.spec.unwrap().template.spec.unwrap().containers[0];
+        let env = container.env.as_ref().unwrap();
+
+        let uri_var = env
+            .iter()
+            .find(|e| e.name == "OPENFGA_DATASTORE_URI")
+            .unwrap();
+        assert_eq!(uri_var.value, None);
+        let secret_ref = uri_var
+            .value_from
+            .as_ref()
+            .and_then(|v| v.secret_key_ref.as_ref())
+            .unwrap();
+        assert_eq!(secret_ref.name, Some("pg-creds".to_string()));
+        assert_eq!(secret_ref.key, "uri");
+    }
+
+    #[test]
+    fn test_create_deployment_engine_settings() {
+        let mut openfga = create_test_openfga();
+        openfga.spec.datastore.engine = "postgres".to_string();
+        openfga.spec.datastore.uri = Some("postgresql://db/openfga".to_string());
+        openfga.spec.datastore.postgres = Some(crate::types::PostgresConfig {
+            max_open_conns: Some(30),
+            max_idle_conns: Some(10),
+            sslmode: Some("verify-full".to_string()),
+        });
+        // Settings for another engine are ignored
+        openfga.spec.datastore.mysql = Some(crate::types::MySQLConfig {
+            max_open_conns: Some(5),
+            ..Default::default()
+        });
+
+        let env = create_datastore_env(&openfga);
+        let value = |name: &str| {
+            env.iter()
+                .find(|e| e.name == name)
+                .and_then(|e| e.value.clone())
+        };
+        assert_eq!(
+            value("OPENFGA_DATASTORE_MAX_OPEN_CONNS").as_deref(),
+            Some("30")
+        );
+        assert_eq!(
+            value("OPENFGA_DATASTORE_MAX_IDLE_CONNS").as_deref(),
+            Some("10")
+        );
+        assert_eq!(value("PGSSLMODE").as_deref(), Some("verify-full"));
+
+        openfga.spec.datastore.engine = "mysql".to_string();
+        openfga.spec.datastore.uri =
+            Some("user:pass@tcp(db:3306)/openfga?parseTime=true".to_string());
+        openfga.spec.datastore.mysql = Some(crate::types::MySQLConfig {
+            max_open_conns: None,
+            max_idle_conns: Some(2),
+            tls: Some("true".to_string()),
+        });
+        let env = create_datastore_env(&openfga);
+        let value = |name: &str| {
+            env.iter()
+                .find(|e| e.name == name)
+                .and_then(|e| e.value.clone())
+        };
+        assert_eq!(
+            value("OPENFGA_DATASTORE_URI").as_deref(),
+            Some("user:pass@tcp(db:3306)/openfga?parseTime=true&tls=true")
+        );
+        assert_eq!(value("OPENFGA_DATASTORE_MAX_OPEN_CONNS"), None);
+        assert_eq!(
+            value("OPENFGA_DATASTORE_MAX_IDLE_CONNS").as_deref(),
+            Some("2")
+        );
+        assert_eq!(value("PGSSLMODE"), None);
+
+        assert_eq!(
+            with_query_param("user@tcp(db)/openfga?tls=false", "tls", "true"),
+            "user@tcp(db)/openfga?tls=false"
+        );
+    }
+
+    #[test]
+    fn test_create_deployment_merges_user_env() {
+        use k8s_openapi::api::core::v1::{ConfigMapEnvSource, EnvFromSource};
+
+        let mut openfga = create_test_openfga();
+        openfga.spec.env = vec![
+            EnvVar {
+                name: "OPENFGA_LOG_LEVEL".to_string(),
+                value: Some("debug".to_string()),
+                ..Default::default()
+            },
+            EnvVar {
+                name: "OPENFGA_DATASTORE_ENGINE".to_string(),
+                value: Some("postgres".to_string()),
+                ..Default::default()
+            },
+        ];
+        openfga.spec.env_from = vec![EnvFromSource {
+            config_map_ref: Some(ConfigMapEnvSource {
+                name: Some("openfga-env".to_string()),
+                optional: None,
+            }),
+            ..Default::default()
+        }];
+
+        let deployment = create_deployment(&openfga, "test-ns", "test-openfga").unwrap();
+        let container = &deployment.spec.unwrap().template.spec.unwrap().containers[0];
+        let env = container.env.as_ref().unwrap();
+
+        let names: Vec<&str> = env.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["OPENFGA_LOG_LEVEL", "OPENFGA_DATASTORE_ENGINE"]);
+        assert_eq!(env[1].value, Some("postgres".to_string()));
+        assert_eq!(container.env_from.as_ref().map(|e| e.len()), Some(1));
+    }
+
+    #[test]
+    fn test_create_deployment_with_resources() {
+        let mut openfga = create_test_openfga();
+        openfga.spec.resources = Some(ResourceSpec {
+            requests: Some(ResourceQuantities {
+                cpu: Some("250m".to_string()),
+                memory: Some("512Mi".to_string()),
+                ephemeral_storage: Some("1Gi".to_string()),
+            }),
+            limits: Some(ResourceQuantities {
+                cpu: None,
+                memory: Some("2Gi".to_string()),
+                ephemeral_storage: Some("4Gi".to_string()),
+            }),
+        });
+
+        let deployment = create_deployment(&openfga, "test-ns", "test-openfga").unwrap();
+        let container = &deployment.spec.unwrap().template.spec.unwrap().containers[0];
+        let resources = container.resources.as_ref().unwrap();
+
+        let requests = resources.requests.as_ref().unwrap();
+        assert_eq!(requests.get("cpu"), Some(&Quantity("250m".to_string())));
+        assert_eq!(requests.get("memory"), Some(&Quantity("512Mi".to_string())));
+        assert_eq!(
+            requests.get("ephemeral-storage"),
+            Some(&Quantity("1Gi".to_string()))
+        );
+        let limits = resources.limits.as_ref().unwrap();
+        assert_eq!(limits.len(), 2);
+        assert_eq!(limits.get("memory"), Some(&Quantity("2Gi".to_string())));
+        assert_eq!(
+            limits.get("ephemeral-storage"),
+            Some(&Quantity("4Gi".to_string()))
+        );
+    }
 
-        OpenFGA {
+    #[test]
+    fn test_metrics_port_and_env() {
+        let mut openfga = create_test_openfga();
+        openfga.spec.observability.metrics.enabled = true;
+        openfga.spec.observability.metrics.port = 9090;
+
+        let deployment = create_deployment(&openfga, "test-ns", "test-openfga").unwrap();
+        let container = &deployment.spec.unwrap().template.spec.unwrap().containers[0];
+        let ports = container.ports.as_ref().unwrap();
+        assert!(ports
+            .iter()
+            .any(|p| p.name.as_deref() == Some("metrics") && p.container_port == 9090));
+        let env = container.env.as_ref().unwrap();
+        assert!(env.iter().any(
+            |e| e.name == "OPENFGA_METRICS_ADDR" && e.value.as_deref() == Some("0.0.0.0:9090")
+        ));
+
+        let service = create_service(&openfga, "test-ns", "test-openfga").unwrap();
+        let ports = service.spec.unwrap().ports.unwrap();
+        assert!(ports
+            .iter()
+            .any(|p| p.name.as_deref() == Some("metrics") && p.port == 9090));
+    }
+
+    #[test]
+    fn test_feature_flags_args() {
+        let mut openfga = create_test_openfga();
+        let deployment = create_deployment(&openfga, "test-ns", "test-openfga").unwrap();
+        assert!(
+            deployment.spec.unwrap().template.spec.unwrap().containers[0]
+                .args
+                .is_none()
+        );
+
+        openfga.spec.feature_flags = vec![
+            "enable-check-optimizations".to_string(),
+            "enable-list-objects-optimizations".to_string(),
+        ];
+        let deployment = create_deployment(&openfga, "test-ns", "test-openfga").unwrap();
+        assert_eq!(
+            deployment.spec.unwrap().template.spec.unwrap().containers[0]
+                .args
+                .as_deref()
+                .unwrap(),
+            [
+                "run",
+                "--experimentals=enable-check-optimizations,enable-list-objects-optimizations"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_logging_env() {
+        let mut openfga = create_test_openfga();
+        openfga.spec.logging = Some(LoggingConfig {
+            level: Some(LogLevel::Debug),
+            format: Some(LogFormat::Json),
+        });
+        let env = create_container_env(&openfga);
+        let value = |name: &str| {
+            env.iter()
+                .find(|e| e.name == name)
+                .and_then(|e| e.value.clone())
+        };
+        assert_eq!(value("OPENFGA_LOG_LEVEL").as_deref(), Some("debug"));
+        assert_eq!(value("OPENFGA_LOG_FORMAT").as_deref(), Some("json"));
+
+        // Unset fields leave OpenFGA's defaults or the server config in effect
+        openfga.spec.logging = Some(LoggingConfig {
+            level: Some(LogLevel::Warn),
+            format: None,
+        });
+        let env = create_container_env(&openfga);
+        assert!(env.iter().any(|e| e.name == "OPENFGA_LOG_LEVEL"));
+        assert!(!env.iter().any(|e| e.name == "OPENFGA_LOG_FORMAT"));
+    }
+
+    #[test]
+    fn test_tracing_env() {
+        let mut openfga = create_test_openfga();
+        assert!(create_tracing_env(&openfga).is_empty());
+
+        let tracing = &mut openfga.spec.observability.tracing;
+        tracing.enabled = true;
+        tracing.otlp_endpoint = Some("otel-collector.observability:4317".to_string());
+        tracing.sample_ratio = Some(0.25);
+        tracing.headers = BTreeMap::from([
+            ("x-tenant".to_string(), "auth".to_string()),
+            ("x-env".to_string(), "prod".to_string()),
+        ]);
+        let env = create_container_env(&openfga);
+        let value = |name: &str| {
+            env.iter()
+                .find(|e| e.name == name)
+                .and_then(|e| e.value.clone())
+        };
+        assert_eq!(value("OPENFGA_TRACE_ENABLED").as_deref(), Some("true"));
+        assert_eq!(
+            value("OPENFGA_TRACE_OTLP_ENDPOINT").as_deref(),
+            Some("otel-collector.observability:4317")
+        );
+        assert_eq!(value("OPENFGA_TRACE_SAMPLE_RATIO").as_deref(), Some("0.25"));
+        assert_eq!(
+            value("OPENFGA_TRACE_OTLP_TLS_ENABLED").as_deref(),
+            Some("false")
+        );
+        assert_eq!(
+            value("OTEL_EXPORTER_OTLP_HEADERS").as_deref(),
+            Some("x-env=prod,x-tenant=auth")
+        );
+
+        // Headers from a Secret never land in the pod spec
+        openfga.spec.observability.tracing.headers_secret_ref = Some(SecretKeyReference {
+            name: "otlp".to_string(),
+            key: "headers".to_string(),
+        });
+        let env = create_tracing_env(&openfga);
+        let headers = env
+            .iter()
+            .find(|e| e.name == "OTEL_EXPORTER_OTLP_HEADERS")
+            .unwrap();
+        assert!(headers.value.is_none());
+        assert_eq!(
+            headers
+                .value_from
+                .as_ref()
+                .and_then(|v| v.secret_key_ref.as_ref())
+                .map(|s| s.key.as_str()),
+            Some("headers")
+        );
+    }
+
+    #[test]
+    fn test_image_pull_settings() {
+        use k8s_openapi::api::core::v1::LocalObjectReference;
+
+        let mut openfga = create_test_openfga();
+        let pod_spec = |openfga: &OpenFGA| {
+            create_deployment(openfga, "test-ns", "test-openfga")
+                .unwrap()
+                .spec
+                .unwrap()
+                .template
+                .spec
+                .unwrap()
+        };
+        let spec = pod_spec(&openfga);
+        assert!(spec.image_pull_secrets.is_none());
+        assert!(spec.containers[0].image_pull_policy.is_none());
+
+        openfga.spec.image_pull_secrets = vec![LocalObjectReference {
+            name: Some("mirror-credentials".to_string()),
+        }];
+        openfga.spec.image_pull_policy = Some(ImagePullPolicy::IfNotPresent);
+        let spec = pod_spec(&openfga);
+        assert_eq!(
+            spec.image_pull_secrets.unwrap()[0].name.as_deref(),
+            Some("mirror-credentials")
+        );
+        assert_eq!(
+            spec.containers[0].image_pull_policy.as_deref(),
+            Some("IfNotPresent")
+        );
+    }
+
+    #[test]
+    fn test_priority_and_runtime_class() {
+        let mut openfga = create_test_openfga();
+        openfga.spec.priority_class_name = Some("system-cluster-critical".to_string());
+        openfga.spec.runtime_class_name = Some("gvisor".to_string());
+        let spec = create_deployment(&openfga, "test-ns", "test-openfga")
+            .unwrap()
+            .spec
+            .unwrap()
+            .template
+            .spec
+            .unwrap();
+        assert_eq!(
+            spec.priority_class_name.as_deref(),
+            Some("system-cluster-critical")
+        );
+        assert_eq!(spec.runtime_class_name.as_deref(), Some("gvisor"));
+    }
+
+    #[test]
+    fn test_termination_settings() {
+        let mut openfga = create_test_openfga();
+        let pod_spec = |openfga: &OpenFGA| {
+            create_deployment(openfga, "test-ns", "test-openfga")
+                .unwrap()
+                .spec
+                .unwrap()
+                .template
+                .spec
+                .unwrap()
+        };
+        let spec = pod_spec(&openfga);
+        assert_eq!(spec.termination_grace_period_seconds, None);
+        assert!(spec.containers[0].lifecycle.is_none());
+
+        openfga.spec.termination = Some(TerminationConfig {
+            grace_period_seconds: None,
+            pre_stop_sleep_seconds: Some(10),
+        });
+        let spec = pod_spec(&openfga);
+        assert_eq!(spec.termination_grace_period_seconds, Some(40));
+        let command = spec.containers[0]
+            .lifecycle
+            .as_ref()
+            .and_then(|l| l.pre_stop.as_ref())
+            .and_then(|h| h.exec.as_ref())
+            .and_then(|e| e.command.clone());
+        assert_eq!(command, Some(vec!["sleep".to_string(), "10".to_string()]));
+
+        openfga.spec.termination = Some(TerminationConfig {
+            grace_period_seconds: Some(60),
+            pre_stop_sleep_seconds: None,
+        });
+        let spec = pod_spec(&openfga);
+        assert_eq!(spec.termination_grace_period_seconds, Some(60));
+        assert!(spec.containers[0].lifecycle.is_none());
+    }
+
+    #[test]
+    fn test_security_context_defaults_and_overrides() {
+        let mut openfga = create_test_openfga();
+        let deployment = create_deployment(&openfga, "test-ns", "test-openfga").unwrap();
+        let pod = deployment.spec.unwrap().template.spec.unwrap();
+        let pod_context = pod.security_context.unwrap();
+        assert_eq!(pod_context.run_as_non_root, Some(true));
+        assert_eq!(pod_context.run_as_user, Some(65532));
+        assert_eq!(pod_context.seccomp_profile.unwrap().type_, "RuntimeDefault");
+        let container_context = pod.containers[0].security_context.clone().unwrap();
+        assert_eq!(container_context.read_only_root_filesystem, Some(true));
+        assert_eq!(container_context.allow_privilege_escalation, Some(false));
+        assert_eq!(
+            container_context.capabilities.unwrap().drop,
+            Some(vec!["ALL".to_string()])
+        );
+
+        openfga.spec.pod_security_context = Some(PodSecurityContext {
+            run_as_user: Some(1000),
+            ..Default::default()
+        });
+        openfga.spec.security_context = Some(SecurityContext {
+            read_only_root_filesystem: Some(false),
+            ..Default::default()
+        });
+        let deployment = create_deployment(&openfga, "test-ns", "test-openfga").unwrap();
+        let pod = deployment.spec.unwrap().template.spec.unwrap();
+        let pod_context = pod.security_context.unwrap();
+        assert_eq!(pod_context.run_as_user, Some(1000));
+        assert_eq!(pod_context.run_as_non_root, Some(true));
+        let container_context = pod.containers[0].security_context.clone().unwrap();
+        assert_eq!(container_context.read_only_root_filesystem, Some(false));
+        assert_eq!(container_context.allow_privilege_escalation, Some(false));
+    }
+
+    #[test]
+    fn test_create_deployment_cache_volume() {
+        let mut openfga = create_test_openfga();
+        let deployment = create_deployment(&openfga, "test-ns", "test-openfga").unwrap();
+        let pod = deployment.spec.unwrap().template.spec.unwrap();
+        assert!(pod.volumes.is_none());
+        assert!(pod.containers[0].volume_mounts.is_none());
+
+        openfga.spec.cache_volume = Some(CacheVolumeConfig {
+            medium: CacheVolumeMedium::Memory,
+            size_limit: Some("256Mi".to_string()),
+            mount_path: "/var/cache/openfga".to_string(),
+        });
+        let deployment = create_deployment(&openfga, "test-ns", "test-openfga").unwrap();
+        let pod = deployment.spec.unwrap().template.spec.unwrap();
+
+        let volumes = pod.volumes.unwrap();
+        let empty_dir = volumes[0].empty_dir.as_ref().unwrap();
+        assert_eq!(empty_dir.medium.as_deref(), Some("Memory"));
+        assert_eq!(empty_dir.size_limit, Some(Quantity("256Mi".to_string())));
+        let mounts = pod.containers[0].volume_mounts.as_ref().unwrap();
+        assert_eq!(mounts[0].name, volumes[0].name);
+        assert_eq!(mounts[0].mount_path, "/var/cache/openfga");
+    }
+
+    #[test]
+    fn test_create_deployment_server_config() {
+        let mut openfga = create_test_openfga();
+        let deployment = create_deployment(&openfga, "test-ns", "test-openfga").unwrap();
+        let template = deployment.spec.unwrap().template;
+        assert!(template.metadata.unwrap().annotations.is_none());
+
+        openfga.spec.config = serde_json::from_value(serde_json::json!({
+            "log": { "level": "debug" },
+        }))
+        .unwrap();
+        let deployment = create_deployment(&openfga, "test-ns", "test-openfga").unwrap();
+        let template = deployment.spec.unwrap().template;
+        let annotations = template.metadata.unwrap().annotations.unwrap();
+        let hash = annotations[server_config::CONFIG_HASH_ANNOTATION].clone();
+
+        let env_from = template.spec.unwrap().containers[0]
+            .env_from
+            .clone()
+            .unwrap();
+        assert_eq!(
+            env_from[0].config_map_ref.as_ref().unwrap().name.as_deref(),
+            Some("test-openfga-config")
+        );
+
+        openfga.spec.config = serde_json::from_value(serde_json::json!({
+            "log": { "level": "info" },
+        }))
+        .unwrap();
+        let deployment = create_deployment(&openfga, "test-ns", "test-openfga").unwrap();
+        let annotations = deployment
+            .spec
+            .unwrap()
+            .template
+            .metadata
+            .unwrap()
+            .annotations;
+        assert_ne!(
+            annotations.unwrap()[server_config::CONFIG_HASH_ANNOTATION],
+            hash
+        );
+    }
+
+    #[test]
+    fn test_create_deployment_extra_volumes() {
+        let mut openfga = create_test_openfga();
+        openfga.spec.cache_volume = Some(CacheVolumeConfig {
+            medium: CacheVolumeMedium::Disk,
+            size_limit: None,
+            mount_path: "/tmp".to_string(),
+        });
+        openfga.spec.volumes = serde_json::from_value(serde_json::json!([
+            { "name": "ca-bundle", "configMap": { "name": "corporate-ca" } },
+            { "name": "audit", "persistentVolumeClaim": { "claimName": "openfga-audit" } },
+        ]))
+        .unwrap();
+        openfga.spec.volume_mounts = serde_json::from_value(serde_json::json!([
+            { "name": "ca-bundle", "mountPath": "/etc/ssl/custom", "readOnly": true },
+            { "name": "audit", "mountPath": "/var/log/openfga" },
+        ]))
+        .unwrap();
+
+        let deployment = create_deployment(&openfga, "test-ns", "test-openfga").unwrap();
+        let pod = deployment.spec.unwrap().template.spec.unwrap();
+
+        let volumes = pod.volumes.unwrap();
+        let names: Vec<_> = volumes.iter().map(|v| v.name.as_str()).collect();
+        assert_eq!(names, vec![CACHE_VOLUME_NAME, "ca-bundle", "audit"]);
+        assert_eq!(
+            volumes[1].config_map.as_ref().unwrap().name.as_deref(),
+            Some("corporate-ca")
+        );
+        let mounts = pod.containers[0].volume_mounts.as_ref().unwrap();
+        assert_eq!(mounts.len(), 3);
+        assert_eq!(mounts[1].read_only, Some(true));
+        assert_eq!(mounts[2].mount_path, "/var/log/openfga");
+    }
+
+    #[test]
+    fn test_create_deployment_probes() {
+        let mut openfga = create_test_openfga();
+        openfga.spec.probes.liveness.failure_threshold = Some(6);
+        openfga.spec.probes.startup.enabled = false;
+
+        let deployment = create_deployment(&openfga, "test-ns", "test-openfga").unwrap();
+        let container = &deployment.spec.unwrap().template.spec.unwrap().containers[0];
+
+        let liveness = container.liveness_probe.as_ref().unwrap();
+        let http_get = liveness.http_get.as_ref().unwrap();
+        assert_eq!(http_get.path, Some("/healthz".to_string()));
+        assert_eq!(http_get.port, IntOrString::Int(8080));
+        assert_eq!(liveness.failure_threshold, Some(6));
+        assert_eq!(liveness.period_seconds, Some(10));
+
+        let readiness = container.readiness_probe.as_ref().unwrap();
+        assert_eq!(readiness.grpc.as_ref().map(|g| g.port), Some(8081));
+
+        assert!(container.startup_probe.is_none());
+    }
+
+    #[test]
+    fn test_create_deployment_grpc_tls() {
+        let mut openfga = create_test_openfga();
+        openfga.spec.tls = Some(TlsConfig {
+            secret_name: "openfga-grpc-tls".to_string(),
+        });
+
+        let deployment = create_deployment(&openfga, "test-ns", "test-openfga").unwrap();
+        let pod = deployment.spec.unwrap().template.spec.unwrap();
+        let volume = &pod.volumes.as_ref().unwrap()[0];
+        assert_eq!(
+            volume.secret.as_ref().unwrap().secret_name.as_deref(),
+            Some("openfga-grpc-tls")
+        );
+        let container = &pod.containers[0];
+        let mount = &container.volume_mounts.as_ref().unwrap()[0];
+        assert_eq!(mount.mount_path, "/etc/openfga/tls");
+        assert_eq!(mount.read_only, Some(true));
+
+        let env = container.env.as_ref().unwrap();
+        let value = |name: &str| {
+            env.iter()
+                .find(|e| e.name == name)
+                .and_then(|e| e.value.clone())
+        };
+        assert_eq!(value("OPENFGA_GRPC_TLS_ENABLED").as_deref(), Some("true"));
+        assert_eq!(
+            value("OPENFGA_GRPC_TLS_CERT").as_deref(),
+            Some("/etc/openfga/tls/tls.crt")
+        );
+
+        let readiness = container.readiness_probe.as_ref().unwrap();
+        assert!(readiness.grpc.is_none());
+        assert!(readiness.http_get.is_some());
+    }
+
+    #[test]
+    fn test_readiness_probe_follows_tls_settings() {
+        let readiness = |openfga: &OpenFGA| {
+            create_deployment(openfga, "test-ns", "test-openfga")
+                .unwrap()
+                .spec
+                .unwrap()
+                .template
+                .spec
+                .unwrap()
+                .containers[0]
+                .readiness_probe
+                .clone()
+                .unwrap()
+        };
+
+        // TLS switched on through the server config instead of spec.tls
+        let mut openfga = create_test_openfga();
+        openfga.spec.config = serde_json::from_value(serde_json::json!({
+            "grpc": { "tls": { "enabled": true } }
+        }))
+        .unwrap();
+        let probe = readiness(&openfga);
+        assert!(probe.grpc.is_none());
+        assert_eq!(probe.http_get.unwrap().scheme.as_deref(), Some("HTTP"));
+
+        // With the HTTP listener on TLS too, the probe uses HTTPS
+        openfga.spec.env = vec![EnvVar {
+            name: "OPENFGA_HTTP_TLS_ENABLED".to_string(),
+            value: Some("true".to_string()),
+            ..Default::default()
+        }];
+        let probe = readiness(&openfga);
+        assert_eq!(probe.http_get.unwrap().scheme.as_deref(), Some("HTTPS"));
+
+        // spec.env shadows the config
+        openfga.spec.env[0].name = "OPENFGA_GRPC_TLS_ENABLED".to_string();
+        openfga.spec.env[0].value = Some("false".to_string());
+        assert!(readiness(&openfga).grpc.is_some());
+    }
+
+    #[test]
+    fn test_create_deployment_extended_resources() {
+        let mut openfga = create_test_openfga();
+        openfga.metadata.annotations = Some(BTreeMap::from([(
+            conversion::EXTENDED_RESOURCES_ANNOTATION.to_string(),
+            r#"{"limits":{"nvidia.com/gpu":"1"}}"#.to_string(),
+        )]));
+
+        let deployment = create_deployment(&openfga, "test-ns", "test-openfga").unwrap();
+        let container = &deployment.spec.unwrap().template.spec.unwrap().containers[0];
+        let limits = container
+            .resources
+            .as_ref()
+            .unwrap()
+            .limits
+            .as_ref()
+            .unwrap();
+        assert_eq!(limits["nvidia.com/gpu"], Quantity("1".to_string()));
+    }
+
+    #[test]
+    fn test_pod_zones() {
+        use k8s_openapi::api::core::v1::PodSpec;
+
+        let pod_on = |node: &str| Pod {
+            spec: Some(PodSpec {
+                node_name: Some(node.to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let pods = vec![
+            pod_on("node-1"),
+            pod_on("node-2"),
+            pod_on("node-3"),
+            Pod::default(),
+        ];
+        let node_zones = BTreeMap::from([
+            ("node-1".to_string(), "zone-b".to_string()),
+            ("node-2".to_string(), "zone-a".to_string()),
+            ("node-3".to_string(), "zone-b".to_string()),
+        ]);
+
+        assert_eq!(pod_zones(&pods, &node_zones), vec!["zone-a", "zone-b"]);
+    }
+
+    #[test]
+    fn test_node_zone_prefers_ga_label() {
+        let node = Node {
             metadata: ObjectMeta {
-                name: Some("test-openfga".to_string()),
-                namespace: Some("test-ns".to_string()),
+                labels: Some(BTreeMap::from([
+                    (LEGACY_ZONE_LABEL.to_string(), "legacy".to_string()),
+                    (ZONE_LABEL.to_string(), "zone-a".to_string()),
+                ])),
                 ..Default::default()
             },
-            spec: crate::types::OpenFGASpec {
-                replicas: 2,
-                image: "openfga/openfga:v1.0.0".to_string(),
-                datastore: DatastoreConfig {
-                    engine: "memory".to_string(),
-                    uri: None,
-                },
-                playground: PlaygroundConfig {
-                    enabled: false,
-                    port: 3000,
-                },
-                grpc: GrpcConfig { port: 8081 },
-                http: HttpConfig { port: 8080 },
+            ..Default::default()
+        };
+        assert_eq!(node_zone(&node), Some("zone-a".to_string()));
+        assert_eq!(node_zone(&Node::default()), None);
+    }
+
+    fn deployment_with_status(
+        generation: i64,
+        status: k8s_openapi::api::apps::v1::DeploymentStatus,
+    ) -> Deployment {
+        Deployment {
+            metadata: ObjectMeta {
+                generation: Some(generation),
+                ..Default::default()
             },
-            status: None,
+            status: Some(status),
+            ..Default::default()
         }
     }
+
+    fn find<'a>(conditions: &'a [OpenFGACondition], type_: &str) -> &'a OpenFGACondition {
+        conditions.iter().find(|c| c.type_ == type_).unwrap()
+    }
+
+    #[test]
+    fn test_advisory_conditions() {
+        let data: advisory::Advisories = serde_yaml::from_str(
+            "latest: v1.8.4\nseries:\n  - version: v1.5\n    status: Deprecated\n\
+             \x20 - version: v1.3\n    status: EndOfLife\n    note: Upgrade now.\n",
+        )
+        .unwrap();
+        let conditions =
+            |image| advisory_conditions(image, data.advise(image).as_ref(), Some(1), None);
+
+        let unknown = conditions("openfga/openfga:latest");
+        assert_eq!(find(&unknown, "UpdateAvailable").status, "False");
+        assert_eq!(
+            find(&unknown, "VersionEOL").reason.as_deref(),
+            Some("UnknownVersion")
+        );
+
+        let current = conditions("openfga/openfga:v1.8.4");
+        assert_eq!(
+            find(&current, "UpdateAvailable").reason.as_deref(),
+            Some("UpToDate")
+        );
+
+        let deprecated = conditions("openfga/openfga:v1.5.0");
+        assert_eq!(find(&deprecated, "UpdateAvailable").status, "True");
+        assert_eq!(
+            find(&deprecated, "UpdateAvailable").reason.as_deref(),
+            Some("Deprecated")
+        );
+        assert_eq!(find(&deprecated, "VersionEOL").status, "False");
+
+        let eol = conditions("openfga/openfga:v1.3.2");
+        assert_eq!(
+            find(&eol, "UpdateAvailable").reason.as_deref(),
+            Some("NewerVersion")
+        );
+        assert_eq!(find(&eol, "VersionEOL").status, "True");
+        assert_eq!(
+            find(&eol, "VersionEOL").message.as_deref(),
+            Some("OpenFGA v1.3.2 is end of life. Upgrade now.")
+        );
+    }
+
+    #[test]
+    fn test_server_reachable_condition() {
+        let waiting = server_reachable_condition(None, Some(3), None);
+        assert_eq!(waiting.type_, "ServerReachable");
+        assert_eq!(waiting.status, "False");
+        assert_eq!(waiting.reason.as_deref(), Some("NoReadyReplicas"));
+
+        let reachable = server_reachable_condition(Some(&Ok(())), Some(3), None);
+        assert_eq!(reachable.status, "True");
+        assert_eq!(reachable.reason.as_deref(), Some("Reachable"));
+
+        let unreachable = server_reachable_condition(
+            Some(&Err("connection refused".to_string())),
+            Some(3),
+            Some(std::slice::from_ref(&reachable)),
+        );
+        assert_eq!(unreachable.status, "False");
+        assert_eq!(unreachable.reason.as_deref(), Some("Unreachable"));
+        assert_eq!(unreachable.message.as_deref(), Some("connection refused"));
+    }
+
+    #[test]
+    fn test_rollout_conditions_without_deployment() {
+        let conditions = rollout_conditions(None, 2, Some(4), None);
+
+        assert_eq!(find(&conditions, "Ready").status, "False");
+        assert_eq!(
+            find(&conditions, "Ready").reason.as_deref(),
+            Some("DeploymentNotFound")
+        );
+        assert_eq!(find(&conditions, "Progressing").status, "True");
+        assert!(conditions.iter().all(|c| c.observed_generation == Some(4)));
+    }
+
+    #[test]
+    fn test_rollout_conditions_rolling_out_and_complete() {
+        use k8s_openapi::api::apps::v1::DeploymentStatus;
+
+        let rolling = deployment_with_status(
+            2,
+            DeploymentStatus {
+                observed_generation: Some(2),
+                replicas: Some(3),
+                updated_replicas: Some(1),
+                ready_replicas: Some(2),
+                ..Default::default()
+            },
+        );
+        let conditions = rollout_conditions(Some(&rolling), 2, Some(1), None);
+        assert_eq!(find(&conditions, "Ready").status, "True");
+        assert_eq!(find(&conditions, "Progressing").status, "True");
+        assert_eq!(
+            find(&conditions, "Progressing").reason.as_deref(),
+            Some("RollingOut")
+        );
+        assert_eq!(find(&conditions, "Degraded").status, "False");
+
+        let complete = deployment_with_status(
+            2,
+            DeploymentStatus {
+                observed_generation: Some(2),
+                replicas: Some(2),
+                updated_replicas: Some(2),
+                ready_replicas: Some(2),
+                ..Default::default()
+            },
+        );
+        let conditions = rollout_conditions(Some(&complete), 2, Some(1), None);
+        assert_eq!(find(&conditions, "Progressing").status, "False");
+        assert_eq!(
+            find(&conditions, "Progressing").reason.as_deref(),
+            Some("RolloutComplete")
+        );
+        assert_eq!(
+            find(&conditions, "Ready").message.as_deref(),
+            Some("2/2 replicas ready")
+        );
+    }
+
+    #[test]
+    fn test_rollout_conditions_degraded() {
+        use k8s_openapi::api::apps::v1::{DeploymentCondition, DeploymentStatus};
+
+        let stuck = deployment_with_status(
+            3,
+            DeploymentStatus {
+                observed_generation: Some(3),
+                replicas: Some(2),
+                updated_replicas: Some(1),
+                ready_replicas: Some(1),
+                conditions: Some(vec![DeploymentCondition {
+                    type_: "Progressing".to_string(),
+                    status: "False".to_string(),
+                    reason: Some("ProgressDeadlineExceeded".to_string()),
+                    ..Default::default()
+                }]),
+                ..Default::default()
+            },
+        );
+
+        let conditions = rollout_conditions(Some(&stuck), 2, Some(3), None);
+        assert_eq!(find(&conditions, "Ready").status, "False");
+        assert_eq!(find(&conditions, "Progressing").status, "False");
+        assert_eq!(find(&conditions, "Degraded").status, "True");
+        assert_eq!(
+            find(&conditions, "Degraded").reason.as_deref(),
+            Some("ProgressDeadlineExceeded")
+        );
+    }
+
+    #[test]
+    fn test_ready_requires_exact_available_replicas() {
+        use k8s_openapi::api::apps::v1::DeploymentStatus;
+
+        let status = |ready, available| DeploymentStatus {
+            observed_generation: Some(1),
+            replicas: Some(ready),
+            updated_replicas: Some(ready),
+            ready_replicas: Some(ready),
+            available_replicas: Some(available),
+            ..Default::default()
+        };
+        let reason = |ready, available| {
+            let deployment = deployment_with_status(1, status(ready, available));
+            let conditions = rollout_conditions(Some(&deployment), 3, Some(1), None);
+            let ready = find(&conditions, "Ready");
+            (ready.status.clone(), ready.reason.clone().unwrap())
+        };
+
+        assert_eq!(reason(2, 2), ("False".into(), "ReplicasNotReady".into()));
+        assert_eq!(reason(4, 4), ("False".into(), "SurplusReplicas".into()));
+        assert_eq!(
+            reason(3, 1),
+            ("False".into(), "MinReadySecondsPending".into())
+        );
+        assert_eq!(reason(3, 3), ("True".into(), "ReplicasReady".into()));
+
+        let mut openfga = create_test_openfga();
+        openfga.spec.min_ready_seconds = Some(15);
+        let deployment = create_deployment(&openfga, "test-ns", "test-openfga").unwrap();
+        assert_eq!(deployment.spec.unwrap().min_ready_seconds, Some(15));
+    }
+
+    #[test]
+    fn test_ready_requires_reachable_server() {
+        let ready = || {
+            vec![OpenFGACondition::new(
+                "Ready",
+                true,
+                "ReplicasReady",
+                "3/3 replicas ready",
+                Some(1),
+                None,
+            )]
+        };
+
+        let mut conditions = ready();
+        require_reachable(&mut conditions, Some(&Ok(())), Some(1), None);
+        assert_eq!(conditions[0].status, "True");
+
+        let mut conditions = ready();
+        require_reachable(
+            &mut conditions,
+            Some(&Err("connection refused".to_string())),
+            Some(1),
+            None,
+        );
+        assert_eq!(conditions[0].status, "False");
+        assert_eq!(conditions[0].reason.as_deref(), Some("ServerUnreachable"));
+        assert!(conditions[0]
+            .message
+            .as_deref()
+            .unwrap()
+            .contains("connection refused"));
+
+        let mut conditions = ready();
+        require_reachable(&mut conditions, None, Some(1), None);
+        assert_eq!(conditions[0].reason.as_deref(), Some("ServerUnreachable"));
+    }
 }
@@ -0,0 +1,310 @@
+//! Conversion between the served OpenFGA API versions. `v1alpha1` is the
+//! storage version the controller reconciles; `v1beta1` differs in
+//! `spec.resources`, which takes native Kubernetes resource requirements
+//! including extended resources such as `nvidia.com/gpu`.
+//!
+//! `v1alpha1` only has fields for cpu, memory and ephemeral storage, so other
+//! resources are kept in [`EXTENDED_RESOURCES_ANNOTATION`] on the stored
+//! object. That makes the round trip lossless, and the controller applies them
+//! together with `spec.resources`.
+
+use crate::types::OpenFGA;
+use k8s_openapi::api::core::v1::ResourceRequirements;
+use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
+use kube::core::conversion::{ConversionRequest, ConversionResponse, ConversionReview};
+use kube::core::Status;
+use kube::ResourceExt;
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+
+pub const V1ALPHA1: &str = "authorization.openfga.dev/v1alpha1";
+pub const V1BETA1: &str = "authorization.openfga.dev/v1beta1";
+
+/// Resources of a `v1beta1` object that have no `v1alpha1` field, as JSON.
+pub const EXTENDED_RESOURCES_ANNOTATION: &str = "openfga.dev/extended-resources";
+
+/// `v1beta1` resource names and the `v1alpha1` fields holding them.
+const ALPHA_RESOURCES: &[(&str, &str)] = &[
+    ("cpu", "cpu"),
+    ("memory", "memory"),
+    ("ephemeral-storage", "ephemeralStorage"),
+];
+
+fn quantity(value: &Value) -> Value {
+    match value {
+        Value::Number(n) => Value::String(n.to_string()),
+        other => other.clone(),
+    }
+}
+
+fn annotations(object: &mut Value) -> &mut Map<String, Value> {
+    let metadata = &mut object["metadata"];
+    if !metadata["annotations"].is_object() {
+        metadata["annotations"] = json!({});
+    }
+    metadata["annotations"]
+        .as_object_mut()
+        .expect("annotations is an object")
+}
+
+fn drop_empty_annotations(object: &mut Value) {
+    if object["metadata"]["annotations"]
+        .as_object()
+        .is_some_and(|a| a.is_empty())
+    {
+        if let Some(metadata) = object["metadata"].as_object_mut() {
+            metadata.remove("annotations");
+        }
+    }
+}
+
+fn to_v1beta1(mut object: Value) -> Result<Value, String> {
+    let extended: Value = match annotations(&mut object).remove(EXTENDED_RESOURCES_ANNOTATION) {
+        Some(Value::String(raw)) => serde_json::from_str(&raw)
+            .map_err(|e| format!("invalid {}: {}", EXTENDED_RESOURCES_ANNOTATION, e))?,
+        _ => json!({}),
+    };
+    drop_empty_annotations(&mut object);
+
+    let alpha = object["spec"]["resources"].take();
+    let mut resources = Map::new();
+    for section in ["requests", "limits"] {
+        let mut quantities = Map::new();
+        for (name, field) in ALPHA_RESOURCES {
+            if let Some(value) = alpha[section].get(*field).filter(|v| !v.is_null()) {
+                quantities.insert(name.to_string(), value.clone());
+            }
+        }
+        if let Some(extra) = extended[section].as_object() {
+            quantities.extend(extra.clone());
+        }
+        if !quantities.is_empty() || alpha[section].is_object() {
+            resources.insert(section.to_string(), Value::Object(quantities));
+        }
+    }
+    set_resources(
+        &mut object,
+        alpha.is_object() || !resources.is_empty(),
+        resources,
+    );
+    object["apiVersion"] = json!(V1BETA1);
+    Ok(object)
+}
+
+fn to_v1alpha1(mut object: Value) -> Result<Value, String> {
+    let beta = object["spec"]["resources"].take();
+    let mut resources = Map::new();
+    let mut extended = Map::new();
+    for section in ["requests", "limits"] {
+        let Some(quantities) = beta[section].as_object() else {
+            continue;
+        };
+        let mut known = Map::new();
+        let mut other = Map::new();
+        for (name, value) in quantities {
+            match ALPHA_RESOURCES.iter().find(|(n, _)| n == name) {
+                Some((_, field)) => {
+                    known.insert(field.to_string(), quantity(value));
+                }
+                None => {
+                    other.insert(name.clone(), quantity(value));
+                }
+            }
+        }
+        resources.insert(section.to_string(), Value::Object(known));
+        if !other.is_empty() {
+            extended.insert(section.to_string(), Value::Object(other));
+        }
+    }
+    if beta.get("claims").is_some_and(|c| !c.is_null()) {
+        return Err("spec.resources.claims cannot be stored in v1alpha1".to_string());
+    }
+    set_resources(&mut object, beta.is_object(), resources);
+
+    let annotations = annotations(&mut object);
+    annotations.remove(EXTENDED_RESOURCES_ANNOTATION);
+    if !extended.is_empty() {
+        annotations.insert(
+            EXTENDED_RESOURCES_ANNOTATION.to_string(),
+            Value::String(Value::Object(extended).to_string()),
+        );
+    }
+    drop_empty_annotations(&mut object);
+    object["apiVersion"] = json!(V1ALPHA1);
+    Ok(object)
+}
+
+fn set_resources(object: &mut Value, present: bool, resources: Map<String, Value>) {
+    let Some(spec) = object["spec"].as_object_mut() else {
+        return;
+    };
+    if present {
+        spec.insert("resources".to_string(), Value::Object(resources));
+    } else {
+        spec.remove("resources");
+    }
+}
+
+/// `object` in `desired_api_version`.
+pub fn convert(object: Value, desired_api_version: &str) -> Result<Value, String> {
+    let current = object["apiVersion"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string();
+    if object["kind"] != "OpenFGA" {
+        return Err(format!("cannot convert kind {}", object["kind"]));
+    }
+    match (current.as_str(), desired_api_version) {
+        (from, to) if from == to => Ok(object),
+        (V1ALPHA1, V1BETA1) => to_v1beta1(object),
+        (V1BETA1, V1ALPHA1) => to_v1alpha1(object),
+        (from, to) => Err(format!("cannot convert from {} to {}", from, to)),
+    }
+}
+
+/// Answers one conversion review; any object that fails fails the whole review.
+pub fn review(review: ConversionReview) -> ConversionReview {
+    let request = match ConversionRequest::from_review(review) {
+        Ok(request) => request,
+        Err(_) => {
+            return ConversionResponse::invalid(Status::failure(
+                "conversion review has no request",
+                "InvalidRequest",
+            ))
+            .into_review()
+        }
+    };
+    let desired = request.desired_api_version.clone();
+    let objects = request.objects.clone();
+    let response = ConversionResponse::for_request(request);
+    match objects
+        .into_iter()
+        .map(|object| convert(object, &desired))
+        .collect::<Result<Vec<_>, _>>()
+    {
+        Ok(converted) => response.success(converted).into_review(),
+        Err(e) => response
+            .failure(Status::failure(&e, "ConversionFailed"))
+            .into_review(),
+    }
+}
+
+/// Extended resources a `v1beta1` client set on the instance.
+pub fn extended_resources(openfga: &OpenFGA) -> ResourceRequirements {
+    let parsed: BTreeMap<String, BTreeMap<String, Quantity>> = openfga
+        .annotations()
+        .get(EXTENDED_RESOURCES_ANNOTATION)
+        .and_then(|raw| serde_json::from_str(raw).ok())
+        .unwrap_or_default();
+    ResourceRequirements {
+        requests: parsed.get("requests").cloned(),
+        limits: parsed.get("limits").cloned(),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alpha() -> Value {
+        json!({
+            "apiVersion": V1ALPHA1,
+            "kind": "OpenFGA",
+            "metadata": {
+                "name": "authz",
+                "annotations": {
+                    EXTENDED_RESOURCES_ANNOTATION: r#"{"limits":{"nvidia.com/gpu":"1"}}"#,
+                },
+            },
+            "spec": {
+                "datastore": { "engine": "memory" },
+                "resources": {
+                    "requests": { "cpu": "250m", "memory": "512Mi" },
+                    "limits": { "memory": "1Gi", "ephemeralStorage": "2Gi" },
+                },
+            },
+            "status": { "replicas": 1 },
+        })
+    }
+
+    #[test]
+    fn test_alpha_to_beta() {
+        let beta = convert(alpha(), V1BETA1).unwrap();
+        assert_eq!(beta["apiVersion"], V1BETA1);
+        assert_eq!(
+            beta["spec"]["resources"],
+            json!({
+                "requests": { "cpu": "250m", "memory": "512Mi" },
+                "limits": { "memory": "1Gi", "ephemeral-storage": "2Gi", "nvidia.com/gpu": "1" },
+            })
+        );
+        assert!(beta["metadata"].get("annotations").is_none());
+        assert_eq!(beta["status"]["replicas"], 1);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let beta = convert(alpha(), V1BETA1).unwrap();
+        assert_eq!(convert(beta, V1ALPHA1).unwrap(), alpha());
+
+        let mut bare = alpha();
+        bare["metadata"]
+            .as_object_mut()
+            .unwrap()
+            .remove("annotations");
+        bare["spec"].as_object_mut().unwrap().remove("resources");
+        let beta = convert(bare.clone(), V1BETA1).unwrap();
+        assert!(beta["spec"].get("resources").is_none());
+        assert_eq!(convert(beta, V1ALPHA1).unwrap(), bare);
+    }
+
+    #[test]
+    fn test_beta_numbers_become_strings() {
+        let beta = json!({
+            "apiVersion": V1BETA1,
+            "kind": "OpenFGA",
+            "metadata": { "name": "authz" },
+            "spec": { "resources": { "requests": { "cpu": 1 } } },
+        });
+        let alpha = convert(beta, V1ALPHA1).unwrap();
+        assert_eq!(alpha["spec"]["resources"]["requests"]["cpu"], "1");
+    }
+
+    #[test]
+    fn test_review() {
+        let review: ConversionReview = serde_json::from_value(json!({
+            "apiVersion": "apiextensions.k8s.io/v1",
+            "kind": "ConversionReview",
+            "request": {
+                "uid": "705ab4f5-6393-11e8-b7cc-42010a800002",
+                "desiredAPIVersion": V1BETA1,
+                "objects": [alpha()],
+            },
+        }))
+        .unwrap();
+        let response = review_json(review);
+        assert_eq!(response["response"]["result"]["status"], "Success");
+        assert_eq!(
+            response["response"]["convertedObjects"][0]["apiVersion"],
+            V1BETA1
+        );
+
+        let review: ConversionReview = serde_json::from_value(json!({
+            "apiVersion": "apiextensions.k8s.io/v1",
+            "kind": "ConversionReview",
+            "request": {
+                "uid": "705ab4f5-6393-11e8-b7cc-42010a800002",
+                "desiredAPIVersion": "authorization.openfga.dev/v2",
+                "objects": [alpha()],
+            },
+        }))
+        .unwrap();
+        let response = review_json(review);
+        assert_eq!(response["response"]["result"]["status"], "Failure");
+    }
+
+    fn review_json(review: ConversionReview) -> Value {
+        serde_json::to_value(super::review(review)).unwrap()
+    }
+}
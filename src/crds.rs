@@ -0,0 +1,395 @@
+//! The operator's CustomResourceDefinitions at startup. The controllers cannot
+//! watch resources the API server does not serve, so until every CRD is
+//! installed the operator waits, polling discovery, instead of failing.
+//!
+//! With `OPENFGA_OPERATOR_INSTALL_CRDS=true` (or `--install-crds=true`) it
+//! also keeps them in step with the manifests built into the binary: missing
+//! CRDs are created, and installed ones are replaced when the schema version
+//! recorded on them is older than the operator's, or the same with a
+//! different schema hash. CRDs from a newer operator are left alone. An
+//! upgrade keeps the conversion webhook's `caBundle` cert-manager injected, and
+//! is refused while it would drop a version objects are still stored in. This
+//! needs `get`, `create` and `update` on `customresourcedefinitions` in
+//! addition to the usual RBAC.
+
+use crate::advisory::Version;
+use crate::types::{self, OpenFGA};
+use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinition;
+use kube::api::{Api, PostParams};
+use kube::discovery::{self, ApiGroup};
+use kube::error::DiscoveryError;
+use kube::{Client, Resource, ResourceExt};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeSet;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+/// Operator version whose manifest a CRD was last installed from.
+pub const SCHEMA_VERSION_ANNOTATION: &str = "openfga.dev/schema-version";
+
+/// Hash of the spec a CRD was last installed with.
+pub const SCHEMA_HASH_ANNOTATION: &str = "openfga.dev/schema-hash";
+
+/// Schema version of the built-in manifests.
+pub const SCHEMA_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// How often discovery is polled while CRDs are missing.
+pub const POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The manifests under `crds/`, in the order of `types::crds()`.
+pub const MANIFESTS: [&str; 9] = [
+    include_str!("../crds/openfga-crd.yaml"),
+    include_str!("../crds/authorizationmodel-crd.yaml"),
+    include_str!("../crds/openfgastore-crd.yaml"),
+    include_str!("../crds/openfgapool-crd.yaml"),
+    include_str!("../crds/openfgaclaim-crd.yaml"),
+    include_str!("../crds/openfgaaccessrequest-crd.yaml"),
+    include_str!("../crds/openfgabackup-crd.yaml"),
+    include_str!("../crds/openfgarestore-crd.yaml"),
+    include_str!("../crds/openfgareferencegrant-crd.yaml"),
+];
+
+/// The built-in CRD manifests.
+pub fn manifests() -> Vec<CustomResourceDefinition> {
+    MANIFESTS
+        .iter()
+        .map(|manifest| serde_yaml::from_str(manifest).expect("valid CRD manifest"))
+        .collect()
+}
+
+/// Names of the operator's CRDs whose resources are not among `served`, the
+/// plurals the API server serves in the operator's group.
+pub fn missing_from(served: &BTreeSet<String>) -> Vec<String> {
+    types::crds()
+        .into_iter()
+        .filter(|crd| !served.contains(&crd.spec.names.plural))
+        .filter_map(|crd| crd.metadata.name)
+        .collect()
+}
+
+fn served_plurals(group: &ApiGroup) -> BTreeSet<String> {
+    group
+        .versions()
+        .flat_map(|version| group.versioned_resources(version))
+        .map(|(resource, _)| resource.plural)
+        .collect()
+}
+
+/// Names of the operator's CRDs the API server does not serve yet.
+pub async fn missing(client: &Client) -> Result<Vec<String>, kube::Error> {
+    let served = match discovery::group(client, &OpenFGA::group(&())).await {
+        Ok(group) => served_plurals(&group),
+        Err(kube::Error::Discovery(DiscoveryError::MissingApiGroup(_))) => BTreeSet::new(),
+        Err(e) => return Err(e),
+    };
+    Ok(missing_from(&served))
+}
+
+/// Hash of a CRD's spec, as recorded in `SCHEMA_HASH_ANNOTATION`.
+pub fn schema_hash(crd: &CustomResourceDefinition) -> String {
+    let spec = serde_json::to_vec(&crd.spec).expect("CRD spec serializes");
+    Sha256::digest(spec)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// What `sync` does with one CRD.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyncAction {
+    Create,
+    /// Replace the installed spec, recorded at the given schema version if any.
+    Upgrade(Option<String>),
+    Keep,
+}
+
+/// How the installed CRD `existing` is brought in step with `desired`.
+pub fn plan(
+    existing: Option<&CustomResourceDefinition>,
+    desired: &CustomResourceDefinition,
+) -> SyncAction {
+    let Some(existing) = existing else {
+        return SyncAction::Create;
+    };
+    let annotation = |key: &str| existing.annotations().get(key).cloned();
+    let recorded = annotation(SCHEMA_VERSION_ANNOTATION);
+    let ours = Version::parse(SCHEMA_VERSION);
+    match (recorded.as_deref().and_then(Version::parse), ours) {
+        (Some(theirs), Some(ours)) if theirs > ours => SyncAction::Keep,
+        (Some(theirs), Some(ours))
+            if theirs == ours
+                && annotation(SCHEMA_HASH_ANNOTATION) == Some(schema_hash(desired)) =>
+        {
+            SyncAction::Keep
+        }
+        _ => SyncAction::Upgrade(recorded),
+    }
+}
+
+/// `crd` annotated with the schema version and hash of `desired`.
+fn annotated(
+    mut crd: CustomResourceDefinition,
+    desired: &CustomResourceDefinition,
+) -> CustomResourceDefinition {
+    let annotations = crd.annotations_mut();
+    annotations.insert(
+        SCHEMA_VERSION_ANNOTATION.to_string(),
+        SCHEMA_VERSION.to_string(),
+    );
+    annotations.insert(SCHEMA_HASH_ANNOTATION.to_string(), schema_hash(desired));
+    crd
+}
+
+/// `existing` with the spec of `desired`, keeping the conversion webhook's CA
+/// bundle; an error when `desired` no longer serves a stored version.
+pub fn upgraded(
+    existing: CustomResourceDefinition,
+    desired: &CustomResourceDefinition,
+) -> Result<CustomResourceDefinition, String> {
+    let stored = existing
+        .status
+        .as_ref()
+        .and_then(|status| status.stored_versions.as_ref());
+    let dropped: Vec<&String> = stored
+        .into_iter()
+        .flatten()
+        .filter(|version| !desired.spec.versions.iter().any(|v| &v.name == *version))
+        .collect();
+    if !dropped.is_empty() {
+        return Err(format!(
+            "objects are still stored as {}, which the new schema drops; migrate them and \
+             remove the versions from status.storedVersions first",
+            dropped
+                .iter()
+                .map(|v| v.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+
+    let ca_bundle = existing
+        .spec
+        .conversion
+        .as_ref()
+        .and_then(|c| c.webhook.as_ref())
+        .and_then(|w| w.client_config.as_ref())
+        .and_then(|c| c.ca_bundle.clone());
+    // Keeps labels and annotations others put on the CRD
+    let mut updated = existing;
+    updated.spec = desired.spec.clone();
+    if let Some(client_config) = updated
+        .spec
+        .conversion
+        .as_mut()
+        .and_then(|c| c.webhook.as_mut())
+        .and_then(|w| w.client_config.as_mut())
+        .filter(|c| c.ca_bundle.is_none())
+    {
+        client_config.ca_bundle = ca_bundle;
+    }
+    Ok(annotated(updated, desired))
+}
+
+/// Creates missing CRDs and upgrades outdated ones from the built-in manifests.
+pub async fn sync(client: &Client) -> Result<(), kube::Error> {
+    let api: Api<CustomResourceDefinition> = Api::all(client.clone());
+    for desired in manifests() {
+        let name = desired.name_any();
+        let existing = api.get_opt(&name).await?;
+        match plan(existing.as_ref(), &desired) {
+            SyncAction::Create => {
+                match api
+                    .create(
+                        &PostParams::default(),
+                        &annotated(desired.clone(), &desired),
+                    )
+                    .await
+                {
+                    Ok(_) => info!(
+                        event = "crd_installed",
+                        crd = %name,
+                        schema_version = SCHEMA_VERSION,
+                        "Installed CRD"
+                    ),
+                    // Installed in the meantime, e.g. by another replica
+                    Err(kube::Error::Api(e)) if e.code == 409 => {}
+                    Err(e) => return Err(e),
+                }
+            }
+            SyncAction::Upgrade(from) => {
+                let existing = existing.expect("upgrades have an installed CRD");
+                let updated = match upgraded(existing, &desired) {
+                    Ok(updated) => updated,
+                    Err(reason) => {
+                        warn!(
+                            event = "crd_upgrade_refused",
+                            crd = %name,
+                            reason = %reason,
+                            "Not upgrading CRD"
+                        );
+                        continue;
+                    }
+                };
+                api.replace(&name, &PostParams::default(), &updated).await?;
+                info!(
+                    event = "crd_upgraded",
+                    crd = %name,
+                    from_schema_version = from.as_deref().unwrap_or("unknown"),
+                    schema_version = SCHEMA_VERSION,
+                    "Upgraded CRD"
+                );
+            }
+            SyncAction::Keep => {
+                let recorded = existing
+                    .as_ref()
+                    .and_then(|crd| crd.annotations().get(SCHEMA_VERSION_ANNOTATION).cloned());
+                if recorded.as_deref() == Some(SCHEMA_VERSION) {
+                    debug!(event = "crd_current", crd = %name, "CRD is up to date");
+                } else {
+                    warn!(
+                        event = "crd_newer",
+                        crd = %name,
+                        installed_schema_version = recorded.as_deref().unwrap_or("unknown"),
+                        schema_version = SCHEMA_VERSION,
+                        "CRD was installed by a newer operator, leaving it as is"
+                    );
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinitionStatus;
+    use k8s_openapi::ByteString;
+
+    #[test]
+    fn test_manifests_match_types() {
+        let names: Vec<_> = manifests()
+            .into_iter()
+            .map(|crd| crd.metadata.name)
+            .collect();
+        let expected: Vec<_> = types::crds()
+            .into_iter()
+            .map(|crd| crd.metadata.name)
+            .collect();
+        assert_eq!(names, expected);
+    }
+
+    #[test]
+    fn test_plan() {
+        let desired = manifests().remove(0);
+        assert_eq!(plan(None, &desired), SyncAction::Create);
+
+        let current = annotated(desired.clone(), &desired);
+        assert_eq!(plan(Some(&current), &desired), SyncAction::Keep);
+
+        // Installed without the operator, e.g. with kubectl
+        assert_eq!(plan(Some(&desired), &desired), SyncAction::Upgrade(None));
+
+        let mut older = current.clone();
+        older
+            .annotations_mut()
+            .insert(SCHEMA_VERSION_ANNOTATION.to_string(), "0.0.1".to_string());
+        assert_eq!(
+            plan(Some(&older), &desired),
+            SyncAction::Upgrade(Some("0.0.1".to_string()))
+        );
+
+        let mut newer = current.clone();
+        newer
+            .annotations_mut()
+            .insert(SCHEMA_VERSION_ANNOTATION.to_string(), "999.0.0".to_string());
+        assert_eq!(plan(Some(&newer), &desired), SyncAction::Keep);
+
+        // Same version, different schema
+        let mut changed = current;
+        changed
+            .annotations_mut()
+            .insert(SCHEMA_HASH_ANNOTATION.to_string(), "0123".to_string());
+        assert_eq!(
+            plan(Some(&changed), &desired),
+            SyncAction::Upgrade(Some(SCHEMA_VERSION.to_string()))
+        );
+    }
+
+    #[test]
+    fn test_upgraded_keeps_ca_bundle() {
+        let desired = manifests().remove(0);
+        assert!(
+            desired.spec.conversion.is_some(),
+            "OpenFGA has a conversion webhook"
+        );
+        let mut existing = desired.clone();
+        let client_config = existing
+            .spec
+            .conversion
+            .as_mut()
+            .and_then(|c| c.webhook.as_mut())
+            .and_then(|w| w.client_config.as_mut())
+            .unwrap();
+        client_config.ca_bundle = Some(ByteString(b"injected".to_vec()));
+
+        let updated = upgraded(existing, &desired).unwrap();
+        let ca_bundle = updated
+            .spec
+            .conversion
+            .clone()
+            .and_then(|c| c.webhook)
+            .and_then(|w| w.client_config)
+            .and_then(|c| c.ca_bundle);
+        assert_eq!(ca_bundle, Some(ByteString(b"injected".to_vec())));
+        assert_eq!(
+            updated
+                .annotations()
+                .get(SCHEMA_VERSION_ANNOTATION)
+                .map(String::as_str),
+            Some(SCHEMA_VERSION)
+        );
+    }
+
+    #[test]
+    fn test_upgraded_keeps_stored_versions() {
+        let desired = manifests().remove(0);
+        let mut existing = desired.clone();
+        existing.status = Some(CustomResourceDefinitionStatus {
+            stored_versions: Some(vec!["v1alpha1".to_string(), "v1alpha0".to_string()]),
+            ..Default::default()
+        });
+        assert!(upgraded(existing.clone(), &desired)
+            .unwrap_err()
+            .contains("stored as v1alpha0"));
+
+        existing.status.as_mut().unwrap().stored_versions = Some(vec!["v1alpha1".to_string()]);
+        assert!(upgraded(existing, &desired).is_ok());
+    }
+
+    #[test]
+    fn test_schema_hash() {
+        let crds = manifests();
+        assert_eq!(schema_hash(&crds[0]), schema_hash(&crds[0].clone()));
+        assert_ne!(schema_hash(&crds[0]), schema_hash(&crds[1]));
+        assert_eq!(schema_hash(&crds[0]).len(), 64);
+    }
+
+    #[test]
+    fn test_missing_from() {
+        assert_eq!(missing_from(&BTreeSet::new()).len(), MANIFESTS.len());
+
+        let mut served: BTreeSet<String> = types::crds()
+            .into_iter()
+            .map(|crd| crd.spec.names.plural)
+            .collect();
+        assert!(missing_from(&served).is_empty());
+
+        served.remove("openfgabackups");
+        served.insert("unrelated".to_string());
+        assert_eq!(
+            missing_from(&served),
+            ["openfgabackups.authorization.openfga.dev"]
+        );
+    }
+}
@@ -0,0 +1,137 @@
+//! In-process state served on `/debug/state` for troubleshooting in the field:
+//! the OpenFGA controller's object caches, the last reconcile error of each
+//! object still failing, and the tokio runtimes' scheduler metrics.
+
+use crate::types::OpenFGA;
+use chrono::Utc;
+use kube::runtime::reflector::Store;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use tokio::runtime::Handle;
+
+/// Most recent failure of an object that has not reconciled since.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LastError {
+    pub error_type: String,
+    pub message: String,
+    pub attempt: u32,
+    pub time: String,
+}
+
+#[derive(Default)]
+struct State {
+    caches: Vec<(String, Store<OpenFGA>)>,
+    errors: BTreeMap<String, LastError>,
+}
+
+fn state() -> MutexGuard<'static, State> {
+    static STATE: OnceLock<Mutex<State>> = OnceLock::new();
+    STATE
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+/// Registers the object cache of the OpenFGA watch over `scope`.
+pub fn register_cache(scope: &str, store: Store<OpenFGA>) {
+    state().caches.push((scope.to_string(), store));
+}
+
+/// Records a failed reconcile of the object `key` (`namespace/name`).
+pub fn record_error(key: &str, error_type: &str, message: &str, attempt: u32) {
+    state().errors.insert(
+        key.to_string(),
+        LastError {
+            error_type: error_type.to_string(),
+            message: message.to_string(),
+            attempt,
+            time: Utc::now().to_rfc3339(),
+        },
+    );
+}
+
+/// Forgets the last error of `key` after it reconciled successfully.
+pub fn clear_error(key: &str) {
+    state().errors.remove(key);
+}
+
+/// Scheduler metrics of a tokio runtime.
+pub fn runtime_stats(runtime: &Handle) -> Value {
+    let metrics = runtime.metrics();
+    let workers = metrics.num_workers();
+    let busy_seconds: f64 = (0..workers)
+        .map(|worker| metrics.worker_total_busy_duration(worker).as_secs_f64())
+        .sum();
+    json!({
+        "workers": workers,
+        "alive_tasks": metrics.num_alive_tasks(),
+        "global_queue_depth": metrics.global_queue_depth(),
+        "worker_busy_seconds": busy_seconds,
+    })
+}
+
+/// Renders the cache sizes per watch scope and the failing objects.
+pub fn render(caches: &BTreeMap<String, usize>, errors: &BTreeMap<String, LastError>) -> Value {
+    json!({
+        "openfga": {
+            "watched_objects": caches.values().sum::<usize>(),
+            "watched_objects_by_scope": caches,
+            "failing_objects": errors.len(),
+            "last_errors": errors,
+        },
+    })
+}
+
+/// The full `/debug/state` document, with the metrics of each named runtime.
+pub fn snapshot(runtimes: &[(&str, &Handle)]) -> Value {
+    let mut document = {
+        let state = state();
+        let mut caches = BTreeMap::new();
+        for (scope, store) in &state.caches {
+            *caches.entry(scope.clone()).or_insert(0) += store.state().len();
+        }
+        render(&caches, &state.errors)
+    };
+    document["runtimes"] = runtimes
+        .iter()
+        .map(|(name, runtime)| (name.to_string(), runtime_stats(runtime)))
+        .collect::<serde_json::Map<_, _>>()
+        .into();
+    document
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_counts_objects_and_errors() {
+        let caches = BTreeMap::from([("team-a".to_string(), 2), ("team-b".to_string(), 3)]);
+        let errors = BTreeMap::from([(
+            "team-a/authz".to_string(),
+            LastError {
+                error_type: "Permission".to_string(),
+                message: "forbidden".to_string(),
+                attempt: 3,
+                time: "2026-01-01T00:00:00+00:00".to_string(),
+            },
+        )]);
+        let rendered = render(&caches, &errors);
+        assert_eq!(rendered["openfga"]["watched_objects"], 5);
+        assert_eq!(rendered["openfga"]["watched_objects_by_scope"]["team-b"], 3);
+        assert_eq!(rendered["openfga"]["failing_objects"], 1);
+        assert_eq!(
+            rendered["openfga"]["last_errors"]["team-a/authz"]["error_type"],
+            "Permission"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_includes_runtime_metrics() {
+        let snapshot = snapshot(&[("controller", &Handle::current())]);
+        assert_eq!(snapshot["runtimes"]["controller"]["workers"], 1);
+        assert!(snapshot["openfga"]["watched_objects"].is_number());
+    }
+}
@@ -0,0 +1,371 @@
+//! Cleanup of an OpenFGA instance according to its `deletionPolicy`, run from a
+//! finalizer so that namespace deletion cannot remove a populated datastore
+//! without an explicit confirmation.
+//!
+//! When the stores cannot be cleaned up, e.g. because the OpenFGA API is
+//! unreachable, the instance reports `DeletionBlocked` and deletion waits. To
+//! let it finish anyway, set `openfga.dev/force-cleanup: "true"`: once the
+//! resource has been terminating for [`FORCE_CLEANUP_DELAY`], the operator skips
+//! the store cleanup, leaving the datastore contents behind, and releases the
+//! finalizer.
+
+use crate::controller::ControllerResult;
+use crate::history;
+use crate::openfga_client::{ClientResult, OpenFGAClient};
+use crate::target_cluster;
+use crate::types::{DeletionPolicy, OpenFGA, OpenFGACondition};
+use crate::upgrade;
+use chrono::{DateTime, Utc};
+use k8s_openapi::api::apps::v1::{Deployment, StatefulSet};
+use k8s_openapi::api::core::v1::Service;
+use kube::api::{Api, DeleteParams, Patch, PatchParams};
+use kube::runtime::controller::Action;
+use kube::{Client, ResourceExt};
+use serde_json::json;
+use tokio::time::Duration;
+use tracing::{info, warn};
+
+/// Finalizer the operator holds while an instance's deletion policy needs cleanup.
+pub const CLEANUP_FINALIZER: &str = "openfga.dev/cleanup";
+/// Annotation that must name the instance before a `Delete` policy removes more
+/// tuples than the confirmation threshold.
+pub const CONFIRM_DELETE_ANNOTATION: &str = "openfga.dev/confirm-delete";
+
+/// Annotation that, set to `"true"`, skips store cleanup once deletion has been
+/// pending for [`FORCE_CLEANUP_DELAY`].
+pub const FORCE_CLEANUP_ANNOTATION: &str = "openfga.dev/force-cleanup";
+/// How long a deleted instance keeps retrying cleanup before a force-cleanup
+/// annotation takes effect, so transient outages do not lose the cleanup.
+pub const FORCE_CLEANUP_DELAY: Duration = Duration::from_secs(600);
+
+const TUPLE_PAGE_SIZE: usize = 100;
+
+/// Why the Delete policy could not remove the stores yet.
+enum CleanupBlocked {
+    ConfirmationRequired(String),
+    Failed(String),
+}
+
+pub fn has_finalizer(openfga: &OpenFGA) -> bool {
+    openfga.finalizers().iter().any(|f| f == CLEANUP_FINALIZER)
+}
+
+/// Whether the policy needs the operator to act before the resource goes away.
+/// Children in a target cluster have no owner reference to be collected by.
+pub fn needs_finalizer(openfga: &OpenFGA) -> bool {
+    openfga.spec.deletion_policy != DeletionPolicy::Retain
+        || openfga.spec.target_cluster_secret_ref.is_some()
+}
+
+/// Reason a `Delete` must wait for confirmation, if any.
+pub fn deletion_blocked(openfga: &OpenFGA, tuples: u64) -> Option<String> {
+    let threshold = openfga.spec.deletion_confirmation_threshold;
+    let confirmed = openfga
+        .annotations()
+        .get(CONFIRM_DELETE_ANNOTATION)
+        .is_some_and(|v| *v == openfga.name_any());
+    (tuples > threshold && !confirmed).then(|| {
+        format!(
+            "Stores hold more than {} tuples; set annotation {}={} to delete them, \
+             or change deletionPolicy to RetainData",
+            threshold,
+            CONFIRM_DELETE_ANNOTATION,
+            openfga.name_any()
+        )
+    })
+}
+
+/// Whether the force-cleanup annotation is set and its safety delay has passed.
+pub fn force_cleanup_due(openfga: &OpenFGA, now: DateTime<Utc>) -> bool {
+    let requested = openfga
+        .annotations()
+        .get(FORCE_CLEANUP_ANNOTATION)
+        .is_some_and(|v| v == "true");
+    let pending = openfga
+        .metadata
+        .deletion_timestamp
+        .as_ref()
+        .and_then(|t| (now - t.0).to_std().ok());
+    requested && pending.is_some_and(|pending| pending >= FORCE_CLEANUP_DELAY)
+}
+
+/// Adds or removes the cleanup finalizer so it is only present while needed.
+pub async fn sync_finalizer(client: &Client, openfga: &OpenFGA) -> ControllerResult<()> {
+    let wanted = needs_finalizer(openfga);
+    if wanted == has_finalizer(openfga) {
+        return Ok(());
+    }
+
+    let mut finalizers: Vec<String> = openfga
+        .finalizers()
+        .iter()
+        .filter(|f| *f != CLEANUP_FINALIZER)
+        .cloned()
+        .collect();
+    if wanted {
+        finalizers.push(CLEANUP_FINALIZER.to_string());
+    }
+    patch_finalizers(client, openfga, finalizers).await
+}
+
+async fn patch_finalizers(
+    client: &Client,
+    openfga: &OpenFGA,
+    finalizers: Vec<String>,
+) -> ControllerResult<()> {
+    let api: Api<OpenFGA> =
+        Api::namespaced(client.clone(), &openfga.namespace().unwrap_or_default());
+    // resourceVersion makes the list replacement fail instead of dropping a
+    // finalizer another controller added concurrently
+    let patch = json!({
+        "metadata": {
+            "finalizers": finalizers,
+            "resourceVersion": openfga.resource_version(),
+        }
+    });
+    api.patch(
+        &openfga.name_any(),
+        &PatchParams::default(),
+        &Patch::Merge(&patch),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Applies the deletion policy and releases the finalizer once done.
+pub async fn finalize(
+    client: &Client,
+    cluster: Option<&Client>,
+    openfga: &OpenFGA,
+) -> ControllerResult<Action> {
+    if !has_finalizer(openfga) {
+        return Ok(Action::await_change());
+    }
+    let ns = openfga.namespace().unwrap_or_default();
+    let name = openfga.name_any();
+    let policy = openfga.spec.deletion_policy;
+
+    if policy == DeletionPolicy::Delete {
+        if force_cleanup_due(openfga, Utc::now()) {
+            warn!(
+                event = "deletion_forced",
+                namespace = %ns,
+                resource_name = %name,
+                "Force cleanup requested, skipping store deletion; datastore contents are left behind"
+            );
+        } else {
+            match delete_stores(openfga).await {
+                Ok(()) => {}
+                Err(CleanupBlocked::ConfirmationRequired(message)) => {
+                    warn!(
+                        event = "deletion_blocked",
+                        namespace = %ns,
+                        resource_name = %name,
+                        "Deletion of populated stores awaits confirmation"
+                    );
+                    set_blocked_condition(client, openfga, "ConfirmationRequired", &message)
+                        .await?;
+                    return Ok(Action::requeue(Duration::from_secs(60)));
+                }
+                Err(CleanupBlocked::Failed(error)) => {
+                    warn!(
+                        event = "store_cleanup_failed",
+                        namespace = %ns,
+                        resource_name = %name,
+                        error = %error,
+                        "Failed to clean up stores, retrying"
+                    );
+                    let message = format!(
+                        "Store cleanup failed: {}. To skip it, set annotation {}=\"true\"; \
+                         it takes effect {} minutes after deletion was requested",
+                        error,
+                        FORCE_CLEANUP_ANNOTATION,
+                        FORCE_CLEANUP_DELAY.as_secs() / 60
+                    );
+                    set_blocked_condition(client, openfga, "CleanupFailed", &message).await?;
+                    return Ok(Action::requeue(Duration::from_secs(30)));
+                }
+            }
+        }
+    }
+
+    match cluster {
+        Some(cluster) if policy != DeletionPolicy::Retain => {
+            remove_workload(cluster, &ns, &name).await?
+        }
+        _ => {}
+    }
+    if let Some(cluster) = cluster.filter(|_| openfga.spec.target_cluster_secret_ref.is_some()) {
+        target_cluster::remove_children(cluster, openfga).await?;
+    }
+
+    info!(
+        event = "instance_finalized",
+        namespace = %ns,
+        resource_name = %name,
+        deletion_policy = ?policy,
+        "Applied deletion policy, releasing finalizer"
+    );
+    let finalizers = openfga
+        .finalizers()
+        .iter()
+        .filter(|f| *f != CLEANUP_FINALIZER)
+        .cloned()
+        .collect();
+    patch_finalizers(client, openfga, finalizers).await?;
+    target_cluster::forget(openfga);
+    Ok(Action::await_change())
+}
+
+/// Deletes the main workload, its Service and any canary from `cluster`.
+async fn remove_workload(cluster: &Client, ns: &str, name: &str) -> ControllerResult<()> {
+    let deployments: Api<Deployment> = Api::namespaced(cluster.clone(), ns);
+    let services: Api<Service> = Api::namespaced(cluster.clone(), ns);
+    let stateful_sets: Api<StatefulSet> = Api::namespaced(cluster.clone(), ns);
+    if deployments.get_opt(name).await?.is_some() {
+        deployments.delete(name, &DeleteParams::default()).await?;
+    }
+    if stateful_sets.get_opt(name).await?.is_some() {
+        stateful_sets.delete(name, &DeleteParams::default()).await?;
+    }
+    if services.get_opt(name).await?.is_some() {
+        services.delete(name, &DeleteParams::default()).await?;
+    }
+    upgrade::remove_canary(cluster, ns, name).await
+}
+
+/// Counts tuples across all stores, stopping once `limit` is exceeded.
+async fn count_tuples(api: &OpenFGAClient, limit: u64) -> ClientResult<u64> {
+    let mut total = 0u64;
+    for store in api.list_stores().await? {
+        let mut token: Option<String> = None;
+        loop {
+            let page = api
+                .read_tuples(&store.id, TUPLE_PAGE_SIZE, token.as_deref())
+                .await?;
+            total += page.tuples.len() as u64;
+            if total > limit {
+                return Ok(total);
+            }
+            match page.continuation_token {
+                Some(next) => token = Some(next),
+                None => break,
+            }
+        }
+    }
+    Ok(total)
+}
+
+async fn delete_stores(openfga: &OpenFGA) -> Result<(), CleanupBlocked> {
+    let api = OpenFGAClient::for_instance(openfga);
+    let failed = |e: crate::openfga_client::ClientError| CleanupBlocked::Failed(e.to_string());
+
+    let tuples = count_tuples(&api, openfga.spec.deletion_confirmation_threshold)
+        .await
+        .map_err(failed)?;
+    if let Some(message) = deletion_blocked(openfga, tuples) {
+        return Err(CleanupBlocked::ConfirmationRequired(message));
+    }
+    for store in api.list_stores().await.map_err(failed)? {
+        api.delete_store(&store.id).await.map_err(failed)?;
+    }
+    Ok(())
+}
+
+async fn set_blocked_condition(
+    client: &Client,
+    openfga: &OpenFGA,
+    reason: &str,
+    message: &str,
+) -> ControllerResult<()> {
+    let condition = OpenFGACondition::new(
+        "DeletionBlocked",
+        true,
+        reason,
+        message,
+        openfga.metadata.generation,
+        openfga
+            .status
+            .as_ref()
+            .and_then(|s| s.conditions.as_deref()),
+    );
+    history::set_condition(client, openfga, condition).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::test_openfga;
+    use crate::types::TargetClusterSecretRef;
+
+    fn instance(policy: &str) -> OpenFGA {
+        let mut openfga = test_openfga(json!({
+            "datastore": { "engine": "postgres" },
+            "deletionPolicy": policy,
+            "deletionConfirmationThreshold": 10,
+        }));
+        openfga.metadata.name = Some("authz".to_string());
+        openfga
+    }
+
+    #[test]
+    fn test_needs_finalizer() {
+        assert!(!needs_finalizer(&instance("Retain")));
+        assert!(needs_finalizer(&instance("RetainData")));
+        assert!(needs_finalizer(&instance("Delete")));
+
+        // As the API server defaults a manifest that leaves both out
+        let default: OpenFGA = OpenFGA::new(
+            "authz",
+            serde_json::from_value(json!({ "datastore": { "engine": "memory" } })).unwrap(),
+        );
+        assert_eq!(default.spec.deletion_policy, DeletionPolicy::Retain);
+        assert_eq!(default.spec.deletion_confirmation_threshold, 1000);
+
+        let mut remote = instance("Retain");
+        remote.spec.target_cluster_secret_ref = Some(TargetClusterSecretRef {
+            name: "edge-eu".to_string(),
+            key: None,
+        });
+        assert!(needs_finalizer(&remote));
+    }
+
+    #[test]
+    fn test_force_cleanup_due() {
+        use k8s_openapi::apimachinery::pkg::apis::meta::v1::Time;
+
+        let now = Utc::now();
+        let mut openfga = instance("Delete");
+        openfga
+            .annotations_mut()
+            .insert(FORCE_CLEANUP_ANNOTATION.to_string(), "true".to_string());
+        assert!(!force_cleanup_due(&openfga, now));
+
+        openfga.metadata.deletion_timestamp = Some(Time(now - chrono::Duration::minutes(2)));
+        assert!(!force_cleanup_due(&openfga, now));
+
+        openfga.metadata.deletion_timestamp = Some(Time(now - chrono::Duration::minutes(11)));
+        assert!(force_cleanup_due(&openfga, now));
+
+        openfga.annotations_mut().remove(FORCE_CLEANUP_ANNOTATION);
+        assert!(!force_cleanup_due(&openfga, now));
+    }
+
+    #[test]
+    fn test_deletion_blocked() {
+        let mut openfga = instance("Delete");
+        assert!(deletion_blocked(&openfga, 10).is_none());
+        assert!(deletion_blocked(&openfga, 11)
+            .unwrap()
+            .contains("openfga.dev/confirm-delete=authz"));
+
+        openfga
+            .annotations_mut()
+            .insert(CONFIRM_DELETE_ANNOTATION.to_string(), "other".to_string());
+        assert!(deletion_blocked(&openfga, 11).is_some());
+        openfga
+            .annotations_mut()
+            .insert(CONFIRM_DELETE_ANNOTATION.to_string(), "authz".to_string());
+        assert!(deletion_blocked(&openfga, 11).is_none());
+    }
+}
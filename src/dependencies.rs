@@ -0,0 +1,250 @@
+//! `spec.dependsOn`: external objects (a Postgres cluster, an ExternalSecret,
+//! another OpenFGA, ...) that must be ready before an instance is provisioned.
+//! While one is not, the instance reports `Ready=False` with reason
+//! `WaitingForDependency` naming the blocker, instead of starting pods that
+//! crash-loop until the database exists.
+
+use crate::controller::ControllerResult;
+use crate::history;
+use crate::types::{DependencyRef, OpenFGA, OpenFGACondition};
+use kube::api::{Api, DynamicObject};
+use kube::core::GroupVersionKind;
+use kube::discovery::{self, Scope};
+use kube::runtime::reflector::ObjectRef;
+use kube::{Client, ResourceExt};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::info;
+
+pub const WAITING_REASON: &str = "WaitingForDependency";
+
+/// How often an instance waiting on a kind the operator does not watch is rechecked.
+pub const POLL_INTERVAL: Duration = Duration::from_secs(15);
+
+const DEFAULT_CONDITION: &str = "Ready";
+
+pub fn describe(dependency: &DependencyRef, ns: &str) -> String {
+    format!(
+        "{} {}/{} ({})",
+        dependency.kind,
+        dependency.namespace.as_deref().unwrap_or(ns),
+        dependency.name,
+        dependency.api_version
+    )
+}
+
+fn group_version_kind(dependency: &DependencyRef) -> GroupVersionKind {
+    let (group, version) = dependency
+        .api_version
+        .split_once('/')
+        .unwrap_or(("", &dependency.api_version));
+    GroupVersionKind::gvk(group, version, &dependency.kind)
+}
+
+/// Why `object` does not satisfy the dependency, or `None` when it does. Objects
+/// without status conditions (Secrets, ConfigMaps, ...) only need to exist.
+pub fn blocker(object: Option<&DynamicObject>, condition: &str) -> Option<String> {
+    let Some(object) = object else {
+        return Some("not found".to_string());
+    };
+    if object.metadata.deletion_timestamp.is_some() {
+        return Some("being deleted".to_string());
+    }
+    // Objects that report no conditions only need to exist
+    let conditions = object
+        .data
+        .pointer("/status/conditions")
+        .and_then(|c| c.as_array())?;
+    let found = conditions
+        .iter()
+        .find(|c| c["type"].as_str() == Some(condition));
+    match found {
+        Some(c) if c["status"].as_str() == Some("True") => None,
+        Some(c) => Some(format!(
+            "condition {} is {}{}",
+            condition,
+            c["status"].as_str().unwrap_or("Unknown"),
+            c["reason"]
+                .as_str()
+                .map(|r| format!(" ({})", r))
+                .unwrap_or_default()
+        )),
+        None => Some(format!("condition {} not reported yet", condition)),
+    }
+}
+
+async fn check(
+    client: &Client,
+    dependency: &DependencyRef,
+    ns: &str,
+) -> kube::Result<Option<String>> {
+    let gvk = group_version_kind(dependency);
+    let (resource, capabilities) = match discovery::pinned_kind(client, &gvk).await {
+        Ok(found) => found,
+        Err(kube::Error::Api(e)) if e.code == 404 => {
+            return Ok(Some("kind is not installed in the cluster".to_string()))
+        }
+        Err(kube::Error::Discovery(_)) => {
+            return Ok(Some("kind is not installed in the cluster".to_string()))
+        }
+        Err(e) => return Err(e),
+    };
+    let api: Api<DynamicObject> = match capabilities.scope {
+        Scope::Cluster => Api::all_with(client.clone(), &resource),
+        Scope::Namespaced => Api::namespaced_with(
+            client.clone(),
+            dependency.namespace.as_deref().unwrap_or(ns),
+            &resource,
+        ),
+    };
+    let object = api.get_opt(&dependency.name).await?;
+    let condition = dependency.condition.as_deref().unwrap_or(DEFAULT_CONDITION);
+    Ok(blocker(object.as_ref(), condition))
+}
+
+/// The first dependency that is not ready, described for the status message.
+pub async fn first_blocker(client: &Client, openfga: &OpenFGA) -> ControllerResult<Option<String>> {
+    let ns = openfga.namespace().unwrap_or_default();
+    for dependency in &openfga.spec.depends_on {
+        if let Some(reason) = check(client, dependency, &ns).await? {
+            return Ok(Some(format!(
+                "waiting for {}: {}",
+                describe(dependency, &ns),
+                reason
+            )));
+        }
+    }
+    Ok(None)
+}
+
+pub async fn set_waiting_condition(
+    client: &Client,
+    openfga: &OpenFGA,
+    message: &str,
+) -> ControllerResult<()> {
+    let condition = OpenFGACondition::new(
+        "Ready",
+        false,
+        WAITING_REASON,
+        message,
+        openfga.metadata.generation,
+        openfga
+            .status
+            .as_ref()
+            .and_then(|s| s.conditions.as_deref()),
+    );
+
+    info!(
+        event = "waiting_for_dependency",
+        namespace = ?openfga.namespace(),
+        resource_name = %openfga.name_any(),
+        blocker = %message,
+        "Instance is waiting for a dependency"
+    );
+    history::set_condition(client, openfga, condition).await
+}
+
+/// Instances that depend on `changed`, for re-triggering them from the OpenFGA watch.
+pub fn dependents(instances: &[Arc<OpenFGA>], changed: &OpenFGA) -> Vec<ObjectRef<OpenFGA>> {
+    let changed_ns = changed.namespace().unwrap_or_default();
+    instances
+        .iter()
+        .filter(|instance| {
+            let ns = instance.namespace().unwrap_or_default();
+            instance.spec.depends_on.iter().any(|d| {
+                d.kind == "OpenFGA"
+                    && d.api_version.starts_with("authorization.openfga.dev/")
+                    && d.name == changed.name_any()
+                    && d.namespace.as_deref().unwrap_or(&ns) == changed_ns
+            })
+        })
+        .map(|instance| ObjectRef::from_obj(instance.as_ref()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::test_openfga;
+    use serde_json::json;
+
+    fn object(status: serde_json::Value) -> DynamicObject {
+        serde_json::from_value(json!({
+            "apiVersion": "postgresql.cnpg.io/v1",
+            "kind": "Cluster",
+            "metadata": { "name": "db", "namespace": "ns" },
+            "status": status,
+        }))
+        .unwrap()
+    }
+
+    fn instance(name: &str, depends_on: serde_json::Value) -> Arc<OpenFGA> {
+        let mut openfga = test_openfga(json!({ "dependsOn": depends_on }));
+        openfga.metadata.name = Some(name.to_string());
+        openfga.metadata.namespace = Some("ns".to_string());
+        Arc::new(openfga)
+    }
+
+    #[test]
+    fn test_blocker() {
+        assert_eq!(blocker(None, "Ready").as_deref(), Some("not found"));
+        assert_eq!(blocker(Some(&object(json!({}))), "Ready"), None);
+
+        let ready = object(json!({ "conditions": [{ "type": "Ready", "status": "True" }] }));
+        assert_eq!(blocker(Some(&ready), "Ready"), None);
+        assert_eq!(
+            blocker(Some(&ready), "Synced").as_deref(),
+            Some("condition Synced not reported yet")
+        );
+
+        let failing = object(json!({
+            "conditions": [{ "type": "Ready", "status": "False", "reason": "ClusterIsNotReady" }],
+        }));
+        assert_eq!(
+            blocker(Some(&failing), "Ready").as_deref(),
+            Some("condition Ready is False (ClusterIsNotReady)")
+        );
+    }
+
+    #[test]
+    fn test_group_version_kind() {
+        let dependency: DependencyRef = serde_json::from_value(json!({
+            "apiVersion": "postgresql.cnpg.io/v1", "kind": "Cluster", "name": "db",
+        }))
+        .unwrap();
+        let gvk = group_version_kind(&dependency);
+        assert_eq!(
+            (gvk.group.as_str(), gvk.version.as_str()),
+            ("postgresql.cnpg.io", "v1")
+        );
+        assert_eq!(
+            describe(&dependency, "ns"),
+            "Cluster ns/db (postgresql.cnpg.io/v1)"
+        );
+
+        let secret: DependencyRef = serde_json::from_value(json!({
+            "apiVersion": "v1", "kind": "Secret", "name": "db-uri", "namespace": "other",
+        }))
+        .unwrap();
+        let gvk = group_version_kind(&secret);
+        assert_eq!((gvk.group.as_str(), gvk.version.as_str()), ("", "v1"));
+        assert_eq!(describe(&secret, "ns"), "Secret other/db-uri (v1)");
+    }
+
+    #[test]
+    fn test_dependents() {
+        let upstream = instance("upstream", json!([]));
+        let downstream = instance(
+            "downstream",
+            json!([{ "apiVersion": "authorization.openfga.dev/v1alpha1", "kind": "OpenFGA", "name": "upstream" }]),
+        );
+        let unrelated = instance(
+            "unrelated",
+            json!([{ "apiVersion": "v1", "kind": "Secret", "name": "upstream" }]),
+        );
+
+        let refs = dependents(&[upstream.clone(), downstream, unrelated], &upstream);
+        assert_eq!(refs.len(), 1);
+        assert_eq!(refs[0].name, "downstream");
+    }
+}
@@ -0,0 +1,231 @@
+//! Detection of out-of-band edits to the children the operator manages. Only
+//! fields the operator sets are compared, so values the API server defaults or
+//! other controllers own are not reported as drift.
+
+use crate::metrics;
+use crate::types::OpenFGA;
+use k8s_openapi::api::apps::v1::Deployment;
+use k8s_openapi::api::core::v1::Service;
+use kube::runtime::events::{Event, EventType, Recorder};
+use kube::{Client, Resource, ResourceExt};
+use serde::Serialize;
+use serde_json::Value;
+use tracing::warn;
+
+/// Container fields compared per container. Resources are left out because the API
+/// server canonicalizes quantities, which would read as drift on every reconcile.
+const CONTAINER_FIELDS: &[&str] = &["image", "command", "args", "env", "envFrom", "ports"];
+
+/// True when every field set in `desired` has the same value in `live`. Arrays must
+/// have the same length and match element by element.
+pub fn is_subset(desired: &Value, live: &Value) -> bool {
+    match (desired, live) {
+        (Value::Object(desired), Value::Object(live)) => desired
+            .iter()
+            .filter(|(_, value)| !value.is_null())
+            .all(|(key, value)| live.get(key).is_some_and(|live| is_subset(value, live))),
+        (Value::Array(desired), Value::Array(live)) => {
+            desired.len() == live.len() && desired.iter().zip(live).all(|(d, l)| is_subset(d, l))
+        }
+        _ => desired == live,
+    }
+}
+
+/// Names of the fields at `pointers` where `live` no longer matches `desired`.
+fn drifted(desired: &Value, live: &Value, pointers: &[(String, String)]) -> Vec<String> {
+    pointers
+        .iter()
+        .filter(|(pointer, _)| match desired.pointer(pointer) {
+            None | Some(Value::Null) => false,
+            Some(wanted) => !live.pointer(pointer).is_some_and(|l| is_subset(wanted, l)),
+        })
+        .map(|(_, field)| field.clone())
+        .collect()
+}
+
+fn to_value(object: &impl Serialize) -> Value {
+    serde_json::to_value(object).unwrap_or_default()
+}
+
+/// Fields of the Deployment that were changed outside the operator.
+pub fn deployment_drift(desired: &Deployment, live: &Deployment) -> Vec<String> {
+    let (desired, live) = (to_value(desired), to_value(live));
+    let mut pointers = vec![
+        ("/spec/replicas".to_string(), "spec.replicas".to_string()),
+        (
+            "/spec/template/metadata/annotations".to_string(),
+            "spec.template.metadata.annotations".to_string(),
+        ),
+        (
+            "/spec/template/spec/serviceAccountName".to_string(),
+            "spec.template.spec.serviceAccountName".to_string(),
+        ),
+    ];
+    let containers = desired
+        .pointer("/spec/template/spec/containers")
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+    for (index, container) in containers.iter().enumerate() {
+        let name = container["name"].as_str().unwrap_or_default();
+        // Containers are matched by name so a reordered list is reported, not misread
+        let live_index = live
+            .pointer("/spec/template/spec/containers")
+            .and_then(Value::as_array)
+            .and_then(|live| live.iter().position(|c| c["name"] == container["name"]));
+        if live_index != Some(index) {
+            pointers.push((
+                format!("/spec/template/spec/containers/{}/name", index),
+                format!("containers[{}]", name),
+            ));
+            continue;
+        }
+        pointers.extend(CONTAINER_FIELDS.iter().map(|field| {
+            (
+                format!("/spec/template/spec/containers/{}/{}", index, field),
+                format!("containers[{}].{}", name, field),
+            )
+        }));
+    }
+    drifted(&desired, &live, &pointers)
+}
+
+/// Fields of the Service that were changed outside the operator.
+pub fn service_drift(desired: &Service, live: &Service) -> Vec<String> {
+    let pointers: Vec<(String, String)> = ["type", "selector", "ports", "externalTrafficPolicy"]
+        .iter()
+        .map(|field| (format!("/spec/{}", field), format!("spec.{}", field)))
+        .collect();
+    drifted(&to_value(desired), &to_value(live), &pointers)
+}
+
+/// Logs, counts and publishes a `DriftCorrected` event for a child that is about to
+/// be reverted.
+pub async fn report(client: &Client, openfga: &OpenFGA, kind: &str, fields: &[String]) {
+    let ns = openfga.namespace().unwrap_or_default();
+    let name = openfga.name_any();
+    warn!(
+        event = "drift_detected",
+        namespace = %ns,
+        resource_name = %name,
+        kind = %kind,
+        fields = ?fields,
+        "Reverting changes made outside the operator"
+    );
+    metrics::metrics()
+        .drift_corrections_total
+        .with_label_values(&[kind])
+        .inc();
+
+    let recorder = Recorder::new(
+        client.clone(),
+        "openfga-controller".into(),
+        openfga.object_ref(&()),
+    );
+    let event = Event {
+        type_: EventType::Warning,
+        reason: "DriftCorrected".to_string(),
+        note: Some(format!("{} {}: reverted {}", kind, name, fields.join(", "))),
+        action: "Reconcile".to_string(),
+        secondary: None,
+    };
+    if let Err(e) = recorder.publish(event).await {
+        warn!(
+            event = "drift_event_failed",
+            namespace = %ns,
+            resource_name = %name,
+            error = %e,
+            "Failed to publish drift event"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn deployment(value: Value) -> Deployment {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_is_subset() {
+        assert!(is_subset(
+            &json!({"a": 1, "b": {"c": [1, {"d": 2}]}, "n": null}),
+            &json!({"a": 1, "b": {"c": [1, {"d": 2, "e": 3}]}, "x": true}),
+        ));
+        assert!(!is_subset(&json!({"a": 1}), &json!({"a": 2})));
+        assert!(!is_subset(&json!([1, 2]), &json!([1, 2, 3])));
+        assert!(!is_subset(&json!({"a": 1}), &json!({})));
+    }
+
+    #[test]
+    fn test_deployment_drift() {
+        let desired = deployment(json!({
+            "spec": {
+                "replicas": 2,
+                "selector": {},
+                "template": { "spec": { "containers": [
+                    { "name": "openfga", "image": "openfga/openfga:v1.5.3",
+                      "ports": [{ "containerPort": 8080 }],
+                      "resources": { "limits": { "cpu": "1000m" } } },
+                ] } },
+            },
+        }));
+        // Server defaults and canonicalized quantities are not drift
+        let live = deployment(json!({
+            "spec": {
+                "replicas": 2,
+                "selector": {},
+                "template": { "spec": { "containers": [
+                    { "name": "openfga", "image": "openfga/openfga:v1.5.3",
+                      "ports": [{ "containerPort": 8080, "protocol": "TCP" }],
+                      "resources": { "limits": { "cpu": "1" } },
+                      "terminationMessagePath": "/dev/termination-log" },
+                ] } },
+            },
+        }));
+        assert!(deployment_drift(&desired, &live).is_empty());
+
+        let mut edited = live.clone();
+        let spec = edited.spec.as_mut().unwrap();
+        spec.replicas = Some(5);
+        spec.template.spec.as_mut().unwrap().containers[0].image =
+            Some("openfga/openfga:latest".to_string());
+        assert_eq!(
+            deployment_drift(&desired, &edited),
+            vec!["spec.replicas", "containers[openfga].image"]
+        );
+
+        let mut renamed = live;
+        renamed
+            .spec
+            .as_mut()
+            .unwrap()
+            .template
+            .spec
+            .as_mut()
+            .unwrap()
+            .containers[0]
+            .name = "sidecar".to_string();
+        assert_eq!(
+            deployment_drift(&desired, &renamed),
+            vec!["containers[openfga]"]
+        );
+    }
+
+    #[test]
+    fn test_service_drift() {
+        let desired: Service = serde_json::from_value(json!({
+            "spec": { "type": "ClusterIP", "ports": [{ "name": "http", "port": 8080 }] },
+        }))
+        .unwrap();
+        let live: Service = serde_json::from_value(json!({
+            "spec": { "type": "NodePort", "clusterIP": "10.0.0.1",
+                      "ports": [{ "name": "http", "port": 8080, "protocol": "TCP", "nodePort": 30080 }] },
+        }))
+        .unwrap();
+        assert_eq!(service_drift(&desired, &live), vec!["spec.type"]);
+    }
+}
@@ -0,0 +1,251 @@
+//! Dry-run mode of the OpenFGA controller. The desired workload and Service of
+//! each instance are server-side applied with `dryRun=All`, so the API server
+//! computes the objects an apply would produce, defaults and admission
+//! included, without persisting them. The differences to the live objects are
+//! logged and summarized in `status.pendingChanges`; nothing else is written.
+//! Events the reconcile would publish are listed there too, as `Event` changes.
+
+use crate::access_tokens;
+use crate::apply;
+use crate::controller::ControllerResult;
+use crate::image_digest;
+use crate::playground;
+use crate::template_hash;
+use crate::topology;
+use crate::types::{OpenFGA, PendingChange, WorkloadType};
+use crate::upgrade;
+use crate::workload;
+use k8s_openapi::api::apps::v1::{Deployment, StatefulSet};
+use k8s_openapi::api::core::v1::Service;
+use k8s_openapi::NamespaceResourceScope;
+use kube::api::{Api, Patch, PatchParams};
+use kube::runtime::controller::Action;
+use kube::{Client, Resource, ResourceExt};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::fmt::Debug;
+use std::time::Duration;
+use tracing::info;
+
+/// The parts of an object the operator sets: labels, annotations and the spec.
+fn comparable(object: &Value) -> Value {
+    json!({
+        "metadata": {
+            "labels": object["metadata"]["labels"],
+            "annotations": object["metadata"]["annotations"],
+        },
+        "spec": object["spec"],
+    })
+}
+
+/// JSON patch operations turning `live` into `desired`, compared on the
+/// fields the operator sets.
+pub fn changes(live: Option<&Value>, desired: &Value) -> Vec<Value> {
+    let live = live.map(comparable).unwrap_or_else(|| json!({}));
+    let patch = json_patch::diff(&live, &comparable(desired));
+    match serde_json::to_value(patch) {
+        Ok(Value::Array(operations)) => operations,
+        _ => Vec::new(),
+    }
+}
+
+/// Summary of the change an apply of `desired` over `live` would make.
+pub fn pending_change(
+    kind: &str,
+    name: &str,
+    live: Option<&Value>,
+    desired: &Value,
+) -> PendingChange {
+    let operations = changes(live, desired);
+    let action = match (live, operations.is_empty()) {
+        (None, _) => "Create",
+        (Some(_), true) => "None",
+        (Some(_), false) => "Update",
+    };
+    PendingChange {
+        kind: kind.to_string(),
+        name: name.to_string(),
+        action: action.to_string(),
+        fields: operations
+            .iter()
+            .filter_map(|op| op["path"].as_str())
+            .map(str::to_string)
+            .collect(),
+        message: None,
+    }
+}
+
+/// Dry-run applies `desired` and logs what a real apply would change.
+async fn preview<K>(api: &Api<K>, name: &str, desired: &K) -> ControllerResult<PendingChange>
+where
+    K: Resource<Scope = NamespaceResourceScope, DynamicType = ()>
+        + Clone
+        + Debug
+        + DeserializeOwned
+        + Serialize,
+{
+    let kind = K::kind(&()).to_string();
+    let live = api.get_opt(name).await?;
+    let params = PatchParams {
+        dry_run: true,
+        ..apply::apply_params()
+    };
+    let would_be = api.patch(name, &params, &Patch::Apply(desired)).await?;
+
+    let live = live.map(|l| serde_json::to_value(l)).transpose()?;
+    let would_be = serde_json::to_value(would_be)?;
+    let change = pending_change(&kind, name, live.as_ref(), &would_be);
+    if change.action != "None" {
+        let diff = serde_json::to_string(&changes(live.as_ref(), &would_be))?;
+        info!(
+            event = "dry_run_diff",
+            kind = %kind,
+            resource_name = %name,
+            action = %change.action,
+            diff = %diff,
+            "Dry run: change not applied"
+        );
+    }
+    Ok(change)
+}
+
+/// The `PolicyViolation` event an instance would get for `violations` of the
+/// playground policy.
+pub fn policy_violation_change(violations: &[String]) -> Option<PendingChange> {
+    (!violations.is_empty()).then(|| PendingChange {
+        kind: "Event".to_string(),
+        name: "PolicyViolation".to_string(),
+        action: "Create".to_string(),
+        fields: vec![],
+        message: Some(playground::violation_note(violations)),
+    })
+}
+
+/// Reconciles an instance in dry-run mode: previews the workload and Service
+/// and records the pending changes, among them the `PolicyViolation` event for
+/// `policy_violations`, without applying anything.
+pub async fn reconcile(
+    client: &Client,
+    openfga: &OpenFGA,
+    ns: &str,
+    name: &str,
+    policy_violations: &[String],
+    interval: Duration,
+) -> ControllerResult<Action> {
+    let mut deployment = crate::controller::create_deployment(openfga, ns, name)?;
+    match image_digest::pinned_image(client, openfga).await {
+        Some(Ok(pinned)) => upgrade::set_image(&mut deployment, &pinned),
+        Some(Err(_)) => {
+            if let Some(previous) = image_digest::previously_pinned(openfga) {
+                upgrade::set_image(&mut deployment, &previous);
+            }
+        }
+        None => {}
+    }
+    if let Some(keys) = access_tokens::read_keys(client, openfga).await? {
+        access_tokens::set_preshared_auth(&mut deployment, openfga, name, &keys);
+    }
+    if let Some(hash) = template_hash::compute(client, ns, &deployment).await? {
+        template_hash::stamp(&mut deployment, hash);
+    }
+
+    let mut pending = Vec::new();
+    if openfga.spec.workload_type == WorkloadType::StatefulSet {
+        let stateful_set = workload::create_stateful_set(openfga, &deployment, name);
+        let api: Api<StatefulSet> = Api::namespaced(client.clone(), ns);
+        pending.push(preview(&api, name, &stateful_set).await?);
+    } else {
+        let api: Api<Deployment> = Api::namespaced(client.clone(), ns);
+        let live = api.get_opt(name).await?;
+        apply::cede_replicas(&mut deployment, live.as_ref());
+        pending.push(preview(&api, name, &deployment).await?);
+    }
+    let service = crate::controller::create_service(openfga, ns, name)?;
+    let api: Api<Service> = Api::namespaced(client.clone(), ns);
+    pending.push(preview(&api, name, &service).await?);
+
+    for (role, shard) in topology::shards(openfga) {
+        let shard_name = topology::shard_name(name, role);
+        let mut shard_deployment =
+            topology::create_shard_deployment(openfga, &deployment, name, role, shard);
+        let deployments: Api<Deployment> = Api::namespaced(client.clone(), ns);
+        let live = deployments.get_opt(&shard_name).await?;
+        apply::cede_replicas(&mut shard_deployment, live.as_ref());
+        pending.push(preview(&deployments, &shard_name, &shard_deployment).await?);
+        let shard_service = topology::create_shard_service(openfga, &service, name, role);
+        pending.push(preview(&api, &shard_name, &shard_service).await?);
+    }
+
+    pending.retain(|change| change.action != "None");
+    pending.extend(policy_violation_change(policy_violations));
+    info!(
+        event = "dry_run_complete",
+        namespace = %ns,
+        resource_name = %name,
+        pending_changes = pending.len(),
+        "Dry run reconciliation completed"
+    );
+    Api::<OpenFGA>::namespaced(client.clone(), ns)
+        .patch_status(
+            &openfga.name_any(),
+            &PatchParams::default(),
+            &Patch::Merge(&json!({ "status": { "pendingChanges": pending } })),
+        )
+        .await?;
+    Ok(Action::requeue(interval))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changes_ignore_server_fields() {
+        let live = json!({
+            "metadata": {
+                "labels": { "app": "openfga" },
+                "resourceVersion": "41",
+                "managedFields": [{ "manager": "kubectl" }]
+            },
+            "spec": { "replicas": 2, "template": { "spec": { "containers": [{ "image": "openfga/openfga:v1.5.0" }] } } },
+            "status": { "readyReplicas": 2 }
+        });
+        let mut desired = live.clone();
+        desired["metadata"]["resourceVersion"] = json!("42");
+        desired["status"]["readyReplicas"] = json!(0);
+        assert!(changes(Some(&live), &desired).is_empty());
+        assert_eq!(
+            pending_change("Deployment", "authz", Some(&live), &desired).action,
+            "None"
+        );
+
+        desired["spec"]["replicas"] = json!(3);
+        desired["spec"]["template"]["spec"]["containers"][0]["image"] =
+            json!("openfga/openfga:v1.6.0");
+        let change = pending_change("Deployment", "authz", Some(&live), &desired);
+        assert_eq!(change.action, "Update");
+        assert_eq!(
+            change.fields,
+            vec!["/spec/replicas", "/spec/template/spec/containers/0/image"]
+        );
+
+        let created = pending_change("Service", "authz", None, &desired);
+        assert_eq!(created.action, "Create");
+        assert!(!created.fields.is_empty());
+    }
+
+    #[test]
+    fn test_policy_violation_change() {
+        assert_eq!(policy_violation_change(&[]), None);
+        let change =
+            policy_violation_change(&["spec.playground.enabled is disallowed".to_string()])
+                .unwrap();
+        assert_eq!(change.kind, "Event");
+        assert_eq!(change.action, "Create");
+        assert_eq!(
+            change.message.as_deref(),
+            Some("spec.playground.enabled is disallowed; the playground is not deployed")
+        );
+    }
+}
@@ -0,0 +1,307 @@
+//! Fixture bundles: an OpenFGA resource and the children the operator manages
+//! for it, captured from a live cluster with
+//! `openfga-operator capture <namespace>/<name>` and sanitized so they can be
+//! checked in and compared against what the operator generates in tests.
+
+use crate::types::OpenFGA;
+use k8s_openapi::api::apps::v1::Deployment;
+use k8s_openapi::api::core::v1::Service;
+use k8s_openapi::api::networking::v1::Ingress;
+use kube::api::Api;
+use kube::{Client, Resource};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::Path;
+
+const USAGE: &str = "\
+Usage:
+  openfga-operator capture <namespace>/<name> [-o <file>]
+
+Writes the OpenFGA resource and its Deployment, Service and Ingress as a
+sanitized fixture bundle to <file>, or to stdout.";
+
+const REDACTED: &str = "[REDACTED]";
+
+/// Substrings of environment variable names whose values are never captured.
+const SENSITIVE_ENV: &[&str] = &["URI", "PASSWORD", "SECRET", "TOKEN", "KEY"];
+
+/// Metadata fields that only describe one particular cluster object.
+const VOLATILE_METADATA: &[&str] = &[
+    "managedFields",
+    "uid",
+    "resourceVersion",
+    "creationTimestamp",
+    "selfLink",
+    "generation",
+];
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FixtureBundle {
+    /// `namespace/name` of the captured OpenFGA resource.
+    pub source: String,
+    pub resources: Vec<Value>,
+}
+
+impl FixtureBundle {
+    pub fn from_yaml(yaml: &str) -> Result<Self, String> {
+        serde_yaml::from_str(yaml).map_err(|e| e.to_string())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let yaml =
+            std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Self::from_yaml(&yaml).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn to_yaml(&self) -> Result<String, String> {
+        serde_yaml::to_string(self).map_err(|e| e.to_string())
+    }
+
+    /// The first captured resource of kind `K`, decoded.
+    pub fn get<K>(&self) -> Option<K>
+    where
+        K: Resource<DynamicType = ()> + DeserializeOwned,
+    {
+        let kind = K::kind(&());
+        self.resources
+            .iter()
+            .find(|r| r["kind"] == *kind)
+            .and_then(|r| serde_json::from_value(r.clone()).ok())
+    }
+}
+
+/// Strips cluster-specific metadata and status timestamps and redacts secrets.
+pub fn sanitize(mut resource: Value) -> Value {
+    if let Some(metadata) = resource.get_mut("metadata").and_then(Value::as_object_mut) {
+        for field in VOLATILE_METADATA {
+            metadata.remove(*field);
+        }
+        if let Some(annotations) = metadata
+            .get_mut("annotations")
+            .and_then(Value::as_object_mut)
+        {
+            annotations.remove("kubectl.kubernetes.io/last-applied-configuration");
+            annotations.remove("deployment.kubernetes.io/revision");
+        }
+        if let Some(owners) = metadata
+            .get_mut("ownerReferences")
+            .and_then(Value::as_array_mut)
+        {
+            for owner in owners {
+                owner["uid"] = Value::String("<uid>".to_string());
+            }
+        }
+    }
+    if let Some(status) = resource.get_mut("status") {
+        strip_timestamps(status);
+    }
+    if let Some(spec) = resource.get_mut("spec") {
+        // Cluster-assigned addresses differ between clusters
+        if let Some(spec) = spec.as_object_mut() {
+            spec.remove("clusterIP");
+            spec.remove("clusterIPs");
+        }
+        redact_env(spec);
+    }
+    resource
+}
+
+fn strip_timestamps(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            map.retain(|key, _| !matches!(key.as_str(), "lastTransitionTime" | "lastUpdateTime"));
+            map.values_mut().for_each(strip_timestamps);
+        }
+        Value::Array(items) => items.iter_mut().for_each(strip_timestamps),
+        _ => {}
+    }
+}
+
+/// Replaces the literal value of every sensitive-looking `env` entry, wherever
+/// it appears (OpenFGA spec or pod template).
+fn redact_env(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            if let Some(Value::Array(env)) = map.get_mut("env") {
+                for var in env.iter_mut() {
+                    let name = var["name"].as_str().unwrap_or_default().to_uppercase();
+                    if var.get("value").is_some() && SENSITIVE_ENV.iter().any(|s| name.contains(s))
+                    {
+                        var["value"] = Value::String(REDACTED.to_string());
+                    }
+                }
+            }
+            map.values_mut().for_each(redact_env);
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact_env),
+        _ => {}
+    }
+}
+
+/// Reads the OpenFGA resource and the same-named children the operator manages.
+pub async fn capture(client: Client, ns: &str, name: &str) -> Result<FixtureBundle, String> {
+    async fn fetch<K>(client: &Client, ns: &str, name: &str) -> Result<Option<Value>, String>
+    where
+        K: Resource<DynamicType = (), Scope = k8s_openapi::NamespaceResourceScope>
+            + Clone
+            + DeserializeOwned
+            + Serialize
+            + std::fmt::Debug,
+    {
+        let api: Api<K> = Api::namespaced(client.clone(), ns);
+        let object = api
+            .get_opt(name)
+            .await
+            .map_err(|e| format!("failed to read {} {}/{}: {}", K::kind(&()), ns, name, e))?;
+        object
+            .map(|o| {
+                let mut value = serde_json::to_value(o).map_err(|e| e.to_string())?;
+                // Typed objects skip apiVersion/kind when they come back from the API
+                value["apiVersion"] = Value::String(K::api_version(&()).to_string());
+                value["kind"] = Value::String(K::kind(&()).to_string());
+                Ok(sanitize(value))
+            })
+            .transpose()
+    }
+
+    let openfga = fetch::<OpenFGA>(&client, ns, name)
+        .await?
+        .ok_or_else(|| format!("OpenFGA {}/{} not found", ns, name))?;
+
+    let mut resources = vec![openfga];
+    for child in [
+        fetch::<Deployment>(&client, ns, name).await?,
+        fetch::<Service>(&client, ns, name).await?,
+        fetch::<Ingress>(&client, ns, name).await?,
+    ]
+    .into_iter()
+    .flatten()
+    {
+        resources.push(child);
+    }
+
+    Ok(FixtureBundle {
+        source: format!("{}/{}", ns, name),
+        resources,
+    })
+}
+
+/// Returns true when the arguments select the capture subcommand.
+pub fn is_capture_invocation(args: &[String]) -> bool {
+    args.get(1).is_some_and(|arg| arg == "capture")
+}
+
+/// Runs `capture` against the current kubeconfig context, returning the process exit code.
+pub async fn run(args: &[String]) -> i32 {
+    let args: Vec<&str> = args.iter().skip(1).map(String::as_str).collect();
+    let (target, output) = match args.as_slice() {
+        ["capture", target] => (*target, None),
+        ["capture", target, "-o" | "--output", file] => (*target, Some(*file)),
+        _ => {
+            eprintln!("{}", USAGE);
+            return 2;
+        }
+    };
+    let Some((ns, name)) = target.split_once('/') else {
+        eprintln!("expected <namespace>/<name>, got '{}'\n\n{}", target, USAGE);
+        return 2;
+    };
+
+    let result = match Client::try_default().await {
+        Ok(client) => capture(client, ns, name).await,
+        Err(e) => Err(format!("failed to connect to the cluster: {}", e)),
+    };
+    let yaml = match result.and_then(|bundle| bundle.to_yaml()) {
+        Ok(yaml) => yaml,
+        Err(message) => {
+            eprintln!("{}", message);
+            return 1;
+        }
+    };
+
+    match output {
+        Some(file) => match std::fs::write(file, yaml) {
+            Ok(()) => {
+                eprintln!("wrote fixture bundle for {} to {}", target, file);
+                0
+            }
+            Err(e) => {
+                eprintln!("{}: {}", file, e);
+                1
+            }
+        },
+        None => {
+            print!("{}", yaml);
+            0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_sanitize() {
+        let deployment = json!({
+            "apiVersion": "apps/v1",
+            "kind": "Deployment",
+            "metadata": {
+                "name": "authz",
+                "uid": "1234",
+                "resourceVersion": "99",
+                "managedFields": [{}],
+                "annotations": { "deployment.kubernetes.io/revision": "3", "team": "iam" },
+                "ownerReferences": [{ "kind": "OpenFGA", "name": "authz", "uid": "abcd" }],
+            },
+            "spec": { "template": { "spec": { "containers": [{
+                "name": "openfga",
+                "env": [
+                    { "name": "OPENFGA_DATASTORE_URI", "value": "postgres://u:p@db" },
+                    { "name": "OPENFGA_DATASTORE_ENGINE", "value": "postgres" },
+                    { "name": "OPENFGA_AUTHN_PRESHARED_KEYS", "valueFrom": { "secretKeyRef": { "name": "s", "key": "k" } } },
+                ],
+            }] } } },
+            "status": { "conditions": [{ "type": "Available", "lastUpdateTime": "2024-01-01T00:00:00Z" }] },
+        });
+
+        let sanitized = sanitize(deployment);
+        let metadata = &sanitized["metadata"];
+        assert!(metadata.get("uid").is_none());
+        assert!(metadata.get("managedFields").is_none());
+        assert_eq!(metadata["annotations"], json!({ "team": "iam" }));
+        assert_eq!(metadata["ownerReferences"][0]["uid"], "<uid>");
+
+        let env = &sanitized["spec"]["template"]["spec"]["containers"][0]["env"];
+        assert_eq!(env[0]["value"], REDACTED);
+        assert_eq!(env[1]["value"], "postgres");
+        assert!(env[2].get("value").is_none());
+        assert!(sanitized["status"]["conditions"][0]
+            .get("lastUpdateTime")
+            .is_none());
+    }
+
+    #[test]
+    fn test_bundle_round_trip() {
+        let bundle = FixtureBundle {
+            source: "ns/authz".to_string(),
+            resources: vec![json!({
+                "apiVersion": "authorization.openfga.dev/v1alpha1",
+                "kind": "OpenFGA",
+                "metadata": { "name": "authz", "namespace": "ns" },
+                "spec": { "datastore": { "engine": "memory" } },
+            })],
+        };
+
+        let loaded = FixtureBundle::from_yaml(&bundle.to_yaml().unwrap()).unwrap();
+        assert_eq!(loaded, bundle);
+        assert_eq!(
+            loaded.get::<OpenFGA>().unwrap().spec.datastore.engine,
+            "memory"
+        );
+        assert!(loaded.get::<Deployment>().is_none());
+    }
+}
@@ -0,0 +1,428 @@
+//! Bulk operations over every OpenFGA instance matching a label selector, e.g.
+//! `openfga-operator fleet upgrade --selector env=staging --to v1.5.3`.
+//!
+//! Instances are patched one at a time with a pause between them. Upgrades wait
+//! for each instance to report `Ready` on the new generation before moving on and
+//! stop at the first instance that does not, leaving the rest untouched.
+
+use crate::controller::{is_paused, PAUSED_ANNOTATION};
+use crate::pool_controller::is_ready;
+use crate::types::OpenFGA;
+use kube::api::{Api, ListParams, Patch, PatchParams};
+use kube::{Client, ResourceExt};
+use serde_json::{json, Value};
+use std::time::Duration;
+use tokio::time::{sleep, Instant};
+
+const USAGE: &str = "\
+Usage:
+  openfga-operator fleet upgrade --selector <labels> --to <tag> [options]
+  openfga-operator fleet pause --selector <labels> [options]
+  openfga-operator fleet resume --selector <labels> [options]
+
+Options:
+  -n, --namespace <ns>    Only consider instances in this namespace (default: all)
+  --interval <seconds>    Pause between two instances (default: 5)
+  --timeout <seconds>     How long an upgrade waits for an instance to become Ready (default: 600)
+  --dry-run               Print the plan without patching anything";
+
+const FIELD_MANAGER: &str = "openfga-operator-fleet";
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FleetAction {
+    /// Replace the image tag of every instance, keeping its repository.
+    Upgrade {
+        tag: String,
+    },
+    /// Stop reconciling the instances; their workloads keep running as-is.
+    Pause,
+    Resume,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FleetCommand {
+    pub action: FleetAction,
+    pub selector: String,
+    pub namespace: Option<String>,
+    pub interval: Duration,
+    pub timeout: Duration,
+    pub dry_run: bool,
+}
+
+/// Returns true when the arguments select a fleet subcommand.
+pub fn is_fleet_invocation(args: &[String]) -> bool {
+    args.get(1).is_some_and(|arg| arg == "fleet")
+}
+
+/// Runs a fleet subcommand against the current kubeconfig context, returning the
+/// process exit code.
+pub async fn run(args: &[String]) -> i32 {
+    let command = match parse(&args[1..]) {
+        Ok(command) => command,
+        Err(message) => {
+            eprintln!("{}", message);
+            return 2;
+        }
+    };
+
+    let client = match Client::try_default().await {
+        Ok(client) => client,
+        Err(e) => {
+            eprintln!("failed to connect to the cluster: {}", e);
+            return 1;
+        }
+    };
+
+    match execute(client, &command).await {
+        Ok(summary) => {
+            println!("{}", summary);
+            0
+        }
+        Err(message) => {
+            eprintln!("{}", message);
+            1
+        }
+    }
+}
+
+pub fn parse(args: &[String]) -> Result<FleetCommand, String> {
+    let mut rest = args.iter().map(String::as_str);
+    if rest.next() != Some("fleet") {
+        return Err(USAGE.to_string());
+    }
+    let verb = rest.next().ok_or_else(|| USAGE.to_string())?;
+
+    let mut selector = None;
+    let mut tag = None;
+    let mut namespace = None;
+    let mut interval = Duration::from_secs(5);
+    let mut timeout = Duration::from_secs(600);
+    let mut dry_run = false;
+
+    let seconds = |flag: &str, value: Option<&str>| -> Result<Duration, String> {
+        value
+            .and_then(|v| v.parse::<u64>().ok())
+            .map(Duration::from_secs)
+            .ok_or_else(|| format!("{} expects a number of seconds", flag))
+    };
+
+    while let Some(arg) = rest.next() {
+        match arg {
+            "--selector" | "-l" => selector = rest.next().map(str::to_string),
+            "--to" => tag = rest.next().map(str::to_string),
+            "--namespace" | "-n" => namespace = rest.next().map(str::to_string),
+            "--interval" => interval = seconds(arg, rest.next())?,
+            "--timeout" => timeout = seconds(arg, rest.next())?,
+            "--dry-run" => dry_run = true,
+            other => return Err(format!("unexpected argument '{}'\n\n{}", other, USAGE)),
+        }
+    }
+
+    let selector = selector
+        .filter(|s| !s.is_empty())
+        .ok_or_else(|| format!("--selector is required\n\n{}", USAGE))?;
+
+    let action = match (verb, tag) {
+        ("upgrade", Some(tag)) if !tag.is_empty() => FleetAction::Upgrade { tag },
+        ("upgrade", _) => return Err(format!("upgrade requires --to <tag>\n\n{}", USAGE)),
+        ("pause", None) => FleetAction::Pause,
+        ("resume", None) => FleetAction::Resume,
+        ("pause" | "resume", Some(_)) => {
+            return Err(format!("--to only applies to upgrade\n\n{}", USAGE))
+        }
+        _ => return Err(USAGE.to_string()),
+    };
+
+    Ok(FleetCommand {
+        action,
+        selector,
+        namespace,
+        interval,
+        timeout,
+        dry_run,
+    })
+}
+
+/// Replaces the tag (and any digest) of an image reference, keeping registry and
+/// repository. A registry port such as `registry:5000/openfga` is not a tag.
+pub fn image_with_tag(image: &str, tag: &str) -> String {
+    let repository = image.split_once('@').map_or(image, |(repo, _)| repo);
+    let name_start = repository.rfind('/').map_or(0, |i| i + 1);
+    let repository = match repository[name_start..].rfind(':') {
+        Some(colon) => &repository[..name_start + colon],
+        None => repository,
+    };
+    format!("{}:{}", repository, tag)
+}
+
+/// Merge patch that applies the action to an instance, or None when the
+/// instance is already in the requested state.
+pub fn patch_for(openfga: &OpenFGA, action: &FleetAction) -> Option<Value> {
+    match action {
+        FleetAction::Upgrade { tag } => {
+            let image = image_with_tag(&openfga.spec.image, tag);
+            (image != openfga.spec.image).then(|| json!({ "spec": { "image": image } }))
+        }
+        FleetAction::Pause => (!is_paused(openfga))
+            .then(|| json!({ "metadata": { "annotations": { PAUSED_ANNOTATION: "true" } } })),
+        FleetAction::Resume => openfga
+            .annotations()
+            .contains_key(PAUSED_ANNOTATION)
+            .then(|| json!({ "metadata": { "annotations": { PAUSED_ANNOTATION: Value::Null } } })),
+    }
+}
+
+/// Whether the operator has reconciled `generation` and the instance is Ready.
+pub fn is_rolled_out(openfga: &OpenFGA, generation: Option<i64>) -> bool {
+    let observed = openfga.status.as_ref().and_then(|s| s.observed_generation);
+    observed >= generation && is_ready(openfga)
+}
+
+async fn execute(client: Client, command: &FleetCommand) -> Result<String, String> {
+    let all: Api<OpenFGA> = match &command.namespace {
+        Some(ns) => Api::namespaced(client.clone(), ns),
+        None => Api::all(client.clone()),
+    };
+    let mut instances = all
+        .list(&ListParams::default().labels(&command.selector))
+        .await
+        .map_err(|e| format!("failed to list OpenFGA instances: {}", e))?
+        .items;
+    instances.sort_by_key(|o| (o.namespace(), o.name_any()));
+
+    let total = instances.len();
+    let (mut patched, mut unchanged, mut skipped) = (0, 0, 0);
+    println!(
+        "{} instance(s) match '{}'{}",
+        total,
+        command.selector,
+        if command.dry_run { " (dry run)" } else { "" }
+    );
+
+    for (index, openfga) in instances.iter().enumerate() {
+        let ns = openfga.namespace().unwrap_or_default();
+        let name = openfga.name_any();
+        let progress = format!("[{}/{}] {}/{}", index + 1, total, ns, name);
+
+        let Some(patch) = patch_for(openfga, &command.action) else {
+            println!("{}: already up to date", progress);
+            unchanged += 1;
+            continue;
+        };
+        if matches!(command.action, FleetAction::Upgrade { .. }) && is_paused(openfga) {
+            println!("{}: skipped, instance is paused", progress);
+            skipped += 1;
+            continue;
+        }
+
+        println!("{}: {}", progress, describe(openfga, &command.action));
+        if command.dry_run {
+            patched += 1;
+            continue;
+        }
+
+        if patched > 0 && !command.interval.is_zero() {
+            sleep(command.interval).await;
+        }
+
+        let api: Api<OpenFGA> = Api::namespaced(client.clone(), &ns);
+        let updated = api
+            .patch(
+                &name,
+                &PatchParams {
+                    field_manager: Some(FIELD_MANAGER.to_string()),
+                    ..Default::default()
+                },
+                &Patch::Merge(&patch),
+            )
+            .await
+            .map_err(|e| format!("{}: patch failed: {}", progress, e))?;
+        patched += 1;
+
+        if matches!(command.action, FleetAction::Upgrade { .. }) {
+            wait_for_rollout(&api, &name, updated.metadata.generation, command.timeout)
+                .await
+                .map_err(|e| {
+                    format!(
+                        "{}: {}; stopping with {} instance(s) not processed",
+                        progress,
+                        e,
+                        total - index - 1
+                    )
+                })?;
+            println!("{}: ready", progress);
+        }
+    }
+
+    Ok(format!(
+        "{} matched, {} {}, {} unchanged, {} skipped",
+        total,
+        patched,
+        if command.dry_run {
+            "would be patched"
+        } else {
+            "patched"
+        },
+        unchanged,
+        skipped
+    ))
+}
+
+fn describe(openfga: &OpenFGA, action: &FleetAction) -> String {
+    match action {
+        FleetAction::Upgrade { tag } => format!(
+            "image {} -> {}",
+            openfga.spec.image,
+            image_with_tag(&openfga.spec.image, tag)
+        ),
+        FleetAction::Pause => "pausing reconciliation".to_string(),
+        FleetAction::Resume => "resuming reconciliation".to_string(),
+    }
+}
+
+async fn wait_for_rollout(
+    api: &Api<OpenFGA>,
+    name: &str,
+    generation: Option<i64>,
+    timeout: Duration,
+) -> Result<(), String> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        let openfga = api.get(name).await.map_err(|e| e.to_string())?;
+        if is_rolled_out(&openfga, generation) {
+            return Ok(());
+        }
+        if Instant::now() >= deadline {
+            return Err(format!("not Ready after {}s", timeout.as_secs()));
+        }
+        sleep(POLL_INTERVAL).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::test_openfga;
+    use crate::types::{OpenFGACondition, OpenFGAStatus};
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn instance(image: &str) -> OpenFGA {
+        let mut openfga = test_openfga(json!({ "image": image }));
+        openfga.metadata.name = Some("authz".to_string());
+        openfga.metadata.namespace = Some("staging".to_string());
+        openfga
+    }
+
+    #[test]
+    fn test_parse() {
+        let command = parse(&args(&[
+            "fleet",
+            "upgrade",
+            "--selector",
+            "env=staging",
+            "--to",
+            "v1.5.3",
+            "--interval",
+            "30",
+            "--dry-run",
+        ]))
+        .unwrap();
+        assert_eq!(
+            command.action,
+            FleetAction::Upgrade {
+                tag: "v1.5.3".to_string()
+            }
+        );
+        assert_eq!(command.selector, "env=staging");
+        assert_eq!(command.interval, Duration::from_secs(30));
+        assert!(command.dry_run);
+
+        let command = parse(&args(&["fleet", "pause", "-l", "team=payments", "-n", "a"])).unwrap();
+        assert_eq!(command.action, FleetAction::Pause);
+        assert_eq!(command.namespace.as_deref(), Some("a"));
+
+        assert!(parse(&args(&["fleet", "upgrade", "--selector", "env=staging"])).is_err());
+        assert!(parse(&args(&["fleet", "pause"])).is_err());
+        assert!(parse(&args(&["fleet", "resume", "-l", "a=b", "--to", "v1"])).is_err());
+        assert!(parse(&args(&[
+            "fleet",
+            "pause",
+            "-l",
+            "a=b",
+            "--interval",
+            "soon"
+        ]))
+        .is_err());
+    }
+
+    #[test]
+    fn test_image_with_tag() {
+        assert_eq!(
+            image_with_tag("openfga/openfga:v1.4.0", "v1.5.3"),
+            "openfga/openfga:v1.5.3"
+        );
+        assert_eq!(
+            image_with_tag("openfga/openfga", "v1.5.3"),
+            "openfga/openfga:v1.5.3"
+        );
+        assert_eq!(
+            image_with_tag("registry:5000/openfga/openfga", "v1.5.3"),
+            "registry:5000/openfga/openfga:v1.5.3"
+        );
+        assert_eq!(
+            image_with_tag("registry:5000/openfga@sha256:abc", "v1.5.3"),
+            "registry:5000/openfga:v1.5.3"
+        );
+    }
+
+    #[test]
+    fn test_patch_for() {
+        let upgrade = FleetAction::Upgrade {
+            tag: "v1.5.3".to_string(),
+        };
+        let mut openfga = instance("openfga/openfga:v1.4.0");
+        assert_eq!(
+            patch_for(&openfga, &upgrade),
+            Some(json!({"spec": {"image": "openfga/openfga:v1.5.3"}}))
+        );
+        assert!(patch_for(&instance("openfga/openfga:v1.5.3"), &upgrade).is_none());
+
+        assert!(patch_for(&openfga, &FleetAction::Resume).is_none());
+        assert!(patch_for(&openfga, &FleetAction::Pause).is_some());
+        openfga
+            .annotations_mut()
+            .insert(PAUSED_ANNOTATION.to_string(), "true".to_string());
+        assert!(is_paused(&openfga));
+        assert!(patch_for(&openfga, &FleetAction::Pause).is_none());
+        assert_eq!(
+            patch_for(&openfga, &FleetAction::Resume),
+            Some(json!({"metadata": {"annotations": {PAUSED_ANNOTATION: null}}}))
+        );
+    }
+
+    #[test]
+    fn test_is_rolled_out() {
+        let mut openfga = instance("openfga/openfga:v1.5.3");
+        assert!(!is_rolled_out(&openfga, Some(2)));
+
+        openfga.status = Some(OpenFGAStatus {
+            observed_generation: Some(1),
+            conditions: Some(vec![OpenFGACondition::new(
+                "Ready",
+                true,
+                "ReplicasReady",
+                "",
+                Some(1),
+                None,
+            )]),
+            ..Default::default()
+        });
+        assert!(!is_rolled_out(&openfga, Some(2)));
+        openfga.status.as_mut().unwrap().observed_generation = Some(2);
+        assert!(is_rolled_out(&openfga, Some(2)));
+    }
+}
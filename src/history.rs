@@ -0,0 +1,142 @@
+//! Bounded transition history kept in `status.history`. Kubernetes Events
+//! expire after about an hour; the history keeps the last few significant
+//! condition changes on the resource itself so they are still visible with
+//! `kubectl get -o yaml` days later.
+
+use crate::controller::ControllerResult;
+use crate::types::{OpenFGA, OpenFGACondition, OpenFGAHistoryEntry};
+use kube::api::{Api, Patch, PatchParams};
+use kube::{Client, ResourceExt};
+use serde_json::json;
+
+/// Number of transitions kept, oldest dropped first.
+pub const HISTORY_LIMIT: usize = 10;
+
+/// Appends an entry for every condition whose status or reason differs from
+/// `previous`, keeping the newest `HISTORY_LIMIT` entries.
+pub fn record(
+    history: Option<&[OpenFGAHistoryEntry]>,
+    previous: &[OpenFGACondition],
+    current: &[OpenFGACondition],
+    now: &str,
+) -> Vec<OpenFGAHistoryEntry> {
+    let mut history = history.map(<[_]>::to_vec).unwrap_or_default();
+    for condition in current {
+        let unchanged = previous.iter().any(|p| {
+            p.type_ == condition.type_
+                && p.status == condition.status
+                && p.reason == condition.reason
+        });
+        if !unchanged {
+            history.push(OpenFGAHistoryEntry {
+                time: now.to_string(),
+                type_: condition.type_.clone(),
+                status: condition.status.clone(),
+                reason: condition.reason.clone(),
+                message: condition.message.clone(),
+            });
+        }
+    }
+    let excess = history.len().saturating_sub(HISTORY_LIMIT);
+    history.drain(..excess);
+    history
+}
+
+/// `previous` with `condition` in place of the one of its type.
+fn replaced(previous: &[OpenFGACondition], condition: OpenFGACondition) -> Vec<OpenFGACondition> {
+    let mut conditions: Vec<OpenFGACondition> = previous
+        .iter()
+        .filter(|c| c.type_ != condition.type_)
+        .cloned()
+        .collect();
+    conditions.push(condition);
+    conditions
+}
+
+/// Sets one condition of `openfga` outside a full status update, recording the
+/// transition in the history.
+pub async fn set_condition(
+    client: &Client,
+    openfga: &OpenFGA,
+    condition: OpenFGACondition,
+) -> ControllerResult<()> {
+    let status = openfga.status.clone().unwrap_or_default();
+    let previous = status.conditions.unwrap_or_default();
+    let conditions = replaced(&previous, condition);
+    let history = record(
+        status.history.as_deref(),
+        &previous,
+        &conditions,
+        &chrono::Utc::now().to_rfc3339(),
+    );
+
+    let api: Api<OpenFGA> =
+        Api::namespaced(client.clone(), &openfga.namespace().unwrap_or_default());
+    api.patch_status(
+        &openfga.name_any(),
+        &PatchParams::default(),
+        &Patch::Merge(&json!({ "status": { "conditions": conditions, "history": history } })),
+    )
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn condition(type_: &str, status: bool, reason: &str) -> OpenFGACondition {
+        OpenFGACondition::new(type_, status, reason, "", None, None)
+    }
+
+    #[test]
+    fn test_records_only_transitions() {
+        let ready = [condition("Ready", true, "ReplicasReady")];
+        let history = record(None, &[], &ready, "t1");
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].type_, "Ready");
+        assert_eq!(history[0].time, "t1");
+
+        let history = record(Some(&history), &ready, &ready, "t2");
+        assert_eq!(history.len(), 1);
+
+        let degraded = [condition("Ready", false, "ReplicasUnavailable")];
+        let history = record(Some(&history), &ready, &degraded, "t3");
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[1].status, "False");
+        assert_eq!(history[1].reason.as_deref(), Some("ReplicasUnavailable"));
+
+        let rolling = [condition("Ready", false, "RollingUpdate")];
+        let history = record(Some(&history), &degraded, &rolling, "t4");
+        assert_eq!(history.len(), 3);
+    }
+
+    #[test]
+    fn test_replaced() {
+        let previous = [
+            condition("Ready", true, "ReplicasReady"),
+            condition("DeletionBlocked", false, "AsExpected"),
+        ];
+        let conditions = replaced(&previous, condition("Ready", false, "WaitingForDependency"));
+        assert_eq!(conditions.len(), 2);
+        assert_eq!(conditions[0].type_, "DeletionBlocked");
+        assert_eq!(
+            conditions[1].reason.as_deref(),
+            Some("WaitingForDependency")
+        );
+    }
+
+    #[test]
+    fn test_bounded() {
+        let mut history = Vec::new();
+        let mut previous = Vec::new();
+        for i in 0..15 {
+            let current = vec![condition("Ready", i % 2 == 0, "Flapping")];
+            history = record(Some(&history), &previous, &current, &format!("t{}", i));
+            previous = current;
+        }
+        assert_eq!(history.len(), HISTORY_LIMIT);
+        assert_eq!(history[0].time, "t5");
+        assert_eq!(history[HISTORY_LIMIT - 1].time, "t14");
+    }
+}
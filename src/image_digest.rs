@@ -0,0 +1,663 @@
+//! Resolution of `spec.image` tags to content digests through the registry's
+//! v2 API, so an instance with `resolveImageDigest` runs the exact image the
+//! tag pointed to when it was reconciled, even if the tag is later moved.
+//! Registries are queried with the bearer token flow Docker Hub and most
+//! registries require, authenticated with the instance's `imagePullSecrets`
+//! when one holds credentials for the registry.
+
+use crate::types::{OpenFGA, OpenFGACondition};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use hyper::client::Client as HttpClient;
+use hyper::client::HttpConnector;
+use hyper::header::{ACCEPT, AUTHORIZATION, LOCATION, WWW_AUTHENTICATE};
+use hyper::{Body, Method, Request, Response, StatusCode};
+use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder};
+use k8s_openapi::api::core::v1::Secret;
+use kube::api::Api;
+use kube::{Client, ResourceExt};
+use serde::Deserialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tracing::{instrument, warn};
+
+/// Upper bound on resolving one image, token exchange included.
+pub const RESOLVE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long a resolved digest is reused before the registry is asked again.
+pub const CACHE_TTL: Duration = Duration::from_secs(300);
+
+const DOCKER_HUB: &str = "docker.io";
+const DOCKER_HUB_REGISTRY: &str = "registry-1.docker.io";
+const MAX_REDIRECTS: usize = 3;
+
+/// Manifest types accepted, multi-arch indexes first so the digest is the one
+/// every node architecture can pull.
+pub const MANIFEST_TYPES: &str = "application/vnd.oci.image.index.v1+json, \
+application/vnd.docker.distribution.manifest.list.v2+json, \
+application/vnd.oci.image.manifest.v1+json, \
+application/vnd.docker.distribution.manifest.v2+json";
+
+#[derive(Error, Debug)]
+pub enum DigestError {
+    #[error("HTTP error: {0}")]
+    Http(#[from] hyper::Error),
+    #[error("invalid request: {0}")]
+    Request(#[from] hyper::http::Error),
+    #[error("registry returned {status} for {url}")]
+    Status { status: u16, url: String },
+    #[error("registry token request failed: {0}")]
+    Token(String),
+    #[error("registry did not return a sha256 digest for {0}")]
+    MissingDigest(String),
+    #[error("registry returned an invalid manifest for {0}")]
+    InvalidManifest(String),
+    #[error("blob {0} does not match its digest")]
+    DigestMismatch(String),
+    #[error("no response from the registry within {}s", RESOLVE_TIMEOUT.as_secs())]
+    Timeout,
+}
+
+pub type DigestResult<T> = std::result::Result<T, DigestError>;
+
+/// An image reference split into the parts the registry API addresses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageReference {
+    /// Host of the registry API, `registry-1.docker.io` for Docker Hub.
+    pub registry: String,
+    /// Repository path, with `library/` for official Docker Hub images.
+    pub repository: String,
+    pub tag: String,
+    pub digest: Option<String>,
+}
+
+impl ImageReference {
+    /// Parses `[registry/]repository[:tag][@digest]`, defaulting the tag to `latest`.
+    pub fn parse(image: &str) -> Self {
+        let (name, digest) = match image.split_once('@') {
+            Some((name, digest)) => (name, Some(digest.to_string())),
+            None => (image, None),
+        };
+        let name_start = name.rfind('/').map_or(0, |i| i + 1);
+        let (name, tag) = match name[name_start..].rfind(':') {
+            Some(colon) => (
+                &name[..name_start + colon],
+                name[name_start + colon + 1..].to_string(),
+            ),
+            None => (name, "latest".to_string()),
+        };
+        let (registry, repository) = match name.split_once('/') {
+            Some((host, path))
+                if host.contains('.') || host.contains(':') || host == "localhost" =>
+            {
+                (host.to_string(), path.to_string())
+            }
+            _ => (DOCKER_HUB.to_string(), name.to_string()),
+        };
+        let (registry, repository) = if registry == DOCKER_HUB {
+            let repository = if repository.contains('/') {
+                repository
+            } else {
+                format!("library/{}", repository)
+            };
+            (DOCKER_HUB_REGISTRY.to_string(), repository)
+        } else {
+            (registry, repository)
+        };
+        Self {
+            registry,
+            repository,
+            tag,
+            digest,
+        }
+    }
+
+    fn manifest_url(&self, tag_or_digest: &str) -> String {
+        format!(
+            "https://{}/v2/{}/manifests/{}",
+            self.registry, self.repository, tag_or_digest
+        )
+    }
+}
+
+/// `image` pinned to `digest`, keeping the tag for readability:
+/// `openfga/openfga:v1.5.0@sha256:...`.
+pub fn pinned(image: &str, digest: &str) -> String {
+    let name = image.split_once('@').map_or(image, |(name, _)| name);
+    format!("{}@{}", name, digest)
+}
+
+pub fn is_sha256(digest: &str) -> bool {
+    digest
+        .strip_prefix("sha256:")
+        .is_some_and(|hex| hex.len() == 64 && hex.bytes().all(|b| b.is_ascii_hexdigit()))
+}
+
+/// The `key="value"` parameters of a `Bearer` `WWW-Authenticate` challenge.
+pub fn bearer_challenge(header: &str) -> Option<HashMap<String, String>> {
+    let params = header.strip_prefix("Bearer ")?;
+    let mut parsed = HashMap::new();
+    let mut rest = params.trim();
+    while let Some((key, value)) = rest.split_once("=\"") {
+        let (value, remainder) = value.split_once('"')?;
+        parsed.insert(key.trim().to_string(), value.to_string());
+        rest = remainder.trim_start_matches(',').trim();
+    }
+    parsed.contains_key("realm").then_some(parsed)
+}
+
+/// Credentials of `registry` in a `.dockerconfigjson` document, as base64
+/// `user:password`.
+pub fn docker_config_auth(config: &[u8], registry: &str) -> Option<String> {
+    let config: Value = serde_json::from_slice(config).ok()?;
+    let (_, entry) = config["auths"]
+        .as_object()?
+        .iter()
+        .find(|(server, _)| registry_host(server) == registry)?;
+    if let Some(auth) = entry["auth"].as_str() {
+        return Some(auth.to_string());
+    }
+    let username = entry["username"].as_str()?;
+    let password = entry["password"].as_str()?;
+    Some(BASE64.encode(format!("{}:{}", username, password)))
+}
+
+/// The registry API host a docker config server key stands for.
+fn registry_host(server: &str) -> &str {
+    let host = server
+        .trim_start_matches("https://")
+        .trim_start_matches("http://")
+        .split('/')
+        .next()
+        .unwrap_or_default();
+    match host {
+        DOCKER_HUB | "index.docker.io" => DOCKER_HUB_REGISTRY,
+        host => host,
+    }
+}
+
+/// Credentials for `registry` from the first of the instance's
+/// `imagePullSecrets` that has them.
+pub async fn pull_credentials(
+    client: &Client,
+    openfga: &OpenFGA,
+    registry: &str,
+) -> Option<String> {
+    let secrets: Api<Secret> =
+        Api::namespaced(client.clone(), &openfga.namespace().unwrap_or_default());
+    for reference in &openfga.spec.image_pull_secrets {
+        let Some(name) = reference.name.as_deref() else {
+            continue;
+        };
+        let secret = match secrets.get(name).await {
+            Ok(secret) => secret,
+            Err(e) => {
+                warn!(
+                    event = "pull_secret_unreadable",
+                    secret = %name,
+                    error = %e,
+                    "Failed to read image pull secret"
+                );
+                continue;
+            }
+        };
+        let auth = secret
+            .data
+            .as_ref()
+            .and_then(|data| data.get(".dockerconfigjson"))
+            .and_then(|config| docker_config_auth(&config.0, registry));
+        if auth.is_some() {
+            return auth;
+        }
+    }
+    None
+}
+
+/// Authorization state of the requests to one repository.
+#[derive(Debug, Clone, Default)]
+pub struct Session {
+    /// Base64 `user:password` for the registry, from a pull secret.
+    credentials: Option<String>,
+    /// `Authorization` header value obtained by answering a challenge.
+    authorization: Option<String>,
+}
+
+impl Session {
+    pub fn new(credentials: Option<String>) -> Self {
+        Self {
+            credentials,
+            authorization: None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    token: Option<String>,
+    access_token: Option<String>,
+}
+
+/// Registry client resolving tags to digests.
+#[derive(Clone)]
+pub struct Resolver {
+    http: HttpClient<HttpsConnector<HttpConnector>>,
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        let connector = HttpsConnectorBuilder::new()
+            .with_native_roots()
+            .https_or_http()
+            .enable_http1()
+            .build();
+        Self {
+            http: HttpClient::builder().build(connector),
+        }
+    }
+}
+
+impl Resolver {
+    /// The digest the tag of `image` currently points to. Images already
+    /// pinned to a digest are returned as they are.
+    #[instrument(skip(self, session))]
+    pub async fn resolve(&self, image: &str, session: &mut Session) -> DigestResult<String> {
+        let reference = ImageReference::parse(image);
+        if let Some(digest) = reference.digest {
+            return Ok(digest);
+        }
+        let url = reference.manifest_url(&reference.tag);
+        let response =
+            within_timeout(self.send(&reference, Method::HEAD, &url, MANIFEST_TYPES, session))
+                .await?;
+        response
+            .headers()
+            .get("docker-content-digest")
+            .and_then(|v| v.to_str().ok())
+            .filter(|digest| is_sha256(digest))
+            .map(str::to_string)
+            .ok_or(DigestError::MissingDigest(url))
+    }
+
+    /// The manifest stored under `tag_or_digest` in the repository of
+    /// `reference`, or `None` when there is none.
+    pub async fn manifest(
+        &self,
+        reference: &ImageReference,
+        tag_or_digest: &str,
+        accept: &str,
+        session: &mut Session,
+    ) -> DigestResult<Option<Value>> {
+        let url = reference.manifest_url(tag_or_digest);
+        let result = within_timeout(async {
+            let response = self
+                .send(reference, Method::GET, &url, accept, session)
+                .await?;
+            Ok(hyper::body::to_bytes(response.into_body()).await?)
+        })
+        .await;
+        match result {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map(Some)
+                .map_err(|_| DigestError::InvalidManifest(url)),
+            Err(DigestError::Status { status: 404, .. }) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The blob `digest` of the repository of `reference`, checked against
+    /// the digest. Registries commonly redirect blob downloads to a CDN.
+    pub async fn blob(
+        &self,
+        reference: &ImageReference,
+        digest: &str,
+        session: &mut Session,
+    ) -> DigestResult<Vec<u8>> {
+        let url = format!(
+            "https://{}/v2/{}/blobs/{}",
+            reference.registry, reference.repository, digest
+        );
+        let bytes = within_timeout(async {
+            let mut response = self
+                .send(reference, Method::GET, &url, "*/*", session)
+                .await?;
+            for _ in 0..MAX_REDIRECTS {
+                if !response.status().is_redirection() {
+                    break;
+                }
+                let location = response
+                    .headers()
+                    .get(LOCATION)
+                    .and_then(|v| v.to_str().ok())
+                    .ok_or_else(|| DigestError::Status {
+                        status: response.status().as_u16(),
+                        url: url.clone(),
+                    })?
+                    .to_string();
+                response = self
+                    .http
+                    .get(location.parse().map_err(hyper::http::Error::from)?)
+                    .await?;
+            }
+            if !response.status().is_success() {
+                return Err(DigestError::Status {
+                    status: response.status().as_u16(),
+                    url: url.clone(),
+                });
+            }
+            Ok(hyper::body::to_bytes(response.into_body()).await?)
+        })
+        .await?;
+        if format!("sha256:{:x}", Sha256::digest(&bytes)) != digest {
+            return Err(DigestError::DigestMismatch(url));
+        }
+        Ok(bytes.to_vec())
+    }
+
+    /// Sends the request, answering a bearer or basic challenge once.
+    /// Redirects are returned to the caller; other unsuccessful responses are
+    /// errors.
+    async fn send(
+        &self,
+        reference: &ImageReference,
+        method: Method,
+        url: &str,
+        accept: &str,
+        session: &mut Session,
+    ) -> DigestResult<Response<Body>> {
+        let authorization = session.authorization.clone();
+        let mut response = self
+            .http
+            .request(registry_request(
+                &method,
+                url,
+                accept,
+                authorization.as_deref(),
+            )?)
+            .await?;
+
+        if response.status() == StatusCode::UNAUTHORIZED {
+            let unauthorized = || DigestError::Status {
+                status: StatusCode::UNAUTHORIZED.as_u16(),
+                url: url.to_string(),
+            };
+            let challenge = response
+                .headers()
+                .get(WWW_AUTHENTICATE)
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or_default()
+                .to_string();
+            let authorization = if let Some(bearer) = bearer_challenge(&challenge) {
+                let token = self.token(&bearer, reference, session).await?;
+                format!("Bearer {}", token)
+            } else if challenge.starts_with("Basic") {
+                let credentials = session.credentials.as_ref().ok_or_else(unauthorized)?;
+                format!("Basic {}", credentials)
+            } else {
+                return Err(unauthorized());
+            };
+            session.authorization = Some(authorization.clone());
+            response = self
+                .http
+                .request(registry_request(
+                    &method,
+                    url,
+                    accept,
+                    Some(&authorization),
+                )?)
+                .await?;
+        }
+
+        let status = response.status();
+        if !status.is_success() && !status.is_redirection() {
+            return Err(DigestError::Status {
+                status: status.as_u16(),
+                url: url.to_string(),
+            });
+        }
+        Ok(response)
+    }
+
+    async fn token(
+        &self,
+        challenge: &HashMap<String, String>,
+        reference: &ImageReference,
+        session: &Session,
+    ) -> DigestResult<String> {
+        let scope = challenge
+            .get("scope")
+            .cloned()
+            .unwrap_or_else(|| format!("repository:{}:pull", reference.repository));
+        let mut url = format!("{}?scope={}", challenge["realm"], scope);
+        if let Some(service) = challenge.get("service") {
+            url.push_str(&format!("&service={}", service));
+        }
+        let mut request = Request::builder().uri(&url);
+        if let Some(credentials) = &session.credentials {
+            request = request.header(AUTHORIZATION, format!("Basic {}", credentials));
+        }
+        let response = self.http.request(request.body(Body::empty())?).await?;
+        let status = response.status();
+        let bytes = hyper::body::to_bytes(response.into_body()).await?;
+        if !status.is_success() {
+            return Err(DigestError::Token(format!("{} returned {}", url, status)));
+        }
+        let token: TokenResponse =
+            serde_json::from_slice(&bytes).map_err(|e| DigestError::Token(e.to_string()))?;
+        token
+            .token
+            .or(token.access_token)
+            .ok_or_else(|| DigestError::Token(format!("{} returned no token", url)))
+    }
+}
+
+async fn within_timeout<T>(request: impl Future<Output = DigestResult<T>>) -> DigestResult<T> {
+    match tokio::time::timeout(RESOLVE_TIMEOUT, request).await {
+        Ok(result) => result,
+        Err(_) => Err(DigestError::Timeout),
+    }
+}
+
+fn registry_request(
+    method: &Method,
+    url: &str,
+    accept: &str,
+    authorization: Option<&str>,
+) -> DigestResult<Request<Body>> {
+    let mut builder = Request::builder()
+        .method(method.clone())
+        .uri(url)
+        .header(ACCEPT, accept);
+    if let Some(authorization) = authorization {
+        builder = builder.header(AUTHORIZATION, authorization);
+    }
+    Ok(builder.body(Body::empty())?)
+}
+
+fn cache() -> &'static Mutex<HashMap<String, (Instant, String)>> {
+    static CACHE: OnceLock<Mutex<HashMap<String, (Instant, String)>>> = OnceLock::new();
+    CACHE.get_or_init(Default::default)
+}
+
+/// The resolver shared by every reconcile.
+pub fn resolver() -> &'static Resolver {
+    static RESOLVER: OnceLock<Resolver> = OnceLock::new();
+    RESOLVER.get_or_init(Resolver::default)
+}
+
+/// The image reference the instance's pods should run: `spec.image` pinned to
+/// its digest when `resolveImageDigest` or `imageVerification` is set, `None`
+/// otherwise. Digests are cached for [`CACHE_TTL`] so frequent reconciles do
+/// not hit the registry.
+pub async fn pinned_image(client: &Client, openfga: &OpenFGA) -> Option<Result<String, String>> {
+    if !openfga.spec.resolve_image_digest && openfga.spec.image_verification.is_none() {
+        return None;
+    }
+    let image = &openfga.spec.image;
+    let now = Instant::now();
+    let cached = cache()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(image)
+        .filter(|(resolved, _)| now.duration_since(*resolved) < CACHE_TTL)
+        .map(|(_, digest)| digest.clone());
+    let digest = match cached {
+        Some(digest) => digest,
+        None => {
+            let registry = ImageReference::parse(image).registry;
+            let credentials = pull_credentials(client, openfga, &registry).await;
+            match resolver()
+                .resolve(image, &mut Session::new(credentials))
+                .await
+            {
+                Ok(digest) => {
+                    cache()
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .insert(image.clone(), (now, digest.clone()));
+                    digest
+                }
+                Err(e) => return Some(Err(e.to_string())),
+            }
+        }
+    };
+    Some(Ok(pinned(image, &digest)))
+}
+
+/// The image to deploy when resolution failed: the digest recorded for the
+/// same `spec.image` by an earlier reconcile, so a registry outage does not
+/// move running pods back to the mutable tag.
+pub fn previously_pinned(openfga: &OpenFGA) -> Option<String> {
+    openfga
+        .status
+        .as_ref()
+        .and_then(|s| s.resolved_image.as_deref())
+        .filter(|resolved| {
+            resolved
+                .split_once('@')
+                .is_some_and(|(name, _)| name == openfga.spec.image)
+        })
+        .map(str::to_string)
+}
+
+/// The `ImageDigestResolved` condition for the outcome of [`pinned_image`].
+pub fn digest_condition(
+    result: &Result<String, String>,
+    generation: Option<i64>,
+    previous: Option<&[OpenFGACondition]>,
+) -> OpenFGACondition {
+    let (status, reason, message) = match result {
+        Ok(pinned) => (true, "Resolved", format!("Running {}", pinned)),
+        Err(e) => (false, "ResolutionFailed", e.clone()),
+    };
+    OpenFGACondition::new(
+        "ImageDigestResolved",
+        status,
+        reason,
+        &message,
+        generation,
+        previous,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIGEST: &str = "sha256:0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef";
+
+    #[test]
+    fn test_parse_image_references() {
+        let official = ImageReference::parse("nginx");
+        assert_eq!(official.registry, "registry-1.docker.io");
+        assert_eq!(official.repository, "library/nginx");
+        assert_eq!(official.tag, "latest");
+
+        let hub = ImageReference::parse("openfga/openfga:v1.5.0");
+        assert_eq!(hub.registry, "registry-1.docker.io");
+        assert_eq!(hub.repository, "openfga/openfga");
+        assert_eq!(hub.tag, "v1.5.0");
+
+        let mirror = ImageReference::parse("mirror.local:5000/team/openfga:v1.5.0");
+        assert_eq!(mirror.registry, "mirror.local:5000");
+        assert_eq!(mirror.repository, "team/openfga");
+        assert_eq!(
+            mirror.manifest_url(&mirror.tag),
+            "https://mirror.local:5000/v2/team/openfga/manifests/v1.5.0"
+        );
+
+        let pinned_ref = ImageReference::parse(&format!("ghcr.io/openfga/openfga:v1@{}", DIGEST));
+        assert_eq!(pinned_ref.registry, "ghcr.io");
+        assert_eq!(pinned_ref.tag, "v1");
+        assert_eq!(pinned_ref.digest.as_deref(), Some(DIGEST));
+    }
+
+    #[test]
+    fn test_pinned_keeps_tag_and_replaces_digest() {
+        assert_eq!(
+            pinned("openfga/openfga:v1.5.0", DIGEST),
+            format!("openfga/openfga:v1.5.0@{}", DIGEST)
+        );
+        assert_eq!(
+            pinned("openfga/openfga:v1.5.0@sha256:old", DIGEST),
+            format!("openfga/openfga:v1.5.0@{}", DIGEST)
+        );
+        assert!(is_sha256(DIGEST));
+        assert!(!is_sha256("sha256:abc"));
+    }
+
+    #[test]
+    fn test_docker_config_auth() {
+        let config = serde_json::json!({
+            "auths": {
+                "https://index.docker.io/v1/": { "auth": "aHViOnNlY3JldA==" },
+                "mirror.local:5000": { "username": "ci", "password": "s3cret" }
+            }
+        })
+        .to_string();
+        assert_eq!(
+            docker_config_auth(config.as_bytes(), "registry-1.docker.io").as_deref(),
+            Some("aHViOnNlY3JldA==")
+        );
+        assert_eq!(
+            docker_config_auth(config.as_bytes(), "mirror.local:5000"),
+            Some(BASE64.encode("ci:s3cret"))
+        );
+        assert_eq!(docker_config_auth(config.as_bytes(), "ghcr.io"), None);
+    }
+
+    #[test]
+    fn test_bearer_challenge() {
+        let challenge = bearer_challenge(
+            r#"Bearer realm="https://auth.docker.io/token",service="registry.docker.io",scope="repository:openfga/openfga:pull""#,
+        )
+        .unwrap();
+        assert_eq!(challenge["realm"], "https://auth.docker.io/token");
+        assert_eq!(challenge["service"], "registry.docker.io");
+        assert_eq!(challenge["scope"], "repository:openfga/openfga:pull");
+
+        assert!(bearer_challenge(r#"Basic realm="registry""#).is_none());
+    }
+
+    #[test]
+    fn test_previously_pinned_requires_same_image() {
+        let mut openfga = crate::controller::test_openfga(
+            serde_json::json!({ "image": "openfga/openfga:v1.5.0" }),
+        );
+        openfga.status = Some(crate::types::OpenFGAStatus {
+            resolved_image: Some(pinned("openfga/openfga:v1.5.0", DIGEST)),
+            ..Default::default()
+        });
+        assert_eq!(
+            previously_pinned(&openfga),
+            Some(format!("openfga/openfga:v1.5.0@{}", DIGEST))
+        );
+
+        openfga.spec.image = "openfga/openfga:v1.6.0".to_string();
+        assert_eq!(previously_pinned(&openfga), None);
+    }
+}
@@ -0,0 +1,881 @@
+//! Cosign signature verification of the OpenFGA image before it is deployed.
+//! Signatures are read from the `sha256-<digest>.sig` artifact cosign pushes
+//! next to the image. An image passes when one signature's payload names its
+//! digest and is signed either by the configured public key, or by a Fulcio
+//! certificate for the configured issuer and subject that chains to the
+//! configured roots.
+//!
+//! Fulcio certificates are only valid for minutes, so a keyless signature is
+//! checked at the time the Rekor transparency log recorded it. That time comes
+//! from the bundle cosign attaches to the signature, which is trusted only once
+//! its signed entry timestamp verifies against the configured Rekor public key
+//! and the log entry it covers records this signature, certificate and payload.
+//! Only `hashedrekord` entries, which cosign has written since 1.x, are
+//! understood. The log's inclusion proof is not checked.
+
+use crate::controller::ControllerResult;
+use crate::history;
+use crate::image_digest::{self, DigestError, ImageReference, Session};
+use crate::types::{ImageVerification, KeylessVerification, OpenFGA, OpenFGACondition};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use k8s_openapi::api::core::v1::Secret;
+use kube::api::Api;
+use kube::{Client, ResourceExt};
+use ring::signature::{self, UnparsedPublicKey, VerificationAlgorithm};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
+use tracing::{info, instrument};
+
+const SIGNATURE_ANNOTATION: &str = "dev.cosignproject.cosign/signature";
+const CERTIFICATE_ANNOTATION: &str = "dev.sigstore.cosign/certificate";
+const CHAIN_ANNOTATION: &str = "dev.sigstore.cosign/chain";
+const BUNDLE_ANNOTATION: &str = "dev.sigstore.cosign/bundle";
+
+const OCI_MANIFEST: &str =
+    "application/vnd.oci.image.manifest.v1+json, application/vnd.docker.distribution.manifest.v2+json";
+
+const OID_EC_PUBLIC_KEY: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01];
+const OID_P256: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07];
+const OID_P384: &[u8] = &[0x2b, 0x81, 0x04, 0x00, 0x22];
+const OID_SUBJECT_ALT_NAME: &[u8] = &[0x55, 0x1d, 0x11];
+/// Fulcio's OIDC issuer extensions: the raw-string original and its DER successor.
+const OID_FULCIO_ISSUER: &[u8] = &[0x2b, 0x06, 0x01, 0x04, 0x01, 0x83, 0xbf, 0x30, 0x01, 0x01];
+const OID_FULCIO_ISSUER_V2: &[u8] = &[0x2b, 0x06, 0x01, 0x04, 0x01, 0x83, 0xbf, 0x30, 0x01, 0x08];
+/// id-kp-codeSigning, the extended key usage of Fulcio certificates.
+const EKU_CODE_SIGNING: &[u8] = &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x03, 0x03];
+
+const CERTIFICATE_ALGORITHMS: &[&webpki::SignatureAlgorithm] = &[
+    &webpki::ECDSA_P256_SHA256,
+    &webpki::ECDSA_P256_SHA384,
+    &webpki::ECDSA_P384_SHA256,
+    &webpki::ECDSA_P384_SHA384,
+];
+
+/// An ECDSA public key, as cosign generates them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PublicKey {
+    curve: &'static str,
+    point: Vec<u8>,
+}
+
+impl PublicKey {
+    /// The key of a `PUBLIC KEY` PEM block.
+    pub fn from_pem(pem: &str) -> Result<Self, String> {
+        let block = pem::parse(pem).map_err(|e| format!("invalid public key PEM: {}", e))?;
+        if block.tag() != "PUBLIC KEY" {
+            return Err(format!(
+                "expected a PUBLIC KEY PEM block, got {}",
+                block.tag()
+            ));
+        }
+        Self::from_spki(block.contents())
+    }
+
+    /// The key of a DER SubjectPublicKeyInfo.
+    fn from_spki(spki: &[u8]) -> Result<Self, String> {
+        let unsupported = || "only ECDSA P-256 and P-384 public keys are supported".to_string();
+        let (_, spki, _) = der_element(spki).ok_or_else(unsupported)?;
+        let fields = der_children(spki).ok_or_else(unsupported)?;
+        let [(_, algorithm), (0x03, bits)] = fields.as_slice() else {
+            return Err(unsupported());
+        };
+        let algorithm = der_children(algorithm).ok_or_else(unsupported)?;
+        let curve = match algorithm.as_slice() {
+            [(0x06, OID_EC_PUBLIC_KEY), (0x06, OID_P256)] => "P-256",
+            [(0x06, OID_EC_PUBLIC_KEY), (0x06, OID_P384)] => "P-384",
+            _ => return Err(unsupported()),
+        };
+        let point = bits.strip_prefix(&[0]).ok_or_else(unsupported)?;
+        Ok(Self {
+            curve,
+            point: point.to_vec(),
+        })
+    }
+
+    fn algorithm(&self) -> &'static dyn VerificationAlgorithm {
+        match self.curve {
+            "P-384" => &signature::ECDSA_P384_SHA384_ASN1,
+            _ => &signature::ECDSA_P256_SHA256_ASN1,
+        }
+    }
+
+    /// Checks the DER ECDSA `signature` of `message`.
+    pub fn verify(&self, message: &[u8], signature: &[u8]) -> Result<(), String> {
+        UnparsedPublicKey::new(self.algorithm(), &self.point)
+            .verify(message, signature)
+            .map_err(|_| "signature does not match the payload".to_string())
+    }
+}
+
+/// One cosign signature of an image.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Signature {
+    /// The signed simple-signing JSON payload.
+    pub payload: Vec<u8>,
+    pub signature: Vec<u8>,
+    /// PEM signing certificate of a keyless signature.
+    pub certificate: Option<String>,
+    /// PEM intermediates between the certificate and the Fulcio root.
+    pub chain: Option<String>,
+    /// JSON Rekor bundle of a keyless signature.
+    pub bundle: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Bundle {
+    #[serde(rename = "SignedEntryTimestamp")]
+    signed_entry_timestamp: String,
+    #[serde(rename = "Payload")]
+    payload: BundlePayload,
+}
+
+/// The log entry a bundle's timestamp signs. Fields are declared in key
+/// order, so serializing one yields the canonical JSON Rekor signed.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BundlePayload {
+    body: String,
+    integrated_time: u64,
+    #[serde(rename = "logID")]
+    log_id: String,
+    log_index: u64,
+}
+
+/// The identity a Fulcio certificate was issued to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CertificateIdentity {
+    /// Email and URI subject alternative names.
+    pub subjects: Vec<String>,
+    /// The OIDC issuer that authenticated the signer.
+    pub issuer: Option<String>,
+    pub public_key: PublicKey,
+}
+
+/// How signatures are checked, with key material loaded.
+#[derive(Debug, Clone)]
+pub enum Verifier {
+    Key(PublicKey),
+    Keyless(KeylessVerification),
+}
+
+/// One DER element of `input`: its tag, contents and the bytes that follow it.
+fn der_element(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, rest) = input.split_first()?;
+    let (&first, rest) = rest.split_first()?;
+    let (length, rest) = if first < 0x80 {
+        (first as usize, rest)
+    } else {
+        let octets = (first & 0x7f) as usize;
+        if octets == 0 || octets > 4 || rest.len() < octets {
+            return None;
+        }
+        let length = rest[..octets]
+            .iter()
+            .fold(0usize, |length, &b| (length << 8) | b as usize);
+        (length, &rest[octets..])
+    };
+    (rest.len() >= length).then(|| (tag, &rest[..length], &rest[length..]))
+}
+
+/// The tags and contents of the elements of a constructed DER value.
+fn der_children(mut input: &[u8]) -> Option<Vec<(u8, &[u8])>> {
+    let mut children = Vec::new();
+    while !input.is_empty() {
+        let (tag, contents, rest) = der_element(input)?;
+        children.push((tag, contents));
+        input = rest;
+    }
+    Some(children)
+}
+
+/// Reads the subject alternative names, Fulcio issuer and public key of a DER
+/// certificate.
+pub fn certificate_identity(der: &[u8]) -> Result<CertificateIdentity, String> {
+    let invalid = || "invalid signing certificate".to_string();
+    let (_, certificate, _) = der_element(der).ok_or_else(invalid)?;
+    let (_, tbs, _) = der_element(certificate).ok_or_else(invalid)?;
+    let mut fields = der_children(tbs).ok_or_else(invalid)?;
+    // Drop the explicit version so the fields line up
+    if fields.first().is_some_and(|(tag, _)| *tag == 0xa0) {
+        fields.remove(0);
+    }
+    // serial, signature, issuer, validity, subject, subjectPublicKeyInfo
+    let spki = fields.get(5).ok_or_else(invalid)?;
+    let mut spki_der = vec![0x30];
+    spki_der.extend(der_length(spki.1.len()));
+    spki_der.extend(spki.1);
+    let public_key = PublicKey::from_spki(&spki_der)?;
+
+    let extensions = fields
+        .iter()
+        .find(|(tag, _)| *tag == 0xa3)
+        .and_then(|(_, explicit)| der_element(explicit))
+        .and_then(|(_, extensions, _)| der_children(extensions))
+        .unwrap_or_default();
+    let mut subjects = Vec::new();
+    let mut issuer = None;
+    let mut issuer_v2 = None;
+    for (_, extension) in extensions {
+        let parts = der_children(extension).ok_or_else(invalid)?;
+        let (Some((0x06, oid)), Some((0x04, value))) = (parts.first(), parts.last()) else {
+            continue;
+        };
+        match *oid {
+            OID_SUBJECT_ALT_NAME => {
+                let (_, names, _) = der_element(value).ok_or_else(invalid)?;
+                for (tag, name) in der_children(names).ok_or_else(invalid)? {
+                    // rfc822Name and uniformResourceIdentifier
+                    if tag == 0x81 || tag == 0x86 {
+                        subjects.push(String::from_utf8_lossy(name).into_owned());
+                    }
+                }
+            }
+            OID_FULCIO_ISSUER => issuer = Some(String::from_utf8_lossy(value).into_owned()),
+            OID_FULCIO_ISSUER_V2 => {
+                issuer_v2 = der_element(value)
+                    .map(|(_, issuer, _)| String::from_utf8_lossy(issuer).into_owned());
+            }
+            _ => {}
+        }
+    }
+    Ok(CertificateIdentity {
+        subjects,
+        issuer: issuer_v2.or(issuer),
+        public_key,
+    })
+}
+
+fn der_length(length: usize) -> Vec<u8> {
+    if length < 0x80 {
+        return vec![length as u8];
+    }
+    let bytes: Vec<u8> = length
+        .to_be_bytes()
+        .into_iter()
+        .skip_while(|b| *b == 0)
+        .collect();
+    let mut encoded = vec![0x80 | bytes.len() as u8];
+    encoded.extend(bytes);
+    encoded
+}
+
+fn certificates(pem: &str) -> Result<Vec<Vec<u8>>, String> {
+    pem::parse_many(pem)
+        .map_err(|e| format!("invalid certificate PEM: {}", e))
+        .map(|blocks| {
+            blocks
+                .into_iter()
+                .filter(|b| b.tag() == "CERTIFICATE")
+                .map(|b| b.into_contents())
+                .collect()
+        })
+}
+
+/// Checks the Rekor bundle of a keyless signature whose certificate is `leaf`,
+/// returning when the log recorded it.
+pub fn verify_bundle(rekor: &PublicKey, signature: &Signature, leaf: &[u8]) -> Result<u64, String> {
+    let bundle = signature
+        .bundle
+        .as_deref()
+        .ok_or("keyless signature has no transparency log bundle")?;
+    let bundle: Bundle =
+        serde_json::from_str(bundle).map_err(|e| format!("invalid Rekor bundle: {}", e))?;
+    let timestamp = BASE64
+        .decode(&bundle.signed_entry_timestamp)
+        .map_err(|_| "invalid Rekor bundle: timestamp is not base64")?;
+    let canonical = serde_json::to_vec(&bundle.payload).expect("bundle payload serializes");
+    rekor
+        .verify(&canonical, &timestamp)
+        .map_err(|_| "Rekor bundle is not signed by the configured Rekor key")?;
+
+    let body = BASE64
+        .decode(&bundle.payload.body)
+        .ok()
+        .and_then(|body| serde_json::from_slice::<Value>(&body).ok())
+        .ok_or("invalid Rekor bundle: entry body is not base64 JSON")?;
+    if body["kind"] != "hashedrekord" {
+        return Err(format!(
+            "Rekor entry is of kind {}, only hashedrekord is supported",
+            body["kind"]
+        ));
+    }
+    let spec = &body["spec"];
+    let payload_hash: String = Sha256::digest(&signature.payload)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    if spec["data"]["hash"]["algorithm"] != "sha256"
+        || spec["data"]["hash"]["value"] != payload_hash.as_str()
+    {
+        return Err("Rekor entry records a different payload".to_string());
+    }
+    let decoded = |value: &Value| value.as_str().and_then(|v| BASE64.decode(v).ok());
+    if decoded(&spec["signature"]["content"]).as_deref() != Some(signature.signature.as_slice()) {
+        return Err("Rekor entry records a different signature".to_string());
+    }
+    let logged = decoded(&spec["signature"]["publicKey"]["content"])
+        .and_then(|pem| certificates(&String::from_utf8_lossy(&pem)).ok())
+        .and_then(|certs| certs.into_iter().next());
+    if logged.as_deref() != Some(leaf) {
+        return Err("Rekor entry records a different signing certificate".to_string());
+    }
+    Ok(bundle.payload.integrated_time)
+}
+
+/// Checks that the signing certificate chains to `roots` and was issued to the
+/// expected identity at the time Rekor logged the signature, returning the key
+/// it certifies.
+pub fn verify_certificate(
+    keyless: &KeylessVerification,
+    signature: &Signature,
+) -> Result<PublicKey, String> {
+    let certificate = signature
+        .certificate
+        .as_deref()
+        .ok_or("signature has no signing certificate")?;
+    let leaf = certificates(certificate)?
+        .into_iter()
+        .next()
+        .ok_or("signature has no signing certificate")?;
+    let intermediates = match signature.chain.as_deref() {
+        Some(chain) => certificates(chain)?,
+        None => Vec::new(),
+    };
+    let roots = certificates(&keyless.trusted_roots)?;
+    let anchors = roots
+        .iter()
+        .map(|root| webpki::TrustAnchor::try_from_cert_der(root))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("invalid trusted root: {:?}", e))?;
+    let rekor = keyless
+        .rekor_public_key
+        .as_deref()
+        .ok_or("keyless verification requires keyless.rekorPublicKey")
+        .and_then(|pem| {
+            PublicKey::from_pem(pem).map_err(|_| "keyless.rekorPublicKey is not an ECDSA key")
+        })?;
+    let time = verify_bundle(&rekor, signature, &leaf)?;
+
+    let end_entity = webpki::EndEntityCert::try_from(leaf.as_slice())
+        .map_err(|e| format!("invalid signing certificate: {:?}", e))?;
+    let intermediates: Vec<&[u8]> = intermediates.iter().map(Vec::as_slice).collect();
+    end_entity
+        .verify_for_usage(
+            CERTIFICATE_ALGORITHMS,
+            &anchors,
+            &intermediates,
+            webpki::Time::from_seconds_since_unix_epoch(time),
+            webpki::KeyUsage::required(EKU_CODE_SIGNING),
+            &[],
+        )
+        .map_err(|e| format!("signing certificate is not trusted: {:?}", e))?;
+
+    let identity = certificate_identity(&leaf)?;
+    if identity.issuer.as_deref() != Some(keyless.issuer.as_str()) {
+        return Err(format!(
+            "signing certificate was issued by {}, expected {}",
+            identity.issuer.as_deref().unwrap_or("an unknown issuer"),
+            keyless.issuer
+        ));
+    }
+    if !identity.subjects.contains(&keyless.subject) {
+        return Err(format!(
+            "signing certificate identifies {}, expected {}",
+            identity.subjects.join(", "),
+            keyless.subject
+        ));
+    }
+    Ok(identity.public_key)
+}
+
+/// Checks that the simple-signing `payload` is about the image `digest`.
+pub fn check_payload(payload: &[u8], digest: &str) -> Result<(), String> {
+    let payload: Value =
+        serde_json::from_slice(payload).map_err(|e| format!("invalid payload: {}", e))?;
+    let signed = payload["critical"]["image"]["docker-manifest-digest"].as_str();
+    if signed != Some(digest) {
+        return Err(format!(
+            "payload signs {}, not {}",
+            signed.unwrap_or("no digest"),
+            digest
+        ));
+    }
+    Ok(())
+}
+
+/// Verifies one signature of the image `digest`.
+pub fn verify_signature(
+    verifier: &Verifier,
+    signature: &Signature,
+    digest: &str,
+) -> Result<(), String> {
+    check_payload(&signature.payload, digest)?;
+    let key = match verifier {
+        Verifier::Key(key) => key.clone(),
+        Verifier::Keyless(keyless) => verify_certificate(keyless, signature)?,
+    };
+    key.verify(&signature.payload, &signature.signature)
+}
+
+/// The signatures cosign stored for the image `digest` in the repository of
+/// `reference`.
+async fn fetch_signatures(
+    reference: &ImageReference,
+    digest: &str,
+    session: &mut Session,
+) -> Result<Vec<Signature>, DigestError> {
+    let resolver = image_digest::resolver();
+    let tag = format!("{}.sig", digest.replace(':', "-"));
+    let Some(manifest) = resolver
+        .manifest(reference, &tag, OCI_MANIFEST, session)
+        .await?
+    else {
+        return Ok(Vec::new());
+    };
+
+    let mut signatures = Vec::new();
+    for layer in manifest["layers"].as_array().into_iter().flatten() {
+        let annotation = |key: &str| layer["annotations"][key].as_str().map(str::to_string);
+        let (Some(layer_digest), Some(encoded)) =
+            (layer["digest"].as_str(), annotation(SIGNATURE_ANNOTATION))
+        else {
+            continue;
+        };
+        let Ok(signature) = BASE64.decode(encoded) else {
+            continue;
+        };
+        let payload = resolver.blob(reference, layer_digest, session).await?;
+        signatures.push(Signature {
+            payload,
+            signature,
+            certificate: annotation(CERTIFICATE_ANNOTATION),
+            chain: annotation(CHAIN_ANNOTATION),
+            bundle: annotation(BUNDLE_ANNOTATION),
+        });
+    }
+    Ok(signatures)
+}
+
+async fn load_verifier(
+    client: &Client,
+    ns: &str,
+    policy: &ImageVerification,
+) -> Result<Verifier, String> {
+    if let Some(keyless) = &policy.keyless {
+        return Ok(Verifier::Keyless(keyless.clone()));
+    }
+    if let Some(pem) = &policy.public_key {
+        return PublicKey::from_pem(pem).map(Verifier::Key);
+    }
+    let Some(reference) = &policy.public_key_secret_ref else {
+        return Err("imageVerification sets no publicKey, publicKeySecretRef or keyless".into());
+    };
+    let secret = Api::<Secret>::namespaced(client.clone(), ns)
+        .get(&reference.name)
+        .await
+        .map_err(|e| format!("failed to read Secret {}: {}", reference.name, e))?;
+    let pem = secret
+        .data
+        .as_ref()
+        .and_then(|data| data.get(&reference.key))
+        .ok_or_else(|| format!("Secret {} has no key {}", reference.name, reference.key))?;
+    PublicKey::from_pem(&String::from_utf8_lossy(&pem.0)).map(Verifier::Key)
+}
+
+fn cache() -> &'static Mutex<HashMap<String, Instant>> {
+    static CACHE: OnceLock<Mutex<HashMap<String, Instant>>> = OnceLock::new();
+    CACHE.get_or_init(Default::default)
+}
+
+/// Verifies the signature of `image`, which must be pinned to a digest.
+/// Successful verifications are cached for [`image_digest::CACHE_TTL`].
+#[instrument(skip(client, openfga, policy), fields(namespace = %openfga.namespace().unwrap_or_default(), name = %openfga.name_any()))]
+pub async fn verify(
+    client: &Client,
+    openfga: &OpenFGA,
+    policy: &ImageVerification,
+    image: &str,
+) -> Result<String, String> {
+    let reference = ImageReference::parse(image);
+    let digest = reference
+        .digest
+        .clone()
+        .filter(|digest| image_digest::is_sha256(digest))
+        .ok_or_else(|| format!("{} is not pinned to a digest", image))?;
+    let ns = openfga.namespace().unwrap_or_default();
+    let verifier = load_verifier(client, &ns, policy).await?;
+
+    let signer = match &verifier {
+        Verifier::Key(_) => "the configured public key".to_string(),
+        Verifier::Keyless(keyless) => format!("{} ({})", keyless.subject, keyless.issuer),
+    };
+    let cache_key = format!("{}|{:?}", image, verifier);
+    let now = Instant::now();
+    let cached = cache()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(&cache_key)
+        .is_some_and(|verified| now.duration_since(*verified) < image_digest::CACHE_TTL);
+    if cached {
+        return Ok(format!("{} is signed by {}", digest, signer));
+    }
+
+    let credentials = image_digest::pull_credentials(client, openfga, &reference.registry).await;
+    let signatures = fetch_signatures(&reference, &digest, &mut Session::new(credentials))
+        .await
+        .map_err(|e| format!("failed to fetch signatures: {}", e))?;
+    if signatures.is_empty() {
+        return Err(format!("no cosign signatures found for {}", image));
+    }
+    let mut failures = Vec::new();
+    for signature in &signatures {
+        match verify_signature(&verifier, signature, &digest) {
+            Ok(()) => {
+                cache()
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .insert(cache_key, now);
+                info!(
+                    event = "image_signature_verified",
+                    image = %image,
+                    signer = %signer,
+                    "Verified image signature"
+                );
+                return Ok(format!("{} is signed by {}", digest, signer));
+            }
+            Err(e) => failures.push(e),
+        }
+    }
+    Err(format!(
+        "none of {} signature(s) verified: {}",
+        signatures.len(),
+        failures.join("; ")
+    ))
+}
+
+/// The `ImageVerified` condition for the outcome of [`verify`].
+pub fn verified_condition(
+    result: &Result<String, String>,
+    generation: Option<i64>,
+    previous: Option<&[OpenFGACondition]>,
+) -> OpenFGACondition {
+    let (status, reason, message) = match result {
+        Ok(message) => (true, "SignatureVerified", message.as_str()),
+        Err(e) => (false, "VerificationFailed", e.as_str()),
+    };
+    OpenFGACondition::new(
+        "ImageVerified",
+        status,
+        reason,
+        message,
+        generation,
+        previous,
+    )
+}
+
+/// Records a failed verification on the instance before the reconcile fails,
+/// since the status update at the end of the reconcile is not reached.
+pub async fn set_failed_condition(
+    client: &Client,
+    openfga: &OpenFGA,
+    error: &str,
+) -> ControllerResult<()> {
+    let condition = verified_condition(
+        &Err(error.to_string()),
+        openfga.metadata.generation,
+        openfga
+            .status
+            .as_ref()
+            .and_then(|s| s.conditions.as_deref()),
+    );
+    history::set_condition(client, openfga, condition).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::rand::SystemRandom;
+    use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_ASN1_SIGNING};
+    use serde_json::json;
+
+    const DIGEST: &str = "sha256:0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef";
+    const ISSUER: &str = "https://token.actions.githubusercontent.com";
+    const SUBJECT: &str =
+        "https://github.com/openfga/openfga/.github/workflows/release.yml@refs/tags/v1.5.0";
+    const FULCIO_ROOT: &str = include_str!("../tests/fixtures/cosign/fulcio-root.pem");
+    const SIGNING_CERT: &str = include_str!("../tests/fixtures/cosign/signing-cert.pem");
+    const SIGNING_KEY: &str = include_str!("../tests/fixtures/cosign/signing-key.pem");
+
+    /// Within the validity of the fixture signing certificate.
+    const SIGNED_AT: u64 = 1_800_000_000;
+
+    fn payload(digest: &str) -> Vec<u8> {
+        json!({
+            "critical": {
+                "identity": { "docker-reference": "openfga/openfga" },
+                "image": { "docker-manifest-digest": digest },
+                "type": "cosign container image signature"
+            },
+            "optional": null
+        })
+        .to_string()
+        .into_bytes()
+    }
+
+    fn sign(pkcs8: &[u8], message: &[u8]) -> (EcdsaKeyPair, Vec<u8>) {
+        let rng = SystemRandom::new();
+        let key = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, pkcs8, &rng).unwrap();
+        let signature = key.sign(&rng, message).unwrap().as_ref().to_vec();
+        (key, signature)
+    }
+
+    fn public_key_pem(key: &EcdsaKeyPair) -> String {
+        // SubjectPublicKeyInfo header of an uncompressed P-256 point
+        let mut spki = vec![
+            0x30, 0x59, 0x30, 0x13, 0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01, 0x06,
+            0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07, 0x03, 0x42, 0x00,
+        ];
+        spki.extend(key.public_key().as_ref());
+        pem::encode(&pem::Pem::new("PUBLIC KEY", spki))
+    }
+
+    #[test]
+    fn test_public_key_signature() {
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &rng).unwrap();
+        let payload = payload(DIGEST);
+        let (key, signature) = sign(pkcs8.as_ref(), &payload);
+        let verifier = Verifier::Key(PublicKey::from_pem(&public_key_pem(&key)).unwrap());
+        let signed = Signature {
+            payload,
+            signature,
+            ..Default::default()
+        };
+        assert_eq!(verify_signature(&verifier, &signed, DIGEST), Ok(()));
+
+        // A valid signature of another image does not vouch for this one
+        let other = DIGEST.replace("0123", "3210");
+        assert!(verify_signature(&verifier, &signed, &other)
+            .unwrap_err()
+            .contains("payload signs"));
+
+        let mut tampered = signed.clone();
+        tampered.payload = payload_with_extra(&tampered.payload);
+        assert!(verify_signature(&verifier, &tampered, DIGEST).is_err());
+
+        let other_pkcs8 =
+            EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &rng).unwrap();
+        let (other_key, _) = sign(other_pkcs8.as_ref(), b"");
+        let wrong = Verifier::Key(PublicKey::from_pem(&public_key_pem(&other_key)).unwrap());
+        assert_eq!(
+            verify_signature(&wrong, &signed, DIGEST),
+            Err("signature does not match the payload".to_string())
+        );
+    }
+
+    fn payload_with_extra(payload: &[u8]) -> Vec<u8> {
+        let mut value: Value = serde_json::from_slice(payload).unwrap();
+        value["optional"] = json!({ "injected": true });
+        value.to_string().into_bytes()
+    }
+
+    #[test]
+    fn test_rejects_non_ecdsa_keys() {
+        let ed25519 = "-----BEGIN PUBLIC KEY-----\nMCowBQYDK2VwAyEAGb9ECWmEzf6FQbrBZ9w7lshQhqowtrbLDFw4rXAxZuE=\n-----END PUBLIC KEY-----\n";
+        assert!(PublicKey::from_pem(ed25519)
+            .unwrap_err()
+            .contains("only ECDSA"));
+        assert!(PublicKey::from_pem(FULCIO_ROOT).is_err());
+    }
+
+    #[test]
+    fn test_certificate_identity() {
+        let der = certificates(SIGNING_CERT).unwrap().remove(0);
+        let identity = certificate_identity(&der).unwrap();
+        assert_eq!(identity.subjects, vec![SUBJECT.to_string()]);
+        assert_eq!(identity.issuer.as_deref(), Some(ISSUER));
+        assert_eq!(identity.public_key.curve, "P-256");
+    }
+
+    /// A Rekor key and a bundle it signed, logging `signed` at `integrated_time`.
+    fn rekor_bundle(signed: &Signature, integrated_time: u64) -> (String, String) {
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &rng).unwrap();
+        let body = json!({
+            "apiVersion": "0.0.1",
+            "kind": "hashedrekord",
+            "spec": {
+                "data": { "hash": {
+                    "algorithm": "sha256",
+                    "value": format!("{:x}", Sha256::digest(&signed.payload)),
+                } },
+                "signature": {
+                    "content": BASE64.encode(&signed.signature),
+                    "publicKey": { "content": BASE64.encode(SIGNING_CERT) },
+                },
+            },
+        });
+        let payload = BundlePayload {
+            body: BASE64.encode(body.to_string()),
+            integrated_time,
+            log_id: "c0d23d6ad406973f9559f3ba2d1ca01f84147d8ffc5b8445c224f98b9591801d".to_string(),
+            log_index: 42,
+        };
+        let (key, timestamp) = sign(pkcs8.as_ref(), &serde_json::to_vec(&payload).unwrap());
+        let bundle = json!({
+            "SignedEntryTimestamp": BASE64.encode(timestamp),
+            "Payload": payload,
+        });
+        (public_key_pem(&key), bundle.to_string())
+    }
+
+    #[test]
+    fn test_keyless_signature() {
+        let key = pem::parse(SIGNING_KEY).unwrap();
+        let payload = payload(DIGEST);
+        let (_, signature) = sign(key.contents(), &payload);
+        let mut signed = Signature {
+            payload,
+            signature,
+            certificate: Some(SIGNING_CERT.to_string()),
+            chain: None,
+            bundle: None,
+        };
+        let (rekor_key, bundle) = rekor_bundle(&signed, SIGNED_AT);
+        signed.bundle = Some(bundle);
+        let keyless = KeylessVerification {
+            issuer: ISSUER.to_string(),
+            subject: SUBJECT.to_string(),
+            trusted_roots: FULCIO_ROOT.to_string(),
+            rekor_public_key: Some(rekor_key.clone()),
+        };
+        let verifier = Verifier::Keyless(keyless.clone());
+        assert_eq!(verify_signature(&verifier, &signed, DIGEST), Ok(()));
+
+        let other_subject = Verifier::Keyless(KeylessVerification {
+            subject:
+                "https://github.com/attacker/fork/.github/workflows/release.yml@refs/heads/main"
+                    .to_string(),
+            ..keyless.clone()
+        });
+        assert!(verify_signature(&other_subject, &signed, DIGEST)
+            .unwrap_err()
+            .contains("expected https://github.com/attacker"));
+
+        let other_issuer = Verifier::Keyless(KeylessVerification {
+            issuer: "https://accounts.google.com".to_string(),
+            ..keyless.clone()
+        });
+        assert!(verify_signature(&other_issuer, &signed, DIGEST)
+            .unwrap_err()
+            .contains("issued by"));
+
+        // The certificate was not valid yet when this signature was logged
+        let (early_key, early_bundle) = rekor_bundle(&signed, 1_600_000_000);
+        let early = Signature {
+            bundle: Some(early_bundle),
+            ..signed.clone()
+        };
+        let early_verifier = Verifier::Keyless(KeylessVerification {
+            rekor_public_key: Some(early_key),
+            ..keyless.clone()
+        });
+        assert!(verify_signature(&early_verifier, &early, DIGEST)
+            .unwrap_err()
+            .contains("not trusted"));
+
+        // A bundle from another log, e.g. forged by the registry
+        let (_, forged) = rekor_bundle(&signed, SIGNED_AT);
+        let forged = Signature {
+            bundle: Some(forged),
+            ..signed.clone()
+        };
+        assert!(verify_signature(&verifier, &forged, DIGEST)
+            .unwrap_err()
+            .contains("not signed by the configured Rekor key"));
+
+        let unlogged = Signature {
+            bundle: None,
+            ..signed.clone()
+        };
+        assert!(verify_signature(&verifier, &unlogged, DIGEST)
+            .unwrap_err()
+            .contains("transparency log"));
+
+        let no_rekor_key = Verifier::Keyless(KeylessVerification {
+            rekor_public_key: None,
+            ..keyless.clone()
+        });
+        assert!(verify_signature(&no_rekor_key, &signed, DIGEST)
+            .unwrap_err()
+            .contains("rekorPublicKey"));
+
+        // A certificate that does not chain to the configured roots is rejected
+        let untrusted = Verifier::Keyless(KeylessVerification {
+            trusted_roots: SIGNING_CERT.to_string(),
+            ..keyless
+        });
+        assert!(verify_signature(&untrusted, &signed, DIGEST).is_err());
+    }
+
+    #[test]
+    fn test_bundle_must_log_this_signature() {
+        let key = pem::parse(SIGNING_KEY).unwrap();
+        let leaf = certificates(SIGNING_CERT).unwrap().remove(0);
+        let (_, signature) = sign(key.contents(), &payload(DIGEST));
+        let logged = Signature {
+            payload: payload(DIGEST),
+            signature,
+            ..Default::default()
+        };
+        let (rekor_key, bundle) = rekor_bundle(&logged, SIGNED_AT);
+        let rekor = PublicKey::from_pem(&rekor_key).unwrap();
+        let with_bundle = |signed: &Signature| Signature {
+            bundle: Some(bundle.clone()),
+            ..signed.clone()
+        };
+        assert_eq!(
+            verify_bundle(&rekor, &with_bundle(&logged), &leaf),
+            Ok(SIGNED_AT)
+        );
+
+        // The bundle of one signature does not date another
+        let (_, other_signature) = sign(key.contents(), &payload(DIGEST));
+        let other = Signature {
+            signature: other_signature,
+            ..logged.clone()
+        };
+        assert!(verify_bundle(&rekor, &with_bundle(&other), &leaf)
+            .unwrap_err()
+            .contains("different signature"));
+
+        let other_payload = Signature {
+            payload: payload_with_extra(&logged.payload),
+            ..logged.clone()
+        };
+        assert!(verify_bundle(&rekor, &with_bundle(&other_payload), &leaf)
+            .unwrap_err()
+            .contains("different payload"));
+
+        let root = certificates(FULCIO_ROOT).unwrap().remove(0);
+        assert!(verify_bundle(&rekor, &with_bundle(&logged), &root)
+            .unwrap_err()
+            .contains("different signing certificate"));
+    }
+
+    #[test]
+    fn test_der_length_round_trips() {
+        for length in [0usize, 0x7f, 0x80, 0xff, 0x100, 0x1234] {
+            let mut element = vec![0x04];
+            element.extend(der_length(length));
+            element.extend(vec![0u8; length]);
+            let (tag, contents, rest) = der_element(&element).unwrap();
+            assert_eq!((tag, contents.len(), rest.len()), (0x04, length, 0));
+        }
+    }
+}
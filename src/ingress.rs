@@ -0,0 +1,314 @@
+use crate::apply;
+use crate::controller::ControllerResult;
+use crate::labels;
+use crate::types::{IngressConfig, IngressKind, OpenFGA};
+use k8s_openapi::api::networking::v1::{
+    HTTPIngressPath, HTTPIngressRuleValue, Ingress, IngressBackend, IngressRule,
+    IngressServiceBackend, IngressSpec, IngressTLS, ServiceBackendPort,
+};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use kube::api::{Api, DeleteParams, DynamicObject, Patch};
+use kube::core::{ApiResource, GroupVersionKind};
+use kube::{Client, Resource, ResourceExt};
+use tracing::{debug, info, instrument, warn};
+
+/// Path prefix the OpenFGA playground is served under.
+const PLAYGROUND_PATH: &str = "/playground";
+
+fn metadata(openfga: &OpenFGA, ns: &str, name: &str, config: &IngressConfig) -> ObjectMeta {
+    ObjectMeta {
+        name: Some(name.to_string()),
+        namespace: Some(ns.to_string()),
+        labels: Some(labels::instance_labels(openfga, name)),
+        annotations: labels::instance_annotations(openfga, config.annotations.clone()),
+        owner_references: openfga.controller_owner_ref(&()).map(|o| vec![o]),
+        ..Default::default()
+    }
+}
+
+/// Path prefixes and the Service port each routes to, most specific first.
+fn routes(openfga: &OpenFGA) -> Vec<(&'static str, i32)> {
+    let mut routes = Vec::new();
+    if openfga.spec.playground.enabled {
+        routes.push((PLAYGROUND_PATH, openfga.spec.playground.port));
+    }
+    routes.push(("/", openfga.spec.http.port));
+    routes
+}
+
+pub fn create_ingress(openfga: &OpenFGA, ns: &str, name: &str, config: &IngressConfig) -> Ingress {
+    let paths = routes(openfga)
+        .into_iter()
+        .map(|(path, port)| HTTPIngressPath {
+            path: Some(path.to_string()),
+            path_type: "Prefix".to_string(),
+            backend: IngressBackend {
+                service: Some(IngressServiceBackend {
+                    name: name.to_string(),
+                    port: Some(ServiceBackendPort {
+                        number: Some(port),
+                        ..Default::default()
+                    }),
+                }),
+                ..Default::default()
+            },
+        })
+        .collect();
+
+    Ingress {
+        metadata: metadata(openfga, ns, name, config),
+        spec: Some(IngressSpec {
+            ingress_class_name: config.class_name.clone(),
+            rules: Some(vec![IngressRule {
+                host: Some(config.host.clone()),
+                http: Some(HTTPIngressRuleValue { paths }),
+            }]),
+            tls: config.tls_secret_name.as_ref().map(|secret| {
+                vec![IngressTLS {
+                    hosts: Some(vec![config.host.clone()]),
+                    secret_name: Some(secret.clone()),
+                }]
+            }),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+pub fn http_route_resource() -> ApiResource {
+    ApiResource::from_gvk_with_plural(
+        &GroupVersionKind::gvk("gateway.networking.k8s.io", "v1", "HTTPRoute"),
+        "httproutes",
+    )
+}
+
+/// Builds the HTTPRoute, or `None` when no Gateway to attach to was given.
+pub fn create_http_route(
+    openfga: &OpenFGA,
+    ns: &str,
+    name: &str,
+    config: &IngressConfig,
+) -> Option<DynamicObject> {
+    let gateway = config.gateway_ref.as_ref()?;
+
+    let mut parent_ref = serde_json::json!({ "name": gateway.name });
+    if let Some(namespace) = &gateway.namespace {
+        parent_ref["namespace"] = serde_json::json!(namespace);
+    }
+    if let Some(section) = &gateway.section_name {
+        parent_ref["sectionName"] = serde_json::json!(section);
+    }
+
+    let rules: Vec<serde_json::Value> = routes(openfga)
+        .into_iter()
+        .map(|(path, port)| {
+            serde_json::json!({
+                "matches": [{ "path": { "type": "PathPrefix", "value": path } }],
+                "backendRefs": [{ "name": name, "port": port }],
+            })
+        })
+        .collect();
+
+    let mut route = DynamicObject::new(name, &http_route_resource()).within(ns);
+    route.metadata = metadata(openfga, ns, name, config);
+    route.data = serde_json::json!({
+        "spec": {
+            "parentRefs": [parent_ref],
+            "hostnames": [config.host],
+            "rules": rules,
+        }
+    });
+    Some(route)
+}
+
+/// Applies the configured routing resource and removes the one no longer configured.
+#[instrument(skip(client, openfga), fields(namespace = %ns, name = %name))]
+pub async fn reconcile_ingress(
+    client: &Client,
+    openfga: &OpenFGA,
+    ns: &str,
+    name: &str,
+) -> ControllerResult<()> {
+    let ingresses: Api<Ingress> = Api::namespaced(client.clone(), ns);
+    let routes: Api<DynamicObject> =
+        Api::namespaced_with(client.clone(), ns, &http_route_resource());
+    let params = apply::apply_params();
+    let kind = openfga.spec.ingress.as_ref().map(|c| c.kind);
+
+    match &openfga.spec.ingress {
+        Some(config) if config.kind == IngressKind::Ingress => {
+            let ingress = create_ingress(openfga, ns, name, config);
+            ingresses
+                .patch(name, &params, &Patch::Apply(&ingress))
+                .await?;
+            info!(
+                event = "ingress_applied",
+                namespace = %ns,
+                resource_name = %name,
+                host = %config.host,
+                "Applied Ingress"
+            );
+        }
+        Some(config) => match create_http_route(openfga, ns, name, config) {
+            Some(route) => {
+                routes.patch(name, &params, &Patch::Apply(&route)).await?;
+                info!(
+                    event = "http_route_applied",
+                    namespace = %ns,
+                    resource_name = %name,
+                    host = %config.host,
+                    "Applied HTTPRoute"
+                );
+            }
+            None => {
+                warn!(
+                    event = "http_route_missing_gateway",
+                    namespace = %ns,
+                    resource_name = %name,
+                    "ingress.kind is HTTPRoute but ingress.gatewayRef is not set, skipping"
+                );
+            }
+        },
+        None => {}
+    }
+
+    if kind != Some(IngressKind::Ingress) {
+        if let Some(existing) = ingresses.get_opt(name).await? {
+            if is_owned_by(existing.owner_references(), openfga) {
+                ingresses.delete(name, &DeleteParams::default()).await?;
+                debug!(
+                    event = "ingress_deleted",
+                    namespace = %ns,
+                    resource_name = %name,
+                    "Deleted Ingress that is no longer configured"
+                );
+            }
+        }
+    }
+    if kind != Some(IngressKind::HttpRoute) {
+        // get_opt also yields None when the Gateway API CRDs are not installed
+        if let Ok(Some(existing)) = routes.get_opt(name).await {
+            if is_owned_by(existing.owner_references(), openfga) {
+                routes.delete(name, &DeleteParams::default()).await?;
+                debug!(
+                    event = "http_route_deleted",
+                    namespace = %ns,
+                    resource_name = %name,
+                    "Deleted HTTPRoute that is no longer configured"
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+pub(crate) fn is_owned_by(
+    owners: &[k8s_openapi::apimachinery::pkg::apis::meta::v1::OwnerReference],
+    openfga: &OpenFGA,
+) -> bool {
+    openfga
+        .uid()
+        .is_some_and(|uid| owners.iter().any(|o| o.uid == uid))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::test_openfga;
+    use crate::types::GatewayReference;
+    use std::collections::BTreeMap;
+
+    fn openfga(playground: bool) -> OpenFGA {
+        test_openfga(serde_json::json!({ "playground": { "enabled": playground } }))
+    }
+
+    fn config(kind: IngressKind) -> IngressConfig {
+        IngressConfig {
+            host: "authz.example.com".to_string(),
+            kind,
+            tls_secret_name: Some("authz-tls".to_string()),
+            class_name: Some("nginx".to_string()),
+            gateway_ref: None,
+            annotations: BTreeMap::from([(
+                "cert-manager.io/cluster-issuer".to_string(),
+                "letsencrypt".to_string(),
+            )]),
+        }
+    }
+
+    #[test]
+    fn test_create_ingress() {
+        let ingress = create_ingress(&openfga(true), "ns", "authz", &config(IngressKind::Ingress));
+        let spec = ingress.spec.unwrap();
+
+        assert_eq!(spec.ingress_class_name.as_deref(), Some("nginx"));
+        assert_eq!(
+            spec.tls.unwrap()[0].secret_name.as_deref(),
+            Some("authz-tls")
+        );
+        let rule = &spec.rules.unwrap()[0];
+        assert_eq!(rule.host.as_deref(), Some("authz.example.com"));
+        let paths = &rule.http.as_ref().unwrap().paths;
+        assert_eq!(paths[0].path.as_deref(), Some("/playground"));
+        assert_eq!(
+            paths[0]
+                .backend
+                .service
+                .as_ref()
+                .unwrap()
+                .port
+                .as_ref()
+                .unwrap()
+                .number,
+            Some(3000)
+        );
+        assert_eq!(paths[1].path.as_deref(), Some("/"));
+        assert_eq!(
+            paths[1]
+                .backend
+                .service
+                .as_ref()
+                .unwrap()
+                .port
+                .as_ref()
+                .unwrap()
+                .number,
+            Some(8080)
+        );
+        assert!(ingress
+            .metadata
+            .annotations
+            .unwrap()
+            .contains_key("cert-manager.io/cluster-issuer"));
+    }
+
+    #[test]
+    fn test_create_http_route() {
+        let mut config = config(IngressKind::HttpRoute);
+        assert!(create_http_route(&openfga(false), "ns", "authz", &config).is_none());
+
+        config.gateway_ref = Some(GatewayReference {
+            name: "public".to_string(),
+            namespace: Some("gateways".to_string()),
+            section_name: None,
+        });
+        let route = create_http_route(&openfga(false), "ns", "authz", &config).unwrap();
+
+        assert_eq!(route.types.as_ref().unwrap().kind, "HTTPRoute");
+        let spec = &route.data["spec"];
+        assert_eq!(spec["parentRefs"][0]["namespace"], "gateways");
+        assert_eq!(spec["hostnames"][0], "authz.example.com");
+        assert_eq!(spec["rules"].as_array().unwrap().len(), 1);
+        assert_eq!(spec["rules"][0]["backendRefs"][0]["port"], 8080);
+    }
+
+    #[test]
+    fn test_ingress_kind_serialization() {
+        let config: IngressConfig =
+            serde_json::from_str(r#"{"host":"a.example.com","kind":"HTTPRoute"}"#).unwrap();
+        assert_eq!(config.kind, IngressKind::HttpRoute);
+        let config: IngressConfig = serde_json::from_str(r#"{"host":"a.example.com"}"#).unwrap();
+        assert_eq!(config.kind, IngressKind::Ingress);
+    }
+}
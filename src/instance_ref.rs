@@ -0,0 +1,260 @@
+//! Resolution of `spec.instanceRef`. A reference names an instance or selects
+//! it by labels; with a selector, blue/green instances take over existing
+//! stores and models when the label moves from one to the other. A selector
+//! must match exactly one instance, so both colours carrying the label at once
+//! is reported rather than guessed at.
+//!
+//! A reference into another namespace only resolves while an
+//! OpenFGAReferenceGrant in that namespace allows it, so application teams
+//! cannot attach stores to a platform instance the platform team did not offer.
+
+use crate::types::{InstanceReference, OpenFGA, OpenFGAReferenceGrant};
+use kube::api::{Api, ListParams};
+use kube::{Client, ResourceExt};
+
+/// The reference as shown in logs and conditions.
+pub fn describe(reference: &InstanceReference) -> String {
+    let target = if reference.selector.is_empty() {
+        format!("'{}'", reference.name)
+    } else {
+        format!("selector '{}'", selector_string(reference))
+    };
+    match &reference.namespace {
+        Some(ns) => format!("{} in namespace '{}'", target, ns),
+        None => target,
+    }
+}
+
+/// Namespace of the instance a resource in `from_ns` references.
+pub fn instance_namespace(reference: &InstanceReference, from_ns: &str) -> String {
+    reference
+        .namespace
+        .clone()
+        .unwrap_or_else(|| from_ns.to_string())
+}
+
+/// Whether one of `grants` lets a `kind` in `from_ns` reference `instance`.
+/// Grants are read from the instance's namespace.
+pub fn granted(
+    grants: &[OpenFGAReferenceGrant],
+    kind: &str,
+    from_ns: &str,
+    instance: &str,
+) -> bool {
+    grants.iter().any(|grant| {
+        grant
+            .spec
+            .from
+            .iter()
+            .any(|from| from.kind == kind && from.namespace == from_ns)
+            && (grant.spec.to.is_empty() || grant.spec.to.iter().any(|to| to.name == instance))
+    })
+}
+
+fn selector_string(reference: &InstanceReference) -> String {
+    reference
+        .selector
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Whether `openfga` is the instance a resource in `from_ns` points at with
+/// `reference`. Grants are not considered.
+pub fn matches(reference: &InstanceReference, from_ns: &str, openfga: &OpenFGA) -> bool {
+    if openfga.namespace().unwrap_or_default() != instance_namespace(reference, from_ns) {
+        return false;
+    }
+    if reference.selector.is_empty() {
+        return openfga.name_any() == reference.name;
+    }
+    let labels = openfga.labels();
+    reference
+        .selector
+        .iter()
+        .all(|(key, value)| labels.get(key) == Some(value))
+}
+
+/// The one instance among `candidates` the reference points at.
+pub fn select(
+    reference: &InstanceReference,
+    from_ns: &str,
+    candidates: Vec<OpenFGA>,
+) -> Result<OpenFGA, String> {
+    let mut matching: Vec<OpenFGA> = candidates
+        .into_iter()
+        .filter(|openfga| openfga.metadata.deletion_timestamp.is_none())
+        .filter(|openfga| matches(reference, from_ns, openfga))
+        .collect();
+    match matching.len() {
+        0 => Err(format!(
+            "no OpenFGA instance matches {}",
+            describe(reference)
+        )),
+        1 => Ok(matching.remove(0)),
+        _ => Err(format!(
+            "{} is ambiguous, it matches instances {}",
+            describe(reference),
+            matching
+                .iter()
+                .map(|openfga| openfga.name_any())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+/// Looks up the instance a `kind` in `ns` points at with `reference`. The outer
+/// error is a failed API call, the inner one a reference that resolves to no
+/// single instance or is not granted.
+pub async fn resolve(
+    client: &Client,
+    ns: &str,
+    kind: &str,
+    reference: &InstanceReference,
+) -> kube::Result<Result<OpenFGA, String>> {
+    let target_ns = instance_namespace(reference, ns);
+    let instances: Api<OpenFGA> = Api::namespaced(client.clone(), &target_ns);
+    let resolved = if reference.selector.is_empty() {
+        instances
+            .get_opt(&reference.name)
+            .await?
+            .ok_or_else(|| format!("OpenFGA instance {} not found", describe(reference)))
+    } else {
+        let candidates = instances
+            .list(&ListParams::default().labels(&selector_string(reference)))
+            .await?;
+        select(reference, ns, candidates.items)
+    };
+    let instance = match resolved {
+        Ok(instance) if target_ns != ns => instance,
+        other => return Ok(other),
+    };
+
+    let grants = Api::<OpenFGAReferenceGrant>::namespaced(client.clone(), &target_ns)
+        .list(&ListParams::default())
+        .await?;
+    if granted(&grants.items, kind, ns, &instance.name_any()) {
+        Ok(Ok(instance))
+    } else {
+        Ok(Err(format!(
+            "no OpenFGAReferenceGrant in namespace '{}' lets {}s from '{}' reference instance '{}'",
+            target_ns,
+            kind,
+            ns,
+            instance.name_any()
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::create_test_openfga;
+    use std::collections::BTreeMap;
+
+    fn instance(name: &str, colour: &str) -> OpenFGA {
+        let mut openfga = create_test_openfga();
+        openfga.metadata.name = Some(name.to_string());
+        openfga.metadata.namespace = Some("apps".to_string());
+        openfga.metadata.labels = Some(BTreeMap::from([
+            ("app.kubernetes.io/part-of".to_string(), "authz".to_string()),
+            ("colour".to_string(), colour.to_string()),
+        ]));
+        openfga
+    }
+
+    fn selector(labels: &[(&str, &str)]) -> InstanceReference {
+        InstanceReference {
+            name: String::new(),
+            selector: labels
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            namespace: None,
+        }
+    }
+
+    #[test]
+    fn test_matches() {
+        let blue = instance("authz-blue", "blue");
+        let mut by_name = InstanceReference {
+            name: "authz-blue".to_string(),
+            selector: BTreeMap::new(),
+            namespace: None,
+        };
+        assert!(matches(&by_name, "apps", &blue));
+        assert!(!matches(&by_name, "apps", &instance("authz-green", "blue")));
+        assert!(!matches(&by_name, "tenant-a", &blue));
+        assert_eq!(describe(&by_name), "'authz-blue'");
+        by_name.namespace = Some("apps".to_string());
+        assert!(matches(&by_name, "tenant-a", &blue));
+        assert_eq!(describe(&by_name), "'authz-blue' in namespace 'apps'");
+
+        let active = selector(&[("app.kubernetes.io/part-of", "authz"), ("colour", "blue")]);
+        assert!(matches(&active, "apps", &blue));
+        assert!(!matches(&active, "apps", &instance("authz-green", "green")));
+        assert_eq!(
+            describe(&active),
+            "selector 'app.kubernetes.io/part-of=authz,colour=blue'"
+        );
+    }
+
+    #[test]
+    fn test_select() {
+        let active = selector(&[("colour", "green")]);
+        let selected = select(
+            &active,
+            "apps",
+            vec![
+                instance("authz-blue", "blue"),
+                instance("authz-green", "green"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(selected.name_any(), "authz-green");
+
+        assert_eq!(
+            select(&active, "apps", vec![instance("authz-blue", "blue")]).unwrap_err(),
+            "no OpenFGA instance matches selector 'colour=green'"
+        );
+        assert_eq!(
+            select(
+                &selector(&[("app.kubernetes.io/part-of", "authz")]),
+                "apps",
+                vec![instance("authz-blue", "blue"), instance("authz-green", "green")],
+            )
+            .unwrap_err(),
+            "selector 'app.kubernetes.io/part-of=authz' is ambiguous, it matches instances authz-blue, authz-green"
+        );
+    }
+
+    #[test]
+    fn test_granted() {
+        let grant = |to: &[&str]| {
+            OpenFGAReferenceGrant::new(
+                "tenants",
+                serde_json::from_value(serde_json::json!({
+                    "from": [
+                        { "kind": "OpenFGAStore", "namespace": "tenant-a" },
+                        { "kind": "AuthorizationModel", "namespace": "tenant-b" }
+                    ],
+                    "to": to.iter().map(|name| serde_json::json!({ "name": name })).collect::<Vec<_>>(),
+                }))
+                .unwrap(),
+            )
+        };
+
+        let all = [grant(&[])];
+        assert!(granted(&all, "OpenFGAStore", "tenant-a", "shared"));
+        assert!(granted(&all, "AuthorizationModel", "tenant-b", "shared"));
+        assert!(!granted(&all, "AuthorizationModel", "tenant-a", "shared"));
+        assert!(!granted(&all, "OpenFGAStore", "tenant-c", "shared"));
+        assert!(!granted(&[], "OpenFGAStore", "tenant-a", "shared"));
+
+        let one = [grant(&["shared"])];
+        assert!(granted(&one, "OpenFGAStore", "tenant-a", "shared"));
+        assert!(!granted(&one, "OpenFGAStore", "tenant-a", "internal"));
+    }
+}
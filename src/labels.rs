@@ -0,0 +1,146 @@
+//! Labels and annotations shared by every resource the operator creates for an
+//! instance: the user's `spec.labels`/`spec.annotations`, the standard
+//! `app.kubernetes.io/*` labels and the selector labels pods are matched by.
+
+use crate::advisory::image_tag;
+use crate::types::OpenFGA;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use kube::runtime::reflector::ObjectRef;
+use std::collections::BTreeMap;
+
+pub const MANAGED_BY: &str = "openfga-operator";
+
+/// Labels Deployments and Services select pods by. Selectors are immutable on a
+/// Deployment, so this set must not change.
+pub fn selector_labels(name: &str) -> BTreeMap<String, String> {
+    BTreeMap::from([
+        ("app".to_string(), "openfga".to_string()),
+        ("app.kubernetes.io/name".to_string(), "openfga".to_string()),
+        ("app.kubernetes.io/instance".to_string(), name.to_string()),
+        ("instance".to_string(), name.to_string()),
+    ])
+}
+
+/// [`selector_labels`] as a label selector string, published in `status.selector`
+/// for the scale subresource so HPAs can find the instance's pods.
+pub fn selector_string(name: &str) -> String {
+    selector_labels(name)
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// The instance a child object was created for, read from its selector labels.
+/// Children carry no owner reference (the `Retain` deletion policy must outlive
+/// the instance), so watches map them back this way.
+pub fn instance_ref(metadata: &ObjectMeta) -> Option<ObjectRef<OpenFGA>> {
+    let labels = metadata.labels.as_ref()?;
+    if labels.get("app").map(String::as_str) != Some("openfga") {
+        return None;
+    }
+    let instance = labels.get("app.kubernetes.io/instance")?;
+    let reference = ObjectRef::new(instance);
+    Some(match &metadata.namespace {
+        Some(ns) => reference.within(ns),
+        None => reference,
+    })
+}
+
+/// The user's labels overlaid with the standard and selector labels.
+pub fn instance_labels(openfga: &OpenFGA, name: &str) -> BTreeMap<String, String> {
+    let mut labels = openfga.spec.labels.clone();
+    labels.insert(
+        "app.kubernetes.io/managed-by".to_string(),
+        MANAGED_BY.to_string(),
+    );
+    labels.insert(
+        "app.kubernetes.io/component".to_string(),
+        "authorization-server".to_string(),
+    );
+    // Label values are at most 63 characters; longer tags are left out
+    if let Some(tag) = image_tag(&openfga.spec.image).filter(|t| t.len() <= 63) {
+        labels.insert("app.kubernetes.io/version".to_string(), tag.to_string());
+    }
+    labels.extend(selector_labels(name));
+    labels
+}
+
+/// The user's annotations overlaid with the resource's own, or None when empty.
+pub fn instance_annotations(
+    openfga: &OpenFGA,
+    own: impl IntoIterator<Item = (String, String)>,
+) -> Option<BTreeMap<String, String>> {
+    let mut annotations = openfga.spec.annotations.clone();
+    annotations.extend(own);
+    (!annotations.is_empty()).then_some(annotations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::{create_test_openfga, test_openfga};
+
+    #[test]
+    fn test_instance_labels_keep_selector() {
+        let openfga = test_openfga(serde_json::json!({
+            "image": "openfga/openfga:v1.5.3",
+            "labels": { "cost-center": "platform", "app.kubernetes.io/instance": "other" },
+        }));
+
+        let labels = instance_labels(&openfga, "authz");
+        assert_eq!(labels["cost-center"], "platform");
+        assert_eq!(labels["app.kubernetes.io/instance"], "authz");
+        assert_eq!(labels["app.kubernetes.io/managed-by"], MANAGED_BY);
+        assert_eq!(labels["app.kubernetes.io/version"], "v1.5.3");
+        for (key, value) in selector_labels("authz") {
+            assert_eq!(labels[&key], value);
+        }
+    }
+
+    #[test]
+    fn test_selector_string() {
+        assert_eq!(
+            selector_string("authz"),
+            "app=openfga,app.kubernetes.io/instance=authz,app.kubernetes.io/name=openfga,instance=authz"
+        );
+    }
+
+    #[test]
+    fn test_instance_ref_from_selector_labels() {
+        let metadata = ObjectMeta {
+            namespace: Some("auth".to_string()),
+            labels: Some(selector_labels("authz")),
+            ..Default::default()
+        };
+        assert_eq!(
+            instance_ref(&metadata),
+            Some(ObjectRef::new("authz").within("auth"))
+        );
+
+        let foreign = ObjectMeta {
+            labels: Some(BTreeMap::from([(
+                "app.kubernetes.io/instance".to_string(),
+                "authz".to_string(),
+            )])),
+            ..Default::default()
+        };
+        assert_eq!(instance_ref(&foreign), None);
+        assert_eq!(instance_ref(&ObjectMeta::default()), None);
+    }
+
+    #[test]
+    fn test_instance_annotations_prefer_own() {
+        let openfga = test_openfga(serde_json::json!({
+            "annotations": { "sidecar.istio.io/inject": "true", "team": "auth" },
+        }));
+
+        let annotations =
+            instance_annotations(&openfga, [("team".to_string(), "identity".to_string())]).unwrap();
+        assert_eq!(annotations["sidecar.istio.io/inject"], "true");
+        assert_eq!(annotations["team"], "identity");
+
+        let bare = create_test_openfga();
+        assert_eq!(instance_annotations(&bare, []), None);
+    }
+}
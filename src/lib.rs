@@ -0,0 +1,67 @@
+pub mod access_request;
+pub mod access_review;
+pub mod access_tokens;
+pub mod admin_api;
+pub mod advisory;
+pub mod api_logging;
+pub mod apply;
+pub mod backoff;
+pub mod backup;
+pub mod backup_controller;
+pub mod bootstrap;
+pub mod bulk_writer;
+pub mod cli;
+pub mod client_pool;
+pub mod controller;
+pub mod conversion;
+pub mod crds;
+pub mod debug_state;
+pub mod deletion;
+pub mod dependencies;
+pub mod drift;
+pub mod dry_run;
+pub mod fixtures;
+pub mod fleet;
+pub mod history;
+pub mod image_digest;
+pub mod image_verification;
+pub mod ingress;
+pub mod instance_ref;
+pub mod labels;
+pub mod load_shedding;
+pub mod metrics;
+pub mod model;
+pub mod model_controller;
+pub mod monitoring;
+pub mod notifications;
+pub mod openfga_client;
+pub mod openfga_grpc;
+pub mod operator_config;
+pub mod overrides;
+pub mod panic_isolation;
+pub mod playground;
+pub mod pool_controller;
+pub mod pruning;
+pub mod query_cache;
+pub mod rate_limit;
+pub mod reconcile_queue;
+pub mod resource_recommendations;
+pub mod responses;
+pub mod restore_controller;
+pub mod retention;
+pub mod rollback;
+pub mod runtimes;
+pub mod server_config;
+pub mod service_account;
+pub mod store_controller;
+pub mod target_cluster;
+pub mod telemetry;
+pub mod template_hash;
+pub mod topology;
+pub mod tuple_scan;
+pub mod tuples;
+pub mod types;
+pub mod upgrade;
+pub mod watchdog;
+pub mod webhook;
+pub mod workload;
@@ -0,0 +1,281 @@
+//! Admission limits for the operator's HTTP server. The health, metrics and any
+//! future endpoints share the tokio runtime with the reconcile loop, so a
+//! misconfigured scraper or abusive client is shed with a cheap 413/503 instead
+//! of queueing work on that runtime.
+
+use crate::metrics;
+use crate::responses::{self, HttpResult};
+use hyper::body::{Bytes, HttpBody};
+use hyper::header::{CONTENT_LENGTH, RETRY_AFTER};
+use hyper::{Body, Request, Response, StatusCode};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tracing::warn;
+
+const MAX_CONCURRENCY_ENV: &str = "OPENFGA_OPERATOR_HTTP_MAX_CONCURRENCY";
+const REQUEST_TIMEOUT_ENV: &str = "OPENFGA_OPERATOR_HTTP_REQUEST_TIMEOUT_SECONDS";
+const MAX_BODY_BYTES_ENV: &str = "OPENFGA_OPERATOR_HTTP_MAX_BODY_BYTES";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ServerLimits {
+    /// Requests handled at once; further requests get 503 immediately.
+    pub max_concurrency: usize,
+    /// Upper bound on handling one request, including reading its headers.
+    pub request_timeout: Duration,
+    pub max_body_bytes: u64,
+}
+
+impl Default for ServerLimits {
+    fn default() -> Self {
+        Self {
+            max_concurrency: 32,
+            request_timeout: Duration::from_secs(5),
+            max_body_bytes: 64 * 1024,
+        }
+    }
+}
+
+impl ServerLimits {
+    /// Defaults, overridden by any valid `OPENFGA_OPERATOR_HTTP_*` variables.
+    pub fn from_env() -> Self {
+        Self::from_lookup(|name| std::env::var(name).ok())
+    }
+
+    fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let defaults = Self::default();
+        let parse = |name: &str| lookup(name).and_then(|v| v.trim().parse::<u64>().ok());
+        Self {
+            max_concurrency: parse(MAX_CONCURRENCY_ENV)
+                .filter(|&n| n > 0)
+                .map_or(defaults.max_concurrency, |n| n as usize),
+            request_timeout: parse(REQUEST_TIMEOUT_ENV)
+                .filter(|&n| n > 0)
+                .map_or(defaults.request_timeout, Duration::from_secs),
+            max_body_bytes: parse(MAX_BODY_BYTES_ENV).unwrap_or(defaults.max_body_bytes),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShedReason {
+    BodyTooLarge,
+    Overloaded,
+    Timeout,
+}
+
+impl ShedReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ShedReason::BodyTooLarge => "body_too_large",
+            ShedReason::Overloaded => "overloaded",
+            ShedReason::Timeout => "timeout",
+        }
+    }
+
+    fn response(&self) -> HttpResult<Response<Body>> {
+        let (status, message) = match self {
+            ShedReason::BodyTooLarge => (StatusCode::PAYLOAD_TOO_LARGE, "request body too large"),
+            ShedReason::Overloaded => (StatusCode::SERVICE_UNAVAILABLE, "server overloaded"),
+            ShedReason::Timeout => (StatusCode::SERVICE_UNAVAILABLE, "request timed out"),
+        };
+        let mut response = responses::json(
+            status,
+            &serde_json::json!({ "error": self.as_str(), "message": message }),
+        )?;
+        if status == StatusCode::SERVICE_UNAVAILABLE {
+            response
+                .headers_mut()
+                .insert(RETRY_AFTER, hyper::header::HeaderValue::from_static("1"));
+        }
+        Ok(response)
+    }
+}
+
+/// Shared by every connection of one server.
+#[derive(Debug, Clone)]
+pub struct LoadShedder {
+    limits: ServerLimits,
+    permits: Arc<Semaphore>,
+}
+
+impl LoadShedder {
+    pub fn new(limits: ServerLimits) -> Self {
+        Self {
+            limits,
+            permits: Arc::new(Semaphore::new(limits.max_concurrency)),
+        }
+    }
+
+    pub fn limits(&self) -> ServerLimits {
+        self.limits
+    }
+
+    /// Runs `handler` if the request fits the limits, otherwise answers with the shed response.
+    pub async fn handle<F, Fut>(&self, req: Request<Body>, handler: F) -> Response<Body>
+    where
+        F: FnOnce(Request<Body>) -> Fut,
+        Fut: Future<Output = Response<Body>>,
+    {
+        let path = req.uri().path().to_string();
+        let declared_length = req
+            .headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<u64>().ok());
+        if declared_length.is_some_and(|len| len > self.limits.max_body_bytes) {
+            return shed(&path, ShedReason::BodyTooLarge);
+        }
+
+        let Ok(_permit) = self.permits.clone().try_acquire_owned() else {
+            return shed(&path, ShedReason::Overloaded);
+        };
+
+        match tokio::time::timeout(self.limits.request_timeout, handler(req)).await {
+            Ok(response) => response,
+            Err(_) => shed(&path, ShedReason::Timeout),
+        }
+    }
+}
+
+fn shed(path: &str, reason: ShedReason) -> Response<Body> {
+    metrics::metrics()
+        .http_requests_shed_total
+        .with_label_values(&[reason.as_str()])
+        .inc();
+    warn!(
+        event = "http_request_shed",
+        path = %path,
+        reason = reason.as_str(),
+        "Rejected HTTP request"
+    );
+    responses::or_error(path, reason.response())
+}
+
+/// Reads a request body, returning `None` once it exceeds `limit` bytes. Handlers
+/// that consume bodies use this, since chunked requests carry no `content-length`.
+pub async fn read_body(mut body: Body, limit: u64) -> Result<Option<Bytes>, hyper::Error> {
+    let mut buffer = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk?;
+        if (buffer.len() + chunk.len()) as u64 > limit {
+            return Ok(None);
+        }
+        buffer.extend_from_slice(&chunk);
+    }
+    Ok(Some(Bytes::from(buffer)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use tokio::sync::oneshot;
+
+    fn limits(max_concurrency: usize, timeout_ms: u64) -> ServerLimits {
+        ServerLimits {
+            max_concurrency,
+            request_timeout: Duration::from_millis(timeout_ms),
+            max_body_bytes: 16,
+        }
+    }
+
+    fn ok() -> Response<Body> {
+        Response::new(Body::from("ok"))
+    }
+
+    #[test]
+    fn test_limits_from_env() {
+        let env = HashMap::from([
+            (MAX_CONCURRENCY_ENV, "4"),
+            (REQUEST_TIMEOUT_ENV, "0"),
+            (MAX_BODY_BYTES_ENV, "not-a-number"),
+        ]);
+        let limits = ServerLimits::from_lookup(|name| env.get(name).map(|v| v.to_string()));
+
+        assert_eq!(limits.max_concurrency, 4);
+        assert_eq!(
+            limits.request_timeout,
+            ServerLimits::default().request_timeout
+        );
+        assert_eq!(
+            limits.max_body_bytes,
+            ServerLimits::default().max_body_bytes
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rejects_large_bodies() {
+        let shedder = LoadShedder::new(limits(1, 1000));
+        let req = Request::builder()
+            .header(CONTENT_LENGTH, "17")
+            .body(Body::from(vec![0; 17]))
+            .unwrap();
+
+        let response = shedder.handle(req, |_| async { ok() }).await;
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let chunked = Body::wrap_stream(futures::stream::iter(vec![
+            Ok::<_, std::io::Error>(vec![0; 10]),
+            Ok(vec![0; 10]),
+        ]));
+        assert!(read_body(chunked, 16).await.unwrap().is_none());
+        assert_eq!(
+            read_body(Body::from("small"), 16).await.unwrap().unwrap(),
+            "small"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sheds_when_saturated() {
+        let shedder = LoadShedder::new(limits(1, 1000));
+        let (release, wait) = oneshot::channel::<()>();
+        let (started, running) = oneshot::channel::<()>();
+
+        let busy = {
+            let shedder = shedder.clone();
+            tokio::spawn(async move {
+                shedder
+                    .handle(Request::new(Body::empty()), |_| async move {
+                        let _ = started.send(());
+                        let _ = wait.await;
+                        ok()
+                    })
+                    .await
+            })
+        };
+        running.await.unwrap();
+
+        let response = shedder
+            .handle(Request::new(Body::empty()), |_| async { ok() })
+            .await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()[RETRY_AFTER], "1");
+
+        release.send(()).unwrap();
+        assert_eq!(busy.await.unwrap().status(), StatusCode::OK);
+        let response = shedder
+            .handle(Request::new(Body::empty()), |_| async { ok() })
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_times_out_slow_handlers() {
+        let shedder = LoadShedder::new(limits(1, 10));
+        let response = shedder
+            .handle(Request::new(Body::empty()), |_| async {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                ok()
+            })
+            .await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let shed = metrics::metrics()
+            .http_requests_shed_total
+            .with_label_values(&["timeout"])
+            .get();
+        assert!(shed >= 1);
+    }
+}
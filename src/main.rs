@@ -1,16 +1,24 @@
-mod controller;
-mod types;
-
 use anyhow::Result;
-use controller::OpenFGAController;
 use hyper::service::{make_service_fn, service_fn};
 use hyper::{Body, Request, Response, Server, StatusCode};
+use kube::client::ClientBuilder;
 use kube::Client;
+use openfga_operator::controller::{shutdown_requested, OpenFGAController, Shutdown};
+use openfga_operator::load_shedding::{LoadShedder, ServerLimits};
+use openfga_operator::operator_config::OperatorConfig;
+use openfga_operator::rate_limit::RateLimitLayer;
+use openfga_operator::responses::{self, HttpResult};
+use openfga_operator::runtimes::RuntimeConfig;
+use openfga_operator::telemetry::{self, TelemetryConfig};
+use openfga_operator::watchdog::{self, WatchdogConfig};
+use openfga_operator::webhook;
+use openfga_operator::{admin_api, backup, cli, crds, debug_state, fixtures, fleet, metrics};
 use std::convert::Infallible;
 use std::env;
 use std::net::SocketAddr;
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 use std::time::Duration;
+use tokio::runtime::Handle;
 use tokio::signal;
 use tokio::sync::RwLock;
 use tokio::time::{interval, sleep};
@@ -24,6 +32,8 @@ struct HealthStatus {
     kubernetes_connected: bool,
     controller_running: bool,
     uptime_seconds: u64,
+    /// CRDs the controllers are waiting for while the status is `crd_missing`.
+    missing_crds: Vec<String>,
 }
 
 impl Default for HealthStatus {
@@ -33,31 +43,74 @@ impl Default for HealthStatus {
             kubernetes_connected: false,
             controller_running: false,
             uptime_seconds: 0,
+            missing_crds: vec![],
         }
     }
 }
 
 type SharedHealthStatus = Arc<RwLock<HealthStatus>>;
 
-#[tokio::main]
-async fn main() -> Result<()> {
+/// The Kubernetes client, set once the operator has connected.
+type SharedClient = Arc<OnceLock<Client>>;
+
+/// How long in-flight reconciles may run after a shutdown signal, within the
+/// pod's default 30s termination grace period.
+const SHUTDOWN_GRACE_PERIOD: Duration = Duration::from_secs(25);
+
+fn main() -> Result<()> {
+    let runtimes = RuntimeConfig::from_env();
+    let controller_runtime = runtimes.controller_runtime()?;
+    let http_runtime = runtimes.http_runtime()?;
+
+    let result = controller_runtime.block_on(run(runtimes, http_runtime.handle().clone()));
+
+    http_runtime.shutdown_timeout(Duration::from_secs(1));
+    result
+}
+
+async fn run(runtimes: RuntimeConfig, http: Handle) -> Result<()> {
+    // Offline model tooling runs without logging setup or cluster access
+    let args: Vec<String> = env::args().collect();
+    if cli::is_cli_invocation(&args) {
+        std::process::exit(cli::run(&args));
+    }
+    if backup::is_backup_invocation(&args) {
+        std::process::exit(backup::run(&args).await);
+    }
+    if fleet::is_fleet_invocation(&args) {
+        std::process::exit(fleet::run(&args).await);
+    }
+    if fixtures::is_capture_invocation(&args) {
+        std::process::exit(fixtures::run(&args).await);
+    }
+
     // Initialize structured logging based on environment
     let json_logging = env::var("OPENFGA_LOG_FORMAT").unwrap_or_default() == "json";
 
-    let env_filter = EnvFilter::from_default_env()
-        .add_directive(Level::INFO.into())
-        .add_directive("openfga_operator=debug".parse().unwrap());
+    let mut env_filter = EnvFilter::from_default_env().add_directive(Level::INFO.into());
+    if let Ok(directive) = "openfga_operator=debug".parse() {
+        env_filter = env_filter.add_directive(directive);
+    }
+
+    // Spans are exported over OTLP when a collector endpoint is configured
+    let telemetry_config = TelemetryConfig::from_env();
+    let (otel_layer, telemetry_error) = match telemetry::init_telemetry(&telemetry_config) {
+        Ok(layer) => (layer, None),
+        Err(e) => (None, Some(e)),
+    };
 
     if json_logging {
         // Use JSON structured logging
         tracing_subscriber::registry()
             .with(env_filter)
+            .with(otel_layer)
             .with(fmt::layer().json())
             .init();
     } else {
         // Use standard human-readable logging
         tracing_subscriber::registry()
             .with(env_filter)
+            .with(otel_layer)
             .with(fmt::layer().pretty())
             .init();
     }
@@ -66,23 +119,94 @@ async fn main() -> Result<()> {
         operator = "openfga-operator",
         version = env!("CARGO_PKG_VERSION"),
         log_format = if json_logging { "json" } else { "pretty" },
+        controller_threads = runtimes.controller_threads,
+        http_threads = runtimes.http_threads,
         "Starting OpenFGA Operator"
     );
+    match (&telemetry_error, &telemetry_config.endpoint) {
+        (Some(e), _) => warn!(
+            event = "telemetry_init_failed",
+            error = %e,
+            "Failed to initialize OpenTelemetry, spans will not be exported"
+        ),
+        (None, Some(endpoint)) => info!(
+            event = "telemetry_initialized",
+            endpoint = %endpoint,
+            sample_ratio = telemetry_config.sample_ratio,
+            "Exporting traces over OTLP"
+        ),
+        (None, None) => {}
+    }
+
+    let config = OperatorConfig::from_env(&args).map_err(anyhow::Error::msg)?;
+    info!(
+        reconcile_interval_seconds = config.reconcile_interval.as_secs(),
+        watch_namespaces = ?config.watch_namespaces,
+        max_concurrent_reconciles = config.max_concurrent_reconciles,
+        api_qps = ?config.api_qps,
+        api_burst = config.api_burst,
+        mode = ?config.mode,
+        install_crds = config.install_crds,
+        "Loaded operator configuration"
+    );
 
     // Initialize shared health status
     let health_status = Arc::new(RwLock::new(HealthStatus::default()));
 
     // Start health endpoint
-    let health_task = start_health_endpoint(health_status.clone());
+    let kube_client = SharedClient::default();
+    let health_task = start_health_endpoint(
+        &http,
+        health_status.clone(),
+        kube_client.clone(),
+        Handle::current(),
+    );
 
-    // Set up graceful shutdown signal handling
-    let _shutdown_signal = setup_signal_handler();
+    // The watchdog samples the controller runtime from the HTTP runtime, so it
+    // keeps running while the controllers are saturated
+    let watchdog_task = http.spawn(watchdog::run(WatchdogConfig::from_env(), Handle::current()));
+    let webhook_task = http.spawn(webhook::serve());
 
-    // Initialize operator with retry logic
-    let operator_result = initialize_operator_with_retry(health_status.clone()).await;
+    // Set up graceful shutdown signal handling
+    let shutdown = setup_signal_handler().await;
+
+    // Initialize operator with retry logic. On a shutdown signal the controllers
+    // stop taking new work and the in-flight reconciles get a grace period
+    let operator = initialize_operator_with_retry(
+        config,
+        health_status.clone(),
+        kube_client,
+        shutdown.clone(),
+    );
+    tokio::pin!(operator);
+    let operator_result = tokio::select! {
+        result = &mut operator => result,
+        _ = shutdown_requested(shutdown) => {
+            health_status.write().await.status = "shutting_down".to_string();
+            info!(
+                event = "shutdown_draining",
+                grace_period_seconds = SHUTDOWN_GRACE_PERIOD.as_secs(),
+                "Waiting for in-flight reconciles to finish"
+            );
+            match tokio::time::timeout(SHUTDOWN_GRACE_PERIOD, operator).await {
+                Ok(result) => result,
+                Err(_) => {
+                    warn!(
+                        event = "shutdown_drain_timeout",
+                        grace_period_seconds = SHUTDOWN_GRACE_PERIOD.as_secs(),
+                        "In-flight reconciles did not finish within the grace period"
+                    );
+                    Ok(())
+                }
+            }
+        }
+    };
 
     // Clean shutdown
     health_task.abort();
+    watchdog_task.abort();
+    webhook_task.abort();
+    telemetry::shutdown().await;
 
     match operator_result {
         Ok(()) => {
@@ -100,24 +224,58 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
-fn start_health_endpoint(health_status: SharedHealthStatus) -> tokio::task::JoinHandle<()> {
-    tokio::spawn(async move {
+/// Serves the health endpoint on the HTTP runtime, away from the controllers.
+fn start_health_endpoint(
+    http: &Handle,
+    health_status: SharedHealthStatus,
+    kube_client: SharedClient,
+    controller_runtime: Handle,
+) -> tokio::task::JoinHandle<()> {
+    http.spawn(async move {
         let addr = SocketAddr::from(([0, 0, 0, 0], 8080));
+        let limits = ServerLimits::from_env();
+        let shedder = LoadShedder::new(limits);
 
         let make_svc = make_service_fn(move |_conn| {
             let health_status = health_status.clone();
+            let kube_client = kube_client.clone();
+            let shedder = shedder.clone();
+            let controller_runtime = controller_runtime.clone();
             async move {
                 Ok::<_, Infallible>(service_fn(move |req| {
-                    handle_health_request(req, health_status.clone())
+                    let health_status = health_status.clone();
+                    let kube_client = kube_client.clone();
+                    let shedder = shedder.clone();
+                    let controller_runtime = controller_runtime.clone();
+                    async move {
+                        Ok::<_, Infallible>(
+                            shedder
+                                .handle(req, |req| {
+                                    handle_health_request(
+                                        req,
+                                        health_status,
+                                        kube_client,
+                                        controller_runtime,
+                                    )
+                                })
+                                .await,
+                        )
+                    }
                 }))
             }
         });
 
-        let server = Server::bind(&addr).serve(make_svc);
+        // Slow clients are cut off while sending headers, before a permit is taken
+        let server = Server::bind(&addr)
+            .http1_header_read_timeout(limits.request_timeout)
+            .serve(make_svc);
 
         info!(
             endpoint = "health",
             address = %addr,
+            max_concurrency = limits.max_concurrency,
+            request_timeout_seconds = limits.request_timeout.as_secs(),
+            max_body_bytes = limits.max_body_bytes,
             "Health check endpoint started"
         );
 
@@ -133,8 +291,39 @@ fn start_health_endpoint(health_status: SharedHealthStatus) -> tokio::task::Join
 async fn handle_health_request(
     req: Request<Body>,
     health_status: SharedHealthStatus,
-) -> Result<Response<Body>, Infallible> {
-    match req.uri().path() {
+    kube_client: SharedClient,
+    controller_runtime: Handle,
+) -> Response<Body> {
+    let path = req.uri().path().to_string();
+    let result = if path.starts_with(admin_api::PATH_PREFIX) {
+        admin_api::handle(req, kube_client.get().cloned()).await
+    } else {
+        route_health_request(&path, health_status, &controller_runtime).await
+    };
+    responses::or_error(&path, result)
+}
+
+/// Build metadata embedded by `build.rs`.
+fn version_info() -> serde_json::Value {
+    let build_time = env!("OPENFGA_OPERATOR_BUILD_EPOCH")
+        .parse::<i64>()
+        .ok()
+        .and_then(|epoch| chrono::DateTime::from_timestamp(epoch, 0))
+        .map(|time| time.to_rfc3339());
+    serde_json::json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "git_sha": env!("OPENFGA_OPERATOR_GIT_SHA"),
+        "build_time": build_time,
+        "rustc": env!("OPENFGA_OPERATOR_RUSTC_VERSION"),
+    })
+}
+
+async fn route_health_request(
+    path: &str,
+    health_status: SharedHealthStatus,
+    controller_runtime: &Handle,
+) -> HttpResult<Response<Body>> {
+    match path {
         "/health" | "/healthz" => {
             let status = health_status.read().await;
             let health_response = serde_json::json!({
@@ -142,52 +331,54 @@ async fn handle_health_request(
                 "kubernetes_connected": status.kubernetes_connected,
                 "controller_running": status.controller_running,
                 "uptime_seconds": status.uptime_seconds,
+                "missing_crds": status.missing_crds,
                 "version": env!("CARGO_PKG_VERSION"),
                 "timestamp": chrono::Utc::now().to_rfc3339()
             });
 
-            let is_healthy = status.kubernetes_connected && status.controller_running;
+            // Waiting for CRDs is healthy, a restart would not install them
+            let is_healthy = status.kubernetes_connected
+                && (status.controller_running || !status.missing_crds.is_empty());
             let status_code = if is_healthy {
                 StatusCode::OK
             } else {
                 StatusCode::SERVICE_UNAVAILABLE
             };
 
-            Ok(Response::builder()
-                .status(status_code)
-                .header("content-type", "application/json")
-                .body(Body::from(health_response.to_string()))
-                .unwrap())
+            responses::json(status_code, &health_response)
         }
         "/ready" | "/readiness" => {
             let status = health_status.read().await;
-            let is_ready = status.kubernetes_connected;
+            let is_ready = status.kubernetes_connected && status.status != "shutting_down";
             let status_code = if is_ready {
                 StatusCode::OK
             } else {
                 StatusCode::SERVICE_UNAVAILABLE
             };
 
-            Ok(Response::builder()
-                .status(status_code)
-                .header("content-type", "text/plain")
-                .body(Body::from(if is_ready { "ready" } else { "not ready" }))
-                .unwrap())
+            responses::respond(
+                status_code,
+                responses::TEXT,
+                if is_ready { "ready" } else { "not ready" },
+            )
         }
-        "/live" | "/liveness" => Ok(Response::builder()
-            .status(StatusCode::OK)
-            .header("content-type", "text/plain")
-            .body(Body::from("alive"))
-            .unwrap()),
-        _ => Ok(Response::builder()
-            .status(StatusCode::NOT_FOUND)
-            .body(Body::from("Not Found"))
-            .unwrap()),
+        "/metrics" => responses::respond(StatusCode::OK, responses::PROMETHEUS, metrics::render()),
+        "/version" => responses::json(StatusCode::OK, &version_info()),
+        // Served from the HTTP runtime, so it answers while the controllers are saturated
+        "/debug/state" => responses::json(
+            StatusCode::OK,
+            &debug_state::snapshot(&[
+                ("controller", controller_runtime),
+                ("http", &Handle::current()),
+            ]),
+        ),
+        "/live" | "/liveness" => responses::respond(StatusCode::OK, responses::TEXT, "alive"),
+        _ => responses::respond(StatusCode::NOT_FOUND, responses::TEXT, "Not Found"),
     }
 }
 
-async fn setup_signal_handler() -> tokio::sync::broadcast::Receiver<()> {
-    let (shutdown_tx, shutdown_rx) = tokio::sync::broadcast::channel(1);
+async fn setup_signal_handler() -> Shutdown {
+    let (shutdown_tx, shutdown_rx) = tokio::sync::watch::channel(false);
 
     tokio::spawn(async move {
         let mut sigterm = signal::unix::signal(signal::unix::SignalKind::terminate())
@@ -204,13 +395,18 @@ async fn setup_signal_handler() -> tokio::sync::broadcast::Receiver<()> {
             }
         }
 
-        let _ = shutdown_tx.send(());
+        let _ = shutdown_tx.send(true);
     });
 
     shutdown_rx
 }
 
-async fn initialize_operator_with_retry(health_status: SharedHealthStatus) -> Result<()> {
+async fn initialize_operator_with_retry(
+    config: OperatorConfig,
+    health_status: SharedHealthStatus,
+    kube_client: SharedClient,
+    shutdown: Shutdown,
+) -> Result<()> {
     let max_retry_attempts = 10;
     let base_delay = Duration::from_secs(5);
     let max_delay = Duration::from_secs(300); // 5 minutes max
@@ -236,6 +432,7 @@ async fn initialize_operator_with_retry(health_status: SharedHealthStatus) -> Re
 
         // Report health status
         tokio::select! {
+            _ = shutdown_requested(shutdown.clone()) => return Ok(()),
             _ = health_interval.tick() => {
                 let status = health_status.read().await;
                 info!(
@@ -246,7 +443,7 @@ async fn initialize_operator_with_retry(health_status: SharedHealthStatus) -> Re
                     "OpenFGA Operator health check - attempting Kubernetes connection"
                 );
             }
-            result = attempt_kubernetes_connection() => {
+            result = attempt_kubernetes_connection(&config) => {
                 match result {
                     Ok(client) => {
                         info!(
@@ -261,9 +458,14 @@ async fn initialize_operator_with_retry(health_status: SharedHealthStatus) -> Re
                             status.status = "running".to_string();
                             status.kubernetes_connected = true;
                         }
+                        let _ = kube_client.set(client.clone());
+
+                        if !wait_for_crds(&client, &config, &health_status, &shutdown).await {
+                            return Ok(());
+                        }
 
                         // Start the main controller loop
-                        return run_controller_with_health_monitoring(client, health_status).await;
+                        return run_controller_with_health_monitoring(client, config, health_status, shutdown).await;
                     }
                     Err(e) => {
                         retry_count += 1;
@@ -306,7 +508,10 @@ async fn initialize_operator_with_retry(health_status: SharedHealthStatus) -> Re
                             "Failed to connect to Kubernetes API, retrying with exponential backoff"
                         );
 
-                        sleep(delay).await;
+                        tokio::select! {
+                            _ = sleep(delay) => {}
+                            _ = shutdown_requested(shutdown.clone()) => return Ok(()),
+                        }
                     }
                 }
             }
@@ -314,18 +519,85 @@ async fn initialize_operator_with_retry(health_status: SharedHealthStatus) -> Re
     }
 }
 
-async fn attempt_kubernetes_connection() -> Result<Client, kube::Error> {
+/// Polls until the API server serves every CRD of the operator, installing
+/// and upgrading them first when `install_crds` is set. False on shutdown.
+async fn wait_for_crds(
+    client: &Client,
+    config: &OperatorConfig,
+    health_status: &SharedHealthStatus,
+    shutdown: &Shutdown,
+) -> bool {
+    let mut synced = !config.install_crds;
+    loop {
+        if !synced {
+            match crds::sync(client).await {
+                Ok(()) => synced = true,
+                Err(e) => warn!(
+                    event = "crd_install_failed",
+                    error = %e,
+                    "Failed to install or upgrade CRDs, check the operator's RBAC"
+                ),
+            }
+        }
+        match crds::missing(client).await {
+            Ok(missing) if missing.is_empty() => {
+                let mut status = health_status.write().await;
+                if !status.missing_crds.is_empty() {
+                    info!(event = "crds_available", "All CRDs are installed");
+                    status.missing_crds.clear();
+                    status.status = "running".to_string();
+                }
+                return true;
+            }
+            Ok(missing) => {
+                warn!(
+                    event = "crd_missing",
+                    missing = ?missing,
+                    install_crds = config.install_crds,
+                    retry_delay_seconds = crds::POLL_INTERVAL.as_secs(),
+                    "CRDs are not installed, waiting before starting the controllers"
+                );
+                let mut status = health_status.write().await;
+                status.status = "crd_missing".to_string();
+                status.missing_crds = missing;
+            }
+            Err(e) => {
+                warn!(
+                    event = "crd_discovery_failed",
+                    error = %e,
+                    "Failed to check which CRDs are installed"
+                );
+            }
+        }
+        tokio::select! {
+            _ = sleep(crds::POLL_INTERVAL) => {}
+            _ = shutdown_requested(shutdown.clone()) => return false,
+        }
+    }
+}
+
+async fn attempt_kubernetes_connection(config: &OperatorConfig) -> Result<Client, kube::Error> {
     debug!("Attempting to connect to Kubernetes API");
-    Client::try_default().await
+    let Some(qps) = config.api_qps else {
+        return Client::try_default().await;
+    };
+    let kube_config = kube::Config::infer()
+        .await
+        .map_err(kube::Error::InferConfig)?;
+    Ok(ClientBuilder::try_from(kube_config)?
+        .with_layer(&RateLimitLayer::new(qps, config.api_burst))
+        .build())
 }
 
 async fn run_controller_with_health_monitoring(
     client: Client,
+    config: OperatorConfig,
     health_status: SharedHealthStatus,
+    shutdown: Shutdown,
 ) -> Result<()> {
     // Create controller
     debug!("Initializing OpenFGA controller");
-    let controller = OpenFGAController::new(client);
+    let controller = OpenFGAController::new(client, config);
 
     // Update health status
     {
@@ -358,50 +630,34 @@ async fn run_controller_with_health_monitoring(
 
     info!("Starting OpenFGA controller reconciliation loop");
 
-    // Run controller with proper error handling
-    tokio::select! {
-        result = controller.run() => {
-            health_task.abort();
-
-            // Update health status
-            {
-                let mut status = health_status.write().await;
-                status.controller_running = false;
-            }
-
-            match result {
-                Ok(_) => {
-                    info!("OpenFGA controller completed successfully");
-                    Ok(())
-                }
-                Err(e) => {
-                    error!(
-                        error = %e,
-                        "OpenFGA controller failed"
-                    );
+    // Run controller until it fails or a shutdown signal has drained it
+    let result = controller.run(shutdown).await;
+    health_task.abort();
 
-                    // Update health status to failed
-                    {
-                        let mut status = health_status.write().await;
-                        status.status = "controller_failed".to_string();
-                    }
+    // Update health status
+    {
+        let mut status = health_status.write().await;
+        status.controller_running = false;
+    }
 
-                    Err(e)
-                }
-            }
+    match result {
+        Ok(_) => {
+            info!("OpenFGA controller stopped after finishing in-flight reconciles");
+            Ok(())
         }
-        _ = signal::ctrl_c() => {
-            info!("Received interrupt signal, shutting down gracefully");
-            health_task.abort();
+        Err(e) => {
+            error!(
+                error = %e,
+                "OpenFGA controller failed"
+            );
 
-            // Update health status
+            // Update health status to failed
             {
                 let mut status = health_status.write().await;
-                status.status = "shutting_down".to_string();
-                status.controller_running = false;
+                status.status = "controller_failed".to_string();
             }
 
-            Ok(())
+            Err(e)
         }
     }
 }
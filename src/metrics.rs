@@ -0,0 +1,636 @@
+use kube::runtime::reflector::ObjectRef;
+use kube::{Resource, ResourceExt};
+use prometheus::{
+    Encoder, GaugeVec, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, Opts,
+    Registry, TextEncoder,
+};
+use std::collections::HashSet;
+use std::future::Future;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Instant;
+
+/// Operator metric families, registered once in a dedicated registry and served on `/metrics`.
+pub struct Metrics {
+    pub registry: Registry,
+    pub reconcile_total: IntCounterVec,
+    pub reconcile_duration_seconds: HistogramVec,
+    pub reconcile_errors_total: IntCounterVec,
+    pub reconcile_panics_total: IntCounterVec,
+    /// Reconciliations in flight per controller, cancelled ones included until they are dropped.
+    pub reconciles_in_flight: IntGaugeVec,
+    /// Objects scheduled for reconciliation that have not started yet, per controller.
+    pub watch_queue_depth: IntGaugeVec,
+    /// Reconciles waiting for a slot under `max_concurrent_reconciles`, by priority.
+    pub reconcile_queue_waiting: IntGaugeVec,
+    pub instance_zones: IntGaugeVec,
+    pub instance_update_available: IntGaugeVec,
+    pub instance_version_eol: IntGaugeVec,
+    pub bulk_write_tuples_total: IntCounterVec,
+    pub bulk_write_throttled_total: IntCounterVec,
+    pub bulk_write_batch_size: IntGaugeVec,
+    pub bulk_write_concurrency: IntGaugeVec,
+    pub bulk_write_tuples_per_second: GaugeVec,
+    pub http_requests_shed_total: IntCounterVec,
+    pub resident_memory_bytes: IntGauge,
+    pub open_fds: IntGauge,
+    pub tasks_alive: IntGauge,
+    pub watch_streams: IntGaugeVec,
+    pub watchdog_breaches_total: IntCounterVec,
+    pub drift_corrections_total: IntCounterVec,
+    pub ready_replicas: IntGaugeVec,
+    pub resource_reconcile_errors_total: IntCounterVec,
+    pub last_successful_reconcile_timestamp: GaugeVec,
+}
+
+impl Metrics {
+    fn new() -> prometheus::Result<Self> {
+        let metrics = Self {
+            registry: Registry::new(),
+            reconcile_total: IntCounterVec::new(
+                Opts::new(
+                    "openfga_operator_reconcile_total",
+                    "Reconciliations by controller and result",
+                ),
+                &["controller", "result"],
+            )?,
+            reconcile_duration_seconds: HistogramVec::new(
+                HistogramOpts::new(
+                    "openfga_operator_reconcile_duration_seconds",
+                    "Duration of reconciliations by controller",
+                )
+                .buckets(vec![0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0]),
+                &["controller"],
+            )?,
+            reconcile_errors_total: IntCounterVec::new(
+                Opts::new(
+                    "openfga_operator_reconcile_errors_total",
+                    "Reconciliation errors by controller and error type",
+                ),
+                &["controller", "error_type"],
+            )?,
+            reconcile_panics_total: IntCounterVec::new(
+                Opts::new(
+                    "openfga_operator_reconcile_panics_total",
+                    "Reconciliations that panicked and were isolated, by controller",
+                ),
+                &["controller"],
+            )?,
+            reconciles_in_flight: IntGaugeVec::new(
+                Opts::new(
+                    "openfga_operator_reconciles_in_flight",
+                    "Reconciliations currently in flight by controller",
+                ),
+                &["controller"],
+            )?,
+            watch_queue_depth: IntGaugeVec::new(
+                Opts::new(
+                    "openfga_operator_watch_queue_depth",
+                    "Objects scheduled for reconciliation that have not started, by controller",
+                ),
+                &["controller"],
+            )?,
+            reconcile_queue_waiting: IntGaugeVec::new(
+                Opts::new(
+                    "openfga_operator_reconcile_queue_waiting",
+                    "OpenFGA reconciles waiting for a concurrency slot, by priority",
+                ),
+                &["priority"],
+            )?,
+            instance_zones: IntGaugeVec::new(
+                Opts::new(
+                    "openfga_operator_instance_zones",
+                    "Number of distinct zones an OpenFGA instance's pods are scheduled in",
+                ),
+                &["namespace", "name"],
+            )?,
+            instance_update_available: IntGaugeVec::new(
+                Opts::new(
+                    "openfga_operator_instance_update_available",
+                    "1 when a newer OpenFGA release than the instance's image is available",
+                ),
+                &["namespace", "name"],
+            )?,
+            instance_version_eol: IntGaugeVec::new(
+                Opts::new(
+                    "openfga_operator_instance_version_eol",
+                    "1 when the instance runs an end-of-life OpenFGA version",
+                ),
+                &["namespace", "name"],
+            )?,
+            bulk_write_tuples_total: IntCounterVec::new(
+                Opts::new(
+                    "openfga_operator_bulk_write_tuples_total",
+                    "Tuples applied by the bulk write pipeline",
+                ),
+                &["pipeline", "operation"],
+            )?,
+            bulk_write_throttled_total: IntCounterVec::new(
+                Opts::new(
+                    "openfga_operator_bulk_write_throttled_total",
+                    "Bulk write batches rejected with 429",
+                ),
+                &["pipeline"],
+            )?,
+            bulk_write_batch_size: IntGaugeVec::new(
+                Opts::new(
+                    "openfga_operator_bulk_write_batch_size",
+                    "Current adaptive bulk write batch size",
+                ),
+                &["pipeline"],
+            )?,
+            bulk_write_concurrency: IntGaugeVec::new(
+                Opts::new(
+                    "openfga_operator_bulk_write_concurrency",
+                    "Current adaptive bulk write concurrency",
+                ),
+                &["pipeline"],
+            )?,
+            bulk_write_tuples_per_second: GaugeVec::new(
+                Opts::new(
+                    "openfga_operator_bulk_write_tuples_per_second",
+                    "Tuples per second of the last bulk write run",
+                ),
+                &["pipeline"],
+            )?,
+            http_requests_shed_total: IntCounterVec::new(
+                Opts::new(
+                    "openfga_operator_http_requests_shed_total",
+                    "HTTP requests rejected by the operator server's limits, by reason",
+                ),
+                &["reason"],
+            )?,
+            resident_memory_bytes: IntGauge::new(
+                "openfga_operator_resident_memory_bytes",
+                "Resident memory of the operator process",
+            )?,
+            open_fds: IntGauge::new(
+                "openfga_operator_open_fds",
+                "Open file descriptors of the operator process",
+            )?,
+            tasks_alive: IntGauge::new(
+                "openfga_operator_tasks_alive",
+                "Live tasks on the controller runtime",
+            )?,
+            watch_streams: IntGaugeVec::new(
+                Opts::new(
+                    "openfga_operator_watch_streams",
+                    "Watch streams held open by controller",
+                ),
+                &["controller"],
+            )?,
+            watchdog_breaches_total: IntCounterVec::new(
+                Opts::new(
+                    "openfga_operator_watchdog_breaches_total",
+                    "Watchdog samples over a threshold, by resource",
+                ),
+                &["resource"],
+            )?,
+            drift_corrections_total: IntCounterVec::new(
+                Opts::new(
+                    "openfga_operator_drift_corrections_total",
+                    "Managed children reverted after edits outside the operator, by kind",
+                ),
+                &["kind"],
+            )?,
+            ready_replicas: IntGaugeVec::new(
+                Opts::new(
+                    "openfga_ready_replicas",
+                    "Ready replicas of an OpenFGA instance",
+                ),
+                &["namespace", "name"],
+            )?,
+            resource_reconcile_errors_total: IntCounterVec::new(
+                Opts::new(
+                    "openfga_reconcile_errors_total",
+                    "Reconciliation errors by resource kind and reason",
+                ),
+                &["kind", "reason"],
+            )?,
+            last_successful_reconcile_timestamp: GaugeVec::new(
+                Opts::new(
+                    "openfga_last_successful_reconcile_timestamp",
+                    "Unix time of the last successful reconciliation of a resource",
+                ),
+                &["kind", "namespace", "name"],
+            )?,
+        };
+
+        metrics
+            .registry
+            .register(Box::new(metrics.reconcile_total.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.reconcile_duration_seconds.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.reconcile_errors_total.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.reconcile_panics_total.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.reconciles_in_flight.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.watch_queue_depth.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.reconcile_queue_waiting.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.instance_zones.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.instance_update_available.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.instance_version_eol.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.bulk_write_tuples_total.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.bulk_write_throttled_total.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.bulk_write_batch_size.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.bulk_write_concurrency.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.bulk_write_tuples_per_second.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.http_requests_shed_total.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.resident_memory_bytes.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.open_fds.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.tasks_alive.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.watch_streams.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.watchdog_breaches_total.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.drift_corrections_total.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.ready_replicas.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.resource_reconcile_errors_total.clone()))?;
+        metrics.registry.register(Box::new(
+            metrics.last_successful_reconcile_timestamp.clone(),
+        ))?;
+
+        Ok(metrics)
+    }
+}
+
+/// Returns the process-wide metrics, registering them on first use.
+pub fn metrics() -> &'static Metrics {
+    static METRICS: OnceLock<Metrics> = OnceLock::new();
+    METRICS.get_or_init(|| Metrics::new().expect("operator metric definitions are valid"))
+}
+
+/// Runs one reconciliation of `resource`, recording its duration, its result and
+/// the in-flight count.
+pub async fn observe_reconcile<K, T, E>(
+    controller: &str,
+    resource: Arc<K>,
+    reconcile: impl Future<Output = Result<T, E>>,
+) -> Result<T, E>
+where
+    K: Resource<DynamicType = ()>,
+{
+    let metrics = metrics();
+    let in_flight = InFlight::start(
+        metrics
+            .reconciles_in_flight
+            .with_label_values(&[controller]),
+    );
+    let started = Instant::now();
+
+    let result = reconcile.await;
+
+    drop(in_flight);
+    metrics
+        .reconcile_duration_seconds
+        .with_label_values(&[controller])
+        .observe(started.elapsed().as_secs_f64());
+    let outcome = if result.is_ok() { "success" } else { "error" };
+    metrics
+        .reconcile_total
+        .with_label_values(&[controller, outcome])
+        .inc();
+    if result.is_ok() {
+        record_reconcile_success(resource.as_ref());
+    }
+
+    result
+}
+
+/// Objects a controller's triggers scheduled that it has not started to
+/// reconcile, as `watch_queue_depth`. The scheduler runs an object once for
+/// any number of triggers, so each object counts once.
+pub struct ReconcileBacklog {
+    gauge: IntGauge,
+    scheduled: Mutex<HashSet<String>>,
+}
+
+impl ReconcileBacklog {
+    pub fn new(controller: &str) -> Self {
+        Self {
+            gauge: metrics().watch_queue_depth.with_label_values(&[controller]),
+            scheduled: Mutex::new(HashSet::new()),
+        }
+    }
+
+    fn key<K: Resource>(object: &ObjectRef<K>) -> String {
+        format!(
+            "{}/{}",
+            object.namespace.as_deref().unwrap_or_default(),
+            object.name
+        )
+    }
+
+    fn update(&self, change: impl FnOnce(&mut HashSet<String>)) {
+        let mut scheduled = self.scheduled.lock().unwrap_or_else(|e| e.into_inner());
+        change(&mut scheduled);
+        self.gauge.set(scheduled.len() as i64);
+    }
+
+    /// Counts the objects a trigger yields, passing them on to the scheduler.
+    pub fn schedule<K: Resource>(
+        &self,
+        objects: impl IntoIterator<Item = ObjectRef<K>>,
+    ) -> Vec<ObjectRef<K>> {
+        let objects: Vec<ObjectRef<K>> = objects.into_iter().collect();
+        self.update(|scheduled| scheduled.extend(objects.iter().map(Self::key)));
+        objects
+    }
+
+    /// Stops counting an object once its reconcile starts, or once the
+    /// scheduler drops it because it no longer exists.
+    pub fn started<K: Resource>(&self, object: &ObjectRef<K>) {
+        let key = Self::key(object);
+        self.update(|scheduled| {
+            scheduled.remove(&key);
+        });
+    }
+}
+
+/// Counts one reconciliation in flight until dropped, so a reconcile future
+/// dropped mid-flight, e.g. on shutdown, is not counted forever.
+struct InFlight(IntGauge);
+
+impl InFlight {
+    fn start(gauge: IntGauge) -> Self {
+        gauge.inc();
+        Self(gauge)
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.dec();
+    }
+}
+
+/// Stamps the resource's last successful reconciliation, or drops its series once
+/// it is being deleted so removed resources do not look stuck.
+fn record_reconcile_success<K: Resource<DynamicType = ()>>(resource: &K) {
+    let kind = K::kind(&());
+    let ns = resource.namespace().unwrap_or_default();
+    let name = resource.name_any();
+    let metrics = metrics();
+    if resource.meta().deletion_timestamp.is_some() {
+        let _ = metrics
+            .last_successful_reconcile_timestamp
+            .remove_label_values(&[&kind, &ns, &name]);
+        if kind == "OpenFGA" {
+            let _ = metrics.ready_replicas.remove_label_values(&[&ns, &name]);
+            let _ = metrics.instance_zones.remove_label_values(&[&ns, &name]);
+            let _ = metrics
+                .instance_update_available
+                .remove_label_values(&[&ns, &name]);
+            let _ = metrics
+                .instance_version_eol
+                .remove_label_values(&[&ns, &name]);
+        }
+        return;
+    }
+    metrics
+        .last_successful_reconcile_timestamp
+        .with_label_values(&[&kind, &ns, &name])
+        .set(chrono::Utc::now().timestamp() as f64);
+}
+
+/// Counts the watch streams a controller holds open while the returned guard lives.
+pub fn track_watch_streams(controller: &str, streams: i64) -> WatchStreams {
+    let gauge = metrics().watch_streams.with_label_values(&[controller]);
+    gauge.add(streams);
+    WatchStreams { gauge, streams }
+}
+
+pub struct WatchStreams {
+    gauge: IntGauge,
+    streams: i64,
+}
+
+impl Drop for WatchStreams {
+    fn drop(&mut self) {
+        self.gauge.sub(self.streams);
+    }
+}
+
+/// Counts a reconciliation error of a `K` under the type chosen by the
+/// controller's error policy.
+pub fn record_reconcile_error<K: Resource<DynamicType = ()>>(controller: &str, error_type: &str) {
+    metrics()
+        .reconcile_errors_total
+        .with_label_values(&[controller, error_type])
+        .inc();
+    metrics()
+        .resource_reconcile_errors_total
+        .with_label_values(&[&K::kind(&()), error_type])
+        .inc();
+}
+
+/// Renders all metrics in the Prometheus text exposition format.
+pub fn render() -> String {
+    let mut buffer = Vec::new();
+    if let Err(e) = TextEncoder::new().encode(&metrics().registry.gather(), &mut buffer) {
+        return format!("# failed to encode metrics: {}\n", e);
+    }
+    String::from_utf8(buffer).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::create_test_openfga;
+    use crate::types::OpenFGA;
+
+    fn instance(name: &str) -> Arc<OpenFGA> {
+        let mut openfga = create_test_openfga();
+        openfga.metadata.name = Some(name.to_string());
+        openfga.metadata.namespace = Some("metrics".to_string());
+        Arc::new(openfga)
+    }
+
+    #[test]
+    fn test_dropped_reconcile_leaves_flight() {
+        let resource = instance("test-dropped");
+        let pending = futures::future::pending::<Result<(), ()>>();
+        let reconcile = observe_reconcile("test-dropped", resource, pending);
+        assert!(futures::FutureExt::now_or_never(reconcile).is_none());
+        assert_eq!(
+            metrics()
+                .reconciles_in_flight
+                .with_label_values(&["test-dropped"])
+                .get(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_reconcile_backlog() {
+        let backlog = ReconcileBacklog::new("test-backlog");
+        let depth = || {
+            metrics()
+                .watch_queue_depth
+                .with_label_values(&["test-backlog"])
+                .get()
+        };
+        let first = ObjectRef::<OpenFGA>::new("first").within("metrics");
+        let second = ObjectRef::<OpenFGA>::new("second").within("metrics");
+
+        // Triggers while the reconcile of `first` is held back by the scheduler
+        let passed = backlog.schedule([first.clone(), second.clone()]);
+        assert_eq!(passed, vec![first.clone(), second.clone()]);
+        backlog.schedule(Some(first.clone()));
+        assert_eq!(depth(), 2);
+
+        backlog.started(&first);
+        assert_eq!(depth(), 1);
+        backlog.started(&second);
+        backlog.started(&second);
+        assert_eq!(depth(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_observe_reconcile() {
+        let resource = instance("test-observe");
+        let ok: Result<(), ()> =
+            observe_reconcile("test-observe", resource.clone(), async { Ok(()) }).await;
+        assert!(ok.is_ok());
+        let err: Result<(), ()> =
+            observe_reconcile("test-observe", resource.clone(), async { Err(()) }).await;
+        assert!(err.is_err());
+
+        let metrics = metrics();
+        for result in ["success", "error"] {
+            assert_eq!(
+                metrics
+                    .reconcile_total
+                    .with_label_values(&["test-observe", result])
+                    .get(),
+                1
+            );
+        }
+        assert_eq!(
+            metrics
+                .reconciles_in_flight
+                .with_label_values(&["test-observe"])
+                .get(),
+            0
+        );
+        assert_eq!(
+            metrics
+                .reconcile_duration_seconds
+                .with_label_values(&["test-observe"])
+                .get_sample_count(),
+            2
+        );
+        assert!(
+            metrics
+                .last_successful_reconcile_timestamp
+                .with_label_values(&["OpenFGA", "metrics", "test-observe"])
+                .get()
+                > 0.0
+        );
+    }
+
+    #[tokio::test]
+    async fn test_deleted_resource_series_removed() {
+        let metrics = metrics();
+        let mut deleted = (*instance("test-deleted")).clone();
+        metrics
+            .ready_replicas
+            .with_label_values(&["metrics", "test-deleted"])
+            .set(2);
+        metrics
+            .instance_zones
+            .with_label_values(&["metrics", "test-deleted"])
+            .set(3);
+        metrics
+            .instance_update_available
+            .with_label_values(&["metrics", "test-deleted"])
+            .set(1);
+        metrics
+            .instance_version_eol
+            .with_label_values(&["metrics", "test-deleted"])
+            .set(1);
+        let _: Result<(), ()> =
+            observe_reconcile("test-deleted", Arc::new(deleted.clone()), async { Ok(()) }).await;
+
+        deleted.metadata.deletion_timestamp = Some(
+            k8s_openapi::apimachinery::pkg::apis::meta::v1::Time(chrono::Utc::now()),
+        );
+        let _: Result<(), ()> =
+            observe_reconcile("test-deleted", Arc::new(deleted), async { Ok(()) }).await;
+        let output = render();
+        assert!(!output.contains("name=\"test-deleted\""));
+        for series in [
+            "instance_zones",
+            "instance_update_available",
+            "instance_version_eol",
+        ] {
+            assert!(!output.contains(&format!(
+                "openfga_operator_{}{{name=\"test-deleted\"",
+                series
+            )));
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        record_reconcile_error::<OpenFGA>("test-render", "NotFound");
+        metrics()
+            .instance_zones
+            .with_label_values(&["ns", "a\"b"])
+            .set(2);
+
+        let output = render();
+        assert!(output.contains("# TYPE openfga_operator_reconcile_errors_total counter"));
+        assert!(output.contains(
+            "openfga_operator_reconcile_errors_total{controller=\"test-render\",error_type=\"NotFound\"} 1"
+        ));
+        assert!(output.contains("# TYPE openfga_reconcile_errors_total counter"));
+        assert!(
+            output.contains("openfga_reconcile_errors_total{kind=\"OpenFGA\",reason=\"NotFound\"}")
+        );
+        assert!(
+            output.contains("openfga_operator_instance_zones{name=\"a\\\"b\",namespace=\"ns\"} 2")
+        );
+    }
+}
@@ -0,0 +1,144 @@
+use super::{AuthorizationModel, ModelError, ModelResult, TypeDefinition};
+use std::collections::BTreeMap;
+
+/// Merges module fragments into a base model.
+///
+/// A module may add new types or extend a type defined elsewhere with extra
+/// relations. Defining the same type twice as new, or the same relation on a
+/// type twice, is a conflict; every conflict is reported, not just the first.
+/// The result is not validated, references may still be dangling.
+pub fn compose(
+    base: &AuthorizationModel,
+    modules: &[(String, AuthorizationModel)],
+) -> ModelResult<AuthorizationModel> {
+    let mut combined = base.clone();
+    // Which source contributed each type and relation, for conflict messages
+    let mut type_owners: BTreeMap<String, String> = BTreeMap::new();
+    let mut relation_owners: BTreeMap<(String, String), String> = BTreeMap::new();
+    for type_def in &base.types {
+        type_owners.insert(type_def.name.clone(), "base model".to_string());
+        for relation in &type_def.relations {
+            relation_owners.insert(
+                (type_def.name.clone(), relation.name.clone()),
+                "base model".to_string(),
+            );
+        }
+    }
+
+    let mut errors = Vec::new();
+    for (module, fragment) in modules {
+        if fragment.schema_version != base.schema_version {
+            errors.push(format!(
+                "module '{}': schema version '{}' does not match '{}'",
+                module, fragment.schema_version, base.schema_version
+            ));
+            continue;
+        }
+
+        for type_def in &fragment.types {
+            let position = combined.types.iter().position(|t| t.name == type_def.name);
+            let target = match position {
+                Some(_) if type_def.relations.is_empty() => {
+                    errors.push(format!(
+                        "module '{}': type '{}' is already defined by {}",
+                        module, type_def.name, type_owners[&type_def.name]
+                    ));
+                    continue;
+                }
+                Some(index) => index,
+                None => {
+                    type_owners.insert(type_def.name.clone(), format!("module '{}'", module));
+                    combined.types.push(TypeDefinition {
+                        name: type_def.name.clone(),
+                        relations: vec![],
+                    });
+                    combined.types.len() - 1
+                }
+            };
+
+            for relation in &type_def.relations {
+                let key = (type_def.name.clone(), relation.name.clone());
+                if let Some(owner) = relation_owners.get(&key) {
+                    errors.push(format!(
+                        "module '{}': {}#{} is already defined by {}",
+                        module, type_def.name, relation.name, owner
+                    ));
+                    continue;
+                }
+                relation_owners.insert(key, format!("module '{}'", module));
+                combined.types[target].relations.push(relation.clone());
+            }
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(combined)
+    } else {
+        Err(ModelError::Invalid(errors))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{parse_dsl, validate};
+
+    fn fragment(src: &str) -> AuthorizationModel {
+        parse_dsl(&format!("model\n  schema 1.1\n{}", src)).unwrap()
+    }
+
+    #[test]
+    fn test_compose_adds_and_extends_types() {
+        let base = fragment("type user\ntype doc\n  relations\n    define owner: [user]\n");
+        let modules = vec![
+            (
+                "folders".to_string(),
+                fragment("type folder\n  relations\n    define viewer: [user]\n"),
+            ),
+            (
+                "sharing".to_string(),
+                fragment("type doc\n  relations\n    define viewer: [user] or owner\n"),
+            ),
+        ];
+
+        let combined = compose(&base, &modules).unwrap();
+        assert_eq!(combined.types.len(), 3);
+        let doc = combined.type_definition("doc").unwrap();
+        assert!(doc.relation("owner").is_some());
+        assert!(doc.relation("viewer").is_some());
+        assert!(validate(&combined).is_ok());
+    }
+
+    #[test]
+    fn test_compose_reports_conflicts() {
+        let base = fragment("type user\ntype doc\n  relations\n    define owner: [user]\n");
+        let modules = vec![
+            ("a".to_string(), fragment("type user\n")),
+            (
+                "b".to_string(),
+                fragment("type doc\n  relations\n    define owner: [user]\n"),
+            ),
+            (
+                "c".to_string(),
+                fragment("type team\n  relations\n    define member: [user]\n"),
+            ),
+            (
+                "d".to_string(),
+                fragment("type team\n  relations\n    define member: [user]\n"),
+            ),
+        ];
+
+        let errors = match compose(&base, &modules) {
+            Err(ModelError::Invalid(errors)) => errors,
+            other => panic!("expected conflicts, got {:?}", other),
+        };
+        assert_eq!(
+            errors,
+            vec![
+                "module 'a': type 'user' is already defined by base model".to_string(),
+                "module 'b': doc#owner is already defined by base model".to_string(),
+                "module 'd': team#member is already defined by module 'c'".to_string(),
+            ]
+        );
+    }
+}
@@ -0,0 +1,218 @@
+use super::dsl::render_relation;
+use super::{AuthorizationModel, RelationDefinition};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// A single difference between two model versions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelChange {
+    pub kind: ChangeKind,
+    /// `type` or `type#relation` the change refers to, or `schema` for the schema version.
+    pub location: String,
+    pub detail: String,
+    /// Whether tuples valid under the old model may be rejected or ignored by the new one.
+    pub breaking: bool,
+}
+
+impl fmt::Display for ModelChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = match self.kind {
+            ChangeKind::Added => '+',
+            ChangeKind::Removed => '-',
+            ChangeKind::Changed => '~',
+        };
+        write!(f, "{} {}", sign, self.location)?;
+        if !self.detail.is_empty() {
+            write!(f, ": {}", self.detail)?;
+        }
+        if self.breaking {
+            f.write_str(" (breaking)")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ModelDiff {
+    pub changes: Vec<ModelChange>,
+}
+
+impl ModelDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn breaking_change(&self) -> bool {
+        self.changes.iter().any(|c| c.breaking)
+    }
+
+    /// One-line summary such as `1 added, 2 removed, 0 changed`.
+    pub fn summary(&self) -> String {
+        let count = |kind| self.changes.iter().filter(|c| c.kind == kind).count();
+        format!(
+            "{} added, {} removed, {} changed",
+            count(ChangeKind::Added),
+            count(ChangeKind::Removed),
+            count(ChangeKind::Changed)
+        )
+    }
+}
+
+/// Compares two model versions type by type and relation by relation.
+pub fn diff(old: &AuthorizationModel, new: &AuthorizationModel) -> ModelDiff {
+    let mut changes = Vec::new();
+    let mut push = |kind, location: String, detail: String, breaking| {
+        changes.push(ModelChange {
+            kind,
+            location,
+            detail,
+            breaking,
+        })
+    };
+
+    if old.schema_version != new.schema_version {
+        push(
+            ChangeKind::Changed,
+            "schema".to_string(),
+            format!("'{}' -> '{}'", old.schema_version, new.schema_version),
+            false,
+        );
+    }
+
+    for old_type in &old.types {
+        let Some(new_type) = new.type_definition(&old_type.name) else {
+            push(
+                ChangeKind::Removed,
+                old_type.name.clone(),
+                "type removed".to_string(),
+                true,
+            );
+            continue;
+        };
+
+        for old_relation in &old_type.relations {
+            let location = format!("{}#{}", old_type.name, old_relation.name);
+            match new_type.relation(&old_relation.name) {
+                None => push(
+                    ChangeKind::Removed,
+                    location,
+                    "relation removed".to_string(),
+                    true,
+                ),
+                Some(new_relation) if new_relation != old_relation => {
+                    let dropped = dropped_references(old_relation, new_relation);
+                    let mut detail = format!(
+                        "'{}' -> '{}'",
+                        render_relation(old_relation),
+                        render_relation(new_relation)
+                    );
+                    if !dropped.is_empty() {
+                        detail
+                            .push_str(&format!(", no longer assignable to {}", dropped.join(", ")));
+                    }
+                    push(ChangeKind::Changed, location, detail, !dropped.is_empty());
+                }
+                Some(_) => {}
+            }
+        }
+
+        for new_relation in &new_type.relations {
+            if old_type.relation(&new_relation.name).is_none() {
+                push(
+                    ChangeKind::Added,
+                    format!("{}#{}", new_type.name, new_relation.name),
+                    render_relation(new_relation),
+                    false,
+                );
+            }
+        }
+    }
+
+    for new_type in &new.types {
+        if old.type_definition(&new_type.name).is_none() {
+            push(
+                ChangeKind::Added,
+                new_type.name.clone(),
+                "type added".to_string(),
+                false,
+            );
+        }
+    }
+
+    ModelDiff { changes }
+}
+
+/// Directly related types the old relation accepted that the new one no longer does.
+fn dropped_references(old: &RelationDefinition, new: &RelationDefinition) -> Vec<String> {
+    old.directly_related
+        .iter()
+        .filter(|r| !new.directly_related.contains(r))
+        .map(|r| r.display())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::parse_dsl;
+
+    const OLD: &str = "model\n  schema 1.1\ntype user\ntype group\n  relations\n    define member: [user]\n\
+        type doc\n  relations\n    define editor: [user, group#member]\n    define viewer: [user] or editor\n";
+
+    #[test]
+    fn test_identical_models() {
+        let model = parse_dsl(OLD).unwrap();
+        let diff = diff(&model, &model);
+        assert!(diff.is_empty());
+        assert!(!diff.breaking_change());
+    }
+
+    #[test]
+    fn test_additive_changes_are_not_breaking() {
+        let old = parse_dsl(OLD).unwrap();
+        let new = parse_dsl(&format!(
+            "{}    define owner: [user]\ntype folder\n",
+            OLD.replace("[user] or editor", "[user] or editor or owner")
+        ))
+        .unwrap();
+
+        let diff = diff(&old, &new);
+        let rendered: Vec<String> = diff.changes.iter().map(|c| c.to_string()).collect();
+        assert_eq!(
+            rendered,
+            vec![
+                "~ doc#viewer: '[user] or editor' -> '[user] or editor or owner'",
+                "+ doc#owner: [user]",
+                "+ folder: type added",
+            ]
+        );
+        assert!(!diff.breaking_change());
+        assert_eq!(diff.summary(), "2 added, 0 removed, 1 changed");
+    }
+
+    #[test]
+    fn test_removals_are_breaking() {
+        let old = parse_dsl(OLD).unwrap();
+        let new = parse_dsl(
+            "model\n  schema 1.1\ntype user\n\
+             type doc\n  relations\n    define editor: [user]\n",
+        )
+        .unwrap();
+
+        let diff = diff(&old, &new);
+        let rendered: Vec<String> = diff.changes.iter().map(|c| c.to_string()).collect();
+        assert!(rendered.contains(&"- group: type removed (breaking)".to_string()));
+        assert!(rendered.contains(&"- doc#viewer: relation removed (breaking)".to_string()));
+        assert!(rendered.contains(
+            &"~ doc#editor: '[user, group#member]' -> '[user]', no longer assignable to group#member (breaking)"
+                .to_string()
+        ));
+        assert!(diff.breaking_change());
+    }
+}
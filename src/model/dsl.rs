@@ -0,0 +1,581 @@
+use super::{
+    AuthorizationModel, ModelError, ModelResult, RelationDefinition, RelationReference,
+    TypeDefinition, Userset,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    LBracket,
+    RBracket,
+    LParen,
+    RParen,
+    Comma,
+}
+
+/// Error at a 1-based line and column of the source.
+fn parse_error(line: usize, column: usize, message: impl Into<String>) -> ModelError {
+    ModelError::Parse {
+        line,
+        column,
+        message: message.into(),
+    }
+}
+
+/// Removes a trailing `# comment`. A `#` only starts a comment at the beginning of the
+/// line or after whitespace, so `group#member` is left alone.
+pub(super) fn strip_comment(line: &str) -> &str {
+    let bytes = line.as_bytes();
+    for (i, b) in bytes.iter().enumerate() {
+        if *b == b'#' && (i == 0 || bytes[i - 1].is_ascii_whitespace()) {
+            return &line[..i];
+        }
+    }
+    line
+}
+
+/// Splits a relation expression into tokens, each with its column. `offset` is
+/// the number of characters on the line before `input`.
+fn tokenize(input: &str, line: usize, offset: usize) -> ModelResult<Vec<(Token, usize)>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().enumerate().peekable();
+
+    while let Some(&(i, c)) = chars.peek() {
+        let column = offset + i + 1;
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '[' | ']' | '(' | ')' | ',' => {
+                chars.next();
+                let token = match c {
+                    '[' => Token::LBracket,
+                    ']' => Token::RBracket,
+                    '(' => Token::LParen,
+                    ')' => Token::RParen,
+                    _ => Token::Comma,
+                };
+                tokens.push((token, column));
+            }
+            c if is_word_char(c) => {
+                let mut word = String::new();
+                while let Some(&(_, c)) = chars.peek() {
+                    if !is_word_char(c) {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push((Token::Word(word), column));
+            }
+            other => {
+                return Err(parse_error(
+                    line,
+                    column,
+                    format!("unexpected character '{}'", other),
+                ))
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | ':' | '*' | '#' | '.' | '/')
+}
+
+fn is_identifier(word: &str) -> bool {
+    !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        && !matches!(word, "or" | "and" | "but" | "not" | "from")
+}
+
+struct ExprParser<'a> {
+    tokens: &'a [(Token, usize)],
+    pos: usize,
+    line: usize,
+    /// Column just past the expression, where running out of tokens is reported.
+    end: usize,
+    directly_related: Option<Vec<RelationReference>>,
+}
+
+impl<'a> ExprParser<'a> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(token, _)| token)
+    }
+
+    /// Error at the token with index `at`.
+    fn error(&self, at: usize, message: impl Into<String>) -> ModelError {
+        let column = self.tokens.get(at).map_or(self.end, |(_, column)| *column);
+        parse_error(self.line, column, message)
+    }
+
+    fn peek_word(&self) -> Option<&str> {
+        match self.peek() {
+            Some(Token::Word(w)) => Some(w.as_str()),
+            _ => None,
+        }
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.peek().cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token, what: &str) -> ModelResult<()> {
+        let at = self.pos;
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            _ => Err(self.error(at, format!("expected {}", what))),
+        }
+    }
+
+    fn identifier(&mut self, what: &str) -> ModelResult<String> {
+        let at = self.pos;
+        match self.next() {
+            Some(Token::Word(w)) if is_identifier(&w) => Ok(w),
+            Some(Token::Word(w)) => Err(self.error(at, format!("'{}' is not a valid {}", w, what))),
+            _ => Err(self.error(at, format!("expected {}", what))),
+        }
+    }
+
+    fn expression(&mut self) -> ModelResult<Userset> {
+        let first = self.term()?;
+        let mut operator: Option<String> = None;
+        let mut children = vec![first];
+
+        while let Some(word) = self.peek_word() {
+            if word != "or" && word != "and" {
+                break;
+            }
+            let word = word.to_string();
+            if operator.as_ref().is_some_and(|op| *op != word) {
+                return Err(self.error(self.pos, "cannot mix 'or' and 'and' without parentheses"));
+            }
+            self.next();
+            operator = Some(word);
+            children.push(self.term()?);
+        }
+
+        let mut expr = match operator.as_deref() {
+            Some("or") => Userset::Union(children),
+            Some("and") => Userset::Intersection(children),
+            _ => children.remove(0),
+        };
+
+        if self.peek_word() == Some("but") {
+            self.next();
+            let at = self.pos;
+            match self.next() {
+                Some(Token::Word(w)) if w == "not" => {}
+                _ => return Err(self.error(at, "expected 'not' after 'but'")),
+            }
+            let subtract = self.term()?;
+            expr = Userset::Difference {
+                base: Box::new(expr),
+                subtract: Box::new(subtract),
+            };
+        }
+
+        Ok(expr)
+    }
+
+    fn term(&mut self) -> ModelResult<Userset> {
+        match self.peek() {
+            Some(Token::LBracket) => {
+                self.next();
+                if self.directly_related.is_some() {
+                    return Err(self.error(
+                        self.pos - 1,
+                        "a relation may only have one set of directly related types",
+                    ));
+                }
+                self.directly_related = Some(self.type_restrictions()?);
+                Ok(Userset::This)
+            }
+            Some(Token::LParen) => {
+                self.next();
+                let expr = self.expression()?;
+                self.expect(Token::RParen, "')'")?;
+                Ok(expr)
+            }
+            Some(Token::Word(_)) => {
+                let relation = self.identifier("relation name")?;
+                if self.peek_word() == Some("from") {
+                    self.next();
+                    let tupleset = self.identifier("relation name after 'from'")?;
+                    Ok(Userset::TupleToUserset {
+                        tupleset,
+                        computed: relation,
+                    })
+                } else {
+                    Ok(Userset::Computed(relation))
+                }
+            }
+            _ => Err(self.error(self.pos, "expected a relation expression")),
+        }
+    }
+
+    fn type_restrictions(&mut self) -> ModelResult<Vec<RelationReference>> {
+        let mut references = Vec::new();
+        loop {
+            let at = self.pos;
+            let word = match self.next() {
+                Some(Token::Word(w)) => w,
+                _ => return Err(self.error(at, "expected a type in '[...]'")),
+            };
+            if self.peek_word() == Some("with") {
+                return Err(self.error(self.pos, "conditions are not supported"));
+            }
+            references.push(parse_reference(&word).ok_or_else(|| {
+                self.error(at, format!("'{}' is not a valid type reference", word))
+            })?);
+
+            let at = self.pos;
+            match self.next() {
+                Some(Token::Comma) => continue,
+                Some(Token::RBracket) => break,
+                _ => return Err(self.error(at, "expected ',' or ']'")),
+            }
+        }
+        Ok(references)
+    }
+}
+
+fn parse_reference(word: &str) -> Option<RelationReference> {
+    if let Some(type_name) = word.strip_suffix(":*") {
+        if !is_identifier(type_name) {
+            return None;
+        }
+        return Some(RelationReference {
+            type_name: type_name.to_string(),
+            relation: None,
+            wildcard: true,
+        });
+    }
+
+    let (type_name, relation) = match word.split_once('#') {
+        Some((t, r)) => (t, Some(r)),
+        None => (word, None),
+    };
+    if !is_identifier(type_name) || relation.is_some_and(|r| !is_identifier(r)) {
+        return None;
+    }
+
+    Some(RelationReference {
+        type_name: type_name.to_string(),
+        relation: relation.map(str::to_string),
+        wildcard: false,
+    })
+}
+
+/// Parses an authorization model written in the OpenFGA DSL.
+pub fn parse_dsl(source: &str) -> ModelResult<AuthorizationModel> {
+    let mut schema_version: Option<String> = None;
+    let mut seen_model = false;
+    let mut types: Vec<TypeDefinition> = Vec::new();
+    let mut in_relations = false;
+
+    for (index, raw) in source.lines().enumerate() {
+        let line = index + 1;
+        let content = strip_comment(raw).trim();
+        if content.is_empty() {
+            continue;
+        }
+
+        let (keyword, rest) = content
+            .split_once(char::is_whitespace)
+            .map(|(k, r)| (k, r.trim()))
+            .unwrap_or((content, ""));
+        let column = raw.chars().take_while(|c| c.is_whitespace()).count() + 1;
+        let rest_column = column + content.chars().count() - rest.chars().count();
+
+        match keyword {
+            "model" if rest.is_empty() => {
+                if seen_model {
+                    return Err(parse_error(line, column, "duplicate 'model' declaration"));
+                }
+                seen_model = true;
+            }
+            "schema" => {
+                if !seen_model {
+                    return Err(parse_error(line, column, "'schema' must follow 'model'"));
+                }
+                schema_version = Some(rest.to_string());
+            }
+            "type" => {
+                if !is_identifier(rest) {
+                    return Err(parse_error(
+                        line,
+                        rest_column,
+                        format!("'{}' is not a valid type name", rest),
+                    ));
+                }
+                types.push(TypeDefinition {
+                    name: rest.to_string(),
+                    relations: Vec::new(),
+                });
+                in_relations = false;
+            }
+            "relations" if rest.is_empty() => {
+                if types.is_empty() {
+                    return Err(parse_error(line, column, "'relations' must follow a type"));
+                }
+                in_relations = true;
+            }
+            "define" => {
+                if !in_relations {
+                    return Err(parse_error(
+                        line,
+                        column,
+                        "'define' must be inside a 'relations' block",
+                    ));
+                }
+                let (name, expression) = rest.split_once(':').ok_or_else(|| {
+                    parse_error(
+                        line,
+                        rest_column,
+                        "expected 'define <relation>: <expression>'",
+                    )
+                })?;
+                let name = name.trim();
+                if !is_identifier(name) {
+                    return Err(parse_error(
+                        line,
+                        rest_column,
+                        format!("'{}' is not a valid relation name", name),
+                    ));
+                }
+
+                // Characters on the line before the expression, up to and including ':'
+                let offset =
+                    rest_column - 1 + rest[..rest.len() - expression.len()].chars().count();
+                let tokens = tokenize(expression, line, offset)?;
+                let mut parser = ExprParser {
+                    tokens: &tokens,
+                    pos: 0,
+                    line,
+                    end: offset + expression.trim_end().chars().count() + 1,
+                    directly_related: None,
+                };
+                let rewrite = parser.expression()?;
+                if parser.pos < tokens.len() {
+                    return Err(parser.error(
+                        parser.pos,
+                        "unexpected trailing input in relation expression",
+                    ));
+                }
+
+                let type_def = types.last_mut().expect("checked by in_relations");
+                type_def.relations.push(RelationDefinition {
+                    name: name.to_string(),
+                    rewrite,
+                    directly_related: parser.directly_related.unwrap_or_default(),
+                });
+            }
+            "condition" => return Err(parse_error(line, column, "conditions are not supported")),
+            other => return Err(parse_error(line, column, format!("unexpected '{}'", other))),
+        }
+    }
+
+    if !seen_model {
+        return Err(parse_error(1, 1, "missing 'model' declaration"));
+    }
+
+    Ok(AuthorizationModel {
+        schema_version: schema_version
+            .ok_or_else(|| parse_error(1, 1, "missing 'schema' version"))?,
+        types,
+    })
+}
+
+/// Renders a model back to the DSL.
+pub fn to_dsl(model: &AuthorizationModel) -> String {
+    let mut out = format!("model\n  schema {}\n", model.schema_version);
+
+    for type_def in &model.types {
+        out.push_str(&format!("\ntype {}\n", type_def.name));
+        if type_def.relations.is_empty() {
+            continue;
+        }
+        out.push_str("  relations\n");
+        for relation in &type_def.relations {
+            out.push_str(&format!(
+                "    define {}: {}\n",
+                relation.name,
+                render_relation(relation)
+            ));
+        }
+    }
+
+    out
+}
+
+/// Renders the right-hand side of a relation's `define`.
+pub(super) fn render_relation(relation: &RelationDefinition) -> String {
+    render(&relation.rewrite, &relation.directly_related)
+}
+
+fn render(userset: &Userset, directly_related: &[RelationReference]) -> String {
+    let nested = |u: &Userset| match u {
+        Userset::Union(_) | Userset::Intersection(_) | Userset::Difference { .. } => {
+            format!("({})", render(u, directly_related))
+        }
+        _ => render(u, directly_related),
+    };
+
+    match userset {
+        Userset::This => format!(
+            "[{}]",
+            directly_related
+                .iter()
+                .map(RelationReference::display)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Userset::Computed(relation) => relation.clone(),
+        Userset::TupleToUserset { tupleset, computed } => format!("{} from {}", computed, tupleset),
+        Userset::Union(children) => children.iter().map(nested).collect::<Vec<_>>().join(" or "),
+        Userset::Intersection(children) => children
+            .iter()
+            .map(nested)
+            .collect::<Vec<_>>()
+            .join(" and "),
+        Userset::Difference { base, subtract } => {
+            let base = match base.as_ref() {
+                Userset::Difference { .. } => nested(base),
+                other => render(other, directly_related),
+            };
+            format!("{} but not {}", base, nested(subtract))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MODEL: &str = r#"
+model
+  schema 1.1
+
+# Users of the system
+type user
+
+type group
+  relations
+    define member: [user, group#member]
+
+type folder
+  relations
+    define viewer: [user, user:*]
+
+type document
+  relations
+    define parent: [folder]
+    define owner: [user]
+    define editor: [user] or owner
+    define viewer: [user, group#member] or editor or viewer from parent
+    define blocked: [user]
+    define can_view: viewer but not blocked
+    define can_share: (editor or owner) and can_view
+"#;
+
+    #[test]
+    fn test_parse_dsl() {
+        let model = parse_dsl(MODEL).unwrap();
+        assert_eq!(model.schema_version, "1.1");
+        assert_eq!(model.types.len(), 4);
+
+        let document = model.type_definition("document").unwrap();
+        let viewer = document.relation("viewer").unwrap();
+        assert_eq!(
+            viewer.rewrite,
+            Userset::Union(vec![
+                Userset::This,
+                Userset::Computed("editor".to_string()),
+                Userset::TupleToUserset {
+                    tupleset: "parent".to_string(),
+                    computed: "viewer".to_string(),
+                },
+            ])
+        );
+        assert_eq!(
+            viewer.directly_related[1].relation,
+            Some("member".to_string())
+        );
+
+        let folder_viewer = model
+            .type_definition("folder")
+            .unwrap()
+            .relation("viewer")
+            .unwrap();
+        assert!(folder_viewer.directly_related[1].wildcard);
+
+        let can_share = document.relation("can_share").unwrap();
+        assert!(
+            matches!(&can_share.rewrite, Userset::Intersection(c) if matches!(c[0], Userset::Union(_)))
+        );
+    }
+
+    #[test]
+    fn test_dsl_round_trip() {
+        let model = parse_dsl(MODEL).unwrap();
+        let rendered = to_dsl(&model);
+        assert!(rendered.contains("define can_share: (editor or owner) and can_view"));
+        assert_eq!(parse_dsl(&rendered).unwrap(), model);
+    }
+
+    #[test]
+    fn test_parse_errors_report_position() {
+        let err =
+            parse_dsl("model\n  schema 1.1\ntype doc\n  relations\n    define a: b or c and d\n")
+                .unwrap_err();
+        assert!(matches!(
+            err,
+            ModelError::Parse {
+                line: 5,
+                column: 22,
+                ..
+            }
+        ));
+        assert_eq!(
+            err.to_string(),
+            "line 5, column 22: cannot mix 'or' and 'and' without parentheses"
+        );
+
+        let err = parse_dsl("model\n  schema 1.1\ntype doc\n    define a: [user]\n").unwrap_err();
+        assert!(matches!(
+            err,
+            ModelError::Parse {
+                line: 4,
+                column: 5,
+                ..
+            }
+        ));
+
+        let err =
+            parse_dsl("model\n  schema 1.1\ntype doc\n  relations\n    define a: [user, team#]\n")
+                .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 5, column 22: 'team#' is not a valid type reference"
+        );
+
+        // Running out of input points just past the expression
+        let err = parse_dsl("model\n  schema 1.1\ntype doc\n  relations\n    define a: [user\n")
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            ModelError::Parse {
+                line: 5,
+                column: 20,
+                ..
+            }
+        ));
+    }
+}
@@ -0,0 +1,269 @@
+use super::{
+    AuthorizationModel, ModelError, ModelResult, RelationDefinition, RelationReference,
+    TypeDefinition, Userset,
+};
+use serde_json::{json, Map, Value};
+
+fn json_error(message: impl Into<String>) -> ModelError {
+    ModelError::Json(message.into())
+}
+
+/// Renders a model in the JSON shape accepted by the OpenFGA `WriteAuthorizationModel` API.
+pub fn to_json(model: &AuthorizationModel) -> Value {
+    let type_definitions: Vec<Value> = model
+        .types
+        .iter()
+        .map(|type_def| {
+            let mut relations = Map::new();
+            let mut metadata = Map::new();
+            for relation in &type_def.relations {
+                relations.insert(relation.name.clone(), userset_to_json(&relation.rewrite));
+                metadata.insert(
+                    relation.name.clone(),
+                    json!({
+                        "directly_related_user_types": relation
+                            .directly_related
+                            .iter()
+                            .map(reference_to_json)
+                            .collect::<Vec<_>>()
+                    }),
+                );
+            }
+
+            let mut value = json!({
+                "type": type_def.name,
+                "relations": relations,
+            });
+            if !metadata.is_empty() {
+                value["metadata"] = json!({ "relations": metadata });
+            }
+            value
+        })
+        .collect();
+
+    json!({
+        "schema_version": model.schema_version,
+        "type_definitions": type_definitions,
+    })
+}
+
+fn userset_to_json(userset: &Userset) -> Value {
+    match userset {
+        Userset::This => json!({ "this": {} }),
+        Userset::Computed(relation) => json!({ "computedUserset": { "relation": relation } }),
+        Userset::TupleToUserset { tupleset, computed } => json!({
+            "tupleToUserset": {
+                "tupleset": { "relation": tupleset },
+                "computedUserset": { "relation": computed },
+            }
+        }),
+        Userset::Union(children) => json!({
+            "union": { "child": children.iter().map(userset_to_json).collect::<Vec<_>>() }
+        }),
+        Userset::Intersection(children) => json!({
+            "intersection": { "child": children.iter().map(userset_to_json).collect::<Vec<_>>() }
+        }),
+        Userset::Difference { base, subtract } => json!({
+            "difference": {
+                "base": userset_to_json(base),
+                "subtract": userset_to_json(subtract),
+            }
+        }),
+    }
+}
+
+fn reference_to_json(reference: &RelationReference) -> Value {
+    let mut value = json!({ "type": reference.type_name });
+    if let Some(relation) = &reference.relation {
+        value["relation"] = json!(relation);
+    }
+    if reference.wildcard {
+        value["wildcard"] = json!({});
+    }
+    value
+}
+
+/// Parses the JSON form of an authorization model.
+pub fn from_json(value: &Value) -> ModelResult<AuthorizationModel> {
+    let schema_version = value
+        .get("schema_version")
+        .and_then(Value::as_str)
+        .ok_or_else(|| json_error("missing 'schema_version'"))?
+        .to_string();
+
+    let type_definitions = value
+        .get("type_definitions")
+        .and_then(Value::as_array)
+        .ok_or_else(|| json_error("missing 'type_definitions'"))?;
+
+    let types = type_definitions
+        .iter()
+        .map(type_from_json)
+        .collect::<ModelResult<Vec<_>>>()?;
+
+    Ok(AuthorizationModel {
+        schema_version,
+        types,
+    })
+}
+
+fn type_from_json(value: &Value) -> ModelResult<TypeDefinition> {
+    let name = value
+        .get("type")
+        .and_then(Value::as_str)
+        .ok_or_else(|| json_error("type definition is missing 'type'"))?
+        .to_string();
+
+    let metadata = value
+        .get("metadata")
+        .and_then(|m| m.get("relations"))
+        .and_then(Value::as_object);
+
+    let mut relations = Vec::new();
+    if let Some(map) = value.get("relations").and_then(Value::as_object) {
+        for (relation_name, rewrite) in map {
+            let directly_related = metadata
+                .and_then(|m| m.get(relation_name))
+                .and_then(|r| r.get("directly_related_user_types"))
+                .and_then(Value::as_array)
+                .map(|refs| refs.iter().map(reference_from_json).collect())
+                .transpose()?
+                .unwrap_or_default();
+
+            relations.push(RelationDefinition {
+                name: relation_name.clone(),
+                rewrite: userset_from_json(rewrite)
+                    .map_err(|e| json_error(format!("{}#{}: {}", name, relation_name, e)))?,
+                directly_related,
+            });
+        }
+    }
+
+    Ok(TypeDefinition { name, relations })
+}
+
+fn reference_from_json(value: &Value) -> ModelResult<RelationReference> {
+    if value
+        .get("condition")
+        .is_some_and(|c| c.as_str() != Some(""))
+    {
+        return Err(json_error("conditions are not supported"));
+    }
+    Ok(RelationReference {
+        type_name: value
+            .get("type")
+            .and_then(Value::as_str)
+            .ok_or_else(|| json_error("directly related type is missing 'type'"))?
+            .to_string(),
+        relation: value
+            .get("relation")
+            .and_then(Value::as_str)
+            .filter(|r| !r.is_empty())
+            .map(str::to_string),
+        wildcard: value.get("wildcard").is_some(),
+    })
+}
+
+fn relation_field(value: &Value, field: &str) -> Result<String, String> {
+    value
+        .get(field)
+        .and_then(|v| v.get("relation"))
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| format!("'{}' is missing 'relation'", field))
+}
+
+fn children(value: &Value) -> Result<Vec<Userset>, String> {
+    value
+        .get("child")
+        .and_then(Value::as_array)
+        .ok_or_else(|| "missing 'child'".to_string())?
+        .iter()
+        .map(userset_from_json)
+        .collect()
+}
+
+fn userset_from_json(value: &Value) -> Result<Userset, String> {
+    if value.get("this").is_some() {
+        Ok(Userset::This)
+    } else if let Some(computed) = value.get("computedUserset") {
+        computed
+            .get("relation")
+            .and_then(Value::as_str)
+            .map(|r| Userset::Computed(r.to_string()))
+            .ok_or_else(|| "'computedUserset' is missing 'relation'".to_string())
+    } else if let Some(ttu) = value.get("tupleToUserset") {
+        Ok(Userset::TupleToUserset {
+            tupleset: relation_field(ttu, "tupleset")?,
+            computed: relation_field(ttu, "computedUserset")?,
+        })
+    } else if let Some(union) = value.get("union") {
+        Ok(Userset::Union(children(union)?))
+    } else if let Some(intersection) = value.get("intersection") {
+        Ok(Userset::Intersection(children(intersection)?))
+    } else if let Some(difference) = value.get("difference") {
+        let part = |field: &str| {
+            difference
+                .get(field)
+                .ok_or_else(|| format!("'difference' is missing '{}'", field))
+                .and_then(userset_from_json)
+        };
+        Ok(Userset::Difference {
+            base: Box::new(part("base")?),
+            subtract: Box::new(part("subtract")?),
+        })
+    } else {
+        Err("unknown userset rewrite".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::parse_dsl;
+
+    #[test]
+    fn test_json_round_trip() {
+        let model = parse_dsl(
+            "model\n  schema 1.1\ntype user\ntype folder\n  relations\n    define viewer: [user, user:*]\n\
+             type doc\n  relations\n    define parent: [folder]\n    define blocked: [user]\n\
+             \x20   define viewer: ([user] or viewer from parent) but not blocked\n",
+        )
+        .unwrap();
+
+        let json = to_json(&model);
+        let viewer = &json["type_definitions"][2]["relations"]["viewer"];
+        assert_eq!(
+            viewer["difference"]["subtract"]["computedUserset"]["relation"],
+            "blocked"
+        );
+        assert_eq!(
+            viewer["difference"]["base"]["union"]["child"][1]["tupleToUserset"]["tupleset"]
+                ["relation"],
+            "parent"
+        );
+        assert_eq!(
+            json["type_definitions"][1]["metadata"]["relations"]["viewer"]
+                ["directly_related_user_types"][1]["wildcard"],
+            json!({})
+        );
+
+        assert_eq!(from_json(&json).unwrap(), model);
+    }
+
+    #[test]
+    fn test_from_json_tolerates_missing_metadata() {
+        let json = json!({
+            "schema_version": "1.1",
+            "type_definitions": [
+                {"type": "user", "metadata": {"description": "A user"}},
+                {"type": "bank", "relations": {"admin": {"this": {}}}}
+            ]
+        });
+
+        let model = from_json(&json).unwrap();
+        assert_eq!(model.types[0].relations.len(), 0);
+        assert_eq!(model.types[1].relations[0].rewrite, Userset::This);
+        assert!(model.types[1].relations[0].directly_related.is_empty());
+    }
+}
@@ -0,0 +1,431 @@
+use super::dsl::strip_comment;
+use super::{AuthorizationModel, TypeDefinition, Userset};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt;
+use std::str::FromStr;
+
+pub const UNREACHABLE_RELATION: &str = "unreachable-relation";
+pub const UNUSED_TYPE: &str = "unused-type";
+pub const PUBLIC_WILDCARD: &str = "public-wildcard";
+pub const MISSING_REVERSE_RELATION: &str = "missing-reverse-relation";
+pub const CYCLIC_REWRITE: &str = "cyclic-rewrite";
+pub const DEPRECATED_SYNTAX: &str = "deprecated-syntax";
+
+/// Every lint rule with its default severity.
+pub const RULES: &[(&str, LintSeverity)] = &[
+    (UNREACHABLE_RELATION, LintSeverity::Warning),
+    (UNUSED_TYPE, LintSeverity::Info),
+    (PUBLIC_WILDCARD, LintSeverity::Warning),
+    (MISSING_REVERSE_RELATION, LintSeverity::Warning),
+    (CYCLIC_REWRITE, LintSeverity::Warning),
+    (DEPRECATED_SYNTAX, LintSeverity::Warning),
+];
+
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, JsonSchema,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum LintSeverity {
+    Off,
+    Info,
+    Warning,
+    Error,
+}
+
+impl fmt::Display for LintSeverity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            LintSeverity::Off => "off",
+            LintSeverity::Info => "info",
+            LintSeverity::Warning => "warning",
+            LintSeverity::Error => "error",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for LintSeverity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(LintSeverity::Off),
+            "info" => Ok(LintSeverity::Info),
+            "warning" => Ok(LintSeverity::Warning),
+            "error" => Ok(LintSeverity::Error),
+            other => Err(format!("unknown severity '{}'", other)),
+        }
+    }
+}
+
+/// Per-rule severity overrides; rules not listed keep their default.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LintConfig {
+    pub overrides: BTreeMap<String, LintSeverity>,
+}
+
+impl LintConfig {
+    pub fn new(overrides: BTreeMap<String, LintSeverity>) -> Result<Self, String> {
+        for rule in overrides.keys() {
+            if !RULES.iter().any(|(name, _)| name == rule) {
+                return Err(format!("unknown lint rule '{}'", rule));
+            }
+        }
+        Ok(Self { overrides })
+    }
+
+    pub fn severity(&self, rule: &str) -> LintSeverity {
+        self.overrides.get(rule).copied().unwrap_or_else(|| {
+            RULES
+                .iter()
+                .find(|(name, _)| *name == rule)
+                .map(|(_, severity)| *severity)
+                .unwrap_or(LintSeverity::Warning)
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintFinding {
+    pub rule: &'static str,
+    pub severity: LintSeverity,
+    /// `type` or `type#relation` the finding refers to.
+    pub location: String,
+    pub message: String,
+}
+
+impl fmt::Display for LintFinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[{}] {} {}: {}",
+            self.severity, self.rule, self.location, self.message
+        )
+    }
+}
+
+fn reporter<'a>(
+    config: &'a LintConfig,
+    findings: &'a mut Vec<LintFinding>,
+) -> impl FnMut(&'static str, String, String) + 'a {
+    move |rule, location, message| {
+        let severity = config.severity(rule);
+        if severity != LintSeverity::Off {
+            findings.push(LintFinding {
+                rule,
+                severity,
+                location,
+                message,
+            });
+        }
+    }
+}
+
+/// Scans DSL source for schema 1.0 constructs. These no longer parse, so the
+/// findings explain a parse failure rather than a model that is otherwise valid.
+pub fn deprecated_syntax(source: &str, config: &LintConfig) -> Vec<LintFinding> {
+    let mut findings = Vec::new();
+    let mut report = reporter(config, &mut findings);
+
+    for (index, raw) in source.lines().enumerate() {
+        let location = format!("line {}", index + 1);
+        let words: Vec<&str> = strip_comment(raw).split_whitespace().collect();
+        match words.as_slice() {
+            ["schema", "1.0"] => report(
+                DEPRECATED_SYNTAX,
+                location,
+                "schema 1.0 is no longer accepted, use 'schema 1.1' with type restrictions"
+                    .to_string(),
+            ),
+            ["define", relation, "as", ..] => report(
+                DEPRECATED_SYNTAX,
+                location,
+                format!(
+                    "'define {} as' is schema 1.0 syntax, use 'define {}: ...'",
+                    relation, relation
+                ),
+            ),
+            ["define", ..] if words.contains(&"self") => report(
+                DEPRECATED_SYNTAX,
+                location,
+                "'self' is schema 1.0 syntax, list the allowed types in '[...]' instead"
+                    .to_string(),
+            ),
+            _ => {}
+        }
+    }
+
+    drop(report);
+    findings
+}
+
+/// Runs the semantic lint rules over a model that already passed validation.
+pub fn lint(model: &AuthorizationModel, config: &LintConfig) -> Vec<LintFinding> {
+    let mut findings = Vec::new();
+    let mut report = reporter(config, &mut findings);
+
+    let satisfiable = satisfiable_relations(model);
+    let referenced: BTreeSet<&str> = model
+        .types
+        .iter()
+        .flat_map(|t| &t.relations)
+        .flat_map(|r| &r.directly_related)
+        .map(|r| r.type_name.as_str())
+        .collect();
+
+    for type_def in &model.types {
+        if type_def.relations.is_empty() && !referenced.contains(type_def.name.as_str()) {
+            report(
+                UNUSED_TYPE,
+                type_def.name.clone(),
+                "type has no relations and is not referenced by any other type".to_string(),
+            );
+        }
+
+        for relation in &type_def.relations {
+            let location = format!("{}#{}", type_def.name, relation.name);
+
+            if let Some(cycle) = computed_cycle(type_def, &relation.name) {
+                report(
+                    CYCLIC_REWRITE,
+                    location.clone(),
+                    format!("rewrites form a cycle: {}", cycle.join(" -> ")),
+                );
+            }
+
+            if !satisfiable.contains(&(type_def.name.as_str(), relation.name.as_str())) {
+                report(
+                    UNREACHABLE_RELATION,
+                    location.clone(),
+                    "no tuple can ever grant this relation".to_string(),
+                );
+            }
+
+            for reference in relation.directly_related.iter().filter(|r| r.wildcard) {
+                report(
+                    PUBLIC_WILDCARD,
+                    location.clone(),
+                    format!(
+                        "'{}' allows granting this relation to every {}",
+                        reference.display(),
+                        reference.type_name
+                    ),
+                );
+            }
+
+            relation.rewrite.walk(&mut |userset| {
+                if let Userset::TupleToUserset { tupleset, computed } = userset {
+                    let Some(tupleset_def) = type_def.relation(tupleset) else {
+                        return;
+                    };
+                    for target in &tupleset_def.directly_related {
+                        let defined = model
+                            .type_definition(&target.type_name)
+                            .is_some_and(|t| t.relation(computed).is_some());
+                        if !defined {
+                            report(
+                                MISSING_REVERSE_RELATION,
+                                location.clone(),
+                                format!(
+                                    "'{} from {}' has no effect for '{}', which does not define '{}'",
+                                    computed, tupleset, target.type_name, computed
+                                ),
+                            );
+                        }
+                    }
+                }
+            });
+        }
+    }
+
+    drop(report);
+    findings
+}
+
+/// Shortest chain of same-object rewrites (`define a: b`) leading from
+/// `relation` back to itself. Such a cycle never resolves to a tuple;
+/// recursion through `from` is left alone, as it is how hierarchies are
+/// modelled.
+fn computed_cycle(type_def: &TypeDefinition, relation: &str) -> Option<Vec<String>> {
+    let computed = |name: &str| {
+        let mut targets = Vec::new();
+        if let Some(definition) = type_def.relation(name) {
+            definition.rewrite.walk(&mut |userset| {
+                if let Userset::Computed(target) = userset {
+                    targets.push(target.clone());
+                }
+            });
+        }
+        targets
+    };
+
+    let mut previous: BTreeMap<String, String> = BTreeMap::new();
+    let mut queue = VecDeque::from([relation.to_string()]);
+    while let Some(current) = queue.pop_front() {
+        for target in computed(&current) {
+            if target == relation {
+                let mut cycle = vec![relation.to_string(), current.clone()];
+                let mut at = current;
+                while let Some(before) = previous.get(&at) {
+                    cycle.push(before.clone());
+                    at = before.clone();
+                }
+                cycle.reverse();
+                return Some(cycle);
+            }
+            if target != current && !previous.contains_key(&target) {
+                previous.insert(target.clone(), current.clone());
+                queue.push_back(target);
+            }
+        }
+    }
+    None
+}
+
+/// Fixpoint over all relations: a relation is satisfiable if some tuple could grant it.
+fn satisfiable_relations(model: &AuthorizationModel) -> BTreeSet<(&str, &str)> {
+    let mut satisfiable: BTreeSet<(&str, &str)> = BTreeSet::new();
+
+    loop {
+        let before = satisfiable.len();
+        for type_def in &model.types {
+            for relation in &type_def.relations {
+                let key = (type_def.name.as_str(), relation.name.as_str());
+                if !satisfiable.contains(&key)
+                    && is_satisfiable(model, &type_def.name, &relation.rewrite, &satisfiable)
+                {
+                    satisfiable.insert(key);
+                }
+            }
+        }
+        if satisfiable.len() == before {
+            return satisfiable;
+        }
+    }
+}
+
+fn is_satisfiable(
+    model: &AuthorizationModel,
+    type_name: &str,
+    userset: &Userset,
+    known: &BTreeSet<(&str, &str)>,
+) -> bool {
+    match userset {
+        Userset::This => true,
+        Userset::Computed(relation) => known.contains(&(type_name, relation.as_str())),
+        Userset::TupleToUserset { tupleset, computed } => {
+            known.contains(&(type_name, tupleset.as_str()))
+                && model
+                    .type_definition(type_name)
+                    .and_then(|t| t.relation(tupleset))
+                    .is_some_and(|r| {
+                        r.directly_related.iter().any(|target| {
+                            known.contains(&(target.type_name.as_str(), computed.as_str()))
+                        })
+                    })
+        }
+        Userset::Union(children) => children
+            .iter()
+            .any(|c| is_satisfiable(model, type_name, c, known)),
+        Userset::Intersection(children) => children
+            .iter()
+            .all(|c| is_satisfiable(model, type_name, c, known)),
+        Userset::Difference { base, .. } => is_satisfiable(model, type_name, base, known),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::parse_dsl;
+
+    const MODEL: &str = "model\n  schema 1.1\ntype user\ntype robot\ntype team\n  relations\n    define member: [user]\n\
+        type folder\n  relations\n    define viewer: [user, user:*]\n\
+        type doc\n  relations\n    define parent: [folder, team]\n    define viewer: viewer from parent\n\
+        \x20   define loop_a: loop_b\n    define loop_b: loop_a\n";
+
+    #[test]
+    fn test_default_rules() {
+        let model = parse_dsl(MODEL).unwrap();
+        let findings = lint(&model, &LintConfig::default());
+        let rules: Vec<(&str, &str)> = findings
+            .iter()
+            .map(|f| (f.rule, f.location.as_str()))
+            .collect();
+
+        assert!(rules.contains(&(UNUSED_TYPE, "robot")));
+        assert!(!rules.contains(&(UNUSED_TYPE, "user")));
+        assert!(rules.contains(&(PUBLIC_WILDCARD, "folder#viewer")));
+        assert!(rules.contains(&(MISSING_REVERSE_RELATION, "doc#viewer")));
+        assert!(rules.contains(&(UNREACHABLE_RELATION, "doc#loop_a")));
+        assert!(rules.contains(&(UNREACHABLE_RELATION, "doc#loop_b")));
+        assert!(!rules.contains(&(UNREACHABLE_RELATION, "doc#viewer")));
+        assert!(rules.contains(&(CYCLIC_REWRITE, "doc#loop_b")));
+        assert!(!rules.contains(&(CYCLIC_REWRITE, "doc#viewer")));
+        let cycle = findings
+            .iter()
+            .find(|f| f.rule == CYCLIC_REWRITE && f.location == "doc#loop_a")
+            .unwrap();
+        assert_eq!(
+            cycle.message,
+            "rewrites form a cycle: loop_a -> loop_b -> loop_a"
+        );
+    }
+
+    #[test]
+    fn test_cyclic_rewrite_through_operators() {
+        let model = parse_dsl(
+            "model\n  schema 1.1\ntype user\ntype doc\n  relations\n    define owner: [user]\n\
+             \x20   define editor: [user] or owner or viewer\n    define viewer: editor but not owner\n",
+        )
+        .unwrap();
+        let cycles: Vec<String> = lint(&model, &LintConfig::default())
+            .into_iter()
+            .filter(|f| f.rule == CYCLIC_REWRITE)
+            .map(|f| f.location)
+            .collect();
+        assert_eq!(cycles, vec!["doc#editor", "doc#viewer"]);
+    }
+
+    #[test]
+    fn test_deprecated_syntax() {
+        let source = "model\n  schema 1.0\ntype doc\n  relations\n    define owner as self\n\
+            \x20   define viewer: self or owner # self\n    define parent: [folder#self]\n";
+        let findings = deprecated_syntax(source, &LintConfig::default());
+        let locations: Vec<&str> = findings.iter().map(|f| f.location.as_str()).collect();
+        assert_eq!(locations, vec!["line 2", "line 5", "line 6"]);
+        assert!(findings.iter().all(|f| f.rule == DEPRECATED_SYNTAX));
+
+        assert!(deprecated_syntax(MODEL, &LintConfig::default()).is_empty());
+        let off = LintConfig::new(BTreeMap::from([(
+            DEPRECATED_SYNTAX.to_string(),
+            LintSeverity::Off,
+        )]))
+        .unwrap();
+        assert!(deprecated_syntax(source, &off).is_empty());
+    }
+
+    #[test]
+    fn test_severity_overrides() {
+        let model = parse_dsl(MODEL).unwrap();
+        let config = LintConfig::new(BTreeMap::from([
+            (UNUSED_TYPE.to_string(), LintSeverity::Off),
+            (PUBLIC_WILDCARD.to_string(), LintSeverity::Error),
+        ]))
+        .unwrap();
+
+        let findings = lint(&model, &config);
+        assert!(findings.iter().all(|f| f.rule != UNUSED_TYPE));
+        let wildcard = findings.iter().find(|f| f.rule == PUBLIC_WILDCARD).unwrap();
+        assert_eq!(wildcard.severity, LintSeverity::Error);
+        assert!(wildcard
+            .to_string()
+            .starts_with("[error] public-wildcard folder#viewer:"));
+
+        assert!(
+            LintConfig::new(BTreeMap::from([("nope".to_string(), LintSeverity::Off)])).is_err()
+        );
+    }
+}
@@ -0,0 +1,159 @@
+//! In-memory representation of OpenFGA authorization models, with conversion
+//! between the DSL and the JSON format accepted by the OpenFGA API.
+
+mod compose;
+mod diff;
+mod dsl;
+mod json;
+pub mod lint;
+mod validate;
+
+pub use compose::compose;
+pub use diff::{diff, ChangeKind, ModelChange, ModelDiff};
+pub use dsl::{parse_dsl, to_dsl};
+pub use json::{from_json, to_json};
+pub use lint::{deprecated_syntax, lint, LintConfig, LintFinding, LintSeverity};
+pub use validate::validate;
+
+use std::path::Path;
+use thiserror::Error;
+
+pub const SCHEMA_VERSION: &str = "1.1";
+
+#[derive(Error, Debug)]
+pub enum ModelError {
+    #[error("line {line}, column {column}: {message}")]
+    Parse {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    #[error("invalid model JSON: {0}")]
+    Json(String),
+    #[error("model is invalid:\n  {}", .0.join("\n  "))]
+    Invalid(Vec<String>),
+    #[error("failed to read model file: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+pub type ModelResult<T> = std::result::Result<T, ModelError>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthorizationModel {
+    pub schema_version: String,
+    pub types: Vec<TypeDefinition>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeDefinition {
+    pub name: String,
+    pub relations: Vec<RelationDefinition>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelationDefinition {
+    pub name: String,
+    pub rewrite: Userset,
+    /// Types allowed in tuples written directly to this relation (`[user, group#member]`).
+    pub directly_related: Vec<RelationReference>,
+}
+
+/// A relation rewrite rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Userset {
+    /// Directly assigned tuples (`[...]` in the DSL).
+    This,
+    /// Another relation on the same object.
+    Computed(String),
+    /// `computed from tupleset`: follow `tupleset` and evaluate `computed` on the target.
+    TupleToUserset {
+        tupleset: String,
+        computed: String,
+    },
+    Union(Vec<Userset>),
+    Intersection(Vec<Userset>),
+    Difference {
+        base: Box<Userset>,
+        subtract: Box<Userset>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct RelationReference {
+    pub type_name: String,
+    pub relation: Option<String>,
+    pub wildcard: bool,
+}
+
+impl AuthorizationModel {
+    pub fn type_definition(&self, name: &str) -> Option<&TypeDefinition> {
+        self.types.iter().find(|t| t.name == name)
+    }
+}
+
+impl TypeDefinition {
+    pub fn relation(&self, name: &str) -> Option<&RelationDefinition> {
+        self.relations.iter().find(|r| r.name == name)
+    }
+}
+
+impl Userset {
+    /// Visits this userset and every nested child.
+    pub fn walk<'a>(&'a self, visit: &mut impl FnMut(&'a Userset)) {
+        visit(self);
+        match self {
+            Userset::Union(children) | Userset::Intersection(children) => {
+                children.iter().for_each(|c| c.walk(visit))
+            }
+            Userset::Difference { base, subtract } => {
+                base.walk(visit);
+                subtract.walk(visit);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl RelationReference {
+    pub fn display(&self) -> String {
+        if self.wildcard {
+            format!("{}:*", self.type_name)
+        } else if let Some(relation) = &self.relation {
+            format!("{}#{}", self.type_name, relation)
+        } else {
+            self.type_name.clone()
+        }
+    }
+}
+
+/// Model source formats understood by [`load_file`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModelFormat {
+    Dsl,
+    Json,
+}
+
+impl ModelFormat {
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => ModelFormat::Json,
+            _ => ModelFormat::Dsl,
+        }
+    }
+}
+
+pub fn parse(source: &str, format: ModelFormat) -> ModelResult<AuthorizationModel> {
+    match format {
+        ModelFormat::Dsl => parse_dsl(source),
+        ModelFormat::Json => {
+            from_json(&serde_json::from_str(source).map_err(|e| ModelError::Json(e.to_string()))?)
+        }
+    }
+}
+
+/// Reads a model file, picking the format from its extension (`.json` or DSL otherwise).
+pub fn load_file(path: &Path) -> ModelResult<(AuthorizationModel, ModelFormat)> {
+    let format = ModelFormat::from_path(path);
+    let source = std::fs::read_to_string(path)?;
+    Ok((parse(&source, format)?, format))
+}
@@ -0,0 +1,158 @@
+use super::{AuthorizationModel, ModelError, ModelResult, Userset, SCHEMA_VERSION};
+use std::collections::BTreeSet;
+
+/// Checks the semantic rules OpenFGA enforces when writing a model.
+pub fn validate(model: &AuthorizationModel) -> ModelResult<()> {
+    let errors = validation_errors(model);
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(ModelError::Invalid(errors))
+    }
+}
+
+fn validation_errors(model: &AuthorizationModel) -> Vec<String> {
+    let mut errors = Vec::new();
+
+    if model.schema_version != SCHEMA_VERSION {
+        errors.push(format!(
+            "unsupported schema version '{}', expected '{}'",
+            model.schema_version, SCHEMA_VERSION
+        ));
+    }
+
+    let mut type_names = BTreeSet::new();
+    for type_def in &model.types {
+        if !type_names.insert(type_def.name.as_str()) {
+            errors.push(format!(
+                "type '{}' is defined more than once",
+                type_def.name
+            ));
+        }
+    }
+
+    for type_def in &model.types {
+        let mut relation_names = BTreeSet::new();
+        for relation in &type_def.relations {
+            let at = format!("{}#{}", type_def.name, relation.name);
+            if !relation_names.insert(relation.name.as_str()) {
+                errors.push(format!("{}: relation is defined more than once", at));
+            }
+
+            let mut assignable = false;
+            relation.rewrite.walk(&mut |userset| match userset {
+                Userset::This => assignable = true,
+                Userset::Computed(target) if type_def.relation(target).is_none() => {
+                    errors.push(format!("{}: relation '{}' is not defined", at, target));
+                }
+                Userset::TupleToUserset { tupleset, computed } => {
+                    match type_def.relation(tupleset) {
+                        None => errors.push(format!(
+                            "{}: tupleset relation '{}' is not defined",
+                            at, tupleset
+                        )),
+                        Some(tupleset_def) => {
+                            if tupleset_def.directly_related.is_empty() {
+                                errors.push(format!(
+                                    "{}: tupleset relation '{}' must be directly assignable",
+                                    at, tupleset
+                                ));
+                            } else if tupleset_def
+                                .directly_related
+                                .iter()
+                                .any(|r| r.relation.is_some() || r.wildcard)
+                            {
+                                errors.push(format!(
+                                    "{}: tupleset relation '{}' may only reference plain types",
+                                    at, tupleset
+                                ));
+                            } else if !tupleset_def.directly_related.iter().any(|r| {
+                                model
+                                    .type_definition(&r.type_name)
+                                    .is_some_and(|t| t.relation(computed).is_some())
+                            }) {
+                                errors.push(format!(
+                                    "{}: relation '{}' is not defined on any type related through '{}'",
+                                    at, computed, tupleset
+                                ));
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            });
+
+            if assignable && relation.directly_related.is_empty() {
+                errors.push(format!(
+                    "{}: directly assignable relation has no allowed types",
+                    at
+                ));
+            }
+            if !assignable && !relation.directly_related.is_empty() {
+                errors.push(format!(
+                    "{}: allowed types given but relation is not directly assignable",
+                    at
+                ));
+            }
+
+            for reference in &relation.directly_related {
+                match model.type_definition(&reference.type_name) {
+                    None => errors.push(format!(
+                        "{}: type '{}' is not defined",
+                        at, reference.type_name
+                    )),
+                    Some(target) => {
+                        if let Some(target_relation) = &reference.relation {
+                            if target.relation(target_relation).is_none() {
+                                errors.push(format!(
+                                    "{}: relation '{}' is not defined on type '{}'",
+                                    at, target_relation, reference.type_name
+                                ));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::parse_dsl;
+
+    #[test]
+    fn test_valid_model() {
+        let model = parse_dsl(
+            "model\n  schema 1.1\ntype user\ntype group\n  relations\n    define member: [user, group#member]\n\
+             type doc\n  relations\n    define owner: [group]\n    define viewer: [user] or member from owner\n",
+        )
+        .unwrap();
+        assert!(validate(&model).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_references() {
+        let model = parse_dsl(
+            "model\n  schema 1.1\ntype user\ntype doc\n  relations\n    define owner: [user, team]\n\
+             \x20   define viewer: editor or viewer from owner\n    define parent: [user#member]\n",
+        )
+        .unwrap();
+
+        let errors = match validate(&model) {
+            Err(ModelError::Invalid(errors)) => errors,
+            other => panic!("expected validation errors, got {:?}", other),
+        };
+        assert!(errors.contains(&"doc#owner: type 'team' is not defined".to_string()));
+        assert!(errors.contains(&"doc#viewer: relation 'editor' is not defined".to_string()));
+        assert!(errors.contains(
+            &"doc#viewer: relation 'viewer' is not defined on any type related through 'owner'"
+                .to_string()
+        ));
+        assert!(errors
+            .contains(&"doc#parent: relation 'member' is not defined on type 'user'".to_string()));
+    }
+}
@@ -0,0 +1,1219 @@
+use crate::controller::{shutdown_requested, ControllerError, ControllerResult, Shutdown};
+use crate::instance_ref;
+use crate::metrics;
+use crate::model::{self, LintConfig, LintSeverity, ModelDiff, ModelFormat};
+use crate::openfga_client::OpenFGAClient;
+use crate::panic_isolation::isolate_panics;
+use crate::tuple_scan::{scan_compatibility, ScanConfig, StoreReader};
+use crate::types::{
+    AuthorizationModel, AuthorizationModelStatus, ModelAssertion, ModelVersion, OpenFGACondition,
+    TupleCompatibility,
+};
+use futures::StreamExt;
+use kube::api::{Api, ListParams, Patch, PatchParams};
+use kube::runtime::controller::{Action, Controller};
+use kube::runtime::events::{Event, EventType, Recorder};
+use kube::runtime::reflector::ObjectRef;
+use kube::runtime::watcher::Config;
+use kube::{Client, Resource, ResourceExt};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use tokio::time::Duration;
+use tracing::{debug, error, info, instrument, warn};
+
+const CONTROLLER_NAME: &str = "authorization-model-controller";
+
+pub struct AuthorizationModelController {
+    client: Client,
+}
+
+impl AuthorizationModelController {
+    pub fn new(client: Client) -> Self {
+        Self { client }
+    }
+
+    pub async fn run(self, shutdown: Shutdown) {
+        let models: Api<AuthorizationModel> = Api::all(self.client.clone());
+
+        info!(
+            controller = CONTROLLER_NAME,
+            "Starting controller with AuthorizationModel resource monitoring"
+        );
+
+        let _watches = metrics::track_watch_streams(CONTROLLER_NAME, 2);
+        Controller::new(models.clone(), Config::default().any_semantic())
+            // A module change re-validates the parent it composes into
+            .watches(models, Config::default().any_semantic(), |module| {
+                let ns = module.namespace()?;
+                module
+                    .spec
+                    .module_of
+                    .as_ref()
+                    .map(|parent| ObjectRef::new(parent).within(&ns))
+            })
+            .graceful_shutdown_on(shutdown_requested(shutdown.clone()))
+            .run(
+                |resource, ctx| {
+                    let guarded = isolate_panics(
+                        ctx.client.clone(),
+                        CONTROLLER_NAME,
+                        resource.clone(),
+                        reconcile(resource.clone(), ctx),
+                    );
+                    metrics::observe_reconcile(CONTROLLER_NAME, resource, guarded)
+                },
+                error_policy,
+                Arc::new(self),
+            )
+            .for_each(|res| async move {
+                match res {
+                    Ok(o) => {
+                        debug!(
+                            reconciliation_result = "success",
+                            object = ?o,
+                            "AuthorizationModel reconciliation completed successfully"
+                        );
+                    }
+                    Err(e) => {
+                        error!(
+                            reconciliation_result = "error",
+                            error = %e,
+                            "AuthorizationModel reconciliation failed"
+                        );
+                    }
+                }
+            })
+            .await;
+    }
+}
+
+/// Outcome of checking an AuthorizationModel spec, independent of the cluster.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelEvaluation {
+    pub valid: bool,
+    pub reason: String,
+    pub message: String,
+    pub lint_warnings: Vec<String>,
+    /// The parsed model, present once it passed validation.
+    pub model: Option<model::AuthorizationModel>,
+}
+
+impl ModelEvaluation {
+    fn invalid(reason: &str, message: String) -> Self {
+        Self {
+            valid: false,
+            reason: reason.to_string(),
+            message,
+            lint_warnings: vec![],
+            model: None,
+        }
+    }
+}
+
+/// Parses the model source of a spec, returning the condition reason and message on failure.
+fn parse_source(
+    resource: &AuthorizationModel,
+) -> Result<model::AuthorizationModel, (&'static str, String)> {
+    let spec = &resource.spec;
+    let parsed = match (&spec.dsl, &spec.json) {
+        (Some(dsl), None) => model::parse(dsl, ModelFormat::Dsl),
+        (None, Some(json)) => model::parse(json, ModelFormat::Json),
+        _ => {
+            return Err((
+                "MissingModel",
+                "exactly one of spec.dsl or spec.json must be set".to_string(),
+            ))
+        }
+    };
+    parsed.map_err(|e| ("ParseError", e.to_string()))
+}
+
+/// Parses, validates and lints the model in an AuthorizationModel spec, composed with
+/// the given modules. A resource that is itself a module is only parsed.
+pub fn evaluate_model(
+    resource: &AuthorizationModel,
+    modules: &[AuthorizationModel],
+) -> ModelEvaluation {
+    let spec = &resource.spec;
+    let config = match LintConfig::new(spec.lint_rules.clone()) {
+        Ok(config) => config,
+        Err(e) => return ModelEvaluation::invalid("InvalidLintConfig", e),
+    };
+    let mut findings = spec
+        .dsl
+        .as_deref()
+        .map(|dsl| model::deprecated_syntax(dsl, &config))
+        .unwrap_or_default();
+    let parsed = match parse_source(resource) {
+        Ok(parsed) => parsed,
+        Err((reason, message)) => {
+            return ModelEvaluation {
+                lint_warnings: findings.iter().map(|f| f.to_string()).collect(),
+                ..ModelEvaluation::invalid(reason, message)
+            }
+        }
+    };
+
+    if let Some(parent) = &spec.module_of {
+        return ModelEvaluation {
+            valid: true,
+            reason: "Module".to_string(),
+            message: format!(
+                "contributes {} type(s) to '{}', validated as part of it",
+                parsed.types.len(),
+                parent
+            ),
+            lint_warnings: vec![],
+            model: None,
+        };
+    }
+
+    let mut fragments = Vec::with_capacity(modules.len());
+    for module in modules {
+        match parse_source(module) {
+            Ok(fragment) => fragments.push((module.name_any(), fragment)),
+            Err((_, message)) => {
+                return ModelEvaluation::invalid(
+                    "ModuleInvalid",
+                    format!("module '{}': {}", module.name_any(), message),
+                )
+            }
+        }
+    }
+    let parsed = match model::compose(&parsed, &fragments) {
+        Ok(combined) => combined,
+        Err(e) => return ModelEvaluation::invalid("CompositionFailed", e.to_string()),
+    };
+    if let Err(e) = model::validate(&parsed) {
+        return ModelEvaluation::invalid("ValidationFailed", e.to_string());
+    }
+
+    findings.extend(model::lint(&parsed, &config));
+    let errors = findings
+        .iter()
+        .filter(|f| f.severity == LintSeverity::Error)
+        .count();
+    let lint_warnings: Vec<String> = findings.iter().map(|f| f.to_string()).collect();
+
+    if errors > 0 && spec.fail_on_lint_errors {
+        ModelEvaluation {
+            valid: false,
+            reason: "LintFailed".to_string(),
+            message: format!("{} lint finding(s) at error severity", errors),
+            lint_warnings,
+            model: Some(parsed),
+        }
+    } else {
+        ModelEvaluation {
+            valid: true,
+            reason: "Valid".to_string(),
+            message: if errors > 0 {
+                format!(
+                    "model is valid with {} lint finding(s), {} at error severity",
+                    lint_warnings.len(),
+                    errors
+                )
+            } else {
+                format!(
+                    "model is valid with {} lint finding(s)",
+                    lint_warnings.len()
+                )
+            },
+            lint_warnings,
+            model: Some(parsed),
+        }
+    }
+}
+
+/// Decides the `Compatible` condition from the last breaking change and its tuple scan.
+pub fn compatibility_condition(
+    breaking: bool,
+    scan: Option<&Result<TupleCompatibility, String>>,
+    allow_incompatible: bool,
+) -> (bool, &'static str, String) {
+    if !breaking {
+        return (
+            true,
+            "NoBreakingChange",
+            "model changes keep existing tuples valid".to_string(),
+        );
+    }
+
+    match scan {
+        None => (
+            true,
+            "StoreNotConfigured",
+            "spec.storeId is not set, existing tuples were not checked".to_string(),
+        ),
+        Some(Err(e)) => (false, "ScanFailed", e.clone()),
+        Some(Ok(report)) => {
+            let scope = if report.complete {
+                format!("{} tuples", report.scanned_tuples)
+            } else {
+                format!("a sample of {} tuples", report.scanned_tuples)
+            };
+            if report.incompatible_tuples == 0 {
+                (
+                    true,
+                    "NoIncompatibleTuples",
+                    format!("checked {}, all fit the model", scope),
+                )
+            } else if allow_incompatible {
+                (
+                    true,
+                    "Overridden",
+                    format!(
+                        "{} of {} no longer fit the model, allowed by spec.allowIncompatibleTuples",
+                        report.incompatible_tuples, scope
+                    ),
+                )
+            } else {
+                (
+                    false,
+                    "IncompatibleTuples",
+                    format!(
+                        "{} of {} no longer fit the model",
+                        report.incompatible_tuples, scope
+                    ),
+                )
+            }
+        }
+    }
+}
+
+fn model_hash(model_json: &str) -> String {
+    Sha256::digest(model_json.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Bound on `status.modelHistory`. Older versions stay in OpenFGA but can no longer be
+/// rolled back to through the resource.
+pub const MODEL_HISTORY_LIMIT: usize = 10;
+
+/// Appends a written model version, dropping the oldest beyond [`MODEL_HISTORY_LIMIT`].
+pub fn record_model_version(history: &[ModelVersion], version: ModelVersion) -> Vec<ModelVersion> {
+    let mut history = history.to_vec();
+    history.push(version);
+    let excess = history.len().saturating_sub(MODEL_HISTORY_LIMIT);
+    history.drain(..excess);
+    history
+}
+
+/// Resolves the model ID consumers should use and the `Active` condition. An unknown
+/// rollback target keeps the previously active model rather than jumping to the latest.
+pub fn active_model(
+    rollback_to: Option<&str>,
+    latest: Option<&str>,
+    previous_active: Option<&str>,
+    history: &[ModelVersion],
+) -> (Option<String>, bool, &'static str, String) {
+    match (rollback_to, latest) {
+        (Some(target), _) if history.iter().any(|v| v.id == target) => (
+            Some(target.to_string()),
+            true,
+            "RolledBack",
+            format!(
+                "rolled back to {}, latest is {}",
+                target,
+                latest.unwrap_or("unknown")
+            ),
+        ),
+        (Some(target), _) => (
+            previous_active.or(latest).map(str::to_string),
+            false,
+            "UnknownModelId",
+            format!("spec.rollbackTo '{}' is not in status.modelHistory", target),
+        ),
+        (None, Some(latest)) => (
+            Some(latest.to_string()),
+            true,
+            "Latest",
+            format!("consumers use the latest model {}", latest),
+        ),
+        (None, None) => (
+            None,
+            false,
+            "NotWritten",
+            "no model version has been written yet".to_string(),
+        ),
+    }
+}
+
+/// Client for the OpenFGA instance the resource refers to.
+async fn instance_client(
+    client: &Client,
+    resource: &AuthorizationModel,
+) -> Result<OpenFGAClient, String> {
+    let ns = resource.namespace().unwrap_or_default();
+    let reference = &resource.spec.instance_ref;
+    let instance = instance_ref::resolve(client, &ns, "AuthorizationModel", reference)
+        .await
+        .map_err(|e| {
+            format!(
+                "failed to get OpenFGA instance {}: {}",
+                instance_ref::describe(reference),
+                e
+            )
+        })??;
+    Ok(OpenFGAClient::for_instance(&instance))
+}
+
+async fn write_model(
+    client: &Client,
+    resource: &AuthorizationModel,
+    store_id: &str,
+    model: &serde_json::Value,
+) -> Result<String, String> {
+    instance_client(client, resource)
+        .await?
+        .write_authorization_model(store_id, model)
+        .await
+        .map_err(|e| format!("failed to write model to store '{}': {}", store_id, e))
+}
+
+/// Checks every assertion against `model_id`, in spec order.
+async fn run_assertions(
+    client: &Client,
+    resource: &AuthorizationModel,
+    store_id: &str,
+    model_id: &str,
+) -> Vec<Result<bool, String>> {
+    let openfga = match instance_client(client, resource).await {
+        Ok(openfga) => openfga,
+        Err(e) => return vec![Err(e); resource.spec.assertions.len()],
+    };
+    let mut results = Vec::with_capacity(resource.spec.assertions.len());
+    for assertion in &resource.spec.assertions {
+        results.push(
+            openfga
+                .check(store_id, model_id, &assertion.tuple())
+                .await
+                .map_err(|e| e.to_string()),
+        );
+    }
+    results
+}
+
+/// Decides the `AssertionsPassed` condition from the check results, one per
+/// assertion. Returns the condition and the failing assertions with their outcome.
+pub fn assertions_condition(
+    assertions: &[ModelAssertion],
+    results: &[Result<bool, String>],
+) -> (bool, &'static str, String, Vec<String>) {
+    let mut failed = Vec::new();
+    let mut errors = 0;
+    for (assertion, result) in assertions.iter().zip(results) {
+        match result {
+            Ok(allowed) if *allowed == assertion.expected => {}
+            Ok(allowed) => failed.push(format!(
+                "{}: expected {}, got {}",
+                assertion.tuple(),
+                assertion.expected,
+                allowed
+            )),
+            Err(e) => {
+                errors += 1;
+                failed.push(format!("{}: check failed: {}", assertion.tuple(), e));
+            }
+        }
+    }
+
+    if failed.is_empty() {
+        (
+            true,
+            "Passed",
+            format!("{} assertion(s) passed", assertions.len()),
+            failed,
+        )
+    } else if errors == failed.len() {
+        (
+            false,
+            "CheckFailed",
+            format!(
+                "{} of {} check(s) could not be run",
+                errors,
+                assertions.len()
+            ),
+            failed,
+        )
+    } else {
+        (
+            false,
+            "AssertionsFailed",
+            format!(
+                "{} of {} assertion(s) failed",
+                failed.len() - errors,
+                assertions.len()
+            ),
+            failed,
+        )
+    }
+}
+
+/// Scans the store behind the referenced instance for tuples `current` no longer accepts.
+async fn scan_store(
+    client: &Client,
+    resource: &AuthorizationModel,
+    store_id: &str,
+    current: &model::AuthorizationModel,
+    model_hash: &str,
+) -> Result<TupleCompatibility, String> {
+    let openfga = instance_client(client, resource).await?;
+    let reader = StoreReader {
+        client: &openfga,
+        store_id,
+    };
+    let report = scan_compatibility(&reader, current, &ScanConfig::default())
+        .await
+        .map_err(|e| format!("failed to read tuples from store '{}': {}", store_id, e))?;
+
+    Ok(TupleCompatibility {
+        model_hash: model_hash.to_string(),
+        scanned_tuples: report.scanned as i64,
+        incompatible_tuples: report.incompatible as i64,
+        examples: report.examples,
+        complete: report.complete,
+    })
+}
+
+/// Modules of the AuthorizationModel `parent`, sorted by name so composition is stable.
+pub fn modules_of(models: &[AuthorizationModel], parent: &str) -> Vec<AuthorizationModel> {
+    let mut modules: Vec<AuthorizationModel> = models
+        .iter()
+        .filter(|m| m.spec.module_of.as_deref() == Some(parent))
+        .cloned()
+        .collect();
+    modules.sort_by_key(|m| m.name_any());
+    modules
+}
+
+/// Diffs the current model against the last valid one recorded in status. Returns `None`
+/// when there is no usable previous model or nothing changed.
+pub fn model_change(
+    previous: Option<&str>,
+    current: &model::AuthorizationModel,
+) -> Option<ModelDiff> {
+    let previous = previous
+        .and_then(|json| serde_json::from_str(json).ok())
+        .and_then(|value| model::from_json(&value).ok())?;
+    let diff = model::diff(&previous, current);
+    (!diff.is_empty()).then_some(diff)
+}
+
+#[instrument(skip(ctx), fields(namespace = %resource.namespace().unwrap_or_default(), name = %resource.name_any(), generation = resource.metadata.generation.unwrap_or_default()))]
+async fn reconcile(
+    resource: Arc<AuthorizationModel>,
+    ctx: Arc<AuthorizationModelController>,
+) -> ControllerResult<Action> {
+    let ns = resource.namespace().unwrap_or_default();
+    let name = resource.name_any();
+
+    info!(
+        event = "model_reconciliation_start",
+        namespace = %ns,
+        resource_name = %name,
+        instance = %instance_ref::describe(&resource.spec.instance_ref),
+        "Starting AuthorizationModel reconciliation"
+    );
+
+    let models: Api<AuthorizationModel> = Api::namespaced(ctx.client.clone(), &ns);
+    let modules = if resource.spec.module_of.is_none() {
+        modules_of(&models.list(&ListParams::default()).await?.items, &name)
+    } else {
+        vec![]
+    };
+
+    let evaluation = evaluate_model(&resource, &modules);
+    if evaluation.valid {
+        debug!(
+            event = "model_valid",
+            namespace = %ns,
+            resource_name = %name,
+            lint_findings = evaluation.lint_warnings.len(),
+            "Authorization model is valid"
+        );
+    } else {
+        warn!(
+            event = "model_invalid",
+            namespace = %ns,
+            resource_name = %name,
+            reason = %evaluation.reason,
+            message = %evaluation.message,
+            "Authorization model is invalid"
+        );
+    }
+
+    let previous_status = resource.status.clone().unwrap_or_default();
+    if !evaluation.lint_warnings.is_empty()
+        && previous_status.lint_warnings.as_ref() != Some(&evaluation.lint_warnings)
+    {
+        publish_lint_findings(&ctx.client, &resource, &evaluation).await;
+    }
+    let previous_conditions = previous_status.conditions.as_deref();
+    let mut conditions = vec![OpenFGACondition::new(
+        "Valid",
+        evaluation.valid,
+        &evaluation.reason,
+        &evaluation.message,
+        resource.metadata.generation,
+        previous_conditions,
+    )];
+    let mut status = AuthorizationModelStatus {
+        conditions: None,
+        lint_warnings: Some(evaluation.lint_warnings),
+        last_valid_model: previous_status.last_valid_model.clone(),
+        model_diff: previous_status.model_diff.clone(),
+        breaking_change: previous_status.breaking_change,
+        compatibility: previous_status.compatibility.clone(),
+        modules: (!modules.is_empty()).then(|| modules.iter().map(|m| m.name_any()).collect()),
+        model_id: previous_status.model_id.clone(),
+        active_model_id: previous_status.active_model_id.clone(),
+        model_history: previous_status.model_history.clone(),
+        failed_assertions: previous_status.failed_assertions.clone(),
+    };
+
+    // Only models that passed validation move the diff base forward
+    match evaluation.model.as_ref().filter(|_| evaluation.valid) {
+        Some(current) => {
+            if let Some(diff) = model_change(previous_status.last_valid_model.as_deref(), current) {
+                publish_model_change(&ctx.client, &resource, &diff).await;
+                status.model_diff = Some(diff.changes.iter().map(|c| c.to_string()).collect());
+                status.breaking_change = Some(diff.breaking_change());
+            }
+            let model_value = model::to_json(current);
+            let model_json = model_value.to_string();
+            let hash = model_hash(&model_json);
+            status.last_valid_model = Some(model_json);
+
+            let breaking = status.breaking_change == Some(true);
+            let scan = match (&resource.spec.store_id, &previous_status.compatibility) {
+                _ if !breaking => None,
+                (_, Some(previous)) if previous.model_hash == hash => Some(Ok(previous.clone())),
+                (Some(store_id), _) => {
+                    Some(scan_store(&ctx.client, &resource, store_id, current, &hash).await)
+                }
+                (None, _) => None,
+            };
+            status.compatibility = scan.clone().and_then(Result::ok);
+
+            let (compatible, reason, message) = compatibility_condition(
+                breaking,
+                scan.as_ref(),
+                resource.spec.allow_incompatible_tuples,
+            );
+            if !compatible {
+                warn!(
+                    event = "model_incompatible_tuples",
+                    namespace = %ns,
+                    resource_name = %name,
+                    reason = %reason,
+                    message = %message,
+                    "Existing tuples block promotion of the authorization model"
+                );
+            }
+            conditions.push(OpenFGACondition::new(
+                "Compatible",
+                compatible,
+                reason,
+                &message,
+                resource.metadata.generation,
+                previous_conditions,
+            ));
+
+            if let Some(store_id) = &resource.spec.store_id {
+                let history = previous_status.model_history.clone().unwrap_or_default();
+                let written = history.last().is_some_and(|v| v.model_hash == hash);
+                let (applied, reason, message) = if !compatible {
+                    (
+                        false,
+                        "Incompatible",
+                        "not written while existing tuples no longer fit the model".to_string(),
+                    )
+                } else if written {
+                    (
+                        true,
+                        "UpToDate",
+                        format!("model is stored as {}", history[history.len() - 1].id),
+                    )
+                } else {
+                    match write_model(&ctx.client, &resource, store_id, &model_value).await {
+                        Ok(id) => {
+                            info!(
+                                event = "model_written",
+                                namespace = %ns,
+                                resource_name = %name,
+                                store_id = %store_id,
+                                model_id = %id,
+                                "Wrote authorization model version"
+                            );
+                            status.model_id = Some(id.clone());
+                            status.model_history = Some(record_model_version(
+                                &history,
+                                ModelVersion {
+                                    id: id.clone(),
+                                    model_hash: hash.clone(),
+                                    written_time: chrono::Utc::now().to_rfc3339(),
+                                },
+                            ));
+                            (true, "Written", format!("model written as {}", id))
+                        }
+                        Err(e) => {
+                            warn!(
+                                event = "model_write_failed",
+                                namespace = %ns,
+                                resource_name = %name,
+                                store_id = %store_id,
+                                error = %e,
+                                "Failed to write authorization model"
+                            );
+                            (false, "WriteFailed", e)
+                        }
+                    }
+                };
+                conditions.push(OpenFGACondition::new(
+                    "Applied",
+                    applied,
+                    reason,
+                    &message,
+                    resource.metadata.generation,
+                    previous_conditions,
+                ));
+
+                match status.model_id.as_deref() {
+                    Some(model_id) if applied && !resource.spec.assertions.is_empty() => {
+                        let results =
+                            run_assertions(&ctx.client, &resource, store_id, model_id).await;
+                        let (passed, reason, message, failed) =
+                            assertions_condition(&resource.spec.assertions, &results);
+                        if !passed {
+                            warn!(
+                                event = "model_assertions_failed",
+                                namespace = %ns,
+                                resource_name = %name,
+                                model_id = %model_id,
+                                failed = failed.len(),
+                                "Authorization model fails its assertions"
+                            );
+                        }
+                        status.failed_assertions = Some(failed);
+                        conditions.push(OpenFGACondition::new(
+                            "AssertionsPassed",
+                            passed,
+                            reason,
+                            &message,
+                            resource.metadata.generation,
+                            previous_conditions,
+                        ));
+                    }
+                    _ => {}
+                }
+            }
+        }
+        None => {
+            conditions.extend(
+                previous_conditions
+                    .into_iter()
+                    .flatten()
+                    .find(|c| c.type_ == "Compatible")
+                    .cloned(),
+            );
+        }
+    }
+
+    if resource.spec.store_id.is_some() && resource.spec.module_of.is_none() {
+        let history = status.model_history.clone().unwrap_or_default();
+        let (active, ok, reason, message) = active_model(
+            resource.spec.rollback_to.as_deref(),
+            status.model_id.as_deref(),
+            previous_status.active_model_id.as_deref(),
+            &history,
+        );
+        if !ok {
+            warn!(
+                event = "model_rollback_invalid",
+                namespace = %ns,
+                resource_name = %name,
+                message = %message,
+                "Rollback target is not a known model version"
+            );
+        }
+        status.active_model_id = active;
+        conditions.push(OpenFGACondition::new(
+            "Active",
+            ok,
+            reason,
+            &message,
+            resource.metadata.generation,
+            previous_conditions,
+        ));
+    }
+    status.conditions = Some(conditions);
+
+    models
+        .patch_status(
+            &name,
+            &PatchParams::default(),
+            &Patch::Merge(&serde_json::json!({ "status": status })),
+        )
+        .await?;
+
+    Ok(Action::requeue(Duration::from_secs(300)))
+}
+
+async fn publish_model_change(client: &Client, resource: &AuthorizationModel, diff: &ModelDiff) {
+    let ns = resource.namespace().unwrap_or_default();
+    let name = resource.name_any();
+    let breaking = diff.breaking_change();
+
+    if breaking {
+        warn!(
+            event = "model_breaking_change",
+            namespace = %ns,
+            resource_name = %name,
+            changes = %diff.summary(),
+            "Authorization model change may invalidate existing tuples"
+        );
+    } else {
+        info!(
+            event = "model_changed",
+            namespace = %ns,
+            resource_name = %name,
+            changes = %diff.summary(),
+            "Authorization model changed"
+        );
+    }
+
+    let note = event_note(diff.changes.iter().map(|c| c.to_string()));
+
+    let recorder = Recorder::new(
+        client.clone(),
+        CONTROLLER_NAME.into(),
+        resource.object_ref(&()),
+    );
+    let event = Event {
+        type_: if breaking {
+            EventType::Warning
+        } else {
+            EventType::Normal
+        },
+        reason: if breaking {
+            "BreakingModelChange"
+        } else {
+            "ModelChanged"
+        }
+        .to_string(),
+        note: Some(note),
+        action: "Diff".to_string(),
+        secondary: None,
+    };
+    if let Err(e) = recorder.publish(event).await {
+        warn!(
+            event = "model_change_event_failed",
+            namespace = %ns,
+            resource_name = %name,
+            error = %e,
+            "Failed to publish model change event"
+        );
+    }
+}
+
+/// Publishes the lint findings of a model as one Event, a Warning when any
+/// finding is at warning severity or above.
+async fn publish_lint_findings(
+    client: &Client,
+    resource: &AuthorizationModel,
+    evaluation: &ModelEvaluation,
+) {
+    let ns = resource.namespace().unwrap_or_default();
+    let name = resource.name_any();
+    let severe = evaluation
+        .lint_warnings
+        .iter()
+        .any(|w| w.starts_with("[warning]") || w.starts_with("[error]"));
+
+    let recorder = Recorder::new(
+        client.clone(),
+        CONTROLLER_NAME.into(),
+        resource.object_ref(&()),
+    );
+    let event = Event {
+        type_: if severe {
+            EventType::Warning
+        } else {
+            EventType::Normal
+        },
+        reason: if evaluation.reason == "LintFailed" {
+            "LintFailed"
+        } else {
+            "LintWarnings"
+        }
+        .to_string(),
+        note: Some(event_note(evaluation.lint_warnings.iter().cloned())),
+        action: "Lint".to_string(),
+        secondary: None,
+    };
+    if let Err(e) = recorder.publish(event).await {
+        warn!(
+            event = "model_lint_event_failed",
+            namespace = %ns,
+            resource_name = %name,
+            error = %e,
+            "Failed to publish lint event"
+        );
+    }
+}
+
+/// Joins entries into an Event note, truncated to the 1kB the API server accepts.
+fn event_note(entries: impl Iterator<Item = String>) -> String {
+    let mut note = entries.collect::<Vec<_>>().join("; ");
+    if note.len() > 1000 {
+        let mut end = 997;
+        while !note.is_char_boundary(end) {
+            end -= 1;
+        }
+        note.truncate(end);
+        note.push_str("...");
+    }
+    note
+}
+
+fn error_policy(
+    resource: Arc<AuthorizationModel>,
+    error: &ControllerError,
+    _ctx: Arc<AuthorizationModelController>,
+) -> Action {
+    warn!(
+        event = "model_reconciliation_error",
+        namespace = %resource.namespace().unwrap_or_default(),
+        resource_name = %resource.name_any(),
+        error_message = %error,
+        "AuthorizationModel reconciliation failed, retrying"
+    );
+    metrics::record_reconcile_error::<AuthorizationModel>(CONTROLLER_NAME, error.kind());
+    Action::requeue(Duration::from_secs(30))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{AuthorizationModelSpec, InstanceReference};
+    use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+    use std::collections::BTreeMap;
+
+    fn resource(dsl: Option<&str>, json: Option<&str>) -> AuthorizationModel {
+        AuthorizationModel {
+            metadata: ObjectMeta {
+                name: Some("test-model".to_string()),
+                namespace: Some("test-ns".to_string()),
+                ..Default::default()
+            },
+            spec: AuthorizationModelSpec {
+                instance_ref: InstanceReference {
+                    name: "test-openfga".to_string(),
+                    selector: BTreeMap::new(),
+                    namespace: None,
+                },
+                dsl: dsl.map(str::to_string),
+                json: json.map(str::to_string),
+                lint_rules: BTreeMap::new(),
+                fail_on_lint_errors: true,
+                store_id: None,
+                allow_incompatible_tuples: false,
+                module_of: None,
+                rollback_to: None,
+                assertions: vec![],
+            },
+            status: None,
+        }
+    }
+
+    #[test]
+    fn test_evaluate_valid_model_with_warnings() {
+        let evaluation = evaluate_model(&resource(
+            Some("model\n  schema 1.1\ntype user\ntype doc\n  relations\n    define viewer: [user:*]\n"),
+            None,
+        ), &[]);
+
+        assert!(evaluation.valid);
+        assert_eq!(evaluation.reason, "Valid");
+        assert_eq!(evaluation.lint_warnings.len(), 1);
+        assert!(evaluation.lint_warnings[0].contains("public-wildcard"));
+    }
+
+    #[test]
+    fn test_evaluate_invalid_models() {
+        assert_eq!(
+            evaluate_model(&resource(None, None), &[]).reason,
+            "MissingModel"
+        );
+        assert_eq!(
+            evaluate_model(&resource(Some("type user"), None), &[]).reason,
+            "ParseError"
+        );
+        assert_eq!(
+            evaluate_model(&resource(
+                None,
+                Some(r#"{"schema_version":"1.1","type_definitions":[{"type":"doc","relations":{"viewer":{"this":{}}},"metadata":{"relations":{"viewer":{"directly_related_user_types":[{"type":"user"}]}}}}]}"#)
+            ), &[])
+            .reason,
+            "ValidationFailed"
+        );
+
+        let mut strict = resource(
+            Some("model\n  schema 1.1\ntype user\ntype doc\n  relations\n    define viewer: [user:*]\n"),
+            None,
+        );
+        strict
+            .spec
+            .lint_rules
+            .insert("public-wildcard".to_string(), LintSeverity::Error);
+        let evaluation = evaluate_model(&strict, &[]);
+        assert!(!evaluation.valid);
+        assert_eq!(evaluation.reason, "LintFailed");
+
+        strict.spec.fail_on_lint_errors = false;
+        let evaluation = evaluate_model(&strict, &[]);
+        assert!(evaluation.valid);
+        assert_eq!(
+            evaluation.message,
+            "model is valid with 1 lint finding(s), 1 at error severity"
+        );
+
+        let legacy = evaluate_model(
+            &resource(
+                Some("model\n  schema 1.0\ntype user\ntype doc\n  relations\n    define viewer as self\n"),
+                None,
+            ),
+            &[],
+        );
+        assert_eq!(legacy.reason, "ParseError");
+        assert_eq!(legacy.lint_warnings.len(), 2);
+        assert!(legacy
+            .lint_warnings
+            .iter()
+            .all(|w| w.contains("deprecated-syntax")));
+    }
+
+    #[test]
+    fn test_evaluate_modules() {
+        let base = resource(
+            Some(
+                "model\n  schema 1.1\ntype user\ntype doc\n  relations\n    define owner: [user]\n",
+            ),
+            None,
+        );
+        let module = |name: &str, dsl: &str| {
+            let mut module = resource(Some(dsl), None);
+            module.metadata.name = Some(name.to_string());
+            module.spec.module_of = Some("test-model".to_string());
+            module
+        };
+        let sharing = module(
+            "sharing",
+            "model\n  schema 1.1\ntype doc\n  relations\n    define viewer: [user] or owner\n",
+        );
+        let evaluation = evaluate_model(&sharing, &[]);
+        assert!(evaluation.valid);
+        assert_eq!(evaluation.reason, "Module");
+        assert!(evaluation.model.is_none());
+
+        let evaluation = evaluate_model(&base, std::slice::from_ref(&sharing));
+        assert!(evaluation.valid, "{}", evaluation.message);
+        let combined = evaluation.model.unwrap();
+        assert!(combined
+            .type_definition("doc")
+            .unwrap()
+            .relation("viewer")
+            .is_some());
+
+        let conflicting = module(
+            "conflicting",
+            "model\n  schema 1.1\ntype doc\n  relations\n    define owner: [user]\n",
+        );
+        assert_eq!(
+            evaluate_model(&base, &[sharing, conflicting]).reason,
+            "CompositionFailed"
+        );
+        let dangling = module(
+            "dangling",
+            "model\n  schema 1.1\ntype folder\n  relations\n    define viewer: [team]\n",
+        );
+        assert_eq!(
+            evaluate_model(&base, &[dangling]).reason,
+            "ValidationFailed"
+        );
+        assert_eq!(
+            evaluate_model(&base, &[module("broken", "type doc")]).reason,
+            "ModuleInvalid"
+        );
+    }
+
+    #[test]
+    fn test_modules_of() {
+        let mut other = resource(None, None);
+        other.metadata.name = Some("b".to_string());
+        other.spec.module_of = Some("test-model".to_string());
+        let mut first = other.clone();
+        first.metadata.name = Some("a".to_string());
+        let mut unrelated = other.clone();
+        unrelated.spec.module_of = Some("elsewhere".to_string());
+
+        let modules = modules_of(
+            &[other, resource(None, None), unrelated, first],
+            "test-model",
+        );
+        let names: Vec<String> = modules.iter().map(|m| m.name_any()).collect();
+        assert_eq!(names, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_record_model_version() {
+        let version = |i: usize| ModelVersion {
+            id: format!("01M{}", i),
+            model_hash: format!("hash-{}", i),
+            written_time: "2024-01-01T00:00:00Z".to_string(),
+        };
+        let mut history = vec![];
+        for i in 0..MODEL_HISTORY_LIMIT + 2 {
+            history = record_model_version(&history, version(i));
+        }
+        assert_eq!(history.len(), MODEL_HISTORY_LIMIT);
+        assert_eq!(history[0].id, "01M2");
+        assert_eq!(history[MODEL_HISTORY_LIMIT - 1].id, "01M11");
+    }
+
+    #[test]
+    fn test_active_model() {
+        let history = vec![
+            ModelVersion {
+                id: "01OLD".to_string(),
+                model_hash: "a".to_string(),
+                written_time: "2024-01-01T00:00:00Z".to_string(),
+            },
+            ModelVersion {
+                id: "01NEW".to_string(),
+                model_hash: "b".to_string(),
+                written_time: "2024-01-02T00:00:00Z".to_string(),
+            },
+        ];
+
+        let (active, ok, reason, _) = active_model(None, Some("01NEW"), None, &history);
+        assert_eq!(
+            (active.as_deref(), ok, reason),
+            (Some("01NEW"), true, "Latest")
+        );
+
+        let (active, ok, reason, message) =
+            active_model(Some("01OLD"), Some("01NEW"), Some("01NEW"), &history);
+        assert_eq!(
+            (active.as_deref(), ok, reason),
+            (Some("01OLD"), true, "RolledBack")
+        );
+        assert_eq!(message, "rolled back to 01OLD, latest is 01NEW");
+
+        // An unknown target keeps whatever consumers were already using
+        let (active, ok, reason, _) =
+            active_model(Some("01GONE"), Some("01NEW"), Some("01OLD"), &history);
+        assert_eq!(
+            (active.as_deref(), ok, reason),
+            (Some("01OLD"), false, "UnknownModelId")
+        );
+
+        let (active, ok, reason, _) = active_model(None, None, None, &[]);
+        assert_eq!((active, ok, reason), (None, false, "NotWritten"));
+    }
+
+    #[test]
+    fn test_assertions_condition() {
+        let assertion = |user: &str, expected: bool| ModelAssertion {
+            user: user.to_string(),
+            relation: "viewer".to_string(),
+            object: "doc:readme".to_string(),
+            expected,
+        };
+        let assertions = vec![assertion("user:anne", true), assertion("user:bob", false)];
+
+        let (passed, reason, message, failed) =
+            assertions_condition(&assertions, &[Ok(true), Ok(false)]);
+        assert!(passed);
+        assert_eq!(reason, "Passed");
+        assert_eq!(message, "2 assertion(s) passed");
+        assert!(failed.is_empty());
+
+        let (passed, reason, message, failed) =
+            assertions_condition(&assertions, &[Ok(true), Ok(true)]);
+        assert!(!passed);
+        assert_eq!(reason, "AssertionsFailed");
+        assert_eq!(message, "1 of 2 assertion(s) failed");
+        assert_eq!(
+            failed,
+            vec!["doc:readme#viewer@user:bob: expected false, got true"]
+        );
+
+        let unreachable = || Err("connection refused".to_string());
+        let (passed, reason, _, failed) =
+            assertions_condition(&assertions, &[unreachable(), unreachable()]);
+        assert!(!passed);
+        assert_eq!(reason, "CheckFailed");
+        assert_eq!(failed.len(), 2);
+    }
+
+    #[test]
+    fn test_compatibility_condition() {
+        let report = |incompatible| TupleCompatibility {
+            model_hash: "abc".to_string(),
+            scanned_tuples: 50,
+            incompatible_tuples: incompatible,
+            examples: vec![],
+            complete: true,
+        };
+
+        assert_eq!(
+            compatibility_condition(false, None, false).1,
+            "NoBreakingChange"
+        );
+        assert_eq!(
+            compatibility_condition(true, None, false).1,
+            "StoreNotConfigured"
+        );
+        assert_eq!(
+            compatibility_condition(true, Some(&Err("down".to_string())), false),
+            (false, "ScanFailed", "down".to_string())
+        );
+        assert_eq!(
+            compatibility_condition(true, Some(&Ok(report(0))), false).1,
+            "NoIncompatibleTuples"
+        );
+        assert_eq!(
+            compatibility_condition(true, Some(&Ok(report(3))), false),
+            (
+                false,
+                "IncompatibleTuples",
+                "3 of 50 tuples no longer fit the model".to_string()
+            )
+        );
+        let (compatible, reason, _) = compatibility_condition(true, Some(&Ok(report(3))), true);
+        assert!(compatible);
+        assert_eq!(reason, "Overridden");
+    }
+
+    #[test]
+    fn test_model_change_against_last_valid_model() {
+        let old = model::parse_dsl(
+            "model\n  schema 1.1\ntype user\ntype doc\n  relations\n    define viewer: [user]\n",
+        )
+        .unwrap();
+        let new = model::parse_dsl("model\n  schema 1.1\ntype user\ntype doc\n").unwrap();
+        let previous = model::to_json(&old).to_string();
+
+        assert!(model_change(None, &new).is_none());
+        assert!(model_change(Some("not json"), &new).is_none());
+        assert!(model_change(Some(&previous), &old).is_none());
+
+        let diff = model_change(Some(&previous), &new).unwrap();
+        assert!(diff.breaking_change());
+        assert_eq!(diff.changes[0].location, "doc#viewer");
+    }
+}
@@ -0,0 +1,225 @@
+use crate::apply;
+use crate::controller::ControllerResult;
+use crate::ingress::is_owned_by;
+use crate::labels;
+use crate::types::{MonitorConfig, MonitorKind, OpenFGA};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use kube::api::{Api, DeleteParams, DynamicObject, Patch};
+use kube::core::{ApiResource, GroupVersionKind};
+use kube::{Client, Resource, ResourceExt};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use tracing::{debug, info, instrument, warn};
+
+/// Named container/Service port OpenFGA serves `/metrics` on.
+const METRICS_PORT_NAME: &str = "metrics";
+
+fn selector_labels(name: &str) -> BTreeMap<String, String> {
+    BTreeMap::from([
+        ("app.kubernetes.io/name".to_string(), "openfga".to_string()),
+        ("app.kubernetes.io/instance".to_string(), name.to_string()),
+    ])
+}
+
+pub fn monitor_resource(kind: MonitorKind) -> Option<ApiResource> {
+    let (kind, plural) = match kind {
+        MonitorKind::ServiceMonitor => ("ServiceMonitor", "servicemonitors"),
+        MonitorKind::PodMonitor => ("PodMonitor", "podmonitors"),
+        MonitorKind::None => return None,
+    };
+    Some(ApiResource::from_gvk_with_plural(
+        &GroupVersionKind::gvk("monitoring.coreos.com", "v1", kind),
+        plural,
+    ))
+}
+
+/// `prometheus.io/*` pod annotations for annotation-based scrape discovery,
+/// used when metrics are enabled without a Prometheus Operator monitor.
+pub fn scrape_annotations(openfga: &OpenFGA) -> Vec<(String, String)> {
+    let metrics = &openfga.spec.observability.metrics;
+    if !metrics.enabled || metrics.monitor.kind != MonitorKind::None {
+        return vec![];
+    }
+    vec![
+        ("prometheus.io/scrape".to_string(), "true".to_string()),
+        ("prometheus.io/port".to_string(), metrics.port.to_string()),
+        ("prometheus.io/path".to_string(), "/metrics".to_string()),
+    ]
+}
+
+/// Builds the ServiceMonitor or PodMonitor scraping the instance's metrics
+/// port, or `None` when metrics are disabled or no monitor is wanted.
+pub fn create_monitor(openfga: &OpenFGA, ns: &str, name: &str) -> Option<DynamicObject> {
+    let metrics = &openfga.spec.observability.metrics;
+    if !metrics.enabled {
+        return None;
+    }
+    let config = &metrics.monitor;
+    let resource = monitor_resource(config.kind)?;
+
+    let endpoints = vec![endpoint(config)];
+    let spec = match config.kind {
+        MonitorKind::PodMonitor => json!({
+            "selector": { "matchLabels": selector_labels(name) },
+            "podMetricsEndpoints": endpoints,
+        }),
+        _ => json!({
+            "selector": { "matchLabels": selector_labels(name) },
+            "endpoints": endpoints,
+        }),
+    };
+
+    let mut labels = labels::instance_labels(openfga, name);
+    labels.extend(config.labels.clone());
+
+    let mut monitor = DynamicObject::new(name, &resource).within(ns);
+    monitor.metadata = ObjectMeta {
+        name: Some(name.to_string()),
+        namespace: Some(ns.to_string()),
+        labels: Some(labels),
+        annotations: labels::instance_annotations(openfga, []),
+        owner_references: openfga.controller_owner_ref(&()).map(|o| vec![o]),
+        ..Default::default()
+    };
+    monitor.data = json!({ "spec": spec });
+    Some(monitor)
+}
+
+fn endpoint(config: &MonitorConfig) -> Value {
+    let mut endpoint = json!({ "port": METRICS_PORT_NAME, "path": "/metrics" });
+    if let Some(interval) = &config.interval {
+        endpoint["interval"] = json!(interval);
+    }
+    if let Some(timeout) = &config.scrape_timeout {
+        endpoint["scrapeTimeout"] = json!(timeout);
+    }
+    endpoint
+}
+
+/// Applies the configured monitor and removes monitors of the other kind. A
+/// cluster without the Prometheus Operator CRDs is logged and otherwise ignored.
+#[instrument(skip(client, openfga), fields(namespace = %ns, name = %name))]
+pub async fn reconcile_monitor(
+    client: &Client,
+    openfga: &OpenFGA,
+    ns: &str,
+    name: &str,
+) -> ControllerResult<()> {
+    let desired = create_monitor(openfga, ns, name);
+    let desired_kind = desired
+        .as_ref()
+        .map(|_| openfga.spec.observability.metrics.monitor.kind);
+
+    if let Some((monitor, resource)) = desired.zip(desired_kind.and_then(monitor_resource)) {
+        let api: Api<DynamicObject> = Api::namespaced_with(client.clone(), ns, &resource);
+        match api
+            .patch(name, &apply::apply_params(), &Patch::Apply(&monitor))
+            .await
+        {
+            Ok(_) => info!(
+                event = "monitor_applied",
+                namespace = %ns,
+                resource_name = %name,
+                kind = %resource.kind,
+                "Applied Prometheus monitor"
+            ),
+            Err(kube::Error::Api(e)) if e.code == 404 => warn!(
+                event = "monitor_crd_missing",
+                namespace = %ns,
+                resource_name = %name,
+                kind = %resource.kind,
+                "Prometheus Operator CRDs are not installed, skipping monitor"
+            ),
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    for kind in [MonitorKind::ServiceMonitor, MonitorKind::PodMonitor] {
+        if Some(kind) == desired_kind {
+            continue;
+        }
+        let Some(resource) = monitor_resource(kind) else {
+            continue;
+        };
+        let api: Api<DynamicObject> = Api::namespaced_with(client.clone(), ns, &resource);
+        // get_opt also fails when the Prometheus Operator CRDs are not installed
+        if let Ok(Some(existing)) = api.get_opt(name).await {
+            if is_owned_by(existing.owner_references(), openfga) {
+                api.delete(name, &DeleteParams::default()).await?;
+                debug!(
+                    event = "monitor_deleted",
+                    namespace = %ns,
+                    resource_name = %name,
+                    kind = %resource.kind,
+                    "Deleted Prometheus monitor that is no longer configured"
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::test_openfga;
+
+    fn openfga(metrics: Value) -> OpenFGA {
+        test_openfga(json!({ "observability": { "metrics": metrics } }))
+    }
+
+    #[test]
+    fn test_no_monitor_unless_enabled() {
+        assert!(create_monitor(&openfga(json!({})), "ns", "authz").is_none());
+        let none = openfga(json!({ "enabled": true, "monitor": { "kind": "None" } }));
+        assert!(create_monitor(&none, "ns", "authz").is_none());
+    }
+
+    #[test]
+    fn test_scrape_annotations_only_without_monitor() {
+        let none = openfga(json!({ "enabled": true, "port": 9090, "monitor": { "kind": "None" } }));
+        let annotations: BTreeMap<_, _> = scrape_annotations(&none).into_iter().collect();
+        assert_eq!(annotations["prometheus.io/scrape"], "true");
+        assert_eq!(annotations["prometheus.io/port"], "9090");
+
+        let service_monitor = openfga(json!({ "enabled": true }));
+        assert!(scrape_annotations(&service_monitor).is_empty());
+        let disabled = openfga(json!({ "monitor": { "kind": "None" } }));
+        assert!(scrape_annotations(&disabled).is_empty());
+    }
+
+    #[test]
+    fn test_create_service_monitor() {
+        let openfga = openfga(json!({
+            "enabled": true,
+            "monitor": { "interval": "15s", "labels": { "release": "prometheus" } },
+        }));
+        let monitor = create_monitor(&openfga, "ns", "authz").unwrap();
+
+        assert_eq!(monitor.types.as_ref().unwrap().kind, "ServiceMonitor");
+        assert_eq!(monitor.labels().get("release").unwrap(), "prometheus");
+        let spec = &monitor.data["spec"];
+        assert_eq!(
+            spec["selector"]["matchLabels"]["app.kubernetes.io/instance"],
+            "authz"
+        );
+        assert_eq!(spec["endpoints"][0]["port"], "metrics");
+        assert_eq!(spec["endpoints"][0]["interval"], "15s");
+        assert!(spec["endpoints"][0].get("scrapeTimeout").is_none());
+    }
+
+    #[test]
+    fn test_create_pod_monitor() {
+        let openfga = openfga(json!({
+            "enabled": true,
+            "monitor": { "kind": "PodMonitor", "scrapeTimeout": "5s" },
+        }));
+        let monitor = create_monitor(&openfga, "ns", "authz").unwrap();
+
+        assert_eq!(monitor.types.as_ref().unwrap().kind, "PodMonitor");
+        let spec = &monitor.data["spec"];
+        assert!(spec.get("endpoints").is_none());
+        assert_eq!(spec["podMetricsEndpoints"][0]["scrapeTimeout"], "5s");
+    }
+}
@@ -0,0 +1,35 @@
+//! Outgoing JSON notifications to an operator-configured webhook, e.g. a chat or
+//! ticketing bridge. Delivery is best effort; callers record failures in status.
+
+use hyper::{Body, Method, Request};
+use serde_json::Value;
+use tokio::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Reads a notification URL from `env`, ignoring an empty value.
+pub fn url_from_env(env: &str) -> Option<String> {
+    std::env::var(env).ok().filter(|url| !url.is_empty())
+}
+
+/// POSTs `body` to `url`, failing on a non-2xx answer or after five seconds.
+pub async fn post_json(url: &str, body: &Value) -> Result<(), String> {
+    let post = Request::builder()
+        .method(Method::POST)
+        .uri(url)
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .map_err(|e| format!("invalid notification request: {}", e))?;
+    match tokio::time::timeout(TIMEOUT, hyper::Client::new().request(post)).await {
+        Ok(Ok(response)) if response.status().is_success() => Ok(()),
+        Ok(Ok(response)) => Err(format!(
+            "notification webhook returned {}",
+            response.status()
+        )),
+        Ok(Err(e)) => Err(format!("notification webhook failed: {}", e)),
+        Err(_) => Err(format!(
+            "notification webhook did not answer within {}s",
+            TIMEOUT.as_secs()
+        )),
+    }
+}
@@ -0,0 +1,963 @@
+use crate::access_tokens;
+use crate::api_logging::ApiLogConfig;
+use crate::bulk_writer::{TupleWriter, WriteError};
+use crate::client_pool::{self, CircuitBreaker, ClientPolicy};
+use crate::openfga_grpc;
+use crate::tuples::{TupleKey, TupleOperation};
+use crate::types::{ApiProtocol, OpenFGA};
+use chrono::{DateTime, Utc};
+use hyper::client::HttpConnector;
+use hyper::http::uri::PathAndQuery;
+use hyper::{Body, Client, Method, Request, StatusCode};
+use kube::ResourceExt;
+use opentelemetry::propagation::TextMapPropagator;
+use opentelemetry::{global, Context};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tonic::codec::ProstCodec;
+use tonic::metadata::{MetadataKey, MetadataValue};
+use tonic::Code;
+use tracing::{debug, instrument, warn};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+#[derive(Error, Debug)]
+pub enum ClientError {
+    #[error("HTTP error: {0}")]
+    Http(#[from] hyper::Error),
+    #[error("invalid request: {0}")]
+    Request(#[from] hyper::http::Error),
+    #[error("rate limited by OpenFGA")]
+    RateLimited { retry_after: Option<Duration> },
+    #[error("OpenFGA returned {status}: {message}")]
+    Status { status: u16, message: String },
+    #[error("invalid OpenFGA response: {0}")]
+    InvalidResponse(#[from] serde_json::Error),
+    #[error("OpenFGA did not answer within {0:?}")]
+    Timeout(Duration),
+    #[error("OpenFGA is failing, requests paused for another {retry_after:?}")]
+    CircuitOpen { retry_after: Duration },
+    #[error("gRPC transport error: {0}")]
+    Transport(#[from] tonic::transport::Error),
+    #[error("OpenFGA returned {code:?}: {message}")]
+    Grpc { code: Code, message: String },
+    #[error("invalid client configuration: {0}")]
+    Config(String),
+}
+
+impl ClientError {
+    /// Failures that point at the server rather than the request: transport
+    /// errors, timeouts, 5xx responses and their gRPC counterparts. These count
+    /// towards the circuit breaker.
+    pub fn is_server_failure(&self) -> bool {
+        match self {
+            ClientError::Http(_) | ClientError::Timeout(_) | ClientError::Transport(_) => true,
+            ClientError::Status { status, .. } => *status >= 500,
+            ClientError::Grpc { code, .. } => matches!(
+                code,
+                Code::Unavailable | Code::Internal | Code::Unknown | Code::DataLoss
+            ),
+            _ => false,
+        }
+    }
+
+    /// Whether the request surely never reached the server.
+    fn not_sent(&self) -> bool {
+        match self {
+            ClientError::Http(e) => e.is_connect(),
+            // Only raised while waiting for the channel to become ready
+            ClientError::Transport(_) => true,
+            _ => false,
+        }
+    }
+}
+
+/// The client error of a failed gRPC call.
+fn grpc_error(status: tonic::Status, timeout: Duration) -> ClientError {
+    match status.code() {
+        Code::ResourceExhausted => ClientError::RateLimited { retry_after: None },
+        Code::DeadlineExceeded => ClientError::Timeout(timeout),
+        code => ClientError::Grpc {
+            code,
+            message: status.message().to_string(),
+        },
+    }
+}
+
+/// Requests that change nothing on the server and may be repeated after a
+/// failure that happened once they were sent.
+pub fn idempotent(method: &Method, path: &str) -> bool {
+    const READ_ONLY: &[&str] = &["/check", "/read", "/expand", "/list-objects", "/list-users"];
+    *method == Method::GET
+        || (*method == Method::POST && READ_ONLY.iter().any(|suffix| path.ends_with(suffix)))
+}
+
+pub type ClientResult<T> = std::result::Result<T, ClientError>;
+
+/// One page of tuples from the Read API.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TuplePage {
+    pub tuples: Vec<TupleKey>,
+    /// Token for the next page, `None` once the store is exhausted.
+    pub continuation_token: Option<String>,
+}
+
+/// Whether a change log entry wrote or deleted its tuple.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum ChangeOperation {
+    #[serde(rename = "TUPLE_OPERATION_WRITE")]
+    Write,
+    #[serde(rename = "TUPLE_OPERATION_DELETE")]
+    Delete,
+}
+
+/// One entry of the ReadChanges API.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct TupleChange {
+    pub tuple_key: TupleKey,
+    pub operation: ChangeOperation,
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChangesPage {
+    pub changes: Vec<TupleChange>,
+    pub continuation_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ChangesResponse {
+    #[serde(default)]
+    changes: Vec<TupleChange>,
+    #[serde(default)]
+    continuation_token: String,
+}
+
+#[derive(Deserialize)]
+struct CreateStoreResponse {
+    id: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct StoreInfo {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Deserialize)]
+struct ListStoresResponse {
+    #[serde(default)]
+    stores: Vec<StoreInfo>,
+    #[serde(default)]
+    continuation_token: String,
+}
+
+#[derive(Deserialize)]
+struct WriteModelResponse {
+    authorization_model_id: String,
+}
+
+#[derive(Deserialize)]
+struct ReadModelsResponse {
+    #[serde(default)]
+    authorization_models: Vec<Value>,
+    #[serde(default)]
+    continuation_token: String,
+}
+
+#[derive(Deserialize)]
+struct CheckResponse {
+    #[serde(default)]
+    allowed: bool,
+}
+
+#[derive(Deserialize)]
+struct HealthResponse {
+    #[serde(default)]
+    status: String,
+}
+
+#[derive(Deserialize)]
+struct ReadResponse {
+    #[serde(default)]
+    tuples: Vec<ReadTuple>,
+    #[serde(default)]
+    continuation_token: String,
+}
+
+#[derive(Deserialize)]
+struct ReadTuple {
+    key: TupleKey,
+}
+
+/// Minimal client for the OpenFGA HTTP API of a managed instance. Clients
+/// share the connections, retry policy and circuit breakers of
+/// [`client_pool::pool`]. With a gRPC URL, tuple reads, writes and checks go
+/// through the gRPC API instead.
+#[derive(Clone)]
+pub struct OpenFGAClient {
+    base_url: String,
+    grpc_url: Option<String>,
+    http: Client<HttpConnector>,
+    policy: ClientPolicy,
+    breaker: Arc<CircuitBreaker>,
+    logging: ApiLogConfig,
+    /// Preshared key sent as a bearer token.
+    token: Option<String>,
+}
+
+impl OpenFGAClient {
+    pub fn new(base_url: &str) -> Self {
+        let base_url = base_url.trim_end_matches('/').to_string();
+        let pool = client_pool::pool();
+        Self {
+            http: pool.http(),
+            policy: pool.policy(),
+            breaker: pool.breaker(&base_url),
+            base_url,
+            grpc_url: None,
+            logging: ApiLogConfig::from_env(),
+            token: None,
+        }
+    }
+
+    /// Authenticates requests with the preshared key `token`.
+    pub fn with_token(mut self, token: Option<String>) -> Self {
+        self.token = token;
+        self
+    }
+
+    /// Sends tuple reads, writes and checks to the gRPC API at `url`.
+    pub fn with_grpc(mut self, url: Option<String>) -> Self {
+        self.grpc_url = url.map(|url| url.trim_end_matches('/').to_string());
+        self
+    }
+
+    /// Client for the in-cluster Service the operator creates for `openfga`,
+    /// authenticated with the operator's key once the instance has keys, over
+    /// the protocol selected by `spec.apiProtocol`.
+    pub fn for_instance(openfga: &OpenFGA) -> Self {
+        Self::new(&instance_url(openfga))
+            .with_token(access_tokens::operator_token(openfga))
+            .with_grpc(instance_grpc_url(openfga))
+    }
+
+    /// Creates a store and returns its id.
+    pub async fn create_store(&self, name: &str) -> ClientResult<String> {
+        let response = self
+            .request(Method::POST, "/stores", Some(&json!({ "name": name })))
+            .await?;
+        let response: CreateStoreResponse = serde_json::from_value(response)?;
+        Ok(response.id)
+    }
+
+    /// Lists every store on the instance.
+    pub async fn list_stores(&self) -> ClientResult<Vec<StoreInfo>> {
+        let mut stores = Vec::new();
+        let mut token: Option<String> = None;
+        loop {
+            let mut path = "/stores?page_size=100".to_string();
+            if let Some(token) = &token {
+                path.push_str(&format!(
+                    "&continuation_token={}",
+                    encode_query_value(token)
+                ));
+            }
+            let response = self.request(Method::GET, &path, None).await?;
+            let response: ListStoresResponse = serde_json::from_value(response)?;
+            stores.extend(response.stores);
+            if response.continuation_token.is_empty() {
+                return Ok(stores);
+            }
+            token = Some(response.continuation_token);
+        }
+    }
+
+    /// Serving status reported by `/healthz`, `SERVING` once the server accepts requests.
+    pub async fn health(&self) -> ClientResult<String> {
+        let response = self.request(Method::GET, "/healthz", None).await?;
+        let response: HealthResponse = serde_json::from_value(response)?;
+        Ok(response.status)
+    }
+
+    /// Checks the API end to end: the health endpoint reports serving and a store
+    /// listing round-trips through the datastore.
+    pub async fn verify(&self) -> ClientResult<()> {
+        let status = self.health().await?;
+        if status != "SERVING" {
+            return Err(ClientError::Status {
+                status: StatusCode::SERVICE_UNAVAILABLE.as_u16(),
+                message: format!("health check reported '{}'", status),
+            });
+        }
+        let response = self
+            .request(Method::GET, "/stores?page_size=1", None)
+            .await?;
+        let _: ListStoresResponse = serde_json::from_value(response)?;
+        Ok(())
+    }
+
+    /// Writes a new, immutable authorization model version and returns its id.
+    pub async fn write_authorization_model(
+        &self,
+        store_id: &str,
+        model: &Value,
+    ) -> ClientResult<String> {
+        let response = self
+            .request(
+                Method::POST,
+                &format!("/stores/{}/authorization-models", store_id),
+                Some(model),
+            )
+            .await?;
+        let response: WriteModelResponse = serde_json::from_value(response)?;
+        Ok(response.authorization_model_id)
+    }
+
+    /// Every authorization model version of the store, newest first.
+    pub async fn read_authorization_models(&self, store_id: &str) -> ClientResult<Vec<Value>> {
+        let mut models = Vec::new();
+        let mut token: Option<String> = None;
+        loop {
+            let mut path = format!("/stores/{}/authorization-models?page_size=50", store_id);
+            if let Some(token) = &token {
+                path.push_str(&format!(
+                    "&continuation_token={}",
+                    encode_query_value(token)
+                ));
+            }
+            let response = self.request(Method::GET, &path, None).await?;
+            let response: ReadModelsResponse = serde_json::from_value(response)?;
+            models.extend(response.authorization_models);
+            if response.continuation_token.is_empty() {
+                return Ok(models);
+            }
+            token = Some(response.continuation_token);
+        }
+    }
+
+    /// Whether `tuple` holds under the given model version.
+    pub async fn check(
+        &self,
+        store_id: &str,
+        model_id: &str,
+        tuple: &TupleKey,
+    ) -> ClientResult<bool> {
+        let body = json!({
+            "authorization_model_id": model_id,
+            "tuple_key": tuple,
+        });
+        if let Some(grpc_url) = &self.grpc_url {
+            let request = openfga_grpc::CheckRequest {
+                store_id: store_id.to_string(),
+                tuple_key: Some(tuple.into()),
+                authorization_model_id: model_id.to_string(),
+            };
+            let response: openfga_grpc::CheckResponse =
+                self.call(grpc_url, "Check", request, &body).await?;
+            return Ok(response.allowed);
+        }
+        let response = self
+            .request(
+                Method::POST,
+                &format!("/stores/{}/check", store_id),
+                Some(&body),
+            )
+            .await?;
+        let response: CheckResponse = serde_json::from_value(response)?;
+        Ok(response.allowed)
+    }
+
+    pub async fn delete_store(&self, store_id: &str) -> ClientResult<()> {
+        self.request(Method::DELETE, &format!("/stores/{}", store_id), None)
+            .await?;
+        Ok(())
+    }
+
+    /// Reads one page of the store's change log, oldest first.
+    pub async fn read_changes(
+        &self,
+        store_id: &str,
+        page_size: usize,
+        continuation_token: Option<&str>,
+    ) -> ClientResult<ChangesPage> {
+        let mut path = format!("/stores/{}/changes?page_size={}", store_id, page_size);
+        if let Some(token) = continuation_token {
+            path.push_str(&format!(
+                "&continuation_token={}",
+                encode_query_value(token)
+            ));
+        }
+
+        let response = self.request(Method::GET, &path, None).await?;
+        let response: ChangesResponse = serde_json::from_value(response)?;
+
+        Ok(ChangesPage {
+            changes: response.changes,
+            continuation_token: Some(response.continuation_token).filter(|t| !t.is_empty()),
+        })
+    }
+
+    /// Applies writes and deletes in a single transactional Write call.
+    pub async fn write_tuples(
+        &self,
+        store_id: &str,
+        operations: &[TupleOperation],
+    ) -> ClientResult<()> {
+        let keys = |write: bool| -> Vec<&TupleKey> {
+            operations
+                .iter()
+                .filter(|op| matches!(op, TupleOperation::Write(_)) == write)
+                .map(TupleOperation::tuple)
+                .collect()
+        };
+
+        let mut body = json!({});
+        let (writes, deletes) = (keys(true), keys(false));
+        if !writes.is_empty() {
+            body["writes"] = json!({ "tuple_keys": writes });
+        }
+        if !deletes.is_empty() {
+            body["deletes"] = json!({ "tuple_keys": deletes });
+        }
+
+        if let Some(grpc_url) = &self.grpc_url {
+            let grpc_keys = |keys: &[&TupleKey]| {
+                (!keys.is_empty()).then(|| openfga_grpc::TupleKeys {
+                    tuple_keys: keys.iter().map(|&key| key.into()).collect(),
+                })
+            };
+            let request = openfga_grpc::WriteRequest {
+                store_id: store_id.to_string(),
+                writes: grpc_keys(&writes),
+                deletes: grpc_keys(&deletes),
+            };
+            let _: openfga_grpc::WriteResponse =
+                self.call(grpc_url, "Write", request, &body).await?;
+            return Ok(());
+        }
+
+        self.request(
+            Method::POST,
+            &format!("/stores/{}/write", store_id),
+            Some(&body),
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub async fn read_tuples(
+        &self,
+        store_id: &str,
+        page_size: usize,
+        continuation_token: Option<&str>,
+    ) -> ClientResult<TuplePage> {
+        let mut body = json!({ "page_size": page_size });
+        if let Some(token) = continuation_token {
+            body["continuation_token"] = json!(token);
+        }
+
+        if let Some(grpc_url) = &self.grpc_url {
+            let request = openfga_grpc::ReadRequest {
+                store_id: store_id.to_string(),
+                page_size: Some(page_size as i32),
+                continuation_token: continuation_token.unwrap_or_default().to_string(),
+            };
+            let response: openfga_grpc::ReadResponse =
+                self.call(grpc_url, "Read", request, &body).await?;
+            return Ok(TuplePage {
+                tuples: response
+                    .tuples
+                    .into_iter()
+                    .filter_map(|tuple| tuple.key)
+                    .map(TupleKey::from)
+                    .collect(),
+                continuation_token: Some(response.continuation_token).filter(|t| !t.is_empty()),
+            });
+        }
+
+        let response = self
+            .request(
+                Method::POST,
+                &format!("/stores/{}/read", store_id),
+                Some(&body),
+            )
+            .await?;
+        let response: ReadResponse = serde_json::from_value(response)?;
+
+        Ok(TuplePage {
+            tuples: response.tuples.into_iter().map(|t| t.key).collect(),
+            continuation_token: Some(response.continuation_token).filter(|t| !t.is_empty()),
+        })
+    }
+
+    #[instrument(
+        skip(self, body),
+        fields(
+            otel.kind = "client",
+            http.method = %method,
+            http.target = %path,
+            http.status_code = tracing::field::Empty,
+        )
+    )]
+    async fn request(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&Value>,
+    ) -> ClientResult<Value> {
+        let url = format!("{}{}", self.base_url, path);
+        let mut headers = vec![("content-type".to_string(), "application/json".to_string())];
+        headers.extend(trace_headers());
+        if let Some(token) = &self.token {
+            headers.push(("authorization".to_string(), format!("Bearer {}", token)));
+        }
+        self.logging.log_request(
+            method.as_str(),
+            &url,
+            &headers,
+            body.unwrap_or(&Value::Null),
+        );
+
+        self.with_retries(method.as_str(), &url, idempotent(&method, path), || {
+            self.attempt(&method, &url, &headers, body)
+        })
+        .await
+    }
+
+    #[instrument(
+        skip(self, grpc_url, message, logged),
+        fields(
+            otel.kind = "client",
+            rpc.system = "grpc",
+            rpc.service = "openfga.v1.OpenFGAService",
+            rpc.method = %method,
+            rpc.grpc.status_code = tracing::field::Empty,
+        )
+    )]
+    async fn call<Req, Resp>(
+        &self,
+        grpc_url: &str,
+        method: &str,
+        message: Req,
+        logged: &Value,
+    ) -> ClientResult<Resp>
+    where
+        Req: prost::Message + Clone + 'static,
+        Resp: prost::Message + Default + 'static,
+    {
+        let path = openfga_grpc::method_path(method);
+        let url = format!("{}{}", grpc_url, path);
+        let mut headers = trace_headers();
+        if let Some(token) = &self.token {
+            headers.push(("authorization".to_string(), format!("Bearer {}", token)));
+        }
+        self.logging.log_request("POST", &url, &headers, logged);
+
+        let repeatable = matches!(method, "Read" | "Check");
+        self.with_retries("POST", &url, repeatable, || {
+            self.call_once(grpc_url, &path, &headers, message.clone())
+        })
+        .await
+    }
+
+    /// Sends with `send` until it succeeds, fails for good or runs out of
+    /// retries, feeding the server's circuit breaker. Failures after the
+    /// request may have reached the server are only retried when `repeatable`.
+    async fn with_retries<T, F, Fut>(
+        &self,
+        method: &str,
+        url: &str,
+        repeatable: bool,
+        send: F,
+    ) -> ClientResult<T>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = ClientResult<T>>,
+    {
+        let mut attempt = 0;
+        loop {
+            if let Err(retry_after) = self.breaker.allow(Instant::now()) {
+                return Err(ClientError::CircuitOpen { retry_after });
+            }
+            let result = send().await;
+            let error = match result {
+                Ok(value) => {
+                    self.breaker.record_success();
+                    return Ok(value);
+                }
+                Err(e) if !e.is_server_failure() => {
+                    self.breaker.record_success();
+                    return Err(e);
+                }
+                Err(e) => e,
+            };
+
+            if self.breaker.record_failure(Instant::now(), &self.policy) {
+                warn!(
+                    event = "openfga_circuit_opened",
+                    url = %self.base_url,
+                    cooldown_seconds = self.policy.cooldown.as_secs(),
+                    error = %error,
+                    "OpenFGA keeps failing, pausing requests to it"
+                );
+            }
+            attempt += 1;
+            if attempt > self.policy.max_retries || !(error.not_sent() || repeatable) {
+                return Err(error);
+            }
+            let delay = self.policy.retry_delay(url, attempt);
+            debug!(
+                event = "openfga_request_retry",
+                method = %method,
+                url = %url,
+                attempt = attempt,
+                delay_ms = delay.as_millis() as u64,
+                error = %error,
+                "Retrying OpenFGA request"
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Makes the call once, with the policy's timeout as its gRPC deadline.
+    /// Logged responses carry the gRPC status code as their status.
+    async fn call_once<Req, Resp>(
+        &self,
+        grpc_url: &str,
+        path: &str,
+        headers: &[(String, String)],
+        message: Req,
+    ) -> ClientResult<Resp>
+    where
+        Req: prost::Message + 'static,
+        Resp: prost::Message + Default + 'static,
+    {
+        let started = Instant::now();
+        let mut request = tonic::Request::new(message);
+        request.set_timeout(self.policy.timeout);
+        for (name, value) in headers {
+            if let (Ok(name), Ok(value)) = (
+                MetadataKey::from_bytes(name.as_bytes()),
+                MetadataValue::try_from(value.as_str()),
+            ) {
+                request.metadata_mut().insert(name, value);
+            }
+        }
+        let rpc = PathAndQuery::try_from(path).map_err(hyper::http::Error::from)?;
+        let mut grpc = tonic::client::Grpc::new(client_pool::pool().channel(grpc_url)?);
+        let exchange = async {
+            grpc.ready().await?;
+            Ok::<_, ClientError>(grpc.unary(request, rpc, ProstCodec::default()).await)
+        };
+        let result = tokio::time::timeout(self.policy.timeout, exchange)
+            .await
+            .map_err(|_| ClientError::Timeout(self.policy.timeout))??;
+        let code = result
+            .as_ref()
+            .map_or_else(tonic::Status::code, |_| Code::Ok);
+        tracing::Span::current().record("rpc.grpc.status_code", code as i32);
+        self.logging.log_response(
+            "POST",
+            &format!("{}{}", grpc_url, path),
+            code as u16,
+            started.elapsed(),
+            &Value::Null,
+        );
+
+        result
+            .map(tonic::Response::into_inner)
+            .map_err(|status| grpc_error(status, self.policy.timeout))
+    }
+
+    /// Sends the request once, bounded by the policy's timeout.
+    async fn attempt(
+        &self,
+        method: &Method,
+        url: &str,
+        headers: &[(String, String)],
+        body: Option<&Value>,
+    ) -> ClientResult<Value> {
+        let started = Instant::now();
+        let request = headers
+            .iter()
+            .fold(
+                Request::builder().method(method.clone()).uri(url),
+                |builder, (name, value)| builder.header(name, value),
+            )
+            .body(body.map_or_else(Body::empty, |b| Body::from(b.to_string())))?;
+        let exchange = async {
+            let response = self.http.request(request).await?;
+            let status = response.status();
+            let retry_after = response
+                .headers()
+                .get("retry-after")
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse().ok())
+                .map(Duration::from_secs);
+            let bytes = hyper::body::to_bytes(response.into_body()).await?;
+            Ok::<_, hyper::Error>((status, retry_after, bytes))
+        };
+        let (status, retry_after, bytes) = tokio::time::timeout(self.policy.timeout, exchange)
+            .await
+            .map_err(|_| ClientError::Timeout(self.policy.timeout))??;
+        tracing::Span::current().record("http.status_code", status.as_u16());
+        let value: Value = serde_json::from_slice(&bytes).unwrap_or(Value::Null);
+        self.logging.log_response(
+            method.as_str(),
+            url,
+            status.as_u16(),
+            started.elapsed(),
+            &value,
+        );
+
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            return Err(ClientError::RateLimited { retry_after });
+        }
+        if !status.is_success() {
+            let message = value
+                .get("message")
+                .and_then(Value::as_str)
+                .map(str::to_string)
+                .unwrap_or_else(|| String::from_utf8_lossy(&bytes).into_owned());
+            return Err(ClientError::Status {
+                status: status.as_u16(),
+                message,
+            });
+        }
+
+        Ok(value)
+    }
+}
+
+/// Writes to one store through the Write API, for use with [`crate::bulk_writer::BulkWriter`].
+pub struct StoreWriter<'a> {
+    pub client: &'a OpenFGAClient,
+    pub store_id: &'a str,
+}
+
+impl TupleWriter for StoreWriter<'_> {
+    async fn write(&self, operations: &[TupleOperation]) -> Result<(), WriteError> {
+        match self.client.write_tuples(self.store_id, operations).await {
+            Ok(()) => Ok(()),
+            Err(ClientError::RateLimited { retry_after }) => {
+                Err(WriteError::RateLimited { retry_after })
+            }
+            Err(e) => Err(WriteError::Failed(e.to_string())),
+        }
+    }
+}
+
+/// W3C trace context headers continuing the current span in the OpenFGA server;
+/// empty while no tracer is installed.
+fn trace_headers() -> Vec<(String, String)> {
+    let context = tracing::Span::current().context();
+    global::get_text_map_propagator(|propagator| inject_context(propagator, &context))
+}
+
+fn inject_context(propagator: &dyn TextMapPropagator, context: &Context) -> Vec<(String, String)> {
+    let mut carrier = HashMap::new();
+    propagator.inject_context(context, &mut carrier);
+    let mut headers: Vec<(String, String)> = carrier
+        .into_iter()
+        .filter(|(_, value)| !value.is_empty())
+        .collect();
+    headers.sort();
+    headers
+}
+
+fn encode_query_value(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Base URL of the HTTP API exposed by the instance's Service.
+pub fn instance_url(openfga: &OpenFGA) -> String {
+    format!(
+        "http://{}.{}.svc:{}",
+        openfga.name_any(),
+        openfga.namespace().unwrap_or_default(),
+        openfga.spec.http.port
+    )
+}
+
+/// URL of the gRPC API exposed by the instance's Service when the operator
+/// talks to it over gRPC, `https` when the instance serves TLS.
+pub fn instance_grpc_url(openfga: &OpenFGA) -> Option<String> {
+    if openfga.spec.api_protocol != ApiProtocol::Grpc {
+        return None;
+    }
+    Some(format!(
+        "{}://{}.{}.svc:{}",
+        if openfga.spec.tls.is_some() {
+            "https"
+        } else {
+            "http"
+        },
+        openfga.name_any(),
+        openfga.namespace().unwrap_or_default(),
+        openfga.spec.grpc.port
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::trace::{
+        SpanContext, SpanId, TraceContextExt, TraceFlags, TraceId, TraceState,
+    };
+    use opentelemetry_sdk::propagation::TraceContextPropagator;
+
+    #[test]
+    fn test_inject_trace_context() {
+        let propagator = TraceContextPropagator::new();
+        assert!(inject_context(&propagator, &Context::new()).is_empty());
+
+        let span = SpanContext::new(
+            TraceId::from_hex("4bf92f3577b34da6a3ce929d0e0e4736").unwrap(),
+            SpanId::from_hex("00f067aa0ba902b7").unwrap(),
+            TraceFlags::SAMPLED,
+            true,
+            TraceState::default(),
+        );
+        let headers = inject_context(&propagator, &Context::new().with_remote_span_context(span));
+        assert_eq!(
+            headers,
+            vec![(
+                "traceparent".to_string(),
+                "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_health_response_parsing() {
+        let response: HealthResponse =
+            serde_json::from_value(json!({"status": "SERVING"})).unwrap();
+        assert_eq!(response.status, "SERVING");
+
+        let response: HealthResponse = serde_json::from_value(json!({})).unwrap();
+        assert!(response.status.is_empty());
+    }
+
+    #[test]
+    fn test_read_response_parsing() {
+        let response: ReadResponse = serde_json::from_value(json!({
+            "tuples": [{
+                "key": {"user": "user:anne", "relation": "viewer", "object": "doc:1"},
+                "timestamp": "2024-01-01T00:00:00Z"
+            }],
+            "continuation_token": ""
+        }))
+        .unwrap();
+
+        assert_eq!(
+            response.tuples[0].key,
+            TupleKey::new("user:anne", "viewer", "doc:1")
+        );
+        assert!(response.continuation_token.is_empty());
+    }
+
+    #[test]
+    fn test_retry_classification() {
+        assert!(idempotent(&Method::GET, "/stores?page_size=1"));
+        assert!(idempotent(&Method::POST, "/stores/01H/check"));
+        assert!(idempotent(&Method::POST, "/stores/01H/read"));
+        assert!(!idempotent(&Method::POST, "/stores/01H/write"));
+        assert!(!idempotent(
+            &Method::POST,
+            "/stores/01H/authorization-models"
+        ));
+        assert!(!idempotent(&Method::DELETE, "/stores/01H"));
+
+        let status = |status: u16| ClientError::Status {
+            status,
+            message: String::new(),
+        };
+        assert!(status(503).is_server_failure());
+        assert!(!status(400).is_server_failure());
+        assert!(ClientError::Timeout(Duration::from_secs(1)).is_server_failure());
+        assert!(!ClientError::RateLimited { retry_after: None }.is_server_failure());
+    }
+
+    #[test]
+    fn test_grpc_errors() {
+        let timeout = Duration::from_secs(10);
+        let error = |status| grpc_error(status, timeout);
+        assert!(matches!(
+            error(tonic::Status::resource_exhausted("slow down")),
+            ClientError::RateLimited { retry_after: None }
+        ));
+        assert!(matches!(
+            error(tonic::Status::deadline_exceeded("")),
+            ClientError::Timeout(t) if t == timeout
+        ));
+
+        let unavailable = error(tonic::Status::unavailable("connection refused"));
+        assert!(unavailable.is_server_failure());
+        assert!(!unavailable.not_sent());
+        assert_eq!(
+            unavailable.to_string(),
+            "OpenFGA returned Unavailable: connection refused"
+        );
+        let invalid = error(tonic::Status::invalid_argument("type 'doc' not found"));
+        assert!(!invalid.is_server_failure());
+    }
+
+    #[test]
+    fn test_instance_grpc_url() {
+        let mut openfga = crate::controller::create_test_openfga();
+        openfga.metadata.name = Some("authz".to_string());
+        openfga.metadata.namespace = Some("auth".to_string());
+        assert_eq!(instance_grpc_url(&openfga), None);
+
+        openfga.spec.api_protocol = ApiProtocol::Grpc;
+        assert_eq!(
+            instance_grpc_url(&openfga).as_deref(),
+            Some("http://authz.auth.svc:8081")
+        );
+        openfga.spec.tls = Some(crate::types::TlsConfig {
+            secret_name: "authz-grpc-tls".to_string(),
+        });
+        assert_eq!(
+            instance_grpc_url(&openfga).as_deref(),
+            Some("https://authz.auth.svc:8081")
+        );
+    }
+
+    #[test]
+    fn test_encode_query_value() {
+        assert_eq!(encode_query_value("ab+c/d=="), "ab%2Bc%2Fd%3D%3D");
+    }
+
+    #[test]
+    fn test_changes_response_parsing() {
+        let response: ChangesResponse = serde_json::from_value(json!({
+            "changes": [{
+                "tuple_key": {"user": "user:anne", "relation": "viewer", "object": "doc:1"},
+                "operation": "TUPLE_OPERATION_DELETE",
+                "timestamp": "2024-01-01T00:00:00Z"
+            }],
+            "continuation_token": "abc"
+        }))
+        .unwrap();
+
+        assert_eq!(response.changes[0].operation, ChangeOperation::Delete);
+        assert_eq!(
+            response.changes[0].timestamp.to_rfc3339(),
+            "2024-01-01T00:00:00+00:00"
+        );
+        assert_eq!(response.continuation_token, "abc");
+    }
+}
@@ -0,0 +1,161 @@
+//! Messages of OpenFGA's gRPC API (`openfga.v1.OpenFGAService`) for the calls
+//! the operator makes over gRPC: Read, Write and Check. Only the fields the
+//! operator sets or reads are declared, by hand rather than generated, so the
+//! build needs no protoc; tags follow `openfga/v1/openfga_service.proto`.
+//! Fields a server sends that are not declared here are skipped on decoding.
+
+use crate::tuples;
+
+/// Full method path of an `OpenFGAService` RPC.
+pub fn method_path(method: &str) -> String {
+    format!("/openfga.v1.OpenFGAService/{}", method)
+}
+
+/// `TupleKey`, also the wire form of `TupleKeyWithoutCondition`,
+/// `ReadRequestTupleKey` and `CheckRequestTupleKey`.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct TupleKey {
+    #[prost(string, tag = "1")]
+    pub user: String,
+    #[prost(string, tag = "2")]
+    pub relation: String,
+    #[prost(string, tag = "3")]
+    pub object: String,
+}
+
+impl From<&tuples::TupleKey> for TupleKey {
+    fn from(key: &tuples::TupleKey) -> Self {
+        Self {
+            user: key.user.clone(),
+            relation: key.relation.clone(),
+            object: key.object.clone(),
+        }
+    }
+}
+
+impl From<TupleKey> for tuples::TupleKey {
+    fn from(key: TupleKey) -> Self {
+        Self {
+            user: key.user,
+            relation: key.relation,
+            object: key.object,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct TupleKeys {
+    #[prost(message, repeated, tag = "1")]
+    pub tuple_keys: Vec<TupleKey>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct WriteRequest {
+    #[prost(string, tag = "1")]
+    pub store_id: String,
+    #[prost(message, optional, tag = "2")]
+    pub writes: Option<TupleKeys>,
+    #[prost(message, optional, tag = "3")]
+    pub deletes: Option<TupleKeys>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct WriteResponse {}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ReadRequest {
+    #[prost(string, tag = "1")]
+    pub store_id: String,
+    /// `google.protobuf.Int32Value`.
+    #[prost(message, optional, tag = "3")]
+    pub page_size: Option<i32>,
+    #[prost(string, tag = "4")]
+    pub continuation_token: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Tuple {
+    #[prost(message, optional, tag = "1")]
+    pub key: Option<TupleKey>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ReadResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub tuples: Vec<Tuple>,
+    #[prost(string, tag = "2")]
+    pub continuation_token: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct CheckRequest {
+    #[prost(string, tag = "1")]
+    pub store_id: String,
+    #[prost(message, optional, tag = "2")]
+    pub tuple_key: Option<TupleKey>,
+    #[prost(string, tag = "4")]
+    pub authorization_model_id: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct CheckResponse {
+    #[prost(bool, tag = "1")]
+    pub allowed: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prost::Message;
+
+    #[test]
+    fn test_wire_format() {
+        let request = WriteRequest {
+            store_id: "s1".to_string(),
+            writes: Some(TupleKeys {
+                tuple_keys: vec![(&tuples::TupleKey::new("user:a", "viewer", "doc:1")).into()],
+            }),
+            deletes: None,
+        };
+        // store_id (1), then writes (2) holding one tuple key of user (1),
+        // relation (2) and object (3)
+        let mut expected = vec![0x0a, 2, b's', b'1', 0x12, 25, 0x0a, 23];
+        expected.extend([0x0a, 6]);
+        expected.extend(b"user:a");
+        expected.extend([0x12, 6]);
+        expected.extend(b"viewer");
+        expected.extend([0x1a, 5]);
+        expected.extend(b"doc:1");
+        assert_eq!(request.encode_to_vec(), expected);
+
+        let page = ReadRequest {
+            store_id: "s1".to_string(),
+            page_size: Some(100),
+            continuation_token: String::new(),
+        };
+        assert_eq!(
+            page.encode_to_vec(),
+            vec![0x0a, 2, b's', b'1', 0x1a, 2, 0x08, 100]
+        );
+    }
+
+    #[test]
+    fn test_decode_skips_unknown_fields() {
+        // A Tuple with its key and a timestamp (2) the operator does not declare
+        let mut tuple = vec![0x0a, 13, 0x0a, 3];
+        tuple.extend(b"u:a");
+        tuple.extend([0x12, 1, b'r', 0x1a, 3]);
+        tuple.extend(b"o:1");
+        tuple.extend([0x12, 2, 0x08, 1]);
+        let mut response = vec![0x0a, tuple.len() as u8];
+        response.extend(tuple);
+        response.extend([0x12, 1, b't']);
+
+        let decoded = ReadResponse::decode(response.as_slice()).unwrap();
+        assert_eq!(decoded.continuation_token, "t");
+        assert_eq!(
+            tuples::TupleKey::from(decoded.tuples[0].key.clone().unwrap()),
+            tuples::TupleKey::new("u:a", "r", "o:1")
+        );
+    }
+}
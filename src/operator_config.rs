@@ -0,0 +1,312 @@
+//! Operator-wide settings for the OpenFGA controller, read from the environment
+//! and overridden by command-line flags.
+
+use std::time::Duration;
+
+const RECONCILE_INTERVAL_ENV: &str = "OPENFGA_OPERATOR_RECONCILE_INTERVAL";
+const WATCH_NAMESPACES_ENV: &str = "OPENFGA_OPERATOR_WATCH_NAMESPACES";
+const MAX_CONCURRENT_RECONCILES_ENV: &str = "OPENFGA_OPERATOR_MAX_CONCURRENT_RECONCILES";
+const API_QPS_ENV: &str = "OPENFGA_OPERATOR_API_QPS";
+const API_BURST_ENV: &str = "OPENFGA_OPERATOR_API_BURST";
+const MODE_ENV: &str = "OPENFGA_OPERATOR_MODE";
+const INSTALL_CRDS_ENV: &str = "OPENFGA_OPERATOR_INSTALL_CRDS";
+const RECONCILE_INTERVAL_FLAG: &str = "--reconcile-interval";
+const WATCH_NAMESPACES_FLAG: &str = "--watch-namespaces";
+const MAX_CONCURRENT_RECONCILES_FLAG: &str = "--max-concurrent-reconciles";
+const API_QPS_FLAG: &str = "--api-qps";
+const API_BURST_FLAG: &str = "--api-burst";
+const MODE_FLAG: &str = "--mode";
+const INSTALL_CRDS_FLAG: &str = "--install-crds";
+const FLAGS: [&str; 7] = [
+    RECONCILE_INTERVAL_FLAG,
+    WATCH_NAMESPACES_FLAG,
+    MAX_CONCURRENT_RECONCILES_FLAG,
+    API_QPS_FLAG,
+    API_BURST_FLAG,
+    MODE_FLAG,
+    INSTALL_CRDS_FLAG,
+];
+
+/// Whether the OpenFGA controller applies the objects it computes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OperatorMode {
+    #[default]
+    Apply,
+    /// Computes desired objects and reports the differences to the live ones in
+    /// `status.pendingChanges`, without changing anything.
+    DryRun,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OperatorConfig {
+    /// How long a healthy instance waits before it is reconciled again.
+    pub reconcile_interval: Duration,
+    /// Namespaces the OpenFGA controller watches; empty watches the whole cluster.
+    pub watch_namespaces: Vec<String>,
+    /// Instances reconciled in parallel across the watched namespaces; 0 leaves
+    /// it unbounded. Under the limit, failing and unready instances go first,
+    /// see `reconcile_queue`. One instance is never reconciled twice at once
+    /// either way.
+    pub max_concurrent_reconciles: u16,
+    /// Sustained Kubernetes API requests per second across all controllers;
+    /// `None` disables client-side rate limiting.
+    pub api_qps: Option<f64>,
+    /// Requests allowed at once above `api_qps` after an idle period.
+    pub api_burst: u32,
+    /// Whether instances are reconciled or only previewed.
+    pub mode: OperatorMode,
+    /// Whether CRDs are installed and upgraded at startup rather than waited for.
+    pub install_crds: bool,
+}
+
+impl Default for OperatorConfig {
+    fn default() -> Self {
+        Self {
+            reconcile_interval: Duration::from_secs(60),
+            watch_namespaces: vec![],
+            max_concurrent_reconciles: 0,
+            api_qps: None,
+            api_burst: 10,
+            mode: OperatorMode::Apply,
+            install_crds: false,
+        }
+    }
+}
+
+impl OperatorConfig {
+    /// Defaults, overridden by `OPENFGA_OPERATOR_RECONCILE_INTERVAL` (seconds),
+    /// `OPENFGA_OPERATOR_WATCH_NAMESPACES` (comma separated),
+    /// `OPENFGA_OPERATOR_MAX_CONCURRENT_RECONCILES`, `OPENFGA_OPERATOR_API_QPS`,
+    /// `OPENFGA_OPERATOR_API_BURST`, `OPENFGA_OPERATOR_MODE` (`apply` or
+    /// `dry-run`) and `OPENFGA_OPERATOR_INSTALL_CRDS` (`true` or `false`), then
+    /// by the matching flags in `args` (`--reconcile-interval`,
+    /// `--watch-namespaces`, `--max-concurrent-reconciles`, `--api-qps`,
+    /// `--api-burst`, `--mode`, `--install-crds`).
+    pub fn from_env(args: &[String]) -> Result<Self, String> {
+        Self::from_lookup(args, |name| std::env::var(name).ok())
+    }
+
+    fn from_lookup(
+        args: &[String],
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, String> {
+        let mut config = Self::default();
+        for (env, flag) in [
+            (RECONCILE_INTERVAL_ENV, RECONCILE_INTERVAL_FLAG),
+            (WATCH_NAMESPACES_ENV, WATCH_NAMESPACES_FLAG),
+            (
+                MAX_CONCURRENT_RECONCILES_ENV,
+                MAX_CONCURRENT_RECONCILES_FLAG,
+            ),
+            (API_QPS_ENV, API_QPS_FLAG),
+            (API_BURST_ENV, API_BURST_FLAG),
+            (MODE_ENV, MODE_FLAG),
+            (INSTALL_CRDS_ENV, INSTALL_CRDS_FLAG),
+        ] {
+            if let Some(value) = lookup(env) {
+                config.set(flag, env, &value)?;
+            }
+        }
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) => (flag, Some(value.to_string())),
+                None => (arg.as_str(), None),
+            };
+            if !FLAGS.contains(&flag) {
+                continue;
+            }
+            let value = inline
+                .or_else(|| args.next().cloned())
+                .ok_or_else(|| format!("{} requires a value", flag))?;
+            config.set(flag, flag, &value)?;
+        }
+        Ok(config)
+    }
+
+    /// Sets the setting of `flag` from `value`, naming it `name` in errors.
+    fn set(&mut self, flag: &str, name: &str, value: &str) -> Result<(), String> {
+        match flag {
+            RECONCILE_INTERVAL_FLAG => self.reconcile_interval = parse_interval(name, value)?,
+            WATCH_NAMESPACES_FLAG => self.watch_namespaces = parse_namespaces(value),
+            MAX_CONCURRENT_RECONCILES_FLAG => {
+                self.max_concurrent_reconciles = value.trim().parse().map_err(|_| {
+                    format!("{} must be a number up to 65535, got '{}'", name, value)
+                })?
+            }
+            API_QPS_FLAG => {
+                let qps = value
+                    .trim()
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|qps| qps.is_finite() && *qps >= 0.0)
+                    .ok_or_else(|| {
+                        format!("{} must be a non-negative number, got '{}'", name, value)
+                    })?;
+                self.api_qps = (qps > 0.0).then_some(qps);
+            }
+            MODE_FLAG => {
+                self.mode = match value.trim() {
+                    "apply" => OperatorMode::Apply,
+                    "dry-run" => OperatorMode::DryRun,
+                    _ => {
+                        return Err(format!(
+                            "{} must be 'apply' or 'dry-run', got '{}'",
+                            name, value
+                        ))
+                    }
+                }
+            }
+            INSTALL_CRDS_FLAG => {
+                self.install_crds = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("{} must be 'true' or 'false', got '{}'", name, value))?
+            }
+            _ => {
+                self.api_burst = value
+                    .trim()
+                    .parse()
+                    .ok()
+                    .filter(|&burst| burst > 0)
+                    .ok_or_else(|| format!("{} must be a positive number, got '{}'", name, value))?
+            }
+        }
+        Ok(())
+    }
+
+    /// The namespaces to run a watch in; `None` stands for the whole cluster.
+    pub fn watch_scopes(&self) -> Vec<Option<String>> {
+        if self.watch_namespaces.is_empty() {
+            vec![None]
+        } else {
+            self.watch_namespaces.iter().cloned().map(Some).collect()
+        }
+    }
+}
+
+fn parse_interval(name: &str, value: &str) -> Result<Duration, String> {
+    value
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .filter(|&seconds| seconds > 0)
+        .map(Duration::from_secs)
+        .ok_or_else(|| {
+            format!(
+                "{} must be a positive number of seconds, got '{}'",
+                name, value
+            )
+        })
+}
+
+fn parse_namespaces(value: &str) -> Vec<String> {
+    let mut namespaces: Vec<String> = value
+        .split(',')
+        .map(str::trim)
+        .filter(|ns| !ns.is_empty())
+        .map(str::to_string)
+        .collect();
+    namespaces.sort();
+    namespaces.dedup();
+    namespaces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn from(list: &[&str], env: &[(&str, &str)]) -> Result<OperatorConfig, String> {
+        let env: HashMap<&str, &str> = env.iter().cloned().collect();
+        OperatorConfig::from_lookup(&args(list), |name| env.get(name).map(|v| v.to_string()))
+    }
+
+    #[test]
+    fn test_defaults_watch_cluster() {
+        let config = from(&["openfga-operator"], &[]).unwrap();
+        assert_eq!(config, OperatorConfig::default());
+        assert_eq!(config.watch_scopes(), vec![None]);
+    }
+
+    #[test]
+    fn test_flags_override_env() {
+        let env = [
+            (RECONCILE_INTERVAL_ENV, "120"),
+            (WATCH_NAMESPACES_ENV, "team-b, team-a,,team-b"),
+        ];
+        let config = from(&["openfga-operator"], &env).unwrap();
+        assert_eq!(config.reconcile_interval, Duration::from_secs(120));
+        assert_eq!(config.watch_namespaces, vec!["team-a", "team-b"]);
+
+        let config = from(
+            &[
+                "openfga-operator",
+                "--reconcile-interval",
+                "30",
+                "--watch-namespaces=auth",
+            ],
+            &env,
+        )
+        .unwrap();
+        assert_eq!(config.reconcile_interval, Duration::from_secs(30));
+        assert_eq!(config.watch_scopes(), vec![Some("auth".to_string())]);
+    }
+
+    #[test]
+    fn test_invalid_values_rejected() {
+        assert!(from(&[], &[(RECONCILE_INTERVAL_ENV, "0")]).is_err());
+        assert!(from(&["openfga-operator", "--reconcile-interval", "1m"], &[]).is_err());
+        assert!(from(&["openfga-operator", "--watch-namespaces"], &[]).is_err());
+        assert!(from(&[], &[(MAX_CONCURRENT_RECONCILES_ENV, "-1")]).is_err());
+        assert!(from(&["openfga-operator", "--api-qps", "fast"], &[]).is_err());
+        assert!(from(&["openfga-operator", "--api-burst=0"], &[]).is_err());
+    }
+
+    #[test]
+    fn test_concurrency_and_rate_limit() {
+        let env = [
+            (MAX_CONCURRENT_RECONCILES_ENV, "8"),
+            (API_QPS_ENV, "20"),
+            (API_BURST_ENV, "40"),
+        ];
+        let config = from(&["openfga-operator"], &env).unwrap();
+        assert_eq!(config.max_concurrent_reconciles, 8);
+        assert_eq!(config.api_qps, Some(20.0));
+        assert_eq!(config.api_burst, 40);
+
+        // A QPS of 0 turns the limiter off
+        let config = from(&["openfga-operator", "--api-qps=0"], &env).unwrap();
+        assert_eq!(config.api_qps, None);
+    }
+
+    #[test]
+    fn test_mode() {
+        let env = [(MODE_ENV, "dry-run")];
+        let config = from(&["openfga-operator"], &env).unwrap();
+        assert_eq!(config.mode, OperatorMode::DryRun);
+
+        let config = from(&["openfga-operator", "--mode", "apply"], &env).unwrap();
+        assert_eq!(config.mode, OperatorMode::Apply);
+        assert!(from(&["openfga-operator", "--mode=plan"], &[]).is_err());
+    }
+
+    #[test]
+    fn test_install_crds() {
+        let config = from(&["openfga-operator"], &[(INSTALL_CRDS_ENV, "true")]).unwrap();
+        assert!(config.install_crds);
+
+        let config = from(
+            &["openfga-operator", "--install-crds=false"],
+            &[(INSTALL_CRDS_ENV, "true")],
+        )
+        .unwrap();
+        assert!(!config.install_crds);
+        assert!(from(&["openfga-operator", "--install-crds", "yes"], &[]).is_err());
+    }
+}
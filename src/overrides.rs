@@ -0,0 +1,350 @@
+//! `spec.overridesFrom`: patches from a ConfigMap layered over the generated
+//! workload and Service right before they are applied, for the edge cases the
+//! CRD has no field for. A patch is merged the way `kubectl patch --type
+//! strategic` merges: objects recursively, `null` removes a field, and lists of
+//! containers, env vars, volumes, mounts and ports are merged by their key,
+//! where `$patch: delete` removes an entry. Other lists are replaced.
+//!
+//! The object's name and namespace cannot be patched.
+
+use crate::controller::{ControllerError, ControllerResult};
+use crate::types::OpenFGA;
+use k8s_openapi::api::core::v1::ConfigMap;
+use kube::api::Api;
+use kube::{Client, ResourceExt};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+/// ConfigMap key of the patch for the Deployment or StatefulSet.
+pub const DEPLOYMENT_KEY: &str = "deployment";
+/// ConfigMap key of the patch for the Service.
+pub const SERVICE_KEY: &str = "service";
+
+const PATCH_DIRECTIVE: &str = "$patch";
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Overrides {
+    pub deployment: Option<Value>,
+    pub service: Option<Value>,
+}
+
+/// The patches in a ConfigMap's `data`.
+pub fn parse(
+    config_map: &str,
+    data: &std::collections::BTreeMap<String, String>,
+) -> Result<Overrides, String> {
+    let patch = |key: &str| -> Result<Option<Value>, String> {
+        let Some(raw) = data.get(key) else {
+            return Ok(None);
+        };
+        match serde_yaml::from_str::<Value>(raw) {
+            Ok(Value::Object(map)) => Ok(Some(Value::Object(map))),
+            Ok(Value::Null) => Ok(None),
+            Ok(_) => Err(format!(
+                "key '{}' of ConfigMap '{}' is not a YAML mapping",
+                key, config_map
+            )),
+            Err(e) => Err(format!(
+                "key '{}' of ConfigMap '{}' is not valid YAML: {}",
+                key, config_map, e
+            )),
+        }
+    };
+    Ok(Overrides {
+        deployment: patch(DEPLOYMENT_KEY)?,
+        service: patch(SERVICE_KEY)?,
+    })
+}
+
+/// Reads the patches of `spec.overridesFrom`; none when it is unset.
+pub async fn load(client: &Client, openfga: &OpenFGA) -> ControllerResult<Overrides> {
+    let Some(source) = &openfga.spec.overrides_from else {
+        return Ok(Overrides::default());
+    };
+    let name = &source.config_map_ref.name;
+    let config_maps: Api<ConfigMap> =
+        Api::namespaced(client.clone(), &openfga.namespace().unwrap_or_default());
+    let config_map = config_maps.get_opt(name).await?.ok_or_else(|| {
+        ControllerError::InvalidOverrides(format!("ConfigMap '{}' not found", name))
+    })?;
+    parse(name, &config_map.data.unwrap_or_default()).map_err(ControllerError::InvalidOverrides)
+}
+
+/// `object` with `patch` merged over it.
+pub fn apply<T: Clone + Serialize + DeserializeOwned>(
+    object: &T,
+    patch: Option<&Value>,
+) -> ControllerResult<T> {
+    let Some(patch) = patch else {
+        return Ok(object.clone());
+    };
+    let mut merged = serde_json::to_value(object)?;
+    let identity = ["name", "namespace"].map(|field| merged["metadata"].get(field).cloned());
+    strategic_merge(&mut merged, patch, "");
+    for (field, value) in ["name", "namespace"].into_iter().zip(identity) {
+        if let (Some(metadata), Some(value)) = (merged["metadata"].as_object_mut(), value) {
+            metadata.insert(field.to_string(), value);
+        }
+    }
+    serde_json::from_value(merged)
+        .map_err(|e| ControllerError::InvalidOverrides(format!("patched object is invalid: {}", e)))
+}
+
+/// Key the entries of the list under `field` are merged by.
+fn merge_key(field: &str, entries: &[Value]) -> Option<&'static str> {
+    let key = match field {
+        "volumeMounts" => "mountPath",
+        "volumeDevices" => "devicePath",
+        "ports"
+            if entries
+                .iter()
+                .any(|entry| entry.get("containerPort").is_some()) =>
+        {
+            "containerPort"
+        }
+        "ports" => "port",
+        "containers"
+        | "initContainers"
+        | "ephemeralContainers"
+        | "env"
+        | "volumes"
+        | "imagePullSecrets" => "name",
+        _ => return None,
+    };
+    Some(key)
+}
+
+/// Merges `patch` into `target`, which sits under `field` of its parent.
+pub fn strategic_merge(target: &mut Value, patch: &Value, field: &str) {
+    match (target, patch) {
+        (Value::Object(target), Value::Object(patch)) => {
+            for (key, value) in patch {
+                if value.is_null() {
+                    target.remove(key);
+                } else if let Some(existing) = target.get_mut(key) {
+                    strategic_merge(existing, value, key);
+                } else {
+                    let mut value = value.clone();
+                    strip_directives(&mut value);
+                    target.insert(key.clone(), value);
+                }
+            }
+        }
+        (Value::Array(target), Value::Array(patch)) => {
+            let Some(key) = merge_key(field, patch) else {
+                *target = patch.clone();
+                return;
+            };
+            for entry in patch {
+                let id = entry.get(key);
+                let position = id.and_then(|id| target.iter().position(|e| e.get(key) == Some(id)));
+                let delete = entry.get(PATCH_DIRECTIVE).and_then(Value::as_str) == Some("delete");
+                match (position, delete) {
+                    (Some(at), true) => {
+                        target.remove(at);
+                    }
+                    (None, true) => {}
+                    (Some(at), false) => strategic_merge(&mut target[at], entry, ""),
+                    (None, false) => {
+                        let mut entry = entry.clone();
+                        strip_directives(&mut entry);
+                        target.push(entry);
+                    }
+                }
+            }
+        }
+        (target, patch) => {
+            let mut patch = patch.clone();
+            strip_directives(&mut patch);
+            *target = patch;
+        }
+    }
+}
+
+/// Drops `$patch` directives and `null`s from a value that is added as a whole.
+fn strip_directives(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            map.remove(PATCH_DIRECTIVE);
+            map.retain(|_, v| !v.is_null());
+            map.values_mut().for_each(strip_directives);
+        }
+        Value::Array(entries) => entries.iter_mut().for_each(strip_directives),
+        _ => {}
+    }
+}
+
+/// Compact description of what a patch touches, for logs.
+pub fn describe(patch: &Value) -> String {
+    fn paths(value: &Value, prefix: &str, out: &mut Vec<String>) {
+        match value {
+            Value::Object(map) if !map.is_empty() => {
+                for (key, value) in map {
+                    let path = if prefix.is_empty() {
+                        key.clone()
+                    } else {
+                        format!("{}.{}", prefix, key)
+                    };
+                    paths(value, &path, out);
+                }
+            }
+            _ => out.push(prefix.to_string()),
+        }
+    }
+    let mut out = Vec::new();
+    paths(patch, "", &mut out);
+    out.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::api::apps::v1::Deployment;
+    use serde_json::json;
+    use std::collections::BTreeMap;
+
+    fn deployment() -> Deployment {
+        serde_json::from_value(json!({
+            "metadata": { "name": "authz", "namespace": "auth" },
+            "spec": {
+                "selector": { "matchLabels": { "app": "openfga" } },
+                "template": { "spec": {
+                    "containers": [{
+                        "name": "openfga",
+                        "image": "openfga/openfga:v1.8.0",
+                        "args": ["run"],
+                        "env": [
+                            { "name": "OPENFGA_LOG_LEVEL", "value": "info" },
+                            { "name": "OPENFGA_DATASTORE_ENGINE", "value": "memory" }
+                        ],
+                        "ports": [{ "name": "grpc", "containerPort": 8081 }]
+                    }],
+                    "nodeSelector": { "pool": "general" }
+                } }
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_strategic_merge() {
+        let patch: Value = serde_yaml::from_str(
+            r#"
+metadata:
+  name: renamed
+spec:
+  template:
+    spec:
+      nodeSelector: null
+      dnsPolicy: None
+      containers:
+        - name: openfga
+          args: [run, --experimentals=check-optimizations]
+          env:
+            - name: OPENFGA_LOG_LEVEL
+              value: debug
+            - name: OPENFGA_DATASTORE_ENGINE
+              $patch: delete
+            - name: GOMAXPROCS
+              value: "2"
+        - name: sidecar
+          image: busybox
+"#,
+        )
+        .unwrap();
+        let patched = apply(&deployment(), Some(&patch)).unwrap();
+
+        assert_eq!(patched.metadata.name.as_deref(), Some("authz"));
+        let pod = patched.spec.unwrap().template.spec.unwrap();
+        assert!(pod.node_selector.is_none());
+        assert_eq!(pod.dns_policy.as_deref(), Some("None"));
+        assert_eq!(pod.containers.len(), 2);
+        let openfga = &pod.containers[0];
+        assert_eq!(openfga.image.as_deref(), Some("openfga/openfga:v1.8.0"));
+        assert_eq!(
+            openfga.args.as_deref().unwrap(),
+            ["run", "--experimentals=check-optimizations"]
+        );
+        let env: Vec<(&str, Option<&str>)> = openfga
+            .env
+            .iter()
+            .flatten()
+            .map(|e| (e.name.as_str(), e.value.as_deref()))
+            .collect();
+        assert_eq!(
+            env,
+            vec![
+                ("OPENFGA_LOG_LEVEL", Some("debug")),
+                ("GOMAXPROCS", Some("2"))
+            ]
+        );
+        assert_eq!(openfga.ports.as_ref().unwrap()[0].container_port, 8081);
+        assert_eq!(pod.containers[1].image.as_deref(), Some("busybox"));
+    }
+
+    #[test]
+    fn test_merge_keys() {
+        let mut service = json!({ "ports": [{ "name": "http", "port": 8080 }] });
+        strategic_merge(
+            &mut service,
+            &json!({ "ports": [{ "port": 8080, "nodePort": 30080 }, { "name": "metrics", "port": 2112 }] }),
+            "",
+        );
+        assert_eq!(
+            service,
+            json!({ "ports": [
+                { "name": "http", "port": 8080, "nodePort": 30080 },
+                { "name": "metrics", "port": 2112 }
+            ] })
+        );
+
+        let mut mounts = json!([{ "name": "cache", "mountPath": "/cache" }]);
+        strategic_merge(
+            &mut mounts,
+            &json!([{ "name": "cache", "mountPath": "/tmp" }]),
+            "volumeMounts",
+        );
+        assert_eq!(mounts.as_array().unwrap().len(), 2);
+
+        let mut tolerations = json!([{ "key": "a" }]);
+        strategic_merge(&mut tolerations, &json!([{ "key": "b" }]), "tolerations");
+        assert_eq!(tolerations, json!([{ "key": "b" }]));
+    }
+
+    #[test]
+    fn test_parse() {
+        let data = BTreeMap::from([
+            (
+                DEPLOYMENT_KEY.to_string(),
+                "spec:\n  revisionHistoryLimit: 3\n".to_string(),
+            ),
+            ("notes".to_string(), "ignored".to_string()),
+        ]);
+        let overrides = parse("authz-overrides", &data).unwrap();
+        assert_eq!(
+            overrides.deployment,
+            Some(json!({ "spec": { "revisionHistoryLimit": 3 } }))
+        );
+        assert_eq!(overrides.service, None);
+        assert_eq!(
+            describe(overrides.deployment.as_ref().unwrap()),
+            "spec.revisionHistoryLimit"
+        );
+
+        let invalid = BTreeMap::from([(SERVICE_KEY.to_string(), "- a list".to_string())]);
+        assert_eq!(
+            parse("authz-overrides", &invalid).unwrap_err(),
+            "key 'service' of ConfigMap 'authz-overrides' is not a YAML mapping"
+        );
+    }
+
+    #[test]
+    fn test_invalid_patch_is_rejected() {
+        let patch = json!({ "spec": { "replicas": "three" } });
+        assert!(matches!(
+            apply(&deployment(), Some(&patch)),
+            Err(ControllerError::InvalidOverrides(_))
+        ));
+    }
+}
@@ -0,0 +1,170 @@
+//! Catches panics raised while reconciling one resource so they fail only that
+//! resource instead of unwinding through the controller and stopping the
+//! operator.
+
+use crate::controller::{ControllerError, ControllerResult};
+use crate::metrics;
+use crate::types::OpenFGACondition;
+use futures::FutureExt;
+use k8s_openapi::NamespaceResourceScope;
+use kube::api::{Api, Patch, PatchParams};
+use kube::{Client, Resource, ResourceExt};
+use serde::de::DeserializeOwned;
+use serde_json::json;
+use std::any::Any;
+use std::fmt::Debug;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::sync::Arc;
+use tracing::{error, warn};
+
+/// Message carried by a panic payload, for `panic!("...")` and `.unwrap()` style panics.
+pub fn panic_message(payload: &(dyn Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "panic with a non-string payload".to_string())
+}
+
+/// Conditions after a panic: `Ready` becomes false with reason `ReconcilePanicked`,
+/// every other condition is kept.
+pub fn panicked_conditions(
+    previous: &[OpenFGACondition],
+    message: &str,
+    generation: Option<i64>,
+) -> Vec<OpenFGACondition> {
+    let mut conditions: Vec<OpenFGACondition> = previous
+        .iter()
+        .filter(|c| c.type_ != "Ready")
+        .cloned()
+        .collect();
+    conditions.insert(
+        0,
+        OpenFGACondition::new(
+            "Ready",
+            false,
+            "ReconcilePanicked",
+            &format!("Reconciliation failed: {}", message),
+            generation,
+            Some(previous),
+        ),
+    );
+    conditions
+}
+
+/// Runs `reconcile`, turning a panic into [`ControllerError::Panic`] after
+/// logging it with the resource identity, counting it and marking the resource
+/// not Ready.
+pub async fn isolate_panics<K, T>(
+    client: Client,
+    controller: &str,
+    resource: Arc<K>,
+    reconcile: impl Future<Output = ControllerResult<T>>,
+) -> ControllerResult<T>
+where
+    K: Resource<DynamicType = (), Scope = NamespaceResourceScope>
+        + Clone
+        + DeserializeOwned
+        + serde::Serialize
+        + Debug,
+{
+    let payload = match AssertUnwindSafe(reconcile).catch_unwind().await {
+        Ok(result) => return result,
+        Err(payload) => payload,
+    };
+
+    let message = panic_message(payload.as_ref());
+    let ns = resource.namespace().unwrap_or_default();
+    let name = resource.name_any();
+    error!(
+        event = "reconcile_panicked",
+        controller = controller,
+        kind = %K::kind(&()),
+        namespace = %ns,
+        resource_name = %name,
+        panic_message = %message,
+        "Reconciliation panicked, marking the resource failed"
+    );
+    metrics::metrics()
+        .reconcile_panics_total
+        .with_label_values(&[controller])
+        .inc();
+
+    if let Err(e) = mark_failed(&client, resource.as_ref(), &message).await {
+        warn!(
+            event = "panic_status_update_failed",
+            kind = %K::kind(&()),
+            namespace = %ns,
+            resource_name = %name,
+            error = %e,
+            "Failed to record the panic in the resource status"
+        );
+    }
+
+    Err(ControllerError::Panic(message))
+}
+
+async fn mark_failed<K>(client: &Client, resource: &K, message: &str) -> ControllerResult<()>
+where
+    K: Resource<DynamicType = (), Scope = NamespaceResourceScope>
+        + Clone
+        + DeserializeOwned
+        + serde::Serialize
+        + Debug,
+{
+    let value = serde_json::to_value(resource)?;
+    let previous: Vec<OpenFGACondition> = value
+        .pointer("/status/conditions")
+        .cloned()
+        .map(serde_json::from_value)
+        .transpose()?
+        .unwrap_or_default();
+    let conditions = panicked_conditions(&previous, message, resource.meta().generation);
+
+    let api: Api<K> = Api::namespaced(client.clone(), &resource.namespace().unwrap_or_default());
+    api.patch_status(
+        &resource.name_any(),
+        &PatchParams::default(),
+        &Patch::Merge(&json!({ "status": { "conditions": conditions } })),
+    )
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_panic_message() {
+        let payload = std::panic::catch_unwind(|| panic!("boom {}", 1)).unwrap_err();
+        assert_eq!(panic_message(payload.as_ref()), "boom 1");
+        let payload = std::panic::catch_unwind(|| panic!("static")).unwrap_err();
+        assert_eq!(panic_message(payload.as_ref()), "static");
+        let payload = std::panic::catch_unwind(|| std::panic::panic_any(42)).unwrap_err();
+        assert_eq!(
+            panic_message(payload.as_ref()),
+            "panic with a non-string payload"
+        );
+    }
+
+    #[test]
+    fn test_panicked_conditions() {
+        let previous = vec![
+            OpenFGACondition::new("Ready", true, "ReplicasReady", "", Some(1), None),
+            OpenFGACondition::new("Progressing", false, "RolloutComplete", "", Some(1), None),
+        ];
+        let conditions = panicked_conditions(&previous, "index out of bounds", Some(2));
+
+        assert_eq!(conditions.len(), 2);
+        assert_eq!(conditions[0].type_, "Ready");
+        assert_eq!(conditions[0].status, "False");
+        assert_eq!(conditions[0].reason.as_deref(), Some("ReconcilePanicked"));
+        assert_eq!(
+            conditions[0].message.as_deref(),
+            Some("Reconciliation failed: index out of bounds")
+        );
+        assert_eq!(conditions[1].type_, "Progressing");
+    }
+}
@@ -0,0 +1,556 @@
+//! Authentication in front of the OpenFGA playground. With
+//! `playground.authProxy` set, a proxy sidecar forwards authenticated requests
+//! to the playground port on localhost, and a separate `<name>-playground`
+//! Service exposes only the proxy; the main Service no longer carries the
+//! playground port.
+//!
+//! `OAuth2Proxy` runs oauth2-proxy configured from the `OAUTH2_PROXY_*`
+//! variables in the referenced Secret. `BasicAuth` runs nginx with a generated
+//! config that checks the htpasswd file under the Secret's `auth` key.
+//!
+//! With `DISALLOW_PLAYGROUND=true` in the operator's environment the playground
+//! is off limits cluster-wide. OpenFGA serves it unless told otherwise, so the
+//! controller sets `OPENFGA_PLAYGROUND_ENABLED=false` on every instance, over
+//! whatever `spec.env`, `spec.envFrom` or `spec.config` say. The webhook
+//! rejects enabling it through `playground.enabled`, `spec.config` or
+//! `spec.env`, and instances admitted before the policy get a `PolicyViolation`
+//! event, or a pending change for it in dry-run mode.
+
+use crate::apply;
+use crate::controller::ControllerResult;
+use crate::ingress::is_owned_by;
+use crate::labels;
+use crate::server_config;
+use crate::types::{AuthProxyType, OpenFGA, OpenFGASpec, PlaygroundAuthProxy};
+use k8s_openapi::api::core::v1::{
+    Capabilities, ConfigMap, ConfigMapVolumeSource, Container, ContainerPort, EnvFromSource,
+    EnvVar, Probe, SecretEnvSource, SecretVolumeSource, SecurityContext, Service, ServicePort,
+    ServiceSpec, TCPSocketAction, Volume, VolumeMount,
+};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use k8s_openapi::apimachinery::pkg::util::intstr::IntOrString;
+use kube::api::{Api, DeleteParams, Patch};
+use kube::runtime::events::{Event, EventType, Recorder};
+use kube::{Client, Resource, ResourceExt};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tracing::{debug, info, instrument, warn};
+
+pub const PROXY_CONTAINER: &str = "playground-proxy";
+pub const OAUTH2_PROXY_IMAGE: &str = "quay.io/oauth2-proxy/oauth2-proxy:v7.6.0";
+pub const NGINX_IMAGE: &str = "nginxinc/nginx-unprivileged:1.25-alpine";
+
+/// Operator environment variable that, set to `true`, forbids the playground.
+pub const DISALLOW_PLAYGROUND_ENV: &str = "DISALLOW_PLAYGROUND";
+
+/// OpenFGA's switch for the playground, on by default.
+pub const PLAYGROUND_ENABLED_ENV: &str = "OPENFGA_PLAYGROUND_ENABLED";
+
+const NGINX_CONFIG_VOLUME: &str = "playground-proxy-config";
+const HTPASSWD_VOLUME: &str = "playground-proxy-htpasswd";
+
+pub fn service_name(name: &str) -> String {
+    format!("{}-playground", name)
+}
+
+pub fn config_map_name(name: &str) -> String {
+    format!("{}-playground-proxy", name)
+}
+
+/// The proxy to run, if the playground is enabled behind one.
+pub fn auth_proxy(openfga: &OpenFGA) -> Option<&PlaygroundAuthProxy> {
+    let playground = &openfga.spec.playground;
+    playground
+        .auth_proxy
+        .as_ref()
+        .filter(|_| playground.enabled)
+}
+
+/// Whether the operator's policy forbids the playground.
+pub fn disallowed() -> bool {
+    disallowed_by(std::env::var(DISALLOW_PLAYGROUND_ENV).ok().as_deref())
+}
+
+fn disallowed_by(value: Option<&str>) -> bool {
+    value.is_some_and(|value| value.trim().eq_ignore_ascii_case("true"))
+}
+
+/// Every way `spec` enables the playground against the policy.
+pub fn policy_violations(spec: &OpenFGASpec, disallowed: bool) -> Vec<String> {
+    if !disallowed {
+        return vec![];
+    }
+    let mut violations = Vec::new();
+    if spec.playground.enabled {
+        violations.push("spec.playground.enabled".to_string());
+    }
+    if server_config::render(&spec.config)
+        .get(PLAYGROUND_ENABLED_ENV)
+        .is_some_and(|value| value != "false")
+    {
+        violations.push("spec.config playground.enabled".to_string());
+    }
+    if spec
+        .env
+        .iter()
+        .any(|var| var.name == PLAYGROUND_ENABLED_ENV && var.value.as_deref() != Some("false"))
+    {
+        violations.push(format!("spec.env {}", PLAYGROUND_ENABLED_ENV));
+    }
+    violations
+        .into_iter()
+        .map(|field| {
+            format!(
+                "{} is disallowed by the operator's policy ({}=true)",
+                field, DISALLOW_PLAYGROUND_ENV
+            )
+        })
+        .collect()
+}
+
+/// Turns the playground of `openfga` off, overriding every other source of
+/// OpenFGA's setting: `spec.env` wins over `envFrom` and the config ConfigMap.
+pub fn disable(openfga: &mut OpenFGA) {
+    openfga.spec.playground.enabled = false;
+    openfga
+        .spec
+        .env
+        .retain(|var| var.name != PLAYGROUND_ENABLED_ENV);
+    openfga.spec.env.push(EnvVar {
+        name: PLAYGROUND_ENABLED_ENV.to_string(),
+        value: Some("false".to_string()),
+        ..Default::default()
+    });
+}
+
+/// `openfga` as the controller reconciles it under the playground policy, and
+/// how it enabled the playground against it: unchanged unless the playground
+/// is `disallowed`, in which case it is turned off.
+pub fn apply_policy(openfga: Arc<OpenFGA>, disallowed: bool) -> (Arc<OpenFGA>, Vec<String>) {
+    if !disallowed {
+        return (openfga, vec![]);
+    }
+    let violations = policy_violations(&openfga.spec, true);
+    let mut openfga = (*openfga).clone();
+    disable(&mut openfga);
+    (Arc::new(openfga), violations)
+}
+
+/// Note of the `PolicyViolation` event for `violations`.
+pub fn violation_note(violations: &[String]) -> String {
+    format!("{}; the playground is not deployed", violations.join("; "))
+}
+
+/// Publishes `violations` of the playground policy as a `PolicyViolation` event.
+pub async fn report_violations(client: &Client, openfga: &OpenFGA, violations: &[String]) {
+    if violations.is_empty() {
+        return;
+    }
+    let ns = openfga.namespace().unwrap_or_default();
+    let name = openfga.name_any();
+    warn!(
+        event = "playground_disallowed",
+        namespace = %ns,
+        resource_name = %name,
+        "Ignoring the playground of an instance, the operator's policy disallows it"
+    );
+
+    let recorder = Recorder::new(
+        client.clone(),
+        "openfga-controller".into(),
+        openfga.object_ref(&()),
+    );
+    let event = Event {
+        type_: EventType::Warning,
+        reason: "PolicyViolation".to_string(),
+        note: Some(violation_note(violations)),
+        action: "Reconcile".to_string(),
+        secondary: None,
+    };
+    if let Err(e) = recorder.publish(event).await {
+        warn!(
+            event = "policy_violation_event_failed",
+            namespace = %ns,
+            resource_name = %name,
+            error = %e,
+            "Failed to publish policy violation event"
+        );
+    }
+}
+
+fn nginx_config(openfga: &OpenFGA, proxy: &PlaygroundAuthProxy) -> String {
+    format!(
+        "server {{\n    listen {listen};\n    location / {{\n        auth_basic \"OpenFGA Playground\";\n        auth_basic_user_file /etc/nginx/htpasswd/auth;\n        proxy_pass http://127.0.0.1:{upstream};\n        proxy_set_header Host $host;\n    }}\n}}\n",
+        listen = proxy.port,
+        upstream = openfga.spec.playground.port,
+    )
+}
+
+/// The sidecar for `proxy`, reaching the playground over localhost.
+pub fn proxy_container(openfga: &OpenFGA, proxy: &PlaygroundAuthProxy) -> Container {
+    let upstream = format!("http://127.0.0.1:{}", openfga.spec.playground.port);
+    let (default_image, args, env_from, volume_mounts) = match proxy.type_ {
+        AuthProxyType::OAuth2Proxy => (
+            OAUTH2_PROXY_IMAGE,
+            Some(vec![
+                format!("--http-address=0.0.0.0:{}", proxy.port),
+                format!("--upstream={}/", upstream),
+                "--reverse-proxy=true".to_string(),
+            ]),
+            Some(vec![EnvFromSource {
+                secret_ref: Some(SecretEnvSource {
+                    name: Some(proxy.secret_name.clone()),
+                    optional: Some(false),
+                }),
+                ..Default::default()
+            }]),
+            None,
+        ),
+        AuthProxyType::BasicAuth => (
+            NGINX_IMAGE,
+            None,
+            None,
+            Some(vec![
+                VolumeMount {
+                    name: NGINX_CONFIG_VOLUME.to_string(),
+                    mount_path: "/etc/nginx/conf.d".to_string(),
+                    read_only: Some(true),
+                    ..Default::default()
+                },
+                VolumeMount {
+                    name: HTPASSWD_VOLUME.to_string(),
+                    mount_path: "/etc/nginx/htpasswd".to_string(),
+                    read_only: Some(true),
+                    ..Default::default()
+                },
+            ]),
+        ),
+    };
+
+    Container {
+        name: PROXY_CONTAINER.to_string(),
+        image: Some(
+            proxy
+                .image
+                .clone()
+                .unwrap_or_else(|| default_image.to_string()),
+        ),
+        args,
+        env_from,
+        ports: Some(vec![ContainerPort {
+            container_port: proxy.port,
+            name: Some("playground-auth".to_string()),
+            protocol: Some("TCP".to_string()),
+            ..Default::default()
+        }]),
+        readiness_probe: Some(Probe {
+            tcp_socket: Some(TCPSocketAction {
+                port: IntOrString::Int(proxy.port),
+                ..Default::default()
+            }),
+            period_seconds: Some(10),
+            ..Default::default()
+        }),
+        security_context: Some(SecurityContext {
+            allow_privilege_escalation: Some(false),
+            run_as_non_root: Some(true),
+            capabilities: Some(Capabilities {
+                drop: Some(vec!["ALL".to_string()]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }),
+        volume_mounts,
+        ..Default::default()
+    }
+}
+
+/// Volumes the sidecar mounts; only the nginx proxy needs any.
+pub fn proxy_volumes(name: &str, proxy: &PlaygroundAuthProxy) -> Vec<Volume> {
+    match proxy.type_ {
+        AuthProxyType::OAuth2Proxy => vec![],
+        AuthProxyType::BasicAuth => vec![
+            Volume {
+                name: NGINX_CONFIG_VOLUME.to_string(),
+                config_map: Some(ConfigMapVolumeSource {
+                    name: Some(config_map_name(name)),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            Volume {
+                name: HTPASSWD_VOLUME.to_string(),
+                secret: Some(SecretVolumeSource {
+                    secret_name: Some(proxy.secret_name.clone()),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+        ],
+    }
+}
+
+fn metadata(openfga: &OpenFGA, ns: &str, name: String, instance: &str) -> ObjectMeta {
+    ObjectMeta {
+        name: Some(name),
+        namespace: Some(ns.to_string()),
+        labels: Some(labels::instance_labels(openfga, instance)),
+        annotations: labels::instance_annotations(openfga, []),
+        owner_references: openfga.controller_owner_ref(&()).map(|o| vec![o]),
+        ..Default::default()
+    }
+}
+
+pub fn create_service(
+    openfga: &OpenFGA,
+    ns: &str,
+    name: &str,
+    proxy: &PlaygroundAuthProxy,
+) -> Service {
+    Service {
+        metadata: metadata(openfga, ns, service_name(name), name),
+        spec: Some(ServiceSpec {
+            selector: Some(labels::selector_labels(name)),
+            ports: Some(vec![ServicePort {
+                name: Some("playground".to_string()),
+                port: proxy.port,
+                target_port: Some(IntOrString::String("playground-auth".to_string())),
+                protocol: Some("TCP".to_string()),
+                ..Default::default()
+            }]),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+pub fn create_config_map(
+    openfga: &OpenFGA,
+    ns: &str,
+    name: &str,
+    proxy: &PlaygroundAuthProxy,
+) -> Option<ConfigMap> {
+    (proxy.type_ == AuthProxyType::BasicAuth).then(|| ConfigMap {
+        metadata: metadata(openfga, ns, config_map_name(name), name),
+        data: Some(BTreeMap::from([(
+            "default.conf".to_string(),
+            nginx_config(openfga, proxy),
+        )])),
+        ..Default::default()
+    })
+}
+
+async fn remove_owned<K>(api: &Api<K>, openfga: &OpenFGA, name: &str) -> ControllerResult<()>
+where
+    K: Resource + Clone + serde::de::DeserializeOwned + std::fmt::Debug,
+{
+    if let Some(existing) = api.get_opt(name).await? {
+        if is_owned_by(existing.owner_references(), openfga) {
+            api.delete(name, &DeleteParams::default()).await?;
+            debug!(
+                event = "playground_proxy_resource_deleted",
+                namespace = %existing.namespace().unwrap_or_default(),
+                resource_name = %name,
+                "Deleted playground proxy resource that is no longer configured"
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Applies the playground Service and nginx config, or removes them once the
+/// proxy is no longer configured.
+#[instrument(skip(client, openfga), fields(namespace = %ns, name = %name))]
+pub async fn reconcile_playground(
+    client: &Client,
+    openfga: &OpenFGA,
+    ns: &str,
+    name: &str,
+) -> ControllerResult<()> {
+    let services: Api<Service> = Api::namespaced(client.clone(), ns);
+    let config_maps: Api<ConfigMap> = Api::namespaced(client.clone(), ns);
+
+    let Some(proxy) = auth_proxy(openfga) else {
+        remove_owned(&services, openfga, &service_name(name)).await?;
+        return remove_owned(&config_maps, openfga, &config_map_name(name)).await;
+    };
+
+    match create_config_map(openfga, ns, name, proxy) {
+        Some(config_map) => {
+            config_maps
+                .patch(
+                    &config_map_name(name),
+                    &apply::apply_params(),
+                    &Patch::Apply(&config_map),
+                )
+                .await?;
+        }
+        None => remove_owned(&config_maps, openfga, &config_map_name(name)).await?,
+    }
+    let service = create_service(openfga, ns, name, proxy);
+    services
+        .patch(
+            &service_name(name),
+            &apply::apply_params(),
+            &Patch::Apply(&service),
+        )
+        .await?;
+    info!(
+        event = "playground_proxy_applied",
+        namespace = %ns,
+        resource_name = %name,
+        proxy = ?proxy.type_,
+        "Applied authenticated playground Service"
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::{create_deployment, test_openfga};
+
+    fn openfga(auth_proxy: serde_json::Value) -> OpenFGA {
+        test_openfga(serde_json::json!({
+            "playground": { "enabled": true, "authProxy": auth_proxy },
+        }))
+    }
+
+    #[test]
+    fn test_playground_policy() {
+        assert!(!disallowed_by(None));
+        assert!(!disallowed_by(Some("false")));
+        assert!(disallowed_by(Some("TRUE")));
+
+        let openfga = openfga(serde_json::Value::Null);
+        assert!(policy_violations(&openfga.spec, false).is_empty());
+        assert_eq!(
+            policy_violations(&openfga.spec, true),
+            ["spec.playground.enabled is disallowed by the operator's policy (DISALLOW_PLAYGROUND=true)"]
+        );
+
+        let mut disabled = openfga.clone();
+        disabled.spec.playground.enabled = false;
+        assert!(policy_violations(&disabled.spec, true).is_empty());
+
+        // Enabling it around spec.playground
+        let mut configured = disabled.clone();
+        configured.spec.config = serde_json::from_value(serde_json::json!({
+            "playground": { "enabled": true }
+        }))
+        .unwrap();
+        configured.spec.env = vec![EnvVar {
+            name: PLAYGROUND_ENABLED_ENV.to_string(),
+            value: Some("true".to_string()),
+            ..Default::default()
+        }];
+        let violations = policy_violations(&configured.spec, true);
+        assert_eq!(violations.len(), 2);
+        assert!(violations[0].starts_with("spec.config playground.enabled"));
+        assert!(violations[1].starts_with("spec.env OPENFGA_PLAYGROUND_ENABLED"));
+
+        // Turning it off explicitly is fine
+        configured.spec.config = serde_json::from_value(serde_json::json!({
+            "playground.enabled": false
+        }))
+        .unwrap();
+        configured.spec.env[0].value = Some("false".to_string());
+        assert!(policy_violations(&configured.spec, true).is_empty());
+    }
+
+    #[test]
+    fn test_disable_overrides_user_env() {
+        let mut openfga = openfga(serde_json::Value::Null);
+        openfga.spec.env = vec![EnvVar {
+            name: PLAYGROUND_ENABLED_ENV.to_string(),
+            value: Some("true".to_string()),
+            ..Default::default()
+        }];
+        disable(&mut openfga);
+        assert!(!openfga.spec.playground.enabled);
+        let deployment = create_deployment(&openfga, "auth", "authz").unwrap();
+        let env = deployment.spec.unwrap().template.spec.unwrap().containers[0]
+            .env
+            .clone()
+            .unwrap();
+        let playground: Vec<_> = env
+            .iter()
+            .filter(|var| var.name == PLAYGROUND_ENABLED_ENV)
+            .collect();
+        assert_eq!(playground.len(), 1);
+        assert_eq!(playground[0].value.as_deref(), Some("false"));
+    }
+
+    #[test]
+    fn test_apply_policy() {
+        let enabled = Arc::new(openfga(serde_json::Value::Null));
+        let (allowed, violations) = apply_policy(enabled.clone(), false);
+        assert!(allowed.spec.playground.enabled);
+        assert!(violations.is_empty());
+
+        let (policed, violations) = apply_policy(enabled, true);
+        assert!(!policed.spec.playground.enabled);
+        assert_eq!(violations.len(), 1);
+
+        // Instances that leave it off are still turned off, without violations
+        let (policed, violations) = apply_policy(policed, true);
+        assert!(!policed.spec.playground.enabled);
+        assert!(violations.is_empty());
+    }
+
+    #[test]
+    fn test_oauth2_proxy_sidecar() {
+        let openfga = openfga(serde_json::json!({
+            "type": "OAuth2Proxy",
+            "secretName": "playground-oidc",
+        }));
+        let proxy = auth_proxy(&openfga).unwrap();
+        let container = proxy_container(&openfga, proxy);
+
+        assert_eq!(container.image.as_deref(), Some(OAUTH2_PROXY_IMAGE));
+        let args = container.args.unwrap();
+        assert!(args.contains(&"--http-address=0.0.0.0:4180".to_string()));
+        assert!(args.contains(&"--upstream=http://127.0.0.1:3000/".to_string()));
+        let secret = container.env_from.unwrap()[0].secret_ref.clone().unwrap();
+        assert_eq!(secret.name.as_deref(), Some("playground-oidc"));
+        assert!(proxy_volumes("authz", proxy).is_empty());
+        assert!(create_config_map(&openfga, "ns", "authz", proxy).is_none());
+
+        let service = create_service(&openfga, "ns", "authz", proxy);
+        assert_eq!(service.metadata.name.as_deref(), Some("authz-playground"));
+        assert_eq!(service.spec.unwrap().ports.unwrap()[0].port, 4180);
+    }
+
+    #[test]
+    fn test_basic_auth_sidecar() {
+        let openfga = openfga(serde_json::json!({
+            "type": "BasicAuth",
+            "port": 8443,
+            "secretName": "playground-htpasswd",
+        }));
+        let proxy = auth_proxy(&openfga).unwrap();
+        let container = proxy_container(&openfga, proxy);
+        assert_eq!(container.image.as_deref(), Some(NGINX_IMAGE));
+        assert_eq!(container.volume_mounts.unwrap().len(), 2);
+
+        let volumes = proxy_volumes("authz", proxy);
+        assert_eq!(
+            volumes[1].secret.as_ref().unwrap().secret_name.as_deref(),
+            Some("playground-htpasswd")
+        );
+        let config = create_config_map(&openfga, "ns", "authz", proxy).unwrap();
+        let conf = &config.data.unwrap()["default.conf"];
+        assert!(conf.contains("listen 8443;"));
+        assert!(conf.contains("proxy_pass http://127.0.0.1:3000;"));
+    }
+
+    #[test]
+    fn test_no_proxy_when_playground_disabled() {
+        let mut openfga = openfga(serde_json::json!({
+            "type": "BasicAuth",
+            "secretName": "playground-htpasswd",
+        }));
+        openfga.spec.playground.enabled = false;
+        assert!(auth_proxy(&openfga).is_none());
+    }
+}
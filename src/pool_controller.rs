@@ -0,0 +1,454 @@
+use crate::controller::{shutdown_requested, ControllerError, ControllerResult, Shutdown};
+use crate::metrics;
+use crate::panic_isolation::isolate_panics;
+use crate::types::{
+    OpenFGA, OpenFGAClaim, OpenFGAClaimStatus, OpenFGACondition, OpenFGAPool, OpenFGAPoolStatus,
+};
+use futures::StreamExt;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use kube::api::{Api, DeleteParams, ListParams, Patch, PatchParams, PostParams};
+use kube::runtime::controller::{Action, Controller};
+use kube::runtime::watcher::Config;
+use kube::{Client, Resource, ResourceExt};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tokio::time::Duration;
+use tracing::{debug, error, info, instrument, warn};
+
+const POOL_CONTROLLER_NAME: &str = "openfga-pool-controller";
+const CLAIM_CONTROLLER_NAME: &str = "openfga-claim-controller";
+
+/// Label carrying the name of the pool an instance was provisioned by.
+pub const POOL_LABEL: &str = "openfga.dev/pool";
+/// Label carrying the name of the claim an instance is assigned to.
+pub const CLAIM_LABEL: &str = "openfga.dev/claim";
+
+pub struct OpenFGAPoolController {
+    client: Client,
+}
+
+impl OpenFGAPoolController {
+    pub fn new(client: Client) -> Self {
+        Self { client }
+    }
+
+    pub async fn run(self, shutdown: Shutdown) {
+        let pools: Api<OpenFGAPool> = Api::all(self.client.clone());
+        let claims: Api<OpenFGAClaim> = Api::all(self.client.clone());
+        let instances: Api<OpenFGA> = Api::all(self.client.clone());
+        let ctx = Arc::new(self);
+
+        info!(
+            controller = POOL_CONTROLLER_NAME,
+            "Starting controllers with OpenFGAPool and OpenFGAClaim resource monitoring"
+        );
+
+        let _pool_watches = metrics::track_watch_streams(POOL_CONTROLLER_NAME, 2);
+        let _claim_watches = metrics::track_watch_streams(CLAIM_CONTROLLER_NAME, 1);
+        let pool_controller = Controller::new(pools, Config::default().any_semantic())
+            .owns(instances, Config::default().any_semantic())
+            .graceful_shutdown_on(shutdown_requested(shutdown.clone()))
+            .run(
+                |pool, ctx| {
+                    let guarded = isolate_panics(
+                        ctx.client.clone(),
+                        POOL_CONTROLLER_NAME,
+                        pool.clone(),
+                        reconcile_pool(pool.clone(), ctx),
+                    );
+                    metrics::observe_reconcile(POOL_CONTROLLER_NAME, pool, guarded)
+                },
+                pool_error_policy,
+                ctx.clone(),
+            )
+            .for_each(|res| async move {
+                match res {
+                    Ok(o) => debug!(
+                        reconciliation_result = "success",
+                        object = ?o,
+                        "OpenFGAPool reconciliation completed successfully"
+                    ),
+                    Err(e) => error!(
+                        reconciliation_result = "error",
+                        error = %e,
+                        "OpenFGAPool reconciliation failed"
+                    ),
+                }
+            });
+
+        let claim_controller = Controller::new(claims, Config::default().any_semantic())
+            .graceful_shutdown_on(shutdown_requested(shutdown.clone()))
+            .run(
+                |claim, ctx| {
+                    let guarded = isolate_panics(
+                        ctx.client.clone(),
+                        CLAIM_CONTROLLER_NAME,
+                        claim.clone(),
+                        reconcile_claim(claim.clone(), ctx),
+                    );
+                    metrics::observe_reconcile(CLAIM_CONTROLLER_NAME, claim, guarded)
+                },
+                claim_error_policy,
+                ctx,
+            )
+            .for_each(|res| async move {
+                match res {
+                    Ok(o) => debug!(
+                        reconciliation_result = "success",
+                        object = ?o,
+                        "OpenFGAClaim reconciliation completed successfully"
+                    ),
+                    Err(e) => error!(
+                        reconciliation_result = "error",
+                        error = %e,
+                        "OpenFGAClaim reconciliation failed"
+                    ),
+                }
+            });
+
+        futures::future::join(pool_controller, claim_controller).await;
+    }
+}
+
+/// Whether the instance reports the `Ready` condition as true.
+pub fn is_ready(openfga: &OpenFGA) -> bool {
+    openfga
+        .status
+        .as_ref()
+        .and_then(|s| s.conditions.as_ref())
+        .is_some_and(|conditions| {
+            conditions
+                .iter()
+                .any(|c| c.type_ == "Ready" && c.status == "True")
+        })
+}
+
+fn is_claimed(openfga: &OpenFGA) -> bool {
+    openfga.labels().contains_key(CLAIM_LABEL)
+}
+
+/// What the pool reconciler needs to do to get back to `size` unclaimed instances.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PoolPlan {
+    pub create: usize,
+    /// Surplus unclaimed instances, not-ready ones first.
+    pub delete: Vec<String>,
+    pub available: i32,
+    pub warming: i32,
+    pub claimed: i32,
+}
+
+pub fn plan_pool(instances: &[OpenFGA], size: i32) -> PoolPlan {
+    let size = size.max(0) as usize;
+    let mut unclaimed: Vec<&OpenFGA> = instances.iter().filter(|i| !is_claimed(i)).collect();
+    unclaimed.sort_by_key(|i| (is_ready(i), i.name_any()));
+
+    let available = unclaimed.iter().filter(|i| is_ready(i)).count();
+    PoolPlan {
+        create: size.saturating_sub(unclaimed.len()),
+        delete: unclaimed
+            .iter()
+            .take(unclaimed.len().saturating_sub(size))
+            .map(|i| i.name_any())
+            .collect(),
+        available: available as i32,
+        warming: (unclaimed.len() - available) as i32,
+        claimed: (instances.len() - unclaimed.len()) as i32,
+    }
+}
+
+/// Picks the unclaimed instance to hand out, preferring ready ones.
+pub fn pick_instance(instances: &[OpenFGA]) -> Option<&OpenFGA> {
+    instances
+        .iter()
+        .filter(|i| !is_claimed(i))
+        .min_by_key(|i| (!is_ready(i), i.name_any()))
+}
+
+#[instrument(skip(ctx), fields(namespace = %pool.namespace().unwrap_or_default(), name = %pool.name_any(), generation = pool.metadata.generation.unwrap_or_default()))]
+async fn reconcile_pool(
+    pool: Arc<OpenFGAPool>,
+    ctx: Arc<OpenFGAPoolController>,
+) -> ControllerResult<Action> {
+    let ns = pool.namespace().unwrap_or_default();
+    let name = pool.name_any();
+    let instances: Api<OpenFGA> = Api::namespaced(ctx.client.clone(), &ns);
+
+    let members = instances
+        .list(&ListParams::default().labels(&format!("{}={}", POOL_LABEL, name)))
+        .await?
+        .items;
+    let plan = plan_pool(&members, pool.spec.size);
+
+    for _ in 0..plan.create {
+        let instance = OpenFGA {
+            metadata: ObjectMeta {
+                generate_name: Some(format!("{}-", name)),
+                namespace: Some(ns.clone()),
+                labels: Some(BTreeMap::from([(POOL_LABEL.to_string(), name.clone())])),
+                owner_references: pool.controller_owner_ref(&()).map(|o| vec![o]),
+                ..Default::default()
+            },
+            spec: pool.spec.template.clone(),
+            status: None,
+        };
+        let created = instances.create(&PostParams::default(), &instance).await?;
+        info!(
+            event = "pool_instance_created",
+            namespace = %ns,
+            pool = %name,
+            instance = %created.name_any(),
+            "Provisioned warm instance"
+        );
+    }
+
+    for surplus in &plan.delete {
+        instances.delete(surplus, &DeleteParams::default()).await?;
+        info!(
+            event = "pool_instance_deleted",
+            namespace = %ns,
+            pool = %name,
+            instance = %surplus,
+            "Removed surplus warm instance"
+        );
+    }
+
+    let previous = pool.status.as_ref().and_then(|s| s.conditions.as_deref());
+    let full = plan.available >= pool.spec.size;
+    let status = OpenFGAPoolStatus {
+        available: Some(plan.available),
+        warming: Some(plan.warming + plan.create as i32),
+        claimed: Some(plan.claimed),
+        conditions: Some(vec![OpenFGACondition::new(
+            "Ready",
+            full,
+            if full { "PoolFull" } else { "Replenishing" },
+            &format!("{}/{} warm instances ready", plan.available, pool.spec.size),
+            pool.metadata.generation,
+            previous,
+        )]),
+    };
+    let pools: Api<OpenFGAPool> = Api::namespaced(ctx.client.clone(), &ns);
+    pools
+        .patch_status(
+            &name,
+            &PatchParams::default(),
+            &Patch::Merge(&serde_json::json!({ "status": status })),
+        )
+        .await?;
+
+    // Claims move instances out of the pool without touching it, so poll for them
+    Ok(Action::requeue(Duration::from_secs(if full {
+        30
+    } else {
+        10
+    })))
+}
+
+#[instrument(skip(ctx), fields(namespace = %claim.namespace().unwrap_or_default(), name = %claim.name_any(), generation = claim.metadata.generation.unwrap_or_default()))]
+async fn reconcile_claim(
+    claim: Arc<OpenFGAClaim>,
+    ctx: Arc<OpenFGAPoolController>,
+) -> ControllerResult<Action> {
+    let ns = claim.namespace().unwrap_or_default();
+    let name = claim.name_any();
+    let pool_name = &claim.spec.pool_ref.name;
+    let instances: Api<OpenFGA> = Api::namespaced(ctx.client.clone(), &ns);
+    let previous = claim.status.as_ref().and_then(|s| s.conditions.as_deref());
+    let condition = |bound: bool, reason: &str, message: &str| {
+        OpenFGACondition::new(
+            "Bound",
+            bound,
+            reason,
+            message,
+            claim.metadata.generation,
+            previous,
+        )
+    };
+
+    let assigned = claim.status.as_ref().and_then(|s| s.instance_name.clone());
+    let (status, requeue) = match assigned {
+        Some(instance) if instances.get_opt(&instance).await?.is_some() => {
+            return Ok(Action::requeue(Duration::from_secs(300)));
+        }
+        Some(instance) => (
+            OpenFGAClaimStatus {
+                instance_name: Some(instance.clone()),
+                conditions: Some(vec![condition(
+                    false,
+                    "InstanceLost",
+                    &format!("assigned instance '{}' no longer exists", instance),
+                )]),
+            },
+            Duration::from_secs(300),
+        ),
+        None => {
+            let members = instances
+                .list(&ListParams::default().labels(&format!("{}={}", POOL_LABEL, pool_name)))
+                .await?
+                .items;
+
+            match pick_instance(&members) {
+                Some(instance) => {
+                    let instance_name = instance.name_any();
+                    // The resourceVersion precondition makes concurrent claims for the same
+                    // instance fail with a conflict instead of both succeeding
+                    let patch = serde_json::json!({
+                        "metadata": {
+                            "resourceVersion": instance.resource_version(),
+                            "labels": { CLAIM_LABEL: name },
+                            "ownerReferences": claim.controller_owner_ref(&()).map(|o| vec![o]),
+                        }
+                    });
+                    instances
+                        .patch(
+                            &instance_name,
+                            &PatchParams::default(),
+                            &Patch::Merge(&patch),
+                        )
+                        .await?;
+
+                    info!(
+                        event = "claim_bound",
+                        namespace = %ns,
+                        claim = %name,
+                        pool = %pool_name,
+                        instance = %instance_name,
+                        ready = is_ready(instance),
+                        "Assigned pooled instance to claim"
+                    );
+                    (
+                        OpenFGAClaimStatus {
+                            instance_name: Some(instance_name.clone()),
+                            conditions: Some(vec![condition(
+                                true,
+                                "Bound",
+                                &format!("bound to instance '{}'", instance_name),
+                            )]),
+                        },
+                        Duration::from_secs(300),
+                    )
+                }
+                None => {
+                    warn!(
+                        event = "claim_pending",
+                        namespace = %ns,
+                        claim = %name,
+                        pool = %pool_name,
+                        "No unclaimed instance available in pool"
+                    );
+                    (
+                        OpenFGAClaimStatus {
+                            instance_name: None,
+                            conditions: Some(vec![condition(
+                                false,
+                                "PoolExhausted",
+                                &format!("no unclaimed instance in pool '{}'", pool_name),
+                            )]),
+                        },
+                        Duration::from_secs(10),
+                    )
+                }
+            }
+        }
+    };
+
+    let claims: Api<OpenFGAClaim> = Api::namespaced(ctx.client.clone(), &ns);
+    claims
+        .patch_status(
+            &name,
+            &PatchParams::default(),
+            &Patch::Merge(&serde_json::json!({ "status": status })),
+        )
+        .await?;
+
+    Ok(Action::requeue(requeue))
+}
+
+fn pool_error_policy(
+    pool: Arc<OpenFGAPool>,
+    error: &ControllerError,
+    _ctx: Arc<OpenFGAPoolController>,
+) -> Action {
+    warn!(
+        event = "pool_reconciliation_error",
+        namespace = %pool.namespace().unwrap_or_default(),
+        resource_name = %pool.name_any(),
+        error_message = %error,
+        "OpenFGAPool reconciliation failed, retrying"
+    );
+    metrics::record_reconcile_error::<OpenFGAPool>(POOL_CONTROLLER_NAME, error.kind());
+    Action::requeue(Duration::from_secs(30))
+}
+
+fn claim_error_policy(
+    claim: Arc<OpenFGAClaim>,
+    error: &ControllerError,
+    _ctx: Arc<OpenFGAPoolController>,
+) -> Action {
+    warn!(
+        event = "claim_reconciliation_error",
+        namespace = %claim.namespace().unwrap_or_default(),
+        resource_name = %claim.name_any(),
+        error_message = %error,
+        "OpenFGAClaim reconciliation failed, retrying"
+    );
+    metrics::record_reconcile_error::<OpenFGAClaim>(CLAIM_CONTROLLER_NAME, error.kind());
+    // Conflicts come from another claim winning the same instance; pick again quickly
+    Action::requeue(Duration::from_secs(2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::create_test_openfga;
+    use crate::types::OpenFGAStatus;
+
+    fn instance(name: &str, ready: bool, claim: Option<&str>) -> OpenFGA {
+        let mut labels = BTreeMap::from([(POOL_LABEL.to_string(), "pool".to_string())]);
+        if let Some(claim) = claim {
+            labels.insert(CLAIM_LABEL.to_string(), claim.to_string());
+        }
+        let mut openfga = create_test_openfga();
+        openfga.metadata.name = Some(name.to_string());
+        openfga.metadata.labels = Some(labels);
+        openfga.status = Some(OpenFGAStatus {
+            conditions: Some(vec![OpenFGACondition::new(
+                "Ready", ready, "Test", "", None, None,
+            )]),
+            ..Default::default()
+        });
+        openfga
+    }
+
+    #[test]
+    fn test_plan_pool_replenishes_and_trims() {
+        let instances = vec![
+            instance("pool-a", true, None),
+            instance("pool-b", false, None),
+            instance("pool-c", true, Some("tenant")),
+        ];
+
+        let plan = plan_pool(&instances, 4);
+        assert_eq!(plan.create, 2);
+        assert!(plan.delete.is_empty());
+        assert_eq!((plan.available, plan.warming, plan.claimed), (1, 1, 1));
+
+        let plan = plan_pool(&instances, 1);
+        assert_eq!(plan.create, 0);
+        assert_eq!(plan.delete, vec!["pool-b".to_string()]);
+    }
+
+    #[test]
+    fn test_pick_instance_prefers_ready() {
+        let instances = vec![
+            instance("pool-a", false, None),
+            instance("pool-b", true, Some("tenant")),
+            instance("pool-c", true, None),
+        ];
+        assert_eq!(pick_instance(&instances).unwrap().name_any(), "pool-c");
+
+        let claimed = vec![instance("pool-a", true, Some("tenant"))];
+        assert!(pick_instance(&claimed).is_none());
+    }
+}
@@ -0,0 +1,329 @@
+//! Garbage collection of child resources an instance no longer declares. Each
+//! child module removes the objects it knows by name, but objects left behind
+//! under another name, or fields applied by an earlier operator version, would
+//! otherwise linger after spec changes.
+//!
+//! Only objects that carry the instance's owner reference and labels are ever
+//! deleted; the main Deployment, StatefulSet and main Service carry no owner
+//! reference and are managed by the workload reconcile alone.
+
+use crate::controller::ControllerResult;
+use crate::ingress::is_owned_by;
+use crate::labels::MANAGED_BY;
+use crate::playground;
+use crate::server_config;
+use crate::service_account;
+use crate::topology;
+use crate::types::{IngressKind, OpenFGA};
+use k8s_openapi::api::apps::v1::Deployment;
+use k8s_openapi::api::core::v1::{ConfigMap, Service, ServiceAccount, ServicePort};
+use k8s_openapi::api::networking::v1::Ingress;
+use k8s_openapi::api::rbac::v1::{Role, RoleBinding};
+use kube::api::{Api, DeleteParams, ListParams, Patch, PatchParams};
+use kube::{Client, Resource, ResourceExt};
+use serde::de::DeserializeOwned;
+use std::collections::BTreeSet;
+use std::fmt::Debug;
+use tracing::{info, instrument};
+
+/// A child object by kind and name.
+pub type ChildKey = (&'static str, String);
+
+/// The owned children the current spec declares.
+pub fn desired_children(openfga: &OpenFGA, ns: &str, name: &str) -> BTreeSet<ChildKey> {
+    let mut desired = BTreeSet::new();
+    if server_config::create_config_map(openfga, ns, name).is_some() {
+        desired.insert(("ConfigMap", server_config::config_map_name(name)));
+    }
+    if let Some(proxy) = playground::auth_proxy(openfga) {
+        desired.insert(("Service", playground::service_name(name)));
+        if playground::create_config_map(openfga, ns, name, proxy).is_some() {
+            desired.insert(("ConfigMap", playground::config_map_name(name)));
+        }
+    }
+    if openfga
+        .spec
+        .ingress
+        .as_ref()
+        .is_some_and(|config| config.kind == IngressKind::Ingress)
+    {
+        desired.insert(("Ingress", name.to_string()));
+    }
+    if let Some(account) = service_account::create_service_account(openfga, ns, name) {
+        desired.insert(("ServiceAccount", account.name_any()));
+    }
+    if service_account::create_role(openfga, ns, name).is_some() {
+        desired.insert(("Role", name.to_string()));
+        desired.insert(("RoleBinding", name.to_string()));
+    }
+    for (role, _) in topology::shards(openfga) {
+        desired.insert(("Deployment", topology::shard_name(name, role)));
+        desired.insert(("Service", topology::shard_name(name, role)));
+    }
+    desired
+}
+
+/// Deletes the objects of one kind owned by `openfga` that are not in `desired`.
+async fn prune_kind<K>(
+    api: Api<K>,
+    kind: &'static str,
+    selector: &str,
+    openfga: &OpenFGA,
+    desired: &BTreeSet<ChildKey>,
+) -> ControllerResult<Vec<String>>
+where
+    K: Resource + Clone + DeserializeOwned + Debug,
+{
+    let mut deleted = Vec::new();
+    for object in api.list(&ListParams::default().labels(selector)).await? {
+        let key = (kind, object.name_any());
+        if !is_owned_by(object.owner_references(), openfga) || desired.contains(&key) {
+            continue;
+        }
+        api.delete(&key.1, &DeleteParams::default()).await?;
+        deleted.push(format!("{}/{}", kind, key.1));
+    }
+    Ok(deleted)
+}
+
+/// Deletes every owned child the spec no longer declares, returning them as
+/// `Kind/name`.
+#[instrument(skip(client, openfga), fields(namespace = %ns, name = %name))]
+pub async fn prune_children(
+    client: &Client,
+    openfga: &OpenFGA,
+    ns: &str,
+    name: &str,
+) -> ControllerResult<Vec<String>> {
+    let desired = desired_children(openfga, ns, name);
+    let selector = format!(
+        "app.kubernetes.io/instance={},app.kubernetes.io/managed-by={}",
+        name, MANAGED_BY
+    );
+    let mut deleted = Vec::new();
+    deleted.extend(
+        prune_kind(
+            Api::<ConfigMap>::namespaced(client.clone(), ns),
+            "ConfigMap",
+            &selector,
+            openfga,
+            &desired,
+        )
+        .await?,
+    );
+    deleted.extend(
+        prune_kind(
+            Api::<Deployment>::namespaced(client.clone(), ns),
+            "Deployment",
+            &selector,
+            openfga,
+            &desired,
+        )
+        .await?,
+    );
+    deleted.extend(
+        prune_kind(
+            Api::<Service>::namespaced(client.clone(), ns),
+            "Service",
+            &selector,
+            openfga,
+            &desired,
+        )
+        .await?,
+    );
+    deleted.extend(
+        prune_kind(
+            Api::<Ingress>::namespaced(client.clone(), ns),
+            "Ingress",
+            &selector,
+            openfga,
+            &desired,
+        )
+        .await?,
+    );
+    deleted.extend(
+        prune_kind(
+            Api::<ServiceAccount>::namespaced(client.clone(), ns),
+            "ServiceAccount",
+            &selector,
+            openfga,
+            &desired,
+        )
+        .await?,
+    );
+    deleted.extend(
+        prune_kind(
+            Api::<Role>::namespaced(client.clone(), ns),
+            "Role",
+            &selector,
+            openfga,
+            &desired,
+        )
+        .await?,
+    );
+    deleted.extend(
+        prune_kind(
+            Api::<RoleBinding>::namespaced(client.clone(), ns),
+            "RoleBinding",
+            &selector,
+            openfga,
+            &desired,
+        )
+        .await?,
+    );
+    for object in &deleted {
+        info!(
+            event = "orphan_deleted",
+            namespace = %ns,
+            resource_name = %name,
+            object = %object,
+            "Deleted child resource that is no longer declared"
+        );
+    }
+    Ok(deleted)
+}
+
+fn port_key(port: &ServicePort) -> (i32, String) {
+    (
+        port.port,
+        port.protocol.clone().unwrap_or_else(|| "TCP".to_string()),
+    )
+}
+
+/// Ports of `live` that `desired` does not declare. Server-side apply removes
+/// ports the operator applied before, but not ones another field manager owns,
+/// e.g. from operator versions that did not use apply.
+pub fn stale_ports(desired: &Service, live: &Service) -> Vec<ServicePort> {
+    let declared: BTreeSet<(i32, String)> = desired
+        .spec
+        .as_ref()
+        .and_then(|s| s.ports.as_ref())
+        .into_iter()
+        .flatten()
+        .map(port_key)
+        .collect();
+    live.spec
+        .as_ref()
+        .and_then(|s| s.ports.as_ref())
+        .into_iter()
+        .flatten()
+        .filter(|port| !declared.contains(&port_key(port)))
+        .cloned()
+        .collect()
+}
+
+/// Removes ports of the live Service the desired one no longer declares. The
+/// remaining ports keep their live values, such as allocated node ports.
+#[instrument(skip(services, desired, live), fields(name = %live.name_any()))]
+pub async fn prune_service_ports(
+    services: &Api<Service>,
+    desired: &Service,
+    live: &Service,
+) -> ControllerResult<()> {
+    let stale = stale_ports(desired, live);
+    if stale.is_empty() {
+        return Ok(());
+    }
+    let stale_keys: BTreeSet<(i32, String)> = stale.iter().map(port_key).collect();
+    let keep: Vec<&ServicePort> = live
+        .spec
+        .as_ref()
+        .and_then(|s| s.ports.as_ref())
+        .into_iter()
+        .flatten()
+        .filter(|port| !stale_keys.contains(&port_key(port)))
+        .collect();
+    let patch: json_patch::Patch = serde_json::from_value(serde_json::json!([
+        { "op": "replace", "path": "/spec/ports", "value": keep },
+    ]))?;
+    services
+        .patch(
+            &live.name_any(),
+            &PatchParams::default(),
+            &Patch::Json::<()>(patch),
+        )
+        .await?;
+    info!(
+        event = "service_ports_pruned",
+        namespace = %live.namespace().unwrap_or_default(),
+        resource_name = %live.name_any(),
+        ports = ?stale.iter().map(port_key).collect::<Vec<_>>(),
+        "Removed Service ports that are no longer declared"
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::test_openfga;
+    use k8s_openapi::api::core::v1::ServiceSpec;
+    use serde_json::json;
+
+    #[test]
+    fn test_desired_children_follow_spec() {
+        let bare = test_openfga(json!({
+            "datastore": { "engine": "memory" },
+            "serviceAccount": { "create": false },
+        }));
+        assert!(desired_children(&bare, "ns", "authz").is_empty());
+
+        let full = test_openfga(json!({
+            "datastore": { "engine": "postgres", "uri": "postgres://openfga@db/openfga" },
+            "config": { "log": { "level": "debug" } },
+            "playground": {
+                "enabled": true,
+                "authProxy": { "type": "BasicAuth", "secretName": "htpasswd" },
+            },
+            "ingress": { "host": "authz.example.com" },
+            "serviceAccount": {
+                "create": true,
+                "rules": [{ "apiGroups": [""], "resources": ["configmaps"], "verbs": ["get"] }],
+            },
+            "topology": { "read": { "replicas": 3 }, "write": {} },
+        }));
+        let desired = desired_children(&full, "ns", "authz");
+        assert!(desired.contains(&("ConfigMap", server_config::config_map_name("authz"))));
+        assert!(desired.contains(&("ConfigMap", playground::config_map_name("authz"))));
+        assert!(desired.contains(&("Service", playground::service_name("authz"))));
+        assert!(desired.contains(&("Ingress", "authz".to_string())));
+        assert!(desired.contains(&("ServiceAccount", "authz".to_string())));
+        assert!(desired.contains(&("Role", "authz".to_string())));
+        assert!(desired.contains(&("RoleBinding", "authz".to_string())));
+        assert!(desired.contains(&("Deployment", "authz-read".to_string())));
+        assert!(desired.contains(&("Service", "authz-write".to_string())));
+    }
+
+    fn service(ports: &[(i32, &str)]) -> Service {
+        Service {
+            spec: Some(ServiceSpec {
+                ports: Some(
+                    ports
+                        .iter()
+                        .map(|(port, name)| ServicePort {
+                            name: Some(name.to_string()),
+                            port: *port,
+                            protocol: Some("TCP".to_string()),
+                            ..Default::default()
+                        })
+                        .collect(),
+                ),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_stale_ports() {
+        let desired = service(&[(8080, "http"), (8081, "grpc")]);
+        let live = service(&[(8080, "http"), (8081, "grpc"), (3000, "playground")]);
+        let stale = stale_ports(&desired, &live);
+        assert_eq!(stale.len(), 1);
+        assert_eq!(stale[0].port, 3000);
+
+        // A renamed port is the same port
+        let renamed = service(&[(8080, "web"), (8081, "grpc")]);
+        assert!(stale_ports(&desired, &renamed).is_empty());
+    }
+}
@@ -0,0 +1,300 @@
+//! `spec.queryCache`: OpenFGA's check query cache, set through the
+//! `OPENFGA_CHECK_QUERY_CACHE_*` variables so it wins over the server config
+//! ConfigMap. The cache lives in each server's memory and is not shared
+//! between replicas.
+//!
+//! With `sidecar` set, every pod also runs a Redis or memcached, exposed on
+//! the instance's Service as the `cache` port, for clients that cache check
+//! results on their side and want the cache one hop from OpenFGA. OpenFGA
+//! itself does not use it. Nothing is persisted: Redis runs without snapshots
+//! and both engines evict least recently used items beyond `maxMemoryMi`.
+
+use crate::types::{CacheEngine, CacheSidecar, OpenFGA, ResourceQuantities};
+use k8s_openapi::api::core::v1::{
+    Capabilities, Container, ContainerPort, EnvVar, Probe, ResourceRequirements, SecurityContext,
+    ServicePort, TCPSocketAction,
+};
+use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
+use k8s_openapi::apimachinery::pkg::util::intstr::IntOrString;
+use std::collections::BTreeMap;
+
+pub const CACHE_CONTAINER: &str = "query-cache";
+pub const REDIS_IMAGE: &str = "redis:7.2-alpine";
+pub const MEMCACHED_IMAGE: &str = "memcached:1.6-alpine";
+
+const PORT_NAME: &str = "cache";
+
+/// Units of a Go `time.Duration` string.
+const DURATION_UNITS: &[&str] = &["ns", "us", "µs", "ms", "s", "m", "h"];
+
+/// The sidecar to run, if any.
+pub fn sidecar(openfga: &OpenFGA) -> Option<&CacheSidecar> {
+    openfga.spec.query_cache.as_ref()?.sidecar.as_ref()
+}
+
+pub fn sidecar_port(sidecar: &CacheSidecar) -> i32 {
+    sidecar.port.unwrap_or(match sidecar.engine {
+        CacheEngine::Redis => 6379,
+        CacheEngine::Memcached => 11211,
+    })
+}
+
+/// Whether `value` parses as a Go duration such as `10s` or `1m30s`.
+pub fn is_duration(value: &str) -> bool {
+    let mut rest = value;
+    if rest.is_empty() {
+        return false;
+    }
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(rest.len());
+        if digits == 0 || rest[..digits].parse::<f64>().is_err() {
+            return false;
+        }
+        rest = &rest[digits..];
+        let unit = rest
+            .find(|c: char| c.is_ascii_digit() || c == '.')
+            .unwrap_or(rest.len());
+        if !DURATION_UNITS.contains(&&rest[..unit]) {
+            return false;
+        }
+        rest = &rest[unit..];
+    }
+    true
+}
+
+/// Variables configuring OpenFGA's check query cache.
+pub fn env(openfga: &OpenFGA) -> Vec<EnvVar> {
+    let Some(cache) = &openfga.spec.query_cache else {
+        return vec![];
+    };
+    let mut env = vec![(
+        "OPENFGA_CHECK_QUERY_CACHE_ENABLED",
+        Some(cache.enabled.to_string()),
+    )];
+    if cache.enabled {
+        env.push(("OPENFGA_CHECK_QUERY_CACHE_TTL", cache.ttl.clone()));
+        env.push((
+            "OPENFGA_CHECK_QUERY_CACHE_LIMIT",
+            cache.limit.map(|limit| limit.to_string()),
+        ));
+    }
+    env.into_iter()
+        .filter_map(|(name, value)| {
+            value.map(|value| EnvVar {
+                name: name.to_string(),
+                value: Some(value),
+                ..Default::default()
+            })
+        })
+        .collect()
+}
+
+fn resource_requirements(sidecar: &CacheSidecar) -> Option<ResourceRequirements> {
+    fn quantities(q: Option<&ResourceQuantities>) -> Option<BTreeMap<String, Quantity>> {
+        let map: BTreeMap<String, Quantity> = q
+            .map(|q| {
+                [
+                    ("cpu", &q.cpu),
+                    ("memory", &q.memory),
+                    ("ephemeral-storage", &q.ephemeral_storage),
+                ]
+            })
+            .into_iter()
+            .flatten()
+            .filter_map(|(k, v)| v.as_ref().map(|v| (k.to_string(), Quantity(v.clone()))))
+            .collect();
+        (!map.is_empty()).then_some(map)
+    }
+
+    let resources = sidecar.resources.as_ref()?;
+    Some(ResourceRequirements {
+        requests: quantities(resources.requests.as_ref()),
+        limits: quantities(resources.limits.as_ref()),
+        ..Default::default()
+    })
+}
+
+/// The cache sidecar. Both images' entrypoints prepend the server binary to
+/// arguments starting with a dash.
+pub fn container(sidecar: &CacheSidecar) -> Container {
+    let port = sidecar_port(sidecar);
+    let (default_image, mut args) = match sidecar.engine {
+        CacheEngine::Redis => (
+            REDIS_IMAGE,
+            vec![
+                "--port".to_string(),
+                port.to_string(),
+                "--save".to_string(),
+                String::new(),
+                "--appendonly".to_string(),
+                "no".to_string(),
+                "--maxmemory-policy".to_string(),
+                "allkeys-lru".to_string(),
+            ],
+        ),
+        CacheEngine::Memcached => (MEMCACHED_IMAGE, vec![format!("--port={}", port)]),
+    };
+    if let Some(mebibytes) = sidecar.max_memory_mi {
+        args.extend(match sidecar.engine {
+            CacheEngine::Redis => vec!["--maxmemory".to_string(), format!("{}mb", mebibytes)],
+            CacheEngine::Memcached => vec![format!("--memory-limit={}", mebibytes)],
+        });
+    }
+
+    Container {
+        name: CACHE_CONTAINER.to_string(),
+        image: Some(
+            sidecar
+                .image
+                .clone()
+                .unwrap_or_else(|| default_image.to_string()),
+        ),
+        args: Some(args),
+        ports: Some(vec![ContainerPort {
+            container_port: port,
+            name: Some(PORT_NAME.to_string()),
+            protocol: Some("TCP".to_string()),
+            ..Default::default()
+        }]),
+        readiness_probe: Some(Probe {
+            tcp_socket: Some(TCPSocketAction {
+                port: IntOrString::Int(port),
+                ..Default::default()
+            }),
+            period_seconds: Some(10),
+            ..Default::default()
+        }),
+        resources: resource_requirements(sidecar),
+        security_context: Some(SecurityContext {
+            allow_privilege_escalation: Some(false),
+            run_as_non_root: Some(true),
+            capabilities: Some(Capabilities {
+                drop: Some(vec!["ALL".to_string()]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+/// The `cache` port of the instance's Service.
+pub fn service_port(sidecar: &CacheSidecar) -> ServicePort {
+    let port = sidecar_port(sidecar);
+    ServicePort {
+        port,
+        target_port: Some(IntOrString::Int(port)),
+        name: Some(PORT_NAME.to_string()),
+        protocol: Some("TCP".to_string()),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::test_openfga;
+    use crate::types::ResourceSpec;
+
+    fn instance(query_cache: serde_json::Value) -> OpenFGA {
+        test_openfga(serde_json::json!({ "queryCache": query_cache }))
+    }
+
+    fn env_pairs(openfga: &OpenFGA) -> Vec<(String, String)> {
+        env(openfga)
+            .into_iter()
+            .map(|e| (e.name, e.value.unwrap_or_default()))
+            .collect()
+    }
+
+    #[test]
+    fn test_env() {
+        let openfga = instance(serde_json::json!({ "ttl": "30s", "limit": 50000 }));
+        assert_eq!(
+            env_pairs(&openfga),
+            vec![
+                (
+                    "OPENFGA_CHECK_QUERY_CACHE_ENABLED".to_string(),
+                    "true".to_string()
+                ),
+                (
+                    "OPENFGA_CHECK_QUERY_CACHE_TTL".to_string(),
+                    "30s".to_string()
+                ),
+                (
+                    "OPENFGA_CHECK_QUERY_CACHE_LIMIT".to_string(),
+                    "50000".to_string()
+                ),
+            ]
+        );
+
+        let disabled = instance(serde_json::json!({ "enabled": false, "ttl": "30s" }));
+        assert_eq!(
+            env_pairs(&disabled),
+            vec![(
+                "OPENFGA_CHECK_QUERY_CACHE_ENABLED".to_string(),
+                "false".to_string()
+            )]
+        );
+
+        assert!(env(&instance(serde_json::Value::Null)).is_empty());
+    }
+
+    #[test]
+    fn test_redis_container() {
+        let openfga = instance(serde_json::json!({
+            "sidecar": { "engine": "Redis", "maxMemoryMi": 128 }
+        }));
+        let sidecar = sidecar(&openfga).unwrap();
+        let container = container(sidecar);
+        assert_eq!(container.name, CACHE_CONTAINER);
+        assert_eq!(container.image.as_deref(), Some(REDIS_IMAGE));
+        let args = container.args.unwrap();
+        assert_eq!(args[..2], ["--port", "6379"]);
+        assert!(args.ends_with(&["--maxmemory".to_string(), "128mb".to_string()]));
+        assert_eq!(container.ports.unwrap()[0].container_port, 6379);
+        assert!(container.resources.is_none());
+        assert_eq!(service_port(sidecar).name.as_deref(), Some("cache"));
+    }
+
+    #[test]
+    fn test_memcached_container() {
+        let mut openfga = instance(serde_json::json!({
+            "sidecar": { "engine": "Memcached", "port": 11311, "image": "mirror/memcached:1.6" }
+        }));
+        openfga
+            .spec
+            .query_cache
+            .as_mut()
+            .unwrap()
+            .sidecar
+            .as_mut()
+            .unwrap()
+            .resources = Some(ResourceSpec {
+            requests: None,
+            limits: Some(ResourceQuantities {
+                memory: Some("96Mi".to_string()),
+                ..Default::default()
+            }),
+        });
+        let container = container(sidecar(&openfga).unwrap());
+        assert_eq!(container.image.as_deref(), Some("mirror/memcached:1.6"));
+        assert_eq!(container.args.unwrap(), ["--port=11311"]);
+        assert_eq!(
+            container.resources.unwrap().limits.unwrap()["memory"],
+            Quantity("96Mi".to_string())
+        );
+    }
+
+    #[test]
+    fn test_is_duration() {
+        for valid in ["10s", "1m30s", "500ms", "1.5h", "0s"] {
+            assert!(is_duration(valid), "{}", valid);
+        }
+        for invalid in ["", "10", "s", "10 s", "1d", "-5s"] {
+            assert!(!is_duration(invalid), "{}", invalid);
+        }
+    }
+}
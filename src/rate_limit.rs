@@ -0,0 +1,147 @@
+//! Client-side rate limiting of Kubernetes API requests: a token bucket that
+//! refills at `qps` tokens per second and holds up to `burst` tokens, applied
+//! as a layer on the kube client so every controller shares one budget.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tokio::time::Sleep;
+use tower::{Layer, Service};
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Token bucket shared by every request of a client.
+#[derive(Debug)]
+pub struct RateLimiter {
+    qps: f64,
+    burst: f64,
+    bucket: Mutex<Bucket>,
+}
+
+impl RateLimiter {
+    /// A full bucket. `qps` must be positive; `burst` is raised to at least one.
+    pub fn new(qps: f64, burst: u32) -> Self {
+        let burst = f64::from(burst.max(1));
+        Self {
+            qps,
+            burst,
+            bucket: Mutex::new(Bucket {
+                tokens: burst,
+                updated: Instant::now(),
+            }),
+        }
+    }
+
+    /// Takes a token, returning how long the caller must wait before it may
+    /// send. Tokens are reserved ahead, so waiting callers are served in order.
+    pub fn reserve(&self, now: Instant) -> Duration {
+        let mut bucket = self.bucket.lock().unwrap_or_else(|e| e.into_inner());
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.qps).min(self.burst);
+        bucket.updated = now;
+        bucket.tokens -= 1.0;
+        if bucket.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-bucket.tokens / self.qps)
+        }
+    }
+}
+
+/// Layer that delays requests beyond the limiter's rate.
+#[derive(Debug, Clone)]
+pub struct RateLimitLayer {
+    limiter: Arc<RateLimiter>,
+}
+
+impl RateLimitLayer {
+    pub fn new(qps: f64, burst: u32) -> Self {
+        Self {
+            limiter: Arc::new(RateLimiter::new(qps, burst)),
+        }
+    }
+}
+
+impl<S> Layer<S> for RateLimitLayer {
+    type Service = RateLimited<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimited {
+            inner,
+            limiter: self.limiter.clone(),
+            reserved: false,
+            wait: None,
+        }
+    }
+}
+
+/// Service that takes a token before each request. The wait happens in
+/// `poll_ready`, so callers such as the client's buffer hold the request until
+/// it may be sent.
+pub struct RateLimited<S> {
+    inner: S,
+    limiter: Arc<RateLimiter>,
+    reserved: bool,
+    wait: Option<Pin<Box<Sleep>>>,
+}
+
+impl<S, Request> Service<Request> for RateLimited<S>
+where
+    S: Service<Request>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if !self.reserved {
+            let delay = self.limiter.reserve(Instant::now());
+            if !delay.is_zero() {
+                self.wait = Some(Box::pin(tokio::time::sleep(delay)));
+            }
+            self.reserved = true;
+        }
+        if let Some(wait) = self.wait.as_mut() {
+            if wait.as_mut().poll(cx).is_pending() {
+                return Poll::Pending;
+            }
+            self.wait = None;
+        }
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        self.reserved = false;
+        self.inner.call(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_burst_then_qps() {
+        let limiter = RateLimiter::new(10.0, 3);
+        let start = Instant::now();
+        for _ in 0..3 {
+            assert_eq!(limiter.reserve(start), Duration::ZERO);
+        }
+        // The bucket is empty: the next requests queue behind each other
+        assert_eq!(limiter.reserve(start).as_millis(), 100);
+        assert_eq!(limiter.reserve(start).as_millis(), 200);
+
+        // Two seconds later the debt is repaid and the bucket holds 3 again
+        let later = start + Duration::from_secs(2);
+        for _ in 0..3 {
+            assert_eq!(limiter.reserve(later), Duration::ZERO);
+        }
+        assert!(limiter.reserve(later) > Duration::ZERO);
+    }
+}
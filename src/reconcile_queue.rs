@@ -0,0 +1,313 @@
+//! Priority admission for OpenFGA reconciles. kube-runtime schedules reconciles
+//! first in, first out, so when hundreds of instances requeue at once a broken
+//! instance waits behind every routine resync of a healthy one. With
+//! `max_concurrent_reconciles` set, reconciles instead wait here for one of
+//! that many slots, and a freed slot goes to the most urgent waiter: instances
+//! that are failing or not Ready, then new and changed ones, then routine
+//! requeues. Waiters of the same priority keep their arrival order.
+
+use crate::metrics;
+use crate::types::OpenFGA;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::oneshot;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    /// Healthy and up to date; a periodic resync.
+    Routine,
+    /// New, changed or being deleted.
+    Changed,
+    /// Failing to reconcile or not Ready.
+    Unhealthy,
+}
+
+impl Priority {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Priority::Routine => "routine",
+            Priority::Changed => "changed",
+            Priority::Unhealthy => "unhealthy",
+        }
+    }
+}
+
+/// How urgently `openfga` needs reconciling; `failing` when its last
+/// reconcile returned an error.
+pub fn priority(openfga: &OpenFGA, failing: bool) -> Priority {
+    let status = openfga.status.as_ref();
+    let ready = status
+        .and_then(|s| s.conditions.as_ref())
+        .and_then(|conditions| conditions.iter().find(|c| c.type_ == "Ready"))
+        .is_some_and(|c| c.status == "True");
+    let observed = status.and_then(|s| s.observed_generation);
+    if failing || (observed.is_some() && !ready) {
+        Priority::Unhealthy
+    } else if observed.is_none()
+        || observed != openfga.metadata.generation
+        || openfga.metadata.deletion_timestamp.is_some()
+    {
+        Priority::Changed
+    } else {
+        Priority::Routine
+    }
+}
+
+struct Waiter {
+    priority: Priority,
+    arrival: u64,
+    grant: oneshot::Sender<Permit>,
+}
+
+impl Ord for Waiter {
+    /// Higher priority first, then earlier arrival.
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.arrival.cmp(&self.arrival))
+    }
+}
+
+impl PartialOrd for Waiter {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Waiter {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Waiter {}
+
+struct QueueState {
+    free: usize,
+    waiting: BinaryHeap<Waiter>,
+    arrivals: u64,
+}
+
+impl QueueState {
+    fn waiting(&self) -> Vec<(Priority, usize)> {
+        [Priority::Unhealthy, Priority::Changed, Priority::Routine]
+            .into_iter()
+            .map(|priority| {
+                let count = self
+                    .waiting
+                    .iter()
+                    .filter(|waiter| waiter.priority == priority)
+                    .count();
+                (priority, count)
+            })
+            .collect()
+    }
+
+    fn publish(&self) {
+        for (priority, count) in self.waiting() {
+            metrics::metrics()
+                .reconcile_queue_waiting
+                .with_label_values(&[priority.as_str()])
+                .set(count as i64);
+        }
+    }
+}
+
+/// A slot to reconcile in, handed to the next waiter when dropped.
+pub struct Permit {
+    queue: Option<Arc<ReconcileQueue>>,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        if let Some(queue) = self.queue.take() {
+            queue.release();
+        }
+    }
+}
+
+pub struct ReconcileQueue {
+    state: Mutex<QueueState>,
+}
+
+impl ReconcileQueue {
+    /// A queue admitting `slots` reconciles at once.
+    pub fn new(slots: usize) -> Arc<Self> {
+        Arc::new(Self {
+            state: Mutex::new(QueueState {
+                free: slots,
+                waiting: BinaryHeap::new(),
+                arrivals: 0,
+            }),
+        })
+    }
+
+    /// Waits for a slot. Dropping the returned future gives up the place in line.
+    pub async fn acquire(self: &Arc<Self>, priority: Priority) -> Permit {
+        let granted = {
+            let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            if state.free > 0 && state.waiting.is_empty() {
+                state.free -= 1;
+                return Permit {
+                    queue: Some(self.clone()),
+                };
+            }
+            let (grant, granted) = oneshot::channel();
+            state.arrivals += 1;
+            let arrival = state.arrivals;
+            state.waiting.push(Waiter {
+                priority,
+                arrival,
+                grant,
+            });
+            state.publish();
+            granted
+        };
+        granted
+            .await
+            .expect("waiters are only dropped after being granted a permit")
+    }
+
+    /// Reconciles waiting for a slot, by priority.
+    pub fn waiting(&self) -> Vec<(Priority, usize)> {
+        self.state
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .waiting()
+    }
+
+    fn release(self: Arc<Self>) {
+        loop {
+            let next = {
+                let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+                let next = state.waiting.pop();
+                state.publish();
+                match next {
+                    Some(waiter) => waiter,
+                    None => {
+                        state.free += 1;
+                        return;
+                    }
+                }
+            };
+            let permit = Permit {
+                queue: Some(self.clone()),
+            };
+            match next.grant.send(permit) {
+                Ok(()) => return,
+                // The waiter gave up; offer the slot to the next one
+                Err(mut permit) => permit.queue = None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::create_test_openfga;
+    use crate::types::{OpenFGACondition, OpenFGAStatus};
+    use std::time::Duration;
+
+    fn instance(generation: i64, observed: Option<i64>, ready: &str) -> OpenFGA {
+        let mut openfga = create_test_openfga();
+        openfga.metadata.generation = Some(generation);
+        openfga.status = Some(OpenFGAStatus {
+            observed_generation: observed,
+            conditions: Some(vec![OpenFGACondition {
+                type_: "Ready".to_string(),
+                status: ready.to_string(),
+                last_transition_time: None,
+                reason: None,
+                message: None,
+                observed_generation: observed,
+            }]),
+            ..Default::default()
+        });
+        openfga
+    }
+
+    #[test]
+    fn test_priority() {
+        assert_eq!(
+            priority(&instance(2, Some(2), "True"), false),
+            Priority::Routine
+        );
+        assert_eq!(
+            priority(&instance(2, Some(2), "True"), true),
+            Priority::Unhealthy
+        );
+        assert_eq!(
+            priority(&instance(2, Some(2), "False"), false),
+            Priority::Unhealthy
+        );
+        assert_eq!(
+            priority(&instance(3, Some(2), "True"), false),
+            Priority::Changed
+        );
+
+        let mut new = instance(1, None, "Unknown");
+        new.status = None;
+        assert_eq!(priority(&new, false), Priority::Changed);
+    }
+
+    #[tokio::test]
+    async fn test_slots_go_to_the_most_urgent_waiter() {
+        let queue = ReconcileQueue::new(1);
+        let held = queue.acquire(Priority::Routine).await;
+
+        let (order_tx, mut order) = tokio::sync::mpsc::unbounded_channel();
+        let mut tasks = Vec::new();
+        for (name, priority) in [
+            ("routine-1", Priority::Routine),
+            ("changed", Priority::Changed),
+            ("routine-2", Priority::Routine),
+            ("unhealthy", Priority::Unhealthy),
+        ] {
+            let queue = queue.clone();
+            let order_tx = order_tx.clone();
+            tasks.push(tokio::spawn(async move {
+                let _permit = queue.acquire(priority).await;
+                order_tx.send(name).unwrap();
+            }));
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        assert_eq!(
+            queue.waiting(),
+            vec![
+                (Priority::Unhealthy, 1),
+                (Priority::Changed, 1),
+                (Priority::Routine, 2)
+            ]
+        );
+
+        drop(held);
+        for task in tasks {
+            task.await.unwrap();
+        }
+        let mut granted = Vec::new();
+        while let Ok(name) = order.try_recv() {
+            granted.push(name);
+        }
+        assert_eq!(granted, ["unhealthy", "changed", "routine-1", "routine-2"]);
+    }
+
+    #[tokio::test]
+    async fn test_abandoned_waiters_do_not_keep_slots() {
+        let queue = ReconcileQueue::new(1);
+        let held = queue.acquire(Priority::Routine).await;
+
+        let abandoned =
+            tokio::time::timeout(Duration::from_millis(5), queue.acquire(Priority::Unhealthy))
+                .await;
+        assert!(abandoned.is_err());
+
+        drop(held);
+        let next = tokio::time::timeout(Duration::from_secs(1), queue.acquire(Priority::Routine))
+            .await
+            .expect("the abandoned waiter's slot is free again");
+        drop(next);
+        assert_eq!(queue.state.lock().unwrap().free, 1);
+    }
+}
@@ -0,0 +1,299 @@
+//! `status.resourceRecommendations`: the 95th percentile of the CPU and memory
+//! the OpenFGA containers of an instance actually use, to right-size
+//! `spec.resources` without deploying the Vertical Pod Autoscaler.
+//!
+//! Usage is read from the metrics API (`metrics.k8s.io`, served by
+//! metrics-server) when the status is updated, at most once a minute, and kept
+//! in memory for a day. Every pod's reading is one sample. The window starts
+//! over when the operator restarts, and without a metrics API the status keeps
+//! what it last reported.
+
+use crate::types::{OpenFGA, ResourceRecommendations};
+use kube::api::{Api, DynamicObject, ListParams};
+use kube::core::{ApiResource, GroupVersionKind};
+use kube::{Client, ResourceExt};
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Mutex, OnceLock};
+use tracing::debug;
+
+/// Name of the OpenFGA container in the instance's pods.
+const CONTAINER: &str = "openfga";
+
+/// Minimum seconds between two readings of an instance's usage.
+pub const SAMPLE_INTERVAL_SECONDS: i64 = 60;
+
+/// How long a reading counts towards the recommendation.
+pub const WINDOW_SECONDS: i64 = 24 * 60 * 60;
+
+/// Upper bound on the samples kept per instance.
+const MAX_SAMPLES: usize = 10_000;
+
+const PERCENTILE: f64 = 0.95;
+
+const MEBIBYTE: f64 = 1024.0 * 1024.0;
+
+/// Usage of one container at one time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sample {
+    /// Unix seconds.
+    pub at: i64,
+    pub cpu_millicores: f64,
+    pub memory_bytes: f64,
+}
+
+/// The samples of one instance inside the window.
+#[derive(Debug, Default)]
+pub struct UsageWindow {
+    samples: VecDeque<Sample>,
+    last_read: Option<i64>,
+}
+
+impl UsageWindow {
+    /// Whether a reading at `now` is due.
+    pub fn due(&self, now: i64) -> bool {
+        self.last_read
+            .is_none_or(|last| now - last >= SAMPLE_INTERVAL_SECONDS)
+    }
+
+    /// Adds the samples of one reading at `now` and drops those that left the window.
+    pub fn record(&mut self, now: i64, samples: impl IntoIterator<Item = Sample>) {
+        self.last_read = Some(now);
+        self.samples.extend(samples);
+        while self
+            .samples
+            .front()
+            .is_some_and(|sample| now - sample.at > WINDOW_SECONDS)
+            || self.samples.len() > MAX_SAMPLES
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    /// The p95 usage over the window; none before the first sample.
+    pub fn recommend(&self, now: &str) -> Option<ResourceRecommendations> {
+        let oldest = self.samples.front()?;
+        let cpu = percentile(self.samples.iter().map(|s| s.cpu_millicores).collect());
+        let memory = percentile(self.samples.iter().map(|s| s.memory_bytes).collect());
+        Some(ResourceRecommendations {
+            container: CONTAINER.to_string(),
+            cpu: format!("{}m", cpu.ceil().max(1.0) as u64),
+            memory: format!("{}Mi", (memory / MEBIBYTE).ceil().max(1.0) as u64),
+            samples: self.samples.len() as u32,
+            window_start: chrono::DateTime::from_timestamp(oldest.at, 0)
+                .map(|t| t.to_rfc3339())
+                .unwrap_or_default(),
+            observed_time: now.to_string(),
+        })
+    }
+}
+
+/// Nearest-rank percentile of non-empty `values`.
+fn percentile(mut values: Vec<f64>) -> f64 {
+    values.sort_by(f64::total_cmp);
+    let rank = (PERCENTILE * values.len() as f64).ceil() as usize;
+    values[rank.clamp(1, values.len()) - 1]
+}
+
+/// CPU quantity in millicores, e.g. `250m`, `1` or the `12345678n` the
+/// metrics API reports.
+pub fn parse_cpu(quantity: &str) -> Option<f64> {
+    let (number, scale) = match quantity {
+        q if q.ends_with('n') => (&q[..q.len() - 1], 1e-6),
+        q if q.ends_with('u') => (&q[..q.len() - 1], 1e-3),
+        q if q.ends_with('m') => (&q[..q.len() - 1], 1.0),
+        q => (q, 1000.0),
+    };
+    number.parse::<f64>().ok().map(|n| n * scale)
+}
+
+/// Memory quantity in bytes, e.g. `131072Ki`, `512Mi` or `1G`.
+pub fn parse_memory(quantity: &str) -> Option<f64> {
+    const SUFFIXES: &[(&str, f64)] = &[
+        ("Ki", 1024.0),
+        ("Mi", MEBIBYTE),
+        ("Gi", MEBIBYTE * 1024.0),
+        ("Ti", MEBIBYTE * 1024.0 * 1024.0),
+        ("k", 1e3),
+        ("M", 1e6),
+        ("G", 1e9),
+        ("T", 1e12),
+    ];
+    let (number, scale) = SUFFIXES
+        .iter()
+        .find_map(|(suffix, scale)| quantity.strip_suffix(suffix).map(|n| (n, *scale)))
+        .unwrap_or((quantity, 1.0));
+    number.parse::<f64>().ok().map(|n| n * scale)
+}
+
+/// The OpenFGA container usage in a `PodMetrics` object, read at `at`.
+pub fn container_sample(pod_metrics: &Value, at: i64) -> Option<Sample> {
+    let usage = pod_metrics["containers"]
+        .as_array()?
+        .iter()
+        .find(|container| container["name"] == CONTAINER)?
+        .get("usage")?;
+    Some(Sample {
+        at,
+        cpu_millicores: parse_cpu(usage["cpu"].as_str()?)?,
+        memory_bytes: parse_memory(usage["memory"].as_str()?)?,
+    })
+}
+
+fn pod_metrics_resource() -> ApiResource {
+    ApiResource::from_gvk_with_plural(
+        &GroupVersionKind::gvk("metrics.k8s.io", "v1beta1", "PodMetrics"),
+        "pods",
+    )
+}
+
+fn windows() -> &'static Mutex<HashMap<String, UsageWindow>> {
+    static WINDOWS: OnceLock<Mutex<HashMap<String, UsageWindow>>> = OnceLock::new();
+    WINDOWS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn key(openfga: &OpenFGA) -> String {
+    format!(
+        "{}/{}",
+        openfga.namespace().unwrap_or_default(),
+        openfga.name_any()
+    )
+}
+
+/// Reads the instance's usage if a reading is due and returns the current
+/// recommendation, or the previous one when there is nothing to go on.
+pub async fn observe(client: &Client, openfga: &OpenFGA) -> Option<ResourceRecommendations> {
+    let previous = openfga
+        .status
+        .as_ref()
+        .and_then(|s| s.resource_recommendations.clone());
+    let now = chrono::Utc::now();
+    let key = key(openfga);
+    let due = windows()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(&key)
+        .is_none_or(|window| window.due(now.timestamp()));
+
+    if due {
+        let api: Api<DynamicObject> = Api::namespaced_with(
+            client.clone(),
+            &openfga.namespace().unwrap_or_default(),
+            &pod_metrics_resource(),
+        );
+        let selector = format!("app.kubernetes.io/instance={}", openfga.name_any());
+        match api.list(&ListParams::default().labels(&selector)).await {
+            Ok(list) => {
+                let samples: Vec<Sample> = list
+                    .items
+                    .iter()
+                    .filter_map(|pod| container_sample(&pod.data, now.timestamp()))
+                    .collect();
+                windows()
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .entry(key.clone())
+                    .or_default()
+                    .record(now.timestamp(), samples);
+            }
+            Err(e) => {
+                debug!(
+                    event = "pod_metrics_unavailable",
+                    namespace = %openfga.namespace().unwrap_or_default(),
+                    name = %openfga.name_any(),
+                    error = %e,
+                    "Failed to read pod metrics, keeping the last resource recommendations"
+                );
+            }
+        }
+    }
+
+    windows()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(&key)
+        .and_then(|window| window.recommend(&now.to_rfc3339()))
+        .or(previous)
+}
+
+/// Drops the samples of a deleted instance.
+pub fn forget(openfga: &OpenFGA) {
+    windows()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(&key(openfga));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_quantities() {
+        assert_eq!(parse_cpu("250m"), Some(250.0));
+        assert_eq!(parse_cpu("2"), Some(2000.0));
+        assert_eq!(parse_cpu("0.5"), Some(500.0));
+        assert_eq!(parse_cpu("12345678n"), Some(12.345678));
+        assert_eq!(parse_cpu("1500u"), Some(1.5));
+        assert_eq!(parse_cpu("lots"), None);
+
+        assert_eq!(parse_memory("131072Ki"), Some(128.0 * MEBIBYTE));
+        assert_eq!(parse_memory("512Mi"), Some(512.0 * MEBIBYTE));
+        assert_eq!(parse_memory("1G"), Some(1e9));
+        assert_eq!(parse_memory("4096"), Some(4096.0));
+        assert_eq!(parse_memory("Mi"), None);
+    }
+
+    #[test]
+    fn test_container_sample() {
+        let pod_metrics = json!({
+            "timestamp": "2026-10-15T00:00:00Z",
+            "containers": [
+                { "name": "istio-proxy", "usage": { "cpu": "900m", "memory": "1Gi" } },
+                { "name": "openfga", "usage": { "cpu": "48213579n", "memory": "65536Ki" } }
+            ]
+        });
+        let sample = container_sample(&pod_metrics, 10).unwrap();
+        assert_eq!(sample.at, 10);
+        assert!((sample.cpu_millicores - 48.213579).abs() < 1e-9);
+        assert_eq!(sample.memory_bytes, 64.0 * MEBIBYTE);
+
+        assert_eq!(container_sample(&json!({ "containers": [] }), 10), None);
+    }
+
+    #[test]
+    fn test_window() {
+        let sample = |at, cpu: f64, memory_mi: f64| Sample {
+            at,
+            cpu_millicores: cpu,
+            memory_bytes: memory_mi * MEBIBYTE,
+        };
+        let mut window = UsageWindow::default();
+        assert!(window.due(0));
+        assert_eq!(window.recommend("now"), None);
+
+        // Twenty readings of one pod, 100m..=2000m and 10..=200Mi
+        for i in 1..=20 {
+            let at = i * SAMPLE_INTERVAL_SECONDS;
+            window.record(at, [sample(at, i as f64 * 100.0, i as f64 * 10.0)]);
+        }
+        assert!(!window.due(20 * SAMPLE_INTERVAL_SECONDS + 1));
+        assert!(window.due(21 * SAMPLE_INTERVAL_SECONDS));
+
+        let recommendation = window.recommend("2026-10-15T00:00:00+00:00").unwrap();
+        assert_eq!(recommendation.container, "openfga");
+        assert_eq!(recommendation.cpu, "1900m");
+        assert_eq!(recommendation.memory, "190Mi");
+        assert_eq!(recommendation.samples, 20);
+        assert_eq!(recommendation.window_start, "1970-01-01T00:01:00+00:00");
+
+        // A day later only the new reading is left
+        let later = 20 * SAMPLE_INTERVAL_SECONDS + WINDOW_SECONDS + 1;
+        window.record(later, [sample(later, 0.2, 0.5)]);
+        let recommendation = window.recommend("later").unwrap();
+        assert_eq!(recommendation.samples, 1);
+        assert_eq!(recommendation.cpu, "1m");
+        assert_eq!(recommendation.memory, "1Mi");
+    }
+}
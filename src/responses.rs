@@ -0,0 +1,100 @@
+//! Response builders for the operator's HTTP endpoints. Building a response is
+//! fallible (headers are validated), so handlers return `HttpResult` and the
+//! server turns any error into a 500 with a JSON body instead of panicking.
+
+use hyper::header::{HeaderValue, CONTENT_TYPE};
+use hyper::{Body, Response, StatusCode};
+use serde_json::Value;
+use thiserror::Error;
+use tracing::error;
+
+pub const TEXT: &str = "text/plain";
+pub const JSON: &str = "application/json";
+pub const PROMETHEUS: &str = "text/plain; version=0.0.4";
+
+#[derive(Error, Debug)]
+pub enum HttpError {
+    #[error("Invalid response: {0}")]
+    Response(#[from] hyper::http::Error),
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+}
+
+pub type HttpResult<T> = std::result::Result<T, HttpError>;
+
+pub fn respond(
+    status: StatusCode,
+    content_type: &str,
+    body: impl Into<Body>,
+) -> HttpResult<Response<Body>> {
+    Ok(Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, content_type)
+        .body(body.into())?)
+}
+
+pub fn json(status: StatusCode, value: &Value) -> HttpResult<Response<Body>> {
+    respond(status, JSON, serde_json::to_vec(value)?)
+}
+
+/// A 500 response describing `error`. Built without the fallible builder so it
+/// cannot fail itself.
+pub fn error_response(error: &HttpError) -> Response<Body> {
+    let body = serde_json::json!({
+        "error": "internal_error",
+        "message": error.to_string(),
+    });
+    let mut response = Response::new(Body::from(body.to_string()));
+    *response.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static(JSON));
+    response
+}
+
+/// Unwraps a handler result, logging and converting errors into a 500.
+pub fn or_error(path: &str, result: HttpResult<Response<Body>>) -> Response<Body> {
+    result.unwrap_or_else(|e| {
+        error!(
+            event = "http_response_failed",
+            path = %path,
+            error = %e,
+            "Failed to build HTTP response"
+        );
+        error_response(&e)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn body_json(response: Response<Body>) -> Value {
+        let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_json_response() {
+        let response = json(StatusCode::OK, &serde_json::json!({ "status": "ok" })).unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[CONTENT_TYPE], JSON);
+        assert_eq!(body_json(response).await["status"], "ok");
+    }
+
+    #[tokio::test]
+    async fn test_invalid_response_becomes_500() {
+        let result = respond(StatusCode::OK, "text/plain\nx-injected: 1", "ok");
+        assert!(matches!(result, Err(HttpError::Response(_))));
+
+        let response = or_error("/healthz", result);
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(response.headers()[CONTENT_TYPE], JSON);
+        let body = body_json(response).await;
+        assert_eq!(body["error"], "internal_error");
+        assert!(body["message"]
+            .as_str()
+            .unwrap()
+            .starts_with("Invalid response"));
+    }
+}
@@ -0,0 +1,747 @@
+//! One-off restores of OpenFGABackup artifacts. Each OpenFGARestore gets a Job
+//! running `openfga-operator backup restore` against the target instance,
+//! preceded by a download container when the artifact is in object storage.
+//!
+//! The job publishes its progress in `status.progress` through a
+//! ServiceAccount that may only patch this one restore's status; the
+//! controller owns the rest of the status and follows the Job to completion.
+
+use crate::access_tokens;
+use crate::apply::apply_params;
+use crate::backup_controller::{
+    destination_uri, env, gcs_credentials, mount, operator_image, pvc_volume, s3_env, with_prefix,
+    work_volume, AWS_CLI_IMAGE, CLOUD_SDK_IMAGE, PVC_MOUNT, WORK_DIR,
+};
+use crate::controller::{shutdown_requested, ControllerError, ControllerResult, Shutdown};
+use crate::metrics;
+use crate::openfga_client::{instance_grpc_url, instance_url};
+use crate::panic_isolation::isolate_panics;
+use crate::types::{
+    BackupDestination, OpenFGA, OpenFGABackup, OpenFGACondition, OpenFGARestore,
+    OpenFGARestoreStatus,
+};
+use futures::StreamExt;
+use k8s_openapi::api::batch::v1::{Job, JobSpec};
+use k8s_openapi::api::core::v1::{
+    Container, PodSpec, PodTemplateSpec, ServiceAccount, VolumeMount,
+};
+use k8s_openapi::api::rbac::v1::{PolicyRule, Role, RoleBinding, RoleRef, Subject};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use kube::api::{Api, Patch, PatchParams};
+use kube::runtime::controller::{Action, Controller};
+use kube::runtime::watcher::Config;
+use kube::{Client, Resource, ResourceExt};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tokio::time::Duration;
+use tracing::{debug, error, info, instrument, warn};
+
+const CONTROLLER_NAME: &str = "openfga-restore-controller";
+
+/// Label on every restore job naming the OpenFGARestore it runs for.
+pub const RESTORE_LABEL: &str = "openfga.dev/restore";
+
+/// Where a downloaded artifact is written.
+const DOWNLOADED_ARTIFACT: &str = "/work/artifact.json";
+
+const S3_DOWNLOAD_SCRIPT: &str = r#"set -eu
+aws $ENDPOINT_ARGS s3 cp "$SOURCE" /work/artifact.json
+"#;
+
+const GCS_DOWNLOAD_SCRIPT: &str = r#"set -eu
+gcloud auth activate-service-account --key-file=/var/secrets/google/key.json
+gsutil cp "$SOURCE" /work/artifact.json
+"#;
+
+pub struct OpenFGARestoreController {
+    client: Client,
+}
+
+impl OpenFGARestoreController {
+    pub fn new(client: Client) -> Self {
+        Self { client }
+    }
+
+    pub async fn run(self, shutdown: Shutdown) {
+        let restores: Api<OpenFGARestore> = Api::all(self.client.clone());
+        let jobs: Api<Job> = Api::all(self.client.clone());
+
+        info!(
+            controller = CONTROLLER_NAME,
+            "Starting controller with OpenFGARestore resource monitoring"
+        );
+
+        let _watches = metrics::track_watch_streams(CONTROLLER_NAME, 2);
+        Controller::new(restores, Config::default().any_semantic())
+            .owns(jobs, Config::default())
+            .graceful_shutdown_on(shutdown_requested(shutdown.clone()))
+            .run(
+                |restore, ctx| {
+                    let guarded = isolate_panics(
+                        ctx.client.clone(),
+                        CONTROLLER_NAME,
+                        restore.clone(),
+                        reconcile(restore.clone(), ctx),
+                    );
+                    metrics::observe_reconcile(CONTROLLER_NAME, restore, guarded)
+                },
+                error_policy,
+                Arc::new(self),
+            )
+            .for_each(|res| async move {
+                match res {
+                    Ok(o) => {
+                        debug!(
+                            reconciliation_result = "success",
+                            object = ?o,
+                            "OpenFGARestore reconciliation completed successfully"
+                        );
+                    }
+                    Err(e) => {
+                        error!(
+                            reconciliation_result = "error",
+                            error = %e,
+                            "OpenFGARestore reconciliation failed"
+                        );
+                    }
+                }
+            })
+            .await;
+    }
+}
+
+/// Name of the Job, ServiceAccount, Role and RoleBinding of a restore.
+pub fn restore_job_name(restore_name: &str) -> String {
+    format!("{}-restore", restore_name)
+}
+
+fn metadata(restore: &OpenFGARestore, labels: &BTreeMap<String, String>) -> ObjectMeta {
+    ObjectMeta {
+        name: Some(restore_job_name(&restore.name_any())),
+        namespace: restore.namespace(),
+        labels: Some(labels.clone()),
+        owner_references: restore.controller_owner_ref(&()).map(|o| vec![o]),
+        ..Default::default()
+    }
+}
+
+fn labels(restore: &OpenFGARestore) -> BTreeMap<String, String> {
+    BTreeMap::from([
+        (
+            "app.kubernetes.io/managed-by".to_string(),
+            "openfga-operator".to_string(),
+        ),
+        (RESTORE_LABEL.to_string(), restore.name_any()),
+    ])
+}
+
+/// ServiceAccount, Role and RoleBinding letting the job patch this restore's
+/// status and nothing else.
+pub fn build_identity(restore: &OpenFGARestore) -> (ServiceAccount, Role, RoleBinding) {
+    let labels = labels(restore);
+    let name = restore_job_name(&restore.name_any());
+    let account = ServiceAccount {
+        metadata: metadata(restore, &labels),
+        ..Default::default()
+    };
+    let role = Role {
+        metadata: metadata(restore, &labels),
+        rules: Some(vec![PolicyRule {
+            api_groups: Some(vec!["authorization.openfga.dev".to_string()]),
+            resources: Some(vec!["openfgarestores/status".to_string()]),
+            resource_names: Some(vec![restore.name_any()]),
+            verbs: vec!["get".to_string(), "patch".to_string()],
+            ..Default::default()
+        }]),
+    };
+    let binding = RoleBinding {
+        metadata: metadata(restore, &labels),
+        role_ref: RoleRef {
+            api_group: "rbac.authorization.k8s.io".to_string(),
+            kind: "Role".to_string(),
+            name: name.clone(),
+        },
+        subjects: Some(vec![Subject {
+            kind: "ServiceAccount".to_string(),
+            name,
+            namespace: restore.namespace(),
+            ..Default::default()
+        }]),
+    };
+    (account, role, binding)
+}
+
+/// The Job restoring the artifact at `artifact`, a location inside
+/// `destination`, into `instance`.
+pub fn build_job(
+    restore: &OpenFGARestore,
+    instance: &OpenFGA,
+    destination: &BackupDestination,
+    artifact: &str,
+    image: &str,
+) -> Result<Job, String> {
+    let file_name = artifact
+        .rsplit('/')
+        .next()
+        .filter(|name| name.ends_with(".json"))
+        .ok_or_else(|| format!("artifact {} is not a .json file", artifact))?;
+    destination_uri(destination)?;
+
+    let restorer = |input: String, volume: VolumeMount| {
+        let mut args = vec![
+            "backup".to_string(),
+            "restore".to_string(),
+            "--url".to_string(),
+            instance_url(instance),
+            "--input".to_string(),
+            input,
+            "--batch-size".to_string(),
+            restore.spec.batch_size.clamp(1, 100).to_string(),
+            "--report".to_string(),
+            format!(
+                "{}/{}",
+                restore.namespace().unwrap_or_default(),
+                restore.name_any()
+            ),
+        ];
+        if let Some(grpc_url) = instance_grpc_url(instance) {
+            args.extend(["--grpc-url".to_string(), grpc_url]);
+        }
+        for store in &restore.spec.stores {
+            args.extend(["--store".to_string(), store.clone()]);
+        }
+        if restore.spec.dry_run {
+            args.push("--dry-run".to_string());
+        }
+        Container {
+            name: "restore".to_string(),
+            image: Some(image.to_string()),
+            args: Some(args),
+            env: Some(vec![access_tokens::api_token_env(instance)]),
+            volume_mounts: Some(vec![VolumeMount {
+                read_only: Some(true),
+                ..volume
+            }]),
+            ..Default::default()
+        }
+    };
+    let (init_containers, container, volumes) = if let Some(pvc) = &destination.pvc {
+        let input = format!(
+            "{}/{}",
+            with_prefix(PVC_MOUNT.to_string(), &pvc.path),
+            file_name
+        );
+        let container = restorer(input, mount("backup", PVC_MOUNT));
+        (None, container, vec![pvc_volume(pvc)])
+    } else {
+        let (image, script, mut env_vars, env_from, credentials) = if let Some(s3) = &destination.s3
+        {
+            let (env_vars, env_from) = s3_env(s3);
+            (
+                AWS_CLI_IMAGE,
+                S3_DOWNLOAD_SCRIPT,
+                env_vars,
+                Some(env_from),
+                None,
+            )
+        } else if let Some(gcs) = &destination.gcs {
+            (
+                CLOUD_SDK_IMAGE,
+                GCS_DOWNLOAD_SCRIPT,
+                vec![],
+                None,
+                Some(gcs_credentials(gcs)),
+            )
+        } else {
+            unreachable!("destination_uri accepted the destination");
+        };
+        env_vars.push(env("SOURCE", artifact));
+        let mut volume_mounts = vec![mount("work", WORK_DIR)];
+        let mut volumes = vec![work_volume()];
+        if let Some((volume, credentials_mount)) = credentials {
+            volume_mounts.push(credentials_mount);
+            volumes.push(volume);
+        }
+        let downloader = Container {
+            name: "download".to_string(),
+            image: Some(image.to_string()),
+            command: Some(vec![
+                "/bin/sh".to_string(),
+                "-c".to_string(),
+                script.to_string(),
+            ]),
+            env: Some(env_vars),
+            env_from,
+            volume_mounts: Some(volume_mounts),
+            ..Default::default()
+        };
+        let container = restorer(DOWNLOADED_ARTIFACT.to_string(), mount("work", WORK_DIR));
+        (Some(vec![downloader]), container, volumes)
+    };
+
+    let labels = labels(restore);
+    Ok(Job {
+        metadata: metadata(restore, &labels),
+        spec: Some(JobSpec {
+            // Restores skip what the target already has, so a retry resumes
+            backoff_limit: Some(2),
+            template: PodTemplateSpec {
+                metadata: Some(ObjectMeta {
+                    labels: Some(labels),
+                    ..Default::default()
+                }),
+                spec: Some(PodSpec {
+                    restart_policy: Some("Never".to_string()),
+                    service_account_name: Some(restore_job_name(&restore.name_any())),
+                    init_containers,
+                    containers: vec![container],
+                    volumes: Some(volumes),
+                    ..Default::default()
+                }),
+            },
+            ..Default::default()
+        }),
+        ..Default::default()
+    })
+}
+
+/// Phase, reason and message for a restore whose Job is in `job`'s state.
+fn job_phase(job: &Job, dry_run: bool) -> (&'static str, &'static str, String) {
+    let name = job.name_any();
+    let finished = |kind: &str| {
+        job.status
+            .as_ref()
+            .and_then(|s| s.conditions.as_ref())
+            .and_then(|conditions| {
+                conditions
+                    .iter()
+                    .find(|c| c.type_ == kind && c.status == "True")
+            })
+    };
+    if finished("Complete").is_some() {
+        let (reason, message) = if dry_run {
+            ("DryRunCompleted", "dry run completed, nothing was written")
+        } else {
+            ("Restored", "restore completed")
+        };
+        ("Succeeded", reason, message.to_string())
+    } else if let Some(failed) = finished("Failed") {
+        (
+            "Failed",
+            "RestoreFailed",
+            format!(
+                "job {} failed: {}",
+                name,
+                failed.message.clone().unwrap_or_default()
+            ),
+        )
+    } else {
+        ("Running", "Running", format!("job {} is running", name))
+    }
+}
+
+#[instrument(skip(ctx), fields(namespace = %restore.namespace().unwrap_or_default(), name = %restore.name_any(), generation = restore.metadata.generation.unwrap_or_default()))]
+async fn reconcile(
+    restore: Arc<OpenFGARestore>,
+    ctx: Arc<OpenFGARestoreController>,
+) -> ControllerResult<Action> {
+    let ns = restore.namespace().unwrap_or_default();
+    let name = restore.name_any();
+    let previous = restore.status.clone().unwrap_or_default();
+    if matches!(previous.phase.as_deref(), Some("Succeeded" | "Failed")) {
+        return Ok(Action::await_change());
+    }
+    let mut status = OpenFGARestoreStatus {
+        conditions: None,
+        phase: Some("Pending".to_string()),
+        ..previous.clone()
+    };
+    let condition = |ready: bool, reason: &str, message: &str| {
+        OpenFGACondition::new(
+            "Ready",
+            ready,
+            reason,
+            message,
+            restore.metadata.generation,
+            previous.conditions.as_deref(),
+        )
+    };
+
+    info!(
+        event = "restore_reconciliation_start",
+        namespace = %ns,
+        resource_name = %name,
+        instance = %restore.spec.instance_ref.name,
+        backup = %restore.spec.source.backup_ref.name,
+        "Starting OpenFGARestore reconciliation"
+    );
+
+    let instances: Api<OpenFGA> = Api::namespaced(ctx.client.clone(), &ns);
+    let Some(instance) = instances.get_opt(&restore.spec.instance_ref.name).await? else {
+        warn!(
+            event = "restore_instance_missing",
+            namespace = %ns,
+            resource_name = %name,
+            instance = %restore.spec.instance_ref.name,
+            "Referenced OpenFGA instance does not exist"
+        );
+        status.conditions = Some(vec![condition(
+            false,
+            "InstanceNotFound",
+            &format!(
+                "OpenFGA instance '{}' not found",
+                restore.spec.instance_ref.name
+            ),
+        )]);
+        patch_status(&ctx.client, &ns, &name, &status).await?;
+        return Ok(Action::requeue(Duration::from_secs(60)));
+    };
+
+    let backups: Api<OpenFGABackup> = Api::namespaced(ctx.client.clone(), &ns);
+    let backup_name = &restore.spec.source.backup_ref.name;
+    let Some(backup) = backups.get_opt(backup_name).await? else {
+        status.conditions = Some(vec![condition(
+            false,
+            "BackupNotFound",
+            &format!("OpenFGABackup '{}' not found", backup_name),
+        )]);
+        patch_status(&ctx.client, &ns, &name, &status).await?;
+        return Ok(Action::requeue(Duration::from_secs(60)));
+    };
+    let destination = &backup.spec.destination;
+
+    // Pinned once found, so a backup finishing mid-restore cannot change it
+    let artifact = previous
+        .artifact
+        .clone()
+        .or_else(|| match &restore.spec.source.artifact {
+            Some(file) => destination_uri(destination)
+                .ok()
+                .map(|dest| format!("{}/{}", dest, file)),
+            None => backup.status.as_ref().and_then(|s| s.last_backup.clone()),
+        });
+    let Some(artifact) = artifact else {
+        status.conditions = Some(vec![condition(
+            false,
+            "NoArtifact",
+            &format!(
+                "OpenFGABackup '{}' has no successful backup yet",
+                backup_name
+            ),
+        )]);
+        patch_status(&ctx.client, &ns, &name, &status).await?;
+        return Ok(Action::requeue(Duration::from_secs(60)));
+    };
+    status.artifact = Some(artifact.clone());
+
+    let image = operator_image(restore.spec.image.as_deref());
+    let job = match build_job(&restore, &instance, destination, &artifact, &image) {
+        Ok(job) => job,
+        Err(message) => {
+            status.phase = Some("Failed".to_string());
+            status.conditions = Some(vec![condition(false, "InvalidSource", &message)]);
+            patch_status(&ctx.client, &ns, &name, &status).await?;
+            return Ok(Action::await_change());
+        }
+    };
+
+    let job_name = restore_job_name(&name);
+    let jobs: Api<Job> = Api::namespaced(ctx.client.clone(), &ns);
+    let job = match jobs.get_opt(&job_name).await? {
+        Some(job) => job,
+        None => {
+            let (account, role, binding) = build_identity(&restore);
+            Api::<ServiceAccount>::namespaced(ctx.client.clone(), &ns)
+                .patch(&job_name, &apply_params(), &Patch::Apply(&account))
+                .await?;
+            Api::<Role>::namespaced(ctx.client.clone(), &ns)
+                .patch(&job_name, &apply_params(), &Patch::Apply(&role))
+                .await?;
+            Api::<RoleBinding>::namespaced(ctx.client.clone(), &ns)
+                .patch(&job_name, &apply_params(), &Patch::Apply(&binding))
+                .await?;
+            info!(
+                event = "restore_started",
+                namespace = %ns,
+                resource_name = %name,
+                artifact = %artifact,
+                dry_run = restore.spec.dry_run,
+                "Starting restore job"
+            );
+            jobs.patch(&job_name, &apply_params(), &Patch::Apply(&job))
+                .await?
+        }
+    };
+
+    let (phase, reason, message) = job_phase(&job, restore.spec.dry_run);
+    let job_status = job.status.clone().unwrap_or_default();
+    status.job = Some(job_name);
+    status.phase = Some(phase.to_string());
+    status.start_time = job_status.start_time.map(|t| t.0.to_rfc3339());
+    status.completion_time = match phase {
+        "Running" => None,
+        _ => Some(
+            job_status
+                .completion_time
+                .map(|t| t.0)
+                .unwrap_or_else(chrono::Utc::now)
+                .to_rfc3339(),
+        ),
+    };
+    if phase == "Failed" {
+        warn!(
+            event = "restore_failed",
+            namespace = %ns,
+            resource_name = %name,
+            message = %message,
+            "Restore job failed"
+        );
+    } else if phase == "Succeeded" {
+        info!(
+            event = "restore_completed",
+            namespace = %ns,
+            resource_name = %name,
+            dry_run = restore.spec.dry_run,
+            "Restore job completed"
+        );
+    }
+    status.conditions = Some(vec![condition(phase == "Succeeded", reason, &message)]);
+
+    patch_status(&ctx.client, &ns, &name, &status).await?;
+    Ok(match phase {
+        "Running" => Action::requeue(Duration::from_secs(60)),
+        _ => Action::await_change(),
+    })
+}
+
+/// Patches everything but `progress` and `appliedTupleBatches`, which belong
+/// to the restore job.
+async fn patch_status(
+    client: &Client,
+    ns: &str,
+    name: &str,
+    status: &OpenFGARestoreStatus,
+) -> ControllerResult<()> {
+    let mut status = serde_json::to_value(status)?;
+    if let Some(fields) = status.as_object_mut() {
+        fields.remove("progress");
+        fields.remove("appliedTupleBatches");
+    }
+    let restores: Api<OpenFGARestore> = Api::namespaced(client.clone(), ns);
+    restores
+        .patch_status(
+            name,
+            &PatchParams::default(),
+            &Patch::Merge(&serde_json::json!({ "status": status })),
+        )
+        .await?;
+    Ok(())
+}
+
+fn error_policy(
+    restore: Arc<OpenFGARestore>,
+    error: &ControllerError,
+    _ctx: Arc<OpenFGARestoreController>,
+) -> Action {
+    warn!(
+        event = "restore_reconciliation_error",
+        namespace = %restore.namespace().unwrap_or_default(),
+        resource_name = %restore.name_any(),
+        error_message = %error,
+        "OpenFGARestore reconciliation failed, retrying"
+    );
+    metrics::record_reconcile_error::<OpenFGARestore>(CONTROLLER_NAME, error.kind());
+    Action::requeue(Duration::from_secs(30))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::create_test_openfga;
+    use crate::types::ApiProtocol;
+    use k8s_openapi::api::batch::v1::{JobCondition, JobStatus};
+    use serde_json::{json, Value};
+
+    fn instance() -> OpenFGA {
+        let mut openfga = create_test_openfga();
+        openfga.metadata.name = Some("authz".to_string());
+        openfga.metadata.namespace = Some("auth".to_string());
+        openfga
+    }
+
+    fn restore(dry_run: bool) -> OpenFGARestore {
+        let mut restore = OpenFGARestore::new(
+            "recover",
+            serde_json::from_value(json!({
+                "instanceRef": { "name": "authz" },
+                "source": { "backupRef": { "name": "nightly" } },
+                "stores": ["default"],
+                "dryRun": dry_run,
+                "batchSize": 50,
+            }))
+            .unwrap(),
+        );
+        restore.metadata.namespace = Some("auth".to_string());
+        restore.metadata.uid = Some("5678".to_string());
+        restore
+    }
+
+    fn destination(value: Value) -> BackupDestination {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn pod_spec(job: &Job) -> PodSpec {
+        job.spec.clone().unwrap().template.spec.unwrap()
+    }
+
+    #[test]
+    fn test_pvc_job_reads_artifact_in_place() {
+        let job = build_job(
+            &restore(true),
+            &instance(),
+            &destination(json!({ "pvc": { "claimName": "backups", "path": "openfga" } })),
+            "pvc://backups/openfga/nightly-backup-29000000.json",
+            "operator:1",
+        )
+        .unwrap();
+        assert_eq!(job.metadata.name.as_deref(), Some("recover-restore"));
+        assert_eq!(
+            job.metadata.owner_references.as_ref().unwrap()[0].uid,
+            "5678"
+        );
+
+        let pod = pod_spec(&job);
+        assert_eq!(pod.service_account_name.as_deref(), Some("recover-restore"));
+        assert!(pod.init_containers.is_none());
+        assert_eq!(
+            pod.containers[0].args.as_ref().unwrap(),
+            &[
+                "backup",
+                "restore",
+                "--url",
+                "http://authz.auth.svc:8080",
+                "--input",
+                "/backup/openfga/nightly-backup-29000000.json",
+                "--batch-size",
+                "50",
+                "--report",
+                "auth/recover",
+                "--store",
+                "default",
+                "--dry-run",
+            ]
+        );
+        assert_eq!(
+            pod.containers[0].volume_mounts.as_ref().unwrap()[0].read_only,
+            Some(true)
+        );
+    }
+
+    #[test]
+    fn test_gcs_job_downloads_first() {
+        let job = build_job(
+            &restore(false),
+            &instance(),
+            &destination(json!({ "gcs": { "bucket": "b", "credentialsSecret": "gcs-key" } })),
+            "gs://b/nightly-backup-29000000.json",
+            "operator:1",
+        )
+        .unwrap();
+        let pod = pod_spec(&job);
+        let download = &pod.init_containers.as_ref().unwrap()[0];
+        assert_eq!(download.image.as_deref(), Some(CLOUD_SDK_IMAGE));
+        let source = download
+            .env
+            .as_ref()
+            .unwrap()
+            .iter()
+            .find(|e| e.name == "SOURCE")
+            .unwrap();
+        assert_eq!(
+            source.value.as_deref(),
+            Some("gs://b/nightly-backup-29000000.json")
+        );
+        let args = pod.containers[0].args.as_ref().unwrap();
+        assert!(args.contains(&DOWNLOADED_ARTIFACT.to_string()));
+        assert!(!args.contains(&"--dry-run".to_string()));
+        assert_eq!(pod.volumes.as_ref().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_job_writes_over_grpc() {
+        let mut instance = instance();
+        instance.spec.api_protocol = ApiProtocol::Grpc;
+        let job = build_job(
+            &restore(false),
+            &instance,
+            &destination(json!({ "pvc": { "claimName": "backups" } })),
+            "pvc://backups/nightly-backup-29000000.json",
+            "operator:1",
+        )
+        .unwrap();
+        let args = pod_spec(&job).containers[0].args.clone().unwrap();
+        let at = args.iter().position(|arg| arg == "--grpc-url").unwrap();
+        assert_eq!(args[at + 1], "http://authz.auth.svc:8081");
+    }
+
+    #[test]
+    fn test_job_rejects_non_json_artifact() {
+        assert!(build_job(
+            &restore(false),
+            &instance(),
+            &destination(json!({ "pvc": { "claimName": "backups" } })),
+            "pvc://backups/",
+            "operator:1",
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_identity_only_patches_own_status() {
+        let (account, role, binding) = build_identity(&restore(false));
+        assert_eq!(account.metadata.name.as_deref(), Some("recover-restore"));
+        let rule = &role.rules.as_ref().unwrap()[0];
+        assert_eq!(
+            rule.resources.as_deref(),
+            Some(&["openfgarestores/status".to_string()][..])
+        );
+        assert_eq!(
+            rule.resource_names.as_deref(),
+            Some(&["recover".to_string()][..])
+        );
+        assert_eq!(
+            binding.subjects.as_ref().unwrap()[0].name,
+            "recover-restore"
+        );
+    }
+
+    fn job(condition: Option<&str>) -> Job {
+        Job {
+            metadata: ObjectMeta {
+                name: Some("recover-restore".to_string()),
+                ..Default::default()
+            },
+            status: Some(JobStatus {
+                conditions: condition.map(|kind| {
+                    vec![JobCondition {
+                        type_: kind.to_string(),
+                        status: "True".to_string(),
+                        message: Some("BackoffLimitExceeded".to_string()),
+                        ..Default::default()
+                    }]
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_job_phase() {
+        assert_eq!(job_phase(&job(None), false).0, "Running");
+        assert_eq!(job_phase(&job(Some("Complete")), true).1, "DryRunCompleted");
+        let (phase, reason, message) = job_phase(&job(Some("Failed")), false);
+        assert_eq!((phase, reason), ("Failed", "RestoreFailed"));
+        assert!(message.contains("BackoffLimitExceeded"));
+    }
+}
@@ -0,0 +1,387 @@
+use crate::bulk_writer::{BulkWriter, BulkWriterConfig, TupleWriter};
+use crate::openfga_client::{ChangeOperation, ChangesPage, ClientResult, TupleChange};
+use crate::tuple_scan::StoreReader;
+use crate::tuples::{TupleKey, TupleOperation};
+use crate::types::{OrphanRule, RetentionPolicy, RetentionRule};
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{BTreeMap, BTreeSet};
+use std::future::Future;
+
+const MAX_EXAMPLES: usize = 10;
+const CHANGES_PAGE_SIZE: usize = 100;
+
+/// Paged access to a store's change log.
+pub trait ChangeReader {
+    fn read_changes_page(
+        &self,
+        page_size: usize,
+        continuation_token: Option<&str>,
+    ) -> impl Future<Output = ClientResult<ChangesPage>>;
+}
+
+impl ChangeReader for StoreReader<'_> {
+    async fn read_changes_page(
+        &self,
+        page_size: usize,
+        continuation_token: Option<&str>,
+    ) -> ClientResult<ChangesPage> {
+        self.client
+            .read_changes(self.store_id, page_size, continuation_token)
+            .await
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RetentionOutcome {
+    pub matched: usize,
+    /// Matched tuples that were orphaned rather than expired.
+    pub orphaned: usize,
+    pub deleted: usize,
+    pub failed: usize,
+    pub examples: Vec<String>,
+}
+
+/// Glob match where `*` stands for any sequence of characters.
+pub fn matches_pattern(pattern: &str, value: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == value;
+    }
+
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if !value.starts_with(first) || value.len() < first.len() + last.len() {
+        return false;
+    }
+    let mut rest = &value[first.len()..value.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    value.ends_with(last)
+}
+
+fn object_type(object: &str) -> &str {
+    object.split(':').next().unwrap_or_default()
+}
+
+/// The object a tuple's user refers to, e.g. `folder:1` for `folder:1#viewer`;
+/// `None` for type-bound wildcards such as `user:*`.
+fn user_object(user: &str) -> Option<&str> {
+    let object = user.split('#').next().unwrap_or_default();
+    (object.contains(':') && !object.ends_with(":*")).then_some(object)
+}
+
+fn rule_matches(rule: &RetentionRule, tuple: &TupleKey) -> bool {
+    let object_type = object_type(&tuple.object);
+    rule.relations
+        .iter()
+        .any(|p| matches_pattern(p, &tuple.relation))
+        && (rule.object_types.is_empty()
+            || rule
+                .object_types
+                .iter()
+                .any(|p| matches_pattern(p, object_type)))
+}
+
+/// Replays the change log into the set of tuples that still exist, keyed to the time
+/// they were last written.
+pub fn live_tuples(
+    changes: impl IntoIterator<Item = TupleChange>,
+) -> BTreeMap<TupleKey, DateTime<Utc>> {
+    let mut live = BTreeMap::new();
+    for change in changes {
+        match change.operation {
+            ChangeOperation::Write => {
+                live.insert(change.tuple_key, change.timestamp);
+            }
+            ChangeOperation::Delete => {
+                live.remove(&change.tuple_key);
+            }
+        }
+    }
+    live
+}
+
+/// Tuples matched by any rule and last written before that rule's cutoff.
+pub fn stale_tuples(
+    live: &BTreeMap<TupleKey, DateTime<Utc>>,
+    rules: &[RetentionRule],
+    now: DateTime<Utc>,
+) -> Vec<TupleKey> {
+    live.iter()
+        .filter(|(tuple, written)| {
+            rules.iter().any(|rule| {
+                rule_matches(rule, tuple)
+                    && **written < now - Duration::days(i64::from(rule.max_age_days))
+            })
+        })
+        .map(|(tuple, _)| tuple.clone())
+        .collect()
+}
+
+/// Tuples of objects that lost their anchor relation, and tuples naming such
+/// objects as the user, written before the rule's grace period.
+pub fn orphaned_tuples(
+    live: &BTreeMap<TupleKey, DateTime<Utc>>,
+    rules: &[OrphanRule],
+    now: DateTime<Utc>,
+) -> Vec<TupleKey> {
+    let mut orphaned = BTreeSet::new();
+    for rule in rules {
+        let anchored: BTreeSet<&str> = live
+            .keys()
+            .filter(|t| object_type(&t.object) == rule.object_type)
+            .filter(|t| t.relation == rule.anchor_relation)
+            .map(|t| t.object.as_str())
+            .collect();
+        let is_orphan =
+            |object: &str| object_type(object) == rule.object_type && !anchored.contains(object);
+        let cutoff = now - Duration::minutes(i64::from(rule.grace_minutes));
+        for (tuple, written) in live {
+            if *written >= cutoff {
+                continue;
+            }
+            let own = is_orphan(&tuple.object)
+                && (rule.relations.is_empty()
+                    || rule
+                        .relations
+                        .iter()
+                        .any(|p| matches_pattern(p, &tuple.relation)));
+            if own || user_object(&tuple.user).is_some_and(is_orphan) {
+                orphaned.insert(tuple.clone());
+            }
+        }
+    }
+    orphaned.into_iter().collect()
+}
+
+/// Reads the full change log, then reports or deletes stale and orphaned tuples
+/// per the policy.
+pub async fn run_retention(
+    reader: &impl ChangeReader,
+    writer: impl TupleWriter,
+    policy: &RetentionPolicy,
+    now: DateTime<Utc>,
+) -> ClientResult<RetentionOutcome> {
+    let mut changes = Vec::new();
+    let mut token: Option<String> = None;
+    loop {
+        let page = reader
+            .read_changes_page(CHANGES_PAGE_SIZE, token.as_deref())
+            .await?;
+        // ReadChanges keeps returning the last token once the log is exhausted
+        if page.changes.is_empty() {
+            break;
+        }
+        changes.extend(page.changes);
+        match page.continuation_token {
+            Some(next) => token = Some(next),
+            None => break,
+        }
+    }
+
+    let live = live_tuples(changes);
+    let orphaned = orphaned_tuples(&live, &policy.orphans, now);
+    let stale: Vec<TupleKey> = stale_tuples(&live, &policy.rules, now)
+        .into_iter()
+        .chain(orphaned.iter().cloned())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    let mut outcome = RetentionOutcome {
+        matched: stale.len(),
+        orphaned: orphaned.len(),
+        examples: stale
+            .iter()
+            .take(MAX_EXAMPLES)
+            .map(|t| t.to_string())
+            .collect(),
+        ..Default::default()
+    };
+
+    if !policy.dry_run && !stale.is_empty() {
+        let report = BulkWriter::new(writer, BulkWriterConfig::default(), "retention")
+            .apply(stale.into_iter().map(TupleOperation::Delete).collect())
+            .await;
+        outcome.deleted = report.deleted;
+        outcome.failed = report.failed.len();
+    }
+
+    Ok(outcome)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bulk_writer::WriteError;
+    use std::sync::Mutex;
+
+    fn change(user: &str, relation: &str, op: ChangeOperation, days_ago: i64) -> TupleChange {
+        TupleChange {
+            tuple_key: TupleKey::new(user, relation, "doc:1"),
+            operation: op,
+            timestamp: Utc::now() - Duration::days(days_ago),
+        }
+    }
+
+    fn rule(relations: &[&str], max_age_days: u32) -> RetentionRule {
+        RetentionRule {
+            relations: relations.iter().map(|r| r.to_string()).collect(),
+            object_types: vec![],
+            max_age_days,
+        }
+    }
+
+    struct LogReader {
+        changes: Vec<TupleChange>,
+    }
+
+    impl ChangeReader for LogReader {
+        async fn read_changes_page(
+            &self,
+            page_size: usize,
+            continuation_token: Option<&str>,
+        ) -> ClientResult<ChangesPage> {
+            let start: usize = continuation_token.map_or(0, |t| t.parse().unwrap());
+            let end = (start + page_size).min(self.changes.len());
+            Ok(ChangesPage {
+                changes: self.changes[start..end].to_vec(),
+                continuation_token: Some(end.to_string()),
+            })
+        }
+    }
+
+    #[derive(Default)]
+    struct RecordingWriter {
+        deleted: Mutex<Vec<TupleOperation>>,
+    }
+
+    impl TupleWriter for &RecordingWriter {
+        async fn write(&self, batch: &[TupleOperation]) -> Result<(), WriteError> {
+            self.deleted.lock().unwrap().extend_from_slice(batch);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_matches_pattern() {
+        assert!(matches_pattern("viewer", "viewer"));
+        assert!(!matches_pattern("viewer", "viewers"));
+        assert!(matches_pattern("temp_*", "temp_access"));
+        assert!(matches_pattern("*_guest", "doc_guest"));
+        assert!(matches_pattern("a*b*c", "axxbyyc"));
+        assert!(!matches_pattern("a*b*c", "acb"));
+        assert!(matches_pattern("*", ""));
+        assert!(!matches_pattern("ab*ba", "aba"));
+    }
+
+    #[test]
+    fn test_stale_tuples() {
+        let live = live_tuples(vec![
+            change("user:old", "temp_viewer", ChangeOperation::Write, 40),
+            change("user:new", "temp_viewer", ChangeOperation::Write, 5),
+            change("user:gone", "temp_viewer", ChangeOperation::Write, 50),
+            change("user:gone", "temp_viewer", ChangeOperation::Delete, 45),
+            change("user:owner", "owner", ChangeOperation::Write, 400),
+        ]);
+        assert_eq!(live.len(), 3);
+
+        let stale = stale_tuples(&live, &[rule(&["temp_*"], 30)], Utc::now());
+        assert_eq!(
+            stale,
+            vec![TupleKey::new("user:old", "temp_viewer", "doc:1")]
+        );
+
+        let typed = RetentionRule {
+            object_types: vec!["folder".to_string()],
+            ..rule(&["temp_*"], 30)
+        };
+        assert!(stale_tuples(&live, &[typed], Utc::now()).is_empty());
+    }
+
+    #[test]
+    fn test_orphaned_tuples() {
+        let write = |user: &str, relation: &str, object: &str, minutes_ago: i64| TupleChange {
+            tuple_key: TupleKey::new(user, relation, object),
+            operation: ChangeOperation::Write,
+            timestamp: Utc::now() - Duration::minutes(minutes_ago),
+        };
+        let live = live_tuples(vec![
+            write("user:anne", "owner", "document:kept", 600),
+            write("user:bob", "viewer", "document:kept", 600),
+            write("user:anne", "owner", "document:gone", 600),
+            write("user:bob", "viewer", "document:gone", 600),
+            write("team:a#member", "editor", "document:gone", 600),
+            write("document:gone", "parent", "comment:1", 600),
+            write("document:kept", "parent", "comment:2", 600),
+            write("user:*", "viewer", "document:public", 600),
+            write("user:carol", "viewer", "document:new", 5),
+            TupleChange {
+                operation: ChangeOperation::Delete,
+                ..write("user:anne", "owner", "document:gone", 300)
+            },
+        ]);
+        let rule = OrphanRule {
+            object_type: "document".to_string(),
+            anchor_relation: "owner".to_string(),
+            relations: vec![],
+            grace_minutes: 60,
+        };
+        let orphaned = orphaned_tuples(&live, std::slice::from_ref(&rule), Utc::now());
+        assert_eq!(
+            orphaned,
+            vec![
+                TupleKey::new("document:gone", "parent", "comment:1"),
+                TupleKey::new("team:a#member", "editor", "document:gone"),
+                TupleKey::new("user:*", "viewer", "document:public"),
+                TupleKey::new("user:bob", "viewer", "document:gone"),
+            ]
+        );
+
+        let viewers_only = OrphanRule {
+            relations: vec!["view*".to_string()],
+            ..rule
+        };
+        let orphaned = orphaned_tuples(&live, &[viewers_only], Utc::now());
+        assert_eq!(orphaned.len(), 3);
+        assert!(!orphaned.contains(&TupleKey::new("team:a#member", "editor", "document:gone")));
+    }
+
+    #[tokio::test]
+    async fn test_run_retention_dry_run_and_enforce() {
+        let reader = LogReader {
+            changes: (0..150)
+                .map(|i| change(&format!("user:{}", i), "temp", ChangeOperation::Write, 10))
+                .collect(),
+        };
+        let mut policy = RetentionPolicy {
+            rules: vec![rule(&["temp"], 7)],
+            orphans: vec![],
+            dry_run: true,
+            interval_minutes: 60,
+        };
+
+        let writer = RecordingWriter::default();
+        let outcome = run_retention(&reader, &writer, &policy, Utc::now())
+            .await
+            .unwrap();
+        assert_eq!(outcome.matched, 150);
+        assert_eq!(outcome.deleted, 0);
+        assert_eq!(outcome.examples.len(), MAX_EXAMPLES);
+        assert!(writer.deleted.lock().unwrap().is_empty());
+
+        policy.dry_run = false;
+        let outcome = run_retention(&reader, &writer, &policy, Utc::now())
+            .await
+            .unwrap();
+        assert_eq!(outcome.deleted, 150);
+        assert!(writer
+            .deleted
+            .lock()
+            .unwrap()
+            .iter()
+            .all(|op| matches!(op, TupleOperation::Delete(_))));
+    }
+}
@@ -0,0 +1,386 @@
+//! Automatic rollback of a Deployment rollout that gets stuck. Whenever the
+//! Deployment has fully rolled out the pod template the operator applied, that
+//! template is recorded on the instance in [`LAST_KNOWN_GOOD_ANNOTATION`]. When a
+//! later operator change stops with `ProgressDeadlineExceeded`, the Deployment
+//! is reverted to the recorded template and the failed template's hash is kept
+//! in [`ROLLED_BACK_ANNOTATION`], so the change is not retried until the spec
+//! produces a different template.
+
+use crate::controller::ControllerResult;
+use crate::drift;
+use crate::types::{OpenFGA, OpenFGACondition};
+use k8s_openapi::api::apps::v1::Deployment;
+use k8s_openapi::api::core::v1::PodTemplateSpec;
+use kube::api::{Api, Patch, PatchParams};
+use kube::runtime::events::{Event, EventType, Recorder};
+use kube::{Client, Resource, ResourceExt};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use tracing::{info, instrument, warn};
+
+/// Pod template of the last rollout that completed, as JSON.
+pub const LAST_KNOWN_GOOD_ANNOTATION: &str = "openfga.dev/last-known-good";
+/// Hash of the pod template that was rolled back.
+pub const ROLLED_BACK_ANNOTATION: &str = "openfga.dev/rolled-back-template";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RollbackAction {
+    None,
+    /// The applied template rolled out; remember it.
+    Record,
+    /// The applied template is stuck; revert to the known-good one.
+    RollBack {
+        failed: String,
+    },
+    /// The spec still produces the rolled back template; keep the known-good one.
+    Hold,
+    /// The spec changed since the rollback; try the new template.
+    Clear,
+}
+
+pub fn template_hash(template: &PodTemplateSpec) -> String {
+    let digest = Sha256::digest(serde_json::to_vec(template).unwrap_or_default());
+    digest
+        .iter()
+        .take(8)
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn template(deployment: &Deployment) -> Option<&PodTemplateSpec> {
+    deployment.spec.as_ref().map(|s| &s.template)
+}
+
+/// Whether the live Deployment runs the template the operator applied; the API
+/// server adds defaults, so the desired template only has to be contained in it.
+fn applied(desired: &PodTemplateSpec, live: &Deployment) -> bool {
+    let Some(live) = template(live) else {
+        return false;
+    };
+    match (serde_json::to_value(desired), serde_json::to_value(live)) {
+        (Ok(desired), Ok(live)) => drift::is_subset(&desired, &live),
+        _ => false,
+    }
+}
+
+fn deadline_exceeded(live: &Deployment) -> bool {
+    live.status
+        .as_ref()
+        .and_then(|s| s.conditions.as_ref())
+        .into_iter()
+        .flatten()
+        .any(|c| {
+            c.type_ == "Progressing"
+                && c.status == "False"
+                && c.reason.as_deref() == Some("ProgressDeadlineExceeded")
+        })
+}
+
+fn rolled_out(live: &Deployment) -> bool {
+    let Some(status) = live.status.as_ref() else {
+        return false;
+    };
+    let replicas = live.spec.as_ref().and_then(|s| s.replicas).unwrap_or(1);
+    let current = match (status.observed_generation, live.metadata.generation) {
+        (Some(observed), Some(generation)) => observed >= generation,
+        _ => false,
+    };
+    current
+        && status.updated_replicas.unwrap_or(0) >= replicas
+        && status.ready_replicas.unwrap_or(0) >= replicas
+        && status.replicas.unwrap_or(0) <= replicas
+}
+
+/// What to do with the template about to be applied, given the live Deployment
+/// and what the instance recorded so far.
+pub fn next_action(
+    desired: &PodTemplateSpec,
+    live: &Deployment,
+    known_good: Option<&PodTemplateSpec>,
+    rolled_back: Option<&str>,
+) -> RollbackAction {
+    let hash = template_hash(desired);
+    match rolled_back {
+        Some(failed) if failed == hash && known_good.is_some() => return RollbackAction::Hold,
+        Some(_) => return RollbackAction::Clear,
+        None => {}
+    }
+    if known_good == Some(desired) || !applied(desired, live) {
+        return RollbackAction::None;
+    }
+    if deadline_exceeded(live) {
+        if known_good.is_some() {
+            return RollbackAction::RollBack { failed: hash };
+        }
+        return RollbackAction::None;
+    }
+    if rolled_out(live) {
+        return RollbackAction::Record;
+    }
+    RollbackAction::None
+}
+
+/// `RollbackPerformed` is true while the instance holds a rolled back template;
+/// instances in a target cluster are never rolled back.
+pub fn rollback_condition(
+    openfga: &OpenFGA,
+    previous: Option<&[OpenFGACondition]>,
+) -> OpenFGACondition {
+    let generation = openfga.metadata.generation;
+    if openfga.spec.target_cluster_secret_ref.is_some() {
+        return OpenFGACondition::new(
+            "RollbackPerformed",
+            false,
+            "TargetCluster",
+            "Automatic rollback is not run for instances in a target cluster",
+            generation,
+            previous,
+        );
+    }
+    match openfga.annotations().get(ROLLED_BACK_ANNOTATION) {
+        Some(hash) => OpenFGACondition::new(
+            "RollbackPerformed",
+            true,
+            "ProgressDeadlineExceeded",
+            &format!(
+                "Pod template {} did not roll out, reverted to the last known-good template",
+                hash
+            ),
+            generation,
+            previous,
+        ),
+        None => OpenFGACondition::new(
+            "RollbackPerformed",
+            false,
+            "AsExpected",
+            "No rollback performed",
+            generation,
+            previous,
+        ),
+    }
+}
+
+async fn annotate(
+    client: &Client,
+    openfga: &OpenFGA,
+    annotations: serde_json::Value,
+) -> ControllerResult<()> {
+    let api: Api<OpenFGA> =
+        Api::namespaced(client.clone(), &openfga.namespace().unwrap_or_default());
+    api.patch(
+        &openfga.name_any(),
+        &PatchParams::default(),
+        &Patch::Merge(&json!({ "metadata": { "annotations": annotations } })),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Records or reverts the pod template `deployment` is about to be applied with.
+#[instrument(skip(client, openfga, deployment, live), fields(namespace = %ns, name = %name))]
+pub async fn reconcile_rollback(
+    client: &Client,
+    openfga: &OpenFGA,
+    deployment: &mut Deployment,
+    live: &Deployment,
+    ns: &str,
+    name: &str,
+) -> ControllerResult<()> {
+    let Some(desired) = template(deployment).cloned() else {
+        return Ok(());
+    };
+    let annotations = openfga.annotations();
+    let known_good: Option<PodTemplateSpec> = annotations
+        .get(LAST_KNOWN_GOOD_ANNOTATION)
+        .and_then(|v| serde_json::from_str(v).ok());
+    let rolled_back = annotations.get(ROLLED_BACK_ANNOTATION).map(String::as_str);
+
+    let action = next_action(&desired, live, known_good.as_ref(), rolled_back);
+    match action {
+        RollbackAction::None => {}
+        RollbackAction::Record => {
+            let recorded = serde_json::to_string(&desired)?;
+            annotate(
+                client,
+                openfga,
+                json!({ LAST_KNOWN_GOOD_ANNOTATION: recorded }),
+            )
+            .await?;
+            info!(
+                event = "known_good_template_recorded",
+                namespace = %ns,
+                resource_name = %name,
+                template = %template_hash(&desired),
+                "Recorded rolled out pod template as last known-good"
+            );
+        }
+        RollbackAction::Clear => {
+            annotate(client, openfga, json!({ ROLLED_BACK_ANNOTATION: null })).await?;
+        }
+        RollbackAction::Hold | RollbackAction::RollBack { .. } => {
+            if let (Some(spec), Some(known_good)) = (deployment.spec.as_mut(), known_good) {
+                spec.template = known_good;
+            }
+        }
+    }
+
+    if let RollbackAction::RollBack { failed } = action {
+        annotate(client, openfga, json!({ ROLLED_BACK_ANNOTATION: failed })).await?;
+        warn!(
+            event = "rollback_performed",
+            namespace = %ns,
+            resource_name = %name,
+            template = %failed,
+            "Rollout exceeded its progress deadline, reverting to the last known-good pod template"
+        );
+        let recorder = Recorder::new(
+            client.clone(),
+            "openfga-controller".into(),
+            openfga.object_ref(&()),
+        );
+        let event = Event {
+            type_: EventType::Warning,
+            reason: "RollbackPerformed".to_string(),
+            note: Some(format!(
+                "Deployment {} exceeded its progress deadline, reverted to the last known-good pod template",
+                name
+            )),
+            action: "Rollback".to_string(),
+            secondary: None,
+        };
+        if let Err(e) = recorder.publish(event).await {
+            warn!(
+                event = "rollback_event_failed",
+                namespace = %ns,
+                resource_name = %name,
+                error = %e,
+                "Failed to publish rollback event"
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::api::apps::v1::{DeploymentCondition, DeploymentSpec, DeploymentStatus};
+    use k8s_openapi::api::core::v1::{Container, PodSpec};
+    use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+
+    fn template(image: &str) -> PodTemplateSpec {
+        PodTemplateSpec {
+            metadata: None,
+            spec: Some(PodSpec {
+                containers: vec![Container {
+                    name: "openfga".to_string(),
+                    image: Some(image.to_string()),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }),
+        }
+    }
+
+    fn live(image: &str, ready: i32, progressing: Option<&str>) -> Deployment {
+        let mut template = template(image);
+        // Defaults added by the API server
+        template.spec.as_mut().unwrap().restart_policy = Some("Always".to_string());
+        Deployment {
+            metadata: ObjectMeta {
+                generation: Some(2),
+                ..Default::default()
+            },
+            spec: Some(DeploymentSpec {
+                replicas: Some(2),
+                template,
+                ..Default::default()
+            }),
+            status: Some(DeploymentStatus {
+                observed_generation: Some(2),
+                replicas: Some(2),
+                updated_replicas: Some(2),
+                ready_replicas: Some(ready),
+                conditions: progressing.map(|reason| {
+                    vec![DeploymentCondition {
+                        type_: "Progressing".to_string(),
+                        status: "False".to_string(),
+                        reason: Some(reason.to_string()),
+                        ..Default::default()
+                    }]
+                }),
+                ..Default::default()
+            }),
+        }
+    }
+
+    const GOOD: &str = "openfga/openfga:v1.5.2";
+    const BAD: &str = "openfga/openfga:v1.5.3";
+
+    #[test]
+    fn test_records_rolled_out_template() {
+        let desired = template(GOOD);
+        assert_eq!(
+            next_action(&desired, &live(GOOD, 2, None), None, None),
+            RollbackAction::Record
+        );
+        assert_eq!(
+            next_action(&desired, &live(GOOD, 2, None), Some(&desired), None),
+            RollbackAction::None
+        );
+        // Not ready yet, or still running the previous template
+        assert_eq!(
+            next_action(&desired, &live(GOOD, 1, None), None, None),
+            RollbackAction::None
+        );
+        assert_eq!(
+            next_action(&desired, &live(BAD, 2, None), None, None),
+            RollbackAction::None
+        );
+    }
+
+    #[test]
+    fn test_rolls_back_stuck_template() {
+        let good = template(GOOD);
+        let desired = template(BAD);
+        let stuck = live(BAD, 1, Some("ProgressDeadlineExceeded"));
+        assert_eq!(
+            next_action(&desired, &stuck, Some(&good), None),
+            RollbackAction::RollBack {
+                failed: template_hash(&desired)
+            }
+        );
+        // Nothing to revert to
+        assert_eq!(
+            next_action(&desired, &stuck, None, None),
+            RollbackAction::None
+        );
+        // Stuck on a template the operator did not apply
+        assert_eq!(
+            next_action(
+                &good,
+                &stuck,
+                Some(&template("openfga/openfga:v1.4.0")),
+                None
+            ),
+            RollbackAction::None
+        );
+    }
+
+    #[test]
+    fn test_holds_until_spec_changes() {
+        let good = template(GOOD);
+        let desired = template(BAD);
+        let hash = template_hash(&desired);
+        let reverted = live(GOOD, 2, None);
+        assert_eq!(
+            next_action(&desired, &reverted, Some(&good), Some(&hash)),
+            RollbackAction::Hold
+        );
+        let fixed = template("openfga/openfga:v1.5.4");
+        assert_eq!(
+            next_action(&fixed, &reverted, Some(&good), Some(&hash)),
+            RollbackAction::Clear
+        );
+    }
+}
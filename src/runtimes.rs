@@ -0,0 +1,97 @@
+//! Separate tokio runtimes for the controllers and the HTTP servers, so a burst
+//! of reconciliations cannot delay liveness probes and a flood of HTTP requests
+//! cannot starve reconciles of worker threads.
+
+use std::io;
+use std::thread;
+use tokio::runtime::{Builder, Runtime};
+
+const CONTROLLER_THREADS_ENV: &str = "OPENFGA_OPERATOR_CONTROLLER_THREADS";
+const HTTP_THREADS_ENV: &str = "OPENFGA_OPERATOR_HTTP_THREADS";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RuntimeConfig {
+    pub controller_threads: usize,
+    pub http_threads: usize,
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        Self {
+            controller_threads: thread::available_parallelism().map_or(2, |n| n.get()),
+            http_threads: 1,
+        }
+    }
+}
+
+impl RuntimeConfig {
+    /// Defaults, overridden by any valid positive `OPENFGA_OPERATOR_*_THREADS` variables.
+    pub fn from_env() -> Self {
+        Self::from_lookup(|name| std::env::var(name).ok())
+    }
+
+    fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let defaults = Self::default();
+        let parse = |name: &str| {
+            lookup(name)
+                .and_then(|v| v.trim().parse::<usize>().ok())
+                .filter(|&n| n > 0)
+        };
+        Self {
+            controller_threads: parse(CONTROLLER_THREADS_ENV)
+                .unwrap_or(defaults.controller_threads),
+            http_threads: parse(HTTP_THREADS_ENV).unwrap_or(defaults.http_threads),
+        }
+    }
+
+    pub fn controller_runtime(&self) -> io::Result<Runtime> {
+        build("openfga-controller", self.controller_threads)
+    }
+
+    pub fn http_runtime(&self) -> io::Result<Runtime> {
+        build("openfga-http", self.http_threads)
+    }
+}
+
+fn build(name: &str, threads: usize) -> io::Result<Runtime> {
+    Builder::new_multi_thread()
+        .worker_threads(threads)
+        .thread_name(name)
+        .enable_all()
+        .build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_config_from_env() {
+        let env = HashMap::from([(CONTROLLER_THREADS_ENV, "3"), (HTTP_THREADS_ENV, "0")]);
+        let config = RuntimeConfig::from_lookup(|name| env.get(name).map(|v| v.to_string()));
+
+        assert_eq!(config.controller_threads, 3);
+        assert_eq!(config.http_threads, RuntimeConfig::default().http_threads);
+    }
+
+    #[test]
+    fn test_runtimes_use_separate_threads() {
+        let config = RuntimeConfig {
+            controller_threads: 1,
+            http_threads: 1,
+        };
+        let controller = config.controller_runtime().unwrap();
+        let http = config.http_runtime().unwrap();
+
+        let http_thread = controller.block_on(async {
+            http.spawn(async { thread::current().name().map(str::to_string) })
+                .await
+                .unwrap()
+        });
+        assert_eq!(http_thread.as_deref(), Some("openfga-http"));
+        assert_eq!(controller.metrics().num_workers(), 1);
+
+        http.shutdown_background();
+    }
+}
@@ -0,0 +1,242 @@
+//! Renders `spec.config` into a ConfigMap of `OPENFGA_*` variables that the
+//! OpenFGA container loads with `envFrom`. Keys follow OpenFGA's config file
+//! layout, either nested (`log: {level: info}`) or dotted (`log.level`), and
+//! camelCase segments become snake case (`maxTuplesPerWrite` →
+//! `OPENFGA_MAX_TUPLES_PER_WRITE`).
+
+use crate::apply;
+use crate::controller::ControllerResult;
+use crate::ingress::is_owned_by;
+use crate::labels;
+use crate::types::OpenFGA;
+use k8s_openapi::api::core::v1::{ConfigMap, ConfigMapEnvSource, EnvFromSource};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use kube::api::{Api, DeleteParams, Patch};
+use kube::{Client, Resource, ResourceExt};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use tracing::{debug, info, instrument};
+
+/// Pod template annotation carrying the rendered config's hash, so a config
+/// change rolls the Deployment.
+pub const CONFIG_HASH_ANNOTATION: &str = "openfga.dev/config-hash";
+
+pub fn config_map_name(name: &str) -> String {
+    format!("{}-config", name)
+}
+
+/// The `OPENFGA_*` variables for `config`, sorted by name.
+pub fn render(config: &BTreeMap<String, Value>) -> BTreeMap<String, String> {
+    let mut env = BTreeMap::new();
+    for (key, value) in config {
+        flatten(&mut env, &env_segment(key), value);
+    }
+    env
+}
+
+fn flatten(env: &mut BTreeMap<String, String>, prefix: &str, value: &Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                flatten(env, &format!("{}_{}", prefix, env_segment(key)), value);
+            }
+        }
+        Value::Null => {}
+        value => {
+            env.insert(format!("OPENFGA_{}", prefix), scalar(value));
+        }
+    }
+}
+
+/// OpenFGA reads list settings as comma-separated values.
+fn scalar(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Array(items) => items.iter().map(scalar).collect::<Vec<_>>().join(","),
+        other => other.to_string(),
+    }
+}
+
+fn env_segment(key: &str) -> String {
+    let mut out = String::new();
+    let mut previous_lower = false;
+    for c in key.chars() {
+        if c == '.' || c == '-' || c == '_' {
+            out.push('_');
+            previous_lower = false;
+        } else if c.is_ascii_uppercase() {
+            if previous_lower {
+                out.push('_');
+            }
+            out.push(c);
+            previous_lower = false;
+        } else {
+            out.push(c.to_ascii_uppercase());
+            previous_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+        }
+    }
+    out
+}
+
+pub fn config_hash(data: &BTreeMap<String, String>) -> String {
+    let mut hasher = Sha256::new();
+    for (key, value) in data {
+        hasher.update(key.as_bytes());
+        hasher.update([0]);
+        hasher.update(value.as_bytes());
+        hasher.update([0]);
+    }
+    hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+pub fn create_config_map(openfga: &OpenFGA, ns: &str, name: &str) -> Option<ConfigMap> {
+    let data = render(&openfga.spec.config);
+    if data.is_empty() {
+        return None;
+    }
+    Some(ConfigMap {
+        metadata: ObjectMeta {
+            name: Some(config_map_name(name)),
+            namespace: Some(ns.to_string()),
+            labels: Some(labels::instance_labels(openfga, name)),
+            annotations: labels::instance_annotations(openfga, []),
+            owner_references: openfga.controller_owner_ref(&()).map(|o| vec![o]),
+            ..Default::default()
+        },
+        data: Some(data),
+        ..Default::default()
+    })
+}
+
+/// The `envFrom` source for the generated ConfigMap, if there is any config.
+pub fn env_from(openfga: &OpenFGA, name: &str) -> Option<EnvFromSource> {
+    (!openfga.spec.config.is_empty()).then(|| EnvFromSource {
+        config_map_ref: Some(ConfigMapEnvSource {
+            name: Some(config_map_name(name)),
+            optional: Some(false),
+        }),
+        ..Default::default()
+    })
+}
+
+/// Applies the generated ConfigMap, or removes it once `spec.config` is emptied.
+#[instrument(skip(client, openfga), fields(namespace = %ns, name = %name))]
+pub async fn reconcile_config_map(
+    client: &Client,
+    openfga: &OpenFGA,
+    ns: &str,
+    name: &str,
+) -> ControllerResult<()> {
+    let config_maps: Api<ConfigMap> = Api::namespaced(client.clone(), ns);
+    let config_map_name = config_map_name(name);
+
+    match create_config_map(openfga, ns, name) {
+        Some(config_map) => {
+            config_maps
+                .patch(
+                    &config_map_name,
+                    &apply::apply_params(),
+                    &Patch::Apply(&config_map),
+                )
+                .await?;
+            info!(
+                event = "config_map_applied",
+                namespace = %ns,
+                resource_name = %name,
+                settings = config_map.data.as_ref().map_or(0, |d| d.len()),
+                "Applied OpenFGA server configuration"
+            );
+        }
+        None => {
+            if let Some(existing) = config_maps.get_opt(&config_map_name).await? {
+                if is_owned_by(existing.owner_references(), openfga) {
+                    config_maps
+                        .delete(&config_map_name, &DeleteParams::default())
+                        .await?;
+                    debug!(
+                        event = "config_map_deleted",
+                        namespace = %ns,
+                        resource_name = %name,
+                        "Deleted ConfigMap that is no longer configured"
+                    );
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn config(value: Value) -> BTreeMap<String, Value> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_render() {
+        let env = render(&config(json!({
+            "log": { "level": "info", "format": "json" },
+            "maxTuplesPerWrite": 100,
+            "experimentals": ["enable-list-users", "enable-check-optimizations"],
+            "requestTimeout": "3s",
+            "playground.enabled": false,
+            "unset": null,
+        })));
+
+        assert_eq!(
+            env,
+            BTreeMap::from([
+                (
+                    "OPENFGA_EXPERIMENTALS".to_string(),
+                    "enable-list-users,enable-check-optimizations".to_string()
+                ),
+                ("OPENFGA_LOG_FORMAT".to_string(), "json".to_string()),
+                ("OPENFGA_LOG_LEVEL".to_string(), "info".to_string()),
+                (
+                    "OPENFGA_MAX_TUPLES_PER_WRITE".to_string(),
+                    "100".to_string()
+                ),
+                (
+                    "OPENFGA_PLAYGROUND_ENABLED".to_string(),
+                    "false".to_string()
+                ),
+                ("OPENFGA_REQUEST_TIMEOUT".to_string(), "3s".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_config_hash_changes_with_values() {
+        let a = render(&config(json!({ "log": { "level": "info" } })));
+        let b = render(&config(json!({ "log": { "level": "debug" } })));
+        assert_eq!(config_hash(&a), config_hash(&a.clone()));
+        assert_ne!(config_hash(&a), config_hash(&b));
+        assert_eq!(config_hash(&a).len(), 64);
+    }
+
+    #[test]
+    fn test_create_config_map() {
+        let mut openfga = crate::controller::create_test_openfga();
+        assert!(create_config_map(&openfga, "ns", "authz").is_none());
+        assert!(env_from(&openfga, "authz").is_none());
+
+        openfga.spec.config = config(json!({ "log": { "level": "warn" } }));
+        let config_map = create_config_map(&openfga, "ns", "authz").unwrap();
+        assert_eq!(config_map.metadata.name.as_deref(), Some("authz-config"));
+        assert_eq!(config_map.data.unwrap()["OPENFGA_LOG_LEVEL"], "warn");
+        let source = env_from(&openfga, "authz").unwrap();
+        assert_eq!(
+            source.config_map_ref.unwrap().name.as_deref(),
+            Some("authz-config")
+        );
+    }
+}
@@ -0,0 +1,220 @@
+use crate::apply;
+use crate::controller::ControllerResult;
+use crate::ingress::is_owned_by;
+use crate::labels;
+use crate::types::OpenFGA;
+use k8s_openapi::api::core::v1::ServiceAccount;
+use k8s_openapi::api::rbac::v1::{Role, RoleBinding, RoleRef, Subject};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use kube::api::{Api, DeleteParams, Patch};
+use kube::{Client, Resource, ResourceExt};
+use serde::de::DeserializeOwned;
+use std::fmt::Debug;
+use tracing::{debug, info, instrument};
+
+fn metadata(openfga: &OpenFGA, ns: &str, name: &str) -> ObjectMeta {
+    ObjectMeta {
+        name: Some(name.to_string()),
+        namespace: Some(ns.to_string()),
+        labels: Some(labels::instance_labels(openfga, &openfga.name_any())),
+        annotations: labels::instance_annotations(openfga, []),
+        owner_references: openfga.controller_owner_ref(&()).map(|o| vec![o]),
+        ..Default::default()
+    }
+}
+
+/// ServiceAccount the pods run as, or `None` for the namespace default.
+pub fn service_account_name(openfga: &OpenFGA, name: &str) -> Option<String> {
+    let config = &openfga.spec.service_account;
+    match (&config.name, config.create) {
+        (Some(account), _) => Some(account.clone()),
+        (None, true) => Some(name.to_string()),
+        (None, false) => None,
+    }
+}
+
+/// Whether the pods get an API token mounted.
+pub fn automount_token(openfga: &OpenFGA) -> bool {
+    let config = &openfga.spec.service_account;
+    config.automount_token.unwrap_or(!config.rules.is_empty())
+}
+
+pub fn create_service_account(openfga: &OpenFGA, ns: &str, name: &str) -> Option<ServiceAccount> {
+    let config = &openfga.spec.service_account;
+    if !config.create {
+        return None;
+    }
+    let account = service_account_name(openfga, name)?;
+    let mut metadata = metadata(openfga, ns, &account);
+    metadata.annotations = labels::instance_annotations(openfga, config.annotations.clone());
+    Some(ServiceAccount {
+        metadata,
+        automount_service_account_token: Some(automount_token(openfga)),
+        ..Default::default()
+    })
+}
+
+/// Role and RoleBinding granting `rules` to the pods' ServiceAccount, if any rules are set.
+pub fn create_role(openfga: &OpenFGA, ns: &str, name: &str) -> Option<(Role, RoleBinding)> {
+    let config = &openfga.spec.service_account;
+    if config.rules.is_empty() {
+        return None;
+    }
+    let role = Role {
+        metadata: metadata(openfga, ns, name),
+        rules: Some(config.rules.clone()),
+    };
+    let binding = RoleBinding {
+        metadata: metadata(openfga, ns, name),
+        role_ref: RoleRef {
+            api_group: "rbac.authorization.k8s.io".to_string(),
+            kind: "Role".to_string(),
+            name: name.to_string(),
+        },
+        subjects: Some(vec![Subject {
+            kind: "ServiceAccount".to_string(),
+            name: service_account_name(openfga, name).unwrap_or_else(|| "default".to_string()),
+            namespace: Some(ns.to_string()),
+            ..Default::default()
+        }]),
+    };
+    Some((role, binding))
+}
+
+/// Applies the ServiceAccount, Role and RoleBinding and removes the ones no longer configured.
+#[instrument(skip(client, openfga), fields(namespace = %ns, name = %name))]
+pub async fn reconcile_service_account(
+    client: &Client,
+    openfga: &OpenFGA,
+    ns: &str,
+    name: &str,
+) -> ControllerResult<()> {
+    let accounts: Api<ServiceAccount> = Api::namespaced(client.clone(), ns);
+    let roles: Api<Role> = Api::namespaced(client.clone(), ns);
+    let bindings: Api<RoleBinding> = Api::namespaced(client.clone(), ns);
+    let params = apply::apply_params();
+
+    match create_service_account(openfga, ns, name) {
+        Some(account) => {
+            let account_name = account.name_any();
+            accounts
+                .patch(&account_name, &params, &Patch::Apply(&account))
+                .await?;
+            info!(
+                event = "service_account_applied",
+                namespace = %ns,
+                resource_name = %name,
+                service_account = %account_name,
+                "Applied ServiceAccount"
+            );
+        }
+        None => delete_if_owned(&accounts, openfga, name).await?,
+    }
+
+    match create_role(openfga, ns, name) {
+        Some((role, binding)) => {
+            roles.patch(name, &params, &Patch::Apply(&role)).await?;
+            bindings
+                .patch(name, &params, &Patch::Apply(&binding))
+                .await?;
+            info!(
+                event = "role_applied",
+                namespace = %ns,
+                resource_name = %name,
+                rules = openfga.spec.service_account.rules.len(),
+                "Applied Role and RoleBinding"
+            );
+        }
+        None => {
+            delete_if_owned(&bindings, openfga, name).await?;
+            delete_if_owned(&roles, openfga, name).await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn delete_if_owned<K>(api: &Api<K>, openfga: &OpenFGA, name: &str) -> ControllerResult<()>
+where
+    K: Resource + Clone + DeserializeOwned + Debug,
+{
+    if let Some(existing) = api.get_opt(name).await? {
+        if is_owned_by(
+            existing
+                .meta()
+                .owner_references
+                .as_deref()
+                .unwrap_or_default(),
+            openfga,
+        ) {
+            api.delete(name, &DeleteParams::default()).await?;
+            debug!(
+                event = "identity_resource_deleted",
+                resource_name = %name,
+                "Deleted identity resource that is no longer configured"
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::test_openfga;
+    use serde_json::json;
+
+    fn openfga(service_account: serde_json::Value) -> OpenFGA {
+        test_openfga(json!({ "serviceAccount": service_account }))
+    }
+
+    #[test]
+    fn test_default_creates_dedicated_account() {
+        let openfga = openfga(json!({}));
+        assert_eq!(
+            service_account_name(&openfga, "authz").as_deref(),
+            Some("authz")
+        );
+
+        let account = create_service_account(&openfga, "ns", "authz").unwrap();
+        assert_eq!(account.metadata.name.as_deref(), Some("authz"));
+        assert_eq!(account.automount_service_account_token, Some(false));
+        assert!(create_role(&openfga, "ns", "authz").is_none());
+    }
+
+    #[test]
+    fn test_existing_account() {
+        let shared = openfga(json!({ "create": false, "name": "shared" }));
+        assert_eq!(
+            service_account_name(&shared, "authz").as_deref(),
+            Some("shared")
+        );
+        assert!(create_service_account(&shared, "ns", "authz").is_none());
+
+        let default = openfga(json!({ "create": false }));
+        assert!(service_account_name(&default, "authz").is_none());
+    }
+
+    #[test]
+    fn test_annotations_and_rules() {
+        let openfga = openfga(json!({
+            "annotations": { "eks.amazonaws.com/role-arn": "arn:aws:iam::1:role/openfga" },
+            "rules": [{ "apiGroups": [""], "resources": ["configmaps"], "verbs": ["get"] }],
+        }));
+
+        let account = create_service_account(&openfga, "ns", "authz").unwrap();
+        assert!(account
+            .metadata
+            .annotations
+            .unwrap()
+            .contains_key("eks.amazonaws.com/role-arn"));
+        assert_eq!(account.automount_service_account_token, Some(true));
+
+        let (role, binding) = create_role(&openfga, "ns", "authz").unwrap();
+        assert_eq!(role.rules.unwrap()[0].verbs, vec!["get".to_string()]);
+        let subject = &binding.subjects.unwrap()[0];
+        assert_eq!(subject.name, "authz");
+        assert_eq!(subject.namespace.as_deref(), Some("ns"));
+        assert_eq!(binding.role_ref.name, "authz");
+    }
+}
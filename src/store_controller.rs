@@ -0,0 +1,447 @@
+use crate::access_review;
+use crate::access_tokens;
+use crate::controller::{shutdown_requested, ControllerError, ControllerResult, Shutdown};
+use crate::instance_ref;
+use crate::metrics;
+use crate::openfga_client::{OpenFGAClient, StoreWriter};
+use crate::panic_isolation::isolate_panics;
+use crate::retention::run_retention;
+use crate::tuple_scan::StoreReader;
+use crate::types::{
+    AccessReviewPolicy, AccessReviewSummary, OpenFGACondition, OpenFGAStore, OpenFGAStoreStatus,
+    RetentionPolicy, RetentionReport,
+};
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use kube::api::{Api, Patch, PatchParams};
+use kube::runtime::controller::{Action, Controller};
+use kube::runtime::watcher::Config;
+use kube::{Client, ResourceExt};
+use std::sync::Arc;
+use tokio::time::Duration;
+use tracing::{debug, error, info, instrument, warn};
+
+const CONTROLLER_NAME: &str = "openfga-store-controller";
+
+/// Upper bound between reconciles so a lost store or a changed instance is noticed.
+const RESYNC_INTERVAL: Duration = Duration::from_secs(300);
+
+pub struct OpenFGAStoreController {
+    client: Client,
+}
+
+impl OpenFGAStoreController {
+    pub fn new(client: Client) -> Self {
+        Self { client }
+    }
+
+    pub async fn run(self, shutdown: Shutdown) {
+        let stores: Api<OpenFGAStore> = Api::all(self.client.clone());
+
+        info!(
+            controller = CONTROLLER_NAME,
+            "Starting controller with OpenFGAStore resource monitoring"
+        );
+
+        let _watches = metrics::track_watch_streams(CONTROLLER_NAME, 1);
+        Controller::new(stores, Config::default().any_semantic())
+            .graceful_shutdown_on(shutdown_requested(shutdown.clone()))
+            .run(
+                |store, ctx| {
+                    let guarded = isolate_panics(
+                        ctx.client.clone(),
+                        CONTROLLER_NAME,
+                        store.clone(),
+                        reconcile(store.clone(), ctx),
+                    );
+                    metrics::observe_reconcile(CONTROLLER_NAME, store, guarded)
+                },
+                error_policy,
+                Arc::new(self),
+            )
+            .for_each(|res| async move {
+                match res {
+                    Ok(o) => {
+                        debug!(
+                            reconciliation_result = "success",
+                            object = ?o,
+                            "OpenFGAStore reconciliation completed successfully"
+                        );
+                    }
+                    Err(e) => {
+                        error!(
+                            reconciliation_result = "error",
+                            error = %e,
+                            "OpenFGAStore reconciliation failed"
+                        );
+                    }
+                }
+            })
+            .await;
+    }
+}
+
+/// Time until a periodic job last run at `last_run_time` is due; zero when it should
+/// run now.
+fn next_run_in(interval_minutes: u32, last_run_time: Option<&str>, now: DateTime<Utc>) -> Duration {
+    let interval = chrono::Duration::minutes(i64::from(interval_minutes));
+    last_run_time
+        .and_then(|time| DateTime::parse_from_rfc3339(time).ok())
+        .map(|last_run| last_run.with_timezone(&Utc) + interval - now)
+        .and_then(|remaining| remaining.to_std().ok())
+        .unwrap_or(Duration::ZERO)
+}
+
+/// Time until the next retention run is due; zero when it should run now.
+pub fn next_retention_in(
+    policy: &RetentionPolicy,
+    last: Option<&RetentionReport>,
+    now: DateTime<Utc>,
+) -> Duration {
+    next_run_in(
+        policy.interval_minutes,
+        last.map(|report| report.last_run_time.as_str()),
+        now,
+    )
+}
+
+/// Time until the next access review is due; zero when it should run now.
+pub fn next_review_in(
+    policy: &AccessReviewPolicy,
+    last: Option<&AccessReviewSummary>,
+    now: DateTime<Utc>,
+) -> Duration {
+    next_run_in(
+        policy.interval_minutes,
+        last.map(|summary| summary.last_run_time.as_str()),
+        now,
+    )
+}
+
+#[instrument(skip(ctx), fields(namespace = %store.namespace().unwrap_or_default(), name = %store.name_any(), generation = store.metadata.generation.unwrap_or_default()))]
+async fn reconcile(
+    store: Arc<OpenFGAStore>,
+    ctx: Arc<OpenFGAStoreController>,
+) -> ControllerResult<Action> {
+    let ns = store.namespace().unwrap_or_default();
+    let name = store.name_any();
+    let previous = store.status.clone().unwrap_or_default();
+    let mut status = OpenFGAStoreStatus {
+        conditions: None,
+        ..previous.clone()
+    };
+    let condition = |ready: bool, reason: &str, message: &str| {
+        OpenFGACondition::new(
+            "Ready",
+            ready,
+            reason,
+            message,
+            store.metadata.generation,
+            previous.conditions.as_deref(),
+        )
+    };
+
+    info!(
+        event = "store_reconciliation_start",
+        namespace = %ns,
+        resource_name = %name,
+        instance = %instance_ref::describe(&store.spec.instance_ref),
+        "Starting OpenFGAStore reconciliation"
+    );
+
+    let instance =
+        match instance_ref::resolve(&ctx.client, &ns, "OpenFGAStore", &store.spec.instance_ref)
+            .await?
+        {
+            Ok(instance) => instance,
+            Err(message) => {
+                warn!(
+                    event = "store_instance_missing",
+                    namespace = %ns,
+                    resource_name = %name,
+                    instance = %instance_ref::describe(&store.spec.instance_ref),
+                    message = %message,
+                    "Referenced OpenFGA instance does not resolve"
+                );
+                status.conditions = Some(vec![condition(false, "InstanceNotFound", &message)]);
+                patch_status(&ctx.client, &ns, &name, &status).await?;
+                return Ok(Action::requeue(Duration::from_secs(60)));
+            }
+        };
+
+    // Syncing the keys also gives the client the operator's key
+    let keys = access_tokens::sync_keys(&ctx.client, &instance).await?;
+    let client = OpenFGAClient::for_instance(&instance);
+    let store_id = match &previous.store_id {
+        Some(id) => id.clone(),
+        None => {
+            let store_name = store
+                .spec
+                .store_name
+                .clone()
+                .unwrap_or_else(|| name.clone());
+            match client.create_store(&store_name).await {
+                Ok(id) => {
+                    info!(
+                        event = "store_created",
+                        namespace = %ns,
+                        resource_name = %name,
+                        store_id = %id,
+                        "Created OpenFGA store"
+                    );
+                    id
+                }
+                Err(e) => {
+                    warn!(
+                        event = "store_create_failed",
+                        namespace = %ns,
+                        resource_name = %name,
+                        error = %e,
+                        "Failed to create OpenFGA store"
+                    );
+                    status.conditions =
+                        Some(vec![condition(false, "StoreCreateFailed", &e.to_string())]);
+                    patch_status(&ctx.client, &ns, &name, &status).await?;
+                    return Ok(Action::requeue(Duration::from_secs(30)));
+                }
+            }
+        }
+    };
+    status.store_id = Some(store_id.clone());
+    status.conditions = Some(vec![condition(
+        true,
+        "StoreReady",
+        &format!("store {} exists", store_id),
+    )]);
+    let issued = access_tokens::issue_tokens(
+        &ctx.client,
+        &store,
+        &instance,
+        &store_id,
+        keys.as_ref(),
+        previous.access_tokens.as_deref().unwrap_or_default(),
+    )
+    .await?;
+    status.access_tokens = issued.tokens;
+    if !issued.refused.is_empty() {
+        status
+            .conditions
+            .get_or_insert_with(Vec::new)
+            .push(OpenFGACondition::new(
+                "AccessTokensIssued",
+                false,
+                "AccessTokensRefused",
+                &issued.refused.join("; "),
+                store.metadata.generation,
+                previous.conditions.as_deref(),
+            ));
+    }
+
+    let mut requeue = RESYNC_INTERVAL;
+    if let Some(policy) = &store.spec.retention {
+        let now = Utc::now();
+        if next_retention_in(policy, previous.retention.as_ref(), now).is_zero() {
+            let reader = StoreReader {
+                client: &client,
+                store_id: &store_id,
+            };
+            let writer = StoreWriter {
+                client: &client,
+                store_id: &store_id,
+            };
+            let report = match run_retention(&reader, writer, policy, now).await {
+                Ok(outcome) => {
+                    info!(
+                        event = "retention_completed",
+                        namespace = %ns,
+                        resource_name = %name,
+                        dry_run = policy.dry_run,
+                        matched = outcome.matched,
+                        orphaned = outcome.orphaned,
+                        deleted = outcome.deleted,
+                        failed = outcome.failed,
+                        "Retention run completed"
+                    );
+                    RetentionReport {
+                        last_run_time: now.to_rfc3339(),
+                        dry_run: policy.dry_run,
+                        matched_tuples: outcome.matched as i64,
+                        deleted_tuples: outcome.deleted as i64,
+                        orphaned_tuples: outcome.orphaned as i64,
+                        examples: outcome.examples,
+                        message: (outcome.failed > 0)
+                            .then(|| format!("{} deletes failed", outcome.failed)),
+                    }
+                }
+                Err(e) => {
+                    warn!(
+                        event = "retention_failed",
+                        namespace = %ns,
+                        resource_name = %name,
+                        error = %e,
+                        "Retention run failed"
+                    );
+                    RetentionReport {
+                        last_run_time: now.to_rfc3339(),
+                        dry_run: policy.dry_run,
+                        message: Some(e.to_string()),
+                        ..Default::default()
+                    }
+                }
+            };
+            status.retention = Some(report);
+        }
+        requeue = requeue.min(next_retention_in(policy, status.retention.as_ref(), now));
+    }
+
+    if let Some(policy) = &store.spec.access_review {
+        let now = Utc::now();
+        if next_review_in(policy, previous.access_review.as_ref(), now).is_zero() {
+            let reader = StoreReader {
+                client: &client,
+                store_id: &store_id,
+            };
+            let generated_at = now.to_rfc3339();
+            let summary = match access_review::collect_grants(&reader, policy).await {
+                Ok(grants) => {
+                    let (changes, failure) =
+                        access_review::publish_report(&ctx.client, &store, &grants, &generated_at)
+                            .await?;
+                    info!(
+                        event = "access_review_completed",
+                        namespace = %ns,
+                        resource_name = %name,
+                        privileged_grants = grants.len(),
+                        added = changes.added.len(),
+                        removed = changes.removed.len(),
+                        "Access review completed"
+                    );
+                    if let Some(failure) = &failure {
+                        warn!(
+                            event = "access_review_notify_failed",
+                            namespace = %ns,
+                            resource_name = %name,
+                            error = %failure,
+                            "Failed to announce access review changes"
+                        );
+                    }
+                    AccessReviewSummary {
+                        last_run_time: generated_at,
+                        privileged_grants: grants.len() as i64,
+                        added: changes.added.len() as i64,
+                        removed: changes.removed.len() as i64,
+                        report: Some(access_review::report_name(&name)),
+                        message: failure,
+                    }
+                }
+                Err(e) => {
+                    warn!(
+                        event = "access_review_failed",
+                        namespace = %ns,
+                        resource_name = %name,
+                        error = %e,
+                        "Access review failed"
+                    );
+                    AccessReviewSummary {
+                        last_run_time: generated_at,
+                        message: Some(e.to_string()),
+                        ..previous.access_review.clone().unwrap_or_default()
+                    }
+                }
+            };
+            status.access_review = Some(summary);
+        }
+        requeue = requeue.min(next_review_in(policy, status.access_review.as_ref(), now));
+    }
+
+    patch_status(&ctx.client, &ns, &name, &status).await?;
+    Ok(Action::requeue(requeue.max(Duration::from_secs(1))))
+}
+
+async fn patch_status(
+    client: &Client,
+    ns: &str,
+    name: &str,
+    status: &OpenFGAStoreStatus,
+) -> ControllerResult<()> {
+    let stores: Api<OpenFGAStore> = Api::namespaced(client.clone(), ns);
+    stores
+        .patch_status(
+            name,
+            &PatchParams::default(),
+            &Patch::Merge(&serde_json::json!({ "status": status })),
+        )
+        .await?;
+    Ok(())
+}
+
+fn error_policy(
+    store: Arc<OpenFGAStore>,
+    error: &ControllerError,
+    _ctx: Arc<OpenFGAStoreController>,
+) -> Action {
+    warn!(
+        event = "store_reconciliation_error",
+        namespace = %store.namespace().unwrap_or_default(),
+        resource_name = %store.name_any(),
+        error_message = %error,
+        "OpenFGAStore reconciliation failed, retrying"
+    );
+    metrics::record_reconcile_error::<OpenFGAStore>(CONTROLLER_NAME, error.kind());
+    Action::requeue(Duration::from_secs(30))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(interval_minutes: u32) -> RetentionPolicy {
+        RetentionPolicy {
+            rules: vec![],
+            orphans: vec![],
+            dry_run: true,
+            interval_minutes,
+        }
+    }
+
+    #[test]
+    fn test_next_retention_in() {
+        let now = Utc::now();
+        assert!(next_retention_in(&policy(60), None, now).is_zero());
+
+        let report = RetentionReport {
+            last_run_time: (now - chrono::Duration::minutes(20)).to_rfc3339(),
+            ..Default::default()
+        };
+        let remaining = next_retention_in(&policy(60), Some(&report), now);
+        assert!(remaining > Duration::from_secs(39 * 60));
+        assert!(remaining <= Duration::from_secs(40 * 60));
+
+        assert!(next_retention_in(&policy(10), Some(&report), now).is_zero());
+
+        let unparseable = RetentionReport {
+            last_run_time: "yesterday".to_string(),
+            ..Default::default()
+        };
+        assert!(next_retention_in(&policy(60), Some(&unparseable), now).is_zero());
+    }
+
+    #[test]
+    fn test_next_review_in() {
+        let now = Utc::now();
+        let review = AccessReviewPolicy {
+            relations: vec!["admin".to_string()],
+            object_types: vec![],
+            interval_minutes: 1440,
+        };
+        assert!(next_review_in(&review, None, now).is_zero());
+
+        let summary = AccessReviewSummary {
+            last_run_time: (now - chrono::Duration::hours(23)).to_rfc3339(),
+            ..Default::default()
+        };
+        let remaining = next_review_in(&review, Some(&summary), now);
+        assert!(remaining > Duration::from_secs(59 * 60));
+        assert!(remaining <= Duration::from_secs(60 * 60));
+    }
+}
@@ -0,0 +1,381 @@
+//! `spec.targetClusterSecretRef`: runs an instance in another cluster, so a
+//! central operator can administer the authorization infrastructure of a
+//! fleet. The referenced Secret, next to the OpenFGA resource, holds a
+//! kubeconfig for the target cluster; the workload and its children are
+//! applied there, in a namespace of the same name, which must exist.
+//!
+//! The resource, its status, events and the preshared keys stay in the
+//! operator's cluster; the keys Secret is mirrored to the target. Children in
+//! the target cluster cannot carry owner references to a resource that lives
+//! elsewhere, so the operator holds a finalizer and removes them when the
+//! instance is deleted. Until then, children a spec change no longer declares
+//! are left in place. Changes to the children in the target cluster are not
+//! watched; they are corrected on the next reconcile of the instance. Canary
+//! upgrades are rejected by the webhook, and automatic rollbacks are not run,
+//! which the `RollbackPerformed` condition reports.
+//!
+//! The operator still reaches the OpenFGA API at `<name>.<namespace>.svc`, so
+//! stores, models and the reachability check need that name to resolve across
+//! clusters, e.g. through multi-cluster services.
+
+use crate::access_tokens;
+use crate::apply;
+use crate::controller::{ControllerError, ControllerResult};
+use crate::labels::MANAGED_BY;
+use crate::types::OpenFGA;
+use k8s_openapi::api::apps::v1::{Deployment, StatefulSet};
+use k8s_openapi::api::core::v1::{ConfigMap, Secret, Service, ServiceAccount};
+use k8s_openapi::api::networking::v1::Ingress;
+use k8s_openapi::api::rbac::v1::{Role, RoleBinding};
+use kube::api::{Api, DeleteParams, ListParams, Patch};
+use kube::config::{KubeConfigOptions, Kubeconfig};
+use kube::{Client, Config, Resource, ResourceExt};
+use serde::de::DeserializeOwned;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Debug;
+use std::sync::{Mutex, OnceLock};
+use tracing::info;
+
+/// Key of the kubeconfig in the Secret when the reference names none.
+pub const DEFAULT_KEY: &str = "kubeconfig";
+
+/// The target cluster of an instance.
+pub struct TargetCluster {
+    pub client: Client,
+    /// The instance as its children in the target cluster are built from.
+    pub instance: OpenFGA,
+}
+
+/// `openfga` without its UID, so the children built from it carry no owner
+/// reference the target cluster's garbage collector would act on.
+pub fn detached(openfga: &OpenFGA) -> OpenFGA {
+    let mut instance = openfga.clone();
+    instance.metadata.uid = None;
+    instance
+}
+
+/// The kubeconfig under `key` in the Secret `secret`.
+pub fn kubeconfig(secret: &Secret, key: &str) -> Result<Kubeconfig, String> {
+    let name = secret.name_any();
+    let raw = secret
+        .data
+        .as_ref()
+        .and_then(|data| data.get(key))
+        .ok_or_else(|| format!("Secret '{}' has no key '{}'", name, key))?;
+    let yaml = std::str::from_utf8(&raw.0)
+        .map_err(|_| format!("key '{}' of Secret '{}' is not UTF-8", key, name))?;
+    Kubeconfig::from_yaml(yaml).map_err(|e| {
+        format!(
+            "key '{}' of Secret '{}' is not a kubeconfig: {}",
+            key, name, e
+        )
+    })
+}
+
+async fn build_client(kubeconfig: Kubeconfig) -> Result<Client, String> {
+    let config = Config::from_custom_kubeconfig(kubeconfig, &KubeConfigOptions::default())
+        .await
+        .map_err(|e| e.to_string())?;
+    Client::try_from(config).map_err(|e| e.to_string())
+}
+
+/// Clients by Secret, with the resource version they were built from.
+type ClientCache = Mutex<HashMap<String, (Option<String>, Client)>>;
+
+fn clients() -> &'static ClientCache {
+    static CLIENTS: OnceLock<ClientCache> = OnceLock::new();
+    CLIENTS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Drops the client cached for the Secret of a deleted instance.
+pub fn forget(openfga: &OpenFGA) {
+    if let Some(reference) = &openfga.spec.target_cluster_secret_ref {
+        let cache_key = format!(
+            "{}/{}",
+            openfga.namespace().unwrap_or_default(),
+            reference.name
+        );
+        clients()
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&cache_key);
+    }
+}
+
+/// The target cluster of `openfga`; none when it runs in the operator's cluster.
+pub async fn connect(
+    client: &Client,
+    openfga: &OpenFGA,
+) -> ControllerResult<Option<TargetCluster>> {
+    let Some(reference) = &openfga.spec.target_cluster_secret_ref else {
+        return Ok(None);
+    };
+    let ns = openfga.namespace().unwrap_or_default();
+    let secret = Api::<Secret>::namespaced(client.clone(), &ns)
+        .get_opt(&reference.name)
+        .await?
+        .ok_or_else(|| {
+            ControllerError::TargetCluster(format!("Secret '{}' not found", reference.name))
+        })?;
+
+    let cache_key = format!("{}/{}", ns, reference.name);
+    let version = secret.resource_version();
+    let cached = clients()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(&cache_key)
+        .filter(|(built_from, _)| *built_from == version)
+        .map(|(_, client)| client.clone());
+    let remote = match cached {
+        Some(remote) => remote,
+        None => {
+            let kubeconfig = kubeconfig(&secret, reference.key.as_deref().unwrap_or(DEFAULT_KEY))
+                .map_err(ControllerError::TargetCluster)?;
+            let remote = build_client(kubeconfig).await.map_err(|e| {
+                ControllerError::TargetCluster(format!(
+                    "cannot connect with Secret '{}': {}",
+                    reference.name, e
+                ))
+            })?;
+            clients()
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(cache_key, (version, remote.clone()));
+            remote
+        }
+    };
+    Ok(Some(TargetCluster {
+        client: remote,
+        instance: detached(openfga),
+    }))
+}
+
+/// Copies the keys Secret to the target cluster, or removes it there once the
+/// instance has no keys.
+pub async fn mirror_keys(
+    target: &TargetCluster,
+    keys: Option<&BTreeMap<String, String>>,
+) -> ControllerResult<()> {
+    let instance = &target.instance;
+    let secret_name = access_tokens::keys_secret_name(&instance.name_any());
+    let secrets: Api<Secret> = Api::namespaced(
+        target.client.clone(),
+        &instance.namespace().unwrap_or_default(),
+    );
+    match keys {
+        Some(keys) => {
+            let secret = access_tokens::keys_secret(instance, keys, None);
+            secrets
+                .patch(&secret_name, &apply::apply_params(), &Patch::Apply(&secret))
+                .await?;
+        }
+        None => {
+            if secrets.get_opt(&secret_name).await?.is_some() {
+                secrets
+                    .delete(&secret_name, &DeleteParams::default())
+                    .await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Deletes the labelled objects of one kind, except those named `keep`.
+async fn remove_kind<K>(
+    api: Api<K>,
+    kind: &str,
+    selector: &str,
+    keep: Option<&str>,
+) -> ControllerResult<Vec<String>>
+where
+    K: Resource + Clone + DeserializeOwned + Debug,
+{
+    let mut deleted = Vec::new();
+    for object in api.list(&ListParams::default().labels(selector)).await? {
+        let name = object.name_any();
+        if keep == Some(name.as_str()) {
+            continue;
+        }
+        api.delete(&name, &DeleteParams::default()).await?;
+        deleted.push(format!("{}/{}", kind, name));
+    }
+    Ok(deleted)
+}
+
+/// Deletes the children of a deleted instance from its target cluster, what
+/// owner references would have garbage collected in the operator's cluster.
+/// The main Deployment, StatefulSet and Service are left to the deletion policy.
+pub async fn remove_children(cluster: &Client, openfga: &OpenFGA) -> ControllerResult<()> {
+    let ns = openfga.namespace().unwrap_or_default();
+    let name = openfga.name_any();
+    let selector = format!(
+        "app.kubernetes.io/instance={},app.kubernetes.io/managed-by={}",
+        name, MANAGED_BY
+    );
+    let keep = Some(name.as_str());
+    let mut deleted = Vec::new();
+    deleted.extend(
+        remove_kind(
+            Api::<Deployment>::namespaced(cluster.clone(), &ns),
+            "Deployment",
+            &selector,
+            keep,
+        )
+        .await?,
+    );
+    deleted.extend(
+        remove_kind(
+            Api::<StatefulSet>::namespaced(cluster.clone(), &ns),
+            "StatefulSet",
+            &selector,
+            keep,
+        )
+        .await?,
+    );
+    deleted.extend(
+        remove_kind(
+            Api::<Service>::namespaced(cluster.clone(), &ns),
+            "Service",
+            &selector,
+            keep,
+        )
+        .await?,
+    );
+    deleted.extend(
+        remove_kind(
+            Api::<ConfigMap>::namespaced(cluster.clone(), &ns),
+            "ConfigMap",
+            &selector,
+            None,
+        )
+        .await?,
+    );
+    deleted.extend(
+        remove_kind(
+            Api::<Secret>::namespaced(cluster.clone(), &ns),
+            "Secret",
+            &selector,
+            None,
+        )
+        .await?,
+    );
+    deleted.extend(
+        remove_kind(
+            Api::<ServiceAccount>::namespaced(cluster.clone(), &ns),
+            "ServiceAccount",
+            &selector,
+            None,
+        )
+        .await?,
+    );
+    deleted.extend(
+        remove_kind(
+            Api::<Role>::namespaced(cluster.clone(), &ns),
+            "Role",
+            &selector,
+            None,
+        )
+        .await?,
+    );
+    deleted.extend(
+        remove_kind(
+            Api::<RoleBinding>::namespaced(cluster.clone(), &ns),
+            "RoleBinding",
+            &selector,
+            None,
+        )
+        .await?,
+    );
+    deleted.extend(
+        remove_kind(
+            Api::<Ingress>::namespaced(cluster.clone(), &ns),
+            "Ingress",
+            &selector,
+            None,
+        )
+        .await?,
+    );
+    if !deleted.is_empty() {
+        info!(
+            event = "target_cluster_children_removed",
+            namespace = %ns,
+            resource_name = %name,
+            deleted = ?deleted,
+            "Removed the instance's children from its target cluster"
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::ByteString;
+
+    const KUBECONFIG: &str = r#"
+apiVersion: v1
+kind: Config
+clusters:
+- name: edge-eu
+  cluster:
+    server: https://edge-eu.example.com:6443
+contexts:
+- name: operator@edge-eu
+  context:
+    cluster: edge-eu
+    user: operator
+current-context: operator@edge-eu
+users:
+- name: operator
+  user:
+    token: s3cret
+"#;
+
+    fn secret(key: &str, value: &str) -> Secret {
+        Secret {
+            metadata: kube::api::ObjectMeta {
+                name: Some("edge-eu".to_string()),
+                ..Default::default()
+            },
+            data: Some(BTreeMap::from([(
+                key.to_string(),
+                ByteString(value.as_bytes().to_vec()),
+            )])),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_detached() {
+        let mut openfga = crate::controller::create_test_openfga();
+        openfga.metadata.name = Some("authz".to_string());
+        openfga.metadata.namespace = Some("auth".to_string());
+        openfga.metadata.uid = Some("0d6a3c56".to_string());
+        assert!(openfga.controller_owner_ref(&()).is_some());
+
+        let instance = detached(&openfga);
+        assert_eq!(instance.controller_owner_ref(&()), None);
+        assert_eq!(instance.name_any(), "authz");
+        assert_eq!(instance.namespace().as_deref(), Some("auth"));
+    }
+
+    #[tokio::test]
+    async fn test_kubeconfig() {
+        let kubeconfig = kubeconfig(&secret(DEFAULT_KEY, KUBECONFIG), DEFAULT_KEY).unwrap();
+        assert_eq!(
+            kubeconfig.current_context.as_deref(),
+            Some("operator@edge-eu")
+        );
+        assert!(build_client(kubeconfig).await.is_ok());
+
+        assert_eq!(
+            super::kubeconfig(&secret("config", KUBECONFIG), DEFAULT_KEY).unwrap_err(),
+            "Secret 'edge-eu' has no key 'kubeconfig'"
+        );
+        assert!(
+            super::kubeconfig(&secret(DEFAULT_KEY, "- not a kubeconfig"), DEFAULT_KEY)
+                .unwrap_err()
+                .starts_with("key 'kubeconfig' of Secret 'edge-eu' is not a kubeconfig")
+        );
+    }
+}
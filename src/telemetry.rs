@@ -0,0 +1,179 @@
+//! OpenTelemetry tracing for the operator itself. Configured with the standard
+//! OTLP environment variables; without `OTEL_EXPORTER_OTLP_ENDPOINT` nothing is
+//! exported and spans only feed the log output.
+//!
+//! Spans are batched in the background, so [`shutdown`] must run before the
+//! process exits or the last batch is lost.
+
+use opentelemetry::trace::TraceError;
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{self, Sampler, Tracer};
+use opentelemetry_sdk::{runtime, Resource};
+use tracing::Subscriber;
+use tracing_opentelemetry::OpenTelemetryLayer;
+use tracing_subscriber::registry::LookupSpan;
+
+const ENDPOINT_ENV: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+const SAMPLE_RATIO_ENV: &str = "OTEL_TRACES_SAMPLER_ARG";
+const SERVICE_NAME_ENV: &str = "OTEL_SERVICE_NAME";
+const RESOURCE_ATTRIBUTES_ENV: &str = "OTEL_RESOURCE_ATTRIBUTES";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TelemetryConfig {
+    /// OTLP gRPC collector endpoint; tracing is off without one.
+    pub endpoint: Option<String>,
+    /// Fraction of new traces sampled. Spans continuing a remote trace follow
+    /// the caller's decision.
+    pub sample_ratio: f64,
+    /// Resource attributes, `service.name` first.
+    pub resource_attributes: Vec<(String, String)>,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            endpoint: None,
+            sample_ratio: 1.0,
+            resource_attributes: vec![("service.name".to_string(), "openfga-operator".to_string())],
+        }
+    }
+}
+
+impl TelemetryConfig {
+    /// Defaults overridden by `OTEL_EXPORTER_OTLP_ENDPOINT`,
+    /// `OTEL_TRACES_SAMPLER_ARG` (0.0 to 1.0), `OTEL_SERVICE_NAME` and
+    /// `OTEL_RESOURCE_ATTRIBUTES` (`key=value` pairs, comma separated).
+    pub fn from_env() -> Self {
+        Self::from_lookup(|name| std::env::var(name).ok())
+    }
+
+    fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let defaults = Self::default();
+        let service_name = lookup(SERVICE_NAME_ENV)
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+            .unwrap_or_else(|| defaults.resource_attributes[0].1.clone());
+
+        let mut resource_attributes = vec![("service.name".to_string(), service_name)];
+        resource_attributes.extend(
+            lookup(RESOURCE_ATTRIBUTES_ENV)
+                .unwrap_or_default()
+                .split(',')
+                .filter_map(|pair| pair.split_once('='))
+                .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+                .filter(|(key, _)| !key.is_empty() && key != "service.name"),
+        );
+
+        Self {
+            endpoint: lookup(ENDPOINT_ENV)
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty()),
+            sample_ratio: lookup(SAMPLE_RATIO_ENV)
+                .and_then(|v| v.trim().parse::<f64>().ok())
+                .filter(|r| (0.0..=1.0).contains(r))
+                .unwrap_or(defaults.sample_ratio),
+            resource_attributes,
+        }
+    }
+}
+
+/// Installs the OTLP exporter and W3C trace context propagation, returning a
+/// layer that exports the operator's spans. `None` when no endpoint is set.
+/// Must be called from within the Tokio runtime the exporter runs on.
+pub fn init_telemetry<S>(
+    config: &TelemetryConfig,
+) -> Result<Option<OpenTelemetryLayer<S, Tracer>>, TraceError>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    let Some(endpoint) = &config.endpoint else {
+        return Ok(None);
+    };
+    opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+
+    let resource = Resource::new(
+        config
+            .resource_attributes
+            .iter()
+            .map(|(key, value)| KeyValue::new(key.clone(), value.clone())),
+    );
+    let tracer = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(
+            opentelemetry_otlp::new_exporter()
+                .tonic()
+                .with_endpoint(endpoint.clone()),
+        )
+        .with_trace_config(
+            trace::config()
+                .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(
+                    config.sample_ratio,
+                ))))
+                .with_resource(resource),
+        )
+        .install_batch(runtime::Tokio)?;
+    Ok(Some(tracing_opentelemetry::layer().with_tracer(tracer)))
+}
+
+/// Flushes buffered spans and stops the exporter. Blocks until the collector
+/// answered or the export timed out, so it runs off the async workers.
+pub async fn shutdown() {
+    let _ = tokio::task::spawn_blocking(opentelemetry::global::shutdown_tracer_provider).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn config(vars: &[(&str, &str)]) -> TelemetryConfig {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        TelemetryConfig::from_lookup(|name| vars.get(name).cloned())
+    }
+
+    #[test]
+    fn test_defaults_disable_export() {
+        assert_eq!(config(&[]), TelemetryConfig::default());
+        assert_eq!(config(&[(ENDPOINT_ENV, " ")]).endpoint, None);
+    }
+
+    #[test]
+    fn test_from_env() {
+        let config = config(&[
+            (ENDPOINT_ENV, "http://otel-collector.observability:4317"),
+            (SAMPLE_RATIO_ENV, "0.25"),
+            (SERVICE_NAME_ENV, "openfga-operator-eu"),
+            (
+                RESOURCE_ATTRIBUTES_ENV,
+                "deployment.environment=prod, k8s.cluster.name=eu-1,service.name=ignored,bogus",
+            ),
+        ]);
+        assert_eq!(
+            config.endpoint.as_deref(),
+            Some("http://otel-collector.observability:4317")
+        );
+        assert_eq!(config.sample_ratio, 0.25);
+        assert_eq!(
+            config.resource_attributes,
+            vec![
+                (
+                    "service.name".to_string(),
+                    "openfga-operator-eu".to_string()
+                ),
+                ("deployment.environment".to_string(), "prod".to_string()),
+                ("k8s.cluster.name".to_string(), "eu-1".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_sample_ratio_uses_default() {
+        assert_eq!(config(&[(SAMPLE_RATIO_ENV, "1.5")]).sample_ratio, 1.0);
+        assert_eq!(config(&[(SAMPLE_RATIO_ENV, "half")]).sample_ratio, 1.0);
+    }
+}
@@ -0,0 +1,329 @@
+//! Rolls the pods when data they read at startup changes. The Secrets and
+//! ConfigMaps the pod template references, through `env`, `envFrom` or
+//! volumes (the datastore URI Secret, the TLS certificate, user-supplied
+//! sources), are hashed into an annotation on the template, so a rotated
+//! password or certificate restarts the pods like a spec change would.
+//!
+//! The OpenFGA controller watches Secrets and ConfigMaps and re-reconciles the
+//! instances that reference a changed one. References added only through
+//! `overridesFrom` are hashed but not watched, and neither are objects in a
+//! target cluster; changes to those are picked up on the next resync.
+
+use crate::controller::{create_deployment, ControllerResult};
+use crate::types::OpenFGA;
+use k8s_openapi::api::apps::v1::Deployment;
+use k8s_openapi::api::core::v1::{ConfigMap, PodSpec, Secret};
+use kube::api::Api;
+use kube::runtime::reflector::ObjectRef;
+use kube::{Client, ResourceExt};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+/// Pod template annotation carrying the hash of the referenced data.
+pub const TEMPLATE_HASH_ANNOTATION: &str = "openfga.dev/referenced-data-hash";
+
+/// Names of the Secrets and ConfigMaps a pod reads.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct References {
+    pub secrets: BTreeSet<String>,
+    pub config_maps: BTreeSet<String>,
+}
+
+impl References {
+    pub fn is_empty(&self) -> bool {
+        self.secrets.is_empty() && self.config_maps.is_empty()
+    }
+}
+
+/// The Secrets and ConfigMaps `pod` references.
+pub fn references(pod: &PodSpec) -> References {
+    let mut refs = References::default();
+    let containers = pod
+        .containers
+        .iter()
+        .chain(pod.init_containers.iter().flatten());
+    for container in containers {
+        for source in container
+            .env
+            .iter()
+            .flatten()
+            .filter_map(|var| var.value_from.as_ref())
+        {
+            if let Some(name) = source.secret_key_ref.as_ref().and_then(|s| s.name.clone()) {
+                refs.secrets.insert(name);
+            }
+            if let Some(name) = source
+                .config_map_key_ref
+                .as_ref()
+                .and_then(|c| c.name.clone())
+            {
+                refs.config_maps.insert(name);
+            }
+        }
+        for source in container.env_from.iter().flatten() {
+            if let Some(name) = source.secret_ref.as_ref().and_then(|s| s.name.clone()) {
+                refs.secrets.insert(name);
+            }
+            if let Some(name) = source.config_map_ref.as_ref().and_then(|c| c.name.clone()) {
+                refs.config_maps.insert(name);
+            }
+        }
+    }
+    for volume in pod.volumes.iter().flatten() {
+        if let Some(name) = volume.secret.as_ref().and_then(|s| s.secret_name.clone()) {
+            refs.secrets.insert(name);
+        }
+        if let Some(name) = volume.config_map.as_ref().and_then(|c| c.name.clone()) {
+            refs.config_maps.insert(name);
+        }
+        let projections = volume
+            .projected
+            .iter()
+            .flat_map(|p| p.sources.iter().flatten());
+        for projection in projections {
+            if let Some(name) = projection.secret.as_ref().and_then(|s| s.name.clone()) {
+                refs.secrets.insert(name);
+            }
+            if let Some(name) = projection.config_map.as_ref().and_then(|c| c.name.clone()) {
+                refs.config_maps.insert(name);
+            }
+        }
+    }
+    refs
+}
+
+/// Hash over the data of the referenced objects; `None` stands for one that
+/// does not exist (yet).
+pub fn hash(
+    secrets: &[(String, Option<Secret>)],
+    config_maps: &[(String, Option<ConfigMap>)],
+) -> String {
+    let mut hasher = Sha256::new();
+    let mut entry = |kind: &str, name: &str, data: Option<Vec<(&String, &[u8])>>| {
+        hasher.update(kind.as_bytes());
+        hasher.update([0]);
+        hasher.update(name.as_bytes());
+        hasher.update([0]);
+        match data {
+            Some(data) => {
+                for (key, value) in data {
+                    hasher.update(key.as_bytes());
+                    hasher.update([0]);
+                    hasher.update(value);
+                    hasher.update([0]);
+                }
+            }
+            None => hasher.update([1]),
+        }
+    };
+    for (name, secret) in secrets {
+        let data = secret.as_ref().map(|secret| {
+            secret
+                .data
+                .iter()
+                .flatten()
+                .map(|(key, value)| (key, value.0.as_slice()))
+                .collect()
+        });
+        entry("Secret", name, data);
+    }
+    for (name, config_map) in config_maps {
+        let data = config_map.as_ref().map(|config_map| {
+            config_map
+                .data
+                .iter()
+                .flatten()
+                .map(|(key, value)| (key, value.as_bytes()))
+                .chain(
+                    config_map
+                        .binary_data
+                        .iter()
+                        .flatten()
+                        .map(|(key, value)| (key, value.0.as_slice())),
+                )
+                .collect()
+        });
+        entry("ConfigMap", name, data);
+    }
+    hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Hash of the data `deployment`'s pods reference in `ns`; none when they
+/// reference nothing.
+pub async fn compute(
+    client: &Client,
+    ns: &str,
+    deployment: &Deployment,
+) -> ControllerResult<Option<String>> {
+    let Some(pod) = deployment
+        .spec
+        .as_ref()
+        .and_then(|spec| spec.template.spec.as_ref())
+    else {
+        return Ok(None);
+    };
+    let refs = references(pod);
+    if refs.is_empty() {
+        return Ok(None);
+    }
+    let secrets_api: Api<Secret> = Api::namespaced(client.clone(), ns);
+    let mut secrets = Vec::new();
+    for name in refs.secrets {
+        let secret = secrets_api.get_opt(&name).await?;
+        secrets.push((name, secret));
+    }
+    let config_maps_api: Api<ConfigMap> = Api::namespaced(client.clone(), ns);
+    let mut config_maps = Vec::new();
+    for name in refs.config_maps {
+        let config_map = config_maps_api.get_opt(&name).await?;
+        config_maps.push((name, config_map));
+    }
+    Ok(Some(hash(&secrets, &config_maps)))
+}
+
+/// Puts `hash` on the pod template of `deployment`.
+pub fn stamp(deployment: &mut Deployment, hash: String) {
+    if let Some(spec) = deployment.spec.as_mut() {
+        spec.template
+            .metadata
+            .get_or_insert_with(Default::default)
+            .annotations
+            .get_or_insert_with(Default::default)
+            .insert(TEMPLATE_HASH_ANNOTATION.to_string(), hash);
+    }
+}
+
+/// The instances in `namespace` whose pods reference an object `matches` picks.
+pub fn referencing(
+    instances: &[Arc<OpenFGA>],
+    namespace: Option<&str>,
+    matches: impl Fn(&References) -> bool,
+) -> Vec<ObjectRef<OpenFGA>> {
+    instances
+        .iter()
+        .filter(|openfga| openfga.namespace().as_deref() == namespace)
+        .filter(|openfga| {
+            let ns = openfga.namespace().unwrap_or_default();
+            create_deployment(openfga, &ns, &openfga.name_any())
+                .ok()
+                .and_then(|deployment| deployment.spec?.template.spec)
+                .is_some_and(|pod| matches(&references(&pod)))
+        })
+        .map(|openfga| ObjectRef::from_obj(openfga.as_ref()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::test_openfga;
+    use k8s_openapi::ByteString;
+    use std::collections::BTreeMap;
+
+    fn instance(name: &str, ns: &str, spec: serde_json::Value) -> Arc<OpenFGA> {
+        let mut openfga = test_openfga(spec);
+        openfga.metadata.name = Some(name.to_string());
+        openfga.metadata.namespace = Some(ns.to_string());
+        Arc::new(openfga)
+    }
+
+    fn secret(value: &str) -> Secret {
+        Secret {
+            data: Some(BTreeMap::from([(
+                "uri".to_string(),
+                ByteString(value.as_bytes().to_vec()),
+            )])),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_references() {
+        let openfga = instance(
+            "authz",
+            "auth",
+            serde_json::json!({
+                "datastore": {
+                    "engine": "postgres",
+                    "uriSecretRef": { "name": "authz-db", "key": "uri" },
+                },
+                "tls": { "secretName": "authz-tls" },
+                "envFrom": [{ "configMapRef": { "name": "authz-extra" } }],
+                "volumes": [{
+                    "name": "bundle",
+                    "projected": { "sources": [{ "configMap": { "name": "ca-bundle" } }] },
+                }],
+            }),
+        );
+        let deployment = create_deployment(&openfga, "auth", "authz").unwrap();
+        let refs = references(&deployment.spec.unwrap().template.spec.unwrap());
+        assert_eq!(
+            refs.secrets,
+            BTreeSet::from(["authz-db".to_string(), "authz-tls".to_string()])
+        );
+        assert_eq!(
+            refs.config_maps,
+            BTreeSet::from(["authz-extra".to_string(), "ca-bundle".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_hash_follows_data() {
+        let name = "authz-db".to_string();
+        let before = hash(&[(name.clone(), Some(secret("postgres://a")))], &[]);
+        assert_eq!(
+            before,
+            hash(&[(name.clone(), Some(secret("postgres://a")))], &[])
+        );
+        assert_ne!(
+            before,
+            hash(&[(name.clone(), Some(secret("postgres://b")))], &[])
+        );
+        assert_ne!(before, hash(&[(name, None)], &[]));
+    }
+
+    #[test]
+    fn test_stamp() {
+        let openfga = instance(
+            "authz",
+            "auth",
+            serde_json::json!({ "datastore": { "engine": "memory" } }),
+        );
+        let mut deployment = create_deployment(&openfga, "auth", "authz").unwrap();
+        stamp(&mut deployment, "abc".to_string());
+        let annotations = deployment
+            .spec
+            .unwrap()
+            .template
+            .metadata
+            .unwrap()
+            .annotations;
+        assert_eq!(annotations.unwrap()[TEMPLATE_HASH_ANNOTATION], "abc");
+    }
+
+    #[test]
+    fn test_referencing() {
+        let spec = |secret: &str| {
+            serde_json::json!({
+                "datastore": {
+                    "engine": "postgres",
+                    "uriSecretRef": { "name": secret, "key": "uri" },
+                },
+            })
+        };
+        let instances = vec![
+            instance("a", "auth", spec("db-a")),
+            instance("b", "auth", spec("db-b")),
+            instance("c", "other", spec("db-a")),
+        ];
+        let refs = referencing(&instances, Some("auth"), |refs| {
+            refs.secrets.contains("db-a")
+        });
+        assert_eq!(refs, vec![ObjectRef::new("a").within("auth")]);
+    }
+}
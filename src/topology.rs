@@ -0,0 +1,232 @@
+//! Read/write sharding of an instance. With `spec.topology` set, the operator
+//! runs a `<name>-read` and a `<name>-write` Deployment next to the main one,
+//! each with its own replica count and environment and behind a Service of the
+//! same name, so a routing proxy can send checks and writes to separate pods.
+//!
+//! Shard pods keep `app.kubernetes.io/instance` but carry the shard's name in
+//! the `instance` label, so neither the main Deployment nor the main Service
+//! select them. The shards carry the instance's owner reference and are pruned
+//! once `topology` is removed.
+
+use crate::apply;
+use crate::controller::ControllerResult;
+use crate::labels;
+use crate::types::{OpenFGA, ShardConfig};
+use k8s_openapi::api::apps::v1::Deployment;
+use k8s_openapi::api::core::v1::Service;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{LabelSelector, ObjectMeta};
+use kube::api::{Api, Patch};
+use kube::{Client, Resource};
+use std::collections::BTreeMap;
+use tracing::{info, instrument};
+
+/// Label telling read and write shard pods apart.
+pub const ROLE_LABEL: &str = "openfga.dev/role";
+
+pub fn shard_name(name: &str, role: &str) -> String {
+    format!("{}-{}", name, role)
+}
+
+/// The configured shards by role.
+pub fn shards(openfga: &OpenFGA) -> Vec<(&'static str, &ShardConfig)> {
+    match &openfga.spec.topology {
+        Some(topology) => vec![("read", &topology.read), ("write", &topology.write)],
+        None => vec![],
+    }
+}
+
+/// Labels the shard's Deployment and Service select its pods by.
+pub fn shard_selector(name: &str, role: &str) -> BTreeMap<String, String> {
+    let mut selector = labels::selector_labels(name);
+    selector.insert("instance".to_string(), shard_name(name, role));
+    selector.insert(ROLE_LABEL.to_string(), role.to_string());
+    selector
+}
+
+fn shard_metadata(openfga: &OpenFGA, main: &ObjectMeta, name: &str, role: &str) -> ObjectMeta {
+    let mut metadata_labels = main.labels.clone().unwrap_or_default();
+    metadata_labels.extend(shard_selector(name, role));
+    ObjectMeta {
+        name: Some(shard_name(name, role)),
+        namespace: main.namespace.clone(),
+        labels: Some(metadata_labels),
+        annotations: main.annotations.clone(),
+        owner_references: openfga.controller_owner_ref(&()).map(|o| vec![o]),
+        ..Default::default()
+    }
+}
+
+/// The main Deployment with the shard's name, selector, replicas and extra
+/// environment.
+pub fn create_shard_deployment(
+    openfga: &OpenFGA,
+    main: &Deployment,
+    name: &str,
+    role: &str,
+    shard: &ShardConfig,
+) -> Deployment {
+    let mut deployment = main.clone();
+    deployment.metadata = shard_metadata(openfga, &main.metadata, name, role);
+    if let Some(spec) = deployment.spec.as_mut() {
+        spec.replicas = Some(shard.replicas);
+        spec.selector = LabelSelector {
+            match_labels: Some(shard_selector(name, role)),
+            ..Default::default()
+        };
+        let template = spec.template.metadata.get_or_insert_with(Default::default);
+        template
+            .labels
+            .get_or_insert_with(Default::default)
+            .extend(shard_selector(name, role));
+        if let Some(container) = spec
+            .template
+            .spec
+            .as_mut()
+            .and_then(|p| p.containers.first_mut())
+        {
+            let env = container.env.get_or_insert_with(Vec::new);
+            env.retain(|var| !shard.env.iter().any(|own| own.name == var.name));
+            env.extend(shard.env.iter().cloned());
+        }
+    }
+    deployment
+}
+
+/// The main Service with the shard's name and selector.
+pub fn create_shard_service(openfga: &OpenFGA, main: &Service, name: &str, role: &str) -> Service {
+    let mut service = main.clone();
+    service.metadata = shard_metadata(openfga, &main.metadata, name, role);
+    if let Some(spec) = service.spec.as_mut() {
+        spec.selector = Some(shard_selector(name, role));
+    }
+    service
+}
+
+/// Applies the shard Deployments and Services derived from the main ones.
+/// Shards no longer declared are left to [`crate::pruning`].
+#[instrument(skip(client, openfga, deployment, service), fields(namespace = %ns, name = %name))]
+pub async fn reconcile_topology(
+    client: &Client,
+    openfga: &OpenFGA,
+    deployment: &Deployment,
+    service: &Service,
+    ns: &str,
+    name: &str,
+) -> ControllerResult<()> {
+    let deployments: Api<Deployment> = Api::namespaced(client.clone(), ns);
+    let services: Api<Service> = Api::namespaced(client.clone(), ns);
+    for (role, shard) in shards(openfga) {
+        let shard_name = shard_name(name, role);
+        let mut shard_deployment = create_shard_deployment(openfga, deployment, name, role, shard);
+        let live = deployments.get_opt(&shard_name).await?;
+        apply::cede_replicas(&mut shard_deployment, live.as_ref());
+        deployments
+            .patch(
+                &shard_name,
+                &apply::apply_params(),
+                &Patch::Apply(&shard_deployment),
+            )
+            .await?;
+        services
+            .patch(
+                &shard_name,
+                &apply::apply_params(),
+                &Patch::Apply(&create_shard_service(openfga, service, name, role)),
+            )
+            .await?;
+        info!(
+            event = "shard_applied",
+            namespace = %ns,
+            resource_name = %name,
+            role = %role,
+            replicas = shard.replicas,
+            "Applied shard Deployment and Service"
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::{create_deployment, create_service, test_openfga};
+
+    fn openfga() -> OpenFGA {
+        let mut openfga = test_openfga(serde_json::json!({
+            "replicas": 2,
+            "datastore": { "engine": "postgres", "uri": "postgres://openfga@db/openfga" },
+            "env": [{ "name": "OPENFGA_LOG_LEVEL", "value": "info" }],
+            "topology": {
+                "read": {
+                    "replicas": 5,
+                    "env": [{ "name": "OPENFGA_CHECK_QUERY_CACHE_ENABLED", "value": "true" }]
+                },
+                "write": {
+                    "env": [{ "name": "OPENFGA_LOG_LEVEL", "value": "debug" }]
+                }
+            }
+        }));
+        openfga.metadata.name = Some("authz".to_string());
+        openfga
+    }
+
+    #[test]
+    fn test_shard_deployments() {
+        let openfga = openfga();
+        let main = create_deployment(&openfga, "ns", "authz").unwrap();
+        let shards = shards(&openfga);
+        assert_eq!(shards.len(), 2);
+
+        let read = create_shard_deployment(&openfga, &main, "authz", "read", shards[0].1);
+        assert_eq!(read.metadata.name.as_deref(), Some("authz-read"));
+        let spec = read.spec.unwrap();
+        assert_eq!(spec.replicas, Some(5));
+        let selector = spec.selector.match_labels.unwrap();
+        assert_eq!(selector["instance"], "authz-read");
+        assert_eq!(selector["app.kubernetes.io/instance"], "authz");
+        assert_eq!(selector[ROLE_LABEL], "read");
+        let pod_labels = spec.template.metadata.unwrap().labels.unwrap();
+        assert!(selector.iter().all(|(k, v)| pod_labels.get(k) == Some(v)));
+        // The main Deployment does not select shard pods
+        let main_selector = labels::selector_labels("authz");
+        assert!(!main_selector
+            .iter()
+            .all(|(k, v)| pod_labels.get(k) == Some(v)));
+        let env = spec.template.spec.unwrap().containers[0]
+            .env
+            .clone()
+            .unwrap();
+        assert!(env
+            .iter()
+            .any(|var| var.name == "OPENFGA_CHECK_QUERY_CACHE_ENABLED"));
+
+        let write = create_shard_deployment(&openfga, &main, "authz", "write", shards[1].1);
+        let spec = write.spec.unwrap();
+        assert_eq!(spec.replicas, Some(1));
+        let env = spec.template.spec.unwrap().containers[0]
+            .env
+            .clone()
+            .unwrap();
+        let levels: Vec<_> = env
+            .iter()
+            .filter(|var| var.name == "OPENFGA_LOG_LEVEL")
+            .collect();
+        assert_eq!(levels.len(), 1);
+        assert_eq!(levels[0].value.as_deref(), Some("debug"));
+    }
+
+    #[test]
+    fn test_shard_service() {
+        let openfga = openfga();
+        let main = create_service(&openfga, "ns", "authz").unwrap();
+        let service = create_shard_service(&openfga, &main, "authz", "write");
+        assert_eq!(service.metadata.name.as_deref(), Some("authz-write"));
+        assert_eq!(
+            service.metadata.labels.unwrap()["app.kubernetes.io/instance"],
+            "authz"
+        );
+        let spec = service.spec.unwrap();
+        assert_eq!(spec.selector, Some(shard_selector("authz", "write")));
+        assert_eq!(spec.ports, main.spec.unwrap().ports);
+    }
+}
@@ -0,0 +1,216 @@
+use crate::model::AuthorizationModel;
+use crate::openfga_client::{ClientResult, OpenFGAClient, TuplePage};
+use crate::tuples::TupleKey;
+use std::future::Future;
+
+/// Paged access to the tuples of a store.
+pub trait TupleReader {
+    fn read_page(
+        &self,
+        page_size: usize,
+        continuation_token: Option<&str>,
+    ) -> impl Future<Output = ClientResult<TuplePage>>;
+}
+
+/// Reads the tuples of one store through the OpenFGA Read API.
+pub struct StoreReader<'a> {
+    pub client: &'a OpenFGAClient,
+    pub store_id: &'a str,
+}
+
+impl TupleReader for StoreReader<'_> {
+    async fn read_page(
+        &self,
+        page_size: usize,
+        continuation_token: Option<&str>,
+    ) -> ClientResult<TuplePage> {
+        self.client
+            .read_tuples(self.store_id, page_size, continuation_token)
+            .await
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ScanConfig {
+    pub page_size: usize,
+    /// Stores with more tuples than this are sampled instead of scanned in full.
+    pub max_tuples: usize,
+    pub max_examples: usize,
+}
+
+impl Default for ScanConfig {
+    fn default() -> Self {
+        Self {
+            page_size: 100,
+            max_tuples: 10_000,
+            max_examples: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CompatibilityReport {
+    pub scanned: usize,
+    pub incompatible: usize,
+    /// Up to `max_examples` offending tuples with the reason they no longer fit.
+    pub examples: Vec<String>,
+    /// False when the scan stopped at `max_tuples` and only sampled the store.
+    pub complete: bool,
+}
+
+/// Explains why `tuple` could not be written under `model`, or `None` if it still fits.
+pub fn incompatibility(tuple: &TupleKey, model: &AuthorizationModel) -> Option<String> {
+    let object_type = tuple.object.split(':').next().unwrap_or_default();
+    let Some(type_def) = model.type_definition(object_type) else {
+        return Some(format!("type '{}' no longer exists", object_type));
+    };
+    let Some(relation) = type_def.relation(&tuple.relation) else {
+        return Some(format!(
+            "relation '{}#{}' no longer exists",
+            object_type, tuple.relation
+        ));
+    };
+
+    let (subject, user_relation) = match tuple.user.split_once('#') {
+        Some((subject, relation)) => (subject, Some(relation)),
+        None => (tuple.user.as_str(), None),
+    };
+    let (user_type, user_id) = subject.split_once(':').unwrap_or((subject, ""));
+    let wildcard = user_id == "*";
+
+    let allowed = relation.directly_related.iter().any(|r| {
+        r.type_name == user_type && r.wildcard == wildcard && r.relation.as_deref() == user_relation
+    });
+    if allowed {
+        None
+    } else {
+        Some(format!(
+            "'{}' is not assignable to '{}#{}'",
+            tuple.user, object_type, tuple.relation
+        ))
+    }
+}
+
+/// Reads the store page by page and counts tuples the new model would no longer accept.
+pub async fn scan_compatibility(
+    reader: &impl TupleReader,
+    model: &AuthorizationModel,
+    config: &ScanConfig,
+) -> ClientResult<CompatibilityReport> {
+    let mut report = CompatibilityReport::default();
+    let mut token: Option<String> = None;
+
+    loop {
+        let page_size = config
+            .page_size
+            .min(config.max_tuples.saturating_sub(report.scanned))
+            .max(1);
+        let page = reader.read_page(page_size, token.as_deref()).await?;
+
+        for tuple in &page.tuples {
+            report.scanned += 1;
+            if let Some(reason) = incompatibility(tuple, model) {
+                report.incompatible += 1;
+                if report.examples.len() < config.max_examples {
+                    report.examples.push(format!("{}: {}", tuple, reason));
+                }
+            }
+        }
+
+        token = page.continuation_token;
+        if token.is_none() {
+            report.complete = true;
+            return Ok(report);
+        }
+        if report.scanned >= config.max_tuples {
+            return Ok(report);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::parse_dsl;
+
+    const MODEL: &str =
+        "model\n  schema 1.1\ntype user\ntype group\n  relations\n    define member: [user]\n\
+        type doc\n  relations\n    define viewer: [user, user:*, group#member]\n";
+
+    struct PagedReader {
+        tuples: Vec<TupleKey>,
+    }
+
+    impl TupleReader for PagedReader {
+        async fn read_page(
+            &self,
+            page_size: usize,
+            continuation_token: Option<&str>,
+        ) -> ClientResult<TuplePage> {
+            let start: usize = continuation_token.map_or(0, |t| t.parse().unwrap());
+            let end = (start + page_size).min(self.tuples.len());
+            Ok(TuplePage {
+                tuples: self.tuples[start..end].to_vec(),
+                continuation_token: (end < self.tuples.len()).then(|| end.to_string()),
+            })
+        }
+    }
+
+    #[test]
+    fn test_incompatibility() {
+        let model = parse_dsl(MODEL).unwrap();
+        let check = |user: &str, relation: &str, object: &str| {
+            incompatibility(&TupleKey::new(user, relation, object), &model)
+        };
+
+        assert_eq!(check("user:anne", "viewer", "doc:1"), None);
+        assert_eq!(check("user:*", "viewer", "doc:1"), None);
+        assert_eq!(check("group:eng#member", "viewer", "doc:1"), None);
+        assert_eq!(
+            check("user:anne", "viewer", "folder:1"),
+            Some("type 'folder' no longer exists".to_string())
+        );
+        assert_eq!(
+            check("user:anne", "editor", "doc:1"),
+            Some("relation 'doc#editor' no longer exists".to_string())
+        );
+        assert_eq!(
+            check("group:eng", "viewer", "doc:1"),
+            Some("'group:eng' is not assignable to 'doc#viewer'".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_scan_full_and_sampled() {
+        let model = parse_dsl(MODEL).unwrap();
+        let mut tuples: Vec<TupleKey> = (0..25)
+            .map(|i| TupleKey::new(&format!("user:{}", i), "viewer", "doc:1"))
+            .collect();
+        tuples.push(TupleKey::new("user:anne", "editor", "doc:1"));
+        tuples.push(TupleKey::new("user:bob", "owner", "folder:1"));
+        let reader = PagedReader { tuples };
+
+        let config = ScanConfig {
+            page_size: 10,
+            max_tuples: 100,
+            max_examples: 1,
+        };
+        let report = scan_compatibility(&reader, &model, &config).await.unwrap();
+        assert!(report.complete);
+        assert_eq!(report.scanned, 27);
+        assert_eq!(report.incompatible, 2);
+        assert_eq!(
+            report.examples,
+            vec!["doc:1#editor@user:anne: relation 'doc#editor' no longer exists"]
+        );
+
+        let sampled = ScanConfig {
+            max_tuples: 15,
+            ..config
+        };
+        let report = scan_compatibility(&reader, &model, &sampled).await.unwrap();
+        assert!(!report.complete);
+        assert_eq!(report.scanned, 15);
+        assert_eq!(report.incompatible, 0);
+    }
+}
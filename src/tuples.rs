@@ -0,0 +1,55 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A relationship tuple as accepted by the OpenFGA Write API.
+#[derive(
+    Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, JsonSchema,
+)]
+pub struct TupleKey {
+    pub user: String,
+    pub relation: String,
+    pub object: String,
+}
+
+impl TupleKey {
+    pub fn new(user: &str, relation: &str, object: &str) -> Self {
+        Self {
+            user: user.to_string(),
+            relation: relation.to_string(),
+            object: object.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for TupleKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}#{}@{}", self.object, self.relation, self.user)
+    }
+}
+
+/// A single write or delete against a store.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TupleOperation {
+    Write(TupleKey),
+    Delete(TupleKey),
+}
+
+impl TupleOperation {
+    pub fn tuple(&self) -> &TupleKey {
+        match self {
+            TupleOperation::Write(tuple) | TupleOperation::Delete(tuple) => tuple,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tuple_key_display() {
+        let tuple = TupleKey::new("user:anne", "viewer", "document:roadmap");
+        assert_eq!(tuple.to_string(), "document:roadmap#viewer@user:anne");
+    }
+}
@@ -1,6 +1,16 @@
-use kube::CustomResource;
+use crate::model::LintSeverity;
+use crate::tuples::TupleKey;
+use k8s_openapi::api::core::v1::{
+    EnvFromSource, EnvVar, LocalObjectReference, PodSecurityContext, SecurityContext, Volume,
+    VolumeMount,
+};
+use k8s_openapi::api::rbac::v1::PolicyRule;
+use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinition;
+use k8s_openapi::apimachinery::pkg::util::intstr::IntOrString;
+use kube::{CustomResource, CustomResourceExt};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 #[derive(CustomResource, Serialize, Deserialize, Debug, Clone, JsonSchema)]
 #[kube(
@@ -10,6 +20,11 @@ use serde::{Deserialize, Serialize};
     plural = "openfgas",
     shortname = "ofga",
     status = "OpenFGAStatus",
+    scale = r#"{"specReplicasPath":".spec.replicas","statusReplicasPath":".status.replicas","labelSelectorPath":".status.selector"}"#,
+    printcolumn = r#"{"name":"Ready","type":"string","jsonPath":".status.conditions[?(@.type==\"Ready\")].status"}"#,
+    printcolumn = r#"{"name":"Reachable","type":"string","jsonPath":".status.conditions[?(@.type==\"ServerReachable\")].status"}"#,
+    printcolumn = r#"{"name":"Replicas","type":"integer","jsonPath":".status.readyReplicas"}"#,
+    printcolumn = r#"{"name":"Age","type":"date","jsonPath":".metadata.creationTimestamp"}"#,
     namespaced
 )]
 #[serde(rename_all = "camelCase")]
@@ -20,94 +35,1741 @@ pub struct OpenFGASpec {
     #[serde(default = "default_image")]
     pub image: String,
 
+    /// Resolve the tag of `image` to its sha256 digest through the registry API
+    /// and run the pods by digest. The pinned reference is recorded in
+    /// `status.resolvedImage`.
+    #[serde(default)]
+    pub resolve_image_digest: bool,
+
+    /// Cosign signature the image must carry. Verified images are deployed by
+    /// digest; an image that fails verification is not deployed.
+    pub image_verification: Option<ImageVerification>,
+
+    /// Secrets with registry credentials for pulling the image. The operator
+    /// also uses them to resolve and verify the image.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub image_pull_secrets: Vec<LocalObjectReference>,
+
+    /// When the kubelet pulls the image; the Kubernetes default applies when unset.
+    pub image_pull_policy: Option<ImagePullPolicy>,
+
     pub datastore: DatastoreConfig,
 
     #[serde(default)]
-    pub playground: PlaygroundConfig,
+    pub playground: PlaygroundConfig,
+
+    #[serde(default)]
+    pub grpc: GrpcConfig,
+
+    #[serde(default)]
+    pub http: HttpConfig,
+
+    /// Protocol the operator uses for tuple reads, writes and checks, e.g. when
+    /// restoring backups. Stores and models are always managed over HTTP.
+    #[serde(default)]
+    pub api_protocol: ApiProtocol,
+
+    /// Extra environment variables for the OpenFGA container. Variables with the
+    /// same name as an operator-generated one override it.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub env: Vec<EnvVar>,
+
+    /// ConfigMap/Secret sources to populate the OpenFGA container environment from.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub env_from: Vec<EnvFromSource>,
+
+    /// OpenFGA server settings, keyed like OpenFGA's config file (nested or
+    /// dotted keys). Rendered into a ConfigMap of `OPENFGA_*` variables; changes
+    /// roll the pods.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    #[schemars(schema_with = "preserve_unknown_fields")]
+    pub config: BTreeMap<String, serde_json::Value>,
+
+    /// Extra pod volumes, e.g. a CA bundle ConfigMap or an audit log claim.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub volumes: Vec<Volume>,
+
+    /// Mounts into the OpenFGA container for `volumes` (or the cache volume).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub volume_mounts: Vec<VolumeMount>,
+
+    pub resources: Option<ResourceSpec>,
+
+    #[serde(default)]
+    pub probes: ProbesConfig,
+
+    /// Serves the gRPC API over TLS.
+    pub tls: Option<TlsConfig>,
+
+    /// External routing to the HTTP API and, when enabled, the playground.
+    pub ingress: Option<IngressConfig>,
+
+    #[serde(default)]
+    pub service: ServiceConfig,
+
+    /// Scratch volume for OpenFGA's local caches and temporary files.
+    pub cache_volume: Option<CacheVolumeConfig>,
+
+    #[serde(default)]
+    pub observability: ObservabilityConfig,
+
+    /// OpenFGA server logging. Takes precedence over `log.*` in `config`.
+    pub logging: Option<LoggingConfig>,
+
+    #[serde(default)]
+    pub deletion_policy: DeletionPolicy,
+
+    /// Stores holding more tuples than this in total are only deleted under the
+    /// `Delete` policy once the `openfga.dev/confirm-delete` annotation is set to
+    /// the instance name.
+    #[serde(default = "default_deletion_confirmation_threshold")]
+    pub deletion_confirmation_threshold: u64,
+
+    /// Pod-level security settings. Fields set here override the hardened
+    /// defaults (non-root uid 65532, RuntimeDefault seccomp profile).
+    pub pod_security_context: Option<PodSecurityContext>,
+
+    /// Security settings for the OpenFGA container. Fields set here override the
+    /// hardened defaults (read-only root filesystem, no privilege escalation,
+    /// all capabilities dropped).
+    pub security_context: Option<SecurityContext>,
+
+    #[serde(default)]
+    pub service_account: ServiceAccountConfig,
+
+    /// Objects that must exist (and report a True `Ready` condition, if they
+    /// report conditions) before the instance is provisioned.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<DependencyRef>,
+
+    /// Resources created through the OpenFGA API once the server is reachable.
+    #[serde(default)]
+    pub bootstrap: BootstrapConfig,
+
+    /// Labels added to every resource the operator creates for the instance,
+    /// including its pods. The operator's own selector labels take precedence.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
+
+    /// Annotations added to every resource the operator creates for the instance,
+    /// including its pods. Resource-specific annotations take precedence.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub annotations: BTreeMap<String, String>,
+
+    /// Kind of workload the pods run in. `StatefulSet` is only accepted for a
+    /// single replica of the memory engine, which has no shared datastore.
+    #[serde(default)]
+    pub workload_type: WorkloadType,
+
+    /// Volume claim template for the `StatefulSet` workload.
+    pub persistence: Option<PersistenceConfig>,
+
+    /// How image changes roll out to a Deployment workload.
+    pub upgrade_strategy: Option<UpgradeStrategy>,
+
+    /// How pods shut down during rollouts and scale-downs.
+    pub termination: Option<TerminationConfig>,
+
+    /// Seconds a new pod must stay ready before it counts as available. `Ready`
+    /// is only reported once every replica is available, so pods flapping
+    /// between ready and not ready do not flap the instance.
+    pub min_ready_seconds: Option<i32>,
+
+    /// PriorityClass of the pods, e.g. to protect them from eviction.
+    pub priority_class_name: Option<String>,
+
+    /// RuntimeClass of the pods, e.g. to run them under gVisor or Kata Containers.
+    pub runtime_class_name: Option<String>,
+
+    /// Separate read- and write-optimized Deployments, each behind its own
+    /// Service, for routing proxies in front of OpenFGA.
+    pub topology: Option<TopologyConfig>,
+
+    /// Strategic-merge patches layered over the generated workload and
+    /// Service, for settings the CRD has no field for.
+    pub overrides_from: Option<OverridesSource>,
+
+    /// Runs the instance in the cluster of the kubeconfig in this Secret
+    /// instead of the operator's own.
+    pub target_cluster_secret_ref: Option<TargetClusterSecretRef>,
+
+    /// OpenFGA's check query cache, and optionally a cache server next to it
+    /// for latency-sensitive clients.
+    pub query_cache: Option<QueryCacheConfig>,
+
+    /// OpenFGA experimental features to enable, passed as `--experimentals`,
+    /// e.g. `enable-list-objects-optimizations`. Only flags the webhook knows
+    /// to be safe are accepted.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub feature_flags: Vec<String>,
+}
+
+/// Caching of check results. OpenFGA keeps the cache in each server's memory;
+/// the sidecar is a separate Redis or memcached that clients of the instance
+/// can cache their own results in.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryCacheConfig {
+    #[serde(default = "default_query_cache_enabled")]
+    pub enabled: bool,
+
+    /// How long a check result stays cached, as a Go duration such as `10s`.
+    pub ttl: Option<String>,
+
+    /// Maximum number of check results each server caches.
+    pub limit: Option<u32>,
+
+    pub sidecar: Option<CacheSidecar>,
+}
+
+/// Cache server running in every OpenFGA pod, exposed on the instance's
+/// Service as the `cache` port.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheSidecar {
+    pub engine: CacheEngine,
+
+    /// Cache image; defaults to the engine's Alpine image.
+    pub image: Option<String>,
+
+    /// Defaults to 6379 for Redis and 11211 for memcached.
+    pub port: Option<i32>,
+
+    /// Memory the cache may hold items in, in MiB; evicts least recently used
+    /// items beyond it.
+    pub max_memory_mi: Option<u32>,
+
+    pub resources: Option<ResourceSpec>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, JsonSchema)]
+pub enum CacheEngine {
+    Redis,
+    Memcached,
+}
+
+/// Secret next to the instance holding a kubeconfig for its target cluster.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TargetClusterSecretRef {
+    pub name: String,
+    /// Key of the kubeconfig, `kubeconfig` when unset.
+    pub key: Option<String>,
+}
+
+/// ConfigMap holding YAML patches under `deployment` (also applied to a
+/// StatefulSet workload) and `service`. Edits are picked up on the next reconcile.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct OverridesSource {
+    pub config_map_ref: ConfigMapReference,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigMapReference {
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TopologyConfig {
+    /// The `<name>-read` Deployment and Service.
+    pub read: ShardConfig,
+    /// The `<name>-write` Deployment and Service.
+    pub write: ShardConfig,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ShardConfig {
+    #[serde(default = "default_replicas")]
+    pub replicas: i32,
+
+    /// Environment variables for the shard's OpenFGA container, e.g.
+    /// `OPENFGA_CHECK_QUERY_CACHE_ENABLED` for reads. They override variables
+    /// of the same name from the instance.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub env: Vec<EnvVar>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TerminationConfig {
+    /// Seconds between SIGTERM and the pod being killed. Defaults to the
+    /// Kubernetes default of 30, plus `preStopSleepSeconds` when that is set.
+    pub grace_period_seconds: Option<i64>,
+
+    /// Seconds a preStop hook waits before OpenFGA receives SIGTERM, so
+    /// endpoints and load balancers stop sending checks to the pod first. The
+    /// hook runs `sleep` in the OpenFGA container, which the distroless upstream
+    /// image lacks, so it is opt-in.
+    pub pre_stop_sleep_seconds: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UpgradeStrategy {
+    /// Pods that may be unavailable during a rolling update, e.g. `0` or `25%`.
+    pub max_unavailable: Option<IntOrString>,
+
+    /// Pods that may be created above `replicas` during a rolling update.
+    pub max_surge: Option<IntOrString>,
+
+    /// Runs a new image in a separate canary Deployment first and only rolls it
+    /// out once the canary passes an OpenFGA health check after the pause.
+    pub canary: Option<CanaryConfig>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CanaryConfig {
+    #[serde(default = "default_canary_replicas")]
+    pub replicas: i32,
+
+    /// How long the canary runs before it is verified and promoted.
+    #[serde(default = "default_canary_pause_seconds")]
+    pub pause_seconds: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, JsonSchema)]
+pub enum WorkloadType {
+    #[default]
+    Deployment,
+    /// Gives the single pod a stable identity and, with `persistence`, a volume
+    /// that survives restarts.
+    StatefulSet,
+}
+
+/// A PersistentVolumeClaim per StatefulSet pod, mounted into the OpenFGA container.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PersistenceConfig {
+    /// Requested size of the claim, e.g. `1Gi`.
+    #[serde(default = "default_persistence_size")]
+    pub size: String,
+
+    /// Storage class of the claim; the cluster default when unset.
+    pub storage_class_name: Option<String>,
+
+    #[serde(default = "default_persistence_mount_path")]
+    pub mount_path: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BootstrapConfig {
+    /// Names of stores that must exist on the instance; missing ones are created.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stores: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DependencyRef {
+    /// e.g. `postgresql.cnpg.io/v1`, or `v1` for core kinds.
+    pub api_version: String,
+    pub kind: String,
+    pub name: String,
+    /// Defaults to the instance namespace; ignored for cluster-scoped kinds.
+    pub namespace: Option<String>,
+    /// Condition that must be True, `Ready` by default.
+    pub condition: Option<String>,
+}
+
+/// Identity the OpenFGA pods run as.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceAccountConfig {
+    /// Create a dedicated ServiceAccount. When false, `name` must refer to an
+    /// existing one, or the namespace `default` account is used.
+    #[serde(default = "default_service_account_create")]
+    pub create: bool,
+
+    /// Defaults to the instance name for a created ServiceAccount.
+    pub name: Option<String>,
+
+    /// Annotations on the created ServiceAccount, e.g.
+    /// `eks.amazonaws.com/role-arn` or `iam.gke.io/gcp-service-account`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub annotations: BTreeMap<String, String>,
+
+    /// Mount the API token into the pods. Defaults to true only when `rules` are set.
+    pub automount_token: Option<bool>,
+
+    /// Namespaced permissions granted to the ServiceAccount through a Role and RoleBinding.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<PolicyRule>,
+}
+
+impl Default for ServiceAccountConfig {
+    fn default() -> Self {
+        Self {
+            create: default_service_account_create(),
+            name: None,
+            annotations: BTreeMap::new(),
+            automount_token: None,
+            rules: Vec::new(),
+        }
+    }
+}
+
+/// How the operator reaches an instance's API.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, JsonSchema)]
+pub enum ApiProtocol {
+    /// The HTTP API on `http.port`.
+    #[default]
+    Http,
+    /// The gRPC API on `grpc.port`, over TLS when `tls` is set; much faster for
+    /// large tuple writes. The operator must trust the certificate through the
+    /// system roots or `OPENFGA_CLIENT_GRPC_CA_FILE`.
+    Grpc,
+}
+
+/// What happens to an instance's workload and data when the OpenFGA resource is
+/// deleted, including through deletion of its namespace.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, JsonSchema)]
+pub enum DeletionPolicy {
+    /// Leave the Deployment, Service and datastore contents in place.
+    #[default]
+    Retain,
+    /// Remove the Deployment and Service but keep the stores in the datastore.
+    RetainData,
+    /// Delete every store through the OpenFGA API, then the workload.
+    Delete,
+}
+
+/// Log level and format of the OpenFGA server, set through `OPENFGA_LOG_LEVEL`
+/// and `OPENFGA_LOG_FORMAT`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LoggingConfig {
+    pub level: Option<LogLevel>,
+    pub format: Option<LogFormat>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    None,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Panic,
+    Fatal,
+}
+
+impl LogLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LogLevel::None => "none",
+            LogLevel::Debug => "debug",
+            LogLevel::Info => "info",
+            LogLevel::Warn => "warn",
+            LogLevel::Error => "error",
+            LogLevel::Panic => "panic",
+            LogLevel::Fatal => "fatal",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, JsonSchema)]
+pub enum ImagePullPolicy {
+    Always,
+    IfNotPresent,
+    Never,
+}
+
+impl ImagePullPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImagePullPolicy::Always => "Always",
+            ImagePullPolicy::IfNotPresent => "IfNotPresent",
+            ImagePullPolicy::Never => "Never",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+impl LogFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LogFormat::Text => "text",
+            LogFormat::Json => "json",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ObservabilityConfig {
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+
+    #[serde(default)]
+    pub tracing: TracingConfig,
+}
+
+/// OpenTelemetry traces exported by the OpenFGA server over OTLP gRPC.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TracingConfig {
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Collector address, e.g. `otel-collector.observability:4317`. OpenFGA
+    /// defaults to `0.0.0.0:4317`.
+    pub otlp_endpoint: Option<String>,
+
+    /// Share of requests traced, from 0 to 1.
+    pub sample_ratio: Option<f64>,
+
+    /// Connect to the collector over TLS.
+    #[serde(default)]
+    pub tls: bool,
+
+    /// Headers sent with every export, e.g. a tenant id.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+
+    /// Secret key holding headers as `key=value` pairs separated by commas, for
+    /// credentials. Takes precedence over `headers`.
+    pub headers_secret_ref: Option<SecretKeyReference>,
+}
+
+/// OpenFGA's Prometheus metrics endpoint and how Prometheus discovers it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MetricsConfig {
+    #[serde(default)]
+    pub enabled: bool,
+
+    #[serde(default = "default_metrics_port")]
+    pub port: i32,
+
+    #[serde(default)]
+    pub monitor: MonitorConfig,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: default_metrics_port(),
+            monitor: MonitorConfig::default(),
+        }
+    }
+}
+
+/// Prometheus Operator scrape configuration generated while metrics are enabled.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MonitorConfig {
+    #[serde(default)]
+    pub kind: MonitorKind,
+
+    /// Scrape interval, e.g. `30s`. Defaults to the Prometheus global interval.
+    pub interval: Option<String>,
+
+    pub scrape_timeout: Option<String>,
+
+    /// Extra labels on the monitor, typically what the Prometheus
+    /// `serviceMonitorSelector` / `podMonitorSelector` matches on.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, JsonSchema)]
+pub enum MonitorKind {
+    #[default]
+    ServiceMonitor,
+    PodMonitor,
+    /// Expose metrics without creating a monitor resource; pods get
+    /// `prometheus.io/*` annotations for annotation-based discovery instead.
+    None,
+}
+
+/// How the instance's Service exposes OpenFGA.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceConfig {
+    #[serde(default, rename = "type")]
+    pub type_: ServiceType,
+
+    /// Annotations on the Service, typically cloud load balancer settings.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub annotations: BTreeMap<String, String>,
+
+    /// Only applies to NodePort and LoadBalancer Services.
+    pub external_traffic_policy: Option<ExternalTrafficPolicy>,
+
+    /// Fixed node ports; unset ports are allocated by the cluster.
+    #[serde(default)]
+    pub node_ports: NodePorts,
+
+    pub load_balancer_class: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub load_balancer_source_ranges: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, JsonSchema)]
+pub enum ServiceType {
+    #[default]
+    ClusterIP,
+    NodePort,
+    LoadBalancer,
+    /// A ClusterIP Service without a cluster IP, resolving to the pod addresses.
+    Headless,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, JsonSchema)]
+pub enum ExternalTrafficPolicy {
+    Cluster,
+    Local,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NodePorts {
+    pub grpc: Option<i32>,
+    pub http: Option<i32>,
+    pub playground: Option<i32>,
+    pub metrics: Option<i32>,
+}
+
+/// An `emptyDir` mounted into the OpenFGA container. Writes there count against
+/// the pod's ephemeral-storage (disk) or memory limit (memory-backed), rather
+/// than the node's root filesystem.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheVolumeConfig {
+    #[serde(default)]
+    pub medium: CacheVolumeMedium,
+
+    /// Maximum size of the volume, e.g. `1Gi`. Exceeding it evicts the pod.
+    pub size_limit: Option<String>,
+
+    #[serde(default = "default_cache_mount_path")]
+    pub mount_path: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, JsonSchema)]
+pub enum CacheVolumeMedium {
+    /// Node disk, accounted as ephemeral storage.
+    #[default]
+    Disk,
+    /// tmpfs, accounted against the container's memory limit.
+    Memory,
+}
+
+/// Routing resource created for an instance: a `networking.k8s.io` Ingress or a
+/// Gateway API HTTPRoute.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct IngressConfig {
+    pub host: String,
+
+    #[serde(default)]
+    pub kind: IngressKind,
+
+    /// Secret with the TLS certificate for `host`. Ingress only; Gateway API
+    /// terminates TLS on the Gateway.
+    pub tls_secret_name: Option<String>,
+
+    /// IngressClass to use. Ingress only.
+    pub class_name: Option<String>,
+
+    /// Gateway the HTTPRoute attaches to. Required for `HTTPRoute`.
+    pub gateway_ref: Option<GatewayReference>,
+
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub annotations: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, JsonSchema)]
+pub enum IngressKind {
+    #[default]
+    Ingress,
+    #[serde(rename = "HTTPRoute")]
+    HttpRoute,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GatewayReference {
+    pub name: String,
+    /// Defaults to the instance namespace.
+    pub namespace: Option<String>,
+    /// Listener on the Gateway to attach to.
+    pub section_name: Option<String>,
+}
+
+/// CPU, memory and ephemeral-storage requests/limits for the OpenFGA container.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceSpec {
+    pub requests: Option<ResourceQuantities>,
+    pub limits: Option<ResourceQuantities>,
+}
+
+/// Certificate for the gRPC API. Kubernetes gRPC probes cannot use TLS, so
+/// readiness is checked through the HTTP `/healthz` endpoint instead.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TlsConfig {
+    /// `kubernetes.io/tls` Secret with `tls.crt` and `tls.key`.
+    pub secret_name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceQuantities {
+    pub cpu: Option<String>,
+    pub memory: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ephemeral_storage: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DatastoreConfig {
+    #[serde(default = "default_engine")]
+    pub engine: String,
+
+    pub uri: Option<String>,
+
+    /// Reference to a Secret key holding the datastore URI. Takes precedence over `uri`.
+    pub uri_secret_ref: Option<SecretKeyReference>,
+
+    /// Settings for the `postgres` engine.
+    pub postgres: Option<PostgresConfig>,
+
+    /// Settings for the `mysql` engine.
+    pub mysql: Option<MySQLConfig>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PostgresConfig {
+    /// Upper bound of open connections to the database.
+    pub max_open_conns: Option<i32>,
+
+    /// Connections kept open while idle.
+    pub max_idle_conns: Option<i32>,
+
+    /// libpq `sslmode`, e.g. `require` or `verify-full`. An `sslmode` in the URI
+    /// takes precedence.
+    pub sslmode: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MySQLConfig {
+    /// Upper bound of open connections to the database.
+    pub max_open_conns: Option<i32>,
+
+    /// Connections kept open while idle.
+    pub max_idle_conns: Option<i32>,
+
+    /// Driver `tls` parameter (`true`, `false`, `skip-verify` or `preferred`),
+    /// added to an inline `uri`.
+    pub tls: Option<String>,
+}
+
+/// Cosign signature verification of `spec.image`. Exactly one of `publicKey`,
+/// `publicKeySecretRef` and `keyless` is set.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageVerification {
+    /// PEM ECDSA public key the image is signed with (`cosign.pub`).
+    pub public_key: Option<String>,
+    /// Secret key holding the PEM public key.
+    pub public_key_secret_ref: Option<SecretKeyReference>,
+    /// Keyless signature by a Fulcio certificate.
+    pub keyless: Option<KeylessVerification>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct KeylessVerification {
+    /// OIDC issuer that authenticated the signer, e.g.
+    /// `https://token.actions.githubusercontent.com`.
+    pub issuer: String,
+    /// Signer identity: the certificate's email or URI subject alternative name.
+    pub subject: String,
+    /// PEM root certificates of the Fulcio instance that issues signing certificates.
+    pub trusted_roots: String,
+    /// PEM public key of the Rekor log whose bundles date the signatures.
+    /// Required: without it keyless signatures are rejected.
+    pub rekor_public_key: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SecretKeyReference {
+    pub name: String,
+    pub key: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaygroundConfig {
+    #[serde(default)]
+    pub enabled: bool,
+
+    #[serde(default = "default_playground_port")]
+    pub port: i32,
+
+    /// Puts an authenticating proxy in front of the playground and exposes it
+    /// through a separate `<name>-playground` Service instead of the main one.
+    pub auth_proxy: Option<PlaygroundAuthProxy>,
+}
+
+impl Default for PlaygroundConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: default_playground_port(),
+            auth_proxy: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaygroundAuthProxy {
+    #[serde(rename = "type")]
+    pub type_: AuthProxyType,
+
+    /// Proxy image; defaults to oauth2-proxy or nginx depending on `type`.
+    pub image: Option<String>,
+
+    #[serde(default = "default_auth_proxy_port")]
+    pub port: i32,
+
+    /// Secret with the proxy's credentials: `OAUTH2_PROXY_*` variables for
+    /// `OAuth2Proxy`, an htpasswd file under the `auth` key for `BasicAuth`.
+    pub secret_name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, JsonSchema)]
+pub enum AuthProxyType {
+    OAuth2Proxy,
+    BasicAuth,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GrpcConfig {
+    #[serde(default = "default_grpc_port")]
+    pub port: i32,
+}
+
+impl Default for GrpcConfig {
+    fn default() -> Self {
+        Self {
+            port: default_grpc_port(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct HttpConfig {
+    #[serde(default = "default_http_port")]
+    pub port: i32,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            port: default_http_port(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenFGACondition {
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub status: String,
+    #[serde(rename = "lastTransitionTime")]
+    pub last_transition_time: Option<String>,
+    pub reason: Option<String>,
+    pub message: Option<String>,
+    /// Generation of the resource spec the condition was computed from.
+    pub observed_generation: Option<i64>,
+}
+
+impl OpenFGACondition {
+    /// Builds a condition, keeping the previous transition time when the status did not change.
+    pub fn new(
+        type_: &str,
+        status: bool,
+        reason: &str,
+        message: &str,
+        observed_generation: Option<i64>,
+        previous: Option<&[OpenFGACondition]>,
+    ) -> Self {
+        let status = if status { "True" } else { "False" }.to_string();
+        let last_transition_time = previous
+            .and_then(|conditions| conditions.iter().find(|c| c.type_ == type_))
+            .filter(|c| c.status == status)
+            .and_then(|c| c.last_transition_time.clone())
+            .unwrap_or_else(|| chrono::Utc::now().to_rfc3339());
+
+        Self {
+            type_: type_.to_string(),
+            status,
+            last_transition_time: Some(last_transition_time),
+            reason: Some(reason.to_string()),
+            message: Some(message.to_string()),
+            observed_generation,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenFGAStatus {
+    /// Generation of the spec the operator last reconciled.
+    pub observed_generation: Option<i64>,
+    pub replicas: Option<i32>,
+    pub ready_replicas: Option<i32>,
+    /// Label selector of the instance's pods, for the scale subresource.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub selector: Option<String>,
+    pub conditions: Option<Vec<OpenFGACondition>>,
+    /// Distinct topology zones the instance's pods are currently scheduled in.
+    pub zones: Option<Vec<String>>,
+    /// Most recent condition transitions, oldest first.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub history: Option<Vec<OpenFGAHistoryEntry>>,
+    /// Stores ensured from `spec.bootstrap.stores`, in spec order.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stores: Option<Vec<BootstrappedStore>>,
+    /// The canary upgrade in progress or last finished.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upgrade: Option<UpgradeStatus>,
+    /// `spec.image` pinned to the digest its tag resolved to, when
+    /// `resolveImageDigest` or `imageVerification` is set.
+    pub resolved_image: Option<String>,
+    /// Changes the operator would apply, and events it would publish, when it runs in dry-run mode.
+    pub pending_changes: Option<Vec<PendingChange>>,
+    /// Observed usage of the OpenFGA containers, to size `spec.resources` by.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resource_recommendations: Option<ResourceRecommendations>,
+}
+
+/// The 95th percentile of the CPU and memory the OpenFGA containers used, read
+/// from the metrics API since `windowStart`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceRecommendations {
+    pub container: String,
+    /// e.g. `180m`.
+    pub cpu: String,
+    /// e.g. `96Mi`.
+    pub memory: String,
+    /// Pod readings the percentiles are taken over.
+    pub samples: u32,
+    pub window_start: String,
+    pub observed_time: String,
+}
+
+/// A change to a child object, or an event, that a dry-run reconciliation did not make.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingChange {
+    pub kind: String,
+    pub name: String,
+    /// `Create`, `Update` or `None`.
+    pub action: String,
+    /// JSON pointers of the fields that would change.
+    pub fields: Vec<String>,
+    /// What an `Event` would say.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UpgradeStatus {
+    pub phase: UpgradePhase,
+    pub from_image: String,
+    pub to_image: String,
+    /// When the canary was started.
+    pub started_time: String,
+    pub message: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, JsonSchema)]
+pub enum UpgradePhase {
+    /// The canary runs `toImage`; the Deployment still runs `fromImage`.
+    Canary,
+    Promoted,
+    /// The canary failed; `toImage` is not retried until `spec.image` changes.
+    RolledBack,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BootstrappedStore {
+    pub name: String,
+    pub id: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenFGAHistoryEntry {
+    pub time: String,
+    /// Type of the condition that transitioned.
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub status: String,
+    pub reason: Option<String>,
+    pub message: Option<String>,
+}
+
+/// Health probes for the OpenFGA container. Liveness and startup use the HTTP
+/// `/healthz` endpoint, readiness uses the gRPC health service.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ProbesConfig {
+    #[serde(default)]
+    pub liveness: ProbeConfig,
+    #[serde(default)]
+    pub readiness: ProbeConfig,
+    #[serde(default)]
+    pub startup: ProbeConfig,
+}
+
+/// Timing overrides for a single probe; unset fields fall back to per-probe defaults.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ProbeConfig {
+    #[serde(default = "default_probe_enabled")]
+    pub enabled: bool,
+    pub initial_delay_seconds: Option<i32>,
+    pub period_seconds: Option<i32>,
+    pub timeout_seconds: Option<i32>,
+    pub failure_threshold: Option<i32>,
+}
+
+impl Default for ProbeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_probe_enabled(),
+            initial_delay_seconds: None,
+            period_seconds: None,
+            timeout_seconds: None,
+            failure_threshold: None,
+        }
+    }
+}
+
+/// An authorization model to be loaded into an OpenFGA instance, given either in
+/// the DSL or the JSON API format.
+#[derive(CustomResource, Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[kube(
+    group = "authorization.openfga.dev",
+    version = "v1alpha1",
+    kind = "AuthorizationModel",
+    plural = "authorizationmodels",
+    shortname = "ofgam",
+    status = "AuthorizationModelStatus",
+    printcolumn = r#"{"name":"Instance","type":"string","jsonPath":".spec.instanceRef.name"}"#,
+    printcolumn = r#"{"name":"Module-Of","type":"string","jsonPath":".spec.moduleOf","priority":1}"#,
+    printcolumn = r#"{"name":"Valid","type":"string","jsonPath":".status.conditions[?(@.type==\"Valid\")].status"}"#,
+    printcolumn = r#"{"name":"Breaking","type":"boolean","jsonPath":".status.breakingChange"}"#,
+    printcolumn = r#"{"name":"Compatible","type":"string","jsonPath":".status.conditions[?(@.type==\"Compatible\")].status"}"#,
+    printcolumn = r#"{"name":"Active-Model","type":"string","jsonPath":".status.activeModelId"}"#,
+    namespaced
+)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthorizationModelSpec {
+    pub instance_ref: InstanceReference,
+
+    pub dsl: Option<String>,
+
+    pub json: Option<String>,
+
+    /// Severity overrides per lint rule, e.g. `public-wildcard: error`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub lint_rules: BTreeMap<String, LintSeverity>,
+
+    /// Keep the model from being written while lint findings at error severity
+    /// remain. When off, they are only reported.
+    #[serde(default = "default_fail_on_lint_errors")]
+    pub fail_on_lint_errors: bool,
+
+    /// Store the model is written to; existing tuples are checked against breaking changes.
+    pub store_id: Option<String>,
+
+    /// Mark the model compatible even when existing tuples would no longer fit it.
+    #[serde(default)]
+    pub allow_incompatible_tuples: bool,
+
+    /// Name of the AuthorizationModel in the same namespace this resource is a module of.
+    /// Modules contribute types and relations to their parent and are only parsed on
+    /// their own; the parent validates the combined model.
+    pub module_of: Option<String>,
+
+    /// Model ID from `status.modelHistory` consumers should use instead of the latest
+    /// written version. Models are immutable in OpenFGA, so rolling back only moves
+    /// `status.activeModelId`.
+    pub rollback_to: Option<String>,
+
+    /// Checks run against every written model version; a failing one sets
+    /// `AssertionsPassed=False`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub assertions: Vec<ModelAssertion>,
+}
+
+/// Expected outcome of a check against the model.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelAssertion {
+    pub user: String,
+    pub relation: String,
+    pub object: String,
+    pub expected: bool,
+}
+
+impl ModelAssertion {
+    pub fn tuple(&self) -> TupleKey {
+        TupleKey::new(&self.user, &self.relation, &self.object)
+    }
+}
+
+/// Reference to an OpenFGA instance, by name or, for stores and models, by the
+/// instance's labels.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct InstanceReference {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub name: String,
+
+    /// Labels the instance must carry; takes the place of `name`. Exactly one
+    /// instance may match.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub selector: BTreeMap<String, String>,
+
+    /// Namespace of the instance for stores and models, their own when unset.
+    /// Another namespace must grant the reference with an OpenFGAReferenceGrant.
+    pub namespace: Option<String>,
+}
+
+/// Lets AuthorizationModels and OpenFGAStores in other namespaces reference
+/// OpenFGA instances in this one, in the manner of the Gateway API's
+/// ReferenceGrant. Created by whoever runs the instances.
+#[derive(CustomResource, Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[kube(
+    group = "authorization.openfga.dev",
+    version = "v1alpha1",
+    kind = "OpenFGAReferenceGrant",
+    plural = "openfgareferencegrants",
+    shortname = "ofgarg",
+    namespaced
+)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenFGAReferenceGrantSpec {
+    /// Kinds and namespaces allowed to reference instances here.
+    pub from: Vec<ReferenceGrantFrom>,
+
+    /// Instances that may be referenced; every instance in the namespace when empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub to: Vec<ReferenceGrantTo>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReferenceGrantFrom {
+    /// `AuthorizationModel` or `OpenFGAStore`.
+    pub kind: String,
+    pub namespace: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReferenceGrantTo {
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthorizationModelStatus {
+    pub conditions: Option<Vec<OpenFGACondition>>,
+    /// Lint findings for the current spec, formatted as `[severity] rule location: message`.
+    pub lint_warnings: Option<Vec<String>>,
+    /// Compact JSON of the last model that passed validation, the base for the next diff.
+    pub last_valid_model: Option<String>,
+    /// Changes from the previous valid model to the current one, one per entry.
+    pub model_diff: Option<Vec<String>>,
+    /// Set when the last change removed types, relations or assignable types that
+    /// existing tuples may rely on.
+    pub breaking_change: Option<bool>,
+    /// Result of checking existing tuples against the last breaking change.
+    pub compatibility: Option<TupleCompatibility>,
+    /// Modules composed into the current model, in the order they were applied.
+    pub modules: Option<Vec<String>>,
+    /// ID of the latest model version written to `spec.storeId`.
+    pub model_id: Option<String>,
+    /// Model ID consumers should use: `spec.rollbackTo` when set, the latest otherwise.
+    pub active_model_id: Option<String>,
+    /// Most recently written model versions, oldest first.
+    pub model_history: Option<Vec<ModelVersion>>,
+    /// Assertions from `spec.assertions` the active model fails, with the outcome.
+    pub failed_assertions: Option<Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelVersion {
+    pub id: String,
+    /// Hash of the model JSON, used to skip rewriting an unchanged model.
+    pub model_hash: String,
+    pub written_time: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TupleCompatibility {
+    /// Hash of the model version the scan was run against.
+    pub model_hash: String,
+    pub scanned_tuples: i64,
+    pub incompatible_tuples: i64,
+    /// Sample of tuples the model no longer accepts, with the reason.
+    pub examples: Vec<String>,
+    /// False when the store was larger than the scan limit and only sampled.
+    pub complete: bool,
+}
+
+/// A store inside an OpenFGA instance, created on first reconcile.
+#[derive(CustomResource, Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[kube(
+    group = "authorization.openfga.dev",
+    version = "v1alpha1",
+    kind = "OpenFGAStore",
+    plural = "openfgastores",
+    shortname = "ofgas",
+    status = "OpenFGAStoreStatus",
+    printcolumn = r#"{"name":"Instance","type":"string","jsonPath":".spec.instanceRef.name"}"#,
+    printcolumn = r#"{"name":"Store ID","type":"string","jsonPath":".status.storeId"}"#,
+    printcolumn = r#"{"name":"Ready","type":"string","jsonPath":".status.conditions[?(@.type==\"Ready\")].status"}"#,
+    namespaced
+)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenFGAStoreSpec {
+    pub instance_ref: InstanceReference,
+
+    /// Store name in OpenFGA; defaults to the resource name.
+    pub store_name: Option<String>,
+
+    pub retention: Option<RetentionPolicy>,
+
+    /// Periodic report of who holds privileged relations, for recertification.
+    pub access_review: Option<AccessReviewPolicy>,
+
+    /// API tokens for application teams, each written to a Secret in the
+    /// consumer's namespace. The instance accepts them as preshared keys.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub access_tokens: Vec<AccessTokenSpec>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessTokenSpec {
+    /// Identifies the token within the store.
+    pub name: String,
+
+    /// Namespace of the consumer Secret; defaults to the store's namespace.
+    /// Another namespace needs an OpenFGAReferenceGrant from the store's.
+    pub namespace: Option<String>,
+
+    /// Name of the consumer Secret; defaults to `<store>-<name>-token`.
+    pub secret_name: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessReviewPolicy {
+    /// Privileged relation name patterns, e.g. `admin` or `*owner`.
+    pub relations: Vec<String>,
+
+    /// Object type patterns; all types when empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub object_types: Vec<String>,
+
+    #[serde(default = "default_access_review_interval_minutes")]
+    pub interval_minutes: u32,
+}
+
+/// Periodic deletion of tuples that have not been rewritten for a while.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RetentionPolicy {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<RetentionRule>,
+
+    /// Cleanup of tuples left behind by deleted objects.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub orphans: Vec<OrphanRule>,
+
+    /// Only report matching tuples; set to false to delete them.
+    #[serde(default = "default_retention_dry_run")]
+    pub dry_run: bool,
+
+    #[serde(default = "default_retention_interval_minutes")]
+    pub interval_minutes: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RetentionRule {
+    /// Relation name patterns; `*` matches any sequence of characters.
+    pub relations: Vec<String>,
+
+    /// Object type patterns; all types when empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub object_types: Vec<String>,
+
+    /// Tuples last written more than this many days ago are stale.
+    pub max_age_days: u32,
+}
+
+/// Objects of `objectType` without a tuple for `anchorRelation` count as
+/// deleted: their remaining tuples, and tuples naming them as the user, are
+/// orphaned.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct OrphanRule {
+    pub object_type: String,
+
+    /// Relation every live object has, e.g. `owner`.
+    pub anchor_relation: String,
+
+    /// Relation name patterns of the object's own tuples to remove; all when empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub relations: Vec<String>,
+
+    /// Tuples written more recently are kept, so objects whose anchor tuple is
+    /// written after their other tuples are not cleaned up early.
+    #[serde(default = "default_orphan_grace_minutes")]
+    pub grace_minutes: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenFGAStoreStatus {
+    pub store_id: Option<String>,
+    pub conditions: Option<Vec<OpenFGACondition>>,
+    pub retention: Option<RetentionReport>,
+    pub access_review: Option<AccessReviewSummary>,
+    /// Secrets the tokens of `spec.accessTokens` were written to.
+    pub access_tokens: Option<Vec<IssuedAccessToken>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct IssuedAccessToken {
+    pub name: String,
+    pub secret_namespace: String,
+    pub secret_name: String,
+}
+
+/// Outcome of the last access review; the full report is in the named ConfigMap.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessReviewSummary {
+    pub last_run_time: String,
+    pub privileged_grants: i64,
+    /// Grants that appeared since the previous report.
+    pub added: i64,
+    /// Grants that disappeared since the previous report.
+    pub removed: i64,
+    pub report: Option<String>,
+    pub message: Option<String>,
+}
+
+/// Outcome of the last retention run.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RetentionReport {
+    pub last_run_time: String,
+    pub dry_run: bool,
+    pub matched_tuples: i64,
+    pub deleted_tuples: i64,
+    /// Matched tuples that were orphaned rather than expired.
+    #[serde(default)]
+    pub orphaned_tuples: i64,
+    /// Sample of matched tuples.
+    pub examples: Vec<String>,
+    pub message: Option<String>,
+}
+
+/// A warm pool of identical OpenFGA instances handed out to claims on demand.
+#[derive(CustomResource, Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[kube(
+    group = "authorization.openfga.dev",
+    version = "v1alpha1",
+    kind = "OpenFGAPool",
+    plural = "openfgapools",
+    shortname = "ofgap",
+    status = "OpenFGAPoolStatus",
+    printcolumn = r#"{"name":"Size","type":"integer","jsonPath":".spec.size"}"#,
+    printcolumn = r#"{"name":"Available","type":"integer","jsonPath":".status.available"}"#,
+    printcolumn = r#"{"name":"Claimed","type":"integer","jsonPath":".status.claimed"}"#,
+    namespaced
+)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenFGAPoolSpec {
+    /// Number of unclaimed instances kept provisioned.
+    pub size: i32,
+
+    /// Spec for every instance the pool creates.
+    pub template: OpenFGASpec,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenFGAPoolStatus {
+    /// Unclaimed instances that are ready to be handed out.
+    pub available: Option<i32>,
+    /// Unclaimed instances still starting up.
+    pub warming: Option<i32>,
+    /// Instances handed out to claims.
+    pub claimed: Option<i32>,
+    pub conditions: Option<Vec<OpenFGACondition>>,
+}
 
+/// A request for an instance from an [`OpenFGAPool`].
+#[derive(CustomResource, Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[kube(
+    group = "authorization.openfga.dev",
+    version = "v1alpha1",
+    kind = "OpenFGAClaim",
+    plural = "openfgaclaims",
+    shortname = "ofgac",
+    status = "OpenFGAClaimStatus",
+    printcolumn = r#"{"name":"Pool","type":"string","jsonPath":".spec.poolRef.name"}"#,
+    printcolumn = r#"{"name":"Instance","type":"string","jsonPath":".status.instanceName"}"#,
+    printcolumn = r#"{"name":"Bound","type":"string","jsonPath":".status.conditions[?(@.type==\"Bound\")].status"}"#,
+    namespaced
+)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenFGAClaimSpec {
+    pub pool_ref: PoolReference,
+}
+
+/// Reference to an OpenFGAPool in the same namespace.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PoolReference {
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenFGAClaimStatus {
+    /// Name of the OpenFGA instance assigned to this claim.
+    pub instance_name: Option<String>,
+    pub conditions: Option<Vec<OpenFGACondition>>,
+}
+
+/// A request for one relationship tuple, written to the store once an authorized
+/// approver accepts it.
+#[derive(CustomResource, Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[kube(
+    group = "authorization.openfga.dev",
+    version = "v1alpha1",
+    kind = "OpenFGAAccessRequest",
+    plural = "openfgaaccessrequests",
+    shortname = "ofgaar",
+    status = "OpenFGAAccessRequestStatus",
+    printcolumn = r#"{"name":"Store","type":"string","jsonPath":".spec.storeRef.name"}"#,
+    printcolumn = r#"{"name":"User","type":"string","jsonPath":".spec.user"}"#,
+    printcolumn = r#"{"name":"Relation","type":"string","jsonPath":".spec.relation"}"#,
+    printcolumn = r#"{"name":"Object","type":"string","jsonPath":".spec.object"}"#,
+    printcolumn = r#"{"name":"Phase","type":"string","jsonPath":".status.phase"}"#,
+    printcolumn = r#"{"name":"Approver","type":"string","jsonPath":".status.decision.approver","priority":1}"#,
+    namespaced
+)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenFGAAccessRequestSpec {
+    pub store_ref: StoreReference,
+
+    /// Subject of the requested tuple, e.g. `user:anne`.
+    pub user: String,
+
+    pub relation: String,
+
+    /// Object of the requested tuple, e.g. `document:roadmap`.
+    pub object: String,
+
+    /// Why the access is needed, shown to approvers.
+    pub justification: Option<String>,
+}
+
+/// Reference to an OpenFGAStore in the same namespace.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StoreReference {
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenFGAAccessRequestStatus {
+    pub phase: Option<AccessRequestPhase>,
+    /// Set by an approver through the status subresource. The admission webhook
+    /// stamps the approver identity; values supplied by the client are replaced.
+    pub decision: Option<AccessDecision>,
+    /// Every step the request went through, oldest first.
+    pub trail: Option<Vec<AccessTrailEntry>>,
+    pub conditions: Option<Vec<OpenFGACondition>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, JsonSchema)]
+pub enum AccessRequestPhase {
+    /// Waiting for a decision.
+    #[default]
+    Pending,
+    /// Approved; the tuple has not been written yet.
+    Approved,
+    Denied,
+    /// The tuple was written to the store.
+    Granted,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessDecision {
+    pub approved: bool,
+    pub comment: Option<String>,
+    pub approver: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub approver_groups: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessTrailEntry {
+    pub time: String,
+    pub action: String,
+    /// Identity that caused the step, when one applies.
+    pub actor: Option<String>,
+    pub message: Option<String>,
+}
+
+/// Scheduled export of the stores of an OpenFGA instance: every authorization
+/// model and tuple, written as one JSON artifact per run.
+#[derive(CustomResource, Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[kube(
+    group = "authorization.openfga.dev",
+    version = "v1alpha1",
+    kind = "OpenFGABackup",
+    plural = "openfgabackups",
+    shortname = "ofgab",
+    status = "OpenFGABackupStatus",
+    printcolumn = r#"{"name":"Instance","type":"string","jsonPath":".spec.instanceRef.name"}"#,
+    printcolumn = r#"{"name":"Schedule","type":"string","jsonPath":".spec.schedule"}"#,
+    printcolumn = r#"{"name":"Last Backup","type":"date","jsonPath":".status.lastSuccessfulTime"}"#,
+    printcolumn = r#"{"name":"Ready","type":"string","jsonPath":".status.conditions[?(@.type==\"Ready\")].status"}"#,
+    namespaced
+)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenFGABackupSpec {
+    pub instance_ref: InstanceReference,
+
+    /// When to run, in CronJob syntax, e.g. `0 2 * * *`.
+    pub schedule: String,
+
+    /// Names of the stores to export; empty exports every store.
     #[serde(default)]
-    pub grpc: GrpcConfig,
+    pub stores: Vec<String>,
+
+    pub destination: BackupDestination,
 
     #[serde(default)]
-    pub http: HttpConfig,
+    pub retention: BackupRetention,
+
+    /// Stops scheduling new backups; a running one finishes.
+    #[serde(default)]
+    pub suspend: bool,
+
+    /// Image running the export; defaults to the operator's own image.
+    pub image: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+/// Where artifacts are written. Exactly one destination must be set.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, JsonSchema)]
 #[serde(rename_all = "camelCase")]
-pub struct DatastoreConfig {
-    #[serde(default = "default_engine")]
-    pub engine: String,
-
-    pub uri: Option<String>,
+pub struct BackupDestination {
+    pub s3: Option<S3Destination>,
+    pub gcs: Option<GcsDestination>,
+    pub pvc: Option<PvcDestination>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
 #[serde(rename_all = "camelCase")]
-pub struct PlaygroundConfig {
+pub struct S3Destination {
+    pub bucket: String,
+    /// Key prefix, without leading or trailing slash.
     #[serde(default)]
-    pub enabled: bool,
+    pub prefix: String,
+    pub region: Option<String>,
+    /// Endpoint of an S3 compatible service such as MinIO.
+    pub endpoint: Option<String>,
+    /// Secret with `AWS_ACCESS_KEY_ID` and `AWS_SECRET_ACCESS_KEY`.
+    pub credentials_secret: String,
+}
 
-    #[serde(default = "default_playground_port")]
-    pub port: i32,
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GcsDestination {
+    pub bucket: String,
+    /// Object prefix, without leading or trailing slash.
+    #[serde(default)]
+    pub prefix: String,
+    /// Secret with a service account key in `key.json`.
+    pub credentials_secret: String,
 }
 
-impl Default for PlaygroundConfig {
-    fn default() -> Self {
-        Self {
-            enabled: false,
-            port: default_playground_port(),
-        }
-    }
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PvcDestination {
+    pub claim_name: String,
+    /// Directory inside the volume.
+    #[serde(default)]
+    pub path: String,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
 #[serde(rename_all = "camelCase")]
-pub struct GrpcConfig {
-    #[serde(default = "default_grpc_port")]
-    pub port: i32,
+pub struct BackupRetention {
+    /// Artifacts kept at the destination; older ones are deleted after each run.
+    #[serde(default = "default_backup_keep_last")]
+    pub keep_last: u32,
 }
 
-impl Default for GrpcConfig {
+impl Default for BackupRetention {
     fn default() -> Self {
         Self {
-            port: default_grpc_port(),
+            keep_last: default_backup_keep_last(),
         }
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default, JsonSchema)]
 #[serde(rename_all = "camelCase")]
-pub struct HttpConfig {
-    #[serde(default = "default_http_port")]
-    pub port: i32,
+pub struct OpenFGABackupStatus {
+    pub conditions: Option<Vec<OpenFGACondition>>,
+    /// CronJob running the backups.
+    pub cron_job: Option<String>,
+    pub last_schedule_time: Option<String>,
+    pub last_successful_time: Option<String>,
+    /// Location of the artifact written by the last successful run.
+    pub last_backup: Option<String>,
 }
 
-impl Default for HttpConfig {
-    fn default() -> Self {
-        Self {
-            port: default_http_port(),
-        }
-    }
+/// One-off replay of an OpenFGABackup artifact into an OpenFGA instance: stores
+/// are created by name, missing model versions written oldest first, and only
+/// tuples the target lacks are written, so a restore can safely be repeated.
+#[derive(CustomResource, Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[kube(
+    group = "authorization.openfga.dev",
+    version = "v1alpha1",
+    kind = "OpenFGARestore",
+    plural = "openfgarestores",
+    shortname = "ofgar",
+    status = "OpenFGARestoreStatus",
+    printcolumn = r#"{"name":"Instance","type":"string","jsonPath":".spec.instanceRef.name"}"#,
+    printcolumn = r#"{"name":"Phase","type":"string","jsonPath":".status.phase"}"#,
+    printcolumn = r#"{"name":"Tuples","type":"integer","jsonPath":".status.progress.tuplesWritten"}"#,
+    printcolumn = r#"{"name":"Dry Run","type":"boolean","jsonPath":".spec.dryRun"}"#,
+    namespaced
+)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenFGARestoreSpec {
+    /// Instance the stores are restored into.
+    pub instance_ref: InstanceReference,
+
+    pub source: RestoreSource,
+
+    /// Names of the stores to restore; empty restores every store in the artifact.
+    #[serde(default)]
+    pub stores: Vec<String>,
+
+    /// Reports what would be written without writing anything.
+    #[serde(default)]
+    pub dry_run: bool,
+
+    /// Tuples per Write request, at most 100.
+    #[serde(default = "default_restore_batch_size")]
+    pub batch_size: u32,
+
+    /// Image running the restore; defaults to the operator's own image.
+    pub image: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
 #[serde(rename_all = "camelCase")]
-pub struct OpenFGACondition {
-    #[serde(rename = "type")]
-    pub type_: String,
-    pub status: String,
-    #[serde(rename = "lastTransitionTime")]
-    pub last_transition_time: Option<String>,
-    pub reason: Option<String>,
-    pub message: Option<String>,
+pub struct RestoreSource {
+    /// OpenFGABackup whose destination holds the artifact.
+    pub backup_ref: BackupReference,
+    /// Artifact file name, e.g. `nightly-backup-29000000.json`; defaults to the
+    /// backup's last successful one.
+    pub artifact: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct BackupReference {
+    pub name: String,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default, JsonSchema)]
 #[serde(rename_all = "camelCase")]
-pub struct OpenFGAStatus {
-    pub replicas: Option<i32>,
-    pub ready_replicas: Option<i32>,
+pub struct OpenFGARestoreStatus {
     pub conditions: Option<Vec<OpenFGACondition>>,
+    /// `Pending`, `Running`, `Succeeded` or `Failed`.
+    pub phase: Option<String>,
+    /// Location of the artifact being restored, fixed when the restore starts.
+    pub artifact: Option<String>,
+    /// Job running the restore.
+    pub job: Option<String>,
+    /// Written by the restore job as it goes.
+    pub progress: Option<RestoreProgress>,
+    /// Idempotency keys of the tuple batches this generation of the restore
+    /// has fully applied, written by the job so a retried run skips them.
+    pub applied_tuple_batches: Option<Vec<String>>,
+    pub start_time: Option<String>,
+    pub completion_time: Option<String>,
+}
+
+/// Counts of a restore so far. In a dry run, "written" counts what would be written.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreProgress {
+    pub stores_total: i64,
+    pub stores_done: i64,
+    /// Store being restored, unset once all are done.
+    pub current_store: Option<String>,
+    pub models_written: i64,
+    pub tuples_total: i64,
+    pub tuples_written: i64,
+    /// Tuples the target already had.
+    pub tuples_skipped: i64,
+    pub tuples_failed: i64,
 }
 
 // Default value functions
@@ -123,31 +1785,119 @@ fn default_engine() -> String {
 fn default_playground_port() -> i32 {
     3000
 }
+fn default_auth_proxy_port() -> i32 {
+    4180
+}
 fn default_grpc_port() -> i32 {
     8081
 }
 fn default_http_port() -> i32 {
     8080
 }
+fn default_probe_enabled() -> bool {
+    true
+}
+fn default_retention_dry_run() -> bool {
+    true
+}
+fn default_retention_interval_minutes() -> u32 {
+    60
+}
+fn default_orphan_grace_minutes() -> u32 {
+    60
+}
+fn default_access_review_interval_minutes() -> u32 {
+    1440
+}
+fn default_service_account_create() -> bool {
+    true
+}
+fn default_query_cache_enabled() -> bool {
+    true
+}
+fn default_fail_on_lint_errors() -> bool {
+    true
+}
+fn default_deletion_confirmation_threshold() -> u64 {
+    1000
+}
+fn default_metrics_port() -> i32 {
+    2112
+}
+fn default_backup_keep_last() -> u32 {
+    7
+}
+fn default_restore_batch_size() -> u32 {
+    100
+}
+fn default_canary_replicas() -> i32 {
+    1
+}
+
+fn default_canary_pause_seconds() -> u64 {
+    300
+}
+
+fn default_persistence_size() -> String {
+    "1Gi".to_string()
+}
+
+fn default_persistence_mount_path() -> String {
+    "/var/lib/openfga".to_string()
+}
+
+fn default_cache_mount_path() -> String {
+    "/tmp".to_string()
+}
 
 impl Default for DatastoreConfig {
     fn default() -> Self {
         Self {
             engine: default_engine(),
             uri: None,
+            uri_secret_ref: None,
+            postgres: None,
+            mysql: None,
         }
     }
 }
 
+/// Schema for free-form objects; structural schemas cannot describe them otherwise.
+fn preserve_unknown_fields(_: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+    serde_json::from_value(serde_json::json!({
+        "type": "object",
+        "x-kubernetes-preserve-unknown-fields": true,
+    }))
+    .expect("valid schema")
+}
+
+/// The CustomResourceDefinitions of every resource the operator serves, as
+/// generated from the types above.
+pub fn crds() -> Vec<CustomResourceDefinition> {
+    vec![
+        OpenFGA::crd(),
+        AuthorizationModel::crd(),
+        OpenFGAStore::crd(),
+        OpenFGAPool::crd(),
+        OpenFGAClaim::crd(),
+        OpenFGAAccessRequest::crd(),
+        OpenFGABackup::crd(),
+        OpenFGARestore::crd(),
+        OpenFGAReferenceGrant::crd(),
+    ]
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinitionVersion;
 
     #[test]
     fn test_default_values() {
         let datastore = DatastoreConfig::default();
         assert_eq!(datastore.engine, "memory");
         assert_eq!(datastore.uri, None);
+        assert_eq!(datastore.uri_secret_ref, None);
 
         let playground = PlaygroundConfig::default();
         assert!(!playground.enabled);
@@ -158,6 +1908,12 @@ mod tests {
 
         let http = HttpConfig::default();
         assert_eq!(http.port, 8080);
+
+        let probes: ProbesConfig =
+            serde_json::from_str(r#"{"startup":{"enabled":false}}"#).unwrap();
+        assert!(probes.liveness.enabled);
+        assert!(probes.readiness.enabled);
+        assert!(!probes.startup.enabled);
     }
 
     #[test]
@@ -168,13 +1924,67 @@ mod tests {
             datastore: DatastoreConfig {
                 engine: "postgres".to_string(),
                 uri: Some("postgresql://localhost:5432/openfga".to_string()),
+                uri_secret_ref: None,
+                postgres: None,
+                mysql: None,
             },
             playground: PlaygroundConfig {
                 enabled: true,
                 port: 3000,
+                auth_proxy: None,
             },
             grpc: GrpcConfig { port: 8081 },
             http: HttpConfig { port: 8080 },
+            api_protocol: ApiProtocol::Http,
+            env: vec![EnvVar {
+                name: "OPENFGA_LOG_LEVEL".to_string(),
+                value: Some("debug".to_string()),
+                ..Default::default()
+            }],
+            env_from: vec![],
+            config: BTreeMap::new(),
+            volumes: vec![],
+            volume_mounts: vec![],
+            resources: Some(ResourceSpec {
+                requests: Some(ResourceQuantities {
+                    cpu: Some("250m".to_string()),
+                    memory: Some("512Mi".to_string()),
+                    ephemeral_storage: None,
+                }),
+                limits: None,
+            }),
+            probes: ProbesConfig::default(),
+            tls: None,
+            ingress: None,
+            cache_volume: None,
+            observability: ObservabilityConfig::default(),
+            logging: None,
+            deletion_policy: DeletionPolicy::default(),
+            deletion_confirmation_threshold: 1000,
+            pod_security_context: None,
+            security_context: None,
+            service_account: ServiceAccountConfig::default(),
+            service: ServiceConfig::default(),
+            depends_on: vec![],
+            bootstrap: BootstrapConfig::default(),
+            labels: BTreeMap::new(),
+            annotations: BTreeMap::new(),
+            workload_type: WorkloadType::Deployment,
+            persistence: None,
+            upgrade_strategy: None,
+            termination: None,
+            min_ready_seconds: None,
+            priority_class_name: None,
+            runtime_class_name: None,
+            topology: None,
+            overrides_from: None,
+            target_cluster_secret_ref: None,
+            query_cache: None,
+            feature_flags: vec![],
+            resolve_image_digest: false,
+            image_verification: None,
+            image_pull_secrets: vec![],
+            image_pull_policy: None,
         };
 
         // Test serialization to JSON
@@ -182,11 +1992,49 @@ mod tests {
         assert!(json.contains("\"replicas\":2"));
         assert!(json.contains("\"image\":\"openfga/openfga:v1.0.0\""));
         assert!(json.contains("\"engine\":\"postgres\""));
+        assert!(json.contains("\"env\":[{\"name\":\"OPENFGA_LOG_LEVEL\",\"value\":\"debug\"}]"));
+        assert!(!json.contains("envFrom"));
+        assert!(json.contains("\"requests\":{\"cpu\":\"250m\",\"memory\":\"512Mi\"}"));
 
         // Test deserialization from JSON
         let _deserialized: OpenFGASpec = serde_json::from_str(&json).unwrap();
     }
 
+    #[test]
+    fn test_datastore_uri_secret_ref_deserialization() {
+        let json = r#"{"engine":"postgres","uriSecretRef":{"name":"pg-creds","key":"uri"}}"#;
+        let datastore: DatastoreConfig = serde_json::from_str(json).unwrap();
+
+        assert_eq!(datastore.uri, None);
+        assert_eq!(
+            datastore.uri_secret_ref,
+            Some(SecretKeyReference {
+                name: "pg-creds".to_string(),
+                key: "uri".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_authorization_model_spec_deserialization() {
+        let json = r#"{
+            "instanceRef": {"name": "openfga-basic"},
+            "dsl": "model\n  schema 1.1\ntype user\n",
+            "lintRules": {"public-wildcard": "error", "unused-type": "off"}
+        }"#;
+        let spec: AuthorizationModelSpec = serde_json::from_str(json).unwrap();
+
+        assert_eq!(spec.instance_ref.name, "openfga-basic");
+        assert!(spec.json.is_none());
+        assert!(spec.store_id.is_none());
+        assert!(!spec.allow_incompatible_tuples);
+        assert_eq!(
+            spec.lint_rules.get("public-wildcard"),
+            Some(&LintSeverity::Error)
+        );
+        assert_eq!(spec.lint_rules.get("unused-type"), Some(&LintSeverity::Off));
+    }
+
     #[test]
     fn test_condition_serialization() {
         let condition = OpenFGACondition {
@@ -195,11 +2043,13 @@ mod tests {
             last_transition_time: Some("2024-01-01T00:00:00Z".to_string()),
             reason: Some("Deployed".to_string()),
             message: Some("OpenFGA is ready".to_string()),
+            observed_generation: Some(3),
         };
 
         let json = serde_json::to_string(&condition).unwrap();
         assert!(json.contains("\"type\":\"Ready\""));
         assert!(json.contains("\"status\":\"True\""));
+        assert!(json.contains("\"observedGeneration\":3"));
 
         let _deserialized: OpenFGACondition = serde_json::from_str(&json).unwrap();
     }
@@ -207,21 +2057,130 @@ mod tests {
     #[test]
     fn test_status_serialization() {
         let status = OpenFGAStatus {
+            observed_generation: Some(1),
             replicas: Some(2),
             ready_replicas: Some(2),
+            selector: Some("app=openfga".to_string()),
             conditions: Some(vec![OpenFGACondition {
                 type_: "Ready".to_string(),
                 status: "True".to_string(),
                 last_transition_time: None,
                 reason: None,
                 message: None,
+                observed_generation: None,
             }]),
+            zones: Some(vec!["zone-a".to_string(), "zone-b".to_string()]),
+            history: None,
+            stores: None,
+            upgrade: None,
+            resolved_image: None,
+            pending_changes: None,
+            resource_recommendations: None,
         };
 
         let json = serde_json::to_string(&status).unwrap();
         assert!(json.contains("\"replicas\":2"));
         assert!(json.contains("\"readyReplicas\":2"));
+        assert!(json.contains("\"zones\":[\"zone-a\",\"zone-b\"]"));
+        assert!(json.contains("\"selector\":\"app=openfga\""));
 
         let _deserialized: OpenFGAStatus = serde_json::from_str(&json).unwrap();
     }
+
+    #[test]
+    fn test_condition_keeps_transition_time() {
+        let previous = vec![OpenFGACondition {
+            type_: "Ready".to_string(),
+            status: "True".to_string(),
+            last_transition_time: Some("2024-01-01T00:00:00Z".to_string()),
+            reason: None,
+            message: None,
+            observed_generation: Some(1),
+        }];
+
+        let unchanged =
+            OpenFGACondition::new("Ready", true, "Ready", "ok", Some(2), Some(&previous));
+        assert_eq!(
+            unchanged.last_transition_time,
+            Some("2024-01-01T00:00:00Z".to_string())
+        );
+        assert_eq!(unchanged.observed_generation, Some(2));
+
+        let flipped =
+            OpenFGACondition::new("Ready", false, "NotReady", "bad", Some(2), Some(&previous));
+        assert_ne!(
+            flipped.last_transition_time,
+            Some("2024-01-01T00:00:00Z".to_string())
+        );
+    }
+
+    /// Property paths of a schema, e.g. `.spec.datastore.engine` or `.spec.env[].name`.
+    fn property_paths(schema: &serde_json::Value, prefix: &str, paths: &mut Vec<String>) {
+        if let Some(properties) = schema["properties"].as_object() {
+            for (name, property) in properties {
+                let path = format!("{}.{}", prefix, name);
+                property_paths(property, &path, paths);
+                paths.push(path);
+            }
+        }
+        if schema["items"].is_object() {
+            property_paths(&schema["items"], &format!("{}[]", prefix), paths);
+        }
+    }
+
+    #[test]
+    fn test_generated_crds_cover_manifests() {
+        let manifests = crate::crds::MANIFESTS;
+        let generated = crds();
+        assert_eq!(generated.len(), manifests.len());
+
+        for (crd, manifest) in generated.iter().zip(manifests) {
+            let manifest: CustomResourceDefinition = serde_yaml::from_str(manifest).unwrap();
+            assert_eq!(crd.metadata.name, manifest.metadata.name);
+            let (version, expected) = (&crd.spec.versions[0], &manifest.spec.versions[0]);
+            assert_eq!(version.name, expected.name);
+            assert_eq!(version.subresources, expected.subresources);
+            let columns = |v: &CustomResourceDefinitionVersion| {
+                v.additional_printer_columns
+                    .iter()
+                    .flatten()
+                    .map(|c| (c.name.clone(), c.json_path.clone()))
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(columns(version), columns(expected));
+
+            // Every field the manifest declares must exist in the types
+            let schema = |v: &CustomResourceDefinitionVersion| {
+                let schema = v
+                    .schema
+                    .as_ref()
+                    .unwrap()
+                    .open_api_v3_schema
+                    .as_ref()
+                    .unwrap();
+                let mut paths = Vec::new();
+                property_paths(&serde_json::to_value(schema).unwrap(), "", &mut paths);
+                paths
+            };
+            let known = schema(version);
+            let unknown: Vec<String> = schema(expected)
+                .into_iter()
+                .filter(|p| !known.contains(p))
+                .collect();
+            assert!(unknown.is_empty(), "{:?}: {:?}", crd.metadata.name, unknown);
+        }
+    }
+
+    #[test]
+    fn test_crds_share_one_api_group() {
+        let crds = crds();
+        let mut kinds: Vec<&str> = crds.iter().map(|c| c.spec.names.kind.as_str()).collect();
+        assert!(crds
+            .iter()
+            .all(|c| c.spec.group == "authorization.openfga.dev"));
+        kinds.sort_by_key(|k| k.to_lowercase());
+        let before = kinds.len();
+        kinds.dedup_by_key(|k| k.to_lowercase());
+        assert_eq!(kinds.len(), before, "kinds must differ by more than case");
+    }
 }
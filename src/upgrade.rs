@@ -0,0 +1,593 @@
+//! Gradual image upgrades for Deployment workloads. The rolling update bounds
+//! come from `spec.upgradeStrategy`; with a canary configured, a new image first
+//! runs in a separate `<name>-canary` Deployment behind its own Service while
+//! the main Deployment stays on the old image. Once the pause has passed the
+//! canary is verified through the OpenFGA API: on success the main Deployment
+//! is moved to the new image, otherwise the canary is removed and the image is
+//! not tried again until `spec.image` changes.
+
+use crate::access_tokens;
+use crate::apply;
+use crate::controller::ControllerResult;
+use crate::labels;
+use crate::openfga_client::OpenFGAClient;
+use crate::types::{OpenFGA, UpgradePhase, UpgradeStatus, UpgradeStrategy};
+use chrono::{DateTime, Utc};
+use k8s_openapi::api::apps::v1::{Deployment, DeploymentStrategy, RollingUpdateDeployment};
+use k8s_openapi::api::core::v1::{Service, ServicePort, ServiceSpec};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{LabelSelector, ObjectMeta};
+use k8s_openapi::apimachinery::pkg::util::intstr::IntOrString;
+use kube::api::{Api, DeleteParams, Patch, PatchParams};
+use kube::runtime::events::{Event, EventType, Recorder};
+use kube::{Client, Resource, ResourceExt};
+use serde_json::json;
+use std::time::Duration;
+use tracing::{info, instrument, warn};
+
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often a running canary is looked at before its pause is over.
+const CANARY_POLL_INTERVAL: Duration = Duration::from_secs(15);
+
+pub fn canary_name(name: &str) -> String {
+    format!("{}-canary", name)
+}
+
+/// The RollingUpdate strategy for `upgradeStrategy`, or None to keep the defaults.
+pub fn rolling_update(strategy: Option<&UpgradeStrategy>) -> Option<DeploymentStrategy> {
+    let strategy = strategy?;
+    if strategy.max_unavailable.is_none() && strategy.max_surge.is_none() {
+        return None;
+    }
+    Some(DeploymentStrategy {
+        type_: Some("RollingUpdate".to_string()),
+        rolling_update: Some(RollingUpdateDeployment {
+            max_unavailable: strategy.max_unavailable.clone(),
+            max_surge: strategy.max_surge.clone(),
+        }),
+    })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CanaryStep {
+    /// The Deployment already runs `spec.image`.
+    Stable,
+    /// A new image: start a canary for it.
+    Start,
+    /// The canary runs; check again after the given time.
+    Wait(Duration),
+    Promote,
+    RollBack(String),
+    /// `spec.image` failed as a canary before; keep the old image.
+    Blocked,
+}
+
+/// What to do with an image change, given the canary started for it so far and,
+/// once its pause is over, the result of verifying it.
+pub fn next_step(
+    stable_image: &str,
+    desired_image: &str,
+    upgrade: Option<&UpgradeStatus>,
+    health: Option<&Result<(), String>>,
+    now: DateTime<Utc>,
+    pause: Duration,
+) -> CanaryStep {
+    if stable_image == desired_image {
+        return CanaryStep::Stable;
+    }
+    let Some(upgrade) = upgrade.filter(|u| u.to_image == desired_image) else {
+        return CanaryStep::Start;
+    };
+    match upgrade.phase {
+        UpgradePhase::RolledBack => CanaryStep::Blocked,
+        // The Deployment was changed back by hand after promotion; start over
+        UpgradePhase::Promoted => CanaryStep::Start,
+        UpgradePhase::Canary => {
+            let remaining = pause.saturating_sub(elapsed(upgrade, now));
+            if !remaining.is_zero() {
+                return CanaryStep::Wait(remaining.min(CANARY_POLL_INTERVAL));
+            }
+            match health {
+                Some(Ok(())) => CanaryStep::Promote,
+                Some(Err(e)) => CanaryStep::RollBack(e.clone()),
+                None => CanaryStep::Wait(CANARY_POLL_INTERVAL),
+            }
+        }
+    }
+}
+
+fn elapsed(upgrade: &UpgradeStatus, now: DateTime<Utc>) -> Duration {
+    DateTime::parse_from_rfc3339(&upgrade.started_time)
+        .ok()
+        .and_then(|started| (now - started.with_timezone(&Utc)).to_std().ok())
+        .unwrap_or_default()
+}
+
+pub fn container_image(deployment: &Deployment) -> Option<String> {
+    deployment
+        .spec
+        .as_ref()?
+        .template
+        .spec
+        .as_ref()?
+        .containers
+        .first()?
+        .image
+        .clone()
+}
+
+pub fn set_image(deployment: &mut Deployment, image: &str) {
+    if let Some(container) = deployment
+        .spec
+        .as_mut()
+        .and_then(|s| s.template.spec.as_mut())
+        .and_then(|p| p.containers.first_mut())
+    {
+        container.image = Some(image.to_string());
+    }
+}
+
+/// The main Deployment running `image` with `replicas` pods under the canary's
+/// name. The canary's labels differ from the instance's, so neither the main
+/// Deployment nor the main Service select its pods.
+pub fn canary_deployment(main: &Deployment, name: &str, image: &str, replicas: i32) -> Deployment {
+    let canary = canary_name(name);
+    let mut deployment = main.clone();
+    deployment.metadata.name = Some(canary.clone());
+    deployment.metadata.resource_version = None;
+    let mut pod_labels = main
+        .spec
+        .as_ref()
+        .and_then(|s| s.template.metadata.as_ref())
+        .and_then(|m| m.labels.clone())
+        .unwrap_or_default();
+    pod_labels.extend(labels::selector_labels(&canary));
+    let mut metadata_labels = deployment.metadata.labels.clone().unwrap_or_default();
+    metadata_labels.extend(labels::selector_labels(&canary));
+    deployment.metadata.labels = Some(metadata_labels);
+    if let Some(spec) = deployment.spec.as_mut() {
+        spec.replicas = Some(replicas);
+        spec.selector = LabelSelector {
+            match_labels: Some(labels::selector_labels(&canary)),
+            ..Default::default()
+        };
+        spec.template
+            .metadata
+            .get_or_insert_with(Default::default)
+            .labels = Some(pod_labels);
+    }
+    set_image(&mut deployment, image);
+    deployment
+}
+
+/// ClusterIP Service the canary is verified through.
+pub fn canary_service(openfga: &OpenFGA, ns: &str, name: &str) -> Service {
+    let canary = canary_name(name);
+    Service {
+        metadata: ObjectMeta {
+            name: Some(canary.clone()),
+            namespace: Some(ns.to_string()),
+            labels: Some(labels::selector_labels(&canary)),
+            ..Default::default()
+        },
+        spec: Some(ServiceSpec {
+            selector: Some(labels::selector_labels(&canary)),
+            ports: Some(vec![ServicePort {
+                name: Some("http".to_string()),
+                port: openfga.spec.http.port,
+                target_port: Some(IntOrString::Int(openfga.spec.http.port)),
+                protocol: Some("TCP".to_string()),
+                ..Default::default()
+            }]),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+async fn check_canary(
+    client: &Client,
+    openfga: &OpenFGA,
+    ns: &str,
+    name: &str,
+) -> Result<(), String> {
+    let canary = canary_name(name);
+    let replicas = openfga
+        .spec
+        .upgrade_strategy
+        .as_ref()
+        .and_then(|s| s.canary.as_ref())
+        .map_or(1, |c| c.replicas);
+    let deployments: Api<Deployment> = Api::namespaced(client.clone(), ns);
+    let ready = deployments
+        .get_opt(&canary)
+        .await
+        .map_err(|e| e.to_string())?
+        .and_then(|d| d.status)
+        .and_then(|s| s.ready_replicas)
+        .unwrap_or(0);
+    if ready < replicas {
+        return Err(format!("{}/{} canary replicas ready", ready, replicas));
+    }
+
+    let url = format!("http://{}.{}.svc:{}", canary, ns, openfga.spec.http.port);
+    match tokio::time::timeout(
+        CHECK_TIMEOUT,
+        OpenFGAClient::new(&url)
+            .with_token(access_tokens::operator_token(openfga))
+            .verify(),
+    )
+    .await
+    {
+        Ok(result) => result.map_err(|e| format!("health check failed: {}", e)),
+        Err(_) => Err("health check timed out".to_string()),
+    }
+}
+
+pub async fn remove_canary(client: &Client, ns: &str, name: &str) -> ControllerResult<()> {
+    let canary = canary_name(name);
+    let deployments: Api<Deployment> = Api::namespaced(client.clone(), ns);
+    let services: Api<Service> = Api::namespaced(client.clone(), ns);
+    if deployments.get_opt(&canary).await?.is_some() {
+        deployments
+            .delete(&canary, &DeleteParams::default())
+            .await?;
+    }
+    if services.get_opt(&canary).await?.is_some() {
+        services.delete(&canary, &DeleteParams::default()).await?;
+    }
+    Ok(())
+}
+
+async fn set_upgrade_status(
+    client: &Client,
+    openfga: &OpenFGA,
+    upgrade: Option<&UpgradeStatus>,
+) -> ControllerResult<()> {
+    let api: Api<OpenFGA> =
+        Api::namespaced(client.clone(), &openfga.namespace().unwrap_or_default());
+    api.patch_status(
+        &openfga.name_any(),
+        &PatchParams::default(),
+        &Patch::Merge(&json!({ "status": { "upgrade": upgrade } })),
+    )
+    .await?;
+    Ok(())
+}
+
+async fn publish(client: &Client, openfga: &OpenFGA, type_: EventType, reason: &str, note: String) {
+    let recorder = Recorder::new(
+        client.clone(),
+        "openfga-controller".into(),
+        openfga.object_ref(&()),
+    );
+    let event = Event {
+        type_,
+        reason: reason.to_string(),
+        note: Some(note),
+        action: "Upgrade".to_string(),
+        secondary: None,
+    };
+    if let Err(e) = recorder.publish(event).await {
+        warn!(
+            event = "upgrade_event_failed",
+            namespace = %openfga.namespace().unwrap_or_default(),
+            resource_name = %openfga.name_any(),
+            error = %e,
+            "Failed to publish upgrade event"
+        );
+    }
+}
+
+/// Runs the canary step for an image change and sets the image `deployment` is
+/// applied with. Returns how soon the instance should be looked at again while a
+/// canary runs.
+#[instrument(skip(client, openfga, deployment, live), fields(namespace = %ns, name = %name))]
+pub async fn reconcile_canary(
+    client: &Client,
+    openfga: &OpenFGA,
+    deployment: &mut Deployment,
+    live: &Deployment,
+    ns: &str,
+    name: &str,
+) -> ControllerResult<Option<Duration>> {
+    let upgrade = openfga.status.as_ref().and_then(|s| s.upgrade.as_ref());
+    let Some(canary) = openfga
+        .spec
+        .upgrade_strategy
+        .as_ref()
+        .and_then(|s| s.canary.as_ref())
+    else {
+        // Canary removed from the spec while one was running
+        if upgrade.is_some_and(|u| u.phase == UpgradePhase::Canary) {
+            remove_canary(client, ns, name).await?;
+            set_upgrade_status(client, openfga, None).await?;
+        }
+        return Ok(None);
+    };
+    let Some(stable) = container_image(live) else {
+        return Ok(None);
+    };
+    // The generated Deployment carries spec.image, pinned to its digest when resolved
+    let desired = container_image(deployment).unwrap_or_else(|| openfga.spec.image.clone());
+    let pause = Duration::from_secs(canary.pause_seconds);
+    let now = Utc::now();
+
+    let due = upgrade.is_some_and(|u| {
+        u.phase == UpgradePhase::Canary && u.to_image == desired && elapsed(u, now) >= pause
+    });
+    let health = if due {
+        Some(check_canary(client, openfga, ns, name).await)
+    } else {
+        None
+    };
+
+    let step = next_step(&stable, &desired, upgrade, health.as_ref(), now, pause);
+    let status = |phase, started_time: String, message: Option<String>| UpgradeStatus {
+        phase,
+        from_image: stable.clone(),
+        to_image: desired.clone(),
+        started_time,
+        message,
+    };
+    match step {
+        CanaryStep::Stable => {
+            if upgrade.is_some_and(|u| u.phase == UpgradePhase::Canary) {
+                remove_canary(client, ns, name).await?;
+                set_upgrade_status(client, openfga, None).await?;
+            }
+            Ok(None)
+        }
+        CanaryStep::Start | CanaryStep::Wait(_) => {
+            let canary_deployment = canary_deployment(deployment, name, &desired, canary.replicas);
+            let canary_service = canary_service(openfga, ns, name);
+            let params = apply::apply_params();
+            Api::<Deployment>::namespaced(client.clone(), ns)
+                .patch(
+                    &canary_name(name),
+                    &params,
+                    &Patch::Apply(&canary_deployment),
+                )
+                .await?;
+            Api::<Service>::namespaced(client.clone(), ns)
+                .patch(&canary_name(name), &params, &Patch::Apply(&canary_service))
+                .await?;
+            set_image(deployment, &stable);
+
+            if step == CanaryStep::Start {
+                info!(
+                    event = "canary_started",
+                    namespace = %ns,
+                    resource_name = %name,
+                    from_image = %stable,
+                    to_image = %desired,
+                    "Started canary for new image"
+                );
+                let started = status(UpgradePhase::Canary, now.to_rfc3339(), None);
+                set_upgrade_status(client, openfga, Some(&started)).await?;
+                publish(
+                    client,
+                    openfga,
+                    EventType::Normal,
+                    "CanaryStarted",
+                    format!(
+                        "Running {} as a canary for {}s",
+                        desired, canary.pause_seconds
+                    ),
+                )
+                .await;
+                return Ok(Some(pause.min(CANARY_POLL_INTERVAL)));
+            }
+            match step {
+                CanaryStep::Wait(after) => Ok(Some(after)),
+                _ => Ok(Some(CANARY_POLL_INTERVAL)),
+            }
+        }
+        CanaryStep::Promote => {
+            remove_canary(client, ns, name).await?;
+            let started = upgrade.map(|u| u.started_time.clone()).unwrap_or_default();
+            let promoted = status(UpgradePhase::Promoted, started, None);
+            set_upgrade_status(client, openfga, Some(&promoted)).await?;
+            info!(
+                event = "canary_promoted",
+                namespace = %ns,
+                resource_name = %name,
+                image = %desired,
+                "Canary passed verification, rolling out new image"
+            );
+            publish(
+                client,
+                openfga,
+                EventType::Normal,
+                "CanaryPromoted",
+                format!("Rolling out {}", desired),
+            )
+            .await;
+            Ok(None)
+        }
+        CanaryStep::RollBack(reason) => {
+            remove_canary(client, ns, name).await?;
+            set_image(deployment, &stable);
+            let started = upgrade.map(|u| u.started_time.clone()).unwrap_or_default();
+            let rolled_back = status(UpgradePhase::RolledBack, started, Some(reason.clone()));
+            set_upgrade_status(client, openfga, Some(&rolled_back)).await?;
+            warn!(
+                event = "canary_rolled_back",
+                namespace = %ns,
+                resource_name = %name,
+                image = %desired,
+                reason = %reason,
+                "Canary failed verification, keeping the previous image"
+            );
+            publish(
+                client,
+                openfga,
+                EventType::Warning,
+                "CanaryRolledBack",
+                format!("{} failed verification: {}", desired, reason),
+            )
+            .await;
+            Ok(None)
+        }
+        CanaryStep::Blocked => {
+            set_image(deployment, &stable);
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::api::apps::v1::DeploymentSpec;
+    use k8s_openapi::api::core::v1::{Container, PodSpec, PodTemplateSpec};
+
+    fn upgrade(phase: UpgradePhase, to_image: &str, started: &str) -> UpgradeStatus {
+        UpgradeStatus {
+            phase,
+            from_image: "openfga/openfga:v1.5.2".to_string(),
+            to_image: to_image.to_string(),
+            started_time: started.to_string(),
+            message: None,
+        }
+    }
+
+    fn at(time: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(time)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    const OLD: &str = "openfga/openfga:v1.5.2";
+    const NEW: &str = "openfga/openfga:v1.5.3";
+    const PAUSE: Duration = Duration::from_secs(300);
+
+    #[test]
+    fn test_next_step_starts_and_waits() {
+        let now = at("2024-01-01T00:02:00Z");
+        assert_eq!(
+            next_step(NEW, NEW, None, None, now, PAUSE),
+            CanaryStep::Stable
+        );
+        assert_eq!(
+            next_step(OLD, NEW, None, None, now, PAUSE),
+            CanaryStep::Start
+        );
+
+        // A canary for an older target does not count
+        let other = upgrade(
+            UpgradePhase::Canary,
+            "openfga/openfga:v1.5.1",
+            "2024-01-01T00:00:00Z",
+        );
+        assert_eq!(
+            next_step(OLD, NEW, Some(&other), None, now, PAUSE),
+            CanaryStep::Start
+        );
+
+        let running = upgrade(UpgradePhase::Canary, NEW, "2024-01-01T00:00:00Z");
+        assert_eq!(
+            next_step(OLD, NEW, Some(&running), None, now, PAUSE),
+            CanaryStep::Wait(CANARY_POLL_INTERVAL)
+        );
+        assert_eq!(
+            next_step(
+                OLD,
+                NEW,
+                Some(&running),
+                None,
+                at("2024-01-01T00:04:50Z"),
+                PAUSE
+            ),
+            CanaryStep::Wait(Duration::from_secs(10))
+        );
+    }
+
+    #[test]
+    fn test_next_step_after_pause() {
+        let running = upgrade(UpgradePhase::Canary, NEW, "2024-01-01T00:00:00Z");
+        let now = at("2024-01-01T00:05:00Z");
+        assert_eq!(
+            next_step(OLD, NEW, Some(&running), Some(&Ok(())), now, PAUSE),
+            CanaryStep::Promote
+        );
+        assert_eq!(
+            next_step(
+                OLD,
+                NEW,
+                Some(&running),
+                Some(&Err("unreachable".to_string())),
+                now,
+                PAUSE
+            ),
+            CanaryStep::RollBack("unreachable".to_string())
+        );
+
+        let failed = upgrade(UpgradePhase::RolledBack, NEW, "2024-01-01T00:00:00Z");
+        assert_eq!(
+            next_step(OLD, NEW, Some(&failed), None, now, PAUSE),
+            CanaryStep::Blocked
+        );
+    }
+
+    #[test]
+    fn test_canary_deployment_is_not_selected_by_instance() {
+        let main = Deployment {
+            metadata: ObjectMeta {
+                name: Some("authz".to_string()),
+                labels: Some(labels::selector_labels("authz")),
+                ..Default::default()
+            },
+            spec: Some(DeploymentSpec {
+                replicas: Some(3),
+                selector: LabelSelector {
+                    match_labels: Some(labels::selector_labels("authz")),
+                    ..Default::default()
+                },
+                template: PodTemplateSpec {
+                    metadata: Some(ObjectMeta {
+                        labels: Some(labels::selector_labels("authz")),
+                        ..Default::default()
+                    }),
+                    spec: Some(PodSpec {
+                        containers: vec![Container {
+                            name: "openfga".to_string(),
+                            image: Some(OLD.to_string()),
+                            ..Default::default()
+                        }],
+                        ..Default::default()
+                    }),
+                },
+                ..Default::default()
+            }),
+            status: None,
+        };
+
+        let canary = canary_deployment(&main, "authz", NEW, 1);
+        assert_eq!(canary.metadata.name.as_deref(), Some("authz-canary"));
+        assert_eq!(container_image(&canary).as_deref(), Some(NEW));
+        let spec = canary.spec.unwrap();
+        assert_eq!(spec.replicas, Some(1));
+        let pod_labels = spec.template.metadata.unwrap().labels.unwrap();
+        assert_eq!(pod_labels["app.kubernetes.io/instance"], "authz-canary");
+        assert_eq!(container_image(&main).as_deref(), Some(OLD));
+    }
+
+    #[test]
+    fn test_rolling_update() {
+        assert_eq!(rolling_update(None), None);
+        assert_eq!(rolling_update(Some(&UpgradeStrategy::default())), None);
+        let strategy = rolling_update(Some(&UpgradeStrategy {
+            max_unavailable: Some(IntOrString::Int(0)),
+            max_surge: Some(IntOrString::String("25%".to_string())),
+            canary: None,
+        }))
+        .unwrap();
+        let rolling = strategy.rolling_update.unwrap();
+        assert_eq!(rolling.max_unavailable, Some(IntOrString::Int(0)));
+        assert_eq!(
+            rolling.max_surge,
+            Some(IntOrString::String("25%".to_string()))
+        );
+    }
+}
@@ -0,0 +1,277 @@
+//! Self-monitoring for long-running operators: samples resident memory, open
+//! file descriptors and live tokio tasks into metrics, and optionally acts when
+//! a threshold stays exceeded — logging an alert, or exiting so the kubelet
+//! restarts the container before the kernel OOM-kills it mid-reconcile.
+
+use crate::metrics;
+use std::time::Duration;
+use tokio::runtime::Handle;
+use tracing::{debug, error, info, warn};
+
+const INTERVAL_ENV: &str = "OPENFGA_OPERATOR_WATCHDOG_INTERVAL_SECONDS";
+const MAX_RSS_ENV: &str = "OPENFGA_OPERATOR_WATCHDOG_MAX_RSS_BYTES";
+const MAX_FDS_ENV: &str = "OPENFGA_OPERATOR_WATCHDOG_MAX_OPEN_FDS";
+const BREACHES_ENV: &str = "OPENFGA_OPERATOR_WATCHDOG_BREACHES";
+const RESTART_ENV: &str = "OPENFGA_OPERATOR_WATCHDOG_RESTART";
+
+/// Exit code used when the watchdog restarts the process.
+pub const RESTART_EXIT_CODE: i32 = 75;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchdogConfig {
+    pub interval: Duration,
+    pub max_rss_bytes: Option<u64>,
+    pub max_open_fds: Option<u64>,
+    /// Consecutive samples over a threshold before acting, so short spikes are ignored.
+    pub breaches: u32,
+    /// Exit the process once `breaches` is reached instead of only alerting.
+    pub restart: bool,
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(30),
+            max_rss_bytes: None,
+            max_open_fds: None,
+            breaches: 3,
+            restart: false,
+        }
+    }
+}
+
+impl WatchdogConfig {
+    pub fn from_env() -> Self {
+        Self::from_lookup(|name| std::env::var(name).ok())
+    }
+
+    fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let defaults = Self::default();
+        let parse = |name: &str| {
+            lookup(name)
+                .and_then(|v| v.trim().parse::<u64>().ok())
+                .filter(|&n| n > 0)
+        };
+        Self {
+            interval: parse(INTERVAL_ENV).map_or(defaults.interval, Duration::from_secs),
+            max_rss_bytes: parse(MAX_RSS_ENV),
+            max_open_fds: parse(MAX_FDS_ENV),
+            breaches: parse(BREACHES_ENV).map_or(defaults.breaches, |n| n as u32),
+            restart: lookup(RESTART_ENV).is_some_and(|v| v.trim() == "true"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Sample {
+    pub rss_bytes: Option<u64>,
+    pub open_fds: Option<u64>,
+    pub alive_tasks: usize,
+}
+
+impl Sample {
+    /// Reads the current process usage; memory and descriptors are Linux-only.
+    pub fn collect(runtime: &Handle) -> Self {
+        Self {
+            rss_bytes: std::fs::read_to_string("/proc/self/status")
+                .ok()
+                .and_then(|status| parse_rss_bytes(&status)),
+            open_fds: std::fs::read_dir("/proc/self/fd")
+                .ok()
+                .map(|entries| entries.count() as u64),
+            alive_tasks: runtime.metrics().num_alive_tasks(),
+        }
+    }
+
+    fn record(&self) {
+        let metrics = metrics::metrics();
+        if let Some(rss) = self.rss_bytes {
+            metrics.resident_memory_bytes.set(rss as i64);
+        }
+        if let Some(fds) = self.open_fds {
+            metrics.open_fds.set(fds as i64);
+        }
+        metrics.tasks_alive.set(self.alive_tasks as i64);
+    }
+}
+
+fn parse_rss_bytes(status: &str) -> Option<u64> {
+    let line = status.lines().find(|l| l.starts_with("VmRSS:"))?;
+    let kib: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kib * 1024)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    None,
+    Alert,
+    Restart,
+}
+
+/// Tracks consecutive threshold breaches across samples.
+#[derive(Debug)]
+pub struct Watchdog {
+    config: WatchdogConfig,
+    consecutive: u32,
+}
+
+impl Watchdog {
+    pub fn new(config: WatchdogConfig) -> Self {
+        Self {
+            config,
+            consecutive: 0,
+        }
+    }
+
+    /// Names of the resources over their threshold in `sample`.
+    pub fn breached(&self, sample: &Sample) -> Vec<&'static str> {
+        let over = |value: Option<u64>, limit: Option<u64>| matches!((value, limit), (Some(v), Some(l)) if v > l);
+        let mut breached = Vec::new();
+        if over(sample.rss_bytes, self.config.max_rss_bytes) {
+            breached.push("memory");
+        }
+        if over(sample.open_fds, self.config.max_open_fds) {
+            breached.push("fds");
+        }
+        breached
+    }
+
+    pub fn observe(&mut self, sample: &Sample) -> Action {
+        let breached = self.breached(sample);
+        if breached.is_empty() {
+            self.consecutive = 0;
+            return Action::None;
+        }
+        for resource in &breached {
+            metrics::metrics()
+                .watchdog_breaches_total
+                .with_label_values(&[resource])
+                .inc();
+        }
+        self.consecutive += 1;
+        if self.consecutive < self.config.breaches {
+            Action::None
+        } else if self.config.restart {
+            Action::Restart
+        } else {
+            Action::Alert
+        }
+    }
+}
+
+/// Samples `runtime` every interval until the task is aborted.
+pub async fn run(config: WatchdogConfig, runtime: Handle) {
+    info!(
+        event = "watchdog_started",
+        interval_seconds = config.interval.as_secs(),
+        max_rss_bytes = config.max_rss_bytes,
+        max_open_fds = config.max_open_fds,
+        restart = config.restart,
+        "Starting resource watchdog"
+    );
+    let mut watchdog = Watchdog::new(config);
+    let mut interval = tokio::time::interval(config.interval);
+    loop {
+        interval.tick().await;
+        let sample = Sample::collect(&runtime);
+        sample.record();
+        debug!(
+            event = "watchdog_sample",
+            rss_bytes = sample.rss_bytes,
+            open_fds = sample.open_fds,
+            alive_tasks = sample.alive_tasks,
+            "Sampled operator resource usage"
+        );
+
+        match watchdog.observe(&sample) {
+            Action::None => {}
+            Action::Alert => warn!(
+                event = "watchdog_threshold_exceeded",
+                rss_bytes = sample.rss_bytes,
+                open_fds = sample.open_fds,
+                breached = ?watchdog.breached(&sample),
+                "Operator resource usage over watchdog threshold"
+            ),
+            Action::Restart => {
+                error!(
+                    event = "watchdog_restart",
+                    rss_bytes = sample.rss_bytes,
+                    open_fds = sample.open_fds,
+                    breached = ?watchdog.breached(&sample),
+                    "Operator resource usage over watchdog threshold, exiting for restart"
+                );
+                std::process::exit(RESTART_EXIT_CODE);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn sample(rss_bytes: u64, open_fds: u64) -> Sample {
+        Sample {
+            rss_bytes: Some(rss_bytes),
+            open_fds: Some(open_fds),
+            alive_tasks: 1,
+        }
+    }
+
+    #[test]
+    fn test_config_from_env() {
+        let env = HashMap::from([
+            (MAX_RSS_ENV, "536870912"),
+            (BREACHES_ENV, "0"),
+            (RESTART_ENV, "true"),
+        ]);
+        let config = WatchdogConfig::from_lookup(|name| env.get(name).map(|v| v.to_string()));
+
+        assert_eq!(config.max_rss_bytes, Some(512 * 1024 * 1024));
+        assert_eq!(config.max_open_fds, None);
+        assert_eq!(config.breaches, 3);
+        assert!(config.restart);
+    }
+
+    #[test]
+    fn test_parse_rss_bytes() {
+        let status = "Name:\topenfga-operator\nVmPeak:\t  90000 kB\nVmRSS:\t   51200 kB\n";
+        assert_eq!(parse_rss_bytes(status), Some(51200 * 1024));
+        assert_eq!(parse_rss_bytes("Name:\tx\n"), None);
+    }
+
+    #[test]
+    fn test_acts_after_consecutive_breaches() {
+        let mut watchdog = Watchdog::new(WatchdogConfig {
+            max_rss_bytes: Some(100),
+            max_open_fds: Some(10),
+            breaches: 2,
+            ..Default::default()
+        });
+
+        assert_eq!(watchdog.breached(&sample(200, 20)), vec!["memory", "fds"]);
+        assert_eq!(watchdog.observe(&sample(200, 5)), Action::None);
+        assert_eq!(watchdog.observe(&sample(50, 5)), Action::None);
+        assert_eq!(watchdog.observe(&sample(50, 20)), Action::None);
+        assert_eq!(watchdog.observe(&sample(50, 20)), Action::Alert);
+
+        let mut restarting = Watchdog::new(WatchdogConfig {
+            max_open_fds: Some(10),
+            breaches: 1,
+            restart: true,
+            ..Default::default()
+        });
+        assert_eq!(restarting.observe(&sample(u64::MAX, 5)), Action::None);
+        assert_eq!(restarting.observe(&sample(0, 11)), Action::Restart);
+    }
+
+    #[tokio::test]
+    async fn test_collect_sample() {
+        let sample = Sample::collect(&Handle::current());
+        if cfg!(target_os = "linux") {
+            assert!(sample.rss_bytes.is_some_and(|rss| rss > 0));
+            assert!(sample.open_fds.is_some_and(|fds| fds > 0));
+        }
+    }
+}
@@ -0,0 +1,1202 @@
+//! Admission webhooks for OpenFGA resources. `/mutate` resolves defaults
+//! (pinned image tag, standard labels, probe timings) so the stored object is
+//! what the operator actually runs and server-side diffs stay stable; `/validate`
+//! rejects specs the controller could never reconcile into a working instance,
+//! so the mistake is reported by `kubectl apply` instead of a failing pod.
+//! `/mutate` also stamps requester and approver identities onto
+//! OpenFGAAccessRequests, since only admission sees who made a change.
+//! `/convert` is the CRD conversion webhook between OpenFGA API versions.
+//!
+//! Served over TLS on its own listener (`:9443` by default) with the
+//! certificate cert-manager writes to `/etc/certs`.
+
+use crate::access_request;
+use crate::advisory;
+use crate::controller::{
+    ProbeDefaults, LIVENESS_PROBE_DEFAULTS, READINESS_PROBE_DEFAULTS, STARTUP_PROBE_DEFAULTS,
+};
+use crate::conversion;
+use crate::fleet::image_with_tag;
+use crate::load_shedding::{self, LoadShedder, ServerLimits};
+use crate::playground;
+use crate::query_cache;
+use crate::responses::{self, HttpResult};
+use crate::server_config;
+use crate::types::{OpenFGA, OpenFGASpec, OpenFGAStore, OpenFGAStoreSpec, WorkloadType};
+use hyper::server::conn::Http;
+use hyper::service::service_fn;
+use hyper::{Body, Method, Request, Response, StatusCode};
+use kube::core::admission::{AdmissionRequest, AdmissionResponse, AdmissionReview};
+use kube::core::conversion::ConversionReview;
+use kube::core::DynamicObject;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::io::BufReader;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio_rustls::rustls::{Certificate, PrivateKey, ServerConfig};
+use tokio_rustls::TlsAcceptor;
+use tracing::{debug, info, warn};
+
+const ADDR_ENV: &str = "OPENFGA_OPERATOR_WEBHOOK_ADDR";
+const CERT_DIR_ENV: &str = "OPENFGA_OPERATOR_WEBHOOK_CERT_DIR";
+const DEFAULT_ADDR: &str = "0.0.0.0:9443";
+const DEFAULT_CERT_DIR: &str = "/etc/certs";
+
+/// Admission reviews carry the whole object, so allow more than the health server.
+const MAX_REVIEW_BYTES: u64 = 1024 * 1024;
+
+const DATASTORE_ENGINES: &[&str] = &["memory", "postgres", "mysql"];
+
+/// Experimentals accepted from `spec.featureFlags`, `spec.config` or
+/// `spec.env`: features OpenFGA ships behind a flag that change performance or
+/// add APIs, but not stored data.
+const FEATURE_FLAGS: &[&str] = &[
+    "enable-check-optimizations",
+    "enable-list-objects-optimizations",
+    "enable-access-control",
+    "enable-consistency-params",
+    "enable-list-users",
+    "enable-modular-models",
+];
+const EXPERIMENTALS_ENV: &str = "OPENFGA_EXPERIMENTALS";
+const POSTGRES_SSL_MODES: &[&str] = &[
+    "disable",
+    "allow",
+    "prefer",
+    "require",
+    "verify-ca",
+    "verify-full",
+];
+const MYSQL_TLS_MODES: &[&str] = &["true", "false", "skip-verify", "preferred"];
+
+/// Errors in the connection pool bounds of `spec.datastore.<engine>`.
+fn validate_pool(
+    field: &str,
+    max_open_conns: Option<i32>,
+    max_idle_conns: Option<i32>,
+) -> Vec<String> {
+    let mut errors = Vec::new();
+    if let Some(open) = max_open_conns.filter(|&n| n < 1) {
+        errors.push(format!(
+            "{}.maxOpenConns must be at least 1, got {}",
+            field, open
+        ));
+    }
+    if let Some(idle) = max_idle_conns.filter(|&n| n < 0) {
+        errors.push(format!(
+            "{}.maxIdleConns must not be negative, got {}",
+            field, idle
+        ));
+    }
+    if let (Some(open), Some(idle)) = (max_open_conns, max_idle_conns) {
+        if idle > open {
+            errors.push(format!(
+                "{}.maxIdleConns ({}) must not exceed maxOpenConns ({})",
+                field, idle, open
+            ));
+        }
+    }
+    errors
+}
+
+fn validate_datastore(spec: &OpenFGASpec) -> Vec<String> {
+    let datastore = &spec.datastore;
+    let mut errors = Vec::new();
+    if let Some(postgres) = &datastore.postgres {
+        if datastore.engine != "postgres" {
+            errors.push("spec.datastore.postgres requires the postgres engine".to_string());
+        }
+        errors.extend(validate_pool(
+            "spec.datastore.postgres",
+            postgres.max_open_conns,
+            postgres.max_idle_conns,
+        ));
+        if let Some(mode) = postgres
+            .sslmode
+            .as_deref()
+            .filter(|m| !POSTGRES_SSL_MODES.contains(m))
+        {
+            errors.push(format!(
+                "spec.datastore.postgres.sslmode must be one of {}, got '{}'",
+                POSTGRES_SSL_MODES.join(", "),
+                mode
+            ));
+        }
+    }
+    if let Some(mysql) = &datastore.mysql {
+        if datastore.engine != "mysql" {
+            errors.push("spec.datastore.mysql requires the mysql engine".to_string());
+        }
+        errors.extend(validate_pool(
+            "spec.datastore.mysql",
+            mysql.max_open_conns,
+            mysql.max_idle_conns,
+        ));
+        if let Some(tls) = &mysql.tls {
+            if !MYSQL_TLS_MODES.contains(&tls.as_str()) {
+                errors.push(format!(
+                    "spec.datastore.mysql.tls must be one of {}, got '{}'",
+                    MYSQL_TLS_MODES.join(", "),
+                    tls
+                ));
+            } else if datastore.uri_secret_ref.is_some() {
+                errors.push(
+                    "spec.datastore.mysql.tls cannot be added to a URI from uriSecretRef, set it in the Secret"
+                        .to_string(),
+                );
+            }
+        }
+    }
+    errors
+}
+
+/// Errors in the experimentals `spec` turns on. The `--experimentals` flag
+/// from `spec.featureFlags` silently wins over `OPENFGA_EXPERIMENTALS`, so
+/// only one source may be used.
+fn validate_experimentals(spec: &OpenFGASpec) -> Vec<String> {
+    let mut errors = Vec::new();
+    let mut sources: Vec<(&str, Vec<String>)> =
+        vec![("spec.featureFlags", spec.feature_flags.clone())];
+    if let Some(value) = server_config::render(&spec.config).get(EXPERIMENTALS_ENV) {
+        sources.push(("spec.config experimentals", split_flags(value)));
+    }
+    if let Some(var) = spec.env.iter().find(|var| var.name == EXPERIMENTALS_ENV) {
+        match &var.value {
+            Some(value) if var.value_from.is_none() => {
+                sources.push(("spec.env OPENFGA_EXPERIMENTALS", split_flags(value)))
+            }
+            _ => errors.push(
+                "spec.env OPENFGA_EXPERIMENTALS must be a literal value so it can be checked"
+                    .to_string(),
+            ),
+        }
+    }
+
+    for (field, flags) in &sources {
+        for flag in flags {
+            if !FEATURE_FLAGS.contains(&flag.as_str()) {
+                errors.push(format!(
+                    "{}: '{}' is not a known safe experimental, expected one of {}",
+                    field,
+                    flag,
+                    FEATURE_FLAGS.join(", ")
+                ));
+            }
+        }
+    }
+    if !spec.feature_flags.is_empty() {
+        for (field, _) in &sources[1..] {
+            errors.push(format!(
+                "spec.featureFlags cannot be combined with {}",
+                field
+            ));
+        }
+    }
+    errors
+}
+
+fn split_flags(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|flag| !flag.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Every reason `spec` cannot be reconciled, empty when it is valid.
+pub fn validate(spec: &OpenFGASpec) -> Vec<String> {
+    let mut errors = Vec::new();
+
+    if spec.replicas < 1 {
+        errors.push(format!(
+            "spec.replicas must be at least 1, got {}",
+            spec.replicas
+        ));
+    }
+
+    let engine = spec.datastore.engine.as_str();
+    if !DATASTORE_ENGINES.contains(&engine) {
+        errors.push(format!(
+            "spec.datastore.engine must be one of {}, got '{}'",
+            DATASTORE_ENGINES.join(", "),
+            engine
+        ));
+    } else if engine != "memory"
+        && spec.datastore.uri.is_none()
+        && spec.datastore.uri_secret_ref.is_none()
+    {
+        errors.push(format!(
+            "spec.datastore.uri or spec.datastore.uriSecretRef is required for the {} engine",
+            engine
+        ));
+    }
+    errors.extend(validate_datastore(spec));
+
+    let mut ports: Vec<(&str, i32)> = vec![
+        ("spec.grpc.port", spec.grpc.port),
+        ("spec.http.port", spec.http.port),
+    ];
+    if spec.playground.enabled {
+        ports.push(("spec.playground.port", spec.playground.port));
+    }
+    if let Some(proxy) = &spec.playground.auth_proxy {
+        if !spec.playground.enabled {
+            errors.push("spec.playground.authProxy requires spec.playground.enabled".to_string());
+        } else {
+            ports.push(("spec.playground.authProxy.port", proxy.port));
+        }
+        if proxy.secret_name.is_empty() {
+            errors.push("spec.playground.authProxy.secretName must not be empty".to_string());
+        }
+    }
+    if spec
+        .tls
+        .as_ref()
+        .is_some_and(|tls| tls.secret_name.is_empty())
+    {
+        errors.push("spec.tls.secretName must not be empty".to_string());
+    }
+    if spec.observability.metrics.enabled {
+        ports.push((
+            "spec.observability.metrics.port",
+            spec.observability.metrics.port,
+        ));
+    }
+    errors.extend(validate_experimentals(spec));
+    if let Some(cache) = &spec.query_cache {
+        if let Some(ttl) = cache
+            .ttl
+            .as_deref()
+            .filter(|ttl| !query_cache::is_duration(ttl))
+        {
+            errors.push(format!(
+                "spec.queryCache.ttl must be a duration such as 10s, got '{}'",
+                ttl
+            ));
+        }
+        if let Some(sidecar) = &cache.sidecar {
+            ports.push((
+                "spec.queryCache.sidecar.port",
+                query_cache::sidecar_port(sidecar),
+            ));
+            if sidecar.max_memory_mi == Some(0) {
+                errors.push("spec.queryCache.sidecar.maxMemoryMi must be at least 1".to_string());
+            }
+        }
+    }
+    if spec
+        .image_verification
+        .as_ref()
+        .and_then(|policy| policy.keyless.as_ref())
+        .is_some_and(|keyless| keyless.rekor_public_key.is_none())
+    {
+        errors.push(
+            "spec.imageVerification.keyless.rekorPublicKey is required to check when signatures were logged"
+                .to_string(),
+        );
+    }
+    let mut seen: BTreeMap<i32, &str> = BTreeMap::new();
+    for (field, port) in ports {
+        if !(1..=65535).contains(&port) {
+            errors.push(format!(
+                "{} must be between 1 and 65535, got {}",
+                field, port
+            ));
+        } else if let Some(other) = seen.insert(port, field) {
+            errors.push(format!(
+                "{} conflicts with {} (both {})",
+                field, other, port
+            ));
+        }
+    }
+
+    if spec.workload_type == WorkloadType::StatefulSet && (engine != "memory" || spec.replicas != 1)
+    {
+        errors.push(
+            "spec.workloadType StatefulSet requires the memory engine and 1 replica".to_string(),
+        );
+    }
+    if spec.persistence.is_some() && spec.workload_type != WorkloadType::StatefulSet {
+        errors.push("spec.persistence requires spec.workloadType StatefulSet".to_string());
+    }
+    if let Some(canary) = spec
+        .upgrade_strategy
+        .as_ref()
+        .and_then(|s| s.canary.as_ref())
+    {
+        if spec.workload_type != WorkloadType::Deployment {
+            errors.push(
+                "spec.upgradeStrategy.canary requires spec.workloadType Deployment".to_string(),
+            );
+        }
+        if canary.replicas < 1 {
+            errors.push("spec.upgradeStrategy.canary.replicas must be at least 1".to_string());
+        }
+        if spec.target_cluster_secret_ref.is_some() {
+            errors.push(
+                "spec.upgradeStrategy.canary is not supported with spec.targetClusterSecretRef"
+                    .to_string(),
+            );
+        }
+    }
+
+    errors
+}
+
+/// Whether `name` is an RFC 1123 label, as namespace names are.
+fn is_dns_label(name: &str) -> bool {
+    (1..=63).contains(&name.len())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        && !name.starts_with('-')
+        && !name.ends_with('-')
+}
+
+/// Whether `name` is an RFC 1123 subdomain, as Secret names are.
+fn is_dns_subdomain(name: &str) -> bool {
+    name.len() <= 253 && name.split('.').all(is_dns_label)
+}
+
+/// Every reason the OpenFGAStore `spec` cannot be reconciled.
+pub fn validate_store(spec: &OpenFGAStoreSpec) -> Vec<String> {
+    let mut errors = Vec::new();
+    let mut names = BTreeMap::new();
+    let mut secrets = BTreeMap::new();
+    for (i, token) in spec.access_tokens.iter().enumerate() {
+        let field = format!("spec.accessTokens[{}]", i);
+        if !is_dns_label(&token.name) {
+            errors.push(format!(
+                "{}.name must be a DNS label, got '{}'",
+                field, token.name
+            ));
+        }
+        if let Some(other) = names.insert(token.name.as_str(), i) {
+            errors.push(format!(
+                "{}.name '{}' is also used by spec.accessTokens[{}]",
+                field, token.name, other
+            ));
+        }
+        if let Some(ns) = token.namespace.as_deref().filter(|ns| !is_dns_label(ns)) {
+            errors.push(format!(
+                "{}.namespace must be a namespace name, got '{}'",
+                field, ns
+            ));
+        }
+        if let Some(name) = &token.secret_name {
+            if !is_dns_subdomain(name) {
+                errors.push(format!(
+                    "{}.secretName must be a Secret name, got '{}'",
+                    field, name
+                ));
+            }
+            let target = (token.namespace.as_deref(), name.as_str());
+            if let Some(other) = secrets.insert(target, i) {
+                errors.push(format!(
+                    "{}.secretName '{}' is also written by spec.accessTokens[{}]",
+                    field, name, other
+                ));
+            }
+        }
+    }
+    errors
+}
+
+/// The object at `value[key]`, replacing whatever non-object is there.
+fn entry<'a>(value: &'a mut Value, key: &str) -> &'a mut Value {
+    if !value.is_object() {
+        *value = json!({});
+    }
+    if !value[key].is_object() {
+        value[key] = json!({});
+    }
+    &mut value[key]
+}
+
+/// `object` with the operator's defaults filled in wherever the user left them unset.
+pub fn apply_defaults(object: &Value) -> Value {
+    let mut object = object.clone();
+
+    let name = object
+        .pointer("/metadata/name")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    let labels = entry(entry(&mut object, "metadata"), "labels");
+    let mut standard = vec![
+        ("app.kubernetes.io/name", "openfga".to_string()),
+        (
+            "app.kubernetes.io/managed-by",
+            "openfga-operator".to_string(),
+        ),
+    ];
+    if let Some(name) = name {
+        standard.push(("app.kubernetes.io/instance", name));
+    }
+    for (key, value) in standard {
+        if labels.get(key).is_none() {
+            labels[key] = Value::String(value);
+        }
+    }
+
+    let spec = entry(&mut object, "spec");
+    let image = spec["image"].as_str().unwrap_or("openfga/openfga:latest");
+    // Digests are already pinned; a floating tag is resolved to the newest known release
+    if !image.contains('@') && matches!(advisory::image_tag(image), None | Some("latest")) {
+        let latest = advisory::advisories().latest.to_string();
+        spec["image"] = Value::String(image_with_tag(image, &latest));
+    }
+
+    let probes = entry(spec, "probes");
+    for (probe, defaults) in [
+        ("liveness", LIVENESS_PROBE_DEFAULTS),
+        ("readiness", READINESS_PROBE_DEFAULTS),
+        ("startup", STARTUP_PROBE_DEFAULTS),
+    ] {
+        let probe = entry(probes, probe);
+        let ProbeDefaults {
+            initial_delay_seconds,
+            period_seconds,
+            timeout_seconds,
+            failure_threshold,
+        } = defaults;
+        for (key, value) in [
+            ("enabled", json!(true)),
+            ("initialDelaySeconds", json!(initial_delay_seconds)),
+            ("periodSeconds", json!(period_seconds)),
+            ("timeoutSeconds", json!(timeout_seconds)),
+            ("failureThreshold", json!(failure_threshold)),
+        ] {
+            if probe.get(key).is_none() {
+                probe[key] = value;
+            }
+        }
+    }
+
+    object
+}
+
+/// Answers one mutating admission review with a JSON patch resolving OpenFGA
+/// defaults or stamping the requester and approver of an OpenFGAAccessRequest.
+/// Other objects are passed through unchanged.
+pub fn mutate(review: AdmissionReview<DynamicObject>) -> AdmissionReview<DynamicObject> {
+    let request: AdmissionRequest<DynamicObject> = match review.try_into() {
+        Ok(request) => request,
+        Err(e) => return AdmissionResponse::invalid(e.to_string()).into_review(),
+    };
+    let response = AdmissionResponse::from(&request);
+
+    let kind = request.kind.kind.as_str();
+    let Some(object) = request
+        .object
+        .as_ref()
+        .filter(|_| matches!(kind, "OpenFGA" | "OpenFGAAccessRequest"))
+    else {
+        return response.into_review();
+    };
+    let (original, old) = match serde_json::to_value(object).and_then(|original| {
+        let old = request.old_object.as_ref().map(serde_json::to_value);
+        Ok((original, old.transpose()?))
+    }) {
+        Ok(values) => values,
+        Err(e) => {
+            return response
+                .deny(format!("invalid {}: {}", kind, e))
+                .into_review()
+        }
+    };
+    let mutated = if kind == "OpenFGA" {
+        apply_defaults(&original)
+    } else {
+        access_request::stamp_identity(
+            &request.operation,
+            &original,
+            old.as_ref(),
+            &request.user_info,
+        )
+    };
+    let patch = json_patch::diff(&original, &mutated);
+    if patch.0.is_empty() {
+        return response.into_review();
+    }
+    match response.clone().with_patch(patch) {
+        Ok(response) => response.into_review(),
+        Err(e) => response.deny(e.to_string()).into_review(),
+    }
+}
+
+/// Answers one admission review. Only OpenFGA and OpenFGAStore objects are
+/// validated; anything else routed here is allowed.
+pub fn review(review: AdmissionReview<DynamicObject>) -> AdmissionReview<DynamicObject> {
+    let request: AdmissionRequest<DynamicObject> = match review.try_into() {
+        Ok(request) => request,
+        Err(e) => return AdmissionResponse::invalid(e.to_string()).into_review(),
+    };
+    let response = AdmissionResponse::from(&request);
+
+    let kind = request.kind.kind.as_str();
+    let Some(object) = request
+        .object
+        .as_ref()
+        .filter(|_| matches!(kind, "OpenFGA" | "OpenFGAStore"))
+    else {
+        return response.into_review();
+    };
+    let value = serde_json::to_value(object);
+    let errors = if kind == "OpenFGA" {
+        value
+            .and_then(serde_json::from_value)
+            .map(|openfga: OpenFGA| {
+                let mut errors = validate(&openfga.spec);
+                errors.extend(playground::policy_violations(
+                    &openfga.spec,
+                    playground::disallowed(),
+                ));
+                errors
+            })
+    } else {
+        value
+            .and_then(serde_json::from_value)
+            .map(|store: OpenFGAStore| validate_store(&store.spec))
+    };
+    let errors = match errors {
+        Ok(errors) => errors,
+        Err(e) => {
+            return response
+                .deny(format!("invalid {}: {}", kind, e))
+                .into_review()
+        }
+    };
+
+    if errors.is_empty() {
+        response.into_review()
+    } else {
+        debug!(
+            event = "admission_denied",
+            namespace = ?request.namespace,
+            resource_name = %request.name,
+            kind = %kind,
+            errors = ?errors,
+            "Rejected invalid resource"
+        );
+        response.deny(errors.join("; ")).into_review()
+    }
+}
+
+async fn handle(req: Request<Body>) -> Response<Body> {
+    let path = req.uri().path().to_string();
+    responses::or_error(&path, route(req).await)
+}
+
+async fn route(req: Request<Body>) -> HttpResult<Response<Body>> {
+    let handler = match req.uri().path() {
+        "/validate" => Some(review as fn(_) -> _),
+        "/mutate" => Some(mutate as fn(_) -> _),
+        "/convert" => None,
+        _ => return responses::respond(StatusCode::NOT_FOUND, responses::TEXT, "Not Found"),
+    };
+    if req.method() != Method::POST {
+        return responses::respond(StatusCode::NOT_FOUND, responses::TEXT, "Not Found");
+    }
+    let body = match load_shedding::read_body(req.into_body(), MAX_REVIEW_BYTES).await {
+        Ok(Some(body)) => body,
+        Ok(None) => {
+            return responses::respond(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                responses::TEXT,
+                "review too large",
+            )
+        }
+        Err(e) => {
+            return responses::respond(StatusCode::BAD_REQUEST, responses::TEXT, e.to_string())
+        }
+    };
+    let Some(handler) = handler else {
+        let conversion: ConversionReview = match serde_json::from_slice(&body) {
+            Ok(conversion) => conversion,
+            Err(e) => {
+                return responses::respond(
+                    StatusCode::BAD_REQUEST,
+                    responses::TEXT,
+                    format!("invalid conversion review: {}", e),
+                )
+            }
+        };
+        return responses::json(
+            StatusCode::OK,
+            &serde_json::to_value(conversion::review(conversion))?,
+        );
+    };
+    let admission: AdmissionReview<DynamicObject> = match serde_json::from_slice(&body) {
+        Ok(admission) => admission,
+        Err(e) => {
+            return responses::respond(
+                StatusCode::BAD_REQUEST,
+                responses::TEXT,
+                format!("invalid admission review: {}", e),
+            )
+        }
+    };
+    responses::json(StatusCode::OK, &serde_json::to_value(handler(admission))?)
+}
+
+fn load_tls(cert_dir: &Path) -> Result<ServerConfig, String> {
+    let open = |file: &str| {
+        let path = cert_dir.join(file);
+        std::fs::File::open(&path)
+            .map(BufReader::new)
+            .map_err(|e| format!("{}: {}", path.display(), e))
+    };
+    let certs = rustls_pemfile::certs(&mut open("tls.crt")?)
+        .map_err(|e| format!("tls.crt: {}", e))?
+        .into_iter()
+        .map(Certificate)
+        .collect();
+    let key = rustls_pemfile::read_all(&mut open("tls.key")?)
+        .map_err(|e| format!("tls.key: {}", e))?
+        .into_iter()
+        .find_map(|item| match item {
+            rustls_pemfile::Item::RSAKey(key)
+            | rustls_pemfile::Item::PKCS8Key(key)
+            | rustls_pemfile::Item::ECKey(key) => Some(PrivateKey(key)),
+            _ => None,
+        })
+        .ok_or_else(|| "tls.key: no private key found".to_string())?;
+
+    ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(|e| e.to_string())
+}
+
+/// Serves the webhook until the task is aborted. Without a certificate the
+/// webhook is disabled, since the API server only calls webhooks over TLS.
+pub async fn serve() {
+    let cert_dir = PathBuf::from(std::env::var(CERT_DIR_ENV).unwrap_or(DEFAULT_CERT_DIR.into()));
+    let addr = std::env::var(ADDR_ENV).unwrap_or(DEFAULT_ADDR.into());
+
+    let tls = match load_tls(&cert_dir) {
+        Ok(tls) => TlsAcceptor::from(Arc::new(tls)),
+        Err(e) => {
+            info!(
+                event = "webhook_disabled",
+                cert_dir = %cert_dir.display(),
+                reason = %e,
+                "Admission webhook disabled, no usable certificate"
+            );
+            return;
+        }
+    };
+    let addr: SocketAddr = match addr.parse() {
+        Ok(addr) => addr,
+        Err(e) => {
+            warn!(event = "webhook_disabled", address = %addr, error = %e, "Invalid webhook address");
+            return;
+        }
+    };
+    let listener = match TcpListener::bind(addr).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            warn!(event = "webhook_disabled", address = %addr, error = %e, "Failed to bind webhook");
+            return;
+        }
+    };
+
+    let shedder = LoadShedder::new(ServerLimits {
+        max_body_bytes: MAX_REVIEW_BYTES,
+        ..ServerLimits::from_env()
+    });
+    info!(endpoint = "webhook", address = %addr, "Admission webhook started");
+
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                warn!(event = "webhook_accept_failed", error = %e, "Failed to accept connection");
+                continue;
+            }
+        };
+        let tls = tls.clone();
+        let shedder = shedder.clone();
+        tokio::spawn(async move {
+            let stream = match tls.accept(stream).await {
+                Ok(stream) => stream,
+                Err(e) => {
+                    debug!(event = "webhook_tls_failed", peer = %peer, error = %e, "TLS handshake failed");
+                    return;
+                }
+            };
+            let service = service_fn(move |req| {
+                let shedder = shedder.clone();
+                async move { Ok::<_, Infallible>(shedder.handle(req, handle).await) }
+            });
+            if let Err(e) = Http::new().serve_connection(stream, service).await {
+                debug!(event = "webhook_connection_failed", peer = %peer, error = %e, "Webhook connection error");
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn spec(value: serde_json::Value) -> OpenFGASpec {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn admission_review(spec: serde_json::Value) -> AdmissionReview<DynamicObject> {
+        serde_json::from_value(json!({
+            "apiVersion": "admission.k8s.io/v1",
+            "kind": "AdmissionReview",
+            "request": {
+                "uid": "705ab4f5-6393-11e8-b7cc-42010a800002",
+                "kind": { "group": "authorization.openfga.dev", "version": "v1alpha1", "kind": "OpenFGA" },
+                "resource": { "group": "authorization.openfga.dev", "version": "v1alpha1", "resource": "openfgas" },
+                "name": "authz",
+                "namespace": "ns",
+                "operation": "CREATE",
+                "userInfo": {},
+                "object": {
+                    "apiVersion": "authorization.openfga.dev/v1alpha1",
+                    "kind": "OpenFGA",
+                    "metadata": { "name": "authz", "namespace": "ns" },
+                    "spec": spec,
+                },
+                "dryRun": false,
+            },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_validate_accepts_defaults() {
+        assert!(validate(&spec(json!({ "datastore": { "engine": "memory" } }))).is_empty());
+        assert!(validate(&spec(json!({
+            "datastore": { "engine": "postgres", "uriSecretRef": { "name": "db", "key": "uri" } },
+        })))
+        .is_empty());
+    }
+
+    #[test]
+    fn test_validate_rejects_invalid_specs() {
+        let errors = validate(&spec(json!({
+            "replicas": 0,
+            "datastore": { "engine": "sqlserver" },
+        })));
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].contains("replicas"));
+        assert!(errors[1].contains("sqlserver"));
+
+        let errors = validate(&spec(json!({ "datastore": { "engine": "postgres" } })));
+        assert_eq!(
+            errors,
+            vec!["spec.datastore.uri or spec.datastore.uriSecretRef is required for the postgres engine"]
+        );
+
+        let errors = validate(&spec(json!({
+            "datastore": { "engine": "memory" },
+            "playground": { "enabled": true, "port": 8080 },
+            "grpc": { "port": 70000 },
+        })));
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].starts_with("spec.grpc.port must be between"));
+        assert!(errors[1].starts_with("spec.playground.port conflicts with spec.http.port"));
+
+        let errors = validate(&spec(json!({
+            "replicas": 2,
+            "datastore": { "engine": "memory" },
+            "workloadType": "StatefulSet",
+        })));
+        assert_eq!(
+            errors,
+            vec!["spec.workloadType StatefulSet requires the memory engine and 1 replica"]
+        );
+        let errors = validate(&spec(json!({
+            "datastore": { "engine": "memory" },
+            "persistence": { "size": "1Gi" },
+        })));
+        assert_eq!(
+            errors,
+            vec!["spec.persistence requires spec.workloadType StatefulSet"]
+        );
+        let errors = validate(&spec(json!({
+            "datastore": { "engine": "memory" },
+            "workloadType": "StatefulSet",
+            "upgradeStrategy": { "canary": { "replicas": 0 } },
+        })));
+        assert_eq!(
+            errors,
+            vec![
+                "spec.upgradeStrategy.canary requires spec.workloadType Deployment",
+                "spec.upgradeStrategy.canary.replicas must be at least 1",
+            ]
+        );
+        let errors = validate(&spec(json!({
+            "datastore": { "engine": "memory" },
+            "targetClusterSecretRef": { "name": "fleet-east" },
+            "upgradeStrategy": { "canary": { "replicas": 1 } },
+        })));
+        assert_eq!(
+            errors,
+            vec!["spec.upgradeStrategy.canary is not supported with spec.targetClusterSecretRef"]
+        );
+    }
+
+    #[test]
+    fn test_validate_tls() {
+        let errors = validate(&spec(json!({
+            "datastore": { "engine": "memory" },
+            "tls": { "secretName": "" },
+        })));
+        assert_eq!(errors, vec!["spec.tls.secretName must not be empty"]);
+    }
+
+    #[test]
+    fn test_validate_keyless_requires_rekor_key() {
+        let keyless = |extra: serde_json::Value| {
+            let mut keyless = json!({
+                "issuer": "https://token.actions.githubusercontent.com",
+                "subject": "release@example.com",
+                "trustedRoots": "-----BEGIN CERTIFICATE-----",
+            });
+            keyless
+                .as_object_mut()
+                .unwrap()
+                .extend(extra.as_object().unwrap().clone());
+            validate(&spec(json!({
+                "datastore": { "engine": "memory" },
+                "imageVerification": { "keyless": keyless },
+            })))
+        };
+        assert!(keyless(json!({ "rekorPublicKey": "-----BEGIN PUBLIC KEY-----" })).is_empty());
+        assert!(keyless(json!({}))[0].contains("rekorPublicKey is required"));
+    }
+
+    #[test]
+    fn test_validate_playground_auth_proxy() {
+        let errors = validate(&spec(json!({
+            "datastore": { "engine": "memory" },
+            "playground": {
+                "enabled": true,
+                "authProxy": { "type": "OAuth2Proxy", "port": 8080, "secretName": "" },
+            },
+        })));
+        assert_eq!(errors.len(), 2);
+        assert_eq!(
+            errors[0],
+            "spec.playground.authProxy.secretName must not be empty"
+        );
+        assert!(
+            errors[1].starts_with("spec.playground.authProxy.port conflicts with spec.http.port")
+        );
+
+        let errors = validate(&spec(json!({
+            "datastore": { "engine": "memory" },
+            "playground": { "authProxy": { "type": "BasicAuth", "secretName": "htpasswd" } },
+        })));
+        assert_eq!(
+            errors,
+            vec!["spec.playground.authProxy requires spec.playground.enabled"]
+        );
+    }
+
+    #[test]
+    fn test_validate_feature_flags() {
+        let errors = validate(&spec(json!({
+            "datastore": { "engine": "memory" },
+            "featureFlags": ["enable-list-objects-optimizations", "enable-everything"],
+        })));
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with(
+            "spec.featureFlags: 'enable-everything' is not a known safe experimental"
+        ));
+
+        // The same allowlist applies to the config and the environment
+        let errors = validate(&spec(json!({
+            "datastore": { "engine": "memory" },
+            "config": { "experimentals": ["enable-list-users", "enable-everything"] },
+            "env": [{ "name": "OPENFGA_EXPERIMENTALS", "value": "enable-list-users,drop-tables" }],
+        })));
+        assert_eq!(errors.len(), 2, "{:?}", errors);
+        assert!(errors[0].starts_with("spec.config experimentals: 'enable-everything'"));
+        assert!(errors[1].starts_with("spec.env OPENFGA_EXPERIMENTALS: 'drop-tables'"));
+
+        let errors = validate(&spec(json!({
+            "datastore": { "engine": "memory" },
+            "featureFlags": ["enable-list-users"],
+            "config": { "experimentals": "enable-list-users" },
+            "env": [{
+                "name": "OPENFGA_EXPERIMENTALS",
+                "valueFrom": { "configMapKeyRef": { "name": "flags", "key": "all" } },
+            }],
+        })));
+        assert_eq!(
+            errors,
+            vec![
+                "spec.env OPENFGA_EXPERIMENTALS must be a literal value so it can be checked",
+                "spec.featureFlags cannot be combined with spec.config experimentals",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_query_cache() {
+        let errors = validate(&spec(json!({
+            "datastore": { "engine": "memory" },
+            "queryCache": {
+                "ttl": "1d",
+                "sidecar": { "engine": "Redis", "port": 8081, "maxMemoryMi": 0 },
+            },
+        })));
+        assert_eq!(
+            errors,
+            vec![
+                "spec.queryCache.ttl must be a duration such as 10s, got '1d'",
+                "spec.queryCache.sidecar.maxMemoryMi must be at least 1",
+                "spec.queryCache.sidecar.port conflicts with spec.grpc.port (both 8081)",
+            ]
+        );
+
+        let errors = validate(&spec(json!({
+            "datastore": { "engine": "memory" },
+            "queryCache": { "ttl": "10s", "limit": 10000, "sidecar": { "engine": "Memcached" } },
+        })));
+        assert!(errors.is_empty(), "{:?}", errors);
+    }
+
+    #[test]
+    fn test_validate_engine_settings() {
+        assert!(validate(&spec(json!({
+            "datastore": {
+                "engine": "postgres",
+                "uri": "postgres://db/openfga",
+                "postgres": { "maxOpenConns": 30, "maxIdleConns": 10, "sslmode": "require" },
+            },
+        })))
+        .is_empty());
+
+        let errors = validate(&spec(json!({
+            "datastore": {
+                "engine": "postgres",
+                "uri": "postgres://db/openfga",
+                "postgres": { "maxOpenConns": 5, "maxIdleConns": 10, "sslmode": "on" },
+                "mysql": { "maxOpenConns": 0 },
+            },
+        })));
+        assert_eq!(
+            errors,
+            vec![
+                "spec.datastore.postgres.maxIdleConns (10) must not exceed maxOpenConns (5)",
+                "spec.datastore.postgres.sslmode must be one of disable, allow, prefer, require, verify-ca, verify-full, got 'on'",
+                "spec.datastore.mysql requires the mysql engine",
+                "spec.datastore.mysql.maxOpenConns must be at least 1, got 0",
+            ]
+        );
+
+        let errors = validate(&spec(json!({
+            "datastore": {
+                "engine": "mysql",
+                "uriSecretRef": { "name": "db", "key": "dsn" },
+                "mysql": { "tls": "true" },
+            },
+        })));
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with("spec.datastore.mysql.tls cannot be added"));
+    }
+
+    #[test]
+    fn test_apply_defaults() {
+        let object = json!({
+            "metadata": { "name": "authz", "labels": { "app.kubernetes.io/name": "custom" } },
+            "spec": {
+                "image": "registry.example.com/openfga/openfga:latest",
+                "datastore": { "engine": "memory" },
+                "probes": { "startup": { "enabled": false, "failureThreshold": 60 } },
+            },
+        });
+        let defaulted = apply_defaults(&object);
+
+        let labels = &defaulted["metadata"]["labels"];
+        assert_eq!(labels["app.kubernetes.io/name"], "custom");
+        assert_eq!(labels["app.kubernetes.io/instance"], "authz");
+        assert_eq!(labels["app.kubernetes.io/managed-by"], "openfga-operator");
+
+        let latest = advisory::advisories().latest.to_string();
+        assert_eq!(
+            defaulted["spec"]["image"],
+            format!("registry.example.com/openfga/openfga:{}", latest)
+        );
+
+        let probes = &defaulted["spec"]["probes"];
+        assert_eq!(probes["liveness"]["initialDelaySeconds"], 10);
+        assert_eq!(probes["readiness"]["enabled"], true);
+        assert_eq!(probes["startup"]["enabled"], false);
+        assert_eq!(probes["startup"]["failureThreshold"], 60);
+        assert_eq!(probes["startup"]["periodSeconds"], 5);
+
+        // Defaulting is idempotent, so re-admitting a stored object is a no-op
+        assert_eq!(apply_defaults(&defaulted), defaulted);
+
+        let pinned = apply_defaults(&json!({ "spec": { "image": "openfga/openfga:v1.5.3" } }));
+        assert_eq!(pinned["spec"]["image"], "openfga/openfga:v1.5.3");
+        let digest = "openfga/openfga@sha256:0123";
+        let pinned = apply_defaults(&json!({ "spec": { "image": digest } }));
+        assert_eq!(pinned["spec"]["image"], digest);
+    }
+
+    #[test]
+    fn test_mutate() {
+        let mutated = mutate(admission_review(json!({
+            "image": "openfga/openfga:v1.5.3",
+            "datastore": { "engine": "memory" },
+        })));
+        let response = mutated.response.unwrap();
+        assert!(response.allowed);
+        let patch: Vec<Value> = serde_json::from_slice(&response.patch.unwrap()).unwrap();
+        assert!(patch
+            .iter()
+            .any(|op| op["path"] == "/metadata/labels" && op["op"] == "add"));
+        assert!(patch.iter().any(|op| op["path"] == "/spec/probes"));
+        assert!(!patch.iter().any(|op| op["path"] == "/spec/image"));
+    }
+
+    #[test]
+    fn test_mutate_stamps_access_request_approver() {
+        let review: AdmissionReview<DynamicObject> = serde_json::from_value(json!({
+            "apiVersion": "admission.k8s.io/v1",
+            "kind": "AdmissionReview",
+            "request": {
+                "uid": "705ab4f5-6393-11e8-b7cc-42010a800003",
+                "kind": { "group": "authorization.openfga.dev", "version": "v1alpha1", "kind": "OpenFGAAccessRequest" },
+                "resource": { "group": "authorization.openfga.dev", "version": "v1alpha1", "resource": "openfgaaccessrequests" },
+                "subResource": "status",
+                "name": "anne-viewer",
+                "namespace": "ns",
+                "operation": "UPDATE",
+                "userInfo": { "username": "carol" },
+                "object": {
+                    "apiVersion": "authorization.openfga.dev/v1alpha1",
+                    "kind": "OpenFGAAccessRequest",
+                    "metadata": { "name": "anne-viewer", "namespace": "ns" },
+                    "status": { "decision": { "approved": true } },
+                },
+                "oldObject": {
+                    "apiVersion": "authorization.openfga.dev/v1alpha1",
+                    "kind": "OpenFGAAccessRequest",
+                    "metadata": { "name": "anne-viewer", "namespace": "ns" },
+                },
+                "dryRun": false,
+            },
+        }))
+        .unwrap();
+
+        let response = mutate(review).response.unwrap();
+        assert!(response.allowed);
+        let patch: Vec<Value> = serde_json::from_slice(&response.patch.unwrap()).unwrap();
+        assert!(patch
+            .iter()
+            .any(|op| op["path"] == "/status/decision/approver" && op["value"] == "carol"));
+    }
+
+    #[test]
+    fn test_validate_store() {
+        let store = |tokens: serde_json::Value| -> OpenFGAStoreSpec {
+            serde_json::from_value(json!({
+                "instanceRef": { "name": "authz" },
+                "accessTokens": tokens,
+            }))
+            .unwrap()
+        };
+        assert!(validate_store(&store(json!([
+            { "name": "payments" },
+            { "name": "ledger", "namespace": "payments", "secretName": "openfga.ledger" },
+        ])))
+        .is_empty());
+
+        let errors = validate_store(&store(json!([
+            { "name": "Payments", "namespace": "kube_system", "secretName": "-token" },
+            { "name": "agent", "secretName": "shared" },
+            { "name": "agent", "secretName": "shared" },
+        ])));
+        assert_eq!(errors.len(), 5, "{:?}", errors);
+        assert!(errors[0].contains("spec.accessTokens[0].name"));
+        assert!(errors[1].contains("spec.accessTokens[0].namespace"));
+        assert!(errors[2].contains("spec.accessTokens[0].secretName"));
+        assert!(errors[3].contains("also used by spec.accessTokens[1]"));
+        assert!(errors[4].contains("also written by spec.accessTokens[1]"));
+    }
+
+    #[test]
+    fn test_review() {
+        let allowed = review(admission_review(
+            json!({ "datastore": { "engine": "memory" } }),
+        ));
+        let response = allowed.response.unwrap();
+        assert!(response.allowed);
+        assert_eq!(response.uid, "705ab4f5-6393-11e8-b7cc-42010a800002");
+
+        let denied = review(admission_review(
+            json!({ "datastore": { "engine": "mysql" } }),
+        ));
+        let response = denied.response.unwrap();
+        assert!(!response.allowed);
+        assert!(response.result.message.contains("mysql engine"));
+    }
+
+    #[tokio::test]
+    async fn test_route() {
+        let body = serde_json::to_vec(&admission_review(json!({
+            "replicas": 0,
+            "datastore": { "engine": "memory" },
+        })))
+        .unwrap();
+        let req = Request::post("/validate").body(Body::from(body)).unwrap();
+        let response = handle(req).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let review: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(review["response"]["allowed"], false);
+
+        let response = handle(Request::get("/validate").body(Body::empty()).unwrap()).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = handle(Request::post("/validate").body(Body::from("{")).unwrap()).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_route_convert() {
+        let body = serde_json::to_vec(&json!({
+            "apiVersion": "apiextensions.k8s.io/v1",
+            "kind": "ConversionReview",
+            "request": {
+                "uid": "705ab4f5-6393-11e8-b7cc-42010a800002",
+                "desiredAPIVersion": conversion::V1BETA1,
+                "objects": [{
+                    "apiVersion": conversion::V1ALPHA1,
+                    "kind": "OpenFGA",
+                    "metadata": { "name": "authz" },
+                    "spec": { "datastore": { "engine": "memory" } },
+                }],
+            },
+        }))
+        .unwrap();
+        let response = handle(Request::post("/convert").body(Body::from(body)).unwrap()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let review: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(
+            review["response"]["convertedObjects"][0]["apiVersion"],
+            conversion::V1BETA1
+        );
+
+        let response = handle(Request::post("/convert").body(Body::from("{")).unwrap()).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,300 @@
+//! The workload an instance's pods run in. Deployments are the default; a
+//! single memory-engine replica may run as a StatefulSet instead, so the pod
+//! keeps its name and, with `persistence`, its volume across restarts.
+//!
+//! The StatefulSet is built from the same pod template as the Deployment, so
+//! both modes share probes, security settings and volumes.
+
+use crate::apply;
+use crate::controller::ControllerResult;
+use crate::labels;
+use crate::types::{OpenFGA, PersistenceConfig, WorkloadType};
+use k8s_openapi::api::apps::v1::{Deployment, DeploymentStatus, StatefulSet, StatefulSetSpec};
+use k8s_openapi::api::core::v1::{
+    PersistentVolumeClaim, PersistentVolumeClaimSpec, ResourceRequirements, VolumeMount,
+};
+use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use kube::api::{Api, DeleteParams, Patch};
+use kube::{Client, Resource, ResourceExt};
+use serde::de::DeserializeOwned;
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use tracing::{debug, info, instrument};
+
+/// Name of the volume claim template and of its mount in the OpenFGA container.
+pub const DATA_VOLUME: &str = "data";
+
+fn claim_template(config: &PersistenceConfig, name: &str) -> PersistentVolumeClaim {
+    PersistentVolumeClaim {
+        metadata: ObjectMeta {
+            name: Some(DATA_VOLUME.to_string()),
+            labels: Some(labels::selector_labels(name)),
+            ..Default::default()
+        },
+        spec: Some(PersistentVolumeClaimSpec {
+            access_modes: Some(vec!["ReadWriteOnce".to_string()]),
+            storage_class_name: config.storage_class_name.clone(),
+            resources: Some(ResourceRequirements {
+                requests: Some(BTreeMap::from([(
+                    "storage".to_string(),
+                    Quantity(config.size.clone()),
+                )])),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+/// A StatefulSet running the pods `deployment` describes, governed by the
+/// instance's Service.
+pub fn create_stateful_set(openfga: &OpenFGA, deployment: &Deployment, name: &str) -> StatefulSet {
+    let spec = deployment.spec.clone().unwrap_or_default();
+    let mut template = spec.template;
+    let claims = openfga.spec.persistence.as_ref().map(|config| {
+        if let Some(container) = template
+            .spec
+            .as_mut()
+            .and_then(|pod| pod.containers.first_mut())
+        {
+            container
+                .volume_mounts
+                .get_or_insert_with(Vec::new)
+                .push(VolumeMount {
+                    name: DATA_VOLUME.to_string(),
+                    mount_path: config.mount_path.clone(),
+                    ..Default::default()
+                });
+        }
+        vec![claim_template(config, name)]
+    });
+
+    StatefulSet {
+        metadata: deployment.metadata.clone(),
+        spec: Some(StatefulSetSpec {
+            replicas: spec.replicas,
+            selector: spec.selector,
+            service_name: name.to_string(),
+            min_ready_seconds: spec.min_ready_seconds,
+            template,
+            volume_claim_templates: claims,
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+/// The StatefulSet's rollout state in the shape of a Deployment, so both modes
+/// share the `Ready`/`Progressing`/`Degraded` logic. StatefulSets report no
+/// conditions, so a stuck rollout only shows as not ready.
+pub fn as_deployment_status(stateful_set: StatefulSet) -> Deployment {
+    let status = stateful_set.status.unwrap_or_default();
+    Deployment {
+        metadata: stateful_set.metadata,
+        spec: None,
+        status: Some(DeploymentStatus {
+            replicas: Some(status.replicas),
+            ready_replicas: status.ready_replicas,
+            updated_replicas: status.updated_replicas,
+            available_replicas: status.available_replicas,
+            observed_generation: status.observed_generation,
+            ..Default::default()
+        }),
+    }
+}
+
+/// Applies the StatefulSet. Its volume claim templates cannot change once it exists,
+/// so a changed `persistence` is rejected by the API server.
+#[instrument(skip(client, openfga, deployment), fields(namespace = %ns, name = %name))]
+pub async fn apply_stateful_set(
+    client: &Client,
+    openfga: &OpenFGA,
+    deployment: &Deployment,
+    ns: &str,
+    name: &str,
+) -> ControllerResult<()> {
+    let stateful_set = create_stateful_set(openfga, deployment, name);
+    let stateful_sets: Api<StatefulSet> = Api::namespaced(client.clone(), ns);
+    stateful_sets
+        .patch(name, &apply::apply_params(), &Patch::Apply(&stateful_set))
+        .await?;
+    info!(
+        event = "stateful_set_applied",
+        namespace = %ns,
+        resource_name = %name,
+        persistence = openfga.spec.persistence.is_some(),
+        "Applied StatefulSet"
+    );
+    Ok(())
+}
+
+/// The workload of the configured kind, presented as a Deployment for status.
+pub async fn current(
+    client: &Client,
+    openfga: &OpenFGA,
+    ns: &str,
+    name: &str,
+) -> Result<Option<Deployment>, kube::Error> {
+    match openfga.spec.workload_type {
+        WorkloadType::Deployment => {
+            Api::<Deployment>::namespaced(client.clone(), ns)
+                .get_opt(name)
+                .await
+        }
+        WorkloadType::StatefulSet => Ok(Api::<StatefulSet>::namespaced(client.clone(), ns)
+            .get_opt(name)
+            .await?
+            .map(as_deployment_status)),
+    }
+}
+
+async fn delete_if_created_for<K>(
+    api: &Api<K>,
+    openfga: &OpenFGA,
+    name: &str,
+) -> ControllerResult<()>
+where
+    K: Resource + Clone + DeserializeOwned + Debug,
+{
+    if let Some(existing) = api.get_opt(name).await? {
+        let instance = labels::instance_ref(existing.meta());
+        if instance.is_some_and(|i| i.name == openfga.name_any()) {
+            api.delete(name, &DeleteParams::default()).await?;
+            debug!(
+                event = "workload_replaced",
+                namespace = %existing.namespace().unwrap_or_default(),
+                resource_name = %name,
+                "Deleted workload of the previous workloadType"
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Deletes the workload of the kind not configured, after a `workloadType` switch.
+#[instrument(skip(client, openfga), fields(namespace = %ns, name = %name))]
+pub async fn remove_unused(
+    client: &Client,
+    openfga: &OpenFGA,
+    ns: &str,
+    name: &str,
+) -> ControllerResult<()> {
+    match openfga.spec.workload_type {
+        WorkloadType::Deployment => {
+            let api: Api<StatefulSet> = Api::namespaced(client.clone(), ns);
+            delete_if_created_for(&api, openfga, name).await
+        }
+        WorkloadType::StatefulSet => {
+            let api: Api<Deployment> = Api::namespaced(client.clone(), ns);
+            delete_if_created_for(&api, openfga, name).await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::test_openfga;
+    use k8s_openapi::api::apps::v1::{DeploymentSpec, StatefulSetStatus};
+    use k8s_openapi::api::core::v1::{Container, PodSpec, PodTemplateSpec};
+    use k8s_openapi::apimachinery::pkg::apis::meta::v1::LabelSelector;
+
+    fn openfga(persistence: Option<serde_json::Value>) -> OpenFGA {
+        test_openfga(serde_json::json!({
+            "replicas": 1,
+            "workloadType": "StatefulSet",
+            "persistence": persistence,
+        }))
+    }
+
+    fn deployment() -> Deployment {
+        Deployment {
+            metadata: ObjectMeta {
+                name: Some("authz".to_string()),
+                labels: Some(labels::selector_labels("authz")),
+                ..Default::default()
+            },
+            spec: Some(DeploymentSpec {
+                replicas: Some(1),
+                selector: LabelSelector {
+                    match_labels: Some(labels::selector_labels("authz")),
+                    ..Default::default()
+                },
+                template: PodTemplateSpec {
+                    metadata: None,
+                    spec: Some(PodSpec {
+                        containers: vec![Container {
+                            name: "openfga".to_string(),
+                            ..Default::default()
+                        }],
+                        ..Default::default()
+                    }),
+                },
+                ..Default::default()
+            }),
+            status: None,
+        }
+    }
+
+    #[test]
+    fn test_stateful_set_with_persistence() {
+        let openfga = openfga(Some(serde_json::json!({
+            "size": "5Gi",
+            "storageClassName": "fast",
+        })));
+        let stateful_set = create_stateful_set(&openfga, &deployment(), "authz");
+        let spec = stateful_set.spec.unwrap();
+
+        assert_eq!(spec.service_name, "authz");
+        assert_eq!(spec.replicas, Some(1));
+        let claims = spec.volume_claim_templates.unwrap();
+        let claim = claims[0].spec.as_ref().unwrap();
+        assert_eq!(claim.storage_class_name.as_deref(), Some("fast"));
+        assert_eq!(
+            claim.resources.as_ref().unwrap().requests.as_ref().unwrap()["storage"],
+            Quantity("5Gi".to_string())
+        );
+        let mounts = spec.template.spec.unwrap().containers[0]
+            .volume_mounts
+            .clone()
+            .unwrap();
+        assert_eq!(mounts[0].name, DATA_VOLUME);
+        assert_eq!(mounts[0].mount_path, "/var/lib/openfga");
+    }
+
+    #[test]
+    fn test_stateful_set_without_persistence() {
+        let stateful_set = create_stateful_set(&openfga(None), &deployment(), "authz");
+        let spec = stateful_set.spec.unwrap();
+        assert!(spec.volume_claim_templates.is_none());
+        assert!(spec.template.spec.unwrap().containers[0]
+            .volume_mounts
+            .is_none());
+    }
+
+    #[test]
+    fn test_stateful_set_status_as_deployment() {
+        let stateful_set = StatefulSet {
+            metadata: ObjectMeta {
+                generation: Some(2),
+                ..Default::default()
+            },
+            status: Some(StatefulSetStatus {
+                replicas: 1,
+                ready_replicas: Some(1),
+                updated_replicas: Some(1),
+                observed_generation: Some(2),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let deployment = as_deployment_status(stateful_set);
+        let status = deployment.status.unwrap();
+        assert_eq!(deployment.metadata.generation, Some(2));
+        assert_eq!(status.replicas, Some(1));
+        assert_eq!(status.ready_replicas, Some(1));
+        assert_eq!(status.observed_generation, Some(2));
+    }
+}